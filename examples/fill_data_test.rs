@@ -161,6 +161,13 @@ async fn main() {
                         warn!("未找到目标数据: {}", first_uid);
                     }
                 }
+                PageOptions::At(target) => {
+                    // 以目标数据为中心载入一页数据。
+                    let from = data_buffer_rc.read().partition_point(|d| d.id < target).saturating_sub(ps / 2);
+                    let page_data: Vec<UserData> = data_buffer_rc.read().iter().skip(from).take(ps).cloned().collect();
+                    reviewer_rc.load_page_now(page_data, opt);
+                }
+                _ => {}
             }
         }
     };
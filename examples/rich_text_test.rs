@@ -373,6 +373,49 @@ pub fn handle_action(mut action_receiver: tokio::sync::mpsc::Receiver<CallbackDa
                 CallbackData::Image(image_event_data) => {
                     debug!("用户点击图片：{:?}", image_event_data);
                 }
+                CallbackData::Suppressed(count) => {
+                    debug!("抑制了{}条重复数据", count);
+                }
+                CallbackData::Dropped(count) => {
+                    debug!("渲染队列已满，丢弃了{}条数据", count);
+                }
+                CallbackData::ModeChanged(modes) => {
+                    debug!("终端模式变化：{:?}", modes);
+                }
+                CallbackData::MouseReport(report) => {
+                    debug!("鼠标汇报：{:?}", report);
+                }
+                CallbackData::SendText(word) => {
+                    debug!("发送单词：{}", word);
+                }
+                CallbackData::OpenUrl(url) => {
+                    debug!("打开网址：{}", url);
+                }
+                CallbackData::Zoom(size) => {
+                    debug!("字体缩放为：{}", size);
+                }
+                CallbackData::Trigger(hit) => {
+                    debug!("高亮规则触发：{:?}", hit);
+                }
+                CallbackData::Scroll(info) => {
+                    debug!("回顾区滚动：{:?}", info);
+                }
+                CallbackData::Hover { id, entered } => {
+                    debug!("鼠标{}数据段：{}", if entered { "进入" } else { "离开" }, id);
+                }
+                CallbackData::Drop { text, files } => {
+                    debug!("拖放文字：{}，文件：{:?}", text, files);
+                }
+                CallbackData::ContextMenu(x, y) => {
+                    debug!("在空白处右击：{},{}", x, y);
+                }
+                CallbackData::PrimaryPaste(text) => {
+                    debug!("中键粘贴：{}", text);
+                }
+                CallbackData::PromptInput(line) => {
+                    debug!("提示符输入：{}", line);
+                }
+                _ => {}
             }
 
         }
@@ -0,0 +1,104 @@
+//! 无障碍辅助接口。组件本身不直接对接屏幕阅读器，而是把可见文字和新到达的文字行整理成纯文本，
+//! 由应用通过`RichText::set_speech_hook`和`RichText::visible_text`转交给系统的无障碍服务或语音合成引擎，
+//! 便于视障玩家使用基于本组件的客户端。
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use parking_lot::RwLock;
+use crate::{DataType, RichData, UserData};
+
+/// 朗读钩子函数载体。
+#[derive(Clone)]
+pub struct SpeechHook {
+    /// 钩子函数。
+    hook: Arc<RwLock<Box<dyn FnMut(&str) + Send + Sync + 'static>>>,
+}
+
+impl SpeechHook {
+    /// 构建新的朗读钩子函数载体。
+    pub fn new(hook: Arc<RwLock<Box<dyn FnMut(&str) + Send + Sync + 'static>>>) -> Self {
+        Self { hook }
+    }
+
+    /// 调用钩子函数朗读一行文字。
+    pub(crate) fn speak(&self, line: &str) {
+        let hook = &mut *self.hook.write();
+        hook(line);
+    }
+}
+
+impl Debug for SpeechHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SpeechHook count: {}", Arc::strong_count(&self.hook))
+    }
+}
+
+/// 把陆续到达的数据段拼接为完整的文字行。
+/// 服务端通常把一行文字拆分为多个不同样式的数据段发送，逐段朗读会打断语句，因此只在收到换行符后才输出整行。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SpeechLines {
+    /// 尚未以换行符结束的文字。
+    pending: String,
+}
+
+impl SpeechLines {
+    /// 追加新数据段中可朗读的文字，返回已经完整的文字行，空白行被忽略。
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 新数据段。
+    ///
+    /// returns: Vec<String>
+    pub(crate) fn push(&mut self, data: &UserData) -> Vec<String> {
+        if data.data_type != DataType::Text || data.conceal {
+            return vec![];
+        }
+        self.pending.push_str(&data.text.replace('\r', ""));
+        let mut lines = vec![];
+        while let Some(pos) = self.pending.find('\n') {
+            let line = self.pending[..pos].trim_end().to_string();
+            self.pending.drain(..=pos);
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        lines
+    }
+
+    /// 取出尚未以换行符结束的文字，例如不带换行符的提示符。
+    ///
+    /// returns: Option<String> 没有可朗读的文字时返回空。
+    pub(crate) fn flush(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.pending);
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            None
+        } else {
+            Some(line.to_string())
+        }
+    }
+}
+
+/// 提取可见区域内的纯文本。与可见区域有交会的数据段整段输出，隐藏文字和非文本数据段被忽略。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `top`: 可见区域顶部相对于数据内容顶部的y坐标。
+/// * `bottom`: 可见区域底部相对于数据内容顶部的y坐标。
+///
+/// returns: String
+pub(crate) fn visible_text(buffer: &[RichData], top: i32, bottom: i32) -> String {
+    let mut text = String::new();
+    for rd in buffer.iter() {
+        if rd.data_type != DataType::Text || rd.conceal || rd.line_pieces.is_empty() {
+            continue;
+        }
+        let (rd_top, rd_bottom, _, _) = *rd.v_bounds.read();
+        if rd_bottom <= top || rd_top >= bottom {
+            continue;
+        }
+        text.push_str(&rd.text);
+    }
+    text.replace('\r', "")
+}
@@ -0,0 +1,174 @@
+//! 确定性模式支持。组件内部使用的时钟和数据段ID默认分别来自系统时钟和`idgenerator_thin`，
+//! 快照测试时可通过`set_clock`和`set_id_source`注入可控的实现，配合`RichText::set_deterministic`冻结定时器和闪烁相位，
+//! 使同一组脚本化的添加和事件操作在每次运行后得到完全一致的组件状态。
+//!
+//! 时钟和ID来源为全局设置，对所有组件实例生效。
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{Duration, Instant};
+use idgenerator_thin::YitIdHelper;
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use serde::Serialize;
+
+/// 时钟。
+pub trait Clock: Send + Sync {
+    /// 当前时刻。
+    fn now(&self) -> Instant;
+}
+
+/// 数据段ID来源。
+pub trait IdSource: Send + Sync {
+    /// 生成下一个ID。
+    fn next_id(&self) -> i64;
+}
+
+/// 手动推进的时钟，创建后时间静止，只有调用`advance`时才会前进。
+#[derive(Debug)]
+pub struct ManualClock {
+    base: Instant,
+    offset: RwLock<Duration>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self { base: Instant::now(), offset: RwLock::new(Duration::ZERO) }
+    }
+
+    /// 将时钟向前推进指定时长。
+    ///
+    /// # Arguments
+    ///
+    /// * `duration`: 推进时长。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::deterministic::{Clock, ManualClock};
+    ///
+    /// let clock = ManualClock::new();
+    /// let start = clock.now();
+    /// clock.advance(Duration::from_secs(3));
+    /// assert_eq!(clock.now() - start, Duration::from_secs(3));
+    /// ```
+    pub fn advance(&self, duration: Duration) {
+        *self.offset.write() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.base + *self.offset.read()
+    }
+}
+
+/// 从指定值开始依次递增的ID来源。
+#[derive(Debug)]
+pub struct SequentialIds {
+    next: AtomicI64,
+}
+
+impl SequentialIds {
+    pub fn new(start: i64) -> Self {
+        Self { next: AtomicI64::new(start) }
+    }
+}
+
+impl IdSource for SequentialIds {
+    fn next_id(&self) -> i64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// 主视图中一个可见分片的布局信息，用于生成快照。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LayoutRecord {
+    /// 分片所属数据段的ID。
+    pub id: i64,
+    /// 分片文本。
+    pub line: String,
+    /// 分片在内容中的横坐标。
+    pub x: i32,
+    /// 分片在内容中的纵坐标。
+    pub y: i32,
+    /// 分片宽度。
+    pub w: i32,
+    /// 分片高度。
+    pub h: i32,
+}
+
+static CLOCK: Lazy<RwLock<Option<Arc<dyn Clock>>>> = Lazy::new(|| RwLock::new(None));
+static ID_SOURCE: Lazy<RwLock<Option<Arc<dyn IdSource>>>> = Lazy::new(|| RwLock::new(None));
+
+/// 设置组件使用的时钟，为空时恢复使用系统时钟。
+///
+/// # Arguments
+///
+/// * `clock`: 时钟。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use fltkrs_richdisplay::deterministic::{set_clock, ManualClock};
+///
+/// set_clock(Some(Arc::new(ManualClock::new())));
+/// set_clock(None);
+/// ```
+pub fn set_clock(clock: Option<Arc<dyn Clock>>) {
+    *CLOCK.write() = clock;
+}
+
+/// 设置新建数据段使用的ID来源，为空时恢复使用`idgenerator_thin`生成ID。
+///
+/// # Arguments
+///
+/// * `source`: ID来源。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use fltkrs_richdisplay::deterministic::{set_id_source, SequentialIds};
+///
+/// set_id_source(Some(Arc::new(SequentialIds::new(1))));
+/// set_id_source(None);
+/// ```
+pub fn set_id_source(source: Option<Arc<dyn IdSource>>) {
+    *ID_SOURCE.write() = source;
+}
+
+/// 当前时刻。
+pub(crate) fn now() -> Instant {
+    match CLOCK.read().as_ref() {
+        Some(clock) => clock.now(),
+        None => Instant::now(),
+    }
+}
+
+/// 从指定时刻到当前时刻经过的时长。
+pub(crate) fn elapsed_since(start: Instant) -> Duration {
+    now().saturating_duration_since(start)
+}
+
+/// 生成新的数据段ID。
+pub(crate) fn next_id() -> i64 {
+    match ID_SOURCE.read().as_ref() {
+        Some(source) => source.next_id(),
+        None => YitIdHelper::next_id(),
+    }
+}
@@ -0,0 +1,352 @@
+//! HTML子集的输入适配。许多MUD服务器通过类似Pueblo/MXP的标记发送带格式的文本，`to_user_data`将其中常用的标签转换为数据段：
+//!
+//! * `<b>`、`<i>`、`<u>`：粗体、斜体和下划线，可以嵌套。
+//! * `<font color="...">`：文字颜色，支持`#rrggbb`格式和常见的颜色名称。
+//! * `<a href="...">`：可点击的链接，默认动作的指令为链接地址。
+//! * `<img src="..." width="..." height="...">`：图片，先以占位图显示，图片地址交由`ImageLoader`异步加载。
+//! * `<br>`：换行。
+//!
+//! 其他标签被忽略，标签内的文字按照当前样式显示。标签之外的空白和换行原样保留，支持常见的字符实体。
+
+use std::fmt::{Debug, Formatter};
+use fltk::enums::{Color, Font};
+use fltk::image::RgbImage;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::{Action, ActionItem, bold_font, DEFAULT_FONT_SIZE, image_to_rgb_data, italic_font, UserData};
+
+/// 标签属性的匹配规则，属性值可以使用双引号、单引号或不使用引号。MXP标签中没有属性名的带引号参数也会被匹配。
+static ATTR_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r#"([A-Za-z_:][-A-Za-z0-9_:.]*)\s*(?:=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+)))?|"([^"]*)"|'([^']*)'"#).unwrap());
+
+/// 图片加载器，由上层应用实现。转换`<img>`标签时先生成占位图片数据段，再调用`load`通知上层应用加载图片。
+/// 上层应用应在后台任务中下载图片并保存为本地文件，完成后将`load_image_from_file`生成的更新参数发送回`fltk`主线程，
+/// 调用`RichText::update_data`替换占位图片。
+pub trait ImageLoader: Send + Sync {
+    /// 请求加载图片，在转换过程中调用，不应执行耗时操作。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 占位图片数据段的ID，更新图片时使用。
+    /// * `src`: 图片地址。
+    /// * `width`: 图片目标宽度。
+    /// * `height`: 图片目标高度。
+    ///
+    /// returns: ()
+    fn load(&self, id: i64, src: &str, width: i32, height: i32);
+}
+
+impl Debug for dyn ImageLoader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ImageLoader")
+    }
+}
+
+/// HTML转换样式。
+#[derive(Debug, Clone)]
+pub struct HtmlStyle {
+    /// 正文字体，粗体使用其对应的粗体字体。普通文字使用组件的默认字体和字号。
+    pub text_font: Font,
+    /// 粗体文字的字号。
+    pub text_size: i32,
+    /// 链接颜色，为空时使用组件的默认颜色。`<font>`标签设置的颜色优先。
+    pub link_color: Option<Color>,
+    /// 链接互动行为的类型，即`Action::kind`。
+    pub link_kind: u8,
+    /// `<img>`标签未指定宽度时使用的宽度。
+    pub image_width: i32,
+    /// `<img>`标签未指定高度时使用的高度。
+    pub image_height: i32,
+    /// 图片加载完成前显示在占位图上的文字。
+    pub image_placeholder: String,
+}
+
+impl Default for HtmlStyle {
+    fn default() -> Self {
+        Self {
+            text_font: Font::Helvetica,
+            text_size: DEFAULT_FONT_SIZE,
+            link_color: Some(Color::from_rgb(80, 160, 255)),
+            link_kind: 0,
+            image_width: 100,
+            image_height: 100,
+            image_placeholder: "loading...".to_string(),
+        }
+    }
+}
+
+/// 转换过程中的样式状态。
+#[derive(Debug, Default)]
+struct TagState {
+    bold: usize,
+    italic: usize,
+    underline: usize,
+    /// `<font>`标签的颜色栈，无法识别的颜色记为空，保证结束标签能够正确出栈。
+    colors: Vec<Option<Color>>,
+    /// 当前所在链接的地址。
+    link: Option<String>,
+}
+
+impl TagState {
+    fn color(&self) -> Option<Color> {
+        self.colors.iter().rev().find_map(|c| *c)
+    }
+}
+
+/// 将HTML子集文本转换为数据段。
+///
+/// # Arguments
+///
+/// * `html`: HTML文本。
+/// * `style`: 转换样式。
+/// * `loader`: 图片加载器，为空时图片只显示占位图。
+///
+/// returns: Vec<UserData, Global>
+///
+/// # Examples
+///
+/// ```
+/// use fltkrs_richdisplay::html::{HtmlStyle, to_user_data};
+///
+/// let data = to_user_data("<font color=red>危险</font>：<b>巨龙</b>出现了！<br>", &HtmlStyle::default(), None);
+/// assert_eq!(data.len(), 4);
+/// assert_eq!(data[3].text, "出现了！\n");
+/// ```
+pub fn to_user_data(html: &str, style: &HtmlStyle, loader: Option<&dyn ImageLoader>) -> Vec<UserData> {
+    let mut result: Vec<UserData> = vec![];
+    let mut state = TagState::default();
+    let mut run = String::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        run.push_str(&decode_entities(&rest[..start]));
+        let (name, closing, attrs) = parse_tag(&rest[start + 1..start + len]);
+        rest = &rest[start + len + 1..];
+
+        if name == "br" {
+            run.push('\n');
+            continue;
+        }
+        push_run(&mut run, &state, style, &mut result);
+        match (name.as_str(), closing) {
+            ("b" | "strong", false) => state.bold += 1,
+            ("b" | "strong", true) => state.bold = state.bold.saturating_sub(1),
+            ("i" | "em", false) => state.italic += 1,
+            ("i" | "em", true) => state.italic = state.italic.saturating_sub(1),
+            ("u", false) => state.underline += 1,
+            ("u", true) => state.underline = state.underline.saturating_sub(1),
+            ("font", false) => state.colors.push(attr(&attrs, "color").and_then(|c| parse_color(c))),
+            ("font", true) => {
+                state.colors.pop();
+            }
+            ("a", false) => state.link = attr(&attrs, "href").cloned(),
+            ("a", true) => state.link = None,
+            ("img", false) => {
+                if let Some(data) = image_data(&attrs, &state, style, loader) {
+                    result.push(data);
+                }
+            }
+            _ => {}
+        }
+    }
+    run.push_str(&decode_entities(rest));
+    push_run(&mut run, &state, style, &mut result);
+    result
+}
+
+/// 解析标签内容，返回小写的标签名、是否为结束标签和按出现顺序排列的属性。
+/// 属性名保持原样，没有属性名的带引号参数的属性名为空，没有属性值的属性的属性值为空。
+pub(crate) fn parse_tag(tag: &str) -> (String, bool, Vec<(String, String)>) {
+    let tag = tag.trim().trim_end_matches('/').trim_end();
+    let (closing, tag) = match tag.strip_prefix('/') {
+        Some(tag) => (true, tag.trim_start()),
+        None => (false, tag),
+    };
+    let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+    let attrs = ATTR_PATTERN.captures_iter(&tag[name_end..]).map(|cap| {
+        let name = cap.get(1).map(|m| m.as_str().to_string()).unwrap_or_default();
+        let value = (2..=6).find_map(|i| cap.get(i)).map(|m| decode_entities(m.as_str())).unwrap_or_default();
+        (name, value)
+    }).collect();
+    (tag[..name_end].to_ascii_lowercase(), closing, attrs)
+}
+
+/// 按照属性名查找属性值，属性名不区分大小写。
+pub(crate) fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a String> {
+    attrs.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v)
+}
+
+/// 读取表示像素尺寸的属性值，缺失或无效时使用默认值。
+pub(crate) fn size_attr(attrs: &[(String, String)], name: &str, default: i32) -> i32 {
+    attr(attrs, name).and_then(|v| v.trim().trim_end_matches("px").parse::<i32>().ok()).filter(|v| *v > 0).unwrap_or(default)
+}
+
+/// 解析颜色，支持`#rrggbb`、`#rgb`格式和常见的颜色名称。
+pub(crate) fn parse_color(color: &str) -> Option<Color> {
+    let color = color.trim().to_ascii_lowercase();
+    if let Some(hex) = color.strip_prefix('#') {
+        let hex = match hex.len() {
+            3 => hex.chars().flat_map(|c| [c, c]).collect::<String>(),
+            6 => hex.to_string(),
+            _ => return None,
+        };
+        return u32::from_str_radix(&hex, 16).ok().map(Color::from_hex);
+    }
+    let rgb = match color.as_str() {
+        "black" => (0, 0, 0),
+        "white" => (255, 255, 255),
+        "red" => (255, 0, 0),
+        "green" => (0, 128, 0),
+        "lime" => (0, 255, 0),
+        "blue" => (0, 0, 255),
+        "yellow" => (255, 255, 0),
+        "cyan" | "aqua" => (0, 255, 255),
+        "magenta" | "fuchsia" => (255, 0, 255),
+        "gray" | "grey" => (128, 128, 128),
+        "silver" => (192, 192, 192),
+        "maroon" => (128, 0, 0),
+        "olive" => (128, 128, 0),
+        "navy" => (0, 0, 128),
+        "purple" => (128, 0, 128),
+        "teal" => (0, 128, 128),
+        "orange" => (255, 165, 0),
+        _ => return None,
+    };
+    Some(Color::from_rgb(rgb.0, rgb.1, rgb.2))
+}
+
+/// 替换常见的字符实体。
+pub(crate) fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                name => {
+                    let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name.strip_prefix('#').and_then(|dec| dec.parse::<u32>().ok()),
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, end))
+        });
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// 将已收集的文字按照当前样式转换为数据段并清空。
+fn push_run(run: &mut String, state: &TagState, style: &HtmlStyle, result: &mut Vec<UserData>) {
+    if run.is_empty() {
+        return;
+    }
+    let mut data = styled_text(run, state.bold > 0, state.italic > 0, state.underline > 0, style.text_font, style.text_size);
+    if let Some(href) = &state.link {
+        data = data.set_action(link_action(run, href, style));
+        if let Some(color) = style.link_color {
+            data = data.set_fg_color(color);
+        }
+    }
+    if let Some(color) = state.color() {
+        data = data.set_fg_color(color);
+    }
+    result.push(data);
+    run.clear();
+}
+
+/// 生成带有粗体、斜体和下划线样式的文字数据段。
+///
+/// # Arguments
+///
+/// * `text`: 文字。
+/// * `bold`: 是否粗体。
+/// * `italic`: 是否斜体。
+/// * `underline`: 是否有下划线。
+/// * `text_font`: 正文字体，粗体时使用其对应的粗体字体。
+/// * `text_size`: 粗体文字的字号。
+///
+/// returns: UserData
+pub(crate) fn styled_text(text: &str, bold: bool, italic: bool, underline: bool, text_font: Font, text_size: i32) -> UserData {
+    let mut data = UserData::new_text(text.to_string()).set_strong(bold).set_underline(underline);
+    if bold {
+        // 自定义字体后斜体需要通过字体体现。
+        let font = bold_font(text_font);
+        let font = if italic { italic_font(font) } else { font };
+        data = data.set_font_and_size(font, text_size);
+    } else if italic {
+        data = data.set_italic(true);
+    }
+    data
+}
+
+/// 生成链接的互动行为，默认动作的指令为链接地址。
+fn link_action(desc: &str, href: &str, style: &HtmlStyle) -> Action {
+    Action {
+        title: href.to_string(),
+        kind: style.link_kind,
+        items: vec![ActionItem::new(desc, href)],
+        default_item: Some(0),
+        ..Default::default()
+    }
+}
+
+/// 根据`<img>`标签生成占位图片数据段。没有图片地址时返回空。
+fn image_data(attrs: &[(String, String)], state: &TagState, style: &HtmlStyle, loader: Option<&dyn ImageLoader>) -> Option<UserData> {
+    let src = attr(attrs, "src").filter(|src| !src.is_empty())?;
+    let alt = attr(attrs, "alt").unwrap_or(src);
+    let (width, height) = (size_attr(attrs, "width", style.image_width), size_attr(attrs, "height", style.image_height));
+    let mut data = placeholder_image(src, alt, width, height, &style.image_placeholder, loader);
+    if let Some(href) = &state.link {
+        data = data.set_action(link_action(alt, href, style));
+    }
+    Some(data)
+}
+
+/// 生成占位图片数据段，并通知图片加载器加载图片。
+///
+/// # Arguments
+///
+/// * `src`: 图片地址。
+/// * `alt`: 无法创建占位图时显示的替代文字。
+/// * `width`: 图片目标宽度。
+/// * `height`: 图片目标高度。
+/// * `placeholder`: 图片加载完成前显示在占位图上的文字。
+/// * `loader`: 图片加载器。
+///
+/// returns: UserData
+pub(crate) fn placeholder_image(src: &str, alt: &str, width: i32, height: i32, placeholder: &str, loader: Option<&dyn ImageLoader>) -> UserData {
+    let (rgb_data, depth, w, h) = image_to_rgb_data(&None, width, height);
+    let Some(image) = rgb_data.and_then(|rgb_data| RgbImage::new(&rgb_data, w, h, depth).ok()) else {
+        return UserData::new_text(alt.to_string());
+    };
+    let data = UserData::new_image(image, w, h, width, height, Some(src.to_string())).set_text(placeholder.to_string());
+    if let Some(loader) = loader {
+        loader.load(data.id, src, width, height);
+    }
+    data
+}
@@ -160,30 +160,44 @@
 
 use std::cell::{RefCell};
 use std::cmp::{max, min, Ordering};
-use std::collections::{HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{RangeInclusive};
-use std::path::{PathBuf};
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::{Path, PathBuf};
 use std::rc::{Rc};
 use std::slice::Iter;
 use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
 use fltk::{app, draw};
-use fltk::draw::{descent, draw_line, draw_rectf, draw_rounded_rect, draw_rounded_rectf, draw_text_n, LineStyle, measure, set_draw_color, set_font, set_line_style};
-use fltk::enums::{Color, ColorDepth, Cursor, Font};
-use fltk::prelude::{ImageExt, WidgetBase};
+use fltk::draw::{descent, draw_line, draw_rect, draw_rectf, draw_rounded_rect, draw_rounded_rectf, draw_text_n, LineStyle, measure, set_draw_color, set_font, set_line_style};
+use fltk::enums::{Align, Color, ColorDepth, Cursor, Font, MenuFlag};
+use fltk::menu::{MenuButton, MenuButtonType};
+use fltk::prelude::{ImageExt, MenuExt, WidgetBase, WidgetExt};
 use fltk::image::{RgbImage, SharedImage, SvgImage};
 
-use idgenerator_thin::YitIdHelper;
 use log::{error};
+use once_cell::sync::Lazy;
+use regex::Regex;
 use parking_lot::{RwLock};
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::ser::SerializeStruct;
 
 pub mod rich_text;
 pub mod rich_reviewer;
+pub mod plugin;
+pub mod accessibility;
+pub mod deterministic;
+pub mod log_file;
+pub mod markdown;
+pub mod html;
+pub mod mxp;
 mod rewrite_board;
 
-/// 默认内容边界到窗口之间的空白距离。
+/// 默认内容边界到窗口之间的空白距离，可通过`RichText::set_padding`为每个组件单独设置。
 pub(crate) const PADDING: Padding = Padding { left: 5, top: 5, right: 5, bottom: 5 };
 
 /// 图片与其他内容之间的垂直间距。
@@ -192,9 +206,12 @@ pub const IMAGE_PADDING_H: i32 = 2;
 /// 图片与其他内容之间的水平间距。
 pub const IMAGE_PADDING_V: i32 = 2;
 
-/// 闪烁强度切换间隔时间，目前使用固定频率。
+/// 慢速闪烁的默认强度切换间隔时间，单位秒，可通过`RichText::set_blink_interval`修改。
 pub const BLINK_INTERVAL: f64 = 0.5;
 
+/// 快速闪烁的默认强度切换间隔时间，单位秒，可通过`RichText::set_rapid_blink_interval`修改。
+pub const RAPID_BLINK_INTERVAL: f64 = 0.2;
+
 /// 高亮文本背景色，查询目标时所有匹配目标的背景色。
 pub const HIGHLIGHT_BACKGROUND_COLOR: Color = Color::from_rgb(0, 0, 255);
 
@@ -212,6 +229,12 @@ pub const WHITE: Color = Color::from_rgb(255, 255, 255);
 /// 默认字体尺寸。
 pub const DEFAULT_FONT_SIZE: i32 = 16;
 
+/// 按住Ctrl键滚动鼠标滚轮缩放时，默认字体尺寸的下限。
+pub const MIN_ZOOM_FONT_SIZE: i32 = 8;
+
+/// 按住Ctrl键滚动鼠标滚轮缩放时，默认字体尺寸的上限。
+pub const MAX_ZOOM_FONT_SIZE: i32 = 72;
+
 /// 从字体高度计算行高度使用的放大系数。
 pub const LINE_HEIGHT_FACTOR: f32 = 1.4;
 
@@ -221,6 +244,189 @@ pub const BASIC_UNIT_CHAR: char = 'A';
 /// 默认的Tab宽度，使用空格代替。
 pub const DEFAULT_TAB_WIDTH: u8 = 4;
 
+/// 行号栏中行号的颜色。
+pub const LINE_NUMBER_COLOR: Color = Color::from_rgb(128, 128, 128);
+
+/// 行号栏预留的最少位数，行号栏宽度依据默认字体下这些数字的宽度计算。
+pub const LINE_NUMBER_DIGITS: usize = 5;
+
+/// 自动过期检查的时间间隔，单位秒。数据段的实际过期时间会有不超过该间隔的延迟。
+pub const AUTO_EXPIRE_INTERVAL: f64 = 1.0;
+
+/// 译文字体大小相对于原文字体大小的比例。
+pub const TRANSLATION_FONT_SCALE: f32 = 0.8;
+
+/// 链接预览卡片中网站图标的边长。
+pub const LINK_PREVIEW_ICON_SIZE: i32 = 16;
+
+/// 链接预览卡片的内边距。
+pub const LINK_PREVIEW_PADDING: i32 = 4;
+
+/// 换行试算缓存的默认容量。
+pub const DEFAULT_WRAP_CACHE_CAPACITY: usize = 4096;
+
+/// 记录已请求过预览信息的网址数量上限，超出时淘汰最久未出现的网址。
+pub const PREVIEWED_URLS_CAPACITY: usize = 1024;
+
+/// 回复数据段每一级线索的缩进宽度。
+pub const THREAD_INDENT_WIDTH: i32 = 20;
+
+/// 回复数据段左侧连接线的颜色。
+pub const THREAD_CONNECTOR_COLOR: Color = Color::from_rgb(96, 96, 96);
+
+/// 引用内容每一级的缩进宽度。
+pub const QUOTE_INDENT_WIDTH: i32 = 16;
+
+/// 引用内容左侧竖线的宽度。
+pub const QUOTE_RULE_WIDTH: i32 = 3;
+
+/// 气泡下方与后续内容之间的间距。
+pub const BUBBLE_MARGIN_V: i32 = 4;
+
+/// 数据段前置图标与文字之间的间距。
+pub const ICON_GAP: i32 = 4;
+
+/// 表格单元格左右两侧的内边距。
+pub const TABLE_CELL_PADDING: i32 = 6;
+
+/// 进度条上下两侧与所在行边界的间距。
+pub const GAUGE_PADDING_V: i32 = 2;
+
+/// 鼠标在可互动数据段上停留多久后显示提示信息，单位秒。
+pub const TOOLTIP_DELAY: f64 = 0.6;
+
+/// 相邻两次悬停事件通知的最小间隔，单位秒。鼠标快速划过多个可互动数据段时，间隔内的变化合并为一次通知。
+pub const HOVER_MIN_INTERVAL: f64 = 0.05;
+
+/// 提示信息的最大宽度，超出时自动换行。
+pub const TOOLTIP_MAX_WIDTH: i32 = 320;
+
+/// 提示信息文字与边框的间距。
+pub const TOOLTIP_PADDING: i32 = 4;
+
+/// 提示信息的字号。
+pub const TOOLTIP_FONT_SIZE: i32 = 14;
+
+/// 暂停输出提示标记的颜色。
+pub const PAUSE_INDICATOR_COLOR: Color = Color::from_rgb(255, 160, 0);
+
+/// 限制渲染帧率时待合并数据队列的默认最大长度，超出时丢弃最早的数据。可通过`RichText::set_append_queue_capacity`调整。
+pub const APPEND_QUEUE_CAPACITY: usize = 10000;
+
+/// 回顾区懒加载模式下的默认预取距离，以屏为单位，滚动位置距离内容边缘小于该距离时请求相邻页。
+pub const DEFAULT_PREFETCH_SCREENS: f32 = 0.25;
+
+/// 分页请求的默认等待时间，超时仍未收到页数据时允许再次请求。
+pub const PAGE_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// 等待页数据时加载提示的刷新间隔时间，单位秒。
+pub const PAGE_SPINNER_INTERVAL: f64 = 0.1;
+
+/// 回顾区划选超出可视区域上下边界时自动滚动的间隔时间，单位秒。
+pub const DRAG_SCROLL_INTERVAL: f64 = 0.03;
+
+/// 开始拖出选中文字或图片所需的最小移动距离，单位像素，移动距离不足时仍视为点击。
+pub(crate) const DRAG_OUT_THRESHOLD: i32 = 4;
+
+/// 自动折行续行标记的尺寸。
+pub(crate) const WRAP_MARKER_SIZE: i32 = 3;
+
+/// 划选自动滚动时每次滚动的最大距离。
+pub const MAX_DRAG_SCROLL_STEP: i32 = 80;
+
+/// 回顾区分栏显示时相邻两栏之间的间距。
+pub const COLUMN_GAP: i32 = 24;
+
+/// 回顾区最多可分为几栏显示。
+pub const MAX_COLUMNS: usize = 3;
+
+/// 输入行保留的历史记录数量。
+pub const PROMPT_HISTORY_CAPACITY: usize = 100;
+
+/// 主视图底部回显区最多显示的行数。
+pub const ECHO_MAX_LINES: usize = 3;
+
+/// 默认的ANSI 16色调色板，依次为黑、红、绿、黄、蓝、品红、青、白及其高亮色，取值与xterm一致。
+pub const DEFAULT_ANSI_PALETTE: [Color; 16] = [
+    Color::from_rgb(0, 0, 0),
+    Color::from_rgb(205, 0, 0),
+    Color::from_rgb(0, 205, 0),
+    Color::from_rgb(205, 205, 0),
+    Color::from_rgb(0, 0, 238),
+    Color::from_rgb(205, 0, 205),
+    Color::from_rgb(0, 205, 205),
+    Color::from_rgb(229, 229, 229),
+    Color::from_rgb(127, 127, 127),
+    Color::from_rgb(255, 0, 0),
+    Color::from_rgb(0, 255, 0),
+    Color::from_rgb(255, 255, 0),
+    Color::from_rgb(92, 92, 255),
+    Color::from_rgb(255, 0, 255),
+    Color::from_rgb(0, 255, 255),
+    Color::from_rgb(255, 255, 255),
+];
+
+/// 适配绿色盲的ANSI 16色调色板，以橙红色和蓝绿色代替红色和绿色，颜色取自Okabe-Ito色板。
+pub const DEUTERANOPIA_ANSI_PALETTE: [Color; 16] = [
+    Color::from_rgb(0, 0, 0),
+    Color::from_rgb(213, 94, 0),
+    Color::from_rgb(0, 158, 115),
+    Color::from_rgb(240, 228, 66),
+    Color::from_rgb(0, 114, 178),
+    Color::from_rgb(204, 121, 167),
+    Color::from_rgb(86, 180, 233),
+    Color::from_rgb(229, 229, 229),
+    Color::from_rgb(127, 127, 127),
+    Color::from_rgb(255, 130, 40),
+    Color::from_rgb(60, 210, 160),
+    Color::from_rgb(255, 245, 120),
+    Color::from_rgb(90, 160, 255),
+    Color::from_rgb(235, 160, 200),
+    Color::from_rgb(140, 210, 250),
+    Color::from_rgb(255, 255, 255),
+];
+
+/// 适配红色盲的ANSI 16色调色板。红色盲对红色的亮度感知较弱，红色改用明亮的橙色，其余与`DEUTERANOPIA_ANSI_PALETTE`一致。
+pub const PROTANOPIA_ANSI_PALETTE: [Color; 16] = [
+    Color::from_rgb(0, 0, 0),
+    Color::from_rgb(230, 159, 0),
+    Color::from_rgb(0, 158, 115),
+    Color::from_rgb(240, 228, 66),
+    Color::from_rgb(0, 114, 178),
+    Color::from_rgb(204, 121, 167),
+    Color::from_rgb(86, 180, 233),
+    Color::from_rgb(229, 229, 229),
+    Color::from_rgb(127, 127, 127),
+    Color::from_rgb(255, 190, 60),
+    Color::from_rgb(60, 210, 160),
+    Color::from_rgb(255, 245, 120),
+    Color::from_rgb(90, 160, 255),
+    Color::from_rgb(235, 160, 200),
+    Color::from_rgb(140, 210, 250),
+    Color::from_rgb(255, 255, 255),
+];
+
+/// 新数据段淡入效果的刷新间隔时间，单位秒。
+pub const FADE_IN_INTERVAL: f64 = 0.05;
+
+/// 默认的空闲等待时长，单位为秒。
+pub const IDLE_AFTER_SECS: u64 = 30;
+
+/// 默认的空闲状态下主视图刷新的防抖间隔，单位为秒。
+pub const IDLE_INTERVAL: f64 = 1.0;
+
+/// 打印配色中进度条和引用竖线等装饰元素使用的灰色。
+pub(crate) const PRINT_ACCENT_COLOR: Color = Color::from_rgb(160, 160, 160);
+
+/// 新数据段淡入效果的背景着色。
+pub const FADE_IN_TINT_COLOR: Color = Color::from_rgb(255, 220, 120);
+
+/// 淡入开始时着色在背景色中所占的比例。
+pub const FADE_IN_TINT_WEIGHT: f32 = 0.35;
+
+/// 背景图片默认的淡化程度，取值范围0.0~1.0，越大越接近背景色。
+pub const DEFAULT_BACKGROUND_DIM: f32 = 0.6;
+
 pub const MXP_IMAGE_CONTEXT_MENU_REFRESH: &str = "refresh";
 pub const MXP_IMAGE_CONTEXT_MENU_SAVE_AS: &str = "save_as";
 pub const MXP_IMAGE_CONTEXT_MENU_COPY_URL: &str = "copy_url";
@@ -244,6 +450,87 @@ impl LoadImageOption {
     }
 }
 
+/// 链接预览提供者，由上层应用实现并通过`RichText::set_preview_provider`注册。
+/// 新添加的文本数据段中首次出现某个网址时，组件调用`request`通知上层应用。上层应用应异步获取网页标题和图标，
+/// 与异步加载图片一样，将结果发送回`fltk`主线程后调用`RichText::set_link_preview`，预览卡片随即显示在该数据段下方。
+pub trait PreviewProvider: Send + Sync {
+    /// 请求获取网址的预览信息，在`fltk`主线程中调用，不应执行耗时操作。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 网址所在数据段的ID，设置预览信息时使用。
+    /// * `url`: 网址。
+    ///
+    /// returns: ()
+    fn request(&self, id: i64, url: &str);
+}
+
+impl Debug for dyn PreviewProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PreviewProvider")
+    }
+}
+
+/// 链接预览信息，显示为数据段下方由网站图标和标题组成的卡片，点击卡片时通过`CallbackData::OpenUrl`回调汇报网址。
+#[derive(Debug, Clone)]
+pub struct LinkPreview {
+    /// 网址。
+    pub url: String,
+    /// 网页标题。
+    pub title: String,
+    /// 网站图标，缩放后的影像在首次绘制时缓存。
+    favicon: Option<Icon>,
+}
+
+impl LinkPreview {
+    /// 构建链接预览信息。
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: 网址。
+    /// * `title`: 网页标题。
+    ///
+    /// returns: LinkPreview
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::LinkPreview;
+    ///
+    /// let preview = LinkPreview::new("https://www.rust-lang.org".to_string(), "Rust Programming Language".to_string());
+    /// assert_eq!(preview.title, "Rust Programming Language");
+    /// ```
+    pub fn new(url: String, title: String) -> Self {
+        Self { url, title, favicon: None }
+    }
+
+    /// 设置网站图标，绘制时缩放到`LINK_PREVIEW_ICON_SIZE`大小。
+    pub fn set_favicon(mut self, favicon: RgbImage) -> Self {
+        let (data, depth, width, height) = image_to_rgb_data(&Some(favicon), LINK_PREVIEW_ICON_SIZE, LINK_PREVIEW_ICON_SIZE);
+        self.favicon = data.map(|data| Icon::from_rgb_data(data, width, height, depth, LINK_PREVIEW_ICON_SIZE));
+        self
+    }
+
+    /// 在卡片分片的位置绘制预览卡片。
+    fn draw(&self, piece: &LinePiece, offset_y: i32, color: Color) {
+        let y = piece.y - offset_y;
+        draw::push_clip(piece.x, y, piece.w, piece.h);
+        set_draw_color(color.inactive());
+        draw_rounded_rect(piece.x, y, piece.w, piece.h, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+
+        let mut text_x = piece.x + LINK_PREVIEW_PADDING;
+        if let Some(icon) = &self.favicon {
+            icon.draw(text_x, y + (piece.h - LINK_PREVIEW_ICON_SIZE) / 2, LINK_PREVIEW_ICON_SIZE);
+            text_x += LINK_PREVIEW_ICON_SIZE + LINK_PREVIEW_PADDING;
+        }
+
+        set_font(piece.font, piece.font_size);
+        set_draw_color(color);
+        draw_text_n(&self.title, text_x, y + (piece.h - piece.font_height) / 2 + piece.font_size + piece.text_offset);
+        draw::pop_clip();
+    }
+}
+
 #[derive(Clone)]
 pub struct CprCallback {
     pub report: Arc<RwLock<Box<dyn FnMut(String) + Send + Sync +'static>>>
@@ -296,7 +583,31 @@ pub enum DocEditType {
     /// 通过回调函数汇报光标位置。
     CursorPosReport(CprCallback),
     /// 面板流结束标志。
-    PanelFlowEnd
+    PanelFlowEnd,
+    /// 进入(true)或退出(false)备用屏幕缓存，退出时丢弃备用屏幕上的所有数据。
+    AlternateScreen(bool),
+    /// 设置光标定位面板的滚动区域，参数为顶部行号和底部行号，均从1开始，0表示使用默认值。
+    SetScrollRegion(usize, usize),
+    /// 在光标所在行插入n个空行。
+    InsertLines(usize),
+    /// 删除光标所在行开始的n行。
+    DeleteLines(usize),
+    /// 在光标位置插入n个空格。
+    InsertChars(usize),
+    /// 删除光标位置开始的n个字符。
+    DeleteChars(usize),
+    /// 在光标所在列设置制表位。
+    SetTabStop,
+    /// 清除制表位：0清除光标所在列的制表位，3清除所有制表位。
+    ClearTabStop(u8),
+    /// 开启(true)或关闭(false)括号粘贴模式。
+    BracketedPaste(bool),
+    /// 开启(true)或关闭(false)鼠标汇报模式。
+    MouseReport(MouseReportMode, bool),
+    /// 通过回调函数汇报以字符为单位的窗口尺寸。
+    WindowSizeReport(CprCallback),
+    /// 设置光标样式，参数为DECSCUSR序列`CSI n q`中的n。
+    CursorStyle(u8),
 }
 
 impl Display for DocEditType {
@@ -320,12 +631,25 @@ impl Display for DocEditType {
             DocEditType::RemoteFlowControl(code) => {write!(f, "远程流控制子协商开关：{}>", code)}
             DocEditType::CursorPosReport(cb) => {write!(f, "汇报光标位置 {:?}", cb)}
             DocEditType::PanelFlowEnd => {write!(f, "面板流结束")}
+            DocEditType::AlternateScreen(on) => { write!(f, "{}备用屏幕", if *on { "进入" } else { "退出" }) }
+            DocEditType::SetScrollRegion(top, bottom) => { write!(f, "\x1b[{};{}r", top, bottom) }
+            DocEditType::InsertLines(n) => { write!(f, "\x1b[{}L", n) }
+            DocEditType::DeleteLines(n) => { write!(f, "\x1b[{}M", n) }
+            DocEditType::InsertChars(n) => { write!(f, "\x1b[{}@", n) }
+            DocEditType::DeleteChars(n) => { write!(f, "\x1b[{}P", n) }
+            DocEditType::SetTabStop => { write!(f, "\x1bH") }
+            DocEditType::ClearTabStop(mode) => { write!(f, "\x1b[{}g", mode) }
+            DocEditType::BracketedPaste(on) => { write!(f, "\x1b[?2004{}", if *on { "h" } else { "l" }) }
+            DocEditType::MouseReport(mode, on) => { write!(f, "\x1b[?{}{}", mode.code(), if *on { "h" } else { "l" }) }
+            DocEditType::WindowSizeReport(_) => { write!(f, "\x1b[18t") }
+            DocEditType::CursorStyle(n) => { write!(f, "设置光标样式：{}", n) }
         }
     }
 }
 
-/// 回调函数的参数类型，用于区分来源事件。
-#[derive(Debug)]
+/// 回调函数的参数类型，用于区分来源事件。以后可能增加新的事件类型，匹配时需要保留通配分支。
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum CallbackData {
     /// 数据互动事件产生的回调参数。
     Data(UserData),
@@ -333,6 +657,438 @@ pub enum CallbackData {
     Shape(ShapeData),
     /// 图片点击事件的回调参数。
     Image(ImageEventData),
+    /// 重复数据被抑制时产生的回调参数，附带本次被抑制的数据段数量。
+    Suppressed(usize),
+    /// 限制帧率时待合并队列已满而丢弃数据时产生的回调参数，附带上一帧以来丢弃的数据段数量。
+    Dropped(usize),
+    /// 服务端切换终端模式时产生的回调参数，附带切换后的全部模式状态。
+    ModeChanged(TerminalModes),
+    /// 开启鼠标汇报模式后，鼠标在主视图上按下或释放时产生的回调参数，附带应原样发送给服务端的字节序列。
+    /// 传统编码的行列号使用单字节表示，可能不是合法的UTF-8文本。
+    MouseReport(Vec<u8>),
+    /// 修饰键点击绑定为`ClickBinding::SendText`时产生的回调参数，附带点击处的单词。
+    SendText(String),
+    /// 修饰键点击绑定为`ClickBinding::OpenUrl`时产生的回调参数，附带点击处的网址，由上层应用负责打开。
+    OpenUrl(String),
+    /// 按住Ctrl键滚动鼠标滚轮缩放字体时产生的回调参数，附带缩放后的默认字体尺寸，上层应用可据此保存用户偏好。
+    Zoom(i32),
+    /// 新数据段的文本包含监视关键字时产生的回调参数。
+    Watch(WatchHit),
+    /// 新数据段的文本命中开启了触发的高亮规则时产生的回调参数。
+    Trigger(TriggerHit),
+    /// 回顾区滚动位置变化时产生的回调参数。
+    Scroll(ScrollInfo),
+    /// 鼠标进入或离开可互动数据段时产生的回调参数，上层应用可据此显示互动指令的提示信息。
+    /// 鼠标在同一数据段上移动时不会重复产生。
+    Hover {
+        /// 数据段ID。
+        id: i64,
+        /// `true`表示鼠标进入数据段，`false`表示离开。
+        entered: bool,
+    },
+    /// 文字或文件被拖放到主视图上时产生的回调参数。
+    Drop {
+        /// 拖放的原始文字内容。
+        text: String,
+        /// 拖放的文件路径，拖放内容不是文件时为空。
+        files: Vec<PathBuf>,
+    },
+    /// 在空白处或没有菜单的非互动数据段上右击，且未启用组件内置菜单时产生的回调参数，附带鼠标在窗口中的坐标，上层应用可据此弹出自己的菜单。
+    ContextMenu(i32, i32),
+    /// Linux系统中在组件上点击鼠标中键时产生的回调参数，附带PRIMARY选区中的文字，上层应用可将其粘贴到输入框中。
+    PrimaryPaste(String),
+    /// 输入行模式下按下回车键时产生的回调参数，附带输入行中的文字。
+    PromptInput(String),
+}
+
+impl CallbackData {
+    /// 获取回调参数所属的事件类别。
+    ///
+    /// returns: EventKind
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::{CallbackData, EventKind};
+    ///
+    /// assert_eq!(CallbackData::OpenUrl("https://example.com".to_string()).kind(), EventKind::Link);
+    /// ```
+    pub fn kind(&self) -> EventKind {
+        match self {
+            CallbackData::Data(_) => EventKind::Data,
+            CallbackData::Image(_) => EventKind::Image,
+            CallbackData::Shape(_) => EventKind::Shape,
+            CallbackData::SendText(_) | CallbackData::OpenUrl(_) | CallbackData::Hover { .. } => EventKind::Link,
+            CallbackData::Scroll(_) => EventKind::Scroll,
+            _ => EventKind::Other,
+        }
+    }
+}
+
+/// 回调事件的类别，用于指定具名回调函数接收哪些事件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// 数据段互动事件，即`CallbackData::Data`。
+    Data,
+    /// 图片点击事件，即`CallbackData::Image`。
+    Image,
+    /// 主视图缩放事件，即`CallbackData::Shape`。
+    Shape,
+    /// 修饰键点击产生的单词和网址事件，以及可互动数据段的悬停事件，即`CallbackData::SendText`、`CallbackData::OpenUrl`和`CallbackData::Hover`。
+    Link,
+    /// 回顾区滚动事件，即`CallbackData::Scroll`。只发送给具名回调函数。
+    Scroll,
+    /// 其他事件，如重复抑制、终端模式切换、监视关键字命中等。
+    Other,
+}
+
+/// 具名回调函数。
+pub(crate) struct NamedNotifier {
+    /// 名称，同名回调函数只保留最后注册的一个。
+    pub(crate) name: String,
+    /// 接收的事件类别，为空时接收全部事件。
+    pub(crate) kinds: Vec<EventKind>,
+    /// 回调函数。
+    pub(crate) notifier: Arc<RwLock<Box<dyn FnMut(CallbackData) + Send + Sync + 'static>>>,
+}
+
+impl NamedNotifier {
+    pub(crate) fn new<F>(name: &str, kinds: &[EventKind], notifier: F) -> Self where F: FnMut(CallbackData) + Send + Sync + 'static {
+        Self { name: name.to_string(), kinds: kinds.to_vec(), notifier: Arc::new(RwLock::new(Box::new(notifier))) }
+    }
+
+    fn accepts(&self, kind: EventKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+}
+
+/// 监视关键字的标识。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchId(pub(crate) u64);
+
+/// 监视关键字命中的信息。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchHit {
+    /// 命中的监视关键字标识。
+    pub id: WatchId,
+    /// 命中的数据段ID。
+    pub data_id: i64,
+    /// 命中的数据段文本。
+    pub text: String,
+}
+
+/// 高亮规则的标识。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HighlightId(pub(crate) u64);
+
+/// 高亮规则触发的信息。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerHit {
+    /// 命中的高亮规则标识。
+    pub id: HighlightId,
+    /// 命中的数据段ID，即拆分前原数据段的ID。
+    pub data_id: i64,
+    /// 匹配到的文字。
+    pub matched: String,
+    /// 命中的数据段文本。
+    pub text: String,
+}
+
+/// 高亮规则对匹配文字的样式调整，未设置的样式保持原样。
+#[derive(Debug, Clone, Default)]
+pub struct StyleOverride {
+    pub fg_color: Option<Color>,
+    pub bg_color: Option<Color>,
+    pub underline: Option<bool>,
+    pub italic: Option<bool>,
+    pub strike_through: Option<bool>,
+    pub blink: Option<bool>,
+    /// 命中时是否通过`CallbackData::Trigger`回调通知。
+    pub trigger: bool,
+}
+
+impl StyleOverride {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_fg_color(mut self, fg_color: Color) -> Self {
+        self.fg_color = Some(fg_color);
+        self
+    }
+
+    pub fn set_bg_color(mut self, bg_color: Color) -> Self {
+        self.bg_color = Some(bg_color);
+        self
+    }
+
+    pub fn set_underline(mut self, underline: bool) -> Self {
+        self.underline = Some(underline);
+        self
+    }
+
+    pub fn set_italic(mut self, italic: bool) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    pub fn set_strike_through(mut self, strike_through: bool) -> Self {
+        self.strike_through = Some(strike_through);
+        self
+    }
+
+    pub fn set_blink(mut self, blink: bool) -> Self {
+        self.blink = Some(blink);
+        self
+    }
+
+    pub fn set_trigger(mut self, trigger: bool) -> Self {
+        self.trigger = trigger;
+        self
+    }
+
+    /// 将样式调整应用到数据段上。
+    fn apply(&self, mut data: UserData) -> UserData {
+        if let Some(fg_color) = self.fg_color {
+            data = data.set_fg_color(fg_color);
+        }
+        if let Some(bg_color) = self.bg_color {
+            data = data.set_bg_color(Some(bg_color));
+        }
+        if let Some(underline) = self.underline {
+            data = data.set_underline(underline);
+        }
+        if let Some(italic) = self.italic {
+            data = data.set_italic(italic);
+        }
+        if let Some(strike_through) = self.strike_through {
+            data.strike_through = strike_through;
+        }
+        if let Some(blink) = self.blink {
+            data = data.set_blink(blink);
+        }
+        data
+    }
+}
+
+/// 具名样式的定义，通过`RichText::define_style`注册，数据段通过`UserData::with_style`引用。未设置的样式保持原样。
+#[derive(Debug, Clone, Default)]
+pub struct StyleDef {
+    pub fg_color: Option<Color>,
+    pub bg_color: Option<Color>,
+    pub font: Option<Font>,
+    pub font_size: Option<i32>,
+    pub underline: Option<bool>,
+    pub italic: Option<bool>,
+    pub strike_through: Option<bool>,
+    pub blink: Option<bool>,
+}
+
+impl StyleDef {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_fg_color(mut self, fg_color: Color) -> Self {
+        self.fg_color = Some(fg_color);
+        self
+    }
+
+    pub fn set_bg_color(mut self, bg_color: Color) -> Self {
+        self.bg_color = Some(bg_color);
+        self
+    }
+
+    pub fn set_font(mut self, font: Font) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn set_font_size(mut self, font_size: i32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    pub fn set_underline(mut self, underline: bool) -> Self {
+        self.underline = Some(underline);
+        self
+    }
+
+    pub fn set_italic(mut self, italic: bool) -> Self {
+        self.italic = Some(italic);
+        self
+    }
+
+    pub fn set_strike_through(mut self, strike_through: bool) -> Self {
+        self.strike_through = Some(strike_through);
+        self
+    }
+
+    pub fn set_blink(mut self, blink: bool) -> Self {
+        self.blink = Some(blink);
+        self
+    }
+
+    /// 将样式应用到新数据段上。只设置了字号而没有设置字体时，沿用数据段自定义的字体或组件默认字体`base.font`。
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 新数据段。
+    /// * `base`: 组件的默认样式。
+    ///
+    /// returns: UserData
+    pub(crate) fn apply(&self, mut data: UserData, base: &StyleDef) -> UserData {
+        if self.font.is_some() || self.font_size.is_some() {
+            let default_font = if data.custom_font_text { data.font } else { base.font.unwrap_or(data.font) };
+            let default_size = if data.custom_font_text { data.font_size } else { base.font_size.unwrap_or(data.font_size) };
+            data = data.set_font_and_size(self.font.unwrap_or(default_font), self.font_size.unwrap_or(default_size));
+        }
+        if let Some(fg_color) = self.fg_color {
+            data = data.set_fg_color(fg_color);
+        }
+        if let Some(bg_color) = self.bg_color {
+            data = data.set_bg_color(Some(bg_color));
+        }
+        if let Some(underline) = self.underline {
+            data = data.set_underline(underline);
+        }
+        if let Some(italic) = self.italic {
+            data = data.set_italic(italic);
+        }
+        if let Some(strike_through) = self.strike_through {
+            data.strike_through = strike_through;
+        }
+        if let Some(blink) = self.blink {
+            data = data.set_blink(blink);
+        }
+        data
+    }
+
+    /// 将新定义应用到已缓存的数据段上，调用方负责重新排版。旧定义设置过而新定义未设置的样式恢复为组件的默认样式，
+    /// 斜体数据段使用对应的斜体字体。
+    ///
+    /// # Arguments
+    ///
+    /// * `previous`: 数据段原先引用的样式定义。
+    /// * `base`: 组件的默认样式，其中未设置的背景色表示不绘制背景。
+    /// * `rd`: 数据段。
+    ///
+    /// returns: ()
+    pub(crate) fn restyle(&self, previous: Option<&StyleDef>, base: &StyleDef, rd: &mut RichData) {
+        fn pick<T: Copy>(new: Option<T>, old: Option<T>, default: Option<T>) -> Option<T> {
+            new.or(old.and(default))
+        }
+        let old = previous.cloned().unwrap_or_default();
+        let italic = pick(self.italic, old.italic, Some(false)).unwrap_or(rd.italic);
+        let upright = pick(self.font, old.font, base.font).unwrap_or_else(|| upright_font(rd.font));
+        rd.font = if italic { italic_font(upright) } else { upright };
+        rd.italic = italic;
+        if let Some(font_size) = pick(self.font_size, old.font_size, base.font_size) {
+            rd.font_size = font_size;
+        }
+        if let Some(fg_color) = pick(self.fg_color, old.fg_color, base.fg_color) {
+            rd.fg_color = fg_color;
+        }
+        if let Some(bg_color) = self.bg_color {
+            rd.bg_color = Some(bg_color);
+        } else if old.bg_color.is_some() {
+            rd.bg_color = base.bg_color;
+        }
+        if let Some(underline) = pick(self.underline, old.underline, Some(false)) {
+            rd.underline = underline;
+        }
+        if let Some(strike_through) = pick(self.strike_through, old.strike_through, Some(false)) {
+            rd.strike_through = strike_through;
+        }
+        if let Some(blink) = pick(self.blink, old.blink, Some(false)) {
+            rd.blink = blink;
+        }
+    }
+}
+
+/// 持久高亮的关键字，所有出现位置以指定背景色绘制，直到被移除。
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct KeywordHighlight {
+    pub(crate) word: String,
+    pub(crate) color: Color,
+}
+
+/// 已注册的高亮规则。
+#[derive(Debug, Clone)]
+pub(crate) struct HighlightRule {
+    pub(crate) id: HighlightId,
+    pub(crate) pattern: Regex,
+    pub(crate) style: StyleOverride,
+}
+
+/// 按照高亮规则拆分文本数据段，并对匹配的文字应用样式调整。
+/// 多条规则匹配到重叠的文字时，先注册的规则优先，后注册的规则只产生触发通知。
+/// 第一个片段沿用原数据段的ID，其余片段使用新的ID，所有片段的`origin_id`都记录原数据段的ID，
+/// 通过原ID更新、禁用或过期数据时作用于全部拆分片段。
+///
+/// # Arguments
+///
+/// * `user_data`: 文本数据段。
+/// * `rules`: 高亮规则，按注册顺序排列。
+///
+/// returns: (Vec<UserData, Global>, Vec<TriggerHit, Global>) 返回拆分后的数据段和触发信息。
+pub(crate) fn apply_highlights(user_data: UserData, rules: &[HighlightRule]) -> (Vec<UserData>, Vec<TriggerHit>) {
+    let text = user_data.text.clone();
+    let mut owners: Vec<Option<usize>> = vec![None; text.len()];
+    let mut hits = vec![];
+    for (idx, rule) in rules.iter().enumerate() {
+        for m in rule.pattern.find_iter(&text) {
+            if m.is_empty() {
+                continue;
+            }
+            if rule.style.trigger {
+                hits.push(TriggerHit { id: rule.id, data_id: user_data.id, matched: m.as_str().to_string(), text: text.clone() });
+            }
+            if owners[m.range()].iter().all(Option::is_none) {
+                owners[m.range()].fill(Some(idx));
+            }
+        }
+    }
+    if owners.iter().all(Option::is_none) {
+        return (vec![user_data], hits);
+    }
+
+    let mut pieces: Vec<UserData> = vec![];
+    let mut start = 0;
+    for end in 1..=text.len() {
+        if end < text.len() && owners[end] == owners[start] {
+            continue;
+        }
+        let mut piece = user_data.clone();
+        piece.text = text[start..end].to_string();
+        if start > 0 {
+            piece.id = deterministic::next_id();
+        }
+        piece.origin_id = Some(user_data.id);
+        if let Some(idx) = owners[start] {
+            piece = rules[idx].style.apply(piece);
+        }
+        pieces.push(piece);
+        start = end;
+    }
+    (pieces, hits)
+}
+
+/// 获取缓存中指定ID的数据段及其拆分片段的索引范围。高亮规则拆分出的片段在缓存中相邻排列。
+/// 顶部追加模式和载入的会话中数据段不按ID排序，因此顺序查找。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `id`: 数据段ID或拆分片段的原数据段ID。
+///
+/// returns: Range<usize> 未找到时返回空范围。
+pub(crate) fn id_range(buffer: &[RichData], id: i64) -> Range<usize> {
+    let Some(start) = buffer.iter().position(|rd| rd.has_origin(id)) else {
+        return 0..0;
+    };
+    let end = start + buffer[start..].iter().take_while(|rd| rd.has_origin(id)).count();
+    start..end
 }
 
 
@@ -343,6 +1099,8 @@ pub enum CallbackData {
 pub struct Callback {
     /// 回调函数。
     notifier: Arc<RwLock<Box<dyn FnMut(CallbackData) + Send + Sync +'static>>>,
+    /// 具名回调函数，由回调结构体的所有副本共享，注册后主视图和回顾区都能收到事件。
+    listeners: Arc<RwLock<Vec<NamedNotifier>>>,
 }
 
 impl Callback {
@@ -381,7 +1139,12 @@ impl Callback {
     /// rich_text.set_notifier(cb_fn);
     /// ```
     pub fn new(notifier: Arc<RwLock<Box<dyn FnMut(CallbackData) + Send + Sync +'static>>>) -> Callback {
-        Callback { notifier }
+        Callback { notifier, listeners: Arc::new(RwLock::new(vec![])) }
+    }
+
+    /// 构建共享具名回调函数列表的回调结构体实例。
+    pub(crate) fn with_listeners(notifier: Arc<RwLock<Box<dyn FnMut(CallbackData) + Send + Sync +'static>>>, listeners: Arc<RwLock<Vec<NamedNotifier>>>) -> Callback {
+        Callback { notifier, listeners }
     }
 
     /// 执行回调。
@@ -398,9 +1161,27 @@ impl Callback {
     ///
     /// ```
     fn notify(&mut self, data: CallbackData) {
+        self.notify_listeners(data.clone());
         let notify = &mut* self.notifier.write();
         notify(data);
     }
+
+    /// 只向接收该类事件的具名回调函数发送事件，`set_notifier`设置的回调函数不会收到。
+    /// 用于具名回调函数出现之后新增的高频事件，避免改变已有回调函数的行为。
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 事件数据。
+    ///
+    /// returns: ()
+    pub(crate) fn notify_listeners(&self, data: CallbackData) {
+        let kind = data.kind();
+        // 先收集匹配的回调函数并释放列表锁，回调函数中可以注册或移除具名回调函数。
+        let listeners = self.listeners.read().iter().filter(|l| l.accepts(kind)).map(|l| l.notifier.clone()).collect::<Vec<_>>();
+        for listener in listeners {
+            (listener.write())(data.clone());
+        }
+    }
 }
 
 impl Debug for Callback {
@@ -409,16 +1190,351 @@ impl Debug for Callback {
     }
 }
 
-/// 分页请求参数
-#[derive(Debug, Clone)]
-pub enum PageOptions {
-    /// 下一页，附带当前页的最后一条记录的id。
-    NextPage(i64),
-    /// 上一页，附带当前页的第一条记录的id。
-    PrevPage(i64),
+/// 右键菜单的附加菜单项构建器。由`RichText::set_context_menu_hook`设置的钩子函数向其中添加菜单项，
+/// 附加菜单项显示在数据段自身的互动菜单项之后。
+#[derive(Default)]
+pub struct MenuBuilder {
+    items: Vec<(String, Box<dyn FnMut(&UserData)>)>,
 }
 
-/// 请求新页数据的回调函数载体。
+impl MenuBuilder {
+    /// 添加菜单项。
+    ///
+    /// # Arguments
+    ///
+    /// * `label`: 菜单项文字。
+    /// * `cb`: 选中菜单项时执行的函数，参数为右键点击的数据段。
+    ///
+    /// returns: &mut MenuBuilder
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::app;
+    /// use fltkrs_richdisplay::MenuBuilder;
+    ///
+    /// let mut menu = MenuBuilder::default();
+    /// menu.add_item("复制", |data| app::copy(&data.text));
+    /// assert_eq!(menu.len(), 1);
+    /// ```
+    pub fn add_item<F>(&mut self, label: &str, cb: F) -> &mut Self where F: FnMut(&UserData) + 'static {
+        self.items.push((label.to_string(), Box::new(cb)));
+        self
+    }
+
+    /// 已添加的菜单项数量。
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// 是否未添加任何菜单项。
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// 将附加菜单项追加到弹出菜单中，与前面已有的菜单项之间以分隔线隔开。
+    pub(crate) fn append_to(&self, menu: &mut MenuButton) {
+        if menu.size() > 0 && !self.items.is_empty() {
+            menu.set_mode(menu.size() - 1, MenuFlag::MenuDivider);
+        }
+        for (label, _) in self.items.iter() {
+            menu.add_choice(label);
+        }
+    }
+
+    /// 执行第`idx`个附加菜单项。
+    pub(crate) fn run(&mut self, idx: usize, data: &UserData) {
+        if let Some((_, cb)) = self.items.get_mut(idx) {
+            cb(data);
+        }
+    }
+}
+
+impl Debug for MenuBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MenuBuilder items: {:?}", self.items.iter().map(|(label, _)| label).collect::<Vec<_>>())
+    }
+}
+
+/// 在空白处右击时弹出组件内置菜单，提供复制选中内容和全选功能。全选后同样将全部内容拷贝至剪贴板。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `selected_pieces`: 选中的数据片段。
+/// * `after_select_all`: 全选后执行的函数，用于重绘组件。
+///
+/// returns: ()
+pub(crate) fn popup_blank_menu(buffer: Arc<RwLock<Vec<RichData>>>, selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>, keep_soft_wraps: bool, mut after_select_all: impl FnMut() + 'static) {
+    let mut menu = MenuButton::new(0, 0, 0, 0, None);
+    menu.set_type(MenuButtonType::Popup1);
+    menu.set_color(Color::by_index(214));
+    menu.add_choice("复制");
+    menu.add_choice("全选");
+    if selected_pieces.read().is_empty() {
+        menu.set_mode(0, MenuFlag::Inactive);
+    }
+    menu.set_callback(move |m| {
+        match m.value() {
+            0 => {
+                let mut selection = String::new();
+                copy_pieces(selected_pieces.read().iter(), &mut selection, keep_soft_wraps);
+                app::copy(selection.as_str());
+            }
+            1 => {
+                clear_selected_pieces(selected_pieces.clone());
+                let mut all = vec![];
+                for rd in buffer.read().iter().filter(|rd| rd.data_type == DataType::Text) {
+                    for piece_rc in rd.line_pieces.iter() {
+                        piece_rc.read().select_all();
+                        all.push(Arc::downgrade(piece_rc));
+                    }
+                }
+                *selected_pieces.write() = all;
+                let mut selection = String::new();
+                copy_pieces(selected_pieces.read().iter(), &mut selection, keep_soft_wraps);
+                copy_selection(selection.as_str());
+                after_select_all();
+            }
+            _ => {}
+        }
+    });
+    menu.popup();
+}
+
+/// 右键菜单钩子函数载体。
+#[derive(Clone)]
+pub struct ContextMenuHook {
+    /// 钩子函数。
+    hook: Arc<RwLock<Box<dyn FnMut(&UserData, &mut MenuBuilder) + Send + Sync + 'static>>>,
+}
+
+impl ContextMenuHook {
+    /// 构建新的右键菜单钩子函数载体。
+    pub fn new(hook: Arc<RwLock<Box<dyn FnMut(&UserData, &mut MenuBuilder) + Send + Sync + 'static>>>) -> Self {
+        Self { hook }
+    }
+
+    /// 调用钩子函数，生成右键点击数据段时的附加菜单项。
+    pub(crate) fn build(&self, data: &UserData) -> MenuBuilder {
+        let mut menu = MenuBuilder::default();
+        let hook = &mut *self.hook.write();
+        hook(data, &mut menu);
+        menu
+    }
+}
+
+impl Debug for ContextMenuHook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContextMenuHook count: {}", Arc::strong_count(&self.hook))
+    }
+}
+
+/// 色觉障碍类型，用于选择内置的配色方案。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorVision {
+    /// 绿色盲或绿色弱，最常见的红绿色觉障碍。
+    Deuteranopia,
+    /// 红色盲或红色弱。
+    Protanopia,
+}
+
+impl ColorVision {
+    /// 获取适配该类型色觉障碍的ANSI 16色调色板。
+    pub fn ansi_palette(&self) -> [Color; 16] {
+        match self {
+            ColorVision::Deuteranopia => DEUTERANOPIA_ANSI_PALETTE,
+            ColorVision::Protanopia => PROTANOPIA_ANSI_PALETTE,
+        }
+    }
+
+    /// 调整颜色，使其在该类型色觉障碍者眼中更易区分。先模拟色觉障碍者看到的颜色，
+    /// 再将无法感知的差异转移到仍可感知的通道上，灰色保持不变。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`: 原始颜色。
+    ///
+    /// returns: Color
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::ColorVision;
+    ///
+    /// let red = ColorVision::Deuteranopia.correct(Color::from_rgb(255, 0, 0));
+    /// assert_ne!(red, Color::from_rgb(255, 0, 0));
+    /// ```
+    pub fn correct(&self, color: Color) -> Color {
+        let (r, g, b) = color.to_rgb();
+        let (r, g, b) = (r as f32, g as f32, b as f32);
+        // 转换到LMS色彩空间并去除缺失的视锥响应。
+        let l = 17.8824 * r + 43.5161 * g + 4.11935 * b;
+        let m = 3.45565 * r + 27.1554 * g + 3.86714 * b;
+        let s = 0.0299566 * r + 0.184309 * g + 1.46709 * b;
+        let (l, m) = match self {
+            ColorVision::Deuteranopia => (l, 0.494207 * l + 1.24827 * s),
+            ColorVision::Protanopia => (2.02344 * m - 2.52581 * s, m),
+        };
+        let sr = 0.0809444479 * l - 0.130504409 * m + 0.116721066 * s;
+        let sg = -0.0102485335 * l + 0.0540193266 * m - 0.113614708 * s;
+        let sb = -0.000365296938 * l - 0.00412161469 * m + 0.693511405 * s;
+        let (er, eg, eb) = (r - sr, g - sg, b - sb);
+        let clamp = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+        Color::from_rgb(clamp(r), clamp(g + 0.7 * er + eg), clamp(b + 0.7 * er + eb))
+    }
+}
+
+/// 在应用设置的调色板上叠加色觉障碍配色。使用默认调色板时直接换用内置的配色方案，否则逐个调整自定义调色板中的颜色。
+///
+/// # Arguments
+///
+/// * `user`: 应用或主题设置的调色板，为空时使用默认调色板。
+/// * `vision`: 色觉障碍类型。
+///
+/// returns: [Color; 16]
+pub(crate) fn compose_palette(user: Option<[Color; 16]>, vision: Option<ColorVision>) -> [Color; 16] {
+    let palette = user.unwrap_or(DEFAULT_ANSI_PALETTE);
+    match vision {
+        None => palette,
+        Some(vision) if palette == DEFAULT_ANSI_PALETTE => vision.ansi_palette(),
+        Some(vision) => palette.map(|c| vision.correct(c)),
+    }
+}
+
+/// 颜色变换函数载体，绘制时对数据段的前景色和背景色进行变换。
+#[derive(Clone)]
+pub struct ColorFilter {
+    /// 变换函数。
+    filter: Arc<RwLock<Box<dyn FnMut(Color) -> Color + Send + Sync + 'static>>>,
+}
+
+impl ColorFilter {
+    /// 构建新的颜色变换函数载体。
+    pub fn new(filter: Arc<RwLock<Box<dyn FnMut(Color) -> Color + Send + Sync + 'static>>>) -> Self {
+        Self { filter }
+    }
+
+    /// 调用变换函数。
+    pub(crate) fn apply(&self, color: Color) -> Color {
+        let filter = &mut *self.filter.write();
+        filter(color)
+    }
+}
+
+impl Debug for ColorFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ColorFilter count: {}", Arc::strong_count(&self.filter))
+    }
+}
+
+/// 视图绘制时使用的颜色变换，包括色觉障碍配色调整和应用自定义的变换函数。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ColorTransform {
+    /// 应用自定义的变换函数，作用于所有颜色。
+    pub(crate) filter: Option<ColorFilter>,
+    /// 色觉障碍类型。设置了颜色序号的颜色已由对应的调色板处理，只调整直接指定的颜色。
+    pub(crate) vision: Option<ColorVision>,
+}
+
+impl ColorTransform {
+    /// 变换颜色。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`: 原始颜色。
+    /// * `indexed`: 是否为调色板中的颜色。
+    ///
+    /// returns: Color
+    pub(crate) fn apply(&self, color: Color, indexed: bool) -> Color {
+        let color = match self.vision {
+            Some(vision) if !indexed => vision.correct(color),
+            _ => color,
+        };
+        match &self.filter {
+            Some(filter) => filter.apply(color),
+            None => color,
+        }
+    }
+}
+
+thread_local! {
+    /// 当前正在绘制的视图所使用的颜色变换，仅在`fltk`主线程的绘制过程中设置。
+    static ACTIVE_COLOR_TRANSFORM: RefCell<Option<ColorTransform>> = RefCell::new(None);
+}
+
+/// 在绘制期间启用颜色变换，离开作用域时恢复之前的设置。
+pub(crate) struct ColorTransformScope {
+    previous: Option<ColorTransform>,
+}
+
+impl ColorTransformScope {
+    pub(crate) fn enter(transform: &ColorTransform) -> Self {
+        let active = if transform.filter.is_none() && transform.vision.is_none() { None } else { Some(transform.clone()) };
+        let previous = ACTIVE_COLOR_TRANSFORM.with(|t| t.replace(active));
+        Self { previous }
+    }
+}
+
+impl Drop for ColorTransformScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE_COLOR_TRANSFORM.with(|t| t.replace(previous));
+    }
+}
+
+/// 按照当前绘制视图的颜色变换调整颜色，未启用变换时返回原色。
+///
+/// # Arguments
+///
+/// * `color`: 原始颜色。
+/// * `indexed`: 是否为调色板中的颜色。
+///
+/// returns: Color
+pub(crate) fn transform_color(color: Color, indexed: bool) -> Color {
+    ACTIVE_COLOR_TRANSFORM.with(|t| match t.borrow().as_ref() {
+        Some(transform) => transform.apply(color, indexed),
+        None => color,
+    })
+}
+
+/// 新数据段的追加方向。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// 新数据追加在底部，视图固定显示最底部的内容。
+    #[default]
+    Bottom,
+    /// 新数据追加在顶部，视图固定显示最顶部的内容。
+    Top,
+}
+
+/// 自动换行时选择断行位置的策略。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapPolicy {
+    /// 在任意字符处断行。
+    #[default]
+    Anywhere,
+    /// 优先在空白和标点处断行，单词超过整行宽度时才在单词内部断行。
+    WordBoundary,
+    /// 在`WordBoundary`的基础上允许在中日韩文字之间断行，适用于中西文混排的内容。
+    WordBoundaryCJK,
+}
+
+/// 分页请求参数。以后可能增加新的请求方式，匹配时应保留通配分支。
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum PageOptions {
+    /// 下一页，附带当前页的最后一条记录的id。
+    NextPage(i64),
+    /// 上一页，附带当前页的第一条记录的id。
+    PrevPage(i64),
+    /// 跳转到指定位置，附带目标记录的id，或由应用程序自行解释的时间戳等定位值。
+    /// 应用程序应提供包含目标记录的一页数据，回顾区会替换已载入的全部数据，并滚动到id与定位值相同的数据段。
+    At(i64),
+}
+
+/// 请求新页数据的回调函数载体。
 /// 当视图滚动到页面底部或顶部时，通过鼠标滚轮或按键`PageDown`或`PageUp`时，会触发执行预定义的回调函数，
 /// 若有更多可用的数据，用户应当在此时提供下一页或上一页数据。
 #[derive(Clone)]
@@ -464,6 +1580,81 @@ impl ShapeData {
     }
 }
 
+/// 鼠标汇报模式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum MouseReportMode {
+    /// X10兼容模式，仅汇报鼠标按下事件，对应`\x1b[?9h`。
+    X10,
+    /// 常规跟踪模式，汇报鼠标按下和释放事件，对应`\x1b[?1000h`。
+    Normal,
+    /// SGR扩展编码，对应`\x1b[?1006h`，需配合前两种模式之一使用。
+    Sgr,
+}
+
+impl MouseReportMode {
+    /// 获取模式对应的DEC私有模式编号。
+    pub fn code(&self) -> u16 {
+        match self {
+            MouseReportMode::X10 => 9,
+            MouseReportMode::Normal => 1000,
+            MouseReportMode::Sgr => 1006,
+        }
+    }
+}
+
+/// 由服务端控制的终端模式状态。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TerminalModes {
+    /// 是否开启括号粘贴模式。
+    pub bracketed_paste: bool,
+    /// 鼠标跟踪模式，只可能是`X10`或`Normal`，为空时不汇报鼠标事件。
+    pub mouse_tracking: Option<MouseReportMode>,
+    /// 是否使用SGR扩展编码汇报鼠标事件。
+    pub sgr_mouse: bool,
+}
+
+impl TerminalModes {
+    /// 按照当前模式编码鼠标事件。
+    ///
+    /// # Arguments
+    ///
+    /// * `button`: 鼠标按键，0左键，1中键，2右键。
+    /// * `col`: 鼠标所在列，从1开始。
+    /// * `row`: 鼠标所在行，从1开始。
+    /// * `pressed`: 是否为按下事件，否则为释放事件。
+    ///
+    /// returns: Option<Vec<u8, Global>> 应发送给服务端的字节序列。当前模式不需要汇报该事件，
+    /// 或者传统编码下行列号超过223无法表示时返回空。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::{MouseReportMode, TerminalModes};
+    ///
+    /// let modes = TerminalModes { mouse_tracking: Some(MouseReportMode::Normal), ..Default::default() };
+    /// assert_eq!(modes.encode_mouse(0, 1, 1, true), Some(b"\x1b[M !!".to_vec()));
+    /// // 传统编码使用单字节表示行列号，超过223的位置无法汇报。
+    /// assert_eq!(modes.encode_mouse(0, 200, 1, true), Some(vec![0x1b, b'[', b'M', 32, 232, 33]));
+    /// assert_eq!(modes.encode_mouse(0, 224, 1, true), None);
+    /// ```
+    pub fn encode_mouse(&self, button: u8, col: usize, row: usize, pressed: bool) -> Option<Vec<u8>> {
+        let tracking = self.mouse_tracking?;
+        if !pressed && tracking == MouseReportMode::X10 {
+            return None;
+        }
+        if self.sgr_mouse {
+            Some(format!("\x1b[<{};{};{}{}", button, col, row, if pressed { "M" } else { "m" }).into_bytes())
+        } else {
+            // 传统编码以`32+n`的单字节表示行列号，只能表示223以内的位置，且释放事件不区分按键。
+            if col > 223 || row > 223 {
+                return None;
+            }
+            let b = if pressed { button } else { 3 };
+            Some(vec![0x1b, b'[', b'M', 32 + b, 32 + col as u8, 32 + row as u8])
+        }
+    }
+}
+
 /// 用于表示鼠标点击图片时的事件信息。
 #[derive(Debug, Clone)]
 pub struct ImageEventData {
@@ -507,628 +1698,1131 @@ impl CallPage {
     }
 }
 
-/// 闪烁强度状态。
-#[derive(Debug, Clone,Copy, PartialEq, Eq)]
-pub(crate) enum BlinkDegree {
-    /// 正常，原色显示。
-    Normal,
-    /// 对比色或不显示。
-    Contrast,
+/// 后台查找请求，由回顾区在已载入的数据中没有更多目标时发出。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchRequest {
+    /// 目标字符串。
+    pub pattern: String,
+    /// 查找起点数据的id，查找范围不包含该数据本身。为0表示回顾区中没有已载入的数据。
+    pub from_id: i64,
+    /// true表示向更新的数据查找，false表示向更早的数据查找。
+    pub forward: bool,
 }
 
-/// 可视区域闪烁开关标记和状态。
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct BlinkState {
-    /// 可视区域是否存在闪烁内容。
-    on: bool,
-    /// 应闪烁内容在下一次刷新显示时的强度。
-    next: BlinkDegree,
+/// 请求后台查找的回调函数载体。
+/// 历史模式下回顾区只保存了部分数据，当已载入的数据中没有更多查找目标时，会触发执行预定义的回调函数，
+/// 用户应当在自己的数据存储中查找，然后通过`RichReviewer::load_search_result`告知下一个目标所在数据的id。
+#[derive(Clone)]
+pub struct CallSearch {
+    /// 回调函数。
+    notifier: Arc<RwLock<Box<dyn FnMut(SearchRequest) + Sync + Send + 'static>>>,
+}
 
-    /// 焦点目标的边框颜色。
-    focus_boarder_color: Color,
+impl Debug for CallSearch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CallSearch count: {}", Arc::<RwLock<Box<(dyn FnMut(SearchRequest) + Sync + Send + 'static)>>>::strong_count(&self.notifier))
+    }
+}
 
-    /// 焦点目标的边框对比色。
-    focus_boarder_contrast_color: Color,
+impl CallSearch {
+    /// 构建新的后台查找回调结构体实例。
+    pub fn new(notifier: Arc<RwLock<Box<dyn FnMut(SearchRequest) + Sync + Send + 'static>>>) -> Self {
+        Self { notifier }
+    }
 
-    /// 焦点目标的边框线条宽度。
-    focus_boarder_width: i32,
+    fn notify(&mut self, request: SearchRequest) {
+        let notify = &mut* self.notifier.write();
+        notify(request);
+    }
+}
 
-    /// 焦点目标的背景颜色。
-    focus_background_color: Color,
+/// 懒加载分页的预取状态。滚动到距离内容边缘指定距离内时提前请求相邻页，等待期间不重复请求。
+#[derive(Debug, Clone)]
+pub(crate) struct PagePrefetch {
+    /// 预取距离，以屏为单位。
+    pub(crate) screens: f32,
+    /// 请求超时时间。
+    pub(crate) timeout: Duration,
+    /// 正在等待的请求，记录是否请求下一页以及请求时间。
+    pending: Option<(bool, Instant)>,
+    /// 向前和向后是否已经没有更多页数据。
+    exhausted: (bool, bool),
 }
 
-impl BlinkState {
-    pub fn new() -> BlinkState {
-        BlinkState {
-            on: false,
-            next: BlinkDegree::Normal,
-            focus_boarder_color: HIGHLIGHT_RECT_COLOR,
-            focus_boarder_contrast_color: HIGHLIGHT_RECT_CONTRAST_COLOR,
-            focus_boarder_width: 2,
-            focus_background_color: HIGHLIGHT_BACKGROUND_COLOR
+impl Default for PagePrefetch {
+    fn default() -> Self {
+        Self { screens: DEFAULT_PREFETCH_SCREENS, timeout: PAGE_REQUEST_TIMEOUT, pending: None, exhausted: (false, false) }
+    }
+}
+
+impl PagePrefetch {
+    /// 检查滚动位置是否进入预取距离，返回应请求的方向，`true`表示下一页，`false`表示上一页。
+    ///
+    /// # Arguments
+    ///
+    /// * `scroll_y`: 当前滚动高度。
+    /// * `max_y`: 最大可滚动高度。
+    /// * `view_height`: 可视区域高度。
+    ///
+    /// returns: Option<bool>
+    pub(crate) fn near_edge(&self, scroll_y: i32, max_y: i32, view_height: i32) -> Option<bool> {
+        let distance = (self.screens * view_height as f32) as i32;
+        let (to_top, to_bottom) = (scroll_y, max_y - scroll_y);
+        if to_top >= distance && to_bottom >= distance {
+            None
+        } else {
+            Some(to_bottom < to_top)
         }
     }
 
-    pub fn off(&mut self) {
-        self.on = false;
-        // self.next = BlinkDegree::Normal;
+    /// 开始一次分页请求。已有未超时的请求，或者该方向已经没有更多页数据时返回`false`，调用方不应再发出请求。
+    pub(crate) fn begin(&mut self, next: bool, now: Instant) -> bool {
+        if self.pending(now).is_some() || self.is_exhausted(next) {
+            return false;
+        }
+        self.pending = Some((next, now));
+        true
     }
 
-    pub fn on(&mut self) {
-        self.on = true;
+    /// 页数据已到达，结束等待。
+    pub(crate) fn complete(&mut self) {
+        self.pending = None;
     }
 
-    pub fn toggle_when_on(&mut self) -> bool {
-        if self.on {
-            self.next = match self.next {
-                BlinkDegree::Normal => BlinkDegree::Contrast,
-                BlinkDegree::Contrast => BlinkDegree::Normal,
-            };
-            // debug!("切换对比色: {:?}", self.next);
-            true
-        } else {
-            false
+    /// 记录收到的页数据是否为空。向前或向后翻页收到空页时，该方向不再自动请求；跳转后两个方向都重新允许请求。
+    ///
+    /// # Arguments
+    ///
+    /// * `direction`: 页数据对应的请求。
+    /// * `empty`: 页数据是否为空。
+    ///
+    /// returns: ()
+    pub(crate) fn record_page(&mut self, direction: &PageOptions, empty: bool) {
+        match direction {
+            PageOptions::NextPage(_) => self.exhausted.1 = empty,
+            PageOptions::PrevPage(_) => self.exhausted.0 = empty,
+            PageOptions::At(_) => self.reset(),
         }
     }
 
-}
-
-/// 自定义事件。
-pub(crate) struct LocalEvent;
-impl LocalEvent {
+    /// 重新允许两个方向的请求，在数据被整体替换后调用。
+    pub(crate) fn reset(&mut self) {
+        self.exhausted = (false, false);
+    }
 
-    /// 滚动事件。
-    pub const SCROLL_TO: i32 = 100;
+    /// 允许再次向指定方向请求，用于用户在边缘处继续滚动时检查是否有新数据。
+    pub(crate) fn retry(&mut self, next: bool) {
+        if next {
+            self.exhausted.1 = false;
+        } else {
+            self.exhausted.0 = false;
+        }
+    }
 
-    /// 缩放事件。
-    pub const RESIZE: i32 = 101;
+    /// 指定方向是否已经没有更多页数据。
+    pub(crate) fn is_exhausted(&self, next: bool) -> bool {
+        if next { self.exhausted.1 } else { self.exhausted.0 }
+    }
 
-    /// 从rich-display容器外部发起关闭回顾区的事件。
-    pub const DROP_REVIEWER_FROM_EXTERNAL: i32 = 102;
+    /// 正在等待的请求方向和已等待的时间，请求超时后返回空。
+    pub(crate) fn pending(&self, now: Instant) -> Option<(bool, Duration)> {
+        self.pending.map(|(next, at)| (next, now.saturating_duration_since(at))).filter(|(_, waited)| *waited < self.timeout)
+    }
+}
 
-    /// 从rich-display容器外部发起打开回顾区的事件。
-    pub const OPEN_REVIEWER_FROM_EXTERNAL: i32 = 103;
+/// 数据过滤函数载体。
+/// 回顾区依据过滤函数的返回值决定是否显示数据段，返回true表示显示，返回false表示暂时隐藏。
+#[derive(Clone)]
+pub struct DataFilter {
+    /// 过滤函数。
+    filter: Arc<RwLock<Box<dyn FnMut(&UserData) -> bool + Sync + Send + 'static>>>,
 }
 
-/// 矩形结构，元素0/1代表x/y坐标，表示左上角坐标；元素2/3代表w/h宽和高，w/h不为负值。
-#[derive(Debug, Clone, Eq, Hash, PartialEq, Copy)]
-pub(crate) struct Rectangle(i32, i32, i32, i32);
+impl Debug for DataFilter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DataFilter count: {}", Arc::<RwLock<Box<(dyn FnMut(&UserData) -> bool + Sync + Send + 'static)>>>::strong_count(&self.filter))
+    }
+}
 
-impl PartialOrd<Self> for Rectangle {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        if self.1 > other.1 {
-            Some(Ordering::Greater)
-        } else if self.1 < other.1 {
-            Some(Ordering::Less)
-        } else {
-            if self.0 > other.0 {
-                Some(Ordering::Greater)
-            } else if self.0 < other.0 {
-                Some(Ordering::Less)
-            } else {
-                Some(Ordering::Equal)
-            }
-        }
+impl DataFilter {
+    /// 构建新的数据过滤结构体实例。
+    pub fn new(filter: Arc<RwLock<Box<dyn FnMut(&UserData) -> bool + Sync + Send + 'static>>>) -> Self {
+        Self { filter }
+    }
+
+    fn check(&mut self, rd: &RichData) -> bool {
+        let ud: UserData = rd.into();
+        let filter = &mut* self.filter.write();
+        filter(&ud)
     }
 }
 
-impl Ord for Rectangle {
-    fn cmp(&self, other: &Self) -> Ordering {
-        if self.1 > other.1 {
-            Ordering::Greater
-        } else if self.1 < other.1 {
-            Ordering::Less
+/// 回顾区滚动位置信息。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollInfo {
+    /// 当前滚动高度。
+    pub y: i32,
+    /// 可滚动的最大高度，内容不足一屏时为0。
+    pub max_y: i32,
+    /// 当前滚动位置占可滚动高度的比例，取值范围0.0~1.0。
+    pub percent: f64,
+}
+
+impl ScrollInfo {
+    pub fn new(y: i32, max_y: i32) -> Self {
+        let percent = if max_y > 0 {
+            (y as f64 / max_y as f64).clamp(0.0, 1.0)
         } else {
-            if self.0 > other.0 {
-                Ordering::Greater
-            } else if self.0 < other.0 {
-                Ordering::Less
-            } else {
-                Ordering::Equal
-            }
-        }
+            0.0
+        };
+        Self { y, max_y, percent }
     }
 }
 
-impl Rectangle {
-    /// 构建新的矩形结构，并且保证构建出的矩形x/y在左上角，w/h大于等于0.
-    ///
-    /// # Arguments
-    ///
-    /// * `x`: 起始x坐标。
-    /// * `y`: 起始y坐标。
-    /// * `w`: 起始宽度，可以小于零。用于兼容鼠标向任意方向拖拽的场景。
-    /// * `h`: 起始高度，可以小于零。用于兼容鼠标向任意方向拖拽的场景。
-    ///
-    /// returns: Rectangle
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
-        if w < 0 && h < 0 {
-            Rectangle(x + w, y + h, -w, -h)
-        } else if w < 0 {
-            Rectangle(x + w, y, -w, h)
-        } else if h < 0 {
-            Rectangle(x, y + h, w, -h)
-        } else {
-            Rectangle(x, y, w, h)
-        }
+/// 滚动位置变化的回调函数载体。
+#[derive(Clone)]
+pub struct ScrollNotifier {
+    /// 回调函数。
+    notifier: Arc<RwLock<Box<dyn FnMut(ScrollInfo) + Sync + Send + 'static>>>,
+}
+
+impl Debug for ScrollNotifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ScrollNotifier count: {}", Arc::<RwLock<Box<(dyn FnMut(ScrollInfo) + Sync + Send + 'static)>>>::strong_count(&self.notifier))
     }
+}
 
-    /// 构建一个空矩形。
-    pub fn zero() -> Self {
-        Rectangle::new(0, 0, 0, 0)
+impl ScrollNotifier {
+    /// 构建新的滚动回调结构体实例。
+    pub fn new(notifier: Arc<RwLock<Box<dyn FnMut(ScrollInfo) + Sync + Send + 'static>>>) -> Self {
+        Self { notifier }
     }
 
-    pub fn replace(&mut self, x: i32, y: i32, w: i32, h: i32) {
-        self.0 = x;
-        self.1 = y;
-        self.2 = w;
-        self.3 = h;
+    fn notify(&mut self, info: ScrollInfo) {
+        let notify = &mut* self.notifier.write();
+        notify(info);
     }
+}
 
-    pub fn is_below(&self, another: &Self) -> bool {
-        self.1 > another.1 + another.3
+/// 鼠标点击时按下的修饰键。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Modifier {
+    Ctrl,
+    Alt,
+    /// Windows键或macOS上的Command键。
+    Meta,
+    Shift,
+}
+
+/// 修饰键点击事件的信息。
+#[derive(Debug, Clone)]
+pub struct ModifierClickData {
+    /// 按下的修饰键。
+    pub modifier: Modifier,
+    /// 点击位置，窗口坐标系。
+    pub click_point: (i32, i32),
+    /// 点击处的单词。
+    pub word: Option<String>,
+    /// 点击处的网址。
+    pub url: Option<String>,
+    /// 点击处的数据段。
+    pub data: Option<UserData>,
+}
+
+/// 修饰键点击的自定义回调函数载体。
+#[derive(Clone)]
+pub struct ClickCallback {
+    /// 回调函数。
+    notifier: Arc<RwLock<Box<dyn FnMut(ModifierClickData) + Sync + Send + 'static>>>,
+}
+
+impl Debug for ClickCallback {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ClickCallback count: {}", Arc::<RwLock<Box<(dyn FnMut(ModifierClickData) + Sync + Send + 'static)>>>::strong_count(&self.notifier))
     }
+}
 
-    pub fn tup(&self) -> (i32, i32, i32, i32) {
-        (self.0, self.1, self.2, self.3)
+impl ClickCallback {
+    /// 构建新的修饰键点击回调结构体实例。
+    pub fn new<F>(cb: F) -> Self where F: FnMut(ModifierClickData) + Sync + Send + 'static {
+        Self { notifier: Arc::new(RwLock::new(Box::new(cb))) }
     }
 
-    // /// 获得当前矩形左上角和右下角的坐标。
-    // pub(crate) fn corner(&self) -> (ClickPoint, ClickPoint) {
-    //     (ClickPoint::new(self.0, self.1), ClickPoint::new(self.0 + self.2, self.1 + self.3))
-    // }
+    fn notify(&mut self, data: ModifierClickData) {
+        let notify = &mut* self.notifier.write();
+        notify(data);
+    }
+}
 
-    /// 获得当前矩形左上角和右下角的空矩形。
-    pub fn corner_rect(&self) -> (Self, Self) {
-        (ClickPoint::new(self.0, self.1).as_rect(), ClickPoint::new(self.0 + self.2, self.1 + self.3).as_rect())
+/// 按住修饰键点击主视图时执行的动作。
+#[derive(Debug, Clone)]
+pub enum ClickBinding {
+    /// 通过`CallbackData::SendText`回调汇报点击处的单词。
+    SendText,
+    /// 通过`CallbackData::OpenUrl`回调汇报点击处的网址。
+    OpenUrl,
+    /// 执行自定义回调函数。
+    Custom(ClickCallback),
+}
+
+impl ClickBinding {
+    /// 执行绑定的动作，没有可汇报的内容时不执行。
+    pub(crate) fn dispatch(&mut self, notifier: &mut Option<Callback>, data: ModifierClickData) {
+        match self {
+            ClickBinding::SendText => {
+                if let (Some(word), Some(cb)) = (data.word, notifier.as_mut()) {
+                    cb.notify(CallbackData::SendText(word));
+                }
+            }
+            ClickBinding::OpenUrl => {
+                if let (Some(url), Some(cb)) = (data.url, notifier.as_mut()) {
+                    cb.notify(CallbackData::OpenUrl(url));
+                }
+            }
+            ClickBinding::Custom(cb) => {
+                cb.notify(data);
+            }
+        }
     }
+}
 
-    // /// 相对于当前矩形水平向左移动。
-    // ///
-    // /// # Arguments
-    // ///
-    // /// * `offset_x`: 相对位移量。
-    // ///
-    // /// returns: Rectangle 返回新的矩形。
-    // ///
-    // /// # Examples
-    // ///
-    // /// ```
-    // ///
-    // /// ```
-    // pub fn to_left(&self, offset_x: i32) -> Rectangle {
-    //     Rectangle::new(self.0 - offset_x, self.1, self.2, self.3)
-    // }
+/// 闪烁强度状态。
+#[derive(Debug, Clone,Copy, PartialEq, Eq)]
+pub(crate) enum BlinkDegree {
+    /// 正常，原色显示。
+    Normal,
+    /// 对比色或不显示。
+    Contrast,
+}
 
-    // /// 水平向右增加宽度。
-    // ///
-    // /// # Arguments
-    // ///
-    // /// * `add_width`:
-    // ///
-    // /// returns: ()
-    // ///
-    // /// # Examples
-    // ///
-    // /// ```
-    // ///
-    // /// ```
-    // pub fn stretch_to_right(&mut self, add_width: i32) {
-    //     self.2 += add_width;
-    // }
+/// 闪烁速度。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlinkSpeed {
+    /// 慢速闪烁，对应ANSI/CSI/SGR的`5`参数。
+    #[default]
+    Slow,
+    /// 快速闪烁，对应ANSI/CSI/SGR的`6`参数。
+    Rapid,
+}
 
-    /// 水平向左增加宽度。
+/// 光标样式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum CursorStyle {
+    /// 实心方块。
+    Block,
+    /// 下划线。
+    #[default]
+    Underline,
+    /// 竖线。
+    Bar,
+}
+
+impl CursorStyle {
+    /// 解析DECSCUSR序列`CSI n q`的参数。
     ///
     /// # Arguments
     ///
-    /// * `add_width`:
+    /// * `code`: 序列参数，0和1为闪烁方块，2为静止方块，3为闪烁下划线，4为静止下划线，5为闪烁竖线，6为静止竖线。
     ///
-    /// returns: ()
+    /// returns: Option<(CursorStyle, bool)> 返回(光标样式, 是否闪烁)，无法识别的参数返回`None`。
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::CursorStyle;
     ///
+    /// assert_eq!(CursorStyle::from_decscusr(6), Some((CursorStyle::Bar, false)));
     /// ```
-    pub fn stretch_to_left(&mut self, add_width: i32) {
-        self.0 -= add_width;
-        self.2 += add_width;
+    pub fn from_decscusr(code: u8) -> Option<(CursorStyle, bool)> {
+        match code {
+            0 | 1 => Some((CursorStyle::Block, true)),
+            2 => Some((CursorStyle::Block, false)),
+            3 => Some((CursorStyle::Underline, true)),
+            4 => Some((CursorStyle::Underline, false)),
+            5 => Some((CursorStyle::Bar, true)),
+            6 => Some((CursorStyle::Bar, false)),
+            _ => None,
+        }
     }
+}
 
-    /// 检测当前矩形是否与另一个矩形相交。
+/// 文字阴影或描边效果，用于在背景图片或半透明主题上保持浅色文字的可读性。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextShadow {
+    /// 投影，文字先以阴影颜色按照偏移量绘制一次。
+    Shadow {
+        /// 水平偏移量，正值向右。
+        offset_x: i32,
+        /// 垂直偏移量，正值向下。
+        offset_y: i32,
+        /// 阴影颜色。
+        color: Color,
+    },
+    /// 描边，文字先以描边颜色向四周各偏移指定宽度绘制。
+    Outline {
+        /// 描边宽度，像素数。
+        width: i32,
+        /// 描边颜色。
+        color: Color,
+    },
+}
+
+impl TextShadow {
+    /// 阴影或描边颜色。
+    pub fn color(&self) -> Color {
+        match self {
+            TextShadow::Shadow { color, .. } | TextShadow::Outline { color, .. } => *color,
+        }
+    }
+
+    /// 获取绘制阴影时文字相对于原位置的各个偏移量。
+    ///
+    /// returns: Vec<(i32, i32)>
+    pub(crate) fn offsets(&self) -> Vec<(i32, i32)> {
+        match *self {
+            TextShadow::Shadow { offset_x, offset_y, .. } => {
+                if offset_x == 0 && offset_y == 0 { vec![] } else { vec![(offset_x, offset_y)] }
+            }
+            TextShadow::Outline { width, .. } => {
+                let w = width.max(1);
+                let mut offsets = Vec::with_capacity(8);
+                for dy in [-w, 0, w] {
+                    for dx in [-w, 0, w] {
+                        if dx != 0 || dy != 0 {
+                            offsets.push((dx, dy));
+                        }
+                    }
+                }
+                offsets
+            }
+        }
+    }
+
+    /// 以阴影颜色在各个偏移位置绘制文字，绘制后恢复为指定的文字颜色。
     ///
     /// # Arguments
     ///
-    /// * `another`:
+    /// * `text`: 文字。
+    /// * `x`: 文字原位置的x坐标。
+    /// * `y`: 文字原位置的基线y坐标。
+    /// * `text_color`: 文字颜色。
     ///
-    /// returns: bool
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
     ///
     /// ```
-    pub fn intersects(&self, another: &Self) -> bool {
-        self.0 < another.0 + another.2 &&
-        self.0 + self.2 > another.0 &&
-        self.1 < another.1 + another.3 &&
-        self.1 + self.3 > another.1
+    pub(crate) fn draw(&self, text: &str, x: i32, y: i32, text_color: Color) {
+        set_draw_color(transform_color(self.color(), false));
+        for (dx, dy) in self.offsets() {
+            draw_text_n(text, x + dx, y + dy);
+        }
+        set_draw_color(text_color);
     }
+}
 
-    // /// 将矩形限制到可绘制区域内。
-    // ///
-    // /// # Arguments
-    // ///
-    // /// * `panel_width`:
-    // /// * `panel_height`:
-    // ///
-    // /// returns: ()
-    // ///
-    // /// # Examples
-    // ///
-    // /// ```
-    // ///
-    // /// ```
-    // pub fn align(mut self, panel_width: i32, panel_height: i32) -> Self {
-    //     if self.0 < PADDING.left {
-    //         self.0 = PADDING.left;
-    //     }
-    //     if self.1 < PADDING.top {
-    //         self.1 = PADDING.top;
-    //     }
-    //     if self.2 > panel_width - PADDING.right {
-    //         self.2 = panel_width - PADDING.right;
-    //     }
-    //     if self.3 > panel_height - PADDING.bottom {
-    //         self.3 = panel_height - PADDING.bottom;
-    //     }
-    //     self
-    // }
+/// 闪烁定时器的名称。
+pub(crate) const BLINK_TIMER: &str = "blink";
+/// 快速闪烁定时器的名称。
+pub(crate) const RAPID_BLINK_TIMER: &str = "rapid_blink";
+
+/// 可暂停的定时器。
+struct ParkableTimer {
+    name: &'static str,
+    /// 是否已暂停。
+    parked: bool,
+    /// 重新启动定时器。
+    restart: Box<dyn FnMut() + Send + Sync>,
 }
 
-/// 鼠标点击位置的坐标信息和数据片段索引信息。
-#[derive(Debug, Clone, Copy)]
-pub(crate) struct ClickPoint {
-    pub x: i32,
-    pub y: i32,
-    /// 点所在分片在数据段中的索引号。
-    pub p_i: usize,
-    /// 点所在字符在分片中的索引号。
-    pub c_i: usize,
+/// 可暂停的定时器集合。定时器在无事可做时停止轮询，需要时通过`wake`立即重新启动，避免空转。
+#[derive(Default)]
+pub(crate) struct TimerSet {
+    timers: Vec<ParkableTimer>,
 }
 
-impl ClickPoint {
+impl Debug for TimerSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.timers.iter().map(|t| (t.name, t.parked))).finish()
+    }
+}
 
-    pub fn new(x: i32, y: i32) -> ClickPoint {
-        Self {x, y, p_i: 0, c_i: 0}
+impl TimerSet {
+    /// 启动定时器并登记其重新启动的方式。
+    pub(crate) fn start<F>(&mut self, name: &'static str, interval: f64, handler: F) where F: FnMut(app::TimeoutHandle) + Clone + Send + Sync + 'static {
+        app::add_timeout3(interval, handler.clone());
+        self.timers.push(ParkableTimer {
+            name,
+            parked: false,
+            restart: Box::new(move || {
+                app::add_timeout3(interval, handler.clone());
+            }),
+        });
     }
 
-    /// 以一个点构建一个0宽高的举行结构。
-    pub fn as_rect(&self) -> Rectangle {
-        Rectangle::new(self.x, self.y, 0, 0)
+    /// 登记处于暂停状态的定时器，需要时通过`wake`启动。
+    pub(crate) fn register<F>(&mut self, name: &'static str, interval: f64, handler: F) where F: FnMut(app::TimeoutHandle) + Clone + Send + Sync + 'static {
+        self.timers.push(ParkableTimer {
+            name,
+            parked: true,
+            restart: Box::new(move || {
+                app::add_timeout3(interval, handler.clone());
+            }),
+        });
     }
 
-    /// 依据两个点构建新的矩形结构，无需考虑两点之间的相对位置，构建出的矩形x/y始终代表左上角坐标，w/h始终大于等于0。
-    ///
-    /// # Arguments
-    ///
-    /// * `to_point`: 另一个点。
-    ///
-    /// returns: Rectangle
+    /// 标记定时器已暂停，由定时器在停止轮询前调用。
+    pub(crate) fn park(&mut self, name: &str) {
+        if let Some(timer) = self.timers.iter_mut().find(|t| t.name == name) {
+            timer.parked = true;
+        }
+    }
+
+    /// 重新启动已暂停的定时器，未暂停时不做处理。
+    pub(crate) fn wake(&mut self, name: &str) {
+        if let Some(timer) = self.timers.iter_mut().find(|t| t.name == name && t.parked) {
+            timer.parked = false;
+            (timer.restart)();
+        }
+    }
+}
+
+/// 可视区域闪烁开关标记和状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BlinkState {
+    /// 可视区域是否存在闪烁内容。
+    on: bool,
+    /// 应闪烁内容在下一次刷新显示时的强度。
+    next: BlinkDegree,
+
+    /// 可视区域是否存在快速闪烁内容。
+    rapid_on: bool,
+
+    /// 快速闪烁内容在下一次刷新显示时的强度，由独立的快速闪烁定时器切换。
+    rapid_next: BlinkDegree,
+
+    /// 焦点目标的边框颜色。
+    focus_boarder_color: Color,
+
+    /// 焦点目标的边框对比色。
+    focus_boarder_contrast_color: Color,
+
+    /// 焦点目标的边框线条宽度。
+    focus_boarder_width: i32,
+
+    /// 焦点目标的背景颜色。
+    focus_background_color: Color,
+
+    /// 选中文字的背景颜色。
+    selection_color: Color,
+
+    /// 选中文字的前景颜色，为空时保持文字原有颜色。
+    selection_text_color: Option<Color>,
+
+    /// 光标颜色。
+    cursor_color: Color,
+
+    /// 光标样式。
+    cursor_style: CursorStyle,
+
+    /// 光标是否闪烁。
+    cursor_blink: bool,
+
+    /// 光标在下一次刷新显示时的强度，由独立的光标闪烁定时器切换。
+    cursor_next: BlinkDegree,
+
+    /// 自动折行续行标记的颜色，为空时不绘制标记。
+    wrap_marker: Option<Color>,
+}
+
+impl BlinkState {
+    pub fn new() -> BlinkState {
+        BlinkState {
+            on: false,
+            next: BlinkDegree::Normal,
+            rapid_on: false,
+            rapid_next: BlinkDegree::Normal,
+            focus_boarder_color: HIGHLIGHT_RECT_COLOR,
+            focus_boarder_contrast_color: HIGHLIGHT_RECT_CONTRAST_COLOR,
+            focus_boarder_width: 2,
+            focus_background_color: HIGHLIGHT_BACKGROUND_COLOR,
+            selection_color: Color::Selection,
+            selection_text_color: None,
+            cursor_color: WHITE,
+            cursor_style: CursorStyle::Underline,
+            cursor_blink: true,
+            cursor_next: BlinkDegree::Normal,
+            wrap_marker: None,
+        }
+    }
+
+    pub fn off(&mut self) {
+        self.on = false;
+        // self.next = BlinkDegree::Normal;
+    }
+
+    pub fn on(&mut self) {
+        self.on = true;
+    }
+
+    pub fn set_selection_colors(&mut self, bg: Color, fg: Option<Color>) {
+        self.selection_color = bg;
+        self.selection_text_color = fg;
+    }
+
+    pub fn set_wrap_marker(&mut self, color: Option<Color>) {
+        self.wrap_marker = color;
+    }
+
+    pub fn set_cursor_color(&mut self, color: Color) {
+        self.cursor_color = color;
+    }
+
+    /// 光标样式是否要求闪烁。
+    pub fn cursor_blinks(&self) -> bool {
+        self.cursor_blink
+    }
+
+    pub fn set_cursor_style(&mut self, style: CursorStyle, blink: bool) {
+        self.cursor_style = style;
+        self.cursor_blink = blink;
+        self.cursor_next = BlinkDegree::Normal;
+    }
+
+    /// 切换光标的显示强度。不闪烁时恢复为正常显示。
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
-    /// ```
-    pub fn to_rect(&self, to_point: &Self) -> Rectangle {
-        Rectangle::new(self.x, self.y, to_point.x - self.x, to_point.y - self.y)
+    /// * `blinking`: 光标是否应当闪烁。
+    ///
+    /// returns: bool 是否需要刷新显示。
+    pub fn toggle_cursor(&mut self, blinking: bool) -> bool {
+        if blinking && self.cursor_blink {
+            self.cursor_next = match self.cursor_next {
+                BlinkDegree::Normal => BlinkDegree::Contrast,
+                BlinkDegree::Contrast => BlinkDegree::Normal,
+            };
+            true
+        } else if self.cursor_next != BlinkDegree::Normal {
+            self.cursor_next = BlinkDegree::Normal;
+            true
+        } else {
+            false
+        }
     }
 
-    pub fn align(&mut self, panel_width: i32, panel_height: i32, scroll_y: i32) {
-        if self.x < PADDING.left {
-            self.x = PADDING.left;
-            self.p_i = 0;
-            self.c_i = 0;
+    /// 暂停闪烁时将仍处于对比强度的内容和光标恢复为正常显示。
+    ///
+    /// returns: bool 是否需要刷新显示。
+    pub fn settle(&mut self) -> bool {
+        let changed = self.next != BlinkDegree::Normal || self.rapid_next != BlinkDegree::Normal || self.cursor_next != BlinkDegree::Normal;
+        self.reset_phase();
+        changed
+    }
+
+    /// 将内容和光标的闪烁相位恢复为正常显示。
+    pub fn reset_phase(&mut self) {
+        self.next = BlinkDegree::Normal;
+        self.rapid_next = BlinkDegree::Normal;
+        self.cursor_next = BlinkDegree::Normal;
+    }
+
+    pub fn set_rapid(&mut self, on: bool) {
+        self.rapid_on = on;
+    }
+
+    /// 获取指定闪烁速度的内容在下一次刷新显示时的强度。
+    pub fn degree(&self, speed: BlinkSpeed) -> BlinkDegree {
+        match speed {
+            BlinkSpeed::Slow => self.next,
+            BlinkSpeed::Rapid => self.rapid_next,
+        }
+    }
+
+    /// 按照绘制选项切换指定闪烁速度的内容的显示强度，开启减少动态效果时恢复为正常显示。
+    ///
+    /// # Arguments
+    ///
+    /// * `speed`: 闪烁速度。
+    /// * `options`: 绘制选项。
+    ///
+    /// returns: bool 是否需要刷新显示。
+    pub fn advance(&mut self, speed: BlinkSpeed, options: &RenderOptions) -> bool {
+        if options.reduced_motion {
+            return self.settle();
         }
-        if self.y < PADDING.top {
-            self.y = PADDING.top;
+        match speed {
+            BlinkSpeed::Slow => self.toggle_when_on(),
+            BlinkSpeed::Rapid => self.toggle_rapid_when_on(),
         }
-        if self.x > panel_width - PADDING.right {
-            self.x = panel_width - PADDING.right;
+    }
+
+    /// 存在快速闪烁内容时切换其显示强度。
+    ///
+    /// returns: bool 是否需要刷新显示。
+    pub fn toggle_rapid_when_on(&mut self) -> bool {
+        if self.rapid_on {
+            self.rapid_next = match self.rapid_next {
+                BlinkDegree::Normal => BlinkDegree::Contrast,
+                BlinkDegree::Contrast => BlinkDegree::Normal,
+            };
+            true
+        } else {
+            false
         }
-        if self.y > panel_height + scroll_y - PADDING.bottom {
-            self.y = panel_height + scroll_y - PADDING.bottom;
+    }
+
+    pub fn toggle_when_on(&mut self) -> bool {
+        if self.on {
+            self.next = match self.next {
+                BlinkDegree::Normal => BlinkDegree::Contrast,
+                BlinkDegree::Contrast => BlinkDegree::Normal,
+            };
+            // debug!("切换对比色: {:?}", self.next);
+            true
+        } else {
+            false
         }
     }
+
 }
 
-/// 同一行内多个分片之间共享的信息。通过Rc<RefCell<ThroughLine>>进行链接。
+/// 对比度调整结果缓存的容量，超出后清空重新累积。
+const CONTRAST_CACHE_CAPACITY: usize = 1024;
+
+/// 与闪烁无关的绘制选项，包括减少动态效果、文字最低对比度和文字阴影。
 #[derive(Debug, Clone)]
-pub(crate) struct ThroughLine {
-    pub max_h: i32,
-    pub ys: Vec<Weak<RwLock<LinePiece>>>,
-    pub exist_image: bool,
+pub(crate) struct RenderOptions {
+    /// 是否减少动态效果。开启后内容和光标均不闪烁，新数据也不使用淡入效果。
+    reduced_motion: bool,
+
+    /// 文字与背景的最低对比度，以百分之一为单位，0表示不调整文字颜色。
+    min_contrast: u32,
+
+    /// 绘制时视图的背景色，用于计算未设置背景色的文字的对比度。
+    background: Color,
+
+    /// 文字阴影或描边效果，为空时不绘制。
+    text_shadow: Option<TextShadow>,
+
+    /// 按最低对比度调整后的前景色，键为调整前的前景色和背景色。各背景色下的副本共用同一缓存。
+    contrast_cache: Arc<RwLock<HashMap<(u32, u32), Color>>>,
 }
 
-impl Default for ThroughLine {
+impl Default for RenderOptions {
     fn default() -> Self {
-        ThroughLine {
-            max_h: 1,
-            ys: vec![],
-            exist_image: false,
+        Self {
+            reduced_motion: false,
+            min_contrast: 0,
+            background: Color::Black,
+            text_shadow: None,
+            contrast_cache: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 }
 
-impl ThroughLine {
+impl RenderOptions {
+    /// 开启或关闭减少动态效果。
+    ///
+    /// # Arguments
+    ///
+    /// * `on`: 是否开启。
+    ///
+    /// returns: ()
+    pub fn set_reduced_motion(&mut self, on: bool) {
+        self.reduced_motion = on;
+    }
 
-    pub fn new(max_h: i32, exist_image: bool) -> Arc<RwLock<ThroughLine>> {
-        Arc::new(RwLock::new(ThroughLine { max_h, exist_image, ys: vec![] }))
+    /// 是否开启了减少动态效果。
+    pub fn reduced_motion(&self) -> bool {
+        self.reduced_motion
     }
 
-    /// 如果传入的高度大于已记录高度，则替换为传入高度。
+    /// 设置文字与背景的最低对比度，小于等于1时不调整文字颜色。对比度变化时清空调整结果缓存。
     ///
     /// # Arguments
     ///
-    /// * `max_h`:
+    /// * `ratio`: 最低对比度，超过21时按21处理。
     ///
-    /// returns: &mut ThroughLine
+    /// returns: ()
+    pub fn set_minimum_contrast(&mut self, ratio: f32) {
+        let min_contrast = if ratio > 1.0 { (ratio.min(21.0) * 100.0).round() as u32 } else { 0 };
+        if min_contrast != self.min_contrast {
+            self.min_contrast = min_contrast;
+            self.contrast_cache.write().clear();
+        }
+    }
+
+    /// 设置文字阴影或描边效果。
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
+    /// * `shadow`: 阴影或描边效果，为空时不绘制。
     ///
-    /// ```
-    pub fn set_max_h(&mut self, max_h: i32) -> &mut Self {
-        if max_h > self.max_h {
-            self.max_h = max_h;
-        }
-        self
+    /// returns: ()
+    pub fn set_text_shadow(&mut self, shadow: Option<TextShadow>) {
+        self.text_shadow = shadow;
     }
 
-    pub fn add_piece(&mut self, lp: Arc<RwLock<LinePiece>>) -> &mut Self {
-        self.ys.push(Arc::downgrade(&lp));
-        self
+    /// 清空按最低对比度调整后的前景色缓存，各背景色下的副本一并清空。
+    pub fn clear_cache(&self) {
+        self.contrast_cache.write().clear();
     }
 
-    /// 获取前一个分片的链接，或者创建新的链接。
+    /// 获取指定视图背景色下的绘制选项副本。
+    pub fn on_background(&self, background: Color) -> RenderOptions {
+        RenderOptions { background, ..self.clone() }
+    }
+
+    /// 按照最低对比度调整文字的前景色。同一对前景色和背景色只计算一次。
     ///
     /// # Arguments
     ///
-    /// * `x_ref`: 绘制起点的x坐标。
-    /// * `start_x`: 当前分片的绘制起点。
-    /// * `current_line_height`: 当前分片行高，或者图片高度。如果这个高度大于链接中记录的高度，则替换为新的高度。
-    /// * `last_piece`: 前一个分片。
-    /// * `image`: 当前分片是否为图形。
+    /// * `fg`: 前景色。
+    /// * `bg`: 文字背景色，为空时使用视图背景色。
     ///
-    /// returns: Rc<RefCell<ThroughLine>> 若当前分片是所在行的第一个分片则创新的链接，否则返回前一个分片的链接。
+    /// returns: Color
+    pub fn legible(&self, fg: Color, bg: Option<Color>) -> Color {
+        if self.min_contrast == 0 {
+            return fg;
+        }
+        let bg = bg.unwrap_or(self.background);
+        let key = (fg.bits(), bg.bits());
+        if let Some(color) = self.contrast_cache.read().get(&key) {
+            return *color;
+        }
+        let color = ensure_contrast(fg, bg, self.min_contrast as f32 / 100.0);
+        let mut cache = self.contrast_cache.write();
+        if cache.len() >= CONTRAST_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(key, color);
+        color
+    }
+}
+
+/// 空闲节能策略。没有新数据到达且窗口失去焦点达到指定时长后，组件进入空闲状态：暂停内容和光标闪烁的定时器，
+/// 并以较长的防抖间隔合并主视图的刷新；收到新数据或用户输入后立即退出空闲状态并恢复闪烁。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IdlePolicy {
+    /// 进入空闲状态前的等待时长，为空时从不进入空闲状态。
+    pub idle_after: Option<Duration>,
+    /// 空闲状态下主视图刷新的防抖间隔，单位为秒，小于正常的防抖间隔时不生效。
+    pub idle_interval: f64,
+    /// 是否仅在窗口失去焦点时进入空闲状态。对功耗要求严格的应用可设为false，窗口拥有焦点时同样进入空闲状态。
+    pub require_unfocused: bool,
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        Self {
+            idle_after: Some(Duration::from_secs(IDLE_AFTER_SECS)),
+            idle_interval: IDLE_INTERVAL,
+            require_unfocused: true,
+        }
+    }
+}
+
+impl IdlePolicy {
+    /// 从不进入空闲状态的策略。
+    pub fn disabled() -> Self {
+        Self { idle_after: None, ..Self::default() }
+    }
+
+    /// 判断是否应当进入空闲状态。
     ///
-    /// # Examples
+    /// # Arguments
     ///
-    /// ```
+    /// * `quiet`: 距离最近一次新数据或用户输入的时长。
+    /// * `focused`: 窗口是否拥有焦点。
     ///
-    /// ```
-    pub fn create_or_update(x_ref: i32, start_x: i32, current_line_height: i32, last_piece: Arc<RwLock<LinePiece>>, image: bool) -> Arc<RwLock<ThroughLine>> {
-        if start_x == x_ref {
-            ThroughLine::new(current_line_height, image)
-        } else {
-            if image {
-                last_piece.write().through_line.write().exist_image = true;
-            }
-            last_piece.read().through_line.clone()
+    /// returns: bool
+    pub(crate) fn is_idle(&self, quiet: Duration, focused: bool) -> bool {
+        match self.idle_after {
+            Some(after) => quiet >= after && !(self.require_unfocused && focused),
+            None => false,
         }
     }
 }
 
-/// 可视内容在面板容器中的边界空白。
-#[derive(Debug, Clone, Default)]
-pub(crate) struct Padding {
-    pub left: i32,
-    pub top: i32,
-    pub right: i32,
-    pub bottom: i32,
+/// 主题，集中设置主视图和回顾区的配色和默认字体。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// 背景色。
+    pub background_color: Color,
+    /// 默认字体颜色。
+    pub text_color: Color,
+    /// 默认字体。
+    pub text_font: Font,
+    /// 选中文字的背景颜色。
+    pub selection_color: Color,
+    /// 查找焦点的边框颜色。
+    pub search_focus_color: Color,
+    /// 查找焦点闪烁时的边框对比色。
+    pub search_focus_contrast: Color,
+    /// 查找结果的背景颜色。
+    pub search_focus_background: Color,
+    /// 光标颜色。
+    pub cursor_color: Color,
+    /// ANSI 16色调色板。
+    pub ansi_palette: [Color; 16],
+    /// 互动提示信息的背景色。
+    pub tooltip_background: Color,
+    /// 互动提示信息的边框颜色。
+    pub tooltip_border: Color,
+    /// 互动提示信息的文字颜色。
+    pub tooltip_text: Color,
 }
 
-/// 单行文本的渲染参数，通过试算得到。
-/// 一个大段文本在试算过程中，可能被拆分为多个适配当前窗口宽度的单行文本片段，用于简化绘制过程的运算。
-#[derive(Debug, Clone)]
-pub(crate) struct LinePiece {
-    pub line: String,
-    /// 起点x坐标
-    pub x: i32,
-    /// 起点y坐标
-    pub y: i32,
-    /// 分片宽度，小于等于行宽
-    pub w: i32,
-    /// 行高
-    pub h: i32,
-    /// 虚拟行高顶部y坐标
-    pub top_y: i32,
-    /// 额外的行间距。
-    /// 目前默认值为0，未产生实际影响。
-    pub spacing: i32,
-    /// 建议下一个数据分片绘制起点x坐标
-    pub next_x: i32,
-    /// 建议下一个数据分片绘制起点y坐标
-    pub next_y: i32,
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
 
-    /// 字体渲染高度，小于等于行高。
-    pub font_height: i32,
-    /// 当行高与字体渲染高度不一致时，需要通过y轴偏移量矫正渲染文字的位置。
-    pub text_offset: i32,
-    /// 当行高与字体渲染高度不一致时，需要通过y轴偏移量矫正背景色渲染位置。
-    pub bg_offset: i32,
+impl Theme {
+    /// 默认的深色主题，与组件的初始配色一致。
+    pub fn dark() -> Self {
+        Self {
+            background_color: Color::Black,
+            text_color: WHITE,
+            text_font: Font::Helvetica,
+            selection_color: Color::Selection,
+            search_focus_color: HIGHLIGHT_RECT_COLOR,
+            search_focus_contrast: HIGHLIGHT_RECT_CONTRAST_COLOR,
+            search_focus_background: HIGHLIGHT_BACKGROUND_COLOR,
+            cursor_color: WHITE,
+            ansi_palette: DEFAULT_ANSI_PALETTE,
+            tooltip_background: Color::from_rgb(255, 255, 225),
+            tooltip_border: Color::from_rgb(96, 96, 96),
+            tooltip_text: Color::Black,
+        }
+    }
 
-    /// 在同一行内有多个数据分片的情况下， 跟踪行高信息。每次新增行时，第一个分片需要创建新的对象；在同一行其他分片只需要引用第一个分片的对象即可。
-    pub through_line: Arc<RwLock<ThroughLine>>,
+    /// 浅色主题。
+    pub fn light() -> Self {
+        let mut ansi_palette = DEFAULT_ANSI_PALETTE;
+        ansi_palette[7] = Color::from_rgb(96, 96, 96);
+        ansi_palette[15] = Color::from_rgb(0, 0, 0);
+        Self {
+            background_color: Color::from_rgb(250, 250, 250),
+            text_color: Color::from_rgb(32, 32, 32),
+            text_font: Font::Helvetica,
+            selection_color: Color::from_rgb(173, 214, 255),
+            search_focus_color: HIGHLIGHT_RECT_COLOR,
+            search_focus_contrast: HIGHLIGHT_RECT_CONTRAST_COLOR,
+            search_focus_background: Color::from_rgb(255, 235, 130),
+            cursor_color: Color::from_rgb(32, 32, 32),
+            ansi_palette,
+            tooltip_background: Color::from_rgb(255, 255, 225),
+            tooltip_border: Color::from_rgb(96, 96, 96),
+            tooltip_text: Color::Black,
+        }
+    }
 
-    /// 选中文字相对于当前片段的起始到结束位置，位置索引是以`unicode`字符计算的。
-    pub selected_range: Arc<RwLock<Option<(usize, usize)>>>,
+    /// Solarized深色主题。
+    pub fn solarized_dark() -> Self {
+        Self {
+            background_color: Color::from_rgb(0, 43, 54),
+            text_color: Color::from_rgb(131, 148, 150),
+            text_font: Font::Helvetica,
+            selection_color: Color::from_rgb(7, 54, 66),
+            search_focus_color: Color::from_rgb(203, 75, 22),
+            search_focus_contrast: Color::from_rgb(38, 139, 210),
+            search_focus_background: Color::from_rgb(88, 110, 117),
+            cursor_color: Color::from_rgb(147, 161, 161),
+            ansi_palette: [
+                Color::from_rgb(7, 54, 66),
+                Color::from_rgb(220, 50, 47),
+                Color::from_rgb(133, 153, 0),
+                Color::from_rgb(181, 137, 0),
+                Color::from_rgb(38, 139, 210),
+                Color::from_rgb(211, 54, 130),
+                Color::from_rgb(42, 161, 152),
+                Color::from_rgb(238, 232, 213),
+                Color::from_rgb(0, 43, 54),
+                Color::from_rgb(203, 75, 22),
+                Color::from_rgb(88, 110, 117),
+                Color::from_rgb(101, 123, 131),
+                Color::from_rgb(131, 148, 150),
+                Color::from_rgb(108, 113, 196),
+                Color::from_rgb(147, 161, 161),
+                Color::from_rgb(253, 246, 227),
+            ],
+            tooltip_background: Color::from_rgb(7, 54, 66),
+            tooltip_border: Color::from_rgb(88, 110, 117),
+            tooltip_text: Color::from_rgb(147, 161, 161),
+        }
+    }
 
-    pub font: Font,
-    pub font_size: i32,
+    /// 将主题应用到闪烁状态中保存的绘制颜色。
+    pub(crate) fn apply_to_blink_state(&self, state: &mut BlinkState) {
+        state.selection_color = self.selection_color;
+        state.focus_boarder_color = self.search_focus_color;
+        state.focus_boarder_contrast_color = self.search_focus_contrast;
+        state.focus_background_color = self.search_focus_background;
+        state.cursor_color = self.cursor_color;
+    }
 
-    /// 分片所在数据段的边界数据引用。
-    pub rd_bounds: Arc<RwLock<(i32, i32, i32, i32)>>,
+    /// 将主题应用到互动提示信息的配色。
+    pub(crate) fn apply_to_tooltip(&self, state: &mut TooltipState) {
+        state.colors = (self.tooltip_background, self.tooltip_border, self.tooltip_text);
+    }
 }
 
-impl LinePiece {
-    pub fn new(line: String, x: i32, y: i32, w: i32, h: i32, top_y: i32, spacing: i32, next_x: i32, next_y: i32, font_height: i32, font: Font, font_size: i32, through_line: Arc<RwLock<ThroughLine>>, rd_bounds: Arc<RwLock<(i32, i32, i32, i32)>>) -> Arc<RwLock<LinePiece>> {
-        let new_piece = Arc::new(RwLock::new(Self {
-            line,
-            x,
-            y,
-            w,
-            h,
-            top_y,
-            spacing,
-            next_x,
-            next_y,
-            font_height,
-            text_offset: 0,
-            bg_offset: 0,
-            through_line: through_line.clone(),
-            selected_range: Arc::new(RwLock::new(None)),
-            font,
-            font_size,
-            rd_bounds
-        }));
-        through_line.write().add_piece(new_piece.clone());
-        new_piece
-    }
+/// 截图范围。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SnapshotRange {
+    /// 主视图当前显示的画面。
+    #[default]
+    Visible,
+    /// 主缓存中的全部数据内容。
+    All,
+}
 
-    pub fn init_piece(text_size: i32) -> Arc<RwLock<LinePiece>> {
-        let through_line = Arc::new(RwLock::new(Default::default()));
-        let init_piece = Arc::new(RwLock::new(Self {
-            line: "".to_string(),
-            x: PADDING.left,
-            y: PADDING.top,
-            w: 0,
-            h: (text_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32,
-            top_y: PADDING.top,
-            spacing: 0,
-            next_x: PADDING.left,
-            next_y: PADDING.top,
-            font_height: 1,
-            text_offset: 0,
-            bg_offset: 0,
-            through_line: through_line.clone(),
-            selected_range: Arc::new(RwLock::new(None)),
+/// 打印参数。
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// 显示在每页页眉的标题，为空时不显示页眉。
+    pub title: Option<String>,
+    /// 是否在页脚显示页码。
+    pub page_numbers: bool,
+    /// 是否转换为白底黑字，节省墨水。图片保持原样。
+    pub black_on_white: bool,
+    /// 页眉和页脚的字体。
+    pub font: Font,
+    /// 页眉和页脚的字号。
+    pub font_size: i32,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            page_numbers: true,
+            black_on_white: false,
             font: Font::Helvetica,
-            font_size: DEFAULT_FONT_SIZE,
-            rd_bounds: Arc::new(RwLock::new((PADDING.top, PADDING.top + (text_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, PADDING.left, PADDING.left))),
-        }));
-        through_line.write().add_piece(init_piece.clone());
-        init_piece
+            font_size: 10,
+        }
     }
+}
 
-    pub fn select_from(&self, from: usize) {
-        self.selected_range.write().replace((from, self.line.chars().count()));
+impl PrintOptions {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    pub fn select_to(&self, to: usize) {
-        self.selected_range.write().replace((0, to));
+    pub fn set_title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
     }
 
-    pub fn select_range(&self, from: usize, to: usize) {
-        self.selected_range.write().replace((from, to));
+    pub fn set_page_numbers(mut self, page_numbers: bool) -> Self {
+        self.page_numbers = page_numbers;
+        self
     }
 
-    pub fn deselect(&self) {
-        self.selected_range.write().take();
+    pub fn set_black_on_white(mut self, black_on_white: bool) -> Self {
+        self.black_on_white = black_on_white;
+        self
     }
 
-    pub fn select_all(&self) {
-        self.selected_range.write().replace((0, self.line.chars().count()));
+    pub fn set_font(mut self, font: Font, font_size: i32) -> Self {
+        self.font = font;
+        self.font_size = font_size;
+        self
     }
+}
 
-    pub fn copy_selection(&self, selection: &mut String) {
-        if let Some((from, to)) = *self.selected_range.read() {
-            self.line.chars().skip(from).take(max(to, from) - from).for_each(|c| {
-                selection.push(c);
-            });
+/// 按照行的位置对内容分页，分页处尽量不截断行，超过一页高度的行按页高强制截断。
+///
+/// # Arguments
+///
+/// * `rows`: 各行的起始y坐标和高度。
+/// * `content_height`: 内容总高度。
+/// * `page_height`: 每页可容纳的内容高度。
+///
+/// returns: Vec<(i32, i32), Global> 各页内容的起始和截止y坐标。
+pub(crate) fn paginate(rows: &[(i32, i32)], content_height: i32, page_height: i32) -> Vec<(i32, i32)> {
+    let page_height = max(page_height, 1);
+    let mut pages = vec![];
+    let mut start = 0;
+    while start < content_height {
+        let limit = start + page_height;
+        let end = rows.iter()
+            .filter(|(y, h)| *y < limit && y + h > limit)
+            .map(|(y, _)| *y)
+            .min()
+            .filter(|y| *y > start)
+            .unwrap_or(limit);
+        pages.push((start, min(end, content_height)));
+        start = end;
+    }
+    pages
+}
+
+/// 背景图片的显示方式。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BackgroundMode {
+    /// 按原尺寸平铺。
+    #[default]
+    Tile,
+    /// 拉伸填满主视图。
+    Stretch,
+    /// 按原尺寸居中显示。
+    Center,
+}
+
+/// 主视图的背景图片，绘制在数据内容下方。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BackgroundImage {
+    /// 原始图片。
+    image: Option<RgbImage>,
+    /// 显示方式。
+    mode: BackgroundMode,
+    /// 淡化程度，取值范围0.0~1.0。
+    dim: f32,
+    /// 淡化后的图片缓存，以及淡化时使用的背景色和淡化程度。
+    dimmed: Option<(Color, f32, RgbImage)>,
+}
+
+impl BackgroundImage {
+    pub fn new() -> Self {
+        Self {
+            dim: DEFAULT_BACKGROUND_DIM,
+            ..Default::default()
         }
     }
 
-    ///
-    ///
-    /// # Arguments
-    ///
-    /// * `offset_y`:
-    ///
-    /// returns: Rectangle
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn rect(&self, offset_x: i32, offset_y: i32) -> Rectangle {
-        Rectangle::new(self.x + offset_x, self.y + offset_y, self.w, self.h)
+    pub fn set_image(&mut self, image: Option<RgbImage>, mode: BackgroundMode) {
+        self.image = image;
+        self.mode = mode;
+        self.dimmed = None;
     }
 
-    /// 获取当前片段右侧的虚拟光标，虚拟光标是一个零宽度的片段。
-    pub fn get_cursor(&self) -> LinePiece {
-        // 为光标信息附加文本末尾的字符，用于辅助检测换行逻辑，控制行高。
-        let line = if let Some(c) = self.line.chars().last() {
-            c.to_string()
-        } else {
-            String::new()
-        };
-        Self {
-            line,
-            x: self.next_x,
-            y: self.next_y,
-            w: 0,
-            h: self.h,
-            top_y: if self.next_y > self.y { self.next_y } else { self.top_y },
-            spacing: 0,
-            next_x: self.next_x,
-            next_y: self.next_y,
-            font_height: self.font_height,
-            text_offset: 0,
-            bg_offset: 0,
-            through_line: self.through_line.clone(),
-            selected_range: Arc::new(RwLock::new(None)),
-            font: self.font,
-            font_size: self.font_size,
-            rd_bounds: Arc::new(RwLock::new((self.next_y, self.next_y + self.h, self.next_x, self.next_x))),
-        }
+    pub fn set_dim(&mut self, dim: f32) {
+        self.dim = dim.clamp(0.0, 1.0);
+        self.dimmed = None;
     }
 
-    // /// 相对移动虚拟光标。
-    // ///
-    // /// # Arguments
-    // ///
-    // /// * `offset_x`: 水平移动。
-    // /// * `offset_y`: 垂直移动。
-    // ///
-    // /// returns: ()
-    // ///
-    // /// # Examples
-    // ///
-    // /// ```
-    // ///
-    // /// ```
-    // pub fn move_cursor(&mut self, offset_x: i32, offset_y: i32) {
-    //     self.x += offset_x;
-    //     self.y += offset_y;
-    //     self.next_x += offset_x;
-    //     self.next_y += offset_y;
-    // }
+    /// 丢弃已淡化的图片缓存，下次绘制时重新生成。
+    pub fn clear_cache(&mut self) {
+        self.dimmed = None;
+    }
 
-    /// 绝对移动虚拟光标。
+    pub fn has_image(&self) -> bool {
+        self.image.is_some()
+    }
+
+    /// 在当前绘制上下文中绘制背景图片，需事先填充背景色。
     ///
     /// # Arguments
     ///
-    /// * `x`: 水平移动到x位置。
-    /// * `y`: 垂直移动到y位置。
+    /// * `w`: 绘制区域宽度。
+    /// * `h`: 绘制区域高度。
+    /// * `bg_color`: 背景色，图片向该颜色淡化。
     ///
     /// returns: ()
     ///
@@ -1137,1373 +2831,1377 @@ impl LinePiece {
     /// ```
     ///
     /// ```
-    pub fn move_cursor_to(&mut self, x: i32, y: i32) {
-        self.x = x;
-        self.y = y;
-        self.next_x = x;
-        self.next_y = y;
+    pub fn draw(&mut self, w: i32, h: i32, bg_color: Color) {
+        let Some(image) = &self.image else {
+            return;
+        };
+        let cached = matches!(&self.dimmed, Some((c, d, _)) if *c == bg_color && *d == self.dim);
+        if !cached {
+            let mut data = image.to_rgb_data();
+            dim_image_data(&mut data, image.depth(), bg_color, self.dim);
+            match RgbImage::new(&data, image.data_w(), image.data_h(), image.depth()) {
+                Ok(img) => {
+                    self.dimmed = Some((bg_color, self.dim, img));
+                }
+                Err(e) => {
+                    error!("create background image error: {:?}", e);
+                    return;
+                }
+            }
+        }
+
+        if let Some((_, _, img)) = self.dimmed.as_mut() {
+            let (iw, ih) = (img.data_w(), img.data_h());
+            if iw <= 0 || ih <= 0 {
+                return;
+            }
+            match self.mode {
+                BackgroundMode::Tile => {
+                    let mut y = 0;
+                    while y < h {
+                        let mut x = 0;
+                        while x < w {
+                            img.draw(x, y, iw, ih);
+                            x += iw;
+                        }
+                        y += ih;
+                    }
+                }
+                BackgroundMode::Stretch => {
+                    img.scale(w, h, false, true);
+                    img.draw(0, 0, w, h);
+                }
+                BackgroundMode::Center => {
+                    img.draw((w - iw) / 2, (h - ih) / 2, iw, ih);
+                }
+            }
+        }
     }
+}
 
-    pub fn calc_offset(&mut self) {
-        (self.text_offset, _) = calc_v_center_offset(self.h, self.font_size);
-        (self.bg_offset, _) = calc_v_center_offset(self.h, self.font_height);
-        // debug!("text_offset: {}, bg_offset: {}, h: {}, font_size: {}, font_height: {}, line: {}", self.text_offset, self.bg_offset, self.h, self.font_size, self.font_height, self.line);
+/// 将图片数据的颜色通道按比例向背景色混合，透明通道保持不变。
+///
+/// # Arguments
+///
+/// * `data`: 图片数据。
+/// * `depth`: 颜色深度。
+/// * `bg_color`: 背景色。
+/// * `dim`: 背景色所占比例，取值范围0.0~1.0。
+///
+/// returns: ()
+pub(crate) fn dim_image_data(data: &mut [u8], depth: ColorDepth, bg_color: Color, dim: f32) {
+    if dim <= 0.0 {
+        return;
+    }
+    let dim = dim.min(1.0);
+    let (r, g, b) = bg_color.to_rgb();
+    let (bg, color_channels): ([u8; 3], usize) = match depth {
+        ColorDepth::L8 | ColorDepth::La8 => {
+            let gray = ((r as u32 + g as u32 + b as u32) / 3) as u8;
+            ([gray, gray, gray], 1)
+        }
+        ColorDepth::Rgb8 | ColorDepth::Rgba8 => ([r, g, b], 3),
+    };
+    let channels = depth as usize;
+    for pixel in data.chunks_mut(channels) {
+        for (c, bg_c) in pixel.iter_mut().take(color_channels).zip(bg.iter()) {
+            *c = (*c as f32 * (1.0 - dim) + *bg_c as f32 * dim).round() as u8;
+        }
+    }
+}
 
-        #[cfg(target_os = "windows")]
-        {
-            self.text_offset = (self.text_offset as f32  / 2.0f32).floor() as i32;
+/// 切换主题时更新已缓存数据段的颜色和字体：沿用旧默认颜色的数据段改用新默认颜色，设置了颜色序号的数据段按照新调色板着色，
+/// 使用旧默认字体及其粗体、斜体的数据段改用新默认字体的对应字体。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `old`: 旧的默认字体颜色、背景色和字体。
+/// * `theme`: 新主题。
+///
+/// returns: bool 是否有数据段的字体发生变化，字体变化后需要重新计算绘制位置。
+pub(crate) fn retheme_data(buffer: &mut [RichData], old: (Color, Color, Font), theme: &Theme) -> bool {
+    let (old_text_color, old_bg_color, old_font) = old;
+    let mut font_changed = false;
+    for rd in buffer.iter_mut() {
+        if rd.fg_color == old_text_color {
+            rd.fg_color = theme.text_color;
+        }
+        if rd.bg_color == Some(old_bg_color) {
+            rd.bg_color.replace(theme.background_color);
+        }
+        rd.apply_palette(&theme.ansi_palette);
+        if old_font != theme.text_font {
+            let font = if rd.font == old_font {
+                Some(theme.text_font)
+            } else if rd.font == italic_font(old_font) {
+                Some(italic_font(theme.text_font))
+            } else if rd.font == bold_font(old_font) {
+                Some(bold_font(theme.text_font))
+            } else {
+                None
+            };
+            if let Some(font) = font {
+                rd.font = font;
+                font_changed = true;
+            }
         }
     }
+    font_changed
 }
 
-pub(crate) trait LinedData {
-    /// 设置绘制区域顶部和底部边界y坐标，以及起始x坐标。
-    ///
-    /// # Arguments
-    ///
-    /// * `start_point`:
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn set_v_bounds(&mut self, top_y: i32, bottom_y: i32, start_x: i32, end_x: i32);
+/// 使用FNV-1a算法的固定散列器。整数一律按小端字节序写入，同一输入在不同Rust版本和平台上得到相同的散列值，
+/// 适合计算需要持久保存或跨进程比较的内容哈希。
+pub(crate) struct StableHasher(u64);
 
-    /// 表明是否纯文本数据段。
-    fn is_text_data(&self) -> bool;
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
 
-    /// 是否可点击互动。
-    fn clickable(&self) -> bool;
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
 
-    /// 设置可点击互动。
-    fn set_clickable(&mut self, clickable: bool);
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.0 ^= *b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
 
-    /// 是否已失效
-    fn is_expired(&self) -> bool;
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
 
-    /// 设置失效状态。
-    ///
-    /// # Arguments
-    ///
-    /// * `expire`: 指定是否失效。
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn set_expire(&mut self, expire: bool);
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
 
-    /// 设置文本数据。
-    ///
-    /// # Arguments
-    ///
-    /// * `text_data`:
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn set_text_data(&mut self, text_data: &str);
-
-    /// 设置二
-    ///
-    /// # Arguments
-    ///
-    /// * `binary_data`:
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn set_binary_data(&mut self, binary_data: Vec<u8>);
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
 
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
 
-    /// 检测是否位于可视窗口范围内。
-    ///
-    /// # Arguments
-    ///
-    /// * `top_y`: 面板顶部y轴偏移量。
-    /// * `bottom_y`: 面板底部y轴偏移量。
-    ///
-    /// returns: bool
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn is_visible(&self, top_y: i32, bottom_y: i32) -> bool;
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
 
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
 
-    /// 绘制内容。
-    ///
-    /// # Arguments
-    ///
-    /// * `offset_y`: 面板相对于数据的y轴偏移量。
-    /// * `blink_state`: 面板范围内的闪烁状态。
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn draw(&self, offset_y: i32, blink_state: &BlinkState);
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
 
-    /// 试算当前内容绘制后所占高度信息。
-    /// 试算功能自动处理文本超宽时截断换行的逻辑。
-    ///
-    /// # Arguments
-    ///
-    /// * `last_piece`: 前一个数据片段，用于计算当前数据段的绘制坐标。每个数据段和数据片段都是按照缓存数据的顺序依次计算得到。
-    /// * `max_width`: 可视区域最大宽度，不含padding宽度。
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    fn estimate(&mut self, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, basic_char: char) -> Arc<RwLock<LinePiece>>;
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
 
-}
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
 
-/// 数据段类型，当前支持文本和图片两种。
-#[derive(Clone, Debug, PartialEq, Serialize)]
-pub enum DataType {
-    Text,
-    Image,
+    fn write_isize(&mut self, i: isize) {
+        self.write_u64(i as i64 as u64);
+    }
 }
 
-#[derive(Clone, Debug, Default, Serialize)]
-pub struct ActionItem {
-    pub desc: String,
-    pub cmd: String,
+/// 换行试算缓存的统计信息。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WrapCacheStats {
+    /// 命中次数。
+    pub hits: u64,
+    /// 未命中次数。
+    pub misses: u64,
+    /// 当前缓存条目数。
+    pub len: usize,
+    /// 缓存容量。
+    pub capacity: usize,
 }
 
-impl ActionItem {
-    pub fn new(desc: &str, cmd: &str) -> Self {
-        Self {
-            desc: desc.to_string(),
-            cmd: cmd.to_string(),
+impl WrapCacheStats {
+    /// 命中率，尚未查询过缓存时返回0。
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
         }
     }
 }
 
-/// 互动行为定义。
-#[derive(Clone, Debug, Default, Serialize)]
-pub struct Action {
-    /// 互动操作提示信息，当鼠标指向时会弹出该提示，类似于`HTML`标签的`title`属性。
-    pub title: String,
-    /// 互动操作具体类型，由上层应用定义其具体含义。
-    pub kind: u8,
-    /// 互动操作的动作列表，当鼠标点击时弹出该列表，列表中每个元素的格式为(动作描述，动作指令)。
-    /// 弹出列表中可见的是动作描述，当用户选择某项动作时将反馈动作指令给上层应用控制器。
-    pub items: Vec<ActionItem>,
-    /// 用户选择的动作指令。
-    pub active: Option<String>,
-    /// 动作所属类别名称。
-    pub category: Option<String>,
+/// 单个类别的数据到达统计。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CategoryStats {
+    /// 到达的数据段数量。
+    pub segments: u64,
+    /// 到达的文本字节数。
+    pub bytes: u64,
 }
 
-/// 用户提供的数据段结构。。
-#[derive(Clone, Debug)]
-pub struct UserData {
-    /// 数据ID，在初始化新实例时可随意赋值。当源自RichData时，为RichData的ID值。
-    pub id: i64,
-    pub text: String,
-    pub font: Font,
-    pub font_size: i32,
-    pub fg_color: Color,
-    pub bg_color: Option<Color>,
-    pub underline: bool,
-    /// 前景色序号，从1到8对应ANSI/CSI/SGR的黑、红、绿、黄、蓝、品红、青、白的颜色序列。
-    pub fg_color_index: u8,
-    /// 背景色序号，从1到8对应ANSI/CSI/SGR的黑、红、绿、黄、蓝、品红、青、白的颜色序列。
-    pub bg_color_index: u8,
-    /// 显示效果是否加强，对应与ANSI/CSI的`0`和`1`参数。
-    pub strong: bool,
-    /// 文字大小编号，从1到7对应MXP协议中的SMALL、H6、H5、H4、H3、H2、H1。
-    pub font_size_index: u8,
-    pub clickable: bool,
-    pub expired: bool,
-    pub blink: bool,
-    pub disabled: bool,
-    pub strike_through: bool,
-    pub data_type: DataType,
-    pub image: Option<RgbImage>,
-    /// 原始宽度
-    pub image_width: i32,
-    /// 原始高度
-    pub image_height: i32,
-    /// 希望绘制的目标宽度
-    pub image_target_width: i32,
-    /// 希望绘制的目标高度
-    pub image_target_height: i32,
-    /// 图片来源地址
-    pub image_src_url: Option<String>,
-    /// 图片文件临时保存路径。
-    pub image_file_path: Option<PathBuf>,
-    pub(crate) custom_font_text: bool,
-    pub custom_font_color: bool,
-    /// 互动属性。
-    pub action: Option<Action>,
+/// 换行试算缓存的键。保存完整文本，不同文本不会因散列冲突而误用彼此的结果。
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct WrapKey {
+    text: Arc<str>,
+    font: i32,
+    font_size: i32,
+    /// 可用宽度，为空时表示不限宽度的整体测量。
+    width: Option<i32>,
 }
 
-impl Serialize for UserData {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
-        let mut state = serializer.serialize_struct("UserData", 26).unwrap();
-        state.serialize_field("id", &self.id).unwrap();
-        state.serialize_field("text", &self.text).unwrap();
-        state.serialize_field("font", &format!("{}({})", &self.font.get_name(), &self.font.bits())).unwrap();
-        state.serialize_field("font_size", &self.font_size).unwrap();
-        state.serialize_field("fg_color", &self.fg_color.to_hex_str()).unwrap();
-        state.serialize_field("bg_color", &self.bg_color.map(|c| c.to_hex_str())).unwrap();
-        state.serialize_field("underline", &self.underline).unwrap();
-        state.serialize_field("fg_color_index", &self.fg_color_index).unwrap();
-        state.serialize_field("bg_color_index", &self.bg_color_index).unwrap();
-        state.serialize_field("strong", &self.strong).unwrap();
-        state.serialize_field("font_size_index", &self.font_size_index).unwrap();
-        state.serialize_field("clickable", &self.clickable).unwrap();
-        state.serialize_field("expired", &self.expired).unwrap();
-        state.serialize_field("blink", &self.blink).unwrap();
-        state.serialize_field("disabled", &self.disabled).unwrap();
-        state.serialize_field("strike_through", &self.strike_through).unwrap();
-        state.serialize_field("data_type", &self.data_type).unwrap();
-        state.serialize_field("image", &self.image.as_ref().map(|_| "image")).unwrap();
-        state.serialize_field("image_width", &self.image_width).unwrap();
-        state.serialize_field("image_height", &self.image_height).unwrap();
-        state.serialize_field("image_target_width", &self.image_target_width).unwrap();
-        state.serialize_field("image_target_height", &self.image_target_height).unwrap();
-        state.serialize_field("image_src_url", &self.image_src_url).unwrap();
-        state.serialize_field("image_file_path", &self.image_file_path).unwrap();
-        state.serialize_field("custom_font_text", &self.custom_font_text).unwrap();
-        state.serialize_field("custom_font_color", &self.custom_font_color).unwrap();
-        state.serialize_field("action", &self.action.as_ref().map(|a| a)).unwrap();
-        state.end()
+impl WrapKey {
+    pub fn new(text: &str, font: Font, font_size: i32, width: Option<i32>) -> Self {
+        Self { text: Arc::from(text), font: font.bits(), font_size, width }
     }
 }
 
-impl From<&RichData> for UserData {
-    fn from(data: &RichData) -> Self {
+/// 换行试算缓存的值。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WrapValue {
+    /// 整体测量得到的宽度和高度。
+    Measure(i32, i32),
+    /// 在可用宽度内能够容纳的字符数及其宽度，为空时表示需要从下一行行首开始。
+    Break(Option<(usize, i32)>),
+}
+
+/// 按照最近最少使用原则淘汰条目的换行试算缓存，每个组件实例拥有独立的缓存。
+pub(crate) struct WrapCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<WrapKey, (WrapValue, u64)>,
+    /// 按照最近使用时刻排序的键。
+    order: BTreeMap<u64, WrapKey>,
+    hits: u64,
+    misses: u64,
+}
+
+impl WrapCache {
+    pub fn new(capacity: usize) -> Self {
         Self {
-            id: data.id,
-            text: data.text.clone(),
-            font: data.font,
-            font_size: data.font_size,
-            fg_color: data.fg_color,
-            bg_color: data.bg_color.clone(),
-            underline: data.underline,
-            fg_color_index: 0,
-            bg_color_index: 0,
-            strong: false,
-            font_size_index: 0,
-            clickable: data.clickable,
-            expired: data.expired,
-            blink: data.blink,
-            disabled: data.disabled,
-            strike_through: data.strike_through,
-            data_type: data.data_type.clone(),
-            image: None,
-            image_width: data.image_width,
-            image_height: data.image_height,
-            image_target_width: data.image_target_width,
-            image_target_height: data.image_target_height,
-            image_src_url: data.image_src_url.clone(),
-            image_file_path: data.image_file_path.clone(),
-            custom_font_text: false,
-            custom_font_color: false,
-            action: data.action.clone(),
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
         }
     }
-}
 
-impl UserData {
-    pub fn new_text(text: String) -> Self {
-        Self {
-            id: YitIdHelper::next_id(),
-            text,
-            font: Font::Helvetica,
-            font_size: DEFAULT_FONT_SIZE,
-            fg_color: Color::White,
-            bg_color: None,
-            underline: false,
-            fg_color_index: 0,
-            bg_color_index: 0,
-            strong: false,
-            font_size_index: 0,
-            clickable: false,
-            expired: false,
-            blink: false,
-            disabled: false,
-            strike_through: false,
-            data_type: DataType::Text,
-            image: None,
-            image_width: 0,
-            image_height: 0,
-            image_target_width: 0,
-            image_target_height: 0,
-            image_src_url: None,
-            image_file_path: None,
-            custom_font_text: false,
-            custom_font_color: false,
-            action: None,
+    pub fn get(&mut self, key: &WrapKey) -> Option<WrapValue> {
+        if self.capacity == 0 {
+            return None;
+        }
+        self.tick += 1;
+        if let Some((value, last_used)) = self.entries.get_mut(key) {
+            self.order.remove(last_used);
+            *last_used = self.tick;
+            self.order.insert(self.tick, key.clone());
+            self.hits += 1;
+            Some(*value)
+        } else {
+            self.misses += 1;
+            None
         }
     }
 
-    pub fn new_text_with_id(id: i64, text: String) -> Self {
-        Self {
-            id,
-            text,
-            font: Font::Helvetica,
-            font_size: DEFAULT_FONT_SIZE,
-            fg_color: Color::White,
-            bg_color: None,
-            underline: false,
-            fg_color_index: 0,
-            bg_color_index: 0,
-            strong: false,
-            font_size_index: 0,
-            clickable: false,
-            expired: false,
-            blink: false,
-            disabled: false,
-            strike_through: false,
-            data_type: DataType::Text,
-            image: None,
-            image_width: 0,
-            image_height: 0,
-            image_target_width: 0,
-            image_target_height: 0,
-            image_src_url: None,
-            image_file_path: None,
-            custom_font_text: false,
-            custom_font_color: false,
-            action: None,
+    pub fn insert(&mut self, key: WrapKey, value: WrapValue) {
+        if self.capacity == 0 {
+            return;
         }
-    }
-
-    /// 创建新的图形类型的数据段。
-    /// 如果传入的图形源自`SvgImage`，则必须在调用本方法之前首先执行`SvgImage::normalize()`方法进行初始化。
-    ///
-    /// # Arguments
-    ///
-    /// * `image`: RGB图像对象。
-    /// * `original_width`: 原始宽度。
-    /// * `original_height`: 原始高度。
-    /// * `target_width`: 目标宽度，可能与原始宽度不同。
-    /// * `target_height`: 目标高度，可能与原始高度不同。
-    /// * `src`: 图像来源地址。
-    ///
-    /// returns: UserData
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use fltk::image::{SvgImage};
-    /// use fltk::prelude::ImageExt;
-    /// use fltkrs_richdisplay::UserData;
-    ///
-    /// let mut svg = SvgImage::load("res/test.svg").unwrap();
-    /// svg.normalize();
-    /// let image = svg.to_rgb().unwrap();
-    /// let _data = UserData::new_image(image, 100, 100, 100, 100, Some("res/test.svg".to_string()));
-    /// ```
-    pub fn new_image(image: RgbImage, origin_width: i32, origin_height: i32, target_width: i32, target_height: i32, src: Option<String>) -> Self {
-        Self {
-            id: YitIdHelper::next_id(),
-            text: String::new(),
-            font: Font::Helvetica,
-            font_size: DEFAULT_FONT_SIZE,
-            fg_color: Color::White,
-            bg_color: None,
-            underline: false,
-            fg_color_index: 0,
-            bg_color_index: 0,
-            strong: false,
-            font_size_index: 0,
-            clickable: false,
-            expired: false,
-            blink: false,
-            disabled: false,
-            strike_through: false,
-            data_type: DataType::Image,
-            image: Some(image),
-            image_width: origin_width,
-            image_height: origin_height,
-            image_target_width: target_width,
-            image_target_height: target_height,
-            image_src_url: src,
-            image_file_path: None,
-            custom_font_text: false,
-            custom_font_color: false,
-            action: None,
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&last_used);
         }
+        self.order.insert(self.tick, key);
+        self.shrink();
     }
 
-    pub fn set_font_and_size(mut self, font: Font, size: i32) -> Self {
-        self.font = font;
-        self.font_size = size;
-        self.custom_font_text = true;
-        self
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink();
     }
 
-    /// 设置字体和大小，同时确认自定义字体标记。非流式调用接口。
-    ///
-    /// # Arguments
-    ///
-    /// * `font`:
-    /// * `size`:
-    ///
-    /// returns: ()
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn set_font_and_size2(&mut self, font: Font, size: i32) {
-        self.font = font;
-        self.font_size = size;
-        self.custom_font_text = true;
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.hits = 0;
+        self.misses = 0;
     }
 
-    pub fn set_font_size_index(mut self, index: u8) -> Self {
-        self.font_size_index = index;
-        self
+    pub fn stats(&self) -> WrapCacheStats {
+        WrapCacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            len: self.entries.len(),
+            capacity: self.capacity,
+        }
     }
 
-    pub fn set_fg_color(mut self, fg_color: Color) -> Self {
-        self.fg_color = fg_color;
-        self.custom_font_color = true;
-        self
+    /// 淘汰最久未使用的条目，直到条目数不超过容量。
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity {
+            if let Some((_, key)) = self.order.pop_first() {
+                self.entries.remove(&key);
+            } else {
+                break;
+            }
+        }
     }
+}
 
-    pub fn set_fg_color_index(mut self, index: u8) -> Self {
-        self.fg_color_index = index;
-        self
+impl Debug for WrapCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WrapCache").field("capacity", &self.capacity).field("len", &self.entries.len()).finish()
     }
+}
 
-    pub fn set_bg_color(mut self, bg_color: Option<Color>) -> Self {
-        self.bg_color = bg_color;
-        self
-    }
+/// 按照最近最少出现原则淘汰条目的网址集合，用于记录已请求过预览信息的网址。
+#[derive(Debug)]
+pub(crate) struct RecentUrls {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, u64>,
+    /// 按照最近出现时刻排序的网址。
+    order: BTreeMap<u64, String>,
+}
 
-    pub fn set_bg_color_index(mut self, index: u8) -> Self {
-        self.bg_color_index = index;
-        self
+impl RecentUrls {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
     }
 
-    pub fn set_strong(mut self, strong: bool) -> Self {
-        self.strong = strong;
-        self
+    /// 记录网址的出现，返回网址此前是否已被记录。
+    pub fn touch(&mut self, url: &str) -> bool {
+        self.tick += 1;
+        if let Some(last_seen) = self.entries.get_mut(url) {
+            self.order.remove(last_seen);
+            *last_seen = self.tick;
+            self.order.insert(self.tick, url.to_string());
+            true
+        } else {
+            self.entries.insert(url.to_string(), self.tick);
+            self.order.insert(self.tick, url.to_string());
+            while self.entries.len() > self.capacity {
+                if let Some((_, oldest)) = self.order.pop_first() {
+                    self.entries.remove(&oldest);
+                } else {
+                    break;
+                }
+            }
+            false
+        }
     }
 
-    pub fn set_underline(mut self, u: bool) -> Self {
-        self.underline = u;
-        self
+    pub fn len(&self) -> usize {
+        self.entries.len()
     }
+}
 
-    pub fn set_clickable(mut self, clickable: bool) -> Self {
-        self.clickable = clickable;
-        self
+/// 测量文本在指定字体下的宽度和高度，优先使用缓存结果。调用前需已通过`set_font`设置相同的字体。
+///
+/// # Arguments
+///
+/// * `cache`: 所属组件的换行试算缓存，为空时直接测量。
+/// * `text`: 文本。
+/// * `font`: 字体。
+/// * `font_size`: 字体大小。
+///
+/// returns: (i32, i32)
+pub(crate) fn cached_measure(cache: Option<&RwLock<WrapCache>>, text: &str, font: Font, font_size: i32) -> (i32, i32) {
+    let Some(cache) = cache else {
+        return measure(text, false);
+    };
+    let key = WrapKey::new(text, font, font_size, None);
+    if let Some(WrapValue::Measure(w, h)) = cache.write().get(&key) {
+        return (w, h);
     }
+    let (w, h) = measure(text, false);
+    cache.write().insert(key, WrapValue::Measure(w, h));
+    (w, h)
+}
 
-    pub fn set_blink(mut self, blink: bool) -> Self {
-        self.blink = blink;
-        self
-    }
+/// 自定义事件。
+pub(crate) struct LocalEvent;
+impl LocalEvent {
 
-    pub fn set_disabled(mut self, disabled: bool) -> Self {
-        self.disabled = disabled;
-        self
+    /// 滚动事件。
+    pub const SCROLL_TO: i32 = 100;
+
+    /// 缩放事件。
+    pub const RESIZE: i32 = 101;
+
+    /// 从rich-display容器外部发起关闭回顾区的事件。
+    pub const DROP_REVIEWER_FROM_EXTERNAL: i32 = 102;
+
+    /// 从rich-display容器外部发起打开回顾区的事件。
+    pub const OPEN_REVIEWER_FROM_EXTERNAL: i32 = 103;
+}
+
+/// 矩形结构，元素0/1代表x/y坐标，表示左上角坐标；元素2/3代表w/h宽和高，w/h不为负值。
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Copy)]
+pub(crate) struct Rectangle(i32, i32, i32, i32);
+
+impl PartialOrd<Self> for Rectangle {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self.1 > other.1 {
+            Some(Ordering::Greater)
+        } else if self.1 < other.1 {
+            Some(Ordering::Less)
+        } else {
+            if self.0 > other.0 {
+                Some(Ordering::Greater)
+            } else if self.0 < other.0 {
+                Some(Ordering::Less)
+            } else {
+                Some(Ordering::Equal)
+            }
+        }
     }
+}
 
-    /// 设置数据段互动行为。
-    ///
-    /// # Arguments
-    ///
-    /// * `action`: 互动操作。
-    ///
-    /// returns: UserData
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn set_action(mut self, action: Action) -> Self {
-        self.action = Some(action);
-        self.clickable = true;
-        self.underline = true;
-        self.expired = false;
-        self
+impl Ord for Rectangle {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self.1 > other.1 {
+            Ordering::Greater
+        } else if self.1 < other.1 {
+            Ordering::Less
+        } else {
+            if self.0 > other.0 {
+                Ordering::Greater
+            } else if self.0 < other.0 {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        }
     }
+}
 
-    /// 更改当前数据段的互动行为。
+impl Rectangle {
+    /// 构建新的矩形结构，并且保证构建出的矩形x/y在左上角，w/h大于等于0.
     ///
     /// # Arguments
     ///
-    /// * `action`: 互动操作。
+    /// * `x`: 起始x坐标。
+    /// * `y`: 起始y坐标。
+    /// * `w`: 起始宽度，可以小于零。用于兼容鼠标向任意方向拖拽的场景。
+    /// * `h`: 起始高度，可以小于零。用于兼容鼠标向任意方向拖拽的场景。
     ///
-    /// returns: ()
+    /// returns: Rectangle
     ///
     /// # Examples
     ///
     /// ```
     ///
     /// ```
-    pub fn change_action(&mut self, action: Option<Action>) {
-        if action.is_some() {
-            self.action = action;
-            self.clickable = true;
-            self.underline = true;
-            self.expired = false;
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        if w < 0 && h < 0 {
+            Rectangle(x + w, y + h, -w, -h)
+        } else if w < 0 {
+            Rectangle(x + w, y, -w, h)
+        } else if h < 0 {
+            Rectangle(x, y + h, w, -h)
         } else {
-            self.action = None;
-            self.clickable = false;
-            self.underline = false;
-            self.expired = true;
+            Rectangle(x, y, w, h)
         }
     }
 
-    /// 为图片设置简短的文字描述，居中显示。
-    /// 若文字较长请使用换行符`'\n'`进行换行，避免文字超出图片边界。
-    ///
-    /// # Arguments
-    ///
-    /// * `text`:
-    ///
-    /// returns: UserData
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn set_text(mut self, text: String) -> Self {
-        self.text = text;
-        self
+    /// 构建一个空矩形。
+    pub fn zero() -> Self {
+        Rectangle::new(0, 0, 0, 0)
     }
 
-    pub fn set_image_file_path(mut self, path: Option<PathBuf>) -> Self {
-        self.image_file_path = path;
-        self
+    pub fn replace(&mut self, x: i32, y: i32, w: i32, h: i32) {
+        self.0 = x;
+        self.1 = y;
+        self.2 = w;
+        self.3 = h;
     }
-}
-
-
-/// 计算两个重叠垂线居中对齐后，短线相对于长线的上端和下端的偏移量。
-///
-/// # Arguments
-///
-/// * `line_height`:
-/// * `font_height`:
-///
-/// returns: (i32, i32)
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn calc_v_center_offset(line_height: i32, font_height: i32) -> (i32, i32) {
-    let up = (line_height - font_height) / 2;
-    let down = (line_height + font_height) / 2;
-    (up, down)
-}
 
-/// 检测鼠标是否进入可交互的内容区域中。
-///
-/// # Arguments
-///
-/// * `clickable_data_rc`:
-///
-/// returns: (bool, usize) 返回元组(是否进入, 数据段序号)。
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn mouse_enter(clickable_data_rc: Arc<RwLock<HashMap<Rectangle, usize>>>) -> (bool, usize) {
-    for (area, idx) in clickable_data_rc.read().iter() {
-        let (x, y, w, h) = area.tup();
-        if app::event_inside(x, y, w, h) {
-            return (true, *idx);
-        }
+    pub fn is_below(&self, another: &Self) -> bool {
+        self.1 > another.1 + another.3
     }
-    return (false, 0);
-}
 
-/// 更新数据内容的属性。用于用户互动操作反馈。
-///
-/// # Arguments
-///
-/// * `options`:
-/// * `rd`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn update_data_properties(options: RichDataOptions, rd: &mut RichData) {
-    if let Some(clickable) = options.clickable {
-        rd.clickable = clickable;
-        if !clickable {
-            draw::set_cursor(Cursor::Default);
-        }
-    }
-    if let Some(underline) = options.underline {
-        rd.underline = underline;
+    pub fn tup(&self) -> (i32, i32, i32, i32) {
+        (self.0, self.1, self.2, self.3)
     }
-    if let Some(expired) = options.expired {
-        rd.expired = expired;
+
+    // /// 获得当前矩形左上角和右下角的坐标。
+    // pub(crate) fn corner(&self) -> (ClickPoint, ClickPoint) {
+    //     (ClickPoint::new(self.0, self.1), ClickPoint::new(self.0 + self.2, self.1 + self.3))
+    // }
+
+    /// 获得当前矩形左上角和右下角的空矩形。
+    pub fn corner_rect(&self) -> (Self, Self) {
+        (ClickPoint::new(self.0, self.1).as_rect(), ClickPoint::new(self.0 + self.2, self.1 + self.3).as_rect())
     }
-    if let Some(text) = options.text {
-        rd.text = text;
+
+    // /// 相对于当前矩形水平向左移动。
+    // ///
+    // /// # Arguments
+    // ///
+    // /// * `offset_x`: 相对位移量。
+    // ///
+    // /// returns: Rectangle 返回新的矩形。
+    // ///
+    // /// # Examples
+    // ///
+    // /// ```
+    // ///
+    // /// ```
+    // pub fn to_left(&self, offset_x: i32) -> Rectangle {
+    //     Rectangle::new(self.0 - offset_x, self.1, self.2, self.3)
+    // }
+
+    // /// 水平向右增加宽度。
+    // ///
+    // /// # Arguments
+    // ///
+    // /// * `add_width`:
+    // ///
+    // /// returns: ()
+    // ///
+    // /// # Examples
+    // ///
+    // /// ```
+    // ///
+    // /// ```
+    // pub fn stretch_to_right(&mut self, add_width: i32) {
+    //     self.2 += add_width;
+    // }
+
+    /// 水平向左增加宽度。
+    ///
+    /// # Arguments
+    ///
+    /// * `add_width`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn stretch_to_left(&mut self, add_width: i32) {
+        self.0 -= add_width;
+        self.2 += add_width;
     }
-    if let Some(fg_color) = options.fg_color {
-        rd.fg_color = fg_color;
+
+    /// 检测当前矩形是否与另一个矩形相交。
+    ///
+    /// # Arguments
+    ///
+    /// * `another`:
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn intersects(&self, another: &Self) -> bool {
+        self.0 < another.0 + another.2 &&
+        self.0 + self.2 > another.0 &&
+        self.1 < another.1 + another.3 &&
+        self.1 + self.3 > another.1
     }
-    if let Some(bg_color) = options.bg_color {
-        rd.bg_color = Some(bg_color);
+
+    // /// 将矩形限制到可绘制区域内。
+    // ///
+    // /// # Arguments
+    // ///
+    // /// * `panel_width`:
+    // /// * `panel_height`:
+    // ///
+    // /// returns: ()
+    // ///
+    // /// # Examples
+    // ///
+    // /// ```
+    // ///
+    // /// ```
+    // pub fn align(mut self, panel_width: i32, panel_height: i32) -> Self {
+    //     if self.0 < PADDING.left {
+    //         self.0 = PADDING.left;
+    //     }
+    //     if self.1 < PADDING.top {
+    //         self.1 = PADDING.top;
+    //     }
+    //     if self.2 > panel_width - PADDING.right {
+    //         self.2 = panel_width - PADDING.right;
+    //     }
+    //     if self.3 > panel_height - PADDING.bottom {
+    //         self.3 = panel_height - PADDING.bottom;
+    //     }
+    //     self
+    // }
+}
+
+/// 鼠标点击位置的坐标信息和数据片段索引信息。
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ClickPoint {
+    pub x: i32,
+    pub y: i32,
+    /// 点所在分片在数据段中的索引号。
+    pub p_i: usize,
+    /// 点所在字符在分片中的索引号。
+    pub c_i: usize,
+}
+
+impl ClickPoint {
+
+    pub fn new(x: i32, y: i32) -> ClickPoint {
+        Self {x, y, p_i: 0, c_i: 0}
     }
-    if let Some(strike_through) = options.strike_through {
-        rd.strike_through = strike_through;
+
+    /// 以一个点构建一个0宽高的举行结构。
+    pub fn as_rect(&self) -> Rectangle {
+        Rectangle::new(self.x, self.y, 0, 0)
     }
-    if let Some(blink) = options.blink {
-        rd.blink = blink;
+
+    /// 依据两个点构建新的矩形结构，无需考虑两点之间的相对位置，构建出的矩形x/y始终代表左上角坐标，w/h始终大于等于0。
+    ///
+    /// # Arguments
+    ///
+    /// * `to_point`: 另一个点。
+    ///
+    /// returns: Rectangle
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// ```
+    pub fn to_rect(&self, to_point: &Self) -> Rectangle {
+        Rectangle::new(self.x, self.y, to_point.x - self.x, to_point.y - self.y)
     }
-    if let Some(image) = options.image {
-        if let Some(image_color_depth) = options.image_color_depth {
-            rd.image_color_depth = image_color_depth;
+
+    pub fn align(&mut self, panel_width: i32, panel_height: i32, scroll_y: i32, padding: &Padding) {
+        if self.x < padding.left {
+            self.x = padding.left;
+            self.p_i = 0;
+            self.c_i = 0;
         }
-        if let Some(image_target_width) = options.image_target_width {
-            rd.image_target_width = image_target_width;
+        if self.y < padding.top {
+            self.y = padding.top;
         }
-        if let Some(image_target_height) = options.image_target_height {
-            rd.image_target_height = image_target_height;
+        if self.x > panel_width - padding.right {
+            self.x = panel_width - padding.right;
         }
-        if let Some(image_width) = options.image_width {
-            rd.image_width = image_width;
+        if self.y > panel_height + scroll_y - padding.bottom {
+            self.y = panel_height + scroll_y - padding.bottom;
         }
-        if let Some(image_height) = options.image_height {
-            rd.image_height = image_height;
-        }
-        if rd.image_inactive.is_some() {
-            let inactive = gray_image(&image, rd.image_width, rd.image_height, rd.image_color_depth);
-            rd.image_inactive.replace(inactive);
-        }
-        rd.image.replace(image);
     }
+}
 
-    if let Some(image_file_path) = options.image_file_path {
-        rd.image_file_path.replace(image_file_path);
-    }
-    if let Some(action) = options.action {
-        if action.items.is_empty() {
-            rd.action = None;
-        } else {
-            rd.action.replace(action);
+/// 同一行内多个分片之间共享的信息。通过Rc<RefCell<ThroughLine>>进行链接。
+#[derive(Debug, Clone)]
+pub(crate) struct ThroughLine {
+    pub max_h: i32,
+    pub ys: Vec<Weak<RwLock<LinePiece>>>,
+    pub exist_image: bool,
+}
+
+impl Default for ThroughLine {
+    fn default() -> Self {
+        ThroughLine {
+            max_h: 1,
+            ys: vec![],
+            exist_image: false,
         }
     }
+}
 
-    if let Some(disabled) = options.disabled {
-        rd.disabled = disabled;
+impl ThroughLine {
 
-        if rd.data_type == DataType::Image {
-            if rd.disabled && rd.image_inactive.is_none() {
-                if let Some(ref rgb_data) = rd.image {
-                    rd.image_inactive.replace(gray_image(rgb_data, rd.image_width, rd.image_height, rd.image_color_depth));
-                }
-            }
+    pub fn new(max_h: i32, exist_image: bool) -> Arc<RwLock<ThroughLine>> {
+        Arc::new(RwLock::new(ThroughLine { max_h, exist_image, ys: vec![] }))
+    }
+
+    /// 如果传入的高度大于已记录高度，则替换为传入高度。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_h`:
+    ///
+    /// returns: &mut ThroughLine
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_max_h(&mut self, max_h: i32) -> &mut Self {
+        if max_h > self.max_h {
+            self.max_h = max_h;
         }
+        self
     }
-}
 
-/// 禁用数据内容。
-/// 当前的实现为：图形内容增加灰色遮罩层，文本内容增加删除线。
-///
-/// # Arguments
-///
-/// * `rd`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn disable_data(rd: &mut RichData) {
-    rd.set_clickable(false);
-    draw::set_cursor(Cursor::Default);
+    pub fn add_piece(&mut self, lp: Arc<RwLock<LinePiece>>) -> &mut Self {
+        self.ys.push(Arc::downgrade(&lp));
+        self
+    }
 
-    match rd.data_type {
-        DataType::Image => {
-            if rd.image_inactive.is_none() {
-                if let Some(ref rgb_data) = rd.image {
-                    rd.image_inactive.replace(gray_image(rgb_data, rd.image_target_width, rd.image_target_height, rd.image_color_depth));
-                }
+    /// 获取前一个分片的链接，或者创建新的链接。
+    ///
+    /// # Arguments
+    ///
+    /// * `x_ref`: 绘制起点的x坐标。
+    /// * `start_x`: 当前分片的绘制起点。
+    /// * `current_line_height`: 当前分片行高，或者图片高度。如果这个高度大于链接中记录的高度，则替换为新的高度。
+    /// * `last_piece`: 前一个分片。
+    /// * `image`: 当前分片是否为图形。
+    ///
+    /// returns: Rc<RefCell<ThroughLine>> 若当前分片是所在行的第一个分片则创新的链接，否则返回前一个分片的链接。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn create_or_update(x_ref: i32, start_x: i32, current_line_height: i32, last_piece: Arc<RwLock<LinePiece>>, image: bool) -> Arc<RwLock<ThroughLine>> {
+        if start_x == x_ref {
+            ThroughLine::new(current_line_height, image)
+        } else {
+            if image {
+                last_piece.write().through_line.write().exist_image = true;
             }
-        }
-        DataType::Text => {
-            rd.strike_through = true;
+            last_piece.read().through_line.clone()
         }
     }
 }
 
-/// 从影像中提取`RGB`数据，不会损失alpha通道数据。若传入`None`则返回一个对应大小且色深为`L8`的黑板。
-///
-/// # Arguments
-///
-/// * `rgb_image`: RGB影像。
-/// * `target_width`: 希望的影像宽度，像素数。
-/// * `target_height`: 希望的影像的高度，像素数。
-///
-/// returns: (Option<Vec<u8>>, ColorDepth, i32, i32) 返回(RGB数据, 颜色深度, 影像原宽度, 影像原高度)。
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub fn image_to_rgb_data(rgb_image: &Option<RgbImage>, target_width: i32, target_height: i32) -> (Option<Vec<u8>>, ColorDepth, i32, i32) {
-    if let Some(image) = rgb_image {
-        (Some(image.to_rgb_data()), image.depth(), image.w(), image.h())
-    } else {
-        (Some(vec![0u8; target_width as usize * target_height as usize]), ColorDepth::L8, target_width, target_height)
-    }
+/// 可视内容在面板容器中的边界空白。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Padding {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
 }
 
-/// 依据RGB格式的图片计算其L8格式的灰度数据。
-///
-/// # Arguments
-///
-/// * `origin_rgb_data`: 原本的RGB格式的图片数据。
-///
-/// returns: Option<Vec<u8>> 灰度计算后的图片数据，格式为`L8`或`LA8`。
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub fn gray_image(rgb_data: &Vec<u8>, w: i32, h: i32, depth: ColorDepth) -> Vec<u8> {
-    let pixels = w as usize * h as usize;
-    match depth {
-        // ColorDepth::L8 => {
-        //     // 8位灰度，每个像素占据1个字节。
-        //     let la_data = origin_image.to_rgb_data();
-        //     let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
-        //     for i in 0..pixels {
-        //         let g = (la_data[i] as f32 / 2.0).floor() as u8;
-        //         gray_data.push(g);
-        //     }
-        //     Some(gray_data)
-        // }
-        // ColorDepth::La8 => {
-        //     // 8位灰度带有alpha通道，每个像素占据2个8位字节，每个像素的第二个字节表示alpha通道值。
-        //     let la_data = origin_image.to_rgb_data();
-        //     let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
-        //     for i in 0..pixels {
-        //         let j = i * 2;
-        //         let c = la_data[j];
-        //         let g = (c as f32 / 2.0).floor() as u8;
-        //         gray_data.push(g);
-        //     }
-        //     Some(gray_data)
-        // }
+impl Padding {
+    pub fn new(left: i32, top: i32, right: i32, bottom: i32) -> Self {
+        Self { left: max(left, 0), top: max(top, 0), right: max(right, 0), bottom: max(bottom, 0) }
+    }
 
-        ColorDepth::Rgb8 => {
-            // 8位RGB色，每个像素占据3个8位字节.
-            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
-            for i in 0..pixels {
-                let j = i * 3;
-                let (r, g, b) = (rgb_data[j], rgb_data[j + 1], rgb_data[j + 2]);
-                let gray = (306 * r as u32 + 601 * g as u32 + 116 * b as u32) >> 10;
-                gray_data.push(gray as u8);
-            }
-            gray_data
-        }
-        ColorDepth::Rgba8 => {
-            // 8位RGB色带有alpha通道，每个像素占据4个8位字节.每个像素的第四个字节表示alpha通道值。
-            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels * 2);
-            for i in 0..pixels {
-                let j = i * 4;
-                let (r, g, b) = (rgb_data[j], rgb_data[j + 1], rgb_data[j + 2]);
-                let gray = (306 * r as u32 + 601 * g as u32 + 116 * b as u32) >> 10;
-                gray_data.push(gray as u8);
-                gray_data.push(rgb_data[j + 3]);
-            }
-            gray_data
-        }
-        ColorDepth::L8 => {
-            // 固定生成L8格式的灰色图片数据
-            vec![128u8; pixels]
-        }
-        ColorDepth::La8 => {
-            // 固定生成La8格式的灰色图片数据
-            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels * 2);
-            for i in 0..pixels {
-                gray_data.push(128u8);
-                gray_data.push(rgb_data[i * 2 + 1]);
-            }
-            gray_data
-        }
+    /// 扣除左右空白后的可绘制宽度。
+    pub fn drawable_width(&self, panel_width: i32) -> i32 {
+        panel_width - self.left - self.right
+    }
+
+    /// 扣除上下空白后的可绘制高度。
+    pub fn drawable_height(&self, panel_height: i32) -> i32 {
+        panel_height - self.top - self.bottom
     }
 }
 
-/// 组件内部使用的数据段结构。
+/// 单行文本的渲染参数，通过试算得到。
+/// 一个大段文本在试算过程中，可能被拆分为多个适配当前窗口宽度的单行文本片段，用于简化绘制过程的运算。
 #[derive(Debug, Clone)]
-pub(crate) struct RichData {
-    /// 数据ID。
-    pub id: i64,
-    pub text: String,
+pub(crate) struct LinePiece {
+    pub line: String,
+    /// 起点x坐标
+    pub x: i32,
+    /// 起点y坐标
+    pub y: i32,
+    /// 分片宽度，小于等于行宽
+    pub w: i32,
+    /// 行高
+    pub h: i32,
+    /// 虚拟行高顶部y坐标
+    pub top_y: i32,
+    /// 额外的行间距。
+    /// 目前默认值为0，未产生实际影响。
+    pub spacing: i32,
+    /// 建议下一个数据分片绘制起点x坐标
+    pub next_x: i32,
+    /// 建议下一个数据分片绘制起点y坐标
+    pub next_y: i32,
+
+    /// 字体渲染高度，小于等于行高。
+    pub font_height: i32,
+    /// 当行高与字体渲染高度不一致时，需要通过y轴偏移量矫正渲染文字的位置。
+    pub text_offset: i32,
+    /// 当行高与字体渲染高度不一致时，需要通过y轴偏移量矫正背景色渲染位置。
+    pub bg_offset: i32,
+
+    /// 在同一行内有多个数据分片的情况下， 跟踪行高信息。每次新增行时，第一个分片需要创建新的对象；在同一行其他分片只需要引用第一个分片的对象即可。
+    pub through_line: Arc<RwLock<ThroughLine>>,
+
+    /// 选中文字相对于当前片段的起始到结束位置，位置索引是以`unicode`字符计算的。
+    pub selected_range: Arc<RwLock<Option<(usize, usize)>>>,
+
     pub font: Font,
     pub font_size: i32,
-    pub fg_color: Color,
-    pub bg_color: Option<Color>,
-    underline: bool,
-    clickable: bool,
-    expired: bool,
-    /// 闪烁片段列表
-    blink: bool,
-    disabled: bool,
-    pub strike_through: bool,
-    pub line_height: i32,
-    /// 当前内容在面板垂直高度中的起始和截至y坐标，以及起始和结尾x坐标。
-    v_bounds: Arc<RwLock<(i32, i32, i32, i32)>>,
 
-    /// 对当前数据进行试算后，分割成适配单行宽度的分片保存起来。不支持多线程。
-    pub(crate) line_pieces: Vec<Arc<RwLock<LinePiece>>>,
-    data_type: DataType,
-    /// 格式为RGB格式(L8/LA8/RGB8/RGBA8)的图片数据。
-    image: Option<Vec<u8>>,
-    image_color_depth: ColorDepth,
-    /// 原始宽度
-    image_width: i32,
-    /// 原始高度
-    image_height: i32,
-    /// 希望绘制的目标宽度
-    image_target_width: i32,
-    /// 希望绘制的目标高度
-    image_target_height: i32,
-    /// 色深为L8的灰度数据。
-    image_inactive: Option<Vec<u8>>,
-    /// 图片来源地址。
-    image_src_url: Option<String>,
-    image_file_path: Option<PathBuf>,
-    /// 多行片段之间的水平空白距离。
-    piece_spacing: i32,
+    /// 分片所在数据段的边界数据引用。
+    pub rd_bounds: Arc<RwLock<(i32, i32, i32, i32)>>,
 
-    pub(crate) search_result_positions: Option<Vec<(usize, usize)>>,
-    pub(crate) search_highlight_pos: Option<usize>,
+    /// 是否为自动折行产生的续行分片，即前一个分片未以换行符结束，当前分片却从新的一行开始。
+    pub soft_wrapped: bool,
 
-    /// 互动属性。
-    pub action: Option<Action>,
-    /// 是否来自光标定位面板的数据。
-    rewrite_board_data: bool
+    /// 分片左侧的水平留白，图形分片为`IMAGE_PADDING_H`，文本分片为0。
+    pub pad_h: i32,
 }
 
-impl From<UserData> for RichData {
-    fn from(data: UserData) -> Self {
-        match data.data_type {
-            DataType::Text => {
-                RichData {
-                    id: data.id,
-                    text: data.text,
-                    font: data.font,
-                    font_size: data.font_size,
-                    fg_color: data.fg_color,
-                    bg_color: data.bg_color,
-                    underline: data.underline,
-                    clickable: data.clickable,
-                    expired: data.expired,
-                    blink: data.blink,
-                    disabled: false,
-                    strike_through: data.strike_through,
-                    line_height: 1,
-                    v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
-                    line_pieces: vec![],
-                    data_type: DataType::Text,
-                    image: None,
-                    image_color_depth: ColorDepth::L8,
-                    image_width: 0,
-                    image_height: 0,
-                    image_target_width: 0,
-                    image_target_height: 0,
-                    image_inactive: None,
-                    image_src_url: None,
-                    image_file_path: None,
-                    piece_spacing: 0,
-                    search_result_positions: None,
-                    search_highlight_pos: None,
-                    action: data.action,
-                    rewrite_board_data: false,
-                }
-            },
-            DataType::Image => {
-                let (rgb_data, depth, image_width, image_height) = image_to_rgb_data(&data.image, data.image_target_width, data.image_target_height);
-                RichData {
-                    id: data.id,
-                    text: data.text,
-                    font: data.font,
-                    font_size: data.font_size,
-                    fg_color: data.fg_color,
-                    bg_color: data.bg_color,
-                    underline: data.underline,
-                    clickable: data.clickable,
-                    expired: data.expired,
-                    blink: data.blink,
-                    disabled: false,
-                    strike_through: data.strike_through,
-                    line_height: 1,
-                    v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
-                    line_pieces: Vec::with_capacity(0),
-                    data_type: DataType::Image,
-                    image: rgb_data,
-                    image_color_depth: depth,
-                    image_width,
-                    image_height,
-                    image_target_width: data.image_target_width,
-                    image_target_height: data.image_target_height,
-                    image_inactive: None,
-                    image_src_url: data.image_src_url,
-                    image_file_path: data.image_file_path,
-                    piece_spacing: 0,
-                    search_result_positions: None,
-                    search_highlight_pos: None,
-                    action: data.action,
-                    rewrite_board_data: false,
-                }
-            }
-        }
+impl LinePiece {
+    pub fn new(line: String, x: i32, y: i32, w: i32, h: i32, top_y: i32, spacing: i32, next_x: i32, next_y: i32, font_height: i32, font: Font, font_size: i32, through_line: Arc<RwLock<ThroughLine>>, rd_bounds: Arc<RwLock<(i32, i32, i32, i32)>>) -> Arc<RwLock<LinePiece>> {
+        let new_piece = Arc::new(RwLock::new(Self {
+            line,
+            x,
+            y,
+            w,
+            h,
+            top_y,
+            spacing,
+            next_x,
+            next_y,
+            font_height,
+            text_offset: 0,
+            bg_offset: 0,
+            through_line: through_line.clone(),
+            selected_range: Arc::new(RwLock::new(None)),
+            font,
+            font_size,
+            rd_bounds,
+            soft_wrapped: false,
+            pad_h: 0,
+        }));
+        through_line.write().add_piece(new_piece.clone());
+        new_piece
     }
-}
 
-impl RichData {
-    pub(crate) fn empty() -> Self {
-        RichData {
-            id: YitIdHelper::next_id(),
-            text: String::new(),
+    pub fn init_piece(text_size: i32, padding: &Padding) -> Arc<RwLock<LinePiece>> {
+        let through_line = Arc::new(RwLock::new(Default::default()));
+        let init_piece = Arc::new(RwLock::new(Self {
+            line: "".to_string(),
+            x: padding.left,
+            y: padding.top,
+            w: 0,
+            h: (text_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32,
+            top_y: padding.top,
+            spacing: 0,
+            next_x: padding.left,
+            next_y: padding.top,
+            font_height: 1,
+            text_offset: 0,
+            bg_offset: 0,
+            through_line: through_line.clone(),
+            selected_range: Arc::new(RwLock::new(None)),
             font: Font::Helvetica,
-            font_size: 0,
-            fg_color: Color::White,
-            bg_color: None,
-            underline: false,
-            clickable: false,
-            expired: false,
-            blink: false,
-            disabled: false,
-            strike_through: false,
-            line_height: 1,
-            v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
-            line_pieces: Vec::with_capacity(0),
-            data_type: DataType::Text,
-            image: None,
-            image_color_depth: ColorDepth::L8,
-            image_width: 0,
-            image_height: 0,
-            image_target_width: 0,
-            image_target_height: 0,
-            image_inactive: None,
-            image_src_url: None,
-            image_file_path: None,
-            piece_spacing: 0,
-            search_result_positions: None,
-            search_highlight_pos: None,
-            action: None,
-            rewrite_board_data: false,
-        }
+            font_size: DEFAULT_FONT_SIZE,
+            rd_bounds: Arc::new(RwLock::new((padding.top, padding.top + (text_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, padding.left, padding.left))),
+            soft_wrapped: false,
+            pad_h: 0,
+        }));
+        through_line.write().add_piece(init_piece.clone());
+        init_piece
     }
 
-    pub(crate) fn set_piece_spacing(&mut self, piece_spacing: i32) {
-        self.piece_spacing = piece_spacing;
+    pub fn select_from(&self, from: usize) {
+        let (from, _) = snap_to_graphemes(&self.line, from, from);
+        self.selected_range.write().replace((from, self.line.chars().count()));
     }
-    
-    /// 处理超宽的数据单元，自动换行。
+
+    pub fn select_to(&self, to: usize) {
+        let (_, to) = snap_to_graphemes(&self.line, to, to);
+        self.selected_range.write().replace((0, to));
+    }
+
+    /// 选择分片中的部分字符，选区边界扩展到完整的字素簇，避免拆分表情符号序列或组合字符。
+    pub fn select_range(&self, from: usize, to: usize) {
+        self.selected_range.write().replace(snap_to_graphemes(&self.line, from, to));
+    }
+
+    pub fn deselect(&self) {
+        self.selected_range.write().take();
+    }
+
+    pub fn select_all(&self) {
+        self.selected_range.write().replace((0, self.line.chars().count()));
+    }
+
+    pub fn copy_selection(&self, selection: &mut String) {
+        if let Some((from, to)) = *self.selected_range.read() {
+            self.line.chars().skip(from).take(max(to, from) - from).for_each(|c| {
+                selection.push(c);
+            });
+        }
+    }
+
+    ///
     ///
     /// # Arguments
     ///
-    /// * `text`:
-    /// * `last_piece`:
-    /// * `max_width`:
-    /// * `padding`:
-    /// * `measure_width`:
+    /// * `offset_y`:
     ///
-    /// returns: ()
+    /// returns: Rectangle
     ///
     /// # Examples
     ///
     /// ```
     ///
     /// ```
-    pub fn wrap_text_for_estimate(&mut self, text: &str, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, measure_width: i32, font_height: i32) -> Arc<RwLock<LinePiece>> {
-        let original = last_piece.clone();
-        let last_piece = last_piece.read().clone();
-        let tw = Rc::new(RefCell::new(0));
-        let text_len = text.chars().count();
-        let (font, font_size) = (self.font, self.font_size);
-        if let Ok(stop_pos) = (0..text_len).collect::<Vec<usize>>().binary_search_by({
-            let x = last_piece.next_x + self.piece_spacing;
-            let tw_rc = tw.clone();
-            move |pos| {
-                let (tw1, _) = measure(text.chars().take(*pos).collect::<String>().as_str(), false);
-                if x + tw1 <= max_width {
-                    if *pos == text_len - 1 {
-                        tw_rc.replace(tw1);
-                        Ordering::Equal
-                    } else {
-                        let (tw2, _) = measure(text.chars().take(*pos + 1).collect::<String>().as_str(), false);
-                        if x + tw2 > max_width {
-                            tw_rc.replace(tw1);
-                            Ordering::Equal
-                        } else {
-                            Ordering::Less
-                        }
-                    }
-                } else {
-                    Ordering::Greater
-                }
-            }
-        }) {
-            // 出现超宽
-            let w = *tw.borrow();
-            // 换行处理
-            let next_x = PADDING.left;
-            let through_line = ThroughLine::create_or_update(PADDING.left, last_piece.next_x, font_height, original.clone(), false);
-            let line_max_h = through_line.read().max_h;
-            let max_h = max(line_max_h, font_height);
-            let mut next_y = last_piece.next_y + max_h + last_piece.spacing;
-            if through_line.read().exist_image {
-                next_y += IMAGE_PADDING_V * 2;
-            }
-
-            let y = last_piece.next_y;
-            let top_y = last_piece.next_y;
-            let new_piece = LinePiece::new(text.chars().take(stop_pos).collect::<String>(), last_piece.next_x, y, w, font_height, top_y, last_piece.spacing, next_x, next_y, font_height, font, font_size,  through_line.clone(), self.v_bounds.clone());
-            self.line_pieces.push(new_piece.clone());
-
-            let rest_str = text.chars().skip(stop_pos).collect::<String>();
-            let rest_width = measure_width - w;
-
-            if rest_width > max_width {
-                // 剩余部分的宽度仍然大于一整行宽度
-                self.wrap_text_for_estimate(rest_str.as_str(), new_piece.clone(), max_width, rest_width, font_height)
-            } else {
-                let rest_x = next_x;
-                let rest_y = next_y;
-                let top_y = next_y;
-                let mut rest_next_x = rest_x + rest_width + self.piece_spacing;
-                let mut rest_next_y = next_y;
-                if rest_str.ends_with("\n") {
-                    rest_next_x = PADDING.left;
-                    rest_next_y += font_height + last_piece.spacing;
-                }
+    pub fn rect(&self, offset_x: i32, offset_y: i32) -> Rectangle {
+        Rectangle::new(self.x + offset_x, self.y + offset_y, self.w, self.h)
+    }
 
-                let through_line = ThroughLine::create_or_update(PADDING.left, rest_x, font_height, original.clone(), false);
-                let new_piece = LinePiece::new(rest_str, rest_x, rest_y, rest_width, font_height, top_y, last_piece.spacing, rest_next_x, rest_next_y, font_height, font, font_size, through_line, self.v_bounds.clone());
-                self.line_pieces.push(new_piece.clone());
-                new_piece
-            }
-        } else {
-            // 从行首开始
-            let through_line = ThroughLine::create_or_update(PADDING.left, PADDING.left, self.line_height, original.clone(), false);
-            let y = last_piece.next_y + last_piece.through_line.read().max_h + last_piece.spacing;
-            let new_piece = LinePiece::new(text.to_string(), PADDING.left, y, measure_width, self.line_height, y, last_piece.spacing, PADDING.left, y, font_height, font, font_size, through_line, self.v_bounds.clone());
-            self.wrap_text_for_estimate(text, new_piece, max_width, measure_width, font_height)
+    /// 获取分片的命中区域，用于点击和划选的位置检测。
+    /// 与绘制区域`rect()`相比，命中区域向左包含图形留白，向右包含到同一行下一个分片起点之间的片段间距，
+    /// 使得在间隙中点击时仍能命中前一个分片，而不会落空。
+    ///
+    /// returns: Rectangle
+    pub fn hit_rect(&self) -> Rectangle {
+        let mut right = self.x + self.w + self.pad_h;
+        if self.next_y == self.top_y && self.next_x > right {
+            right = self.next_x;
         }
+        let left = self.x - self.pad_h;
+        Rectangle::new(left, self.y, right - left, self.h)
     }
 
-}
-
-
-impl LinedData for RichData {
-    fn set_v_bounds(&mut self, top_y: i32, bottom_y: i32, start_x: i32, end_x: i32,) {
-        *self.v_bounds.write() = (top_y, bottom_y, start_x, end_x);
+    /// 检测坐标点是否位于分片的命中区域内。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 坐标x。
+    /// * `y`: 坐标y。
+    ///
+    /// returns: bool
+    pub fn hit(&self, x: i32, y: i32) -> bool {
+        let Rectangle(hx, hy, hw, hh) = self.hit_rect();
+        x >= hx && x < hx + hw && y >= hy && y < hy + hh
     }
 
-    fn is_text_data(&self) -> bool {
-        self.image.is_none()
+    /// 获取当前片段右侧的虚拟光标，虚拟光标是一个零宽度的片段。
+    pub fn get_cursor(&self) -> LinePiece {
+        // 为光标信息附加文本末尾的字符，用于辅助检测换行逻辑，控制行高。
+        let line = if let Some(c) = self.line.chars().last() {
+            c.to_string()
+        } else {
+            String::new()
+        };
+        Self {
+            line,
+            x: self.next_x,
+            y: self.next_y,
+            w: 0,
+            h: self.h,
+            top_y: if self.next_y > self.y { self.next_y } else { self.top_y },
+            spacing: 0,
+            next_x: self.next_x,
+            next_y: self.next_y,
+            font_height: self.font_height,
+            text_offset: 0,
+            bg_offset: 0,
+            through_line: self.through_line.clone(),
+            selected_range: Arc::new(RwLock::new(None)),
+            font: self.font,
+            font_size: self.font_size,
+            rd_bounds: Arc::new(RwLock::new((self.next_y, self.next_y + self.h, self.next_x, self.next_x))),
+            soft_wrapped: false,
+            pad_h: 0,
+        }
     }
 
-    fn clickable(&self) -> bool {
-        self.clickable && !self.expired
+    /// 以当前分片所在行的下一行行首作为起点，生成用于强制换行的虚拟分片。
+    pub(crate) fn line_break_cursor(&self, left: i32) -> LinePiece {
+        let mut cursor = self.get_cursor();
+        let next_y = self.top_y + self.through_line.read().max_h;
+        cursor.line = "\n".to_string();
+        cursor.x = left;
+        cursor.next_x = left;
+        cursor.y = next_y;
+        cursor.top_y = next_y;
+        cursor.next_y = next_y;
+        cursor
     }
 
-    fn set_clickable(&mut self, clickable: bool) {
-        self.clickable = clickable;
-    }
+    // /// 相对移动虚拟光标。
+    // ///
+    // /// # Arguments
+    // ///
+    // /// * `offset_x`: 水平移动。
+    // /// * `offset_y`: 垂直移动。
+    // ///
+    // /// returns: ()
+    // ///
+    // /// # Examples
+    // ///
+    // /// ```
+    // ///
+    // /// ```
+    // pub fn move_cursor(&mut self, offset_x: i32, offset_y: i32) {
+    //     self.x += offset_x;
+    //     self.y += offset_y;
+    //     self.next_x += offset_x;
+    //     self.next_y += offset_y;
+    // }
 
-    fn is_expired(&self) -> bool {
-        self.expired
+    /// 绝对移动虚拟光标。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 水平移动到x位置。
+    /// * `y`: 垂直移动到y位置。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn move_cursor_to(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+        self.next_x = x;
+        self.next_y = y;
     }
 
-    fn set_expire(&mut self, expire: bool) {
-        self.expired = expire;
-    }
+    pub fn calc_offset(&mut self) {
+        (self.text_offset, _) = calc_v_center_offset(self.h, self.font_size);
+        (self.bg_offset, _) = calc_v_center_offset(self.h, self.font_height);
+        // debug!("text_offset: {}, bg_offset: {}, h: {}, font_size: {}, font_height: {}, line: {}", self.text_offset, self.bg_offset, self.h, self.font_size, self.font_height, self.line);
 
-    fn set_text_data(&mut self, text_data: &str) {
-        self.text.clear();
-        self.text.push_str(text_data);
+        #[cfg(target_os = "windows")]
+        {
+            self.text_offset = (self.text_offset as f32  / 2.0f32).floor() as i32;
+        }
     }
+}
 
-    fn set_binary_data(&mut self, _: Vec<u8>) {}
+pub(crate) trait LinedData {
+    /// 设置绘制区域顶部和底部边界y坐标，以及起始x坐标。
+    ///
+    /// # Arguments
+    ///
+    /// * `start_point`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn set_v_bounds(&mut self, top_y: i32, bottom_y: i32, start_x: i32, end_x: i32);
 
-    fn is_visible(&self, top_y: i32, bottom_y: i32) -> bool {
-        let b = *self.v_bounds.read();
-        !(b.1 < top_y || b.0 > bottom_y)
-    }
+    /// 表明是否纯文本数据段。
+    fn is_text_data(&self) -> bool;
 
-    fn draw(&self, offset_y: i32, blink_state: &BlinkState) {
-        match self.data_type {
-            DataType::Text => {
-                let mut processed_search_len = 0usize;
-                set_font(self.font, self.font_size);
-                for piece in self.line_pieces.iter() {
-                    let piece = &*piece.read();
-                    let text = piece.line.trim_end_matches('\n');
-                    if text.is_empty() {
-                        continue;
-                    }
+    /// 是否可点击互动。
+    fn clickable(&self) -> bool;
 
-                    let y = piece.y - offset_y;
+    /// 设置可点击互动。
+    fn set_clickable(&mut self, clickable: bool);
 
-                    if !self.blink || blink_state.next == BlinkDegree::Normal {
-                        if let Some(bg_color) = &self.bg_color {
-                            // 绘制文字背景色
-                            // debug!("绘制文字背景色: {}", bg_color.to_hex_str());
-                            set_draw_color(*bg_color);
-                            draw_rectf(piece.x, y - piece.spacing + piece.bg_offset, piece.w, piece.font_height);
-                        }
-                    }
+    /// 是否已失效
+    fn is_expired(&self) -> bool;
 
-                    if let Some((from, to)) = *piece.selected_range.read() {
-                        // 绘制选中背景色
-                        let sel_color = if let Some(bg_color) = &self.bg_color {
-                            if *bg_color == Color::Blue || *bg_color == Color::DarkBlue {
-                                Color::DarkMagenta
-                            } else {
-                                Color::Selection
-                            }
-                        } else {
-                            Color::Selection
-                        };
-                        set_draw_color(sel_color);
-                        let (skip_width, _) = measure(piece.line.chars().take(from).collect::<String>().as_str(), false);
-                        let (fill_width, _) = measure(piece.line.chars().skip(from).take(max(to, from) - from).collect::<String>().as_str(), false);
+    /// 设置失效状态。
+    ///
+    /// # Arguments
+    ///
+    /// * `expire`: 指定是否失效。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn set_expire(&mut self, expire: bool);
 
-                        draw_rectf(piece.x + skip_width, y + piece.bg_offset, fill_width, piece.font_height);
-                    }
+    /// 设置文本数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `text_data`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn set_text_data(&mut self, text_data: &str);
 
-                    // 绘制查找焦点框
-                    if let Some(ref pos_vec) = self.search_result_positions {
-                        let rect_color = if blink_state.next == BlinkDegree::Normal {
-                            blink_state.focus_boarder_color
-                        } else {
-                            blink_state.focus_boarder_contrast_color
-                        };
-                        let pl = piece.line.chars().count();
-                        let range = processed_search_len..(processed_search_len + pl);
-                        pos_vec.iter().enumerate().for_each(|(pos_i, (pos_from, pos_to))| {
-                            if range.contains(pos_from) {
-                                let start_index_of_piece = pos_from - processed_search_len;
-                                let (skip_width, _) = measure(piece.line.chars().take(start_index_of_piece).collect::<String>().as_str(), false);
-                                let (fill_width, _) = measure(piece.line.chars().skip(start_index_of_piece).take(pos_to - pos_from).collect::<String>().as_str(), false);
+    /// 设置二
+    ///
+    /// # Arguments
+    ///
+    /// * `binary_data`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn set_binary_data(&mut self, binary_data: Vec<u8>);
 
-                                set_draw_color(blink_state.focus_background_color);
-                                #[cfg(not(target_os = "windows"))]
-                                {
-                                    // draw_rectf(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height);
-                                    draw_rounded_rectf(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                    if let Some(h_i) = self.search_highlight_pos {
-                                        if h_i == pos_i {
-                                            // debug!("blink1: {:?}", blink_state);
-                                            set_draw_color(rect_color);
-                                            set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
-                                            // draw_rect_with_color(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, rect_color);
-                                            draw_rounded_rect(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                            set_line_style(LineStyle::Solid, 0);
-                                        }
-                                    }
-                                }
 
-                                #[cfg(target_os = "windows")]
-                                {
-                                    // draw_rectf(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height);
-                                    draw_rounded_rectf(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                    if let Some(h_i) = self.search_highlight_pos {
-                                        if h_i == pos_i {
-                                            set_draw_color(rect_color);
-                                            set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
-                                            // draw_rect_with_color(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, rect_color);
-                                            draw_rounded_rect(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                            set_line_style(LineStyle::Solid, 0);
-                                        }
-                                    }
-                                }
+    /// 检测是否位于可视窗口范围内。
+    ///
+    /// # Arguments
+    ///
+    /// * `top_y`: 面板顶部y轴偏移量。
+    /// * `bottom_y`: 面板底部y轴偏移量。
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn is_visible(&self, top_y: i32, bottom_y: i32) -> bool;
 
-                            } else if range.contains(pos_to) {
-                                let (fill_width, _) = measure(piece.line.chars().take(pos_to - processed_search_len).collect::<String>().as_str(), false);
 
-                                set_draw_color(blink_state.focus_background_color);
-                                // draw_rectf(piece.x, y - piece.spacing, fill_width, piece.font_height);
-                                draw_rounded_rectf(piece.x, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                if let Some(h_i) = self.search_highlight_pos {
-                                    if h_i == pos_i {
-                                        set_draw_color(rect_color);
-                                        set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
-                                        // draw_rect_with_color(piece.x, y - piece.spacing, fill_width, piece.font_height, rect_color);
-                                        draw_rounded_rect(piece.x, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
-                                        set_line_style(LineStyle::Solid, 0);
-                                    }
-                                }
-                            }
-                        });
-                        processed_search_len += pl;
-                    }
+    /// 绘制内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板相对于数据的y轴偏移量。
+    /// * `blink_state`: 面板范围内的闪烁状态。
+    /// * `options`: 绘制选项。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn draw(&self, offset_y: i32, blink_state: &BlinkState, options: &RenderOptions);
 
-                    if self.blink && blink_state.next == BlinkDegree::Contrast {
-                        set_draw_color(get_lighter_or_darker_color(self.fg_color));
-                    } else {
-                        set_draw_color(self.fg_color);
-                    }
+    /// 试算当前内容绘制后所占高度信息。
+    /// 试算功能自动处理文本超宽时截断换行的逻辑。
+    ///
+    /// # Arguments
+    ///
+    /// * `last_piece`: 前一个数据片段，用于计算当前数据段的绘制坐标。每个数据段和数据片段都是按照缓存数据的顺序依次计算得到。
+    /// * `max_width`: 可视区域最大宽度，不含padding宽度。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn estimate(&mut self, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, basic_char: char) -> Arc<RwLock<LinePiece>>;
 
-                    if self.underline {
-                        // 绘制下划线
-                        // let line_y = y + piece.font_height + piece.bg_offset - 1;
-                        let line_y = y + piece.font_size + piece.text_offset + 2;
-                        draw_line(piece.x, line_y, piece.x + piece.w - 2, line_y);
-                    }
+}
 
-                    // 绘制文本，使用draw_text_n()函数可以正确渲染'@'字符而无需转义处理。
-                    draw_text_n(text, piece.x, y + self.font_size + piece.text_offset);
+/// 数据段类型，当前支持文本、图片、不可见的结构标记、分隔线、表格和进度条六种。
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DataType {
+    Text,
+    Image,
+    /// 不参与绘制且高度为零的结构标记。
+    Marker,
+    /// 独占一行并横贯整个可绘制宽度的分隔线，可带居中的标签。
+    Divider,
+    /// 按列对齐的表格，每行数据独占一行。
+    Table,
+    /// 在行内排列的进度条，可通过`RichDataOptions::gauge_value`更新进度。
+    Gauge,
+}
 
-                    if self.strike_through {
-                        // 绘制删除线
-                        let line_y = y + ((piece.font_height as f32 / 2f32).floor() as i32);
-                        draw_line(piece.x, line_y, piece.x + piece.w - 4, line_y);
-                    }
+/// 下划线样式，对应ANSI/CSI/SGR的`4:1`到`4:4`参数。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum UnderlineStyle {
+    /// 单下划线。
+    #[default]
+    Single,
+    /// 双下划线。
+    Double,
+    /// 波浪线，常用于拼写检查提示。
+    Curly,
+    /// 点状下划线。
+    Dotted,
+}
 
-                    // {
-                    //     // 绘制上边界，用来辅助调试绘制内容的偏移效果
-                    //     draw_line(piece.x, piece.y, piece.x + piece.w, piece.y);
-                    // }
-                }
-            },
-            DataType::Image => {
-                if let Some(piece) = self.line_pieces.last() {
-                    let piece = &*piece.read();
-                    if !self.disabled {
-                        if !self.blink || blink_state.next == BlinkDegree::Normal {
-                            if let Some(img) = &self.image {
-                                // debug!("绘制图像：x:{}, y:{}, w:{}, h:{}", piece.x, piece.y - offset_y, piece.w, piece.h);
-                                match RgbImage::new(img, self.image_width, self.image_height, self.image_color_depth) {
-                                    Ok(mut rgb_img) => {
-                                        if self.image_width != self.image_target_width || self.image_height != self.image_target_height {
-                                            rgb_img.scale(self.image_target_width, self.image_target_height, false, true);
-                                        }
-                                        rgb_img.draw(piece.x, piece.y - offset_y, piece.w, piece.h);
-                                    }
-                                    Err(e) => {
-                                        error!("create rgb image error: {:?}", e);
-                                    }
-                                }
-                            }
-                            if !self.text.is_empty() {
-                                // 在图像上居中绘制文字
-                                set_font(self.font, self.font_size);
-                                set_draw_color(self.fg_color);
-                                let lines = self.text.split("\n").count() as i32;
-                                let total_height = self.font_size * lines;
-                                let img_y_center = piece.y - offset_y + piece.h / 2;
-                                let first_line_y = img_y_center - total_height / 2;
+/// 文字背景的填充样式，渐变从`bg_color`过渡到指定的结束颜色，适合用于醒目的横幅提示。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackgroundStyle {
+    /// 以背景色纯色填充。
+    #[default]
+    Solid,
+    /// 从左至右的水平渐变，参数为右端颜色。
+    HorizontalGradient(Color),
+    /// 从上至下的垂直渐变，参数为底端颜色。
+    VerticalGradient(Color),
+}
 
-                                for (idx, line) in self.text.replace("\r", "").split("\n").enumerate() {
-                                    let (tw, _) = measure(line, false);
-                                    let text_x = piece.x + piece.w / 2 - tw / 2;
-                                    let text_y = first_line_y + idx as i32 * self.font_size;
-                                    draw_text_n(line, text_x, text_y + self.font_size);
-                                }
-                            }
-                        }
-                    } else {
-                        if !self.blink || blink_state.next == BlinkDegree::Normal {
-                            if let Some(img) = &self.image_inactive {
-                                let depth = match self.image_color_depth {
-                                    ColorDepth::Rgb8 | ColorDepth::L8 => {
-                                        ColorDepth::L8
-                                    }
-                                    ColorDepth::Rgba8 | ColorDepth::La8 => {
-                                        ColorDepth::La8
-                                    }
-                                };
-                                match RgbImage::new(img, self.image_width, self.image_height, depth) {
-                                    Ok(mut rgb_img) => {
-                                        if self.image_width != self.image_target_width || self.image_height != self.image_target_height {
-                                            rgb_img.scale(self.image_target_width, self.image_target_height, false, true);
-                                        }
-                                        rgb_img.draw(piece.x, piece.y - offset_y, piece.w, piece.h);
-                                    }
-                                    Err(e) => {
-                                        error!("create rgb image error: {:?}", e);
-                                    }
-                                }
-                                // if let Err(e) = draw_image(img.as_slice(), piece.x, piece.y - offset_y, self.image_width, piece.h, depth) {
-                                //     error!("draw gray image error: {:?}", e);
-                                // }
+impl BackgroundStyle {
+    /// 获取渐变方向和结束颜色。
+    ///
+    /// returns: Option<(bool, Color)> 返回(是否为垂直渐变, 结束颜色)，纯色填充时返回空。
+    pub(crate) fn gradient(&self) -> Option<(bool, Color)> {
+        match *self {
+            BackgroundStyle::Solid => None,
+            BackgroundStyle::HorizontalGradient(end) => Some((false, end)),
+            BackgroundStyle::VerticalGradient(end) => Some((true, end)),
+        }
+    }
 
-                                if !self.text.is_empty() {
-                                    // 在图像上居中绘制文字
-                                    set_font(self.font, self.font_size);
-                                    set_draw_color(Color::Light1);
-                                    let lines = self.text.split("\n").count() as i32;
-                                    let total_height = self.font_size * lines;
-                                    let img_y_center = piece.y - offset_y + piece.h / 2;
-                                    let first_line_y = img_y_center - total_height / 2;
+    /// 由渐变方向和结束颜色构建背景样式。
+    pub(crate) fn from_gradient(vertical: bool, end: Color) -> BackgroundStyle {
+        if vertical {
+            BackgroundStyle::VerticalGradient(end)
+        } else {
+            BackgroundStyle::HorizontalGradient(end)
+        }
+    }
 
-                                    for (idx, line) in self.text.replace("\r", "").split("\n").enumerate() {
-                                        let (tw, _) = measure(line, false);
-                                        let text_x = piece.x + piece.w / 2 - tw / 2;
-                                        let text_y = first_line_y + idx as i32 * self.font_size;
-                                        draw_text_n(line, text_x, text_y + self.font_size);
-                                    }
-                                }
-                            }
-                        }
-                    }
+    /// 以指定函数转换渐变的结束颜色。
+    pub(crate) fn map_color(&self, f: impl Fn(Color) -> Color) -> BackgroundStyle {
+        match *self {
+            BackgroundStyle::Solid => BackgroundStyle::Solid,
+            BackgroundStyle::HorizontalGradient(end) => BackgroundStyle::HorizontalGradient(f(end)),
+            BackgroundStyle::VerticalGradient(end) => BackgroundStyle::VerticalGradient(f(end)),
+        }
+    }
+
+    /// 按照背景样式填充矩形区域。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 左上角x坐标。
+    /// * `y`: 左上角y坐标。
+    /// * `w`: 宽度。
+    /// * `h`: 高度。
+    /// * `start`: 背景色，即渐变的起始颜色。
+    ///
+    /// returns: ()
+    pub(crate) fn fill(&self, x: i32, y: i32, w: i32, h: i32, start: Color) {
+        match *self {
+            BackgroundStyle::Solid => {
+                set_draw_color(start);
+                draw_rectf(x, y, w, h);
+            }
+            BackgroundStyle::HorizontalGradient(end) => {
+                for i in 0..w {
+                    set_draw_color(gradient_color(start, end, i, w));
+                    draw_line(x + i, y, x + i, y + h - 1);
+                }
+            }
+            BackgroundStyle::VerticalGradient(end) => {
+                for i in 0..h {
+                    set_draw_color(gradient_color(start, end, i, h));
+                    draw_line(x, y + i, x + w - 1, y + i);
                 }
+            }
+        }
+    }
+}
 
-            },
+/// 计算渐变中指定位置的颜色。
+///
+/// # Arguments
+///
+/// * `start`: 起始颜色。
+/// * `end`: 结束颜色。
+/// * `pos`: 位置，从0开始。
+/// * `len`: 渐变总长度。
+///
+/// returns: Color
+pub(crate) fn gradient_color(start: Color, end: Color, pos: i32, len: i32) -> Color {
+    if len <= 1 {
+        return start;
+    }
+    let weight = pos.clamp(0, len - 1) as f32 / (len - 1) as f32;
+    Color::color_average(end, start, weight)
+}
+
+/// 数据段前置的小图标，例如聊天消息的头像或通知的类型图标。
+#[derive(Clone, Debug)]
+pub struct Icon {
+    /// RGB数据。
+    data: Arc<Vec<u8>>,
+    /// 原始宽度。
+    width: i32,
+    /// 原始高度。
+    height: i32,
+    /// 色深。
+    depth: ColorDepth,
+    /// 显示尺寸，图标按照正方形缩放。
+    pub size: i32,
+    /// 按照绘制尺寸缩放后的影像，绘制尺寸不变时直接复用。
+    scaled: Arc<RwLock<Option<(i32, RgbImage)>>>,
+}
+
+impl Icon {
+    /// 由影像构建图标。
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: 图标影像。
+    /// * `size`: 显示尺寸，像素数。
+    ///
+    /// returns: Icon
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn new(image: RgbImage, size: i32) -> Self {
+        Self::from_rgb_data(image.to_rgb_data(), image.w(), image.h(), image.depth(), size)
+    }
+
+    fn from_rgb_data(data: Vec<u8>, width: i32, height: i32, depth: ColorDepth, size: i32) -> Self {
+        Self {
+            data: Arc::new(data),
+            width,
+            height,
+            depth,
+            size: size.max(1),
+            scaled: Arc::new(RwLock::new(None)),
         }
     }
 
-    /// 试算当前内容绘制后所占高度信息。
-    /// 试算功能自动处理文本超宽时截断换行的逻辑。
+    /// 创建共享图标数据、显示尺寸不同的图标。
+    fn with_size(&self, size: i32) -> Self {
+        Self {
+            data: self.data.clone(),
+            width: self.width,
+            height: self.height,
+            depth: self.depth,
+            size: size.max(1),
+            scaled: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 按照指定尺寸绘制图标，缩放后的影像被缓存，绘制尺寸变化时才重新缩放。
     ///
     /// # Arguments
     ///
-    /// * `last_piece`: 前一个数据片段，用于计算当前数据段的绘制坐标。每个数据段和数据片段都是按照缓存数据的顺序依次计算得到。
-    /// * `max_width`: 可视区域最大宽度，不含padding宽度。
+    /// * `x`: 左上角x坐标。
+    /// * `y`: 左上角y坐标。
+    /// * `size`: 绘制尺寸。
     ///
     /// returns: ()
     ///
@@ -2512,1238 +4210,9040 @@ impl LinedData for RichData {
     /// ```
     ///
     /// ```
-    fn estimate(&mut self, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, basic_char: char) -> Arc<RwLock<LinePiece>> {
-        let mut ret = last_piece.clone();
-        let mut last_line_piece = last_piece.read().clone();
-        let (top_y, start_x) = (last_line_piece.next_y, last_line_piece.next_x);
-        let (font, font_size) = (self.font, self.font_size);
-        self.line_pieces.clear();
-        match self.data_type {
-            DataType::Text => {
-                set_font(self.font, self.font_size);
+    fn draw(&self, x: i32, y: i32, size: i32) {
+        let mut scaled = self.scaled.write();
+        if scaled.as_ref().map_or(true, |(cached_size, _)| *cached_size != size) {
+            match RgbImage::new(&self.data, self.width, self.height, self.depth) {
+                Ok(mut img) => {
+                    img.scale(size, size, true, true);
+                    scaled.replace((size, img));
+                }
+                Err(e) => {
+                    error!("create icon image error: {:?}", e);
+                    return;
+                }
+            }
+        }
+        if let Some((_, img)) = scaled.as_mut() {
+            img.draw(x, y, size, size);
+        }
+    }
 
-                // 字体渲染高度，小于等于行高度。
-                let ref_font_height = (self.font_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+    /// 图标占用的左侧预留宽度，包含与文字之间的间距。
+    pub(crate) fn gutter(&self) -> i32 {
+        self.size + ICON_GAP
+    }
 
-                let current_line_spacing = min(last_line_piece.spacing, descent());
+    /// 丢弃缩放后的影像缓存，下次绘制时重新缩放。
+    pub(crate) fn clear_cache(&self) {
+        self.scaled.write().take();
+    }
+}
 
-                /*
-                对含有换行符和不含换行符的文本进行不同处理。
-                 */
-                let text = self.text.clone();
-                if text.contains('\n') {
-                    // 以换行符为节点拆分成多段处理。
-                    for line in text.split_inclusive("\n") {
-                        let (tw, th) = measure(line, false);
-                        let mut current_line_height = max(ref_font_height, th);
-                        self.line_height = current_line_height;
+/// 气泡的对齐方向。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BubbleAlign {
+    /// 靠左对齐，通常用于对方的消息。
+    #[default]
+    Left,
+    /// 靠右对齐，通常用于自己的消息。
+    Right,
+}
 
-                        let mut next_x = last_line_piece.next_x + tw;
-                        if next_x > max_width {
-                            // 超出横向右边界
-                            ret = self.wrap_text_for_estimate(line, ret.clone(), max_width, tw, ref_font_height);
-                        } else {
-                            let new_piece: Arc<RwLock<LinePiece>>;
-                            if let Some(lp) = self.line_pieces.last_mut() {
-                                let lp = &mut *lp.write();
-                                let mut next_y = lp.next_y;
-                                // 最后一段可能带有换行符'\n'。
-                                if line.ends_with("\n") {
-                                    next_y += current_line_height;
-                                    next_x = PADDING.left;
-                                }
-                                let y = lp.next_y;
-                                let piece_top_y = lp.next_y;
-                                let through_line = ThroughLine::create_or_update(PADDING.left, lp.next_x, current_line_height, ret.clone(), false);
-                                new_piece = LinePiece::new(line.to_string(), lp.next_x, y, tw, current_line_height, piece_top_y, lp.spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
+/// 聊天气泡样式。设置了气泡样式的文本数据段独占一块区域，所有分片绘制在带内边距的圆角矩形中。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BubbleStyle {
+    /// 对齐方向。
+    pub align: BubbleAlign,
+    /// 气泡背景色。
+    pub color: Color,
+    /// 圆角半径。
+    pub radius: i32,
+    /// 内边距。
+    pub padding: i32,
+    /// 气泡最大宽度占可绘制宽度的百分比，取值范围为10到100。
+    pub max_width_percent: u8,
+}
 
-                            } else {
-                                let mut next_y = last_line_piece.next_y;
-                                // 最后一段可能带有换行符'\n'。
-                                if line.ends_with("\n") {
-                                    // if !last_piece.line.ends_with("\n") && !last_piece.line.is_empty() {
-                                    //     current_line_height = max(current_line_height, last_piece.h);
-                                    // }
-                                    if !last_line_piece.line.ends_with("\n") {
-                                        current_line_height = max(current_line_height, last_line_piece.h);
-                                    }
-                                    next_y += current_line_height;
-                                    next_x = PADDING.left;
-                                }
-                                let y = last_line_piece.next_y;
-                                let piece_top_y = last_line_piece.next_y;
-                                let through_line = ThroughLine::create_or_update(PADDING.left, last_line_piece.next_x, current_line_height, ret.clone(), false);
-                                new_piece = LinePiece::new(line.to_string(), last_line_piece.next_x, y, tw, self.line_height, piece_top_y, last_line_piece.spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
-                            }
-                            self.line_pieces.push(new_piece.clone());
+impl BubbleStyle {
+    /// 构建新的气泡样式，使用默认的圆角半径、内边距和最大宽度。
+    ///
+    /// # Arguments
+    ///
+    /// * `align`: 对齐方向。
+    /// * `color`: 气泡背景色。
+    ///
+    /// returns: BubbleStyle
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{BubbleAlign, BubbleStyle};
+    ///
+    /// let style = BubbleStyle::new(BubbleAlign::Right, Color::from_rgb(40, 90, 60));
+    /// assert_eq!(style.max_width_percent, 75);
+    /// ```
+    pub fn new(align: BubbleAlign, color: Color) -> Self {
+        Self { align, color, radius: 8, padding: 6, max_width_percent: 75 }
+    }
+
+    /// 计算气泡内文字可使用的宽度。
+    ///
+    /// # Arguments
+    ///
+    /// * `left`: 行首x坐标。
+    /// * `max_width`: 可绘制区域的右边界。
+    ///
+    /// returns: i32
+    pub(crate) fn inner_width(&self, left: i32, max_width: i32) -> i32 {
+        let percent = self.max_width_percent.clamp(10, 100) as i32;
+        max((max_width - left) * percent / 100 - self.padding.max(0) * 2, 1)
+    }
+
+    /// 计算内容分片需要水平移动的距离，使气泡按照对齐方向贴靠行首或右边界。
+    ///
+    /// # Arguments
+    ///
+    /// * `content_left`: 内容左边界。
+    /// * `content_right`: 内容右边界。
+    /// * `left`: 行首x坐标。
+    /// * `max_width`: 可绘制区域的右边界。
+    ///
+    /// returns: i32
+    pub(crate) fn offset_x(&self, content_left: i32, content_right: i32, left: i32, max_width: i32) -> i32 {
+        let padding = self.padding.max(0);
+        match self.align {
+            BubbleAlign::Left => left + padding - content_left,
+            BubbleAlign::Right => max(max_width - padding - content_right, left + padding - content_left),
+        }
+    }
+}
+
+/// 结构标记信息，可用作滚动锚点、已读标记或上层应用自定义的文档结构。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Marker {
+    /// 标记类型，由上层应用定义其具体含义。
+    pub kind: String,
+    /// 标记附带的元数据。
+    pub meta: Option<String>,
+}
+
+/// 分隔线样式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DividerStyle {
+    /// 实线。
+    #[default]
+    Solid,
+    /// 虚线。
+    Dashed,
+    /// 点线。
+    Dotted,
+    /// 双实线。
+    Double,
+}
+
+/// 分隔线信息。
+#[derive(Clone, Debug)]
+pub struct Divider {
+    /// 显示在分隔线中间的标签，不参与划选复制。
+    pub label: Option<String>,
+    /// 线条样式。
+    pub style: DividerStyle,
+    /// 线条和标签的颜色。
+    pub color: Color,
+}
+
+impl Divider {
+    /// 在分隔线所在的分片区域内绘制线条和居中的标签。
+    ///
+    /// # Arguments
+    ///
+    /// * `piece`: 分隔线占据的分片。
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub(crate) fn draw(&self, piece: &LinePiece, offset_y: i32) {
+        let (x1, x2) = (piece.x, piece.x + piece.w);
+        let mid_y = piece.y - offset_y + piece.h / 2;
+        set_draw_color(transform_color(self.color, false));
+        let (gap_from, gap_to) = match self.label.as_deref().filter(|label| !label.is_empty()) {
+            Some(label) => {
+                set_font(piece.font, piece.font_size);
+                let (tw, _) = measure(label, false);
+                let label_x = x1 + max(piece.w - tw, 0) / 2;
+                draw_text_n(label, label_x, piece.y - offset_y + piece.font_size + piece.text_offset);
+                (max(label_x - IMAGE_PADDING_H * 2, x1), min(label_x + tw + IMAGE_PADDING_H * 2, x2))
+            }
+            None => (x2, x2),
+        };
+        for (from, to) in [(x1, gap_from), (gap_to, x2)] {
+            if from >= to {
+                continue;
+            }
+            match self.style {
+                DividerStyle::Solid => draw_line(from, mid_y, to, mid_y),
+                DividerStyle::Dashed => {
+                    set_line_style(LineStyle::Dash, 1);
+                    draw_line(from, mid_y, to, mid_y);
+                    set_line_style(LineStyle::Solid, 0);
+                }
+                DividerStyle::Dotted => {
+                    set_line_style(LineStyle::Dot, 1);
+                    draw_line(from, mid_y, to, mid_y);
+                    set_line_style(LineStyle::Solid, 0);
+                }
+                DividerStyle::Double => {
+                    draw_line(from, mid_y - 1, to, mid_y - 1);
+                    draw_line(from, mid_y + 1, to, mid_y + 1);
+                }
+            }
+        }
+    }
+}
+
+/// 表格单元格的水平对齐方式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CellAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// 表格单元格。
+#[derive(Clone, Debug, Default)]
+pub struct TableCell {
+    pub text: String,
+    /// 文字颜色，为空时使用数据段的前景色。
+    pub fg_color: Option<Color>,
+    /// 单元格背景色，为空时不填充。
+    pub bg_color: Option<Color>,
+    pub align: CellAlign,
+}
+
+impl TableCell {
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), ..Default::default() }
+    }
+
+    pub fn set_fg_color(mut self, fg_color: Color) -> Self {
+        self.fg_color = Some(fg_color);
+        self
+    }
+
+    pub fn set_bg_color(mut self, bg_color: Color) -> Self {
+        self.bg_color = Some(bg_color);
+        self
+    }
+
+    pub fn set_align(mut self, align: CellAlign) -> Self {
+        self.align = align;
+        self
+    }
+}
+
+/// 表格信息。列宽在试算时根据单元格内容自动计算，总宽度超出可绘制宽度时按比例压缩。
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    pub rows: Vec<Vec<TableCell>>,
+    /// 表格线颜色，为空时不绘制表格线。
+    pub border_color: Option<Color>,
+    /// 试算得到的各列宽度。
+    pub(crate) col_widths: Vec<i32>,
+}
+
+impl Table {
+    pub fn new(rows: Vec<Vec<TableCell>>) -> Self {
+        Self { rows, ..Default::default() }
+    }
+
+    pub fn set_border_color(mut self, border_color: Color) -> Self {
+        self.border_color = Some(border_color);
+        self
+    }
+
+    /// 列数，以单元格最多的一行为准。
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    /// 每行单元格以制表符分隔的文本，用于划选复制和查找。
+    pub(crate) fn row_texts(&self) -> Vec<String> {
+        self.rows.iter().map(|row| {
+            let mut line = row.iter().map(|cell| cell.text.as_str()).collect::<Vec<&str>>().join("\t");
+            line.push('\n');
+            line
+        }).collect()
+    }
+
+    /// 按照当前字体测量单元格内容并计算列宽，调用前需设置好字体。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_width`: 表格可用的最大宽度。
+    ///
+    /// returns: ()
+    pub(crate) fn layout(&mut self, max_width: i32) {
+        let mut natural = vec![0; self.columns()];
+        for row in self.rows.iter() {
+            for (i, cell) in row.iter().enumerate() {
+                let (tw, _) = measure(&cell.text, false);
+                natural[i] = max(natural[i], tw + TABLE_CELL_PADDING * 2);
+            }
+        }
+        self.col_widths = fit_column_widths(&natural, max_width);
+    }
+
+    /// 绘制表格中的一行。
+    ///
+    /// # Arguments
+    ///
+    /// * `row`: 行序号。
+    /// * `piece`: 该行占据的分片。
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    /// * `fg_color`: 默认文字颜色。
+    ///
+    /// returns: ()
+    pub(crate) fn draw_row(&self, row: usize, piece: &LinePiece, offset_y: i32, fg_color: Color) {
+        let Some(cells) = self.rows.get(row) else {
+            return;
+        };
+        let (y, h) = (piece.y - offset_y, piece.h);
+        let text_y = y + piece.font_size + piece.text_offset;
+        set_font(piece.font, piece.font_size);
+        let mut x = piece.x;
+        for (i, cw) in self.col_widths.iter().enumerate() {
+            if let Some(cell) = cells.get(i) {
+                if let Some(bg_color) = cell.bg_color {
+                    set_draw_color(bg_color);
+                    draw_rectf(x, y, *cw, h);
+                }
+                let (tw, _) = measure(&cell.text, false);
+                let inner = cw - TABLE_CELL_PADDING * 2;
+                let text_x = match cell.align {
+                    CellAlign::Left => x + TABLE_CELL_PADDING,
+                    CellAlign::Center => x + TABLE_CELL_PADDING + max(inner - tw, 0) / 2,
+                    CellAlign::Right => x + TABLE_CELL_PADDING + max(inner - tw, 0),
+                };
+                set_draw_color(cell.fg_color.unwrap_or(fg_color));
+                draw::push_clip(x, y, *cw, h);
+                draw_text_n(&cell.text, text_x, text_y);
+                draw::pop_clip();
+            }
+            x += cw;
+        }
+        if let Some(border_color) = self.border_color {
+            set_draw_color(border_color);
+            if row == 0 {
+                draw_line(piece.x, y, x, y);
+            }
+            draw_line(piece.x, y + h - 1, x, y + h - 1);
+            let mut line_x = piece.x;
+            draw_line(line_x, y, line_x, y + h - 1);
+            for cw in self.col_widths.iter() {
+                line_x += cw;
+                draw_line(line_x - 1, y, line_x - 1, y + h - 1);
+            }
+        }
+    }
+}
+
+/// 根据各列内容的自然宽度计算实际列宽，总宽度超出可用宽度时按比例压缩各列。
+///
+/// # Arguments
+///
+/// * `natural`: 各列内容的自然宽度。
+/// * `max_width`: 可用的最大宽度。
+///
+/// returns: Vec<i32>
+pub(crate) fn fit_column_widths(natural: &[i32], max_width: i32) -> Vec<i32> {
+    let total: i32 = natural.iter().sum();
+    if total <= max_width || total <= 0 {
+        return natural.to_vec();
+    }
+    natural.iter().map(|w| max((*w as i64 * max(max_width, 0) as i64 / total as i64) as i32, 1)).collect()
+}
+
+/// 进度条信息。
+#[derive(Clone, Debug)]
+pub struct Gauge {
+    /// 当前值。
+    pub value: f64,
+    /// 最大值。
+    pub max: f64,
+    /// 已填充部分的颜色，同时用作边框颜色。
+    pub color: Color,
+    /// 显示在进度条中间的标签，为空时只显示数值。
+    pub label: Option<String>,
+    /// 进度条宽度，像素数。
+    pub width: i32,
+}
+
+impl Gauge {
+    /// 已填充部分所占的比例，范围为`0.0`到`1.0`。
+    pub fn ratio(&self) -> f64 {
+        if self.max > 0.0 {
+            (self.value / self.max).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// 进度条的文本形式，用于划选复制和查找。
+    pub(crate) fn text(&self) -> String {
+        match self.label.as_deref().filter(|label| !label.is_empty()) {
+            Some(label) => format!("{} {}/{}", label, self.value, self.max),
+            None => format!("{}/{}", self.value, self.max),
+        }
+    }
+
+    /// 在进度条所在的分片区域内绘制边框、填充部分和居中的文字。
+    ///
+    /// # Arguments
+    ///
+    /// * `piece`: 进度条占据的分片。
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    /// * `fg_color`: 文字颜色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub(crate) fn draw(&self, piece: &LinePiece, offset_y: i32, fg_color: Color) {
+        let (x, y, w) = (piece.x, piece.y - offset_y + GAUGE_PADDING_V, piece.w);
+        let h = max(piece.h - GAUGE_PADDING_V * 2, 1);
+        let filled = (w as f64 * self.ratio()).round() as i32;
+        set_draw_color(self.color);
+        draw_rectf(x, y, filled, h);
+        draw_rect(x, y, w, h);
+
+        let text = self.text();
+        set_font(piece.font, piece.font_size);
+        let (tw, _) = measure(&text, false);
+        set_draw_color(fg_color);
+        draw::push_clip(x, y, w, h);
+        draw_text_n(&text, x + max(w - tw, 0) / 2, piece.y - offset_y + piece.font_size + piece.text_offset);
+        draw::pop_clip();
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ActionItem {
+    pub desc: String,
+    pub cmd: String,
+}
+
+impl ActionItem {
+    pub fn new(desc: &str, cmd: &str) -> Self {
+        Self {
+            desc: desc.to_string(),
+            cmd: cmd.to_string(),
+        }
+    }
+}
+
+/// 互动行为定义。
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Action {
+    /// 互动操作提示信息，当鼠标指向时会弹出该提示，类似于`HTML`标签的`title`属性。
+    pub title: String,
+    /// 互动操作具体类型，由上层应用定义其具体含义。
+    pub kind: u8,
+    /// 互动操作的动作列表，当鼠标点击时弹出该列表，列表中每个元素的格式为(动作描述，动作指令)。
+    /// 弹出列表中可见的是动作描述，当用户选择某项动作时将反馈动作指令给上层应用控制器。
+    pub items: Vec<ActionItem>,
+    /// 用户选择的动作指令。
+    pub active: Option<String>,
+    /// 动作所属类别名称。
+    pub category: Option<String>,
+    /// 默认动作在动作列表中的索引。设置后左键点击直接执行该动作，右键点击仍弹出完整的动作列表。
+    pub default_item: Option<usize>,
+}
+
+/// 用户提供的数据段结构。。
+#[derive(Clone, Debug)]
+pub struct UserData {
+    /// 数据ID，在初始化新实例时可随意赋值。当源自RichData时，为RichData的ID值。
+    pub id: i64,
+    pub text: String,
+    pub font: Font,
+    pub font_size: i32,
+    pub fg_color: Color,
+    pub bg_color: Option<Color>,
+    pub underline: bool,
+    /// 下划线样式，仅在`underline`为`true`时有效。
+    pub underline_style: UnderlineStyle,
+    /// 下划线颜色，对应ANSI/CSI/SGR的`58`参数，为空时使用前景色。
+    pub underline_color: Option<Color>,
+    /// 前景色序号，从1到8对应ANSI/CSI/SGR的黑、红、绿、黄、蓝、品红、青、白的颜色序列，9到16对应其高亮色。
+    /// 设置了调色板时按照调色板中的颜色绘制，0表示不使用调色板。
+    pub fg_color_index: u8,
+    /// 背景色序号，从1到8对应ANSI/CSI/SGR的黑、红、绿、黄、蓝、品红、青、白的颜色序列，9到16对应其高亮色。
+    /// 设置了调色板时按照调色板中的颜色绘制，0表示不使用调色板。
+    pub bg_color_index: u8,
+    /// 显示效果是否加强，对应与ANSI/CSI的`0`和`1`参数。
+    pub strong: bool,
+    /// 文字大小编号，从1到7对应MXP协议中的SMALL、H6、H5、H4、H3、H2、H1。
+    pub font_size_index: u8,
+    pub clickable: bool,
+    pub expired: bool,
+    pub blink: bool,
+    /// 闪烁速度，仅在`blink`为`true`时有效。
+    pub blink_speed: BlinkSpeed,
+    pub disabled: bool,
+    pub strike_through: bool,
+    /// 斜体，对应ANSI/CSI/SGR的`3`参数。
+    pub italic: bool,
+    /// 暗淡显示，对应ANSI/CSI/SGR的`2`参数。
+    pub faint: bool,
+    /// 前景色与背景色互换，对应ANSI/CSI/SGR的`7`参数。
+    pub reverse: bool,
+    /// 隐藏文字，仍占据位置并可被划选复制，对应ANSI/CSI/SGR的`8`参数。
+    pub conceal: bool,
+    pub data_type: DataType,
+    pub image: Option<RgbImage>,
+    /// 原始宽度
+    pub image_width: i32,
+    /// 原始高度
+    pub image_height: i32,
+    /// 希望绘制的目标宽度
+    pub image_target_width: i32,
+    /// 希望绘制的目标高度
+    pub image_target_height: i32,
+    /// 图片来源地址
+    pub image_src_url: Option<String>,
+    /// 图片文件临时保存路径。
+    pub image_file_path: Option<PathBuf>,
+    pub(crate) custom_font_text: bool,
+    pub custom_font_color: bool,
+    /// 互动属性。
+    pub action: Option<Action>,
+    /// 结构标记信息，仅在数据类型为`DataType::Marker`时有效。
+    pub marker: Option<Marker>,
+    /// 分隔线信息，仅在数据类型为`DataType::Divider`时有效。
+    pub divider: Option<Divider>,
+    /// 表格信息，仅在数据类型为`DataType::Table`时有效。
+    pub table: Option<Table>,
+    /// 进度条信息，仅在数据类型为`DataType::Gauge`时有效。
+    pub gauge: Option<Gauge>,
+    /// 应用程序附加的元数据，如频道名称、发言者ID、消息ID等，不影响显示。会随数据段传递到回调函数、过滤器和导出数据中。
+    pub meta: Option<HashMap<String, String>>,
+    /// 引用的具名样式名称，样式定义变化时数据段随之更新。
+    pub style_name: Option<String>,
+    /// 有效期，超过有效期后数据段自动过期。为空时使用`RichText::set_auto_expire()`设置的默认有效期。
+    pub ttl: Option<Duration>,
+    /// 所回复的父数据段ID，为空时表示不属于任何线索。
+    pub parent_id: Option<i64>,
+    /// 由高亮规则拆分出的片段所属的原数据段ID，未被拆分的数据段为空。通过原ID更新、禁用或过期数据时作用于全部拆分片段。
+    pub origin_id: Option<i64>,
+    /// 引用层级，每一级缩进`QUOTE_INDENT_WIDTH`宽度，自动换行的后续行同样从缩进处开始。
+    pub indent_level: u8,
+    /// 引用内容左侧竖线的颜色，为空时只缩进不绘制竖线。
+    pub quote_color: Option<Color>,
+    /// 背景填充样式，仅在设置了背景色时有效。
+    pub bg_style: BackgroundStyle,
+    /// 聊天气泡样式，仅对文本数据段有效。
+    pub bubble: Option<BubbleStyle>,
+    /// 前置图标，绘制在左侧预留的区域中，自动换行的后续行同样从图标右侧开始。
+    pub icon: Option<Icon>,
+}
+
+impl Serialize for UserData {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        let mut state = serializer.serialize_struct("UserData", 48).unwrap();
+        state.serialize_field("id", &self.id).unwrap();
+        state.serialize_field("text", &self.text).unwrap();
+        state.serialize_field("font", &format!("{}({})", &self.font.get_name(), &self.font.bits())).unwrap();
+        state.serialize_field("font_size", &self.font_size).unwrap();
+        state.serialize_field("fg_color", &self.fg_color.to_hex_str()).unwrap();
+        state.serialize_field("bg_color", &self.bg_color.map(|c| c.to_hex_str())).unwrap();
+        state.serialize_field("underline", &self.underline).unwrap();
+        state.serialize_field("underline_style", &self.underline_style).unwrap();
+        state.serialize_field("underline_color", &self.underline_color.map(|c| c.to_hex_str())).unwrap();
+        state.serialize_field("fg_color_index", &self.fg_color_index).unwrap();
+        state.serialize_field("bg_color_index", &self.bg_color_index).unwrap();
+        state.serialize_field("strong", &self.strong).unwrap();
+        state.serialize_field("font_size_index", &self.font_size_index).unwrap();
+        state.serialize_field("clickable", &self.clickable).unwrap();
+        state.serialize_field("expired", &self.expired).unwrap();
+        state.serialize_field("blink", &self.blink).unwrap();
+        state.serialize_field("blink_speed", &self.blink_speed).unwrap();
+        state.serialize_field("disabled", &self.disabled).unwrap();
+        state.serialize_field("strike_through", &self.strike_through).unwrap();
+        state.serialize_field("italic", &self.italic).unwrap();
+        state.serialize_field("faint", &self.faint).unwrap();
+        state.serialize_field("reverse", &self.reverse).unwrap();
+        state.serialize_field("conceal", &self.conceal).unwrap();
+        state.serialize_field("data_type", &self.data_type).unwrap();
+        state.serialize_field("image", &self.image.as_ref().map(|_| "image")).unwrap();
+        state.serialize_field("image_width", &self.image_width).unwrap();
+        state.serialize_field("image_height", &self.image_height).unwrap();
+        state.serialize_field("image_target_width", &self.image_target_width).unwrap();
+        state.serialize_field("image_target_height", &self.image_target_height).unwrap();
+        state.serialize_field("image_src_url", &self.image_src_url).unwrap();
+        state.serialize_field("image_file_path", &self.image_file_path).unwrap();
+        state.serialize_field("custom_font_text", &self.custom_font_text).unwrap();
+        state.serialize_field("custom_font_color", &self.custom_font_color).unwrap();
+        state.serialize_field("action", &self.action.as_ref().map(|a| a)).unwrap();
+        state.serialize_field("marker", &self.marker).unwrap();
+        state.serialize_field("divider", &self.divider.as_ref().map(|d| (&d.label, d.style, d.color.to_hex_str()))).unwrap();
+        state.serialize_field("table", &self.table.as_ref().map(|t| t.row_texts())).unwrap();
+        state.serialize_field("gauge", &self.gauge.as_ref().map(|g| (g.value, g.max, g.color.to_hex_str(), &g.label, g.width))).unwrap();
+        state.serialize_field("meta", &self.meta).unwrap();
+        state.serialize_field("style_name", &self.style_name).unwrap();
+        state.serialize_field("ttl", &self.ttl).unwrap();
+        state.serialize_field("parent_id", &self.parent_id).unwrap();
+        state.serialize_field("origin_id", &self.origin_id).unwrap();
+        state.serialize_field("indent_level", &self.indent_level).unwrap();
+        state.serialize_field("quote_color", &self.quote_color.map(|c| c.to_hex_str())).unwrap();
+        state.serialize_field("bg_style", &self.bg_style.gradient().map(|(vertical, end)| (vertical, end.to_hex_str()))).unwrap();
+        state.serialize_field("bubble", &self.bubble.map(|b| (b.align, b.color.to_hex_str(), b.radius, b.padding, b.max_width_percent))).unwrap();
+        state.serialize_field("icon", &self.icon.as_ref().map(|i| i.size)).unwrap();
+        state.end()
+    }
+}
+
+/// `UserData`序列化结果的中间结构，字段格式与`Serialize`的输出一致。
+#[derive(Deserialize)]
+struct UserDataRepr {
+    id: i64,
+    text: String,
+    font: String,
+    font_size: i32,
+    fg_color: String,
+    bg_color: Option<String>,
+    underline: bool,
+    underline_style: UnderlineStyle,
+    underline_color: Option<String>,
+    fg_color_index: u8,
+    bg_color_index: u8,
+    strong: bool,
+    font_size_index: u8,
+    clickable: bool,
+    expired: bool,
+    blink: bool,
+    blink_speed: BlinkSpeed,
+    disabled: bool,
+    strike_through: bool,
+    italic: bool,
+    faint: bool,
+    reverse: bool,
+    conceal: bool,
+    data_type: DataType,
+    image_width: i32,
+    image_height: i32,
+    image_target_width: i32,
+    image_target_height: i32,
+    image_src_url: Option<String>,
+    image_file_path: Option<PathBuf>,
+    custom_font_text: bool,
+    custom_font_color: bool,
+    action: Option<Action>,
+    marker: Option<Marker>,
+    divider: Option<(Option<String>, DividerStyle, String)>,
+    table: Option<Vec<String>>,
+    gauge: Option<(f64, f64, String, Option<String>, i32)>,
+    meta: Option<HashMap<String, String>>,
+    style_name: Option<String>,
+    ttl: Option<Duration>,
+    parent_id: Option<i64>,
+    #[serde(default)]
+    origin_id: Option<i64>,
+    indent_level: u8,
+    quote_color: Option<String>,
+    bg_style: Option<(bool, String)>,
+    bubble: Option<(BubbleAlign, String, i32, i32, u8)>,
+}
+
+/// 反序列化`UserData`。图片和图标数据不在序列化结果中，需由调用方另行恢复，`read_session`会恢复会话文件中内嵌的图片和图标；表格只恢复单元格文字。
+/// 颜色无法解析时返回错误。
+impl<'de> Deserialize<'de> for UserData {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        let repr = UserDataRepr::deserialize(deserializer)?;
+        let color = |hex: &str| html::parse_color(hex).ok_or_else(|| <D::Error as serde::de::Error>::custom(format!("无法解析的颜色：{}", hex)));
+        Ok(UserData {
+            id: repr.id,
+            text: repr.text,
+            font: parse_font(&repr.font),
+            font_size: repr.font_size,
+            fg_color: color(&repr.fg_color)?,
+            bg_color: repr.bg_color.as_deref().map(color).transpose()?,
+            underline: repr.underline,
+            underline_style: repr.underline_style,
+            underline_color: repr.underline_color.as_deref().map(color).transpose()?,
+            fg_color_index: repr.fg_color_index,
+            bg_color_index: repr.bg_color_index,
+            strong: repr.strong,
+            font_size_index: repr.font_size_index,
+            clickable: repr.clickable,
+            expired: repr.expired,
+            blink: repr.blink,
+            blink_speed: repr.blink_speed,
+            disabled: repr.disabled,
+            strike_through: repr.strike_through,
+            italic: repr.italic,
+            faint: repr.faint,
+            reverse: repr.reverse,
+            conceal: repr.conceal,
+            data_type: repr.data_type,
+            image: None,
+            image_width: repr.image_width,
+            image_height: repr.image_height,
+            image_target_width: repr.image_target_width,
+            image_target_height: repr.image_target_height,
+            image_src_url: repr.image_src_url,
+            image_file_path: repr.image_file_path,
+            custom_font_text: repr.custom_font_text,
+            custom_font_color: repr.custom_font_color,
+            action: repr.action,
+            marker: repr.marker,
+            divider: repr.divider.map(|(label, style, c)| color(&c).map(|color| Divider { label, style, color })).transpose()?,
+            table: repr.table.map(|rows| Table::new(rows.iter().map(|row| row.trim_end_matches('\n').split('\t').map(TableCell::new).collect()).collect())),
+            gauge: repr.gauge.map(|(value, max, c, label, width)| color(&c).map(|color| Gauge { value, max, color, label, width })).transpose()?,
+            meta: repr.meta,
+            style_name: repr.style_name,
+            ttl: repr.ttl,
+            parent_id: repr.parent_id,
+            origin_id: repr.origin_id,
+            indent_level: repr.indent_level,
+            quote_color: repr.quote_color.as_deref().map(color).transpose()?,
+            bg_style: repr.bg_style.map(|(vertical, end)| color(&end).map(|end| BackgroundStyle::from_gradient(vertical, end))).transpose()?.unwrap_or_default(),
+            bubble: repr.bubble.map(|(align, c, radius, padding, max_width_percent)| color(&c).map(|color| BubbleStyle { align, color, radius, padding, max_width_percent })).transpose()?,
+            icon: None,
+        })
+    }
+}
+
+/// 解析`名称(序号)`格式的字体。内置字体按序号恢复，其他字体按名称查找。
+///
+/// # Arguments
+///
+/// * `font`: 序列化后的字体。
+///
+/// returns: Font
+fn parse_font(font: &str) -> Font {
+    const BUILTIN: [Font; 16] = [
+        Font::Helvetica, Font::HelveticaBold, Font::HelveticaItalic, Font::HelveticaBoldItalic,
+        Font::Courier, Font::CourierBold, Font::CourierItalic, Font::CourierBoldItalic,
+        Font::Times, Font::TimesBold, Font::TimesItalic, Font::TimesBoldItalic,
+        Font::Symbol, Font::Screen, Font::ScreenBold, Font::Zapfdingbats,
+    ];
+    let (name, bits) = match font.rfind('(') {
+        Some(pos) => (&font[..pos], font[pos + 1..].trim_end_matches(')').parse::<usize>().ok()),
+        None => (font, None),
+    };
+    match bits {
+        Some(bits) if bits < BUILTIN.len() => BUILTIN[bits],
+        _ => Font::by_name(name),
+    }
+}
+
+/// 会话文件的格式版本。
+const SESSION_FILE_VERSION: u32 = 2;
+
+/// 会话文件中内嵌保存的图片数据。
+#[derive(Serialize, Deserialize)]
+struct EmbeddedImage {
+    width: i32,
+    height: i32,
+    depth: u8,
+    bytes: Vec<u8>,
+}
+
+/// 会话文件中内嵌保存的图标数据。
+#[derive(Serialize, Deserialize)]
+struct EmbeddedIcon {
+    size: i32,
+    image: EmbeddedImage,
+}
+
+/// 会话文件中对共享图标表的引用。
+#[derive(Serialize, Deserialize)]
+struct IconRef {
+    size: i32,
+    index: usize,
+}
+
+/// 会话文件中的数据段。图片数据段有可读取的本地文件时只保存文件路径，否则内嵌保存图片数据。
+/// 图标数据保存在共享的图标表中，数据段只记录图标序号；旧版本的会话文件在数据段中内嵌保存图标。
+#[derive(Serialize, Deserialize)]
+struct SessionEntry {
+    data: UserData,
+    image: Option<EmbeddedImage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon: Option<EmbeddedIcon>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    icon_ref: Option<IconRef>,
+}
+
+/// 会话文件。
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    version: u32,
+    /// 图标表，多个数据段共用的同一个图标只保存一次。
+    #[serde(default)]
+    icons: Vec<EmbeddedImage>,
+    data: Vec<SessionEntry>,
+}
+
+/// 将数据段以JSON格式写入会话文件。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `writer`: 输出目标。
+///
+/// returns: Result<(), Error>
+pub(crate) fn write_session<W: Write>(buffer: &[RichData], writer: W) -> std::io::Result<()> {
+    // 以图标数据的地址为键去重，同一发言人的头像在整个会话中只保存一次。
+    let mut icon_index: HashMap<usize, usize> = HashMap::new();
+    let mut icons = Vec::<EmbeddedImage>::new();
+    let data = buffer.iter().map(|rd| {
+        let file_exists = rd.image_file_path.as_ref().is_some_and(|path| path.exists());
+        let image = match &rd.image {
+            Some(bytes) if !file_exists => Some(EmbeddedImage {
+                width: rd.image_width,
+                height: rd.image_height,
+                depth: rd.image_color_depth as u8,
+                bytes: bytes.to_vec(),
+            }),
+            _ => None,
+        };
+        let icon_ref = rd.icon.as_ref().map(|icon| {
+            let index = *icon_index.entry(Arc::as_ptr(&icon.data) as usize).or_insert_with(|| {
+                icons.push(EmbeddedImage {
+                    width: icon.width,
+                    height: icon.height,
+                    depth: icon.depth as u8,
+                    bytes: icon.data.to_vec(),
+                });
+                icons.len() - 1
+            });
+            IconRef { size: icon.size, index }
+        });
+        SessionEntry { data: UserData::from(rd), image, icon: None, icon_ref }
+    }).collect();
+    serde_json::to_writer(writer, &SessionFile { version: SESSION_FILE_VERSION, icons, data })?;
+    Ok(())
+}
+
+/// 读取由`RichText::save_session`保存的会话文件，恢复其中的数据段、图片和图标。
+/// 返回的数据段可通过`RichText::load_session`添加到主视图，也可通过`RichReviewer::load_page_now`以历史模式浏览。
+///
+/// # Arguments
+///
+/// * `reader`: 输入来源。
+///
+/// returns: Result<Vec<UserData, Global>, Error>
+///
+/// # Examples
+///
+/// ```
+/// use fltkrs_richdisplay::{read_session, UserData};
+///
+/// let json = r#"{"version":1,"data":[]}"#;
+/// let data: Vec<UserData> = read_session(json.as_bytes()).unwrap();
+/// assert!(data.is_empty());
+/// ```
+pub fn read_session<R: Read>(reader: R) -> std::io::Result<Vec<UserData>> {
+    let file: SessionFile = serde_json::from_reader(reader)?;
+    if file.version > SESSION_FILE_VERSION {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unsupported session file version: {}", file.version)));
+    }
+    // 图标表中的每个图标只解码一次，引用同一图标的数据段共享图标数据。
+    let icons: Vec<Option<Icon>> = file.icons.into_iter().map(|image| {
+        let depth = ColorDepth::from_u8(image.depth).ok()?;
+        Some(Icon::from_rgb_data(image.bytes, image.width, image.height, depth, 1))
+    }).collect();
+    Ok(file.data.into_iter().map(|entry| {
+        let mut data = entry.data;
+        if data.data_type == DataType::Image {
+            data.image = match entry.image {
+                Some(image) => ColorDepth::from_u8(image.depth).ok().and_then(|depth| RgbImage::new(&image.bytes, image.width, image.height, depth).ok()),
+                None => data.image_file_path.as_deref().and_then(load_rgb_image),
+            };
+            if let Some(image) = &data.image {
+                data.image_width = image.data_w();
+                data.image_height = image.data_h();
+            }
+        }
+        data.icon = match (entry.icon_ref, entry.icon) {
+            (Some(icon_ref), _) => icons.get(icon_ref.index).and_then(Option::as_ref).map(|icon| icon.with_size(icon_ref.size)),
+            (None, Some(icon)) => ColorDepth::from_u8(icon.image.depth).ok()
+                .map(|depth| Icon::from_rgb_data(icon.image.bytes, icon.image.width, icon.image.height, depth, icon.size)),
+            (None, None) => None,
+        };
+        data
+    }).collect())
+}
+
+/// 从本地文件加载图片并转换为RGB格式。
+fn load_rgb_image(path: &Path) -> Option<RgbImage> {
+    let is_svg = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    let image = if is_svg {
+        SvgImage::load(path).and_then(|mut svg| {
+            svg.normalize();
+            svg.to_rgb()
+        })
+    } else {
+        SharedImage::load(path).and_then(|image| image.to_rgb())
+    };
+    image.map_err(|e| error!("加载图片失败：{:?} {:?}", path, e)).ok()
+}
+
+impl From<&RichData> for UserData {
+    fn from(data: &RichData) -> Self {
+        Self {
+            id: data.id,
+            text: data.text.clone(),
+            font: data.font,
+            font_size: data.font_size,
+            fg_color: data.fg_color,
+            bg_color: data.bg_color.clone(),
+            underline: data.underline,
+            underline_style: data.underline_style,
+            underline_color: data.underline_color,
+            fg_color_index: data.fg_color_index,
+            bg_color_index: data.bg_color_index,
+            strong: false,
+            font_size_index: 0,
+            clickable: data.clickable,
+            expired: data.expired,
+            blink: data.blink,
+            blink_speed: data.blink_speed,
+            disabled: data.disabled,
+            strike_through: data.strike_through,
+            italic: data.italic,
+            faint: data.faint,
+            reverse: data.reverse,
+            conceal: data.conceal,
+            data_type: data.data_type.clone(),
+            image: None,
+            image_width: data.image_width,
+            image_height: data.image_height,
+            image_target_width: data.image_target_width,
+            image_target_height: data.image_target_height,
+            image_src_url: data.image_src_url.clone(),
+            image_file_path: data.image_file_path.clone(),
+            custom_font_text: false,
+            custom_font_color: false,
+            action: data.action.clone(),
+            marker: data.marker.clone(),
+            divider: data.divider.clone(),
+            table: data.table.clone(),
+            gauge: data.gauge.clone(),
+            meta: data.meta.clone(),
+            style_name: data.style_name.clone(),
+            ttl: None,
+            parent_id: data.parent_id,
+            origin_id: data.origin_id,
+            indent_level: data.indent_level,
+            quote_color: data.quote_color,
+            bg_style: data.bg_style,
+            bubble: data.bubble,
+            icon: data.icon.clone(),
+        }
+    }
+}
+
+impl UserData {
+    pub fn new_text(text: String) -> Self {
+        Self {
+            id: deterministic::next_id(),
+            text,
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Text,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: None,
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    pub fn new_text_with_id(id: i64, text: String) -> Self {
+        Self {
+            id,
+            text,
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Text,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: None,
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 创建新的图形类型的数据段。
+    /// 如果传入的图形源自`SvgImage`，则必须在调用本方法之前首先执行`SvgImage::normalize()`方法进行初始化。
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: RGB图像对象。
+    /// * `original_width`: 原始宽度。
+    /// * `original_height`: 原始高度。
+    /// * `target_width`: 目标宽度，可能与原始宽度不同。
+    /// * `target_height`: 目标高度，可能与原始高度不同。
+    /// * `src`: 图像来源地址。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::image::{SvgImage};
+    /// use fltk::prelude::ImageExt;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut svg = SvgImage::load("res/test.svg").unwrap();
+    /// svg.normalize();
+    /// let image = svg.to_rgb().unwrap();
+    /// let _data = UserData::new_image(image, 100, 100, 100, 100, Some("res/test.svg".to_string()));
+    /// ```
+    pub fn new_image(image: RgbImage, origin_width: i32, origin_height: i32, target_width: i32, target_height: i32, src: Option<String>) -> Self {
+        Self {
+            id: deterministic::next_id(),
+            text: String::new(),
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Image,
+            image: Some(image),
+            image_width: origin_width,
+            image_height: origin_height,
+            image_target_width: target_width,
+            image_target_height: target_height,
+            image_src_url: src,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: None,
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 创建不可见的结构标记数据段。标记数据段高度为零且不参与绘制，不影响其他数据段的布局，
+    /// 但会随其他数据一起保存在缓存中，并出现在回顾区的快照中。
+    ///
+    /// # Arguments
+    ///
+    /// * `kind`: 标记类型，由上层应用定义其具体含义。
+    /// * `meta`: 标记附带的元数据。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let _marker = UserData::new_marker("read".to_string(), Some("2024-01-01 12:00:00".to_string()));
+    /// ```
+    pub fn new_marker(kind: String, meta: Option<String>) -> Self {
+        Self {
+            id: deterministic::next_id(),
+            text: String::new(),
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Marker,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: Some(Marker { kind, meta }),
+            divider: None,
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 创建分隔线数据段。分隔线独占一行并横贯整个可绘制宽度，可在中间显示标签，例如区分不同的会话。
+    /// 划选复制时分隔线只保留换行，不包含标签文字。
+    ///
+    /// # Arguments
+    ///
+    /// * `label`: 居中显示的标签，为空时只绘制线条。
+    /// * `style`: 线条样式。
+    /// * `color`: 线条和标签的颜色。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{DividerStyle, UserData};
+    ///
+    /// let _divider = UserData::new_divider(Some("new session".to_string()), DividerStyle::Dashed, Color::from_rgb(128, 128, 128));
+    /// ```
+    pub fn new_divider(label: Option<String>, style: DividerStyle, color: Color) -> Self {
+        Self {
+            id: deterministic::next_id(),
+            text: "\n".to_string(),
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: color,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Divider,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: Some(Divider { label, style, color }),
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 创建表格数据段。表格从新的一行开始，每行数据独占一行，各列按照单元格内容自动对齐，不依赖等宽字体。
+    /// 划选复制时同一行的单元格以制表符分隔。
+    ///
+    /// # Arguments
+    ///
+    /// * `table`: 表格信息。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{CellAlign, Table, TableCell, UserData};
+    ///
+    /// let table = Table::new(vec![
+    ///     vec![TableCell::new("属性"), TableCell::new("数值").set_align(CellAlign::Right)],
+    ///     vec![TableCell::new("力量"), TableCell::new("18").set_align(CellAlign::Right).set_fg_color(Color::Green)],
+    /// ]).set_border_color(Color::from_rgb(96, 96, 96));
+    /// let _data = UserData::new_table(table);
+    /// ```
+    pub fn new_table(table: Table) -> Self {
+        Self {
+            id: deterministic::next_id(),
+            text: table.row_texts().concat(),
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Table,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: None,
+            table: Some(table),
+            gauge: None,
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 创建进度条数据段。进度条按照文字行高在行内排列，适合在文字中嵌入生命值、法力值等状态条，也可以在定位面板中使用。
+    /// 进度变化时可通过`RichDataOptions::gauge_value`更新，不需要重新添加数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: 当前值。
+    /// * `max`: 最大值。
+    /// * `width`: 进度条宽度，像素数。
+    /// * `color`: 已填充部分和边框的颜色。
+    /// * `label`: 显示在进度条中间的标签。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
+    ///
+    /// let hp = UserData::new_gauge(80.0, 100.0, 120, Color::Red, Some("HP".to_string()));
+    /// let _update = RichDataOptions::new(hp.id).gauge_value(65.0);
+    /// ```
+    pub fn new_gauge(value: f64, max: f64, width: i32, color: Color, label: Option<String>) -> Self {
+        let gauge = Gauge { value, max, color, label, width: width.max(1) };
+        Self {
+            id: deterministic::next_id(),
+            text: gauge.text(),
+            font: Font::Helvetica,
+            font_size: DEFAULT_FONT_SIZE,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            strong: false,
+            font_size_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            data_type: DataType::Gauge,
+            image: None,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_src_url: None,
+            image_file_path: None,
+            custom_font_text: false,
+            custom_font_color: false,
+            action: None,
+            marker: None,
+            divider: None,
+            table: None,
+            gauge: Some(gauge),
+            meta: None,
+            style_name: None,
+            ttl: None,
+            parent_id: None,
+            origin_id: None,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            icon: None,
+        }
+    }
+
+    /// 计算数据内容的哈希值，只包含影响显示效果的内容和样式，不包含数据ID，可用于检测重复发送的数据。
+    /// 图片数据不计算像素内容，只使用来源地址、文件路径和尺寸信息。哈希值使用固定算法计算，不随Rust版本和平台变化，可以持久保存。
+    ///
+    /// returns: u64
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let a = UserData::new_text("hello\n".to_string());
+    /// let b = UserData::new_text("hello\n".to_string());
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    /// ```
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::default();
+        self.data_type.hash(&mut hasher);
+        self.text.hash(&mut hasher);
+        self.font.bits().hash(&mut hasher);
+        self.font_size.hash(&mut hasher);
+        self.fg_color.bits().hash(&mut hasher);
+        self.bg_color.map(|c| c.bits()).hash(&mut hasher);
+        self.underline.hash(&mut hasher);
+        self.underline_style.hash(&mut hasher);
+        self.underline_color.map(|c| c.bits()).hash(&mut hasher);
+        self.clickable.hash(&mut hasher);
+        (self.blink, self.blink_speed).hash(&mut hasher);
+        self.strike_through.hash(&mut hasher);
+        (self.italic, self.faint, self.reverse, self.conceal).hash(&mut hasher);
+        self.image_src_url.hash(&mut hasher);
+        self.image_file_path.hash(&mut hasher);
+        (self.image_target_width, self.image_target_height).hash(&mut hasher);
+        self.parent_id.hash(&mut hasher);
+        (self.indent_level, self.quote_color.map(|c| c.bits())).hash(&mut hasher);
+        self.bg_style.gradient().map(|(vertical, end)| (vertical, end.bits())).hash(&mut hasher);
+        self.bubble.map(|b| (b.align, b.color.bits(), b.radius, b.padding, b.max_width_percent)).hash(&mut hasher);
+        self.icon.as_ref().map(|i| (i.size, i.width, i.height, i.data.as_slice())).hash(&mut hasher);
+        if let Some(marker) = &self.marker {
+            marker.kind.hash(&mut hasher);
+            marker.meta.hash(&mut hasher);
+        }
+        if let Some(divider) = &self.divider {
+            (&divider.label, divider.style, divider.color.bits()).hash(&mut hasher);
+        }
+        if let Some(gauge) = &self.gauge {
+            (gauge.color.bits(), gauge.width).hash(&mut hasher);
+        }
+        if let Some(table) = &self.table {
+            table.border_color.map(|c| c.bits()).hash(&mut hasher);
+            for cell in table.rows.iter().flatten() {
+                (cell.fg_color.map(|c| c.bits()), cell.bg_color.map(|c| c.bits()), cell.align).hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    pub fn set_font_and_size(mut self, font: Font, size: i32) -> Self {
+        self.font = font;
+        self.font_size = size;
+        self.custom_font_text = true;
+        self
+    }
+
+    /// 设置字体和大小，同时确认自定义字体标记。非流式调用接口。
+    ///
+    /// # Arguments
+    ///
+    /// * `font`:
+    /// * `size`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_font_and_size2(&mut self, font: Font, size: i32) {
+        self.font = font;
+        self.font_size = size;
+        self.custom_font_text = true;
+    }
+
+    pub fn set_font_size_index(mut self, index: u8) -> Self {
+        self.font_size_index = index;
+        self
+    }
+
+    pub fn set_fg_color(mut self, fg_color: Color) -> Self {
+        self.fg_color = fg_color;
+        self.custom_font_color = true;
+        self
+    }
+
+    pub fn set_fg_color_index(mut self, index: u8) -> Self {
+        self.fg_color_index = index;
+        self
+    }
+
+    pub fn set_bg_color(mut self, bg_color: Option<Color>) -> Self {
+        self.bg_color = bg_color;
+        self
+    }
+
+    pub fn set_bg_color_index(mut self, index: u8) -> Self {
+        self.bg_color_index = index;
+        self
+    }
+
+    pub fn set_strong(mut self, strong: bool) -> Self {
+        self.strong = strong;
+        self
+    }
+
+    pub fn set_underline(mut self, u: bool) -> Self {
+        self.underline = u;
+        self
+    }
+
+    /// 设置下划线样式和颜色，同时开启下划线。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 下划线样式。
+    /// * `color`: 下划线颜色，为空时使用前景色。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{UnderlineStyle, UserData};
+    ///
+    /// let data = UserData::new_text("mispelled".to_string()).set_underline_style(UnderlineStyle::Curly, Some(Color::Red));
+    /// assert!(data.underline);
+    /// ```
+    pub fn set_underline_style(mut self, style: UnderlineStyle, color: Option<Color>) -> Self {
+        self.underline = true;
+        self.underline_style = style;
+        self.underline_color = color;
+        self
+    }
+
+    pub fn set_italic(mut self, italic: bool) -> Self {
+        self.italic = italic;
+        self
+    }
+
+    pub fn set_faint(mut self, faint: bool) -> Self {
+        self.faint = faint;
+        self
+    }
+
+    pub fn set_reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    pub fn set_conceal(mut self, conceal: bool) -> Self {
+        self.conceal = conceal;
+        self
+    }
+
+    pub fn set_clickable(mut self, clickable: bool) -> Self {
+        self.clickable = clickable;
+        self
+    }
+
+    /// 设置数据段的有效期，超过有效期后数据段自动过期，其互动能力被禁用。
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: 有效期。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let _data = UserData::new_text("限时选项\n".to_string()).set_clickable(true).set_ttl(Duration::from_secs(30));
+    /// ```
+    pub fn set_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// 设置所回复的父数据段，回复数据在主视图中按照线索层级缩进显示，并以连接线与上级关联。
+    ///
+    /// # Arguments
+    ///
+    /// * `parent_id`: 父数据段ID。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let topic = UserData::new_text("主题\n".to_string());
+    /// let reply = UserData::new_text("回复\n".to_string()).set_parent(topic.id);
+    /// assert_eq!(reply.parent_id, Some(topic.id));
+    /// ```
+    pub fn set_parent(mut self, parent_id: i64) -> Self {
+        self.parent_id = Some(parent_id);
+        self
+    }
+
+    /// 设置引用层级和左侧竖线颜色，用于显示被引用或转发的内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: 引用层级，0表示不缩进。
+    /// * `quote_color`: 左侧竖线颜色，为空时只缩进不绘制竖线。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let quote = UserData::new_text("被引用的内容\n".to_string()).set_indent(1, Some(Color::from_rgb(128, 128, 128)));
+    /// assert_eq!(quote.indent_level, 1);
+    /// ```
+    pub fn set_indent(mut self, level: u8, quote_color: Option<Color>) -> Self {
+        self.indent_level = level;
+        self.quote_color = quote_color;
+        self
+    }
+
+    /// 设置背景填充样式，渐变从背景色开始过渡，未设置背景色时不绘制背景。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 背景填充样式。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{BackgroundStyle, UserData};
+    ///
+    /// let banner = UserData::new_text("公告\n".to_string())
+    ///     .set_bg_color(Some(Color::from_rgb(120, 0, 0)))
+    ///     .set_bg_style(BackgroundStyle::HorizontalGradient(Color::Black));
+    /// assert_eq!(banner.bg_style, BackgroundStyle::HorizontalGradient(Color::Black));
+    /// ```
+    pub fn set_bg_style(mut self, style: BackgroundStyle) -> Self {
+        self.bg_style = style;
+        self
+    }
+
+    /// 设置聊天气泡样式，数据段的所有分片绘制在按照对齐方向排列的圆角气泡中，前后内容均不与气泡处于同一行。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 气泡样式。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{BubbleAlign, BubbleStyle, UserData};
+    ///
+    /// let message = UserData::new_text("晚上一起组队吗？".to_string())
+    ///     .set_bubble(BubbleStyle::new(BubbleAlign::Right, Color::from_rgb(40, 90, 60)));
+    /// assert!(message.bubble.is_some());
+    /// ```
+    pub fn set_bubble(mut self, style: BubbleStyle) -> Self {
+        self.bubble = Some(style);
+        self
+    }
+
+    /// 设置前置图标，图标绘制在数据段首行左侧预留的区域中，自动换行的后续行与首行文字对齐。
+    /// 图标高度超过首行行高时按照行高缩小。
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: 图标影像。
+    /// * `size`: 显示尺寸，像素数。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::ColorDepth;
+    /// use fltk::image::RgbImage;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let avatar = RgbImage::new(&[255u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+    /// let message = UserData::new_text("大家好\n".to_string()).set_icon(avatar, 16);
+    /// assert_eq!(message.icon.map(|i| i.size), Some(16));
+    /// ```
+    pub fn set_icon(mut self, image: RgbImage, size: i32) -> Self {
+        self.icon = Some(Icon::new(image, size));
+        self
+    }
+
+    pub fn set_blink(mut self, blink: bool) -> Self {
+        self.blink = blink;
+        self
+    }
+
+    /// 附加一项元数据，相同键的旧值被替换。元数据不影响显示和重复检测。
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 键。
+    /// * `value`: 值。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let data = UserData::new_text("[闲聊] 张三：大家好\n".to_string()).set_meta("channel", "chat").set_meta("speaker", "zhangsan");
+    /// assert_eq!(data.meta_value("channel"), Some("chat"));
+    /// ```
+    pub fn set_meta(mut self, key: &str, value: &str) -> Self {
+        self.meta.get_or_insert_with(HashMap::new).insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// 引用具名样式。添加到组件时按照`RichText::define_style`注册的定义设置样式，样式定义变化时已缓存的数据段随之更新。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 样式名称。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let data = UserData::new_text("连接已断开\n".to_string()).with_style("error");
+    /// assert_eq!(data.style_name.as_deref(), Some("error"));
+    /// ```
+    pub fn with_style(mut self, name: &str) -> Self {
+        self.style_name = Some(name.to_string());
+        self
+    }
+
+    /// 获取元数据中指定键的值。
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 键。
+    ///
+    /// returns: Option<&str>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let data = UserData::new_text("[闲聊] 张三：大家好\n".to_string()).set_meta("channel", "chat");
+    /// assert_eq!(data.meta_value("channel"), Some("chat"));
+    /// assert_eq!(data.meta_value("speaker"), None);
+    /// ```
+    pub fn meta_value(&self, key: &str) -> Option<&str> {
+        self.meta.as_ref().and_then(|meta| meta.get(key)).map(|value| value.as_str())
+    }
+
+    /// 设置闪烁速度并开启闪烁，对应ANSI/CSI/SGR的`5`(慢速)和`6`(快速)参数。
+    ///
+    /// # Arguments
+    ///
+    /// * `speed`: 闪烁速度。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::{BlinkSpeed, UserData};
+    ///
+    /// let data = UserData::new_text("警告！\n".to_string()).set_blink_speed(BlinkSpeed::Rapid);
+    /// assert!(data.blink);
+    /// ```
+    pub fn set_blink_speed(mut self, speed: BlinkSpeed) -> Self {
+        self.blink = true;
+        self.blink_speed = speed;
+        self
+    }
+
+    pub fn set_disabled(mut self, disabled: bool) -> Self {
+        self.disabled = disabled;
+        self
+    }
+
+    /// 设置数据段互动行为。
+    ///
+    /// # Arguments
+    ///
+    /// * `action`: 互动操作。
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_action(mut self, action: Action) -> Self {
+        self.action = Some(action);
+        self.clickable = true;
+        self.underline = true;
+        self.expired = false;
+        self
+    }
+
+    /// 更改当前数据段的互动行为。
+    ///
+    /// # Arguments
+    ///
+    /// * `action`: 互动操作。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn change_action(&mut self, action: Option<Action>) {
+        if action.is_some() {
+            self.action = action;
+            self.clickable = true;
+            self.underline = true;
+            self.expired = false;
+        } else {
+            self.action = None;
+            self.clickable = false;
+            self.underline = false;
+            self.expired = true;
+        }
+    }
+
+    /// 为图片设置简短的文字描述，居中显示。
+    /// 若文字较长请使用换行符`'\n'`进行换行，避免文字超出图片边界。
+    ///
+    /// # Arguments
+    ///
+    /// * `text`:
+    ///
+    /// returns: UserData
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
+
+    pub fn set_image_file_path(mut self, path: Option<PathBuf>) -> Self {
+        self.image_file_path = path;
+        self
+    }
+}
+
+
+/// 计算两个重叠垂线居中对齐后，短线相对于长线的上端和下端的偏移量。
+///
+/// # Arguments
+///
+/// * `line_height`:
+/// * `font_height`:
+///
+/// returns: (i32, i32)
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn calc_v_center_offset(line_height: i32, font_height: i32) -> (i32, i32) {
+    let up = (line_height - font_height) / 2;
+    let down = (line_height + font_height) / 2;
+    (up, down)
+}
+
+/// 互动行为提示信息的显示状态。鼠标在带有提示的可互动数据段上停留一段时间后显示，鼠标移动或离开时隐藏。
+#[derive(Debug, Clone)]
+pub(crate) struct TooltipState {
+    /// 显示前的停留时间，为空时不显示提示信息。
+    pub(crate) delay: Option<f64>,
+    /// 预计显示提示的时间，每次鼠标移动时推后。
+    due: Option<Instant>,
+    /// 是否已有等待中的计时器。同一时间只保留一个计时器，到期时若显示时间已被推后则继续等待。
+    timer_armed: bool,
+    /// 等待显示或正在显示的提示文字，以及鼠标位置。
+    tip: Option<(String, i32, i32)>,
+    visible: bool,
+    /// 提示框的背景色、边框颜色和文字颜色。
+    colors: (Color, Color, Color),
+}
+
+impl Default for TooltipState {
+    fn default() -> Self {
+        let theme = Theme::dark();
+        Self { delay: Some(TOOLTIP_DELAY), due: None, timer_armed: false, tip: None, visible: false, colors: (theme.tooltip_background, theme.tooltip_border, theme.tooltip_text) }
+    }
+}
+
+impl TooltipState {
+    /// 鼠标移动时隐藏正在显示的提示，鼠标位于带有提示的数据段上时重新开始计时。
+    ///
+    /// # Arguments
+    ///
+    /// * `title`: 鼠标所在数据段的提示文字，不在可互动数据段上或没有提示时为空。
+    /// * `x`: 鼠标横坐标。
+    /// * `y`: 鼠标纵坐标。
+    /// * `now`: 当前时间。
+    ///
+    /// returns: (bool, Option<f64>) 是否需要重绘以隐藏提示，以及需要新建计时器时的等待秒数。
+    pub(crate) fn mouse_moved(&mut self, title: Option<&str>, x: i32, y: i32, now: Instant) -> (bool, Option<f64>) {
+        let was_visible = self.visible;
+        self.visible = false;
+        match (title, self.delay) {
+            (Some(title), Some(delay)) if !title.is_empty() => {
+                self.tip = Some((title.to_string(), x, y));
+                self.due = Some(now + Duration::from_secs_f64(delay));
+                if self.timer_armed {
+                    (was_visible, None)
+                } else {
+                    self.timer_armed = true;
+                    (was_visible, Some(delay))
+                }
+            }
+            _ => {
+                self.tip = None;
+                self.due = None;
+                (was_visible, None)
+            }
+        }
+    }
+
+    /// 计时器到期时检查是否到达显示时间。
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: 当前时间。
+    ///
+    /// returns: Result<bool, f64> 到达显示时间时结束计时，并返回是否需要重绘以显示提示；显示时间被推后时返回仍需等待的秒数。
+    pub(crate) fn timer_fired(&mut self, now: Instant) -> Result<bool, f64> {
+        if let Some(due) = self.due {
+            if due > now {
+                return Err((due - now).as_secs_f64());
+            }
+        }
+        self.timer_armed = false;
+        self.due = None;
+        if self.tip.is_some() && !self.visible {
+            self.visible = true;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// 正在显示的提示文字及其位置。
+    pub(crate) fn visible_tip(&self) -> Option<&(String, i32, i32)> {
+        if self.visible { self.tip.as_ref() } else { None }
+    }
+}
+
+/// 根据鼠标所在的数据段更新提示信息的显示状态，需要时在停留计时结束后重绘组件以显示提示。
+///
+/// # Arguments
+///
+/// * `state`: 提示信息的显示状态。
+/// * `title`: 鼠标所在数据段的提示文字。
+/// * `widget`: 显示提示信息的组件。
+///
+/// returns: ()
+pub(crate) fn track_tooltip<W: WidgetExt + Clone + 'static>(state: &Arc<RwLock<TooltipState>>, title: Option<&str>, widget: &W) {
+    let (should_hide, waiting) = state.write().mouse_moved(title, app::event_x(), app::event_y(), deterministic::now());
+    let mut widget = widget.clone();
+    if should_hide {
+        widget.redraw();
+    }
+    if let Some(delay) = waiting {
+        let state = state.clone();
+        app::add_timeout3(delay, move |handle| {
+            let fired = state.write().timer_fired(deterministic::now());
+            match fired {
+                Ok(true) => widget.redraw(),
+                Ok(false) => {}
+                // 等待期间鼠标移动过，沿用同一个计时器继续等待。
+                Err(wait) => app::repeat_timeout3(wait, handle),
+            }
+        });
+    }
+}
+
+/// 将提示文字按照最大宽度折行，原文中的换行符保留。
+///
+/// # Arguments
+///
+/// * `text`: 提示文字。
+/// * `max_width`: 每行的最大宽度。
+/// * `measure_width`: 计算文字宽度的函数。
+///
+/// returns: Vec<String, Global>
+pub(crate) fn wrap_tooltip(text: &str, max_width: i32, measure_width: impl Fn(&str) -> i32) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for c in paragraph.chars() {
+            line.push(c);
+            if measure_width(&line) > max_width && line.chars().count() > 1 {
+                line.pop();
+                // 尽量在空白处折行，没有空白时按字符折行。
+                let rest = match line.rfind(char::is_whitespace) {
+                    Some(pos) if pos > 0 => {
+                        let rest = line[pos..].trim_start().to_string();
+                        line.truncate(pos);
+                        rest
+                    }
+                    _ => String::new(),
+                };
+                lines.push(std::mem::take(&mut line));
+                line = rest;
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// 在鼠标附近绘制提示信息，并保证提示框位于可见区域之内。
+///
+/// # Arguments
+///
+/// * `state`: 提示信息的显示状态。
+/// * `bounds`: 可见区域(x, y, w, h)。
+///
+/// returns: ()
+pub(crate) fn draw_tooltip(state: &TooltipState, bounds: (i32, i32, i32, i32)) {
+    let Some((text, mouse_x, mouse_y)) = state.visible_tip() else {
+        return;
+    };
+    let font_size = TOOLTIP_FONT_SIZE;
+    set_font(Font::Helvetica, font_size);
+    let lines = wrap_tooltip(text, TOOLTIP_MAX_WIDTH, |t| measure(t, false).0);
+    let line_height = (font_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+    let w = lines.iter().map(|l| measure(l, false).0).max().unwrap_or(0) + TOOLTIP_PADDING * 2;
+    let h = line_height * lines.len() as i32 + TOOLTIP_PADDING * 2;
+    let (bx, by, bw, bh) = bounds;
+    let x = (*mouse_x + 12).min(bx + bw - w).max(bx);
+    // 下方空间不足时显示在鼠标上方。
+    let y = if *mouse_y + 20 + h > by + bh { (*mouse_y - 4 - h).max(by) } else { *mouse_y + 20 };
+    let (background, border, text_color) = state.colors;
+    set_draw_color(background);
+    draw_rectf(x, y, w, h);
+    set_draw_color(border);
+    draw_rect(x, y, w, h);
+    set_draw_color(text_color);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text_n(line, x + TOOLTIP_PADDING, y + TOOLTIP_PADDING + line_height * i as i32 + (line_height - font_size) / 2 + font_size - descent() / 2);
+    }
+}
+
+/// 解析拖放到组件上的内容。拖放文件时，内容通常是每行一个的`file://`地址或本地路径。
+///
+/// # Arguments
+///
+/// * `text`: 拖放的文字内容。
+///
+/// returns: Vec<PathBuf, Global> 拖放的文件路径。只要有一行不是本机文件就视为拖放文字，返回空列表，
+/// 主机名不是`localhost`的`file://`地址指向其他主机上的文件，同样视为文字。
+pub(crate) fn parse_dropped_files(text: &str) -> Vec<PathBuf> {
+    let mut files = vec![];
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(uri) = line.strip_prefix("file://") {
+            // 去掉主机名部分，Windows路径形如file:///C:/...
+            let (host, path) = uri.split_at(uri.find('/').unwrap_or(uri.len()));
+            if path.is_empty() || !(host.is_empty() || host.eq_ignore_ascii_case("localhost")) {
+                return vec![];
+            }
+            let path = percent_decode(path);
+            let path = if path.len() > 2 && path.as_bytes()[2] == b':' { &path[1..] } else { path.as_str() };
+            files.push(PathBuf::from(path));
+        } else if std::path::Path::new(line).is_absolute() {
+            files.push(PathBuf::from(line));
+        } else {
+            return vec![];
+        }
+    }
+    files
+}
+
+/// 解码地址中的`%XX`转义字符，无效的转义保持原样。
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() && bytes[i + 1].is_ascii_hexdigit() && bytes[i + 2].is_ascii_hexdigit() {
+            if let Ok(b) = u8::from_str_radix(&s[i + 1..i + 3], 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// 暂停输出时在主视图右上角绘制提示标记。
+///
+/// # Arguments
+///
+/// * `bounds`: 主视图区域(x, y, w, h)。
+/// * `pending`: 暂停后新增的数据段数量。
+///
+/// returns: ()
+pub(crate) fn draw_pause_indicator(bounds: (i32, i32, i32, i32), pending: usize) {
+    let label = if pending > 0 { format!("已暂停 +{}", pending) } else { "已暂停".to_string() };
+    set_font(Font::Helvetica, TOOLTIP_FONT_SIZE);
+    let (tw, _) = measure(&label, false);
+    let (w, h) = (tw + TOOLTIP_PADDING * 4, TOOLTIP_FONT_SIZE + TOOLTIP_PADDING * 2);
+    let (x, y) = (bounds.0 + bounds.2 - w - TOOLTIP_PADDING * 2, bounds.1 + TOOLTIP_PADDING * 2);
+    set_draw_color(PAUSE_INDICATOR_COLOR);
+    draw_rounded_rectf(x, y, w, h, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+    set_draw_color(get_contrast_color(PAUSE_INDICATOR_COLOR));
+    draw_text_n(&label, x + TOOLTIP_PADDING * 2, y + TOOLTIP_PADDING + TOOLTIP_FONT_SIZE - descent() / 2);
+}
+
+/// 在光标位置绘制输入行中的文字，使用与正文相同的字体和颜色，获得焦点时绘制插入点。
+///
+/// # Arguments
+///
+/// * `prompt`: 输入行。
+/// * `pos`: 输入行在窗口中的起点坐标及行高(x, y, h)。
+/// * `bounds`: 主视图区域(x, y, w, h)。
+/// * `font`: 字体。
+/// * `font_size`: 字体尺寸。
+/// * `fg_color`: 文字颜色。
+/// * `bg_color`: 背景色。
+/// * `focused`: 主视图是否获得了焦点。
+///
+/// returns: ()
+pub(crate) fn draw_prompt_line(prompt: &PromptLine, pos: (i32, i32, i32), bounds: (i32, i32, i32, i32), font: Font, font_size: i32, fg_color: Color, bg_color: Color, focused: bool) {
+    let (x, y, h) = pos;
+    draw::push_clip(bounds.0, bounds.1, bounds.2, bounds.3);
+    set_font(font, font_size);
+    let (tw, _) = measure(&prompt.text, false);
+    // 覆盖虚拟光标，避免与插入点重叠。
+    set_draw_color(bg_color);
+    draw_rectf(x, y, tw + max(font_size / 2, 4), h);
+    set_draw_color(fg_color);
+    draw_text_n(&prompt.text, x, y + h - descent());
+    if focused {
+        let prefix: String = prompt.text.chars().take(prompt.cursor).collect();
+        let (cw, _) = measure(&prefix, false);
+        draw_rectf(x + cw, y, 2, h);
+        // 将输入法的候选窗口定位到插入点。
+        draw::set_spot(font, font_size, x + cw, y + h - descent(), bounds.2, bounds.3, None::<&fltk::window::Window>);
+    }
+    draw::pop_clip();
+}
+
+/// 将回显文字拆分为回显区中的各行，去掉末尾的空行，超过`ECHO_MAX_LINES`行时只保留最后几行。
+///
+/// # Arguments
+///
+/// * `text`: 回显文字。
+///
+/// returns: Vec<String, Global>
+pub(crate) fn echo_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = text.lines().map(|line| line.trim_end_matches('\r').to_string()).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let skip = lines.len().saturating_sub(ECHO_MAX_LINES);
+    lines.split_off(skip)
+}
+
+/// 主视图顶部和底部不随输出滚动的固定行，例如连接状态或角色状态栏。
+/// 固定行使用与正文相同的排版引擎试算，但不属于数据缓存，不参与滚动、选择和查找。
+#[derive(Debug, Default)]
+pub(crate) struct PinnedRows {
+    pub(crate) top: Vec<RichData>,
+    pub(crate) bottom: Vec<RichData>,
+    top_height: i32,
+    bottom_height: i32,
+    /// 顶部固定行上方的空白。
+    top_margin: i32,
+    /// 底部固定行下方的空白。
+    bottom_margin: i32,
+}
+
+impl PinnedRows {
+    /// 按照可绘制宽度重新试算固定行，以各区域左上角为原点计算坐标。
+    ///
+    /// # Arguments
+    ///
+    /// * `text_size`: 默认字体尺寸。
+    /// * `drawable_max_width`: 可绘制的最大宽度。
+    /// * `basic_char`: 用于计算字符宽度的标准字符。
+    /// * `padding`: 用户设置的面板边界空白。
+    ///
+    /// returns: ()
+    pub(crate) fn layout(&mut self, text_size: i32, drawable_max_width: i32, basic_char: char, padding: &Padding) {
+        let origin = Padding::new(padding.left, 0, padding.right, 0);
+        self.top_height = Self::layout_rows(&mut self.top, text_size, drawable_max_width, basic_char, &origin);
+        self.bottom_height = Self::layout_rows(&mut self.bottom, text_size, drawable_max_width, basic_char, &origin);
+        self.top_margin = padding.top;
+        self.bottom_margin = padding.bottom;
+    }
+
+    fn layout_rows(rows: &mut [RichData], text_size: i32, drawable_max_width: i32, basic_char: char, origin: &Padding) -> i32 {
+        let mut last_piece = LinePiece::init_piece(text_size, origin);
+        for rd in rows.iter_mut() {
+            rd.line_pieces.clear();
+            last_piece = rd.estimate(last_piece, drawable_max_width, basic_char);
+        }
+        rows.iter().map(|rd| rd.v_bounds.read().1).max().unwrap_or(0)
+    }
+
+    /// 顶部和底部固定行占用的高度。
+    pub(crate) fn heights(&self) -> (i32, i32) {
+        (self.top_height, self.bottom_height)
+    }
+
+    /// 获取顶部和底部固定行在面板中的绘制区域(y, h)及其数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `window_height`: 面板高度。
+    ///
+    /// returns: [(i32, i32, &[RichData]); 2]
+    pub(crate) fn strips(&self, window_height: i32) -> [(i32, i32, &[RichData]); 2] {
+        [
+            (self.top_margin, self.top_height, self.top.as_slice()),
+            (window_height - self.bottom_margin - self.bottom_height, self.bottom_height, self.bottom.as_slice()),
+        ]
+    }
+}
+
+/// 在主视图底部绘制回显区，使用与正文相同的字体和颜色。
+///
+/// # Arguments
+///
+/// * `lines`: 回显区中的各行文字。
+/// * `bounds`: 回显区域(x, y, w, h)。
+/// * `padding_left`: 文字左侧空白。
+/// * `font`: 字体。
+/// * `font_size`: 字体尺寸。
+/// * `fg_color`: 文字颜色。
+/// * `bg_color`: 背景色。
+///
+/// returns: ()
+pub(crate) fn draw_echo_area(lines: &[String], bounds: (i32, i32, i32, i32), padding_left: i32, font: Font, font_size: i32, fg_color: Color, bg_color: Color) {
+    let (x, y, w, h) = bounds;
+    let line_height = h / max(lines.len() as i32, 1);
+    draw::push_clip(x, y, w, h);
+    set_draw_color(bg_color);
+    draw_rectf(x, y, w, h);
+    set_draw_color(get_lighter_or_darker_color(bg_color));
+    draw_line(x, y, x + w, y);
+    set_font(font, font_size);
+    set_draw_color(fg_color);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text_n(line, x + padding_left, y + line_height * (i as i32 + 1) - descent());
+    }
+    draw::pop_clip();
+}
+
+/// 等待分页数据时，在回顾区顶部或底部绘制加载提示行。
+///
+/// # Arguments
+///
+/// * `bounds`: 回顾区可视区域(x, y, w, h)。
+/// * `next`: 是否在等待下一页，下一页的提示绘制在底部。
+/// * `frame`: 旋转图标的帧序号。
+/// * `bg_color`: 背景色。
+/// * `fg_color`: 文字颜色。
+///
+/// returns: ()
+pub(crate) fn draw_page_spinner(bounds: (i32, i32, i32, i32), next: bool, frame: usize, bg_color: Color, fg_color: Color) {
+    const FRAMES: [&str; 4] = ["◐", "◓", "◑", "◒"];
+    let h = TOOLTIP_FONT_SIZE + TOOLTIP_PADDING * 2;
+    let y = if next { bounds.1 + bounds.3 - h } else { bounds.1 };
+    draw::draw_rect_fill(bounds.0, y, bounds.2, h, bg_color);
+    set_font(Font::Helvetica, TOOLTIP_FONT_SIZE);
+    set_draw_color(fg_color);
+    draw::draw_text2(&format!("{} 加载中...", FRAMES[frame % FRAMES.len()]), bounds.0, y, bounds.2, h, Align::Center);
+}
+
+/// 限制渲染帧率时的待合并数据队列。新数据先进入队列，每帧统一排版一次，入队操作不会阻塞数据生产方。
+#[derive(Debug)]
+pub(crate) struct AppendQueue {
+    items: VecDeque<DocEditType>,
+    capacity: usize,
+    /// 自上一帧以来因队列已满而丢弃的数据数量。
+    dropped: usize,
+    /// 是否已安排下一帧。
+    scheduled: bool,
+    last_frame: Option<Instant>,
+}
+
+impl Default for AppendQueue {
+    fn default() -> Self {
+        Self::new(APPEND_QUEUE_CAPACITY)
+    }
+}
+
+impl AppendQueue {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::new(),
+            capacity: max(capacity, 1),
+            dropped: 0,
+            scheduled: false,
+            last_frame: None,
+        }
+    }
+
+    /// 数据入队，队列已满时丢弃最早的数据段。光标移动、清屏、模式切换等控制操作不会被丢弃，只包含控制操作的队列可以超出最大长度。
+    ///
+    /// # Arguments
+    ///
+    /// * `item`: 数据或操作。
+    ///
+    /// returns: bool 尚未安排下一帧时返回true，调用方应负责安排。
+    pub(crate) fn push(&mut self, item: DocEditType) -> bool {
+        if self.items.len() >= self.capacity {
+            self.evict_oldest_data();
+        }
+        self.items.push_back(item);
+        !std::mem::replace(&mut self.scheduled, true)
+    }
+
+    /// 调整队列的最大长度，已有数据超出新长度时丢弃最早的数据段，控制操作保留。
+    pub(crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = max(capacity, 1);
+        while self.items.len() > self.capacity && self.evict_oldest_data() {}
+    }
+
+    /// 丢弃队列中最早的数据段。
+    ///
+    /// returns: bool 队列中没有数据段可丢弃时返回false。
+    fn evict_oldest_data(&mut self) -> bool {
+        match self.items.iter().position(|item| matches!(item, DocEditType::Data(_))) {
+            Some(pos) => {
+                self.items.remove(pos);
+                self.dropped += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 计算距下一帧的等待时间，保证相邻两帧的间隔不小于`1 / max_fps`秒。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_fps`: 每秒最大帧数。
+    /// * `now`: 当前时刻。
+    ///
+    /// returns: f64 等待秒数。
+    pub(crate) fn frame_delay(&self, max_fps: u32, now: Instant) -> f64 {
+        let interval = 1.0 / max(max_fps, 1) as f64;
+        match self.last_frame {
+            Some(last) => (interval - now.saturating_duration_since(last).as_secs_f64()).max(0.0),
+            None => 0.0,
+        }
+    }
+
+    /// 取出本帧要处理的全部数据及丢弃数量，并清除已安排标记。
+    ///
+    /// # Arguments
+    ///
+    /// * `now`: 当前时刻。
+    ///
+    /// returns: (Vec<DocEditType, Global>, usize)
+    pub(crate) fn take_frame(&mut self, now: Instant) -> (Vec<DocEditType>, usize) {
+        self.scheduled = false;
+        self.last_frame = Some(now);
+        (self.items.drain(..).collect(), std::mem::take(&mut self.dropped))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// 可互动数据段的悬停状态，只在悬停目标发生变化时产生回调事件。
+/// 相邻两次通知的间隔不小于`HOVER_MIN_INTERVAL`，间隔内的变化暂存起来，间隔结束后只通知最终的悬停目标。
+#[derive(Debug, Default)]
+pub(crate) struct HoverTracker {
+    current: Option<i64>,
+    /// 最近一次通知悬停变化的时刻。
+    last_change: Option<Instant>,
+    /// 防抖间隔内尚未通知的悬停目标。
+    pending: Option<Option<i64>>,
+    /// 是否已安排定时器通知暂存的悬停目标。
+    flush_scheduled: bool,
+}
+
+impl HoverTracker {
+    /// 更新悬停目标，返回应通知的事件：先离开原数据段，再进入新数据段。距离上次通知不足最小间隔时暂存目标，不产生事件。
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: 鼠标当前所在的可互动数据段ID，不在任何可互动数据段上时为空。
+    /// * `now`: 当前时刻。
+    ///
+    /// returns: Vec<CallbackData, Global>
+    pub(crate) fn update(&mut self, target: Option<i64>, now: Instant) -> Vec<CallbackData> {
+        if self.current == target {
+            self.pending = None;
+            return vec![];
+        }
+        let min_interval = Duration::from_secs_f64(HOVER_MIN_INTERVAL);
+        if self.last_change.is_some_and(|last| now.saturating_duration_since(last) < min_interval) {
+            self.pending = Some(target);
+            return vec![];
+        }
+        self.change_to(target, now)
+    }
+
+    /// 通知暂存的悬停目标，由防抖间隔结束时的定时器调用。
+    pub(crate) fn flush(&mut self, now: Instant) -> Vec<CallbackData> {
+        self.flush_scheduled = false;
+        match self.pending.take() {
+            Some(target) if target != self.current => self.change_to(target, now),
+            _ => vec![],
+        }
+    }
+
+    /// 存在暂存的悬停目标且尚未安排定时器时，返回距离防抖间隔结束的秒数。
+    pub(crate) fn take_flush_delay(&mut self, now: Instant) -> Option<f64> {
+        if self.pending.is_none() || self.flush_scheduled {
+            return None;
+        }
+        self.flush_scheduled = true;
+        let elapsed = self.last_change.map_or(HOVER_MIN_INTERVAL, |last| now.saturating_duration_since(last).as_secs_f64());
+        Some((HOVER_MIN_INTERVAL - elapsed).max(0.0))
+    }
+
+    fn change_to(&mut self, target: Option<i64>, now: Instant) -> Vec<CallbackData> {
+        let mut events = vec![];
+        if let Some(id) = self.current {
+            events.push(CallbackData::Hover { id, entered: false });
+        }
+        if let Some(id) = target {
+            events.push(CallbackData::Hover { id, entered: true });
+        }
+        self.current = target;
+        self.last_change = Some(now);
+        self.pending = None;
+        events
+    }
+}
+
+/// 更新鼠标悬停的数据段并通知回调函数，防抖间隔内的变化在间隔结束后由定时器补发通知。
+///
+/// # Arguments
+///
+/// * `state`: 悬停状态。
+/// * `target`: 鼠标当前所在的可互动数据段ID，不在任何可互动数据段上时为空。
+/// * `notifier`: 回调函数。
+///
+/// returns: ()
+pub(crate) fn track_hover(state: &Arc<RwLock<HoverTracker>>, target: Option<i64>, notifier: &Arc<RwLock<Option<Callback>>>) {
+    let now = deterministic::now();
+    let events = state.write().update(target, now);
+    notify_hover(state, events, notifier, now);
+}
+
+/// 发送悬停事件，存在暂存的悬停目标时安排定时器补发通知。
+fn notify_hover(state: &Arc<RwLock<HoverTracker>>, events: Vec<CallbackData>, notifier: &Arc<RwLock<Option<Callback>>>, now: Instant) {
+    if !events.is_empty() {
+        if let Some(cb) = notifier.write().as_mut() {
+            for event in events {
+                cb.notify(event);
+            }
+        }
+    }
+    let delay = state.write().take_flush_delay(now);
+    if let Some(delay) = delay {
+        let state = state.clone();
+        let notifier = notifier.clone();
+        app::add_timeout3(delay, move |_| {
+            let now = deterministic::now();
+            let events = state.write().flush(now);
+            notify_hover(&state, events, &notifier, now);
+        });
+    }
+}
+
+/// 检测鼠标是否进入可交互的内容区域中。
+///
+/// # Arguments
+///
+/// * `clickable_data_rc`:
+///
+/// returns: (bool, usize) 返回元组(是否进入, 数据段序号)。
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn mouse_enter(clickable_data_rc: Arc<RwLock<HashMap<Rectangle, usize>>>) -> (bool, usize) {
+    for (area, idx) in clickable_data_rc.read().iter() {
+        let (x, y, w, h) = area.tup();
+        if app::event_inside(x, y, w, h) {
+            return (true, *idx);
+        }
+    }
+    return (false, 0);
+}
+
+/// 更新指定ID的数据段及其全部拆分片段的属性。替换文本时新文本放在第一个片段中，其余片段的文本被清空。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `options`: 更新选项。
+///
+/// returns: bool 找到目标数据段时返回`true`。
+pub(crate) fn update_origin_data(buffer: &mut [RichData], options: &RichDataOptions, keywords: &[KeywordHighlight]) -> bool {
+    let range = id_range(buffer, options.id);
+    for (i, rd) in buffer[range.clone()].iter_mut().enumerate() {
+        let mut options = options.clone();
+        if i > 0 && options.text.is_some() {
+            options.text = Some(String::new());
+        }
+        update_data_properties(options, rd, keywords);
+    }
+    !range.is_empty()
+}
+
+/// 更新数据内容的属性。用于用户互动操作反馈。
+///
+/// # Arguments
+///
+/// * `options`:
+/// * `rd`:
+/// * `keywords`: 当前的关键字高亮列表，文本变化时据此重新计算高亮范围。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn update_data_properties(options: RichDataOptions, rd: &mut RichData, keywords: &[KeywordHighlight]) {
+    if let Some(clickable) = options.clickable {
+        rd.clickable = clickable;
+        if !clickable {
+            draw::set_cursor(Cursor::Default);
+        }
+    }
+    if let Some(underline) = options.underline {
+        rd.underline = underline;
+    }
+    if let Some(expired) = options.expired {
+        rd.expired = expired;
+    }
+    if let Some(text) = options.text {
+        rd.text = text;
+        rd.apply_keywords(keywords);
+    }
+    if let Some(fg_color) = options.fg_color {
+        rd.fg_color = fg_color;
+    }
+    if let Some(bg_color) = options.bg_color {
+        rd.bg_color = Some(bg_color);
+    }
+    if let Some(strike_through) = options.strike_through {
+        rd.strike_through = strike_through;
+    }
+    if let Some(blink) = options.blink {
+        rd.blink = blink;
+    }
+    if let Some(image) = options.image {
+        if let Some(image_color_depth) = options.image_color_depth {
+            rd.image_color_depth = image_color_depth;
+        }
+        if let Some(image_target_width) = options.image_target_width {
+            rd.image_target_width = image_target_width;
+        }
+        if let Some(image_target_height) = options.image_target_height {
+            rd.image_target_height = image_target_height;
+        }
+        if let Some(image_width) = options.image_width {
+            rd.image_width = image_width;
+        }
+        if let Some(image_height) = options.image_height {
+            rd.image_height = image_height;
+        }
+        rd.image.replace(Arc::new(image));
+        if rd.disabled {
+            rd.refresh_inactive_image();
+        }
+    }
+
+    if let Some(image_file_path) = options.image_file_path {
+        rd.image_file_path.replace(image_file_path);
+    }
+    if let Some(value) = options.gauge_value {
+        if let Some(gauge) = rd.gauge.as_mut() {
+            // 进度条宽度固定，更新数值后不需要重新试算，只同步分片文本。
+            gauge.value = value;
+            rd.text = gauge.text();
+            for piece in rd.line_pieces.iter() {
+                piece.write().line = rd.text.clone();
+            }
+        }
+    }
+    if let Some(action) = options.action {
+        if action.items.is_empty() {
+            rd.action = None;
+        } else {
+            rd.action.replace(action);
+        }
+    }
+
+    if let Some(disabled) = options.disabled {
+        rd.disabled = disabled;
+        if rd.disabled {
+            rd.refresh_inactive_image();
+        }
+    }
+}
+
+/// 复制一个分片，副本使用新的边界和选区，并加入对应的行信息副本中。
+fn detach_piece(piece: &Arc<RwLock<LinePiece>>, rd_bounds: &Arc<RwLock<(i32, i32, i32, i32)>>, lines: &mut HashMap<usize, Arc<RwLock<ThroughLine>>>) -> Arc<RwLock<LinePiece>> {
+    let mut lp = piece.read().clone();
+    let through_line = lines.entry(Arc::as_ptr(&lp.through_line) as usize).or_insert_with(|| {
+        let tl = &*lp.through_line.read();
+        Arc::new(RwLock::new(ThroughLine { max_h: tl.max_h, ys: vec![], exist_image: tl.exist_image }))
+    }).clone();
+    lp.through_line = through_line.clone();
+    lp.rd_bounds = rd_bounds.clone();
+    lp.selected_range = Arc::new(RwLock::new(*lp.selected_range.read()));
+    let new_piece = Arc::new(RwLock::new(lp));
+    through_line.write().add_piece(new_piece.clone());
+    new_piece
+}
+
+/// 复制数据缓存，副本与原缓存互不共享分片，重新排版任意一方都不影响另一方。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+///
+/// returns: Vec<RichData>
+pub(crate) fn detach_buffer(buffer: &[RichData]) -> Vec<RichData> {
+    let mut lines = HashMap::new();
+    buffer.iter().map(|rd| rd.detached(&mut lines)).collect()
+}
+
+/// 禁用数据段的显示样式，可为每个组件分别设置。
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisabledStyle {
+    /// 图形以灰度显示，文本增加删除线。
+    #[default]
+    Strike,
+    /// 图形和文本均以灰度显示。
+    Desaturate,
+    /// 图形和文本向背景色淡化，参数为淡化程度，取值范围为0到1。
+    Dim(f32),
+    /// 保持原有外观，仅移除互动能力。
+    None,
+}
+
+impl DisabledStyle {
+    /// 按照禁用样式调整文字颜色。
+    ///
+    /// # Arguments
+    ///
+    /// * `fg`: 前景色。
+    /// * `bg`: 背景色。
+    ///
+    /// returns: Color
+    pub(crate) fn text_color(&self, fg: Color, bg: Color) -> Color {
+        match self {
+            DisabledStyle::Desaturate => {
+                let (r, g, b) = fg.to_rgb();
+                let l = (0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32).round() as u8;
+                Color::from_rgb(l, l, l)
+            }
+            DisabledStyle::Dim(dim) => Color::color_average(fg, bg, 1.0 - dim.clamp(0.0, 1.0)),
+            DisabledStyle::Strike | DisabledStyle::None => fg,
+        }
+    }
+}
+
+/// 禁用数据内容。
+/// 显示效果由数据段的禁用样式决定，默认为图形以灰度显示，文本增加删除线。
+///
+/// # Arguments
+///
+/// * `rd`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn disable_data(rd: &mut RichData) {
+    rd.set_clickable(false);
+    draw::set_cursor(Cursor::Default);
+
+    rd.disabled = true;
+    rd.refresh_inactive_image();
+    if rd.data_type == DataType::Text && rd.disabled_style == DisabledStyle::Strike {
+        rd.strike_through = true;
+    }
+}
+
+/// 从影像中提取`RGB`数据，不会损失alpha通道数据。若传入`None`则返回一个对应大小且色深为`L8`的黑板。
+///
+/// # Arguments
+///
+/// * `rgb_image`: RGB影像。
+/// * `target_width`: 希望的影像宽度，像素数。
+/// * `target_height`: 希望的影像的高度，像素数。
+///
+/// returns: (Option<Vec<u8>>, ColorDepth, i32, i32) 返回(RGB数据, 颜色深度, 影像原宽度, 影像原高度)。
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub fn image_to_rgb_data(rgb_image: &Option<RgbImage>, target_width: i32, target_height: i32) -> (Option<Vec<u8>>, ColorDepth, i32, i32) {
+    if let Some(image) = rgb_image {
+        (Some(image.to_rgb_data()), image.depth(), image.w(), image.h())
+    } else {
+        (Some(vec![0u8; target_width as usize * target_height as usize]), ColorDepth::L8, target_width, target_height)
+    }
+}
+
+/// 依据RGB格式的图片计算其L8格式的灰度数据。
+///
+/// # Arguments
+///
+/// * `origin_rgb_data`: 原本的RGB格式的图片数据。
+///
+/// returns: Option<Vec<u8>> 灰度计算后的图片数据，格式为`L8`或`LA8`。
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub fn gray_image(rgb_data: &Vec<u8>, w: i32, h: i32, depth: ColorDepth) -> Vec<u8> {
+    let pixels = w as usize * h as usize;
+    match depth {
+        // ColorDepth::L8 => {
+        //     // 8位灰度，每个像素占据1个字节。
+        //     let la_data = origin_image.to_rgb_data();
+        //     let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
+        //     for i in 0..pixels {
+        //         let g = (la_data[i] as f32 / 2.0).floor() as u8;
+        //         gray_data.push(g);
+        //     }
+        //     Some(gray_data)
+        // }
+        // ColorDepth::La8 => {
+        //     // 8位灰度带有alpha通道，每个像素占据2个8位字节，每个像素的第二个字节表示alpha通道值。
+        //     let la_data = origin_image.to_rgb_data();
+        //     let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
+        //     for i in 0..pixels {
+        //         let j = i * 2;
+        //         let c = la_data[j];
+        //         let g = (c as f32 / 2.0).floor() as u8;
+        //         gray_data.push(g);
+        //     }
+        //     Some(gray_data)
+        // }
+
+        ColorDepth::Rgb8 => {
+            // 8位RGB色，每个像素占据3个8位字节.
+            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels);
+            for i in 0..pixels {
+                let j = i * 3;
+                let (r, g, b) = (rgb_data[j], rgb_data[j + 1], rgb_data[j + 2]);
+                let gray = (306 * r as u32 + 601 * g as u32 + 116 * b as u32) >> 10;
+                gray_data.push(gray as u8);
+            }
+            gray_data
+        }
+        ColorDepth::Rgba8 => {
+            // 8位RGB色带有alpha通道，每个像素占据4个8位字节.每个像素的第四个字节表示alpha通道值。
+            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels * 2);
+            for i in 0..pixels {
+                let j = i * 4;
+                let (r, g, b) = (rgb_data[j], rgb_data[j + 1], rgb_data[j + 2]);
+                let gray = (306 * r as u32 + 601 * g as u32 + 116 * b as u32) >> 10;
+                gray_data.push(gray as u8);
+                gray_data.push(rgb_data[j + 3]);
+            }
+            gray_data
+        }
+        ColorDepth::L8 => {
+            // 固定生成L8格式的灰色图片数据
+            vec![128u8; pixels]
+        }
+        ColorDepth::La8 => {
+            // 固定生成La8格式的灰色图片数据
+            let mut gray_data: Vec<u8> = Vec::with_capacity(pixels * 2);
+            for i in 0..pixels {
+                gray_data.push(128u8);
+                gray_data.push(rgb_data[i * 2 + 1]);
+            }
+            gray_data
+        }
+    }
+}
+
+/// 将图片数据转换为8位RGB格式，透明通道按照`bg_color`混合。
+///
+/// # Arguments
+///
+/// * `data`: 图片数据。
+/// * `depth`: 图片数据格式。
+/// * `bg_color`: 透明部分的背景色。
+///
+/// returns: Vec<u8, Global>
+pub(crate) fn to_rgb8(data: &[u8], depth: ColorDepth, bg_color: Color) -> Vec<u8> {
+    let (br, bg, bb) = bg_color.to_rgb();
+    let blend = |c: u8, b: u8, a: u8| ((c as u32 * a as u32 + b as u32 * (255 - a as u32)) / 255) as u8;
+    match depth {
+        ColorDepth::L8 => data.iter().flat_map(|l| [*l, *l, *l]).collect(),
+        ColorDepth::La8 => data.chunks_exact(2).flat_map(|p| [blend(p[0], br, p[1]), blend(p[0], bg, p[1]), blend(p[0], bb, p[1])]).collect(),
+        ColorDepth::Rgb8 => data.to_vec(),
+        ColorDepth::Rgba8 => data.chunks_exact(4).flat_map(|p| [blend(p[0], br, p[3]), blend(p[1], bg, p[3]), blend(p[2], bb, p[3])]).collect(),
+    }
+}
+
+/// deflate压缩的回溯窗口大小。
+const DEFLATE_WINDOW: usize = 32768;
+/// deflate最短匹配长度。
+const DEFLATE_MIN_MATCH: usize = 3;
+/// deflate最长匹配长度。
+const DEFLATE_MAX_MATCH: usize = 258;
+/// 查找匹配时沿哈希链回溯的最大次数。
+const DEFLATE_MAX_CHAIN: usize = 64;
+/// 长度码257~285对应的基础长度。
+const DEFLATE_LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+/// 长度码257~285的扩展位数。
+const DEFLATE_LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+/// 距离码0~29对应的基础距离。
+const DEFLATE_DIST_BASE: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+/// 距离码0~29的扩展位数。
+const DEFLATE_DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// 按PNG规范计算的CRC32查找表。
+static PNG_CRC_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut crc_table = [0u32; 256];
+    for (n, entry) in crc_table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *entry = c;
+    }
+    crc_table
+});
+
+/// 增量计算CRC32，`crc`初始值为`!0`，全部数据处理完成后取反得到校验值。
+fn png_crc(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |c, b| PNG_CRC_TABLE[((c ^ *b as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+/// 低位优先的deflate比特流。
+#[derive(Debug, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    /// 写入低`count`位。
+    fn put(&mut self, value: u32, count: u32) {
+        self.bit_buf |= value << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bit_buf as u8);
+            self.bit_buf >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// 写入高位优先的哈夫曼码。
+    fn put_code(&mut self, code: u32, count: u32) {
+        let mut reversed = 0;
+        for i in 0..count {
+            reversed |= ((code >> i) & 1) << (count - 1 - i);
+        }
+        self.put(reversed, count);
+    }
+
+    /// 按固定哈夫曼表写入字面量或长度码。
+    fn put_literal(&mut self, symbol: u16) {
+        match symbol {
+            0..=143 => self.put_code(0x30 + symbol as u32, 8),
+            144..=255 => self.put_code(0x190 + (symbol - 144) as u32, 9),
+            256..=279 => self.put_code((symbol - 256) as u32, 7),
+            _ => self.put_code(0xC0 + (symbol - 280) as u32, 8),
+        }
+    }
+
+    /// 写入一组长度和距离。
+    fn put_match(&mut self, length: usize, distance: usize) {
+        let li = DEFLATE_LENGTH_BASE.iter().rposition(|base| *base as usize <= length).unwrap_or(0);
+        self.put_literal(257 + li as u16);
+        self.put((length - DEFLATE_LENGTH_BASE[li] as usize) as u32, DEFLATE_LENGTH_EXTRA[li] as u32);
+        let di = DEFLATE_DIST_BASE.iter().rposition(|base| *base as usize <= distance).unwrap_or(0);
+        self.put_code(di as u32, 5);
+        self.put((distance - DEFLATE_DIST_BASE[di] as usize) as u32, DEFLATE_DIST_EXTRA[di] as u32);
+    }
+
+    /// 补齐到字节边界。
+    fn align(&mut self) {
+        if self.bit_count > 0 {
+            self.put(0, 8 - self.bit_count);
+        }
+    }
+}
+
+/// 逐块写入的PNG编码器。图像数据使用固定哈夫曼表的deflate压缩，每次写入的像素行单独形成一个IDAT数据块，
+/// 编码器只保留最近32KB数据作为压缩窗口，不需要一次性持有整幅图片，不依赖额外的编码库。
+pub(crate) struct PngEncoder<W: Write> {
+    out: W,
+    row_len: usize,
+    rows_left: u32,
+    /// 压缩窗口，保存最近写入的已过滤数据。
+    window: Vec<u8>,
+    bits: BitWriter,
+    adler: (u32, u32),
+}
+
+impl<W: Write> PngEncoder<W> {
+    /// 写入文件头和IHDR数据块，创建编码器。
+    ///
+    /// # Arguments
+    ///
+    /// * `out`: 输出目标。
+    /// * `width`: 图片宽度。
+    /// * `height`: 图片高度。
+    ///
+    /// returns: Result<PngEncoder<W>, Error>
+    pub fn new(mut out: W, width: u32, height: u32) -> std::io::Result<Self> {
+        out.write_all(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A])?;
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&width.to_be_bytes());
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        Self::write_chunk(&mut out, b"IHDR", &header)?;
+        let mut bits = BitWriter::default();
+        bits.bytes.extend_from_slice(&[0x78, 0x01]);
+        Ok(Self { out, row_len: width as usize * 3, rows_left: height, window: Vec::new(), bits, adler: (1, 0) })
+    }
+
+    /// 写入一块连续的像素行，超出图片高度的部分被忽略。
+    ///
+    /// # Arguments
+    ///
+    /// * `rgb`: 8位RGB格式的像素行数据。
+    ///
+    /// returns: Result<(), Error>
+    pub fn write_rows(&mut self, rgb: &[u8]) -> std::io::Result<()> {
+        let mut raw = Vec::with_capacity(rgb.len() + rgb.len() / self.row_len.max(1) + 1);
+        // 每行像素前添加过滤类型0。
+        for row in rgb.chunks_exact(self.row_len.max(1)) {
+            if self.rows_left == 0 {
+                break;
+            }
+            raw.push(0);
+            raw.extend_from_slice(row);
+            self.rows_left -= 1;
+        }
+        if raw.is_empty() {
+            return Ok(());
+        }
+        let (mut a, mut b) = self.adler;
+        for byte in &raw {
+            a = (a + *byte as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        self.adler = (a, b);
+
+        // 非最终的固定哈夫曼块。
+        self.bits.put(0b010, 3);
+        let start = self.window.len();
+        self.window.extend_from_slice(&raw);
+        Self::compress(&self.window, start, &mut self.bits);
+        self.bits.put_literal(256);
+        if self.window.len() > DEFLATE_WINDOW {
+            self.window.drain(..self.window.len() - DEFLATE_WINDOW);
+        }
+        self.flush_bits()
+    }
+
+    /// 写入最终块、校验值和IEND数据块，返回输出目标。
+    ///
+    /// returns: Result<W, Error>
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.bits.put(0b011, 3);
+        self.bits.put_literal(256);
+        self.bits.align();
+        let (a, b) = self.adler;
+        self.bits.bytes.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        self.flush_bits()?;
+        Self::write_chunk(&mut self.out, b"IEND", &[])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// 将已经凑满的字节作为一个IDAT数据块写出。
+    fn flush_bits(&mut self) -> std::io::Result<()> {
+        if self.bits.bytes.is_empty() {
+            return Ok(());
+        }
+        let bytes = std::mem::take(&mut self.bits.bytes);
+        Self::write_chunk(&mut self.out, b"IDAT", &bytes)
+    }
+
+    fn write_chunk(out: &mut W, kind: &[u8], data: &[u8]) -> std::io::Result<()> {
+        out.write_all(&(data.len() as u32).to_be_bytes())?;
+        out.write_all(kind)?;
+        out.write_all(data)?;
+        let checksum = !png_crc(png_crc(!0, kind), data);
+        out.write_all(&checksum.to_be_bytes())
+    }
+
+    /// 对`data[start..]`做LZ77匹配并写入比特流，`data[..start]`作为可回溯的历史数据。
+    fn compress(data: &[u8], start: usize, bits: &mut BitWriter) {
+        const HASH_BITS: u32 = 15;
+        let hash = |i: usize| (((data[i] as usize) << 10) ^ ((data[i + 1] as usize) << 5) ^ data[i + 2] as usize) & ((1 << HASH_BITS) - 1);
+        let mut head = vec![usize::MAX; 1 << HASH_BITS];
+        let mut prev = vec![usize::MAX; data.len()];
+        let insert = |i: usize, head: &mut [usize], prev: &mut [usize]| {
+            if i + DEFLATE_MIN_MATCH <= data.len() {
+                let h = hash(i);
+                prev[i] = head[h];
+                head[h] = i;
+            }
+        };
+        for i in 0..start {
+            insert(i, &mut head, &mut prev);
+        }
+        let mut i = start;
+        while i < data.len() {
+            let (mut best_len, mut best_dist) = (0, 0);
+            if i + DEFLATE_MIN_MATCH <= data.len() {
+                let max_len = min(DEFLATE_MAX_MATCH, data.len() - i);
+                let mut candidate = head[hash(i)];
+                let mut chain = 0;
+                while candidate != usize::MAX && i - candidate <= DEFLATE_WINDOW && chain < DEFLATE_MAX_CHAIN {
+                    let len = data[candidate..].iter().zip(&data[i..i + max_len]).take_while(|(a, b)| a == b).count();
+                    if len > best_len {
+                        (best_len, best_dist) = (len, i - candidate);
+                        if len == max_len {
+                            break;
+                        }
+                    }
+                    candidate = prev[candidate];
+                    chain += 1;
+                }
+            }
+            if best_len >= DEFLATE_MIN_MATCH {
+                bits.put_match(best_len, best_dist);
+                for j in i..i + best_len {
+                    insert(j, &mut head, &mut prev);
+                }
+                i += best_len;
+            } else {
+                bits.put_literal(data[i] as u16);
+                insert(i, &mut head, &mut prev);
+                i += 1;
+            }
+        }
+    }
+}
+
+/// 组件内部使用的数据段结构。
+#[derive(Debug, Clone)]
+pub(crate) struct RichData {
+    /// 数据ID。
+    pub id: i64,
+    pub text: String,
+    pub font: Font,
+    pub font_size: i32,
+    pub fg_color: Color,
+    pub bg_color: Option<Color>,
+    underline: bool,
+    underline_style: UnderlineStyle,
+    underline_color: Option<Color>,
+    /// ANSI调色板中的前景色序号，0表示不使用调色板。
+    fg_color_index: u8,
+    /// ANSI调色板中的背景色序号，0表示不使用调色板。
+    bg_color_index: u8,
+    clickable: bool,
+    expired: bool,
+    /// 闪烁片段列表
+    blink: bool,
+    blink_speed: BlinkSpeed,
+    disabled: bool,
+    pub strike_through: bool,
+    /// 斜体，字体已在构建时替换为对应的斜体字体。
+    pub(crate) italic: bool,
+    faint: bool,
+    reverse: bool,
+    conceal: bool,
+    pub line_height: i32,
+    /// 当前内容在面板垂直高度中的起始和截至y坐标，以及起始和结尾x坐标。
+    v_bounds: Arc<RwLock<(i32, i32, i32, i32)>>,
+
+    /// 对当前数据进行试算后，分割成适配单行宽度的分片保存起来。不支持多线程。
+    pub(crate) line_pieces: Vec<Arc<RwLock<LinePiece>>>,
+    data_type: DataType,
+    /// 格式为RGB格式(L8/LA8/RGB8/RGBA8)的图片数据。
+    /// 图片数据在主视图与回顾区之间共享，更新时整体替换而不在原数据上修改，以避免打开回顾区时复制大量图片数据。
+    image: Option<Arc<Vec<u8>>>,
+    image_color_depth: ColorDepth,
+    /// 原始宽度
+    image_width: i32,
+    /// 原始高度
+    image_height: i32,
+    /// 希望绘制的目标宽度
+    image_target_width: i32,
+    /// 希望绘制的目标高度
+    image_target_height: i32,
+    /// 禁用状态下显示的图形数据，灰度样式时色深为L8或La8，淡化样式时与原图色深相同。
+    image_inactive: Option<Arc<Vec<u8>>>,
+    /// 禁用状态的显示样式。
+    pub(crate) disabled_style: DisabledStyle,
+    /// 图片来源地址。
+    image_src_url: Option<String>,
+    image_file_path: Option<PathBuf>,
+    /// 多行片段之间的水平空白距离。
+    piece_spacing: i32,
+
+    pub(crate) search_result_positions: Option<Vec<(usize, usize)>>,
+    pub(crate) search_highlight_pos: Option<usize>,
+    /// 持久高亮关键字的起止字符位置和背景色。
+    pub(crate) keyword_ranges: Option<Vec<(usize, usize, Color)>>,
+
+    /// 互动属性。
+    pub action: Option<Action>,
+    /// 是否来自光标定位面板的数据。
+    rewrite_board_data: bool,
+    /// 数据段首字符所在的逻辑行号，从1开始。
+    pub(crate) line_no: usize,
+    /// 左侧行号栏宽度，为0时表示不显示行号。
+    pub(crate) gutter_width: i32,
+    /// 面板左侧空白宽度，行号栏和内容从此处开始。
+    pub(crate) padding_left: i32,
+    /// 自动换行的断行策略。
+    pub(crate) wrap_policy: WrapPolicy,
+    /// 自动换行产生的后续行相对于行首的缩进宽度。
+    pub(crate) hanging_indent: i32,
+    /// 线索缩进与引用缩进合计的最大宽度，试算时按照可视区域宽度确定，避免缩进占满整行。
+    pub(crate) indent_limit: i32,
+    /// 行高与字体尺寸的比例。
+    pub(crate) line_height_factor: f32,
+    /// 逻辑行结束后额外增加的段落间距。
+    pub(crate) paragraph_spacing: i32,
+    /// 结构标记信息。
+    pub marker: Option<Marker>,
+    /// 分隔线信息。
+    pub(crate) divider: Option<Divider>,
+    /// 表格信息。
+    pub(crate) table: Option<Table>,
+    /// 进度条信息。
+    pub(crate) gauge: Option<Gauge>,
+    /// 应用程序附加的元数据。
+    pub(crate) meta: Option<HashMap<String, String>>,
+    /// 引用的具名样式名称。
+    pub(crate) style_name: Option<String>,
+    /// 显示在原文下方的译文。
+    pub(crate) translation: Option<String>,
+    /// 是否显示译文。
+    pub(crate) show_translation: bool,
+    /// 译文分片，不参与点击检测、划选和查找。
+    pub(crate) translation_pieces: Vec<Arc<RwLock<LinePiece>>>,
+    /// 显示在数据段下方的链接预览。
+    pub(crate) link_preview: Option<LinkPreview>,
+    /// 所属组件的换行试算缓存，为空时不使用缓存。
+    pub(crate) wrap_cache: Option<Arc<RwLock<WrapCache>>>,
+    /// 数据段添加到组件的时刻。
+    pub(crate) arrived_at: Instant,
+    /// 链接预览卡片所占的分片，不参与划选和查找。
+    pub(crate) preview_piece: Option<Arc<RwLock<LinePiece>>>,
+    /// 淡入效果的开始时间和持续时间，为空时不显示淡入效果。
+    pub(crate) fade_in: Option<(Instant, Duration)>,
+    /// 所回复的父数据段ID。
+    pub(crate) parent_id: Option<i64>,
+    /// 高亮规则拆分出的片段所属的原数据段ID。
+    pub(crate) origin_id: Option<i64>,
+    /// 线索层级，0表示不缩进。
+    pub(crate) thread_depth: usize,
+    /// 引用层级，0表示不缩进。
+    pub(crate) indent_level: u8,
+    /// 引用内容左侧竖线的颜色。
+    pub(crate) quote_color: Option<Color>,
+    /// 背景填充样式。
+    pub(crate) bg_style: BackgroundStyle,
+    /// 聊天气泡样式。
+    pub(crate) bubble: Option<BubbleStyle>,
+    /// 试算得到的气泡区域，为(x, y, 宽度, 高度)。
+    pub(crate) bubble_rect: Option<(i32, i32, i32, i32)>,
+    /// 前置图标。
+    pub(crate) icon: Option<Icon>,
+    /// 是否因所属线索被折叠而隐藏，隐藏的数据段不产生分片。
+    pub(crate) hidden: bool,
+}
+
+impl From<UserData> for RichData {
+    fn from(data: UserData) -> Self {
+        match data.data_type {
+            DataType::Text | DataType::Marker | DataType::Divider | DataType::Table | DataType::Gauge => {
+                RichData {
+                    id: data.id,
+                    text: data.text,
+                    font: if data.italic { italic_font(data.font) } else { data.font },
+                    font_size: data.font_size,
+                    fg_color: data.fg_color,
+                    bg_color: data.bg_color,
+                    underline: data.underline,
+                    underline_style: data.underline_style,
+                    underline_color: data.underline_color,
+                    fg_color_index: data.fg_color_index,
+                    bg_color_index: data.bg_color_index,
+                    clickable: data.clickable,
+                    expired: data.expired,
+                    blink: data.blink,
+                    blink_speed: data.blink_speed,
+                    disabled: false,
+                    strike_through: data.strike_through,
+                    italic: data.italic,
+                    faint: data.faint,
+                    reverse: data.reverse,
+                    conceal: data.conceal,
+                    line_height: 1,
+                    v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
+                    line_pieces: vec![],
+                    data_type: data.data_type,
+                    image: None,
+                    image_color_depth: ColorDepth::L8,
+                    image_width: 0,
+                    image_height: 0,
+                    image_target_width: 0,
+                    image_target_height: 0,
+                    image_inactive: None,
+                    disabled_style: DisabledStyle::default(),
+                    image_src_url: None,
+                    image_file_path: None,
+                    piece_spacing: 0,
+                    search_result_positions: None,
+                    search_highlight_pos: None,
+                    keyword_ranges: None,
+                    action: data.action,
+                    rewrite_board_data: false,
+                    line_no: 0,
+                    gutter_width: 0,
+                    padding_left: PADDING.left,
+                    wrap_policy: WrapPolicy::Anywhere,
+                    hanging_indent: 0,
+                    indent_limit: i32::MAX,
+                    line_height_factor: LINE_HEIGHT_FACTOR,
+                    paragraph_spacing: 0,
+                    marker: data.marker,
+                    divider: data.divider,
+                    table: data.table,
+                    gauge: data.gauge,
+                    meta: data.meta,
+                    style_name: data.style_name,
+                    translation: None,
+                    show_translation: false,
+                    translation_pieces: Vec::with_capacity(0),
+                    link_preview: None,
+                    wrap_cache: None,
+                    arrived_at: deterministic::now(),
+                    preview_piece: None,
+                    fade_in: None,
+                    parent_id: data.parent_id,
+                    origin_id: data.origin_id,
+                    thread_depth: 0,
+                    indent_level: data.indent_level,
+                    quote_color: data.quote_color,
+                    bg_style: data.bg_style,
+                    bubble: data.bubble,
+                    bubble_rect: None,
+                    icon: data.icon.clone(),
+                    hidden: false,
+                }
+            },
+            DataType::Image => {
+                let (rgb_data, depth, image_width, image_height) = image_to_rgb_data(&data.image, data.image_target_width, data.image_target_height);
+                RichData {
+                    id: data.id,
+                    text: data.text,
+                    font: if data.italic { italic_font(data.font) } else { data.font },
+                    font_size: data.font_size,
+                    fg_color: data.fg_color,
+                    bg_color: data.bg_color,
+                    underline: data.underline,
+                    underline_style: data.underline_style,
+                    underline_color: data.underline_color,
+                    fg_color_index: data.fg_color_index,
+                    bg_color_index: data.bg_color_index,
+                    clickable: data.clickable,
+                    expired: data.expired,
+                    blink: data.blink,
+                    blink_speed: data.blink_speed,
+                    disabled: false,
+                    strike_through: data.strike_through,
+                    italic: data.italic,
+                    faint: data.faint,
+                    reverse: data.reverse,
+                    conceal: data.conceal,
+                    line_height: 1,
+                    v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
+                    line_pieces: Vec::with_capacity(0),
+                    data_type: DataType::Image,
+                    image: rgb_data.map(Arc::new),
+                    image_color_depth: depth,
+                    image_width,
+                    image_height,
+                    image_target_width: data.image_target_width,
+                    image_target_height: data.image_target_height,
+                    image_inactive: None,
+                    disabled_style: DisabledStyle::default(),
+                    image_src_url: data.image_src_url,
+                    image_file_path: data.image_file_path,
+                    piece_spacing: 0,
+                    search_result_positions: None,
+                    search_highlight_pos: None,
+                    keyword_ranges: None,
+                    action: data.action,
+                    rewrite_board_data: false,
+                    line_no: 0,
+                    gutter_width: 0,
+                    padding_left: PADDING.left,
+                    wrap_policy: WrapPolicy::Anywhere,
+                    hanging_indent: 0,
+                    indent_limit: i32::MAX,
+                    line_height_factor: LINE_HEIGHT_FACTOR,
+                    paragraph_spacing: 0,
+                    marker: data.marker,
+                    divider: data.divider,
+                    table: data.table,
+                    gauge: data.gauge,
+                    meta: data.meta,
+                    style_name: data.style_name,
+                    translation: None,
+                    show_translation: false,
+                    translation_pieces: Vec::with_capacity(0),
+                    link_preview: None,
+                    wrap_cache: None,
+                    arrived_at: deterministic::now(),
+                    preview_piece: None,
+                    fade_in: None,
+                    parent_id: data.parent_id,
+                    origin_id: data.origin_id,
+                    thread_depth: 0,
+                    indent_level: data.indent_level,
+                    quote_color: data.quote_color,
+                    bg_style: data.bg_style,
+                    bubble: data.bubble,
+                    bubble_rect: None,
+                    icon: data.icon.clone(),
+                    hidden: false,
+                }
+            }
+        }
+    }
+}
+
+impl RichData {
+    pub(crate) fn empty() -> Self {
+        RichData {
+            id: deterministic::next_id(),
+            text: String::new(),
+            font: Font::Helvetica,
+            font_size: 0,
+            fg_color: Color::White,
+            bg_color: None,
+            underline: false,
+            underline_style: UnderlineStyle::Single,
+            underline_color: None,
+            fg_color_index: 0,
+            bg_color_index: 0,
+            clickable: false,
+            expired: false,
+            blink: false,
+            blink_speed: BlinkSpeed::Slow,
+            disabled: false,
+            strike_through: false,
+            italic: false,
+            faint: false,
+            reverse: false,
+            conceal: false,
+            line_height: 1,
+            v_bounds: Arc::new(RwLock::new((0, 0, 0, 0))),
+            line_pieces: Vec::with_capacity(0),
+            data_type: DataType::Text,
+            image: None,
+            image_color_depth: ColorDepth::L8,
+            image_width: 0,
+            image_height: 0,
+            image_target_width: 0,
+            image_target_height: 0,
+            image_inactive: None,
+            disabled_style: DisabledStyle::default(),
+            image_src_url: None,
+            image_file_path: None,
+            piece_spacing: 0,
+            search_result_positions: None,
+            search_highlight_pos: None,
+            keyword_ranges: None,
+            action: None,
+            rewrite_board_data: false,
+            line_no: 0,
+            gutter_width: 0,
+            padding_left: PADDING.left,
+            wrap_policy: WrapPolicy::Anywhere,
+            hanging_indent: 0,
+            indent_limit: i32::MAX,
+            line_height_factor: LINE_HEIGHT_FACTOR,
+            paragraph_spacing: 0,
+            marker: None,
+            divider: None,
+            table: None,
+            gauge: None,
+            meta: None,
+            style_name: None,
+            translation: None,
+            show_translation: false,
+            translation_pieces: Vec::with_capacity(0),
+            link_preview: None,
+            wrap_cache: None,
+            arrived_at: deterministic::now(),
+            preview_piece: None,
+            fade_in: None,
+            parent_id: None,
+            origin_id: None,
+            thread_depth: 0,
+            indent_level: 0,
+            quote_color: None,
+            bg_style: BackgroundStyle::Solid,
+            bubble: None,
+            bubble_rect: None,
+            icon: None,
+            hidden: false,
+        }
+    }
+
+    pub(crate) fn set_piece_spacing(&mut self, piece_spacing: i32) {
+        self.piece_spacing = piece_spacing;
+    }
+
+    /// 使当前数据段拥有独立的边界信息。快照数据与来源数据共享图片等只读内容，但重新计算绘制位置时不应影响来源数据。
+    pub(crate) fn detach_bounds(&mut self) {
+        let bounds = *self.v_bounds.read();
+        self.v_bounds = Arc::new(RwLock::new(bounds));
+    }
+
+    /// 将当前数据段的绘制位置整体垂直移动。
+    ///
+    /// # Arguments
+    ///
+    /// * `dy`: 垂直移动的距离，负数表示上移。
+    ///
+    /// returns: ()
+    pub(crate) fn shift_y(&mut self, dy: i32) {
+        for piece in self.line_pieces.iter().chain(self.translation_pieces.iter()).chain(self.preview_piece.iter()) {
+            let lp = &mut *piece.write();
+            lp.y += dy;
+            lp.top_y += dy;
+            lp.next_y += dy;
+        }
+        let vb = &mut *self.v_bounds.write();
+        vb.0 += dy;
+        vb.1 += dy;
+    }
+
+    /// 获取试算后作为下一个数据段起点的分片，依次为链接预览卡片、译文和原文的最后一个分片。
+    /// 只有该分片以换行结束时才返回，气泡数据段的起点分片不被保存，同样返回空。
+    ///
+    /// returns: Option<Arc<RwLock<LinePiece>>>
+    pub(crate) fn line_start_tail(&self) -> Option<Arc<RwLock<LinePiece>>> {
+        if self.bubble_rect.is_some() {
+            return None;
+        }
+        self.preview_piece.as_ref().or(self.translation_pieces.last()).or(self.line_pieces.last())
+            .filter(|piece| piece.read().line.ends_with('\n'))
+            .cloned()
+    }
+
+    /// 计算绘制文字时实际使用的前景色和背景色，依次处理反显和暗淡属性。
+    ///
+    /// returns: (Color, Option<Color>) 前景色和背景色。
+    pub(crate) fn draw_colors(&self) -> (Color, Option<Color>) {
+        let mut fg_color = transform_color(self.fg_color, (1..=16).contains(&self.fg_color_index));
+        let mut bg_color = self.bg_color.map(|c| transform_color(c, (1..=16).contains(&self.bg_color_index)));
+        if self.reverse {
+            let new_fg = bg_color.unwrap_or(Color::Black);
+            bg_color.replace(fg_color);
+            fg_color = new_fg;
+        }
+        if self.faint {
+            // 暗淡效果：向背景色靠拢一半。
+            fg_color = Color::color_average(fg_color, bg_color.unwrap_or(Color::Black), 0.5);
+        }
+        (fg_color, bg_color)
+    }
+
+    /// 按照调色板更新数据段的前景色和背景色，未设置颜色序号的部分保持不变。
+    ///
+    /// # Arguments
+    ///
+    /// * `palette`: ANSI 16色调色板。
+    ///
+    /// returns: ()
+    pub(crate) fn apply_palette(&mut self, palette: &[Color; 16]) {
+        if (1..=16).contains(&self.fg_color_index) {
+            self.fg_color = palette[self.fg_color_index as usize - 1];
+        }
+        if (1..=16).contains(&self.bg_color_index) {
+            self.bg_color.replace(palette[self.bg_color_index as usize - 1]);
+        }
+    }
+
+    /// 按照持久高亮的关键字重新计算高亮位置，只对文本数据段生效。
+    pub(crate) fn apply_keywords(&mut self, keywords: &[KeywordHighlight]) {
+        self.keyword_ranges = if self.data_type == DataType::Text {
+            keyword_ranges(&self.text, keywords)
+        } else {
+            None
+        };
+    }
+
+    /// 转换为白底黑字的打印配色，下划线、删除线、分隔线和表格线随文字颜色变化，进度条和引用竖线使用灰色，
+    /// 表格单元格的颜色被清除，图片保持原样。
+    pub(crate) fn to_print_colors(&mut self) {
+        self.fg_color = Color::Black;
+        self.bg_color = None;
+        self.underline_color = None;
+        self.fg_color_index = 0;
+        self.bg_color_index = 0;
+        self.reverse = false;
+        self.blink = false;
+        self.faint = false;
+        if let Some(divider) = self.divider.as_mut() {
+            divider.color = Color::Black;
+        }
+        if let Some(gauge) = self.gauge.as_mut() {
+            gauge.color = PRINT_ACCENT_COLOR;
+        }
+        if self.quote_color.is_some() {
+            self.quote_color = Some(PRINT_ACCENT_COLOR);
+        }
+        if let Some(table) = self.table.as_mut() {
+            if table.border_color.is_some() {
+                table.border_color = Some(Color::Black);
+            }
+            for cell in table.rows.iter_mut().flatten() {
+                cell.fg_color = None;
+                cell.bg_color = None;
+            }
+        }
+    }
+
+    /// 计算淡入效果下的背景色，着色随时间线性减弱直至消失。
+    ///
+    /// # Arguments
+    ///
+    /// * `bg_color`: 原背景色。
+    ///
+    /// returns: Color
+    fn fade_in_color(&self, bg_color: Color) -> Color {
+        if let Some((start, duration)) = self.fade_in {
+            let elapsed = deterministic::elapsed_since(start);
+            if elapsed < duration {
+                let remain = 1.0 - elapsed.as_secs_f32() / duration.as_secs_f32();
+                return Color::color_average(FADE_IN_TINT_COLOR, bg_color, remain * FADE_IN_TINT_WEIGHT);
+            }
+        }
+        bg_color
+    }
+
+    /// 按照下划线样式绘制下划线，使用当前的绘图颜色。
+    ///
+    /// # Arguments
+    ///
+    /// * `x1`: 起始x坐标。
+    /// * `x2`: 结束x坐标。
+    /// * `line_y`: 下划线的y坐标。
+    ///
+    /// returns: ()
+    fn draw_underline(&self, x1: i32, x2: i32, line_y: i32) {
+        match self.underline_style {
+            UnderlineStyle::Single => {
+                draw_line(x1, line_y, x2, line_y);
+            }
+            UnderlineStyle::Double => {
+                draw_line(x1, line_y, x2, line_y);
+                draw_line(x1, line_y + 2, x2, line_y + 2);
+            }
+            UnderlineStyle::Dotted => {
+                set_line_style(LineStyle::Dot, 1);
+                draw_line(x1, line_y, x2, line_y);
+                set_line_style(LineStyle::Solid, 0);
+            }
+            UnderlineStyle::Curly => {
+                // 以2像素振幅的折线模拟波浪线。
+                let mut x = x1;
+                let mut up = true;
+                while x < x2 {
+                    let next_x = min(x + 2, x2);
+                    let (from_y, to_y) = if up { (line_y + 1, line_y - 1) } else { (line_y - 1, line_y + 1) };
+                    draw_line(x, from_y, next_x, to_y);
+                    x = next_x;
+                    up = !up;
+                }
+            }
+        }
+    }
+
+    /// 是否为指定ID的数据段，或由该数据段拆分出的片段。
+    pub(crate) fn has_origin(&self, id: i64) -> bool {
+        self.id == id || self.origin_id == Some(id)
+    }
+
+    /// 数据段自身或其原数据段的ID是否在指定集合中。
+    pub(crate) fn in_ids(&self, ids: &HashSet<i64>) -> bool {
+        ids.contains(&self.id) || self.origin_id.is_some_and(|id| ids.contains(&id))
+    }
+
+    /// 每一行内容的起始x坐标，已扣除行号栏宽度。
+    pub(crate) fn line_start_x(&self) -> i32 {
+        self.indent_start_x() + self.icon_gutter()
+    }
+
+    /// 前置图标区域的起始x坐标，即行号栏、线索缩进和引用缩进之后的位置。
+    fn indent_start_x(&self) -> i32 {
+        let nested = self.thread_depth as i32 * THREAD_INDENT_WIDTH + self.indent_level as i32 * QUOTE_INDENT_WIDTH;
+        self.padding_left + self.gutter_width + min(nested, self.indent_limit)
+    }
+
+    /// 前置图标占用的宽度，没有图标时为0。
+    fn icon_gutter(&self) -> i32 {
+        self.icon.as_ref().map_or(0, |icon| icon.gutter())
+    }
+
+    /// 在左侧预留区域中绘制前置图标。气泡数据段的图标贴靠气泡左侧，其他数据段仅当从行首开始时在首行绘制。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    ///
+    /// returns: ()
+    fn draw_icon(&self, offset_y: i32) {
+        let Some(icon) = &self.icon else {
+            return;
+        };
+        let (x, top_y, row_h) = if let Some((bx, by, _, bh)) = self.bubble_rect {
+            (bx - icon.gutter(), by, bh)
+        } else if let Some(first_piece) = self.line_pieces.first() {
+            let fp = &*first_piece.read();
+            if fp.x > self.line_start_x() + IMAGE_PADDING_H {
+                return;
+            }
+            (self.indent_start_x(), fp.top_y, max(fp.through_line.read().max_h, fp.h))
+        } else {
+            return;
+        };
+        let size = min(icon.size, row_h);
+        let y = top_y + (row_h - size) / 2 - offset_y;
+        icon.draw(x, y, size);
+    }
+
+    /// 在引用内容左侧的缩进区域中绘制竖线，每个分片所在的行都绘制一段，多级引用只在最内层绘制。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    ///
+    /// returns: ()
+    fn draw_quote_rule(&self, offset_y: i32) {
+        let Some(color) = self.quote_color else {
+            return;
+        };
+        if self.indent_level == 0 {
+            return;
+        }
+        let x = self.indent_start_x() - QUOTE_INDENT_WIDTH / 2 - QUOTE_RULE_WIDTH / 2;
+        set_draw_color(transform_color(color, false));
+        for piece in self.line_pieces.iter() {
+            let piece = &*piece.read();
+            let h = max(piece.through_line.read().max_h, piece.h);
+            draw_rectf(x, piece.top_y - offset_y, QUOTE_RULE_WIDTH, h);
+        }
+    }
+
+    /// 以指定颜色重绘与矩形区域相交的文字分片，用于在方块光标内以背景色显示光标处的字符。调用者负责设置裁剪区域。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 区域左上角在内容中的x坐标。
+    /// * `y`: 区域左上角在内容中的y坐标。
+    /// * `w`: 区域宽度。
+    /// * `h`: 区域高度。
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    /// * `color`: 文字颜色。
+    ///
+    /// returns: ()
+    pub(crate) fn redraw_text_in(&self, x: i32, y: i32, w: i32, h: i32, offset_y: i32, color: Color) {
+        if self.data_type != DataType::Text || self.conceal || self.hidden {
+            return;
+        }
+        let (top_y, bottom_y, _, _) = *self.v_bounds.read();
+        if bottom_y <= y || top_y >= y + h {
+            return;
+        }
+        set_font(self.font, self.font_size);
+        set_draw_color(color);
+        for piece in self.line_pieces.iter() {
+            let piece = &*piece.read();
+            if piece.x < x + w && x < piece.x + piece.w && piece.y < y + h && y < piece.y + piece.h {
+                draw_text_n(piece.line.trim_end_matches('\n'), piece.x, piece.y - offset_y + self.font_size + piece.text_offset);
+            }
+        }
+    }
+
+    /// 在回复数据段左侧绘制与上级线索关联的连接线，仅当数据段从行首开始时绘制。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    ///
+    /// returns: ()
+    fn draw_thread_connector(&self, offset_y: i32) {
+        if self.thread_depth == 0 {
+            return;
+        }
+        if let Some(first_piece) = self.line_pieces.first() {
+            let fp = &*first_piece.read();
+            if fp.x > self.line_start_x() + IMAGE_PADDING_H {
+                return;
+            }
+            // 连接线指向前置图标或首行内容。
+            let left = self.indent_start_x();
+            let x = left - THREAD_INDENT_WIDTH / 2;
+            let top_y = fp.top_y - offset_y;
+            let mid_y = fp.top_y + fp.through_line.read().max_h / 2 - offset_y;
+            set_draw_color(THREAD_CONNECTOR_COLOR);
+            draw_line(x, top_y, x, mid_y);
+            draw_line(x, mid_y, left - IMAGE_PADDING_H, mid_y);
+        }
+    }
+
+    /// 在左侧行号栏中绘制当前数据段包含的逻辑行号，自动换行产生的分片不绘制行号。
+    /// 行号不属于数据分片，因此不会参与点击检测和划选复制。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    /// * `line_start`: 当前数据段的第一个分片是否位于逻辑行首。
+    /// * `color`: 行号颜色。
+    ///
+    /// returns: ()
+    pub(crate) fn draw_line_numbers(&self, offset_y: i32, mut line_start: bool, color: Color) {
+        if self.gutter_width <= 0 {
+            return;
+        }
+        let mut line_no = self.line_no;
+        set_draw_color(color);
+        for piece in self.line_pieces.iter() {
+            let piece = &*piece.read();
+            if line_start {
+                set_font(Font::Courier, piece.font_size);
+                let num = line_no.to_string();
+                let (tw, _) = measure(num.as_str(), false);
+                let x = self.padding_left + self.gutter_width - tw - IMAGE_PADDING_H * 2;
+                draw_text_n(num.as_str(), x, piece.y - offset_y + piece.font_size + piece.text_offset);
+            }
+            line_start = piece.line.ends_with('\n');
+            if line_start {
+                line_no += 1;
+            }
+        }
+        set_font(self.font, self.font_size);
+    }
+    
+    /// 处理超宽的数据单元，自动换行。
+    ///
+    /// # Arguments
+    ///
+    /// * `text`:
+    /// * `last_piece`:
+    /// * `max_width`:
+    /// * `padding`:
+    /// * `measure_width`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn wrap_text_for_estimate(&mut self, text: &str, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, measure_width: i32, font_height: i32) -> Arc<RwLock<LinePiece>> {
+        let original = last_piece.clone();
+        let left = self.line_start_x();
+        // 自动换行产生的后续行从悬挂缩进处开始，悬挂缩进最多占用剩余宽度的一半。
+        let hanging_indent = self.hanging_indent.clamp(0, max((max_width - left) / 2, 0));
+        let indent_left = left + hanging_indent;
+        let mut last_piece = last_piece.read().clone();
+        if last_piece.next_x < left {
+            last_piece.next_x = left;
+        }
+        let x_ref = if hanging_indent > 0 && last_piece.next_x == indent_left { indent_left } else { left };
+        let tw = Rc::new(RefCell::new(0));
+        // 按字素簇折行，不拆分表情符号序列和组合字符。bounds保存各字素簇的起始字节位置，末尾附加文本长度。
+        let bounds = text.grapheme_indices(true).map(|(i, _)| i).chain(std::iter::once(text.len())).collect::<Vec<usize>>();
+        let text_len = bounds.len() - 1;
+        let (font, font_size) = (self.font, self.font_size);
+        // 前一个分片的next_x已包含片段间距，新分片从此处开始，测量时不能重复计入。
+        let x = last_piece.next_x;
+        let wrap_key = WrapKey::new(text, font, font_size, Some(max_width - x));
+        let cached = self.wrap_cache.as_ref().and_then(|cache| cache.write().get(&wrap_key));
+        let found = if let Some(WrapValue::Break(found)) = cached {
+            found
+        } else {
+            let found = (0..text_len).collect::<Vec<usize>>().binary_search_by({
+                let tw_rc = tw.clone();
+                let bounds = &bounds;
+                move |pos| {
+                    let (tw1, _) = measure(&text[..bounds[*pos]], false);
+                    if x + tw1 <= max_width {
+                        if *pos == text_len - 1 {
+                            tw_rc.replace(tw1);
+                            Ordering::Equal
+                        } else {
+                            let (tw2, _) = measure(&text[..bounds[*pos + 1]], false);
+                            if x + tw2 > max_width {
+                                tw_rc.replace(tw1);
+                                Ordering::Equal
+                            } else {
+                                Ordering::Less
+                            }
+                        }
+                    } else {
+                        Ordering::Greater
+                    }
+                }
+            }).ok().map(|stop_pos| (stop_pos, *tw.borrow()));
+            if let Some(cache) = self.wrap_cache.as_ref() {
+                cache.write().insert(wrap_key, WrapValue::Break(found));
+            }
+            found
+        };
+        let found = match found {
+            Some((stop_pos, w)) if self.wrap_policy != WrapPolicy::Anywhere => {
+                let cjk = self.wrap_policy == WrapPolicy::WordBoundaryCJK;
+                match (1..=stop_pos).rev().find(|pos| is_break_opportunity(&text[bounds[*pos - 1]..bounds[*pos]], &text[bounds[*pos]..bounds[*pos + 1]], cjk)) {
+                    Some(pos) if pos == stop_pos => Some((stop_pos, w)),
+                    Some(pos) => {
+                        set_font(font, font_size);
+                        let (tw, _) = measure(&text[..bounds[pos]], false);
+                        Some((pos, tw))
+                    }
+                    // 没有合适的断行位置时，不在行首则整体移到下一行，否则在单词内部断行。
+                    None if x_ref == left && last_piece.next_x > left => None,
+                    None => Some((stop_pos, w)),
+                }
+            }
+            _ => found,
+        };
+        let found = match found {
+            // 行首连一个字素簇都放不下时，至少放置一个字素簇，保证每次换行都有进展。
+            Some((0, _)) | None if last_piece.next_x <= x_ref && text_len > 0 => {
+                set_font(font, font_size);
+                let (w, _) = measure(&text[..bounds[1]], false);
+                Some((1, w))
+            }
+            _ => found,
+        };
+        if let Some((stop_pos, w)) = found {
+            // 出现超宽
+            // 换行处理
+            let next_x = indent_left;
+            let through_line = ThroughLine::create_or_update(x_ref, last_piece.next_x, font_height, original.clone(), false);
+            let line_max_h = through_line.read().max_h;
+            let max_h = max(line_max_h, font_height);
+            let mut next_y = last_piece.next_y + max_h + last_piece.spacing;
+            if through_line.read().exist_image {
+                next_y += IMAGE_PADDING_V * 2;
+            }
+
+            let y = last_piece.next_y;
+            let top_y = last_piece.next_y;
+            let new_piece = LinePiece::new(text[..bounds[stop_pos]].to_string(), last_piece.next_x, y, w, font_height, top_y, last_piece.spacing, next_x, next_y, font_height, font, font_size,  through_line.clone(), self.v_bounds.clone());
+            self.line_pieces.push(new_piece.clone());
+
+            let rest_str = text[bounds[stop_pos]..].to_string();
+            if rest_str.is_empty() {
+                return new_piece;
+            }
+            let rest_width = measure_width - w;
+
+            if next_x + rest_width > max_width {
+                // 剩余部分的宽度仍然大于一整行宽度
+                self.wrap_text_for_estimate(rest_str.as_str(), new_piece.clone(), max_width, rest_width, font_height)
+            } else {
+                let rest_x = next_x;
+                let rest_y = next_y;
+                let top_y = next_y;
+                let mut rest_next_x = rest_x + rest_width + self.piece_spacing;
+                let mut rest_next_y = next_y;
+                if rest_str.ends_with("\n") {
+                    rest_next_x = left;
+                    rest_next_y += font_height + last_piece.spacing + self.paragraph_spacing;
+                }
+
+                let through_line = ThroughLine::create_or_update(indent_left, rest_x, font_height, original.clone(), false);
+                let new_piece = LinePiece::new(rest_str, rest_x, rest_y, rest_width, font_height, top_y, last_piece.spacing, rest_next_x, rest_next_y, font_height, font, font_size, through_line, self.v_bounds.clone());
+                self.line_pieces.push(new_piece.clone());
+                new_piece
+            }
+        } else {
+            // 从行首开始
+            let through_line = ThroughLine::create_or_update(indent_left, indent_left, self.line_height, original.clone(), false);
+            let y = last_piece.next_y + last_piece.through_line.read().max_h + last_piece.spacing;
+            let new_piece = LinePiece::new(text.to_string(), indent_left, y, measure_width, self.line_height, y, last_piece.spacing, indent_left, y, font_height, font, font_size, through_line, self.v_bounds.clone());
+            self.wrap_text_for_estimate(text, new_piece, max_width, measure_width, font_height)
+        }
+    }
+
+    /// 按照气泡样式试算数据段的绘制位置。气泡总是从新的一行开始，内容先在缩小的宽度内正常排列，
+    /// 再整体移动到气泡内，气泡结束后强制换行。
+    ///
+    /// # Arguments
+    ///
+    /// * `bubble`: 气泡样式。
+    /// * `last_piece`: 前一个数据段的最后一个分片。
+    /// * `max_width`: 可视区域最大宽度。
+    /// * `basic_char`: 用于计算行高的基本字符。
+    ///
+    /// returns: Arc<RwLock<LinePiece>> 位于气泡下方行首的占位分片。
+    fn estimate_bubble(&mut self, bubble: BubbleStyle, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, basic_char: char) -> Arc<RwLock<LinePiece>> {
+        let left = self.line_start_x();
+        let padding = bubble.padding.max(0);
+        let (top_y, spacing) = {
+            let lp = &*last_piece.read();
+            let top_y = if lp.next_x > left && !lp.line.ends_with('\n') { lp.next_y + lp.through_line.read().max_h } else { lp.next_y };
+            (top_y, lp.spacing)
+        };
+
+        // 以行首占位分片为起点，在气泡内宽度中排列内容。
+        let anchor = LinePiece::new("\n".to_string(), left, top_y, 0, 0, top_y, spacing, left, top_y + padding, 0, self.font, self.font_size, ThroughLine::new(0, false), Arc::new(RwLock::new((0, 0, 0, 0))));
+        self.bubble = None;
+        self.estimate(anchor, left + bubble.inner_width(left, max_width), basic_char);
+        self.bubble = Some(bubble);
+
+        let pieces: Vec<Arc<RwLock<LinePiece>>> = self.line_pieces.iter().chain(self.translation_pieces.iter()).chain(self.preview_piece.iter()).cloned().collect();
+        if pieces.is_empty() {
+            self.bubble_rect = None;
+            return last_piece;
+        }
+        let (mut content_left, mut content_right, mut content_bottom) = (i32::MAX, i32::MIN, top_y + padding);
+        for piece in pieces.iter() {
+            let p = &*piece.read();
+            content_left = min(content_left, p.x);
+            content_right = max(content_right, p.x + p.w);
+            content_bottom = max(content_bottom, p.top_y + max(p.h, p.through_line.read().max_h));
+        }
+        let offset_x = bubble.offset_x(content_left, content_right, left, max_width);
+        for piece in pieces.iter() {
+            let p = &mut *piece.write();
+            p.x += offset_x;
+            p.next_x += offset_x;
+        }
+
+        let (x, w, h) = (content_left + offset_x - padding, content_right - content_left + padding * 2, content_bottom + padding - top_y);
+        self.bubble_rect = Some((x, top_y, w, h));
+        self.set_v_bounds(top_y, top_y + h, x, x + w);
+        let bottom_y = top_y + h;
+        LinePiece::new("\n".to_string(), left, bottom_y, 0, 0, bottom_y, spacing, left, bottom_y + BUBBLE_MARGIN_V, 0, self.font, self.font_size, ThroughLine::new(0, false), self.v_bounds.clone())
+    }
+
+    /// 绘制气泡背景。
+    ///
+    /// # Arguments
+    ///
+    /// * `offset_y`: 面板滚动的y轴偏移量。
+    ///
+    /// returns: ()
+    fn draw_bubble(&self, offset_y: i32) {
+        if let (Some(bubble), Some((x, y, w, h))) = (self.bubble, self.bubble_rect) {
+            set_draw_color(self.fade_in_color(transform_color(bubble.color, false)));
+            draw_rounded_rectf(x, y - offset_y, w, h, bubble.radius.clamp(0, min(w, h) / 2));
+        }
+    }
+
+    /// 在当前数据段下方试算译文的绘制位置。译文使用较小的斜体字，总是从新的一行开始，超宽时自动换行，
+    /// 译文结束后强制换行，后续数据从译文下方开始绘制。
+    ///
+    /// # Arguments
+    ///
+    /// * `translation`: 译文。
+    /// * `last_piece`: 原文的最后一个分片。
+    /// * `max_width`: 可视区域最大宽度。
+    ///
+    /// returns: Arc<RwLock<LinePiece>> 译文的最后一个分片。
+    fn estimate_translation(&mut self, translation: &str, last_piece: Arc<RwLock<LinePiece>>, max_width: i32) -> Arc<RwLock<LinePiece>> {
+        let left = self.line_start_x();
+        let mut y = {
+            let lp = &*last_piece.read();
+            if lp.line.ends_with('\n') {
+                lp.next_y
+            } else {
+                lp.top_y + lp.through_line.read().max_h
+            }
+        };
+        let font = italic_font(self.font);
+        let font_size = max((self.font_size as f32 * TRANSLATION_FONT_SCALE).round() as i32, 1);
+        let line_height = (font_size as f32 * self.line_height_factor).ceil() as i32;
+        set_font(font, font_size);
+
+        let mut lines: Vec<String> = vec![];
+        let mut current = String::new();
+        for g in translation.replace('\r', "").graphemes(true) {
+            if g == "\n" {
+                lines.push(std::mem::take(&mut current));
+                continue;
+            }
+            current.push_str(g);
+            let (tw, _) = measure(&current, false);
+            if left + tw > max_width && current.len() > g.len() {
+                current.truncate(current.len() - g.len());
+                lines.push(std::mem::take(&mut current));
+                current.push_str(g);
+            }
+        }
+        if !current.is_empty() || lines.is_empty() {
+            lines.push(current);
+        }
+
+        let mut ret = last_piece;
+        for line in lines {
+            let (tw, _) = measure(&line, false);
+            let next_y = y + line_height;
+            let through_line = ThroughLine::new(line_height, false);
+            let new_piece = LinePiece::new(format!("{}\n", line), left, y, tw, line_height, y, 0, left, next_y, line_height, font, font_size, through_line, self.v_bounds.clone());
+            new_piece.write().calc_offset();
+            self.translation_pieces.push(new_piece.clone());
+            ret = new_piece;
+            y = next_y;
+        }
+        self.v_bounds.write().1 = y;
+        ret
+    }
+
+    /// 在当前数据段下方试算链接预览卡片的位置。卡片总是从新的一行开始，宽度不超过可视区域，后续数据从卡片下方开始绘制。
+    ///
+    /// # Arguments
+    ///
+    /// * `title`: 卡片中显示的标题。
+    /// * `has_icon`: 是否显示网站图标。
+    /// * `last_piece`: 当前数据段的最后一个分片。
+    /// * `max_width`: 可视区域最大宽度。
+    ///
+    /// returns: Arc<RwLock<LinePiece>> 卡片分片。
+    fn estimate_link_preview(&mut self, title: &str, has_icon: bool, last_piece: Arc<RwLock<LinePiece>>, max_width: i32) -> Arc<RwLock<LinePiece>> {
+        let left = self.line_start_x();
+        let y = {
+            let lp = &*last_piece.read();
+            if lp.line.ends_with('\n') {
+                lp.next_y
+            } else {
+                lp.top_y + lp.through_line.read().max_h
+            }
+        };
+        let font_size = max((self.font_size as f32 * TRANSLATION_FONT_SCALE).round() as i32, 1);
+        let line_height = (font_size as f32 * self.line_height_factor).ceil() as i32;
+        set_font(self.font, font_size);
+        let (tw, _) = measure(title, false);
+
+        let icon_width = if has_icon { LINK_PREVIEW_ICON_SIZE + LINK_PREVIEW_PADDING } else { 0 };
+        let w = min(LINK_PREVIEW_PADDING * 2 + icon_width + tw, max(max_width - left, 1));
+        let h = max(line_height, LINK_PREVIEW_ICON_SIZE) + LINK_PREVIEW_PADDING * 2;
+        let next_y = y + h + LINK_PREVIEW_PADDING;
+        let through_line = ThroughLine::new(h, false);
+        let piece = LinePiece::new(format!("{}\n", title), left, y, w, h, y, 0, left, next_y, line_height, self.font, font_size, through_line, self.v_bounds.clone());
+        piece.write().calc_offset();
+        self.preview_piece = Some(piece.clone());
+        self.v_bounds.write().1 = next_y;
+        piece
+    }
+
+    /// 位于指定位置的图片可拖出的内容，优先使用图片文件路径，没有文件路径时使用图片地址。
+    pub(crate) fn image_drag_payload_at(&self, x: i32, y: i32) -> Option<String> {
+        if self.data_type != DataType::Image {
+            return None;
+        }
+        let (top_y, bottom_y, start_x, end_x) = *self.v_bounds.read();
+        if x < start_x || x >= end_x || y < top_y || y >= bottom_y {
+            return None;
+        }
+        self.image_file_path.as_ref().map(|path| path.to_string_lossy().to_string()).or_else(|| self.image_src_url.clone())
+    }
+
+    /// 获取数据段所有分片绘制区域的外接矩形，包括译文和链接预览卡片，坐标相对于数据内容顶部。
+    ///
+    /// returns: Option<(i32, i32, i32, i32)> 外接矩形的x/y/w/h，数据段没有分片时返回空。
+    pub(crate) fn piece_bounds(&self) -> Option<(i32, i32, i32, i32)> {
+        let pieces = self.line_pieces.iter().chain(self.translation_pieces.iter()).chain(self.preview_piece.iter());
+        let (left, top, right, bottom) = pieces.fold((i32::MAX, i32::MAX, i32::MIN, i32::MIN), |(l, t, r, b), piece| {
+            let piece = &*piece.read();
+            (min(l, piece.x), min(t, piece.y), max(r, piece.x + piece.w), max(b, piece.y + piece.h))
+        });
+        if left > right {
+            return None;
+        }
+        Some((left, top, right - left, bottom - top))
+    }
+
+    /// 按照禁用样式重新生成禁用状态下显示的图形数据。
+    pub(crate) fn refresh_inactive_image(&mut self) {
+        if self.data_type != DataType::Image {
+            return;
+        }
+        self.image_inactive = self.image.as_ref().and_then(|rgb_data| match self.disabled_style {
+            DisabledStyle::Strike | DisabledStyle::Desaturate => Some(Arc::new(gray_image(rgb_data, self.image_width, self.image_height, self.image_color_depth))),
+            DisabledStyle::Dim(dim) => {
+                let mut data = rgb_data.to_vec();
+                dim_image_data(&mut data, self.image_color_depth, self.bg_color.unwrap_or(Color::Black), dim);
+                Some(Arc::new(data))
+            }
+            DisabledStyle::None => None,
+        });
+    }
+
+    /// 丢弃图标和链接预览网站图标缩放后的影像缓存。
+    pub(crate) fn clear_image_cache(&self) {
+        if let Some(icon) = &self.icon {
+            icon.clear_cache();
+        }
+        if let Some(favicon) = self.link_preview.as_ref().and_then(|preview| preview.favicon.as_ref()) {
+            favicon.clear_cache();
+        }
+    }
+
+    /// 更改禁用样式，已禁用的数据段按照新样式显示。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 禁用样式。
+    ///
+    /// returns: ()
+    pub(crate) fn set_disabled_style(&mut self, style: DisabledStyle) {
+        if self.disabled_style == style {
+            return;
+        }
+        if self.disabled && self.data_type == DataType::Text {
+            // 删除线由禁用样式产生时随样式切换。
+            if self.disabled_style == DisabledStyle::Strike {
+                self.strike_through = false;
+            }
+            if style == DisabledStyle::Strike {
+                self.strike_through = true;
+            }
+        }
+        self.disabled_style = style;
+        if self.disabled {
+            self.refresh_inactive_image();
+        }
+    }
+
+    /// 创建与当前数据段互不共享分片的副本。克隆`RichData`时分片和边界仍由原数据段共享，重新排版会同时改变副本，
+    /// 因此需要独立保存的场合应使用此方法。
+    ///
+    /// # Arguments
+    ///
+    /// * `lines`: 已复制的行信息，以原行信息的地址为键，使同一行中的分片在副本中仍共享同一个行信息。
+    ///
+    /// returns: RichData
+    pub(crate) fn detached(&self, lines: &mut HashMap<usize, Arc<RwLock<ThroughLine>>>) -> RichData {
+        let mut rd = self.clone();
+        rd.v_bounds = Arc::new(RwLock::new(*self.v_bounds.read()));
+        rd.line_pieces = self.line_pieces.iter().map(|piece| detach_piece(piece, &rd.v_bounds, lines)).collect();
+        rd.translation_pieces = self.translation_pieces.iter().map(|piece| detach_piece(piece, &rd.v_bounds, lines)).collect();
+        rd.preview_piece = self.preview_piece.as_ref().map(|piece| detach_piece(piece, &rd.v_bounds, lines));
+        rd
+    }
+
+    /// 获取指定位置处链接预览卡片对应的网址。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 内容坐标系中的横坐标。
+    /// * `y`: 内容坐标系中的纵坐标。
+    ///
+    /// returns: Option<String>
+    pub(crate) fn preview_url_at(&self, x: i32, y: i32) -> Option<String> {
+        let preview = self.link_preview.as_ref()?;
+        let piece = &*self.preview_piece.as_ref()?.read();
+        if x >= piece.x && x < piece.x + piece.w && y >= piece.y && y < piece.y + piece.h {
+            Some(preview.url.clone())
+        } else {
+            None
+        }
+    }
+
+}
+
+
+impl LinedData for RichData {
+    fn set_v_bounds(&mut self, top_y: i32, bottom_y: i32, start_x: i32, end_x: i32,) {
+        *self.v_bounds.write() = (top_y, bottom_y, start_x, end_x);
+    }
+
+    fn is_text_data(&self) -> bool {
+        self.image.is_none()
+    }
+
+    fn clickable(&self) -> bool {
+        self.clickable && !self.expired
+    }
+
+    fn set_clickable(&mut self, clickable: bool) {
+        self.clickable = clickable;
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expired
+    }
+
+    fn set_expire(&mut self, expire: bool) {
+        self.expired = expire;
+    }
+
+    fn set_text_data(&mut self, text_data: &str) {
+        self.text.clear();
+        self.text.push_str(text_data);
+    }
+
+    fn set_binary_data(&mut self, _: Vec<u8>) {}
+
+    fn is_visible(&self, top_y: i32, bottom_y: i32) -> bool {
+        let b = *self.v_bounds.read();
+        !(b.1 < top_y || b.0 > bottom_y)
+    }
+
+    fn draw(&self, offset_y: i32, blink_state: &BlinkState, options: &RenderOptions) {
+        self.draw_bubble(offset_y);
+        self.draw_icon(offset_y);
+        self.draw_thread_connector(offset_y);
+        self.draw_quote_rule(offset_y);
+        match self.data_type {
+            DataType::Text => {
+                let mut processed_search_len = 0usize;
+                let mut processed_keyword_len = 0usize;
+                let (fg_color, bg_color) = self.draw_colors();
+                let fg_color = options.legible(fg_color, bg_color);
+                let fg_color = if self.disabled { self.disabled_style.text_color(fg_color, bg_color.unwrap_or(options.background)) } else { fg_color };
+                set_font(self.font, self.font_size);
+                for piece in self.line_pieces.iter() {
+                    let piece = &*piece.read();
+                    let piece_start = processed_keyword_len;
+                    processed_keyword_len += piece.line.chars().count();
+                    let text = piece.line.trim_end_matches('\n');
+                    if text.is_empty() {
+                        continue;
+                    }
+
+                    let y = piece.y - offset_y;
+
+                    if let (true, Some(color)) = (piece.soft_wrapped, blink_state.wrap_marker) {
+                        // 在续行左侧的空白中绘制一个小的折行标记。
+                        let (mark_x, mark_y) = (piece.x - WRAP_MARKER_SIZE - 1, y + piece.bg_offset + piece.font_height / 2);
+                        set_draw_color(transform_color(color, false));
+                        draw_line(mark_x, mark_y - WRAP_MARKER_SIZE, mark_x, mark_y);
+                        draw_line(mark_x, mark_y, mark_x + WRAP_MARKER_SIZE, mark_y);
+                    }
+
+                    if !self.blink || blink_state.degree(self.blink_speed) == BlinkDegree::Normal {
+                        if let Some(bg_color) = &bg_color {
+                            // 绘制文字背景色
+                            // debug!("绘制文字背景色: {}", bg_color.to_hex_str());
+                            let style = if self.reverse {
+                                BackgroundStyle::Solid
+                            } else {
+                                self.bg_style.map_color(|end| self.fade_in_color(transform_color(end, false)))
+                            };
+                            style.fill(piece.x, y - piece.spacing + piece.bg_offset, piece.w, piece.font_height, self.fade_in_color(*bg_color));
+                        }
+                    }
+
+                    if let Some(ref ranges) = self.keyword_ranges {
+                        // 绘制关键字高亮背景色
+                        for (from, to, color) in ranges.iter() {
+                            let (from, to) = (max(*from, piece_start), min(*to, processed_keyword_len));
+                            if from < to {
+                                let (skip_width, _) = measure(piece.line.chars().take(from - piece_start).collect::<String>().as_str(), false);
+                                let (fill_width, _) = measure(piece.line.chars().skip(from - piece_start).take(to - from).collect::<String>().as_str(), false);
+                                set_draw_color(transform_color(*color, false));
+                                draw_rectf(piece.x + skip_width, y - piece.spacing + piece.bg_offset, fill_width, piece.font_height);
+                            }
+                        }
+                    }
+
+                    let mut selected_span: Option<(i32, i32)> = None;
+                    if let Some((from, to)) = *piece.selected_range.read() {
+                        // 绘制选中背景色
+                        let sel_color = if let Some(bg_color) = &bg_color {
+                            if *bg_color == Color::Blue || *bg_color == Color::DarkBlue || *bg_color == blink_state.selection_color {
+                                Color::DarkMagenta
+                            } else {
+                                blink_state.selection_color
+                            }
+                        } else {
+                            blink_state.selection_color
+                        };
+                        set_draw_color(transform_color(sel_color, false));
+                        let (skip_width, _) = measure(piece.line.chars().take(from).collect::<String>().as_str(), false);
+                        let (fill_width, _) = measure(piece.line.chars().skip(from).take(max(to, from) - from).collect::<String>().as_str(), false);
+
+                        draw_rectf(piece.x + skip_width, y + piece.bg_offset, fill_width, piece.font_height);
+                        selected_span = Some((piece.x + skip_width, fill_width));
+                    }
+
+                    // 绘制查找焦点框
+                    if let Some(ref pos_vec) = self.search_result_positions {
+                        let rect_color = transform_color(if blink_state.next == BlinkDegree::Normal {
+                            blink_state.focus_boarder_color
+                        } else {
+                            blink_state.focus_boarder_contrast_color
+                        }, false);
+                        let focus_background_color = transform_color(blink_state.focus_background_color, false);
+                        let pl = piece.line.chars().count();
+                        let range = processed_search_len..(processed_search_len + pl);
+                        pos_vec.iter().enumerate().for_each(|(pos_i, (pos_from, pos_to))| {
+                            if range.contains(pos_from) {
+                                let start_index_of_piece = pos_from - processed_search_len;
+                                let (skip_width, _) = measure(piece.line.chars().take(start_index_of_piece).collect::<String>().as_str(), false);
+                                let (fill_width, _) = measure(piece.line.chars().skip(start_index_of_piece).take(pos_to - pos_from).collect::<String>().as_str(), false);
+
+                                set_draw_color(focus_background_color);
+                                #[cfg(not(target_os = "windows"))]
+                                {
+                                    // draw_rectf(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height);
+                                    draw_rounded_rectf(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                    if let Some(h_i) = self.search_highlight_pos {
+                                        if h_i == pos_i {
+                                            // debug!("blink1: {:?}", blink_state);
+                                            set_draw_color(rect_color);
+                                            set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
+                                            // draw_rect_with_color(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, rect_color);
+                                            draw_rounded_rect(piece.x + skip_width, y - piece.spacing + 2, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                            set_line_style(LineStyle::Solid, 0);
+                                        }
+                                    }
+                                }
+
+                                #[cfg(target_os = "windows")]
+                                {
+                                    // draw_rectf(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height);
+                                    draw_rounded_rectf(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                    if let Some(h_i) = self.search_highlight_pos {
+                                        if h_i == pos_i {
+                                            set_draw_color(rect_color);
+                                            set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
+                                            // draw_rect_with_color(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, rect_color);
+                                            draw_rounded_rect(piece.x + skip_width, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                            set_line_style(LineStyle::Solid, 0);
+                                        }
+                                    }
+                                }
+
+                            } else if range.contains(pos_to) {
+                                let (fill_width, _) = measure(piece.line.chars().take(pos_to - processed_search_len).collect::<String>().as_str(), false);
+
+                                set_draw_color(focus_background_color);
+                                // draw_rectf(piece.x, y - piece.spacing, fill_width, piece.font_height);
+                                draw_rounded_rectf(piece.x, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                if let Some(h_i) = self.search_highlight_pos {
+                                    if h_i == pos_i {
+                                        set_draw_color(rect_color);
+                                        set_line_style(LineStyle::Solid, blink_state.focus_boarder_width);
+                                        // draw_rect_with_color(piece.x, y - piece.spacing, fill_width, piece.font_height, rect_color);
+                                        draw_rounded_rect(piece.x, y - piece.spacing, fill_width, piece.font_height, HIGHLIGHT_ROUNDED_RECT_RADIUS);
+                                        set_line_style(LineStyle::Solid, 0);
+                                    }
+                                }
+                            }
+                        });
+                        processed_search_len += pl;
+                    }
+
+                    if self.conceal {
+                        // 隐藏文字只保留背景，文字仍可被划选复制。
+                        continue;
+                    }
+
+                    let text_color = if self.blink && blink_state.degree(self.blink_speed) == BlinkDegree::Contrast {
+                        get_lighter_or_darker_color(fg_color)
+                    } else {
+                        fg_color
+                    };
+                    set_draw_color(text_color);
+
+                    // 绘制文字阴影，位于下划线和文字之下。
+                    let text_y = y + self.font_size + piece.text_offset;
+                    if let Some(shadow) = &options.text_shadow {
+                        shadow.draw(text, piece.x, text_y, text_color);
+                    }
+
+                    if self.underline {
+                        // 绘制下划线
+                        // let line_y = y + piece.font_height + piece.bg_offset - 1;
+                        let line_y = y + piece.font_size + piece.text_offset + 2;
+                        if let Some(color) = self.underline_color {
+                            set_draw_color(transform_color(color, false));
+                        }
+                        self.draw_underline(piece.x, piece.x + piece.w - 2, line_y);
+                        set_draw_color(text_color);
+                    }
+
+                    // 绘制文本，使用draw_text_n()函数可以正确渲染'@'字符而无需转义处理。
+                    if let (Some(sel_text_color), Some((sel_x, sel_w))) = (blink_state.selection_text_color, selected_span) {
+                        // 按照选中范围分区裁剪，选中部分使用选中文字颜色，其余部分保持原色。
+                        let (clip_y, clip_h) = (y - piece.h, piece.h * 3);
+                        let right = piece.x + piece.w + piece.font_size;
+                        draw::push_clip(piece.x - piece.font_size, clip_y, sel_x - piece.x + piece.font_size, clip_h);
+                        draw_text_n(text, piece.x, text_y);
+                        draw::pop_clip();
+                        draw::push_clip(sel_x + sel_w, clip_y, right - sel_x - sel_w, clip_h);
+                        draw_text_n(text, piece.x, text_y);
+                        draw::pop_clip();
+                        set_draw_color(transform_color(sel_text_color, false));
+                        draw::push_clip(sel_x, clip_y, sel_w, clip_h);
+                        draw_text_n(text, piece.x, text_y);
+                        draw::pop_clip();
+                        set_draw_color(text_color);
+                    } else {
+                        draw_text_n(text, piece.x, text_y);
+                    }
+
+                    if self.strike_through {
+                        // 绘制删除线
+                        let line_y = y + ((piece.font_height as f32 / 2f32).floor() as i32);
+                        draw_line(piece.x, line_y, piece.x + piece.w - 4, line_y);
+                    }
+
+                    // {
+                    //     // 绘制上边界，用来辅助调试绘制内容的偏移效果
+                    //     draw_line(piece.x, piece.y, piece.x + piece.w, piece.y);
+                    // }
+                }
+
+                if self.show_translation {
+                    // 绘制译文，使用暗淡的前景色
+                    set_draw_color(fg_color.inactive());
+                    for piece in self.translation_pieces.iter() {
+                        let piece = &*piece.read();
+                        let text = piece.line.trim_end_matches('\n');
+                        if text.is_empty() {
+                            continue;
+                        }
+                        set_font(piece.font, piece.font_size);
+                        draw_text_n(text, piece.x, piece.y - offset_y + piece.font_size + piece.text_offset);
+                    }
+                }
+
+                if let (Some(preview), Some(piece)) = (&self.link_preview, &self.preview_piece) {
+                    preview.draw(&piece.read(), offset_y, fg_color);
+                }
+            },
+            DataType::Image => {
+                if let Some(piece) = self.line_pieces.last() {
+                    let piece = &*piece.read();
+                    if !self.disabled || self.image_inactive.is_none() {
+                        if !self.blink || blink_state.degree(self.blink_speed) == BlinkDegree::Normal {
+                            if let Some(img) = &self.image {
+                                // debug!("绘制图像：x:{}, y:{}, w:{}, h:{}", piece.x, piece.y - offset_y, piece.w, piece.h);
+                                match RgbImage::new(img, self.image_width, self.image_height, self.image_color_depth) {
+                                    Ok(mut rgb_img) => {
+                                        if self.image_width != self.image_target_width || self.image_height != self.image_target_height {
+                                            rgb_img.scale(self.image_target_width, self.image_target_height, false, true);
+                                        }
+                                        rgb_img.draw(piece.x, piece.y - offset_y, piece.w, piece.h);
+                                    }
+                                    Err(e) => {
+                                        error!("create rgb image error: {:?}", e);
+                                    }
+                                }
+                            }
+                            if !self.text.is_empty() {
+                                // 在图像上居中绘制文字
+                                let caption_color = transform_color(self.fg_color, (1..=16).contains(&self.fg_color_index));
+                                set_font(self.font, self.font_size);
+                                set_draw_color(caption_color);
+                                let lines = self.text.split("\n").count() as i32;
+                                let total_height = self.font_size * lines;
+                                let img_y_center = piece.y - offset_y + piece.h / 2;
+                                let first_line_y = img_y_center - total_height / 2;
+
+                                for (idx, line) in self.text.replace("\r", "").split("\n").enumerate() {
+                                    let (tw, _) = measure(line, false);
+                                    let text_x = piece.x + piece.w / 2 - tw / 2;
+                                    let text_y = first_line_y + idx as i32 * self.font_size;
+                                    if let Some(shadow) = &options.text_shadow {
+                                        shadow.draw(line, text_x, text_y + self.font_size, caption_color);
+                                    }
+                                    draw_text_n(line, text_x, text_y + self.font_size);
+                                }
+                            }
+                        }
+                    } else {
+                        if !self.blink || blink_state.degree(self.blink_speed) == BlinkDegree::Normal {
+                            if let Some(img) = &self.image_inactive {
+                                let depth = match (self.disabled_style, self.image_color_depth) {
+                                    (DisabledStyle::Dim(_), depth) => depth,
+                                    (_, ColorDepth::Rgb8 | ColorDepth::L8) => {
+                                        ColorDepth::L8
+                                    }
+                                    (_, ColorDepth::Rgba8 | ColorDepth::La8) => {
+                                        ColorDepth::La8
+                                    }
+                                };
+                                match RgbImage::new(img, self.image_width, self.image_height, depth) {
+                                    Ok(mut rgb_img) => {
+                                        if self.image_width != self.image_target_width || self.image_height != self.image_target_height {
+                                            rgb_img.scale(self.image_target_width, self.image_target_height, false, true);
+                                        }
+                                        rgb_img.draw(piece.x, piece.y - offset_y, piece.w, piece.h);
+                                    }
+                                    Err(e) => {
+                                        error!("create rgb image error: {:?}", e);
+                                    }
+                                }
+                                // if let Err(e) = draw_image(img.as_slice(), piece.x, piece.y - offset_y, self.image_width, piece.h, depth) {
+                                //     error!("draw gray image error: {:?}", e);
+                                // }
+
+                                if !self.text.is_empty() {
+                                    // 在图像上居中绘制文字
+                                    set_font(self.font, self.font_size);
+                                    set_draw_color(Color::Light1);
+                                    let lines = self.text.split("\n").count() as i32;
+                                    let total_height = self.font_size * lines;
+                                    let img_y_center = piece.y - offset_y + piece.h / 2;
+                                    let first_line_y = img_y_center - total_height / 2;
+
+                                    for (idx, line) in self.text.replace("\r", "").split("\n").enumerate() {
+                                        let (tw, _) = measure(line, false);
+                                        let text_x = piece.x + piece.w / 2 - tw / 2;
+                                        let text_y = first_line_y + idx as i32 * self.font_size;
+                                        draw_text_n(line, text_x, text_y + self.font_size);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+
+            },
+            DataType::Marker => {
+                // 结构标记不参与绘制
+            }
+            DataType::Divider => {
+                if let (Some(divider), Some(piece)) = (&self.divider, self.line_pieces.first()) {
+                    divider.draw(&piece.read(), offset_y);
+                }
+            }
+            DataType::Table => {
+                if let Some(table) = &self.table {
+                    let (fg_color, _) = self.draw_colors();
+                    for (row, piece) in self.line_pieces.iter().enumerate() {
+                        table.draw_row(row, &piece.read(), offset_y, fg_color);
+                    }
+                }
+            }
+            DataType::Gauge => {
+                if let (Some(gauge), Some(piece)) = (&self.gauge, self.line_pieces.first()) {
+                    let (fg_color, _) = self.draw_colors();
+                    gauge.draw(&piece.read(), offset_y, fg_color);
+                }
+            }
+        }
+    }
+
+    /// 试算当前内容绘制后所占高度信息。
+    /// 试算功能自动处理文本超宽时截断换行的逻辑。
+    ///
+    /// # Arguments
+    ///
+    /// * `last_piece`: 前一个数据片段，用于计算当前数据段的绘制坐标。每个数据段和数据片段都是按照缓存数据的顺序依次计算得到。
+    /// * `max_width`: 可视区域最大宽度，不含padding宽度。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn estimate(&mut self, last_piece: Arc<RwLock<LinePiece>>, max_width: i32, basic_char: char) -> Arc<RwLock<LinePiece>> {
+        // 嵌套缩进最多占用可视宽度的一半。
+        self.indent_limit = max((max_width - self.padding_left - self.gutter_width) / 2, 0);
+        if let Some(bubble) = self.bubble {
+            if self.data_type == DataType::Text && !self.hidden {
+                return self.estimate_bubble(bubble, last_piece, max_width, basic_char);
+            }
+        }
+        let mut ret = last_piece.clone();
+        let mut last_line_piece = last_piece.read().clone();
+        let left = self.line_start_x();
+        if last_line_piece.next_x < left || (last_line_piece.next_x != left && last_line_piece.line.ends_with('\n')) {
+            // 为行号栏和线索缩进预留宽度，位于行首时按照当前数据段的缩进重新定位。
+            last_line_piece.next_x = left;
+        }
+        let (top_y, start_x) = (last_line_piece.next_y, last_line_piece.next_x);
+        let (font, font_size) = (self.font, self.font_size);
+        self.line_pieces.clear();
+        self.translation_pieces.clear();
+        self.preview_piece = None;
+        if self.hidden {
+            // 已折叠线索中的数据段不产生分片，与结构标记一样沿用前一个分片的位置。
+            self.set_v_bounds(top_y, top_y, start_x, start_x);
+            return ret;
+        }
+        match self.data_type {
+            DataType::Text => {
+                set_font(self.font, self.font_size);
+
+                // 字体渲染高度，小于等于行高度。
+                let ref_font_height = (self.font_size as f32 * self.line_height_factor).ceil() as i32;
+
+                let current_line_spacing = min(last_line_piece.spacing, descent());
+
+                /*
+                对含有换行符和不含换行符的文本进行不同处理。
+                 */
+                let text = self.text.clone();
+                if text.contains('\n') {
+                    // 以换行符为节点拆分成多段处理。
+                    for line in text.split_inclusive("\n") {
+                        let (tw, th) = cached_measure(self.wrap_cache.as_deref(), line, font, font_size);
+                        let mut current_line_height = max(ref_font_height, th);
+                        self.line_height = current_line_height;
+
+                        let mut next_x = last_line_piece.next_x + tw;
+                        if next_x > max_width {
+                            // 超出横向右边界
+                            ret = self.wrap_text_for_estimate(line, ret.clone(), max_width, tw, ref_font_height);
+                        } else {
+                            let new_piece: Arc<RwLock<LinePiece>>;
+                            if let Some(lp) = self.line_pieces.last_mut() {
+                                let lp = &mut *lp.write();
+                                let mut next_y = lp.next_y;
+                                // 最后一段可能带有换行符'\n'。
+                                if line.ends_with("\n") {
+                                    next_y += current_line_height + self.paragraph_spacing;
+                                    next_x = left;
+                                }
+                                let y = lp.next_y;
+                                let piece_top_y = lp.next_y;
+                                let through_line = ThroughLine::create_or_update(left, lp.next_x, current_line_height, ret.clone(), false);
+                                new_piece = LinePiece::new(line.to_string(), lp.next_x, y, tw, current_line_height, piece_top_y, lp.spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
+
+                            } else {
+                                let mut next_y = last_line_piece.next_y;
+                                // 最后一段可能带有换行符'\n'。
+                                if line.ends_with("\n") {
+                                    // if !last_piece.line.ends_with("\n") && !last_piece.line.is_empty() {
+                                    //     current_line_height = max(current_line_height, last_piece.h);
+                                    // }
+                                    if !last_line_piece.line.ends_with("\n") {
+                                        current_line_height = max(current_line_height, last_line_piece.h);
+                                    }
+                                    next_y += current_line_height + self.paragraph_spacing;
+                                    next_x = left;
+                                }
+                                let y = last_line_piece.next_y;
+                                let piece_top_y = last_line_piece.next_y;
+                                let through_line = ThroughLine::create_or_update(left, last_line_piece.next_x, current_line_height, ret.clone(), false);
+                                new_piece = LinePiece::new(line.to_string(), last_line_piece.next_x, y, tw, self.line_height, piece_top_y, last_line_piece.spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
+                            }
+                            self.line_pieces.push(new_piece.clone());
                             ret = new_piece;
                         }
                         last_line_piece = ret.read().clone();
                     }
 
-                } else {
-                    let (_, th) = measure(basic_char.to_string().as_str(), false);
-                    self.line_height = max(ref_font_height, th);
+                } else {
+                    let (_, th) = measure(basic_char.to_string().as_str(), false);
+                    self.line_height = max(ref_font_height, th);
+
+                    let line = text.as_str();
+                    let (tw, _) = cached_measure(self.wrap_cache.as_deref(), line, font, font_size);
+                    let next_x = start_x + tw + self.piece_spacing;
+                    if next_x > max_width {
+                        // 超出横向右边界
+                        ret = self.wrap_text_for_estimate(line, ret.clone(), max_width, tw, ref_font_height);
+                    } else {
+                        let y = top_y;
+                        let through_line = ThroughLine::create_or_update(left, start_x, ref_font_height, ret, false);
+                        let next_y = top_y;
+                        let new_piece = LinePiece::new(self.text.clone(), start_x, y, tw, ref_font_height, top_y, current_line_spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
+                        self.line_pieces.push(new_piece.clone());
+                        ret = new_piece;
+                    }
+                }
+            }
+            DataType::Image => {
+                let h = self.image_target_height + IMAGE_PADDING_V * 2;
+                if start_x + self.image_target_width > max_width {
+                    // 本行超宽，直接定位到下一行
+                    let x = left + IMAGE_PADDING_H;
+                    let y = top_y + last_line_piece.through_line.read().max_h + IMAGE_PADDING_V;
+                    let next_x = x + self.image_target_width + IMAGE_PADDING_H;
+                    let next_y = y - IMAGE_PADDING_V;
+                    let piece_top_y = y - IMAGE_PADDING_V;
+                    let through_line = ThroughLine::new(self.image_target_height * IMAGE_PADDING_V * 2, true);
+                    let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, next_y, 1, font, font_size, through_line, self.v_bounds.clone());
+                    self.line_pieces.push(new_piece.clone());
+                    ret = new_piece;
+                } else {
+                    let x = start_x + IMAGE_PADDING_H;
+                    let next_x = start_x + self.image_target_width + IMAGE_PADDING_H * 2 + self.piece_spacing;
+                    if last_line_piece.line.ends_with("\n") {
+                        // 定位在行首
+                        let y = top_y + IMAGE_PADDING_V;
+                        let piece_top_y = y - IMAGE_PADDING_V;
+                        let through_line = ThroughLine::new(self.image_target_height * IMAGE_PADDING_V * 2, true);
+                        let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, top_y, 1, font, font_size, through_line, self.v_bounds.clone());
+                        self.line_pieces.push(new_piece.clone());
+                        ret = new_piece;
+                    } else {
+                        // 在本行已有其他内容，需要与前一个片段协调行高
+                        let current_line_height = max(last_line_piece.h, h);
+                        let mut raw_y = top_y + IMAGE_PADDING_V;
+                        if current_line_height > last_line_piece.h {
+                            // 图形比前一个分片行高要高
+                            last_line_piece.through_line.write().set_max_h(current_line_height);
+                        } else {
+                            // 图形的高度小于等于前一个分片的行高，需要计算垂直居中位置
+                            let (up, _) = calc_v_center_offset(current_line_height, h);
+                            raw_y += up;
+                        }
+                        let y = raw_y;
+                        let piece_top_y = y - IMAGE_PADDING_V;
+                        let through_line = ThroughLine::create_or_update(left + IMAGE_PADDING_H, x, self.image_target_height * IMAGE_PADDING_V * 2, ret, true);
+                        let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, top_y + IMAGE_PADDING_V, 1, font, font_size, through_line, self.v_bounds.clone());
+                        self.line_pieces.push(new_piece.clone());
+                        ret = new_piece;
+                    }
+                }
+            }
+            DataType::Marker => {
+                // 结构标记不产生分片，高度为零，直接沿用前一个分片的位置。
+            }
+            DataType::Divider => {
+                // 分隔线独占一行，前一个分片未结束本行时先移动到下一行。
+                let h = (self.font_size as f32 * self.line_height_factor).ceil() as i32;
+                self.line_height = h;
+                let y = if start_x > left { top_y + last_line_piece.through_line.read().max_h } else { top_y };
+                let through_line = ThroughLine::new(h, false);
+                let new_piece = LinePiece::new(self.text.clone(), left, y, max(max_width - left, 0), h, y, last_line_piece.spacing, left, y + h + self.paragraph_spacing, h, font, font_size, through_line, self.v_bounds.clone());
+                self.line_pieces.push(new_piece.clone());
+                ret = new_piece;
+            }
+            DataType::Table => {
+                // 表格从新的一行开始，每行数据独占一行。
+                set_font(self.font, self.font_size);
+                let h = (self.font_size as f32 * self.line_height_factor).ceil() as i32;
+                self.line_height = h;
+                let mut y = if start_x > left { top_y + last_line_piece.through_line.read().max_h } else { top_y };
+                if let Some(table) = self.table.as_mut() {
+                    table.layout(max(max_width - left, 0));
+                    let w = table.col_widths.iter().sum();
+                    let row_texts = table.row_texts();
+                    let rows = row_texts.len();
+                    for (row, line) in row_texts.into_iter().enumerate() {
+                        let next_y = if row + 1 == rows { y + h + self.paragraph_spacing } else { y + h };
+                        let through_line = ThroughLine::new(h, false);
+                        let new_piece = LinePiece::new(line, left, y, w, h, y, last_line_piece.spacing, left, next_y, h, font, font_size, through_line, self.v_bounds.clone());
+                        self.line_pieces.push(new_piece.clone());
+                        ret = new_piece;
+                        y += h;
+                    }
+                }
+            }
+            DataType::Gauge => {
+                // 进度条按照文字行高在行内排列，本行剩余宽度不足时换到下一行。
+                let h = (self.font_size as f32 * self.line_height_factor).ceil() as i32;
+                self.line_height = h;
+                let w = self.gauge.as_ref().map_or(0, |gauge| gauge.width);
+                let new_piece = if start_x > left && start_x + w > max_width {
+                    let y = top_y + last_line_piece.through_line.read().max_h;
+                    let through_line = ThroughLine::new(h, false);
+                    LinePiece::new(self.text.clone(), left, y, w, h, y, last_line_piece.spacing, left + w + self.piece_spacing, y, h, font, font_size, through_line, self.v_bounds.clone())
+                } else {
+                    let through_line = ThroughLine::create_or_update(left, start_x, h, ret, false);
+                    LinePiece::new(self.text.clone(), start_x, top_y, w, h, top_y, last_line_piece.spacing, start_x + w + self.piece_spacing, top_y, h, font, font_size, through_line, self.v_bounds.clone())
+                };
+                self.line_pieces.push(new_piece.clone());
+                ret = new_piece;
+            }
+        }
+
+        if self.data_type == DataType::Text {
+            // 标记自动折行产生的续行分片，用于绘制折行标记，以及复制时按需还原显示时的换行。
+            let (mut open, mut row) = {
+                let lp = &*last_piece.read();
+                (!lp.line.is_empty() && !lp.line.ends_with('\n'), lp.top_y)
+            };
+            for piece in self.line_pieces.iter() {
+                let lp = &mut *piece.write();
+                lp.soft_wrapped = open && lp.top_y != row;
+                open = !lp.line.ends_with('\n');
+                row = lp.top_y;
+            }
+        } else if self.data_type == DataType::Image {
+            for piece in self.line_pieces.iter() {
+                piece.write().pad_h = IMAGE_PADDING_H;
+            }
+        }
+
+        let (mut _is_first_line, mut bound_start_x, mut bound_end_x) = (true, 0, 0);
+        let mut to_be_updated: Vec<(Arc<RwLock<LinePiece>>, i32)> = Vec::new();
+        for line_piece in self.line_pieces.iter() {
+            let lp = &*line_piece.read();
+            if _is_first_line {
+                bound_start_x = lp.x;
+                _is_first_line = false;
+            }
+
+            let tl = &mut *lp.through_line.write();
+            let mut max_h = 1;
+            // 找出最大的行高
+            for l in tl.ys.iter() {
+                if let Some(l) = l.upgrade() {
+                    if l.read().h > max_h {
+                        max_h = l.read().h;
+                    }
+                }
+            }
+            tl.max_h = max_h;
+            // 收集同一行内低于最大高度的分片。因为borrow作用域的问题，无法在一个for循环内直接处理，只能先收集再处理。
+            for one_piece in tl.ys.iter() {
+                if let Some(p) = one_piece.upgrade() {
+                    let lh = p.read().h;
+                    if lh < max_h {
+                        to_be_updated.push((p.clone(), max_h));
+                    }
+                }
+            }
+        }
+
+        // 计算文字片段字体高度小于行高时的渲染偏移量
+        self.line_pieces.iter_mut().for_each(|lp| lp.write().calc_offset());
+
+        // 重新计算同一行内低于最大行高的片段的y坐标
+        for (lp, max_h) in to_be_updated {
+            let y = lp.read().y;
+            let piece_top_y = lp.read().top_y;
+            let h = lp.read().h;
+
+            if lp.read().line.ends_with("\n") {
+                let mut padding_v = 0;
+                if lp.read().through_line.read().exist_image {
+                    padding_v = IMAGE_PADDING_V;
+                }
+                lp.write().next_y = y + max_h + padding_v;
+            }
+
+            let (up_offset, _) = calc_v_center_offset(max_h, h);
+            lp.write().y = piece_top_y + up_offset;
+            let lpm = &mut*lp.write();
+            let mut vb = *lpm.rd_bounds.write();
+            vb.1 = piece_top_y + up_offset + lpm.h;
+            *lpm.rd_bounds.write() = vb;
+        }
+
+        // let mut pic_y = 0;
+        let v_b_top_y = if let Some(first_piece) = self.line_pieces.first() {
+            let fp = &*first_piece.read();
+            fp.top_y
+            // pic_y = fp.y;
+        } else {
+            top_y
+        };
+        let v_b_bottom_y = if let Some(last_piece) = self.line_pieces.last() {
+            let lp = &*last_piece.read();
+            let bottom_y = lp.top_y + lp.through_line.read().max_h;
+            bound_end_x = lp.x + lp.w;
+            bottom_y
+        } else {
+            v_b_top_y
+        };
+        // debug!("estimated v_b_top_y: {v_b_top_y}, v_b_bottom_y: {v_b_bottom_y}, bound_start_x: {bound_start_x}, bound_end_x: {bound_end_x}, text: {}", self.text);
+        self.set_v_bounds(v_b_top_y, v_b_bottom_y, bound_start_x, bound_end_x);
+
+        if self.data_type == DataType::Text && self.show_translation && !self.line_pieces.is_empty() {
+            if let Some(translation) = self.translation.clone() {
+                ret = self.estimate_translation(&translation, ret, max_width);
+            }
+        }
+        if self.data_type == DataType::Text && !self.line_pieces.is_empty() {
+            if let Some((title, has_icon)) = self.link_preview.as_ref().map(|p| (p.title.clone(), p.favicon.is_some())) {
+                ret = self.estimate_link_preview(&title, has_icon, ret, max_width);
+            }
+        }
+        ret
+    }
+}
+
+/// 数据片段调整属性。
+#[derive(Debug, Clone)]
+pub struct RichDataOptions {
+    pub id: i64,
+    pub clickable: Option<bool>,
+    pub underline: Option<bool>,
+    pub expired: Option<bool>,
+    pub text: Option<String>,
+    pub fg_color: Option<Color>,
+    pub bg_color: Option<Color>,
+    pub strike_through: Option<bool>,
+    pub blink: Option<bool>,
+    pub disabled: Option<bool>,
+    pub image: Option<Vec<u8>>,
+    image_width: Option<i32>,
+    image_height: Option<i32>,
+    pub image_target_width: Option<i32>,
+    pub image_target_height: Option<i32>,
+    pub image_color_depth: Option<ColorDepth>,
+    /// 图片文件临时存储路径。
+    pub image_file_path: Option<PathBuf>,
+    pub action: Option<Action>,
+    /// 进度条的当前值。
+    pub gauge_value: Option<f64>,
+}
+
+impl RichDataOptions {
+    pub fn new(id: i64) -> RichDataOptions {
+        RichDataOptions {
+            id,
+            clickable: None,
+            underline: None,
+            expired: None,
+            text: None,
+            fg_color: None,
+            bg_color: None,
+            strike_through: None,
+            blink: None,
+            disabled: None,
+            image: None,
+            image_width: None,
+            image_height: None,
+            image_target_width: None,
+            image_target_height: None,
+            image_color_depth: None,
+            image_file_path: None,
+            action: None,
+            gauge_value: None,
+        }
+    }
+
+    pub fn clickable(mut self, clickable: bool) -> RichDataOptions {
+        self.clickable = Some(clickable);
+        self
+    }
+
+    pub fn underline(mut self, underline: bool) -> RichDataOptions {
+        self.underline = Some(underline);
+        self
+    }
+
+    pub fn expired(mut self, expired: bool) -> RichDataOptions {
+        self.expired = Some(expired);
+        self
+    }
+
+    pub fn text(mut self, text: String) -> RichDataOptions {
+        self.text = Some(text);
+        self
+    }
+
+    pub fn fg_color(mut self, fg_color: Color) -> RichDataOptions {
+        self.fg_color = Some(fg_color);
+        self
+    }
+
+    pub fn bg_color(mut self, bg_color: Color) -> RichDataOptions {
+        self.bg_color = Some(bg_color);
+        self
+    }
+
+    pub fn strike_through(mut self, strike_through: bool) -> RichDataOptions {
+        self.strike_through = Some(strike_through);
+        self
+    }
+
+    pub fn blink(mut self, blink: bool) -> RichDataOptions {
+        self.blink = Some(blink);
+        self
+    }
+
+    pub fn disabled(mut self, disabled: bool) -> RichDataOptions {
+        self.disabled = Some(disabled);
+        self
+    }
+
+    /// 设置影像更新参数。
+    ///
+    /// # Arguments
+    ///
+    /// * `image`: 新的影像对象。若为 `None` 表示不替换原有的影像，只是变更目标宽高。
+    /// * `target_width`: 目标宽度。
+    /// * `target_height`: 目标高度。
+    ///
+    /// returns: RichDataOptions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn image(mut self, image: Option<RgbImage>, target_width: i32, target_height: i32) -> RichDataOptions {
+        if image.is_some() {
+            let (img_data, depth, width, height) = image_to_rgb_data(&image, target_width, target_height);
+            self.image = img_data;
+            self.image_width = Some(width);
+            self.image_height = Some(height);
+            self.image_target_width = Some(target_width);
+            self.image_target_height = Some(target_height);
+            self.image_color_depth = Some(depth);
+            self
+        } else {
+            self.image_target_width = Some(target_width);
+            self.image_target_height = Some(target_height);
+            self
+        }
+    }
+
+    pub fn image_file_path(mut self, image_file_path: PathBuf) -> RichDataOptions {
+        self.image_file_path = Some(image_file_path);
+        self
+    }
+
+    pub fn change_action(mut self, action: Action) -> RichDataOptions {
+        self.action = Some(action);
+        self
+    }
+
+    /// 更新进度条的当前值，只对进度条数据段有效。
+    ///
+    /// # Arguments
+    ///
+    /// * `value`: 新的当前值。
+    ///
+    /// returns: RichDataOptions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
+    ///
+    /// let hp = UserData::new_gauge(120.0, 120.0, 100, Color::Red, Some("HP".to_string()));
+    /// let options = RichDataOptions::new(hp.id).gauge_value(30.0);
+    /// assert_eq!(options.gauge_value, Some(30.0));
+    /// ```
+    pub fn gauge_value(mut self, value: f64) -> RichDataOptions {
+        self.gauge_value = Some(value);
+        self
+    }
+}
+
+/// 碰撞检测，检查两个矩形区域是否出现交叉。
+///
+/// # Arguments
+///
+/// * `a`: 目标区域。
+/// * `b`: 当前区域，或者鼠标拖拽选择区域。
+///
+/// returns: bool
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn is_overlap(target_area: &Rectangle, selection_area: &Rectangle) -> bool {
+    let mut overlap = target_area.0 <= (selection_area.0 + selection_area.2);
+    overlap &= (target_area.0 + target_area.2) >= selection_area.0;
+    overlap &= target_area.1 <= (selection_area.1 + selection_area.3);
+    overlap &= (target_area.1 + target_area.3) >= selection_area.1;
+    // debug!("overlap: {overlap}");
+    overlap
+    // target_area.0 < (selection_area.0 + selection_area.2)
+    //     && (target_area.0 + target_area.2) > selection_area.0
+    //     && target_area.1 < (selection_area.1 + selection_area.3)
+    //     && (target_area.1 + target_area.3) > selection_area.1
+}
+
+
+/// 复制选中片段的内容。
+///
+/// # Arguments
+///
+/// * `it`:
+/// * `selection`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+fn copy_pieces(it: Iter<Weak<RwLock<LinePiece>>>, selection: &mut String, keep_soft_wraps: bool) {
+    for p in it {
+        if let Some(p) = p.upgrade() {
+            let lp = &*p.read();
+            let continued = matches!(*lp.selected_range.read(), Some((0, _)));
+            if keep_soft_wraps && lp.soft_wrapped && continued && !selection.is_empty() && !selection.ends_with('\n') {
+                selection.push('\n');
+            }
+            lp.copy_selection(selection);
+        }
+    }
+}
+
+/// 获取选中片段的文字内容。
+///
+/// # Arguments
+///
+/// * `selected_pieces`: 选中的数据片段。
+/// * `keep_soft_wraps`: 是否在自动折行处插入换行符。
+///
+/// returns: String
+pub(crate) fn selection_text(selected_pieces: &[Weak<RwLock<LinePiece>>], keep_soft_wraps: bool) -> String {
+    let mut selection = String::new();
+    copy_pieces(selected_pieces.iter(), &mut selection, keep_soft_wraps);
+    selection
+}
+
+/// 检测指定位置是否位于选中的文字上，用于判断按下鼠标时是否开始拖出选中内容。
+///
+/// # Arguments
+///
+/// * `selected_pieces`: 选中的数据片段。
+/// * `x`: 数据坐标系中的横坐标。
+/// * `y`: 数据坐标系中的纵坐标。
+///
+/// returns: bool
+pub(crate) fn selection_contains(selected_pieces: &[Weak<RwLock<LinePiece>>], x: i32, y: i32) -> bool {
+    selected_pieces.iter().filter_map(|p| p.upgrade()).any(|p| {
+        let piece = &*p.read();
+        let selected = matches!(*piece.selected_range.read(), Some((from, to)) if to > from);
+        selected && x >= piece.x && x < piece.x + piece.w && y >= piece.y && y < piece.y + piece.h
+    })
+}
+
+/// 将选中的文字拷贝至剪贴板。Linux系统中同时拷贝至PRIMARY选区，以便在其他程序中点击鼠标中键粘贴。
+///
+/// # Arguments
+///
+/// * `selection`: 选中的文字。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn copy_selection(selection: &str) {
+    app::copy(selection);
+    #[cfg(target_os = "linux")]
+    app::copy2(selection);
+}
+
+/// 开始拖出操作，将拖出内容作为文字交给拖放目标，图片以文件路径的形式拖出。
+///
+/// # Arguments
+///
+/// * `payload`: 拖出的内容。
+///
+/// returns: ()
+pub(crate) fn start_drag_out(payload: &str) {
+    app::copy2(payload);
+    app::dnd();
+}
+
+/// 鼠标从按下位置移动的距离是否足以开始拖出操作。
+///
+/// # Arguments
+///
+/// * `from`: 按下鼠标时的位置。
+/// * `to`: 当前鼠标位置。
+///
+/// returns: bool
+pub(crate) fn drag_out_started(from: (i32, i32), to: (i32, i32)) -> bool {
+    max((to.0 - from.0).abs(), (to.1 - from.1).abs()) >= DRAG_OUT_THRESHOLD
+}
+
+/// 清除数据片段的选中属性。
+///
+/// # Arguments
+///
+/// * `selected_pieces`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn clear_selected_pieces(selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>) {
+    for piece in selected_pieces.read().iter() {
+        if let Some(p) = piece.upgrade() {
+            p.read().deselect();
+        }
+    }
+    selected_pieces.write().clear();
+}
+
+/// 向前或向后选择数据片段。
+///
+/// # Arguments
+///
+/// * `rd`:
+/// * `piece_index`:
+/// * `pos`:
+/// * `selected_pieces`:
+/// * `from`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+fn select_piece_from_or_to(rd: &RichData, piece_index: usize, pos: usize, selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>, from: bool) {
+    if let Some(last_piece_rc) = rd.line_pieces.get(piece_index) {
+        let piece = &*last_piece_rc.read();
+        if from {
+            piece.select_from(pos);
+        } else {
+            piece.select_to(pos);
+        }
+        selected_pieces.write().push(Arc::downgrade(last_piece_rc));
+    }
+}
+
+/// 计算选中文本所在数据片段的选中属性。
+///
+/// # Arguments
+///
+/// * `from_point`: 起始位置。
+/// * `to_point`: 结束位置。
+/// * `data_buffer`: 数据缓存。
+/// * `rd_range`: 选中的数据段索引范围。
+/// * `selected_pieces`: 选中数据片段临时记录容器。
+/// * `layout`: 分栏参数，分栏显示时按阅读顺序判断起止点的先后。
+/// * `keep_soft_wraps`: 复制选中文字时是否在自动折行处插入换行符。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn select_text(
+    from_point: &ClickPoint,
+    to_point: &ClickPoint,
+    data_buffer: &[RichData],
+    rd_range: RangeInclusive<usize>,
+    selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>,
+    select_from_row: usize,
+    layout: &ColumnLayout,
+    keep_soft_wraps: bool) {
+    /*
+    选择片段的原则：应选择起点右下方的第一行片段，结束点左上方的第一行片段，以及两点之间的中间行片段。
+     */
+    // debug!("传入的fp: {:?}, tp: {:?}", from_point, to_point);
+    let drag_rect = from_point.to_rect(to_point);
+    // debug!("drag_rect: {:?}", drag_rect);
+    let (lt, br) = drag_rect.corner_rect();
+    let (mut lt_p, mut br_p) = (from_point, to_point);
+    /*
+    lt_p: 划选区左上角点击位置
+    br_p: 划选区右下角点击位置
+    lf_p_i: 左上角数据分片索引号
+    br_p_i: 右下角数据分片索引号
+     */
+    let (mut lt_p_i, mut br_p_i) = (lt_p.p_i, br_p.p_i);
+    // debug!("lt_p_i: {lt_p_i}, br_p_i: {br_p_i}, rd_range: {:?}, drag_rect: {:?}, lt_p: {:?}, br_p: {:?}", rd_range, drag_rect, lt_p, br_p);
+    // 清理上一次选择的区域
+    clear_selected_pieces(selected_pieces.clone());
+
+    let (r_start, r_end) = (*rd_range.start(), *rd_range.end());
+    let across_rds = max(r_end as isize - r_start as isize, 0);
+    if across_rds > 0 {
+        // 超过一行
+        // debug!("选区超过一个数据段");
+        if let Some(rd) = data_buffer.get(r_start) {
+            // 选择第一个数据段起点之后的所有分片内容。
+            // debug!("第一个数据段 lt_p_i: {lt_p_i}, lt_p.c_i: {}, br_p_i: {br_p_i}, br_p.c_i: {}", lt_p.c_i, br_p.c_i);
+            let v_bounds = &*rd.v_bounds.read();
+            // 若起始点成为划选区的右下角或左下角的时候，对调起始点和结束点
+            let mut should_exchange = (br.0 == from_point.x && br.1 == from_point.y) || (lt.0 == from_point.x && br.1 == from_point.y);
+            // 若划选区在起点数据段边界内，则不应对调
+            if should_exchange && r_start == select_from_row && drag_rect.1 >= v_bounds.0 && (drag_rect.1 + drag_rect.3) <= v_bounds.1 {
+                should_exchange = false;
+            }
+            // 分栏显示时左右两栏的坐标不能反映先后顺序，按阅读顺序判断。
+            if layout.is_flowing() {
+                should_exchange = layout.cmp_points((from_point.x, from_point.y), (to_point.x, to_point.y)) == Ordering::Greater;
+            }
+
+            if should_exchange {
+                // debug!("对换坐标点");
+                lt_p = to_point;
+                br_p = from_point;
+                lt_p_i = lt_p.p_i;
+                br_p_i = br_p.p_i;
+            }
+
+            select_piece_from_or_to(rd, lt_p_i, lt_p.c_i, selected_pieces.clone(), true);
+            for p in rd.line_pieces.iter().skip(lt_p_i + 1) {
+                let piece = &*p.read();
+                piece.select_all();
+                selected_pieces.write().push(Arc::downgrade(p));
+            }
+        }
+
+        // 如果中间有更多跨行数据段，则全选这些数据段。
+        if r_end > r_start + 1 {
+            let mut piece_rcs = Vec::new();
+            for i in r_start + 1..r_end {
+                if let Some(rd) = data_buffer.get(i) {
+                    for p in rd.line_pieces.iter() {
+                        let piece = &*p.read();
+                        piece.select_all();
+                        piece_rcs.push(Arc::downgrade(p));
+                    }
+                }
+            }
+            selected_pieces.write().append(&mut piece_rcs);
+        }
+
+        if let Some(rd) = data_buffer.get(r_end) {
+            // 选择最后一个数据段终点之前的所有内容。
+            for p in rd.line_pieces.iter().take(br_p_i) {
+                let piece = &*p.read();
+                piece.select_all();
+                selected_pieces.write().push(Arc::downgrade(p));
+            }
+            // debug!("br_p_i: {br_p_i}, br_p.c_i: {}", br_p.c_i);
+            select_piece_from_or_to(rd, br_p_i, br_p.c_i + 1, selected_pieces.clone(), false);
+        }
+    } else {
+        // 只有一行
+        // debug!("选区只有一个数据段");
+
+        // 若起始点成为划选区的右下角或左下角的时候，对调起始点和结束点，分栏显示时按阅读顺序判断。
+        let should_exchange = if layout.is_flowing() {
+            (from_point.p_i, from_point.c_i) > (to_point.p_i, to_point.c_i)
+        } else {
+            (br.0 == from_point.x && br.1 == from_point.y) || (lt.0 == from_point.x && br.1 == from_point.y)
+        };
+        if should_exchange {
+            // debug!("对换坐标点");
+            lt_p = to_point;
+            br_p = from_point;
+            lt_p_i = lt_p.p_i;
+            br_p_i = br_p.p_i;
+        }
+
+        if let Some(rd) = data_buffer.get(r_start) {
+            // debug!("br_p_i: {br_p_i}, lt_p_i: {lt_p_i}");
+            if br_p_i != lt_p_i {
+                // 超过一个分片
+                // debug!("选区超过一个分片");
+                select_piece_from_or_to(rd, lt_p_i, lt_p.c_i, selected_pieces.clone(), true);
+
+                // 超过两个分片
+                // debug!("选区超过两个分片");
+                let mut piece_rcs = Vec::new();
+                for i in lt_p_i + 1..br_p_i {
+                    if let Some(piece_rc) = rd.line_pieces.get(i) {
+                        let piece = &*piece_rc.read();
+                        piece.select_all();
+                        piece_rcs.push(Arc::downgrade(piece_rc));
+                    }
+                }
+                selected_pieces.write().append(&mut piece_rcs);
+
+                select_piece_from_or_to(rd, br_p_i, br_p.c_i + 1, selected_pieces.clone(), false);
+            } else {
+                // 在同一个分片内
+                if let Some(piece_rc) = rd.line_pieces.get(lt_p_i) {
+                    // debug!("selected range from: {} to: {}", lt_p.c_i, br_p.c_i + 1);
+                    let (mut fci, mut tci) = (lt_p.c_i, br_p.c_i + 1);
+                    if fci >= tci {
+                        fci = br_p.c_i;
+                        tci = lt_p.c_i + 1;
+                    }
+                    piece_rc.read().select_range(fci, tci);
+                    selected_pieces.write().push(Arc::downgrade(piece_rc));
+                }
+            }
+        }
+    }
+
+    /*
+    拷贝至剪贴板
+     */
+    let mut selection = String::new();
+    copy_pieces(selected_pieces.read().iter(), &mut selection, keep_soft_wraps);
+    copy_selection(selection.as_str());
+}
+
+#[derive(Debug)]
+pub(crate) struct TargetRow {
+    pub(crate) row: usize,
+    pub(crate) expanded: bool,
+}
+
+impl TargetRow {
+    pub fn new(row: usize, expanded: bool) -> Self {
+        Self { row, expanded }
+    }
+}
+
+/// 定位指定坐标处的字符。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `x`: 相对于面板左侧的x坐标。
+/// * `y`: 相对于数据内容顶部的y坐标，已计入滚动距离。
+///
+/// returns: Option<(usize, usize)> 字符所在数据段在缓存中的索引，以及字符在数据段文本中的位置，位置以`unicode`字符计算。
+pub(crate) fn locate_char_at(data_buffer: &[RichData], x: i32, y: i32) -> Option<(usize, usize)> {
+    for (idx, rd) in data_buffer.iter().enumerate() {
+        if rd.data_type != DataType::Text {
+            continue;
+        }
+        let (top_y, bottom_y, _, _) = *rd.v_bounds.read();
+        if y < top_y || y > bottom_y {
+            continue;
+        }
+        let mut char_offset = 0usize;
+        for piece in rd.line_pieces.iter() {
+            let piece = &*piece.read();
+            let piece_len = piece.line.chars().count();
+            if piece.hit(x, y) {
+                set_font(piece.font, piece.font_size);
+                // 逐个字素簇测量，返回字素簇首个字符的位置。
+                let mut prefix = String::new();
+                let mut c_i = 0;
+                for g in piece.line.graphemes(true) {
+                    prefix.push_str(g);
+                    let (tw, _) = measure(&prefix, false);
+                    if x < piece.x + tw {
+                        return Some((idx, char_offset + c_i));
+                    }
+                    c_i += g.chars().count();
+                }
+                return Some((idx, char_offset + piece_len.saturating_sub(1)));
+            }
+            char_offset += piece_len;
+        }
+    }
+    None
+}
+
+/// 定位指定坐标处的数据段，与划选使用相同的分片命中区域，文本和图形均可命中。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `x`: 相对于面板左侧的x坐标。
+/// * `y`: 相对于数据内容顶部的y坐标，已计入滚动距离。
+///
+/// returns: Option<usize> 数据段在缓存中的索引。
+pub(crate) fn locate_data_at(data_buffer: &[RichData], x: i32, y: i32) -> Option<usize> {
+    data_buffer.iter().position(|rd| {
+        let (top_y, bottom_y, _, _) = *rd.v_bounds.read();
+        rd.data_type != DataType::Marker && y >= top_y && y <= bottom_y && rd.line_pieces.iter().any(|piece| piece.read().hit(x, y))
+    })
+}
+
+/// 查找文本中所有目标字符串的位置，用于设置数据段的`search_result_positions`。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `pattern`: 目标字符串。
+///
+/// returns: Vec<(usize, usize), Global> 各目标的起止字符位置，以`unicode`字符计算，从后向前排列。
+pub(crate) fn search_positions(text: &str, pattern: &str) -> Vec<(usize, usize)> {
+    let len = pattern.chars().count();
+    text.rmatch_indices(pattern).map(|(s_idx, _)| {
+        let chars = text[0..s_idx].chars().count();
+        (chars, chars + len)
+    }).collect()
+}
+
+/// 查找文本中所有持久高亮关键字的位置。多个关键字重叠时先添加的关键字优先。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `keywords`: 关键字，按添加顺序排列。
+///
+/// returns: Option<Vec<(usize, usize, Color), Global>> 各关键字的起止字符位置和背景色，从前向后排列。没有关键字时返回空。
+pub(crate) fn keyword_ranges(text: &str, keywords: &[KeywordHighlight]) -> Option<Vec<(usize, usize, Color)>> {
+    let mut ranges: Vec<(usize, usize, Color)> = vec![];
+    for keyword in keywords.iter().filter(|keyword| !keyword.word.is_empty()) {
+        for (from, to) in search_positions(text, &keyword.word) {
+            if ranges.iter().all(|(f, t, _)| to <= *f || from >= *t) {
+                ranges.push((from, to, keyword.color));
+            }
+        }
+    }
+    if ranges.is_empty() {
+        None
+    } else {
+        ranges.sort_by_key(|(from, _, _)| *from);
+        Some(ranges)
+    }
+}
+
+/// 获取文本中指定字符所在的单词，按照`unicode`单词边界规则分词。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `char_idx`: 字符位置，以`unicode`字符计算。
+///
+/// returns: Option<String> 字符位于空白或标点上时返回空。
+pub(crate) fn word_around(text: &str, char_idx: usize) -> Option<String> {
+    let (byte_idx, _) = text.char_indices().nth(char_idx)?;
+    text.split_word_bound_indices()
+        .find(|(start, word)| byte_idx >= *start && byte_idx < start + word.len())
+        .filter(|(_, word)| word.chars().any(|c| c.is_alphanumeric()))
+        .map(|(_, word)| word.to_string())
+}
+
+/// 获取文本中指定字符所在的网址。网址以`http://`、`https://`、`ftp://`或`www.`开头，以空白字符为界，并去除两端的括号、引号等标点。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `char_idx`: 字符位置，以`unicode`字符计算。
+///
+/// returns: Option<String>
+pub(crate) fn url_around(text: &str, char_idx: usize) -> Option<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.get(char_idx).map_or(true, |c| c.is_whitespace()) {
+        return None;
+    }
+    let start = chars[..char_idx].iter().rposition(|c| c.is_whitespace()).map_or(0, |p| p + 1);
+    let end = chars[char_idx..].iter().position(|c| c.is_whitespace()).map_or(chars.len(), |p| char_idx + p);
+    let token: String = chars[start..end].iter().collect();
+    let (url_start, url) = url_in_token(&token)?;
+    // 点击位置须落在网址范围内。
+    let url_from = start + token[..url_start].chars().count();
+    let url_to = url_from + url.chars().count();
+    if char_idx >= url_from && char_idx < url_to {
+        Some(url.to_string())
+    } else {
+        None
+    }
+}
+
+/// 查找不含空白字符的片段中的网址，返回网址在片段中的字节偏移量和去除尾部标点后的网址。
+fn url_in_token(token: &str) -> Option<(usize, &str)> {
+    let lower = token.to_ascii_lowercase();
+    let url_start = ["http://", "https://", "ftp://", "www."].iter().filter_map(|prefix| lower.find(prefix)).min()?;
+    let url = token[url_start..].trim_end_matches(|c: char| matches!(c, '.' | ',' | ';' | ':' | '!' | '?' | ')' | ']' | '}' | '>' | '"' | '\'' | '。' | '，' | '）'));
+    if url.len() > 4 {
+        Some((url_start, url))
+    } else {
+        None
+    }
+}
+
+/// 获取文本中出现的所有网址，按出现顺序排列，识别规则与`url_around`一致。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+///
+/// returns: Vec<String, Global>
+pub(crate) fn urls_in(text: &str) -> Vec<String> {
+    text.split_whitespace().filter_map(url_in_token).map(|(_, url)| url.to_string()).collect()
+}
+
+/// 检测拖选范围所涵盖的数据段。
+///
+/// # Arguments
+///
+/// * `point`: 起始点。
+/// * `drag_rect`: 拖选矩形范围。
+/// * `panel_width`: 容器面板宽度。
+/// * `data_buffer`: 数据缓存。
+/// * `index_vec`: 容器面板可见范围内数据的顺序位置索引。
+/// * `padding`: 面板边界空白。
+/// * `layout`: 分栏参数，分栏显示时按阅读顺序查找。
+///
+/// returns: Option<usize> 返回拖选结束点的数据段索引。
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn locate_target_rd(point: &mut ClickPoint, mut drag_rect: Rectangle, panel_width: i32, data_buffer: &[RichData], index_vec: Vec<usize>, padding: &Padding, layout: &ColumnLayout) -> Option<TargetRow> {
+    let point_rect = point.as_rect();
+    // debug!("index_vec: {:?}", index_vec);
+    if let Ok(idx) = index_vec.binary_search_by({
+        let point_rect_rc = point_rect.clone();
+        let point_rc = point.clone();
+        move |row| if layout.is_flowing() {
+            _search_row_idx_in_columns(&data_buffer[*row], layout, &point_rect_rc, &point_rc)
+        } else {
+            _search_row_idx(row, &data_buffer, panel_width, &drag_rect, point_rect_rc, point_rc)
+        }
+    }) {
+        // debug!("已定位当前数据段 {idx}");
+        let ret = _record_start_char_pos(data_buffer, &index_vec, idx, &point_rect, point);
+        if let Some(row) = ret {
+            Some(TargetRow::new(row, false))
+        } else {
+            None
+        }
+    } else if layout.is_flowing() {
+        // 分栏显示时向左上扩展的矩形会跨越多栏，改为按阅读顺序查找目标点之前的最后一个分片。
+        _record_last_piece_before(data_buffer, &index_vec, layout, point).map(|row| TargetRow::new(row, true))
+    } else {
+        // debug!("没找到目标数据段！向左上扩展");
+        drag_rect.2 = max(drag_rect.0 - padding.left, 0);
+        drag_rect.3 = max(drag_rect.1 - padding.top, 0);
+        drag_rect.0 = padding.left;
+        drag_rect.1 = padding.top;
+        let point_rect = drag_rect.clone();
+        let mut tmp_point = point.clone();
+        tmp_point.x = padding.left;
+
+        // 先用二分法粗略定位到选区中的某个数据段，再从该数据段开始向后遍历找到最后一个位于选区内的数据段，将该数据段的末尾设定为新的选择起点。
+        if let Ok(idx) = index_vec.binary_search_by({
+            let point_rect_rc = point_rect.clone();
+            let point_rc = tmp_point.clone();
+            move |row| _search_row_idx(row, &data_buffer, panel_width, &drag_rect, point_rect_rc, point_rc)
+        }) {
+            // debug!("粗略定位到选区中的数据段 {idx}");
+            let ret = _record_start_char_pos2(data_buffer, index_vec[idx], &point_rect, &mut tmp_point);
+            if let Some(row) = ret {
+                point.x = tmp_point.x;
+                point.y = tmp_point.y;
+                point.p_i = tmp_point.p_i;
+                point.c_i = tmp_point.c_i;
+                // debug!("point: {point:?}");
+                Some(TargetRow::new(row, true))
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+}
+
+fn _search_row_idx(row: &usize, data_buffer: &[RichData], panel_width: i32, drag_rect: &Rectangle, point_rect_rc: Rectangle, point_rc: ClickPoint) -> Ordering {
+    /*
+    先将不规则的数据段外形扩展为顶宽的矩形，再检测划选区是否与之重叠；
+    如果有重叠，则进一步检测其中每个分片是否与划选区有重叠，如果任意分片有重叠，说明划选区包含了该数据段的某些分片，
+    还须再进一步确定选区起点位置所在的分片，最终返回等于，否则返回大于或小于；
+    如果没有重叠，则判断其相对位置，并返回大于或小于。
+     */
+    let mut rd_extend_rect = Rectangle::zero();
+    let rd = &data_buffer[*row];
+    // debug!("检测行 {row} : {}", rd.text);
+    let (rd_top_y, rd_bottom_y, _, _) = *rd.v_bounds.read();
+    // let max_h = if let Some(first_lp) = rd.line_pieces.first() {
+    //     rd_top_y = first_lp.read().top_y;
+    //     first_lp.read().through_line.read().max_h
+    // } else {
+    //     rd_bottom_y - rd_top_y
+    // };
+    rd_extend_rect.replace(0, rd_top_y, panel_width, rd_bottom_y - rd_top_y);
+    // debug!("{point_rect:?}, rd_top_y: {}, rd_bottom_y: {}, drag_rect: {:?}", rd_top_y, rd_bottom_y, drag_rect);
+
+    // 粗略过滤到的数据段，还须进一步检测其中的分片是否包含划选区起点。
+    if is_overlap(&rd_extend_rect, &drag_rect) {
+        // debug!("行 {row} 与划选区有重叠");
+        let mut ord = Ordering::Less;
+        for piece_rc in rd.line_pieces.iter() {
+            let piece = &*piece_rc.read();
+            let piece_rect = piece.hit_rect();
+            // debug!("piece_rect: {:?}, piece_top_y: {}, : {}", piece_rect, piece.top_y, piece.line);
+            if is_overlap(&piece_rect, &point_rect_rc) {
+                // 划选区起点位于分片内
+                // debug!("划选区起点位于分片内：{:?}", piece.line);
+                ord = Ordering::Equal;
+                break;
+            }
+        }
+        // 如果划选起点不在重叠数据段的任意分片内部，则还须判断当前数据段在起点的前面或后面，为查找算法提供判断依据。
+        if ord != Ordering::Equal {
+            if let Some(first_piece_rc) = rd.line_pieces.first() {
+                let piece = &*first_piece_rc.read();
+                // debug!("piece: {:?}", piece);
+                if point_rc.x < piece.x && point_rc.y < piece.top_y + piece.through_line.read().max_h {
+                    ord = Ordering::Greater;
+                } else {
+                    ord = Ordering::Less;
+                }
+            }
+        }
+        // debug!("行 {row}: ord: {:?}", ord);
+        ord
+    } else {
+        if rd_extend_rect.is_below(&drag_rect) {
+            // debug!("行 {row}: 大于");
+            Ordering::Greater
+        } else {
+            // debug!("行 {row}: 小于");
+            Ordering::Less
+        }
+    }
+}
+
+/// 分栏显示时按照阅读顺序判断数据段与划选起点的相对位置，起点与数据段位于同一栏时的判断规则与单栏相同。
+fn _search_row_idx_in_columns(rd: &RichData, layout: &ColumnLayout, point_rect: &Rectangle, point: &ClickPoint) -> Ordering {
+    let Some(first_piece_rc) = rd.line_pieces.first() else {
+        // 没有分片的结构标记按照其所在位置判断。
+        let (top_y, _, x, _) = *rd.v_bounds.read();
+        return match layout.cmp_points((point.x, point.y), (x, top_y)) {
+            Ordering::Less => Ordering::Greater,
+            _ => Ordering::Less,
+        };
+    };
+    if rd.line_pieces.iter().any(|piece_rc| is_overlap(&piece_rc.read().hit_rect(), point_rect)) {
+        return Ordering::Equal;
+    }
+    let piece = &*first_piece_rc.read();
+    match layout.cell_of(point.x, point.y).cmp(&layout.cell_of(piece.x, piece.top_y)) {
+        Ordering::Less => Ordering::Greater,
+        Ordering::Greater => Ordering::Less,
+        Ordering::Equal => {
+            if point.y < piece.top_y || (point.x < piece.x && point.y < piece.top_y + piece.through_line.read().max_h) {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        }
+    }
+}
+
+/// 分栏显示时按照阅读顺序查找位于目标点之前的最后一个文本分片，并将目标点设置为该分片的末尾。
+fn _record_last_piece_before(data_buffer: &[RichData], index_vec: &[usize], layout: &ColumnLayout, point: &mut ClickPoint) -> Option<usize> {
+    let point_cell = layout.cell_of(point.x, point.y);
+    let mut found = None;
+    'OUTER: for &row in index_vec {
+        let rd = &data_buffer[row];
+        if rd.data_type == DataType::Image {
+            continue;
+        }
+        for (p_i, piece_rc) in rd.line_pieces.iter().enumerate() {
+            let piece = &*piece_rc.read();
+            let before = match layout.cell_of(piece.x, piece.top_y).cmp(&point_cell) {
+                Ordering::Less => true,
+                Ordering::Greater => false,
+                Ordering::Equal => piece.top_y + piece.through_line.read().max_h <= point.y || (piece.top_y <= point.y && piece.x <= point.x),
+            };
+            if !before {
+                // 缓存中的数据段按阅读顺序排列，之后的分片都在目标点之后。
+                break 'OUTER;
+            }
+            found = Some((row, p_i, piece.rect(0, 0), piece.line.trim().len()));
+        }
+    }
+    let (row, p_i, piece_rect, c_i) = found?;
+    point.x = piece_rect.0 + piece_rect.2;
+    point.y = piece_rect.1 + piece_rect.3 / 2;
+    point.p_i = p_i;
+    point.c_i = c_i;
+    Some(row)
+}
+
+fn _record_start_char_pos2(data_buffer: &[RichData], from_row: usize, point_rect: &Rectangle, point: &mut ClickPoint) -> Option<usize> {
+    // 从指定位置开始向后查找选区内的数据段
+    let mut ret = from_row;
+    let bottom_y = point_rect.1 + point_rect.3;
+    'OUTER: for row in from_row..data_buffer.len() {
+        let rd = &data_buffer[row];
+        if rd.data_type != DataType::Image {
+            if rd.v_bounds.read().0 > bottom_y {
+                // 直到数据段上边界大于选区的下边界时，结束遍历。
+                break 'OUTER;
+            } else {
+                for (p_i, piece_rc) in rd.line_pieces.iter().enumerate() {
+                    let piece = &*piece_rc.read();
+                    // 将最后一个选区内的数据片段末尾位置，当作选区的起点
+                    if is_overlap(&piece.hit_rect(), &point_rect) {
+                        let piece_rect = piece.rect(0, 0);
+                        point.x = piece_rect.0 + piece_rect.2;
+                        point.y = piece_rect.1 + piece_rect.3 / 2;
+                        point.p_i = p_i;
+                        point.c_i = piece.line.trim().len();
+                        ret = row;
+                    }
+                }
+            }
+        }
+    }
+    Some(ret)
+}
+
+fn _record_start_char_pos(data_buffer: &[RichData], index_vec: &Vec<usize>, idx: usize, point_rect: &Rectangle, point: &mut ClickPoint) -> Option<usize> {
+    let rd = &data_buffer[index_vec[idx]];
+    if rd.data_type != DataType::Image {
+        // debug!("找到目标点所在数据段： {}", rd.text);
+        for (p_i, piece_rc) in rd.line_pieces.iter().enumerate() {
+            let piece = &*piece_rc.read();
+            let piece_rect = piece.hit_rect();
+            // debug!("point_rect: {:?}, piece_rect: {:?}, line: {}", point_rect, piece_rect, piece.line);
+            if is_overlap(&piece_rect, &point_rect) {
+                // 划选区起点位于分片内
+                point.p_i = p_i;
+                // debug!("目标点位于分片:{} 内: {}", p_i, piece.line);
+                search_index_of_piece(piece, point);
+                break;
+            }
+        }
+        return Some(idx);
+    } else {
+        // 选择了图片，不予处理。
+        // debug!("选择了图片")
+        None
+    }
+}
+
+/// 更新拖选结束点所在数段的位置属性，用于拖选过程准实时检测结束点位置。
+///
+/// # Arguments
+///
+/// * `push_from_point`: 起始点。
+/// * `select_from_row`: 起始点所在数据段的顺序索引号。
+/// * `current_point`: 当前结束点。
+/// * `data_buffer_slice`: 数据缓存。
+/// * `selected_pieces`: 临时保存选中数据片段的容器。
+/// * `panel`: 当前容器面板。
+/// * `padding`: 面板边界空白。
+/// * `layout`: 分栏参数，分栏显示时按阅读顺序判断划选方向。
+/// * `keep_soft_wraps`: 复制选中文字时是否在自动折行处插入换行符。
+///
+/// returns: bool
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn update_selection_when_drag(
+    push_from_point: ClickPoint,
+    select_from_row: usize,
+    current_point: &mut ClickPoint,
+    data_buffer_slice: &[RichData],
+    selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>,
+    panel: &mut impl WidgetBase,
+    padding: &Padding,
+    layout: &ColumnLayout,
+    keep_soft_wraps: bool) {
+
+    let mut down = true;
+    let index_vec = if layout.cmp_points((current_point.x, current_point.y), (push_from_point.x, push_from_point.y)) != Ordering::Less {
+        // 向下选择
+        (select_from_row..data_buffer_slice.len()).collect::<Vec<usize>>()
+    } else {
+        // 向上选择
+        // debug!("向上划选");
+        down = false;
+        /*
+        这里引入一个魔法数字100,表示查找范围从0到起点行再向后扩展100个数据段，
+        这是为了临时解决划选区从起点数据段向右侧划选时，跨入同行内后面的数据段无法被查找到的问题，
+        数字100表示允许向起点数据段之后延伸查找最多100个数据段。
+         */
+        let max_rows = min(select_from_row + 100, data_buffer_slice.len() - 1);
+        (0..=max_rows).collect::<Vec<usize>>()
+    };
+    // debug!("开始查找结束点所在数据段: {:?}", index_vec);
+    // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
+    if let Some(select_to_row) = locate_target_rd(current_point, current_point.as_rect(), panel.w(), data_buffer_slice, index_vec, padding, layout) {
+        // debug!("select_from_row: {select_from_row}, select_to_row: {select_to_row:?}");
+        /*
+        向下选择时，select_to_row表示相对于select_from_row的偏移量或绝对位置；
+        向上选择时，select_to_row表示相对于0的偏移量也就是绝对位置。
+         */
+        let rd_range = if down {
+            if !select_to_row.expanded {
+                select_from_row..=(select_from_row + select_to_row.row)
+            } else {
+                select_from_row..=select_to_row.row
+            }
+        } else {
+            if select_from_row < select_to_row.row {
+                select_from_row..=select_to_row.row
+            } else {
+                select_to_row.row..=select_from_row
+            }
+        };
+        // let rd_range = select_from_row..=(select_from_row + select_to_row);
+        // debug!("rd_range: {:?}", rd_range);
+        // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
+        select_text(&push_from_point, current_point, data_buffer_slice, rd_range, selected_pieces, select_from_row, layout, keep_soft_wraps);
+        // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
+        panel.set_damage(true);
+    }
+}
+
+
+/// 测量鼠标点击的片段内容字符索引位置。
+///
+/// # Arguments
+///
+/// * `piece`:
+/// * `point`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn search_index_of_piece(piece: &LinePiece, point: &mut ClickPoint) {
+    // 按字素簇查找，bounds保存各字素簇的起始字节位置和起始字符位置，末尾附加文本长度。
+    let mut bounds = piece.line.grapheme_indices(true).scan(0, |chars, (i, g)| {
+        let start = *chars;
+        *chars += g.chars().count();
+        Some((i, start))
+    }).collect::<Vec<(usize, usize)>>();
+    let len = bounds.len();
+    bounds.push((piece.line.len(), piece.line.chars().count()));
+    if let Ok(g_i) = (0..len).collect::<Vec<usize>>().binary_search_by({
+        set_font(piece.font, piece.font_size);
+        let text = piece.line.clone();
+        let bounds = &bounds;
+        // 落在片段间距或图形留白中的点，归入最近的字素簇。
+        let x = point.x.clamp(piece.x + 1, max(piece.x + piece.w, piece.x + 1));
+        let start_x = piece.x;
+        move |pos| {
+            let (mut pw1, _) = measure(&text[..bounds[*pos + 1].0], false);
+            let (mut pw2, _) = measure(&text[..bounds[*pos].0], false);
+            pw1 += start_x;
+            pw2 += start_x;
+            if x > pw2 && x <= pw1 {
+                Ordering::Equal
+            } else if x <= pw2 {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            }
+        }
+    }) {
+        point.c_i = bounds[g_i].1;
+        // debug!("目标字符：{}，位置：{}, point: {point:?}", piece.line.chars().nth(c_i).unwrap(), c_i);
+    } else {
+        // debug!("没找到目标字符！")
+    }
+}
+
+/// 选择段落。
+/// 段落的定义：在目标点前后两个换行符之间的所有数据片段，可能跨越多个数据段。
+///
+/// # Arguments
+///
+/// * `anchor_row`: 目标点所在数据段索引。
+/// * `data_buffer`: 数据缓存片段。
+/// * `layout`: 分栏参数。
+/// * `keep_soft_wraps`: 复制选中文字时是否在自动折行处插入换行符。
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn select_paragraph(anchor_row: usize, push_from_point: &mut ClickPoint, data_buffer: &[RichData], selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>, layout: &ColumnLayout, keep_soft_wraps: bool) {
+    let (mut from_point, mut to_point) = (ClickPoint::new(0, 0), ClickPoint::new(0, 0));
+    let (mut from_row, mut to_row) = (0, 0);
+
+    'BEFORE: for i in (0..=anchor_row).rev() {
+        let mut piece_range = 0..data_buffer[i].line_pieces.len();
+        if i == anchor_row {
+            if push_from_point.p_i > 0 {
+                piece_range = 0..push_from_point.p_i;
+            } else {
+                continue;
+            }
+        }
+        for j in piece_range.rev() {
+            if let Some(lp_arc) = data_buffer[i].line_pieces.get(j) {
+                let lp = &*lp_arc.read();
+                if lp.line.ends_with('\n') {
+                    from_row = i;
+                    from_point.p_i = j + 1;
+                    from_point.c_i = 0;
+                    from_point.x = lp.next_x;
+                    from_point.y = lp.next_y;
+                    break 'BEFORE;
+                }
+            }
+        }
+    }
+
+    'AFTER: for i in anchor_row..data_buffer.len() {
+        let piece_range = if i == anchor_row {
+            push_from_point.p_i..data_buffer[i].line_pieces.len()
+        } else {
+            0..data_buffer[i].line_pieces.len()
+        };
+        for j in piece_range {
+            if let Some(lp_arc) = data_buffer[i].line_pieces.get(j) {
+                let lp = &*lp_arc.read();
+                if lp.line.ends_with('\n') {
+                    to_row = i;
+                    to_point.p_i = j;
+                    to_point.c_i = lp.line.chars().count() - 1;
+                    to_point.x = lp.next_x;
+                    to_point.y = lp.next_y;
+                    break 'AFTER;
+                }
+            }
+        }
+    }
+
+    let rd_range = from_row..=to_row;
+    select_text(&from_point, &to_point, data_buffer, rd_range, selected_pieces, anchor_row, layout, keep_soft_wraps);
+}
+
+/// 选区端点，以数据段在缓存中的索引和端点在数据段各分片文字中的字符位置表示，不受绘制位置变化和分片重建的影响。
+/// 同时记录数据段id用于校验索引，缓存头部的数据被清理或插入后从原索引附近重新查找。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SelectionAnchor {
+    pub(crate) row: usize,
+    pub(crate) id: i64,
+    pub(crate) char_pos: usize,
+}
+
+/// 选区的起点和终点，终点的字符位置不包含在选区内。
+pub(crate) type SelectionRange = (SelectionAnchor, SelectionAnchor);
+
+impl SelectionAnchor {
+    /// 以数据段中的分片索引和分片内的字符索引生成端点。
+    ///
+    /// # Arguments
+    ///
+    /// * `rd`: 端点所在数据段。
+    /// * `row`: 数据段在缓存中的索引。
+    /// * `p_i`: 分片索引。
+    /// * `c_i`: 分片内的字符索引。
+    ///
+    /// returns: SelectionAnchor
+    pub(crate) fn new(rd: &RichData, row: usize, p_i: usize, c_i: usize) -> Self {
+        let char_pos = rd.line_pieces.iter().take(p_i).map(|p| p.read().line.chars().count()).sum::<usize>() + c_i;
+        Self { row, id: rd.id, char_pos }
+    }
+
+    /// 查找端点所在数据段当前在缓存中的索引。先校验记录的索引，不符时从记录的索引向前查找，
+    /// 缓存头部的数据被清理时只需回退被清理的数量，最后再向后查找。
+    ///
+    /// # Arguments
+    ///
+    /// * `data_buffer`: 数据缓存。
+    ///
+    /// returns: Option<usize> 数据段已不在缓存中时返回空。
+    pub(crate) fn find_row(&self, data_buffer: &[RichData]) -> Option<usize> {
+        let hint = min(self.row, data_buffer.len().checked_sub(1)?);
+        (0..=hint).rev().chain(hint + 1..data_buffer.len()).find(|row| data_buffer[*row].id == self.id)
+    }
+
+    /// 在数据段当前的分片中定位端点，返回分片索引和分片内的字符索引。超出数据段末尾时定位到最后一个字符。
+    ///
+    /// # Arguments
+    ///
+    /// * `rd`: 端点所在数据段。
+    ///
+    /// returns: Option<(usize, usize)>
+    pub(crate) fn locate(&self, rd: &RichData) -> Option<(usize, usize)> {
+        let mut offset = 0;
+        for (p_i, p) in rd.line_pieces.iter().enumerate() {
+            let len = p.read().line.chars().count();
+            if self.char_pos < offset + len {
+                return Some((p_i, self.char_pos - offset));
+            }
+            offset += len;
+        }
+        let last = rd.line_pieces.len().checked_sub(1)?;
+        Some((last, rd.line_pieces[last].read().line.chars().count().saturating_sub(1)))
+    }
+
+    /// 计算端点在数据坐标系中的位置，用于数据段重新排版后继续划选。
+    ///
+    /// # Arguments
+    ///
+    /// * `rd`: 端点所在数据段。
+    ///
+    /// returns: Option<ClickPoint>
+    pub(crate) fn to_point(&self, rd: &RichData) -> Option<ClickPoint> {
+        let (p_i, c_i) = self.locate(rd)?;
+        let piece = &*rd.line_pieces[p_i].read();
+        let prefix: String = piece.line.chars().take(c_i).collect();
+        let (w, _) = cached_measure(rd.wrap_cache.as_deref(), &prefix, piece.font, piece.font_size);
+        Some(ClickPoint { x: piece.x + w, y: piece.y + piece.h / 2, p_i, c_i })
+    }
+}
+
+/// 根据分片的选中状态计算选区两端的位置。选区是连续的并且包含划选起点所在的数据段，
+/// 因此只需从起点向前找到选区开始的数据段，再向后查找，遇到第一个未选中的文本分片时即停止查找，不必扫描整个缓存。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `anchor_row`: 划选起点所在数据段的索引。
+///
+/// returns: Option<(SelectionAnchor, SelectionAnchor)>
+pub(crate) fn selection_range(data_buffer: &[RichData], anchor_row: usize) -> Option<SelectionRange> {
+    let anchor_row = min(anchor_row, data_buffer.len().checked_sub(1)?);
+    let mut start_row = anchor_row;
+    for row in (0..anchor_row).rev() {
+        let rd = &data_buffer[row];
+        if rd.data_type != DataType::Text {
+            continue;
+        }
+        if !rd.line_pieces.iter().any(|p| p.read().selected_range.read().is_some_and(|(from, to)| to > from)) {
+            break;
+        }
+        start_row = row;
+    }
+
+    let mut range: Option<SelectionRange> = None;
+    for (row, rd) in data_buffer.iter().enumerate().skip(start_row).filter(|(_, rd)| rd.data_type == DataType::Text) {
+        let mut offset = 0;
+        for p in rd.line_pieces.iter() {
+            let piece = &*p.read();
+            match (*piece.selected_range.read(), range.as_mut()) {
+                (Some((from, to)), None) if to > from => {
+                    range = Some((SelectionAnchor { row, id: rd.id, char_pos: offset + from }, SelectionAnchor { row, id: rd.id, char_pos: offset + to }));
+                }
+                (Some((from, to)), Some((_, end))) if to > from => {
+                    *end = SelectionAnchor { row, id: rd.id, char_pos: offset + to };
+                }
+                (None, Some(_)) => return range,
+                _ => {}
+            }
+            offset += piece.line.chars().count();
+        }
+    }
+    range
+}
+
+/// 按照选区两端的位置重新选中数据片段。起点所在数据段已被清理时从最早的数据开始，终点所在数据段尚未加载时选到最新的数据为止，
+/// 两端都已不在缓存中时不再恢复。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `range`: 选区两端的位置。
+/// * `selected_pieces`: 选中的数据片段。
+///
+/// returns: bool 选区是否仍在数据缓存中。
+pub(crate) fn restore_selection(data_buffer: &[RichData], range: &SelectionRange, selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>) -> bool {
+    clear_selected_pieces(selected_pieces.clone());
+    let (start, end) = range;
+    let ((from_row, from_pos), (to_row, to_pos)) = match (start.find_row(data_buffer), end.find_row(data_buffer)) {
+        (Some(from), Some(to)) => ((from, start.char_pos), (to, end.char_pos)),
+        (None, Some(to)) => ((0, 0), (to, end.char_pos)),
+        (Some(from), None) => ((from, start.char_pos), (data_buffer.len() - 1, usize::MAX)),
+        (None, None) => return false,
+    };
+
+    let mut pieces = vec![];
+    for (row, rd) in data_buffer.iter().enumerate().take(to_row + 1).skip(from_row) {
+        let sel_from = if row == from_row { from_pos } else { 0 };
+        let sel_to = if row == to_row { to_pos } else { usize::MAX };
+        let mut offset = 0;
+        for p in rd.line_pieces.iter() {
+            let piece = &*p.read();
+            let len = piece.line.chars().count();
+            let (from, to) = (max(sel_from, offset), min(sel_to, offset + len));
+            if from < to {
+                piece.select_range(from - offset, to - offset);
+                pieces.push(Arc::downgrade(p));
+            }
+            offset += len;
+        }
+    }
+    let restored = !pieces.is_empty();
+    *selected_pieces.write() = pieces;
+    restored
+}
+
+/// 数据追加、缓存清理、重新排版或重新加载导致选中的分片被重建时，按照记录的选区位置恢复选区。选区已不在数据缓存中时清除记录。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `selection`: 记录的选区位置。
+/// * `selected_pieces`: 选中的数据片段。
+///
+/// returns: ()
+pub(crate) fn keep_selection(data_buffer: &[RichData], selection: &RwLock<Option<SelectionRange>>, selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>) {
+    let Some(range) = *selection.read() else {
+        return;
+    };
+    let lost = {
+        let pieces = selected_pieces.read();
+        pieces.is_empty() || pieces.iter().any(|p| p.strong_count() == 0)
+    };
+    if lost && !restore_selection(data_buffer, &range, selected_pieces) {
+        selection.write().take();
+    }
+}
+
+/// 划选过程中，新数据的追加、缓存清理或重新排版可能改变起点所在数据段的顺序位置和绘制位置，根据起点记录的索引和数据段id重新定位起点。
+///
+/// # Arguments
+///
+/// * `data_buffer`: 数据缓存。
+/// * `push_anchor`: 起点位置及按下鼠标时起点所在数据段的顶部位置。
+/// * `push_from_point`: 起点。
+/// * `select_from_row`: 起点所在数据段的顺序索引号。
+///
+/// returns: ()
+pub(crate) fn follow_push_anchor(data_buffer: &[RichData], push_anchor: &mut Option<(SelectionAnchor, i32)>, push_from_point: &mut ClickPoint, select_from_row: &mut usize) {
+    let Some((anchor, top_y)) = push_anchor.as_mut() else {
+        return;
+    };
+    let row = match anchor.find_row(data_buffer) {
+        Some(row) => row,
+        None => {
+            // 起点所在数据段已被清理，从最早的数据开始划选。
+            let Some(first) = data_buffer.first() else {
+                return;
+            };
+            *anchor = SelectionAnchor { row: 0, id: first.id, char_pos: 0 };
+            *top_y = i32::MIN;
+            0
+        }
+    };
+    anchor.row = row;
+    *select_from_row = row;
+    let rd = &data_buffer[row];
+    let top = rd.v_bounds.read().0;
+    if top != *top_y {
+        if let Some(point) = anchor.to_point(rd) {
+            *push_from_point = point;
+        }
+        *top_y = top;
+    }
+}
+
+/// 获取指定颜色的对比色。若指定颜色为中等灰色(R/G/B值相等且在116-139之间)，则返回白色。
+///
+/// # Arguments
+///
+/// * `color`: 指定颜色。
+///
+/// returns: Color 返回其对比色。
+///
+/// # Examples
+///
+/// ```
+/// use fltk::enums::Color;
+/// use fltkrs_richdisplay::get_contrast_color;
+///
+/// assert_eq!(get_contrast_color(Color::from_rgb(255, 255, 255)), Color::from_rgb(0, 0, 0));
+/// assert_eq!(get_contrast_color(Color::from_rgb(0, 0, 0)), Color::from_rgb(255, 255, 255));
+/// assert_eq!(get_contrast_color(Color::from_rgb(255, 0, 0)), Color::from_rgb(0, 255, 255));
+/// assert_eq!(get_contrast_color(Color::from_rgb(120, 120, 120)), Color::from_rgb(255, 255, 255));
+/// ```
+pub fn get_contrast_color(color: Color) -> Color {
+    let (r, g, b) = color.to_rgb();
+    let (cr, cg, cb) = (255 - r, 255 - g, 255 - b);
+    if (cr == cg && cg == cb) && ((cr as i16) - (r as i16)).abs() < 25 {
+        WHITE
+    } else {
+        Color::from_rgb(cr, cg, cb)
+    }
+}
+
+/// 计算颜色的相对亮度，按照WCAG 2.x的定义将sRGB分量线性化后加权求和。
+fn relative_luminance(color: Color) -> f32 {
+    let (r, g, b) = color.to_rgb();
+    let linear = |c: u8| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// 计算两个颜色之间的对比度，按照WCAG 2.x的定义，取值范围为1到21。
+///
+/// # Arguments
+///
+/// * `a`: 颜色a。
+/// * `b`: 颜色b。
+///
+/// returns: f32
+///
+/// # Examples
+///
+/// ```
+/// use fltk::enums::Color;
+/// use fltkrs_richdisplay::contrast_ratio;
+///
+/// assert!((contrast_ratio(Color::from_rgb(255, 255, 255), Color::from_rgb(0, 0, 0)) - 21.0).abs() < 0.01);
+/// assert!((contrast_ratio(Color::from_rgb(0, 0, 0), Color::from_rgb(0, 0, 0)) - 1.0).abs() < 0.01);
+/// ```
+pub fn contrast_ratio(a: Color, b: Color) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// 调整前景色，使其与背景色的对比度不低于指定值。前景色向白色或黑色中能以较小改动达标的一方靠拢，
+/// 两者都无法达标时使用对比度较高的一方。
+///
+/// # Arguments
+///
+/// * `fg`: 前景色。
+/// * `bg`: 背景色。
+/// * `min_ratio`: 最低对比度，WCAG建议普通文字为4.5，大号文字为3。
+///
+/// returns: Color 满足对比度要求时返回原前景色。
+///
+/// # Examples
+///
+/// ```
+/// use fltk::enums::Color;
+/// use fltkrs_richdisplay::{contrast_ratio, ensure_contrast};
+///
+/// let bg = Color::from_rgb(0, 0, 128);
+/// let fg = ensure_contrast(Color::from_rgb(0, 0, 255), bg, 4.5);
+/// assert!(contrast_ratio(fg, bg) >= 4.5);
+/// ```
+pub fn ensure_contrast(fg: Color, bg: Color, min_ratio: f32) -> Color {
+    if contrast_ratio(fg, bg) >= min_ratio {
+        return fg;
+    }
+    let (r, g, b) = fg.to_rgb();
+    let mix = |target: u8, t: f32| {
+        let blend = |c: u8| (c as f32 + (target as f32 - c as f32) * t).round() as u8;
+        Color::from_rgb(blend(r), blend(g), blend(b))
+    };
+    // 二分查找向目标颜色靠拢的最小比例。
+    let fit = |target: u8| {
+        if contrast_ratio(mix(target, 1.0), bg) < min_ratio {
+            return None;
+        }
+        let (mut low, mut high) = (0f32, 1f32);
+        for _ in 0..12 {
+            let t = (low + high) / 2.0;
+            if contrast_ratio(mix(target, t), bg) >= min_ratio {
+                high = t;
+            } else {
+                low = t;
+            }
+        }
+        Some(high)
+    };
+    match (fit(255), fit(0)) {
+        (Some(up), Some(down)) if up <= down => mix(255, up),
+        (_, Some(down)) => mix(0, down),
+        (Some(up), None) => mix(255, up),
+        (None, None) => {
+            if contrast_ratio(WHITE, bg) >= contrast_ratio(Color::from_rgb(0, 0, 0), bg) { WHITE } else { Color::from_rgb(0, 0, 0) }
+        }
+    }
+}
+
+/// 获取指定颜色的亮色或暗色，若指定颜色的R/G/B值其中最大的超过128，则获取暗色，否则获取亮色。
+///
+/// # Arguments
+///
+/// * `color`: 指定颜色。
+///
+/// returns: Color 返回对应的亮色或暗色。
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub fn get_lighter_or_darker_color(color: Color) -> Color {
+    let (r, g, b) = color.to_rgb();
+
+    let total = r as u16 + g as u16 + b as u16;
+    let max_c = max(r, max(g, b));
+    if total >= 383 || max_c as u16 + 127 > 255u16 {
+        // 当三原色合计值超过最大合计值的一半时，或者某项原色值超过128，降低各原色数值。效果是变暗。
+        let (cr, cg, cb) = (max(0i16, r as i16 - 127), max(0i16, g as i16 - 127), max(0i16, b as i16 - 127));
+        Color::from_rgb(cr as u8, cg as u8, cb as u8)
+    } else {
+        // 当三原色合计值小于最大合计值的一半时，提高各原色数值。效果是变亮。
+        let (cr, cg, cb) = (min(255i16, r as i16 + 127), min(255i16, g as i16 + 127), min(255i16, b as i16 + 127));
+        Color::from_rgb(cr as u8, cg as u8, cb as u8)
+    }
+}
+
+/// 计算滚动一格鼠标滚轮后的默认字体尺寸，结果限制在`MIN_ZOOM_FONT_SIZE`和`MAX_ZOOM_FONT_SIZE`之间。
+///
+/// # Arguments
+///
+/// * `size`: 当前默认字体尺寸。
+/// * `zoom_in`: true放大，false缩小。
+///
+/// returns: i32
+pub(crate) fn zoom_font_size(size: i32, zoom_in: bool) -> i32 {
+    let step = max(size / 8, 1);
+    let size = if zoom_in { size + step } else { size - step };
+    size.clamp(MIN_ZOOM_FONT_SIZE, MAX_ZOOM_FONT_SIZE)
+}
+
+/// 计算划选超出可视区域边界时每次自动滚动的距离，超出边界越远滚动越快，最大不超过`MAX_DRAG_SCROLL_STEP`。
+///
+/// # Arguments
+///
+/// * `overflow`: 鼠标超出上边界(负值)或下边界(正值)的距离。
+///
+/// returns: i32 向上滚动时为负值。
+pub(crate) fn drag_scroll_step(overflow: i32) -> i32 {
+    if overflow == 0 {
+        return 0;
+    }
+    overflow.signum() * min(overflow.abs() / 2 + 4, MAX_DRAG_SCROLL_STEP)
+}
+
+/// 计算分栏显示时每一栏的宽度。
+///
+/// # Arguments
+///
+/// * `drawable_width`: 扣除左右空白后的可绘制宽度。
+/// * `columns`: 分栏数量。
+///
+/// returns: i32
+pub(crate) fn flow_column_width(drawable_width: i32, columns: usize) -> i32 {
+    let columns = columns.clamp(1, MAX_COLUMNS) as i32;
+    max((drawable_width - COLUMN_GAP * (columns - 1)) / columns, 1)
+}
 
-                    let line = text.as_str();
-                    let (tw, _) = measure(line, false);
-                    let next_x = start_x + tw + self.piece_spacing;
-                    if next_x > max_width {
-                        // 超出横向右边界
-                        ret = self.wrap_text_for_estimate(line, ret.clone(), max_width, tw, ref_font_height);
-                    } else {
-                        let y = top_y;
-                        let through_line = ThroughLine::create_or_update(PADDING.left, start_x, ref_font_height, ret, false);
-                        let next_y = top_y;
-                        let new_piece = LinePiece::new(self.text.clone(), start_x, y, tw, ref_font_height, top_y, current_line_spacing, next_x, next_y, ref_font_height, font, font_size, through_line, self.v_bounds.clone());
-                        self.line_pieces.push(new_piece.clone());
-                        ret = new_piece;
+/// 将已按单栏宽度试算好的数据段依次排入并列的多栏中，像报纸一样先自上而下填满一栏再转入右侧的下一栏，
+/// 各栏都填满后在下方开始新的一页。数据段在缓存中的顺序不变，因此选择和复制仍然按照阅读顺序进行。
+///
+/// # Arguments
+///
+/// * `buffer`: 已完成试算的数据段。
+/// * `columns`: 分栏数量，不大于1时不做处理。
+/// * `column_width`: 每一栏的宽度。
+/// * `column_height`: 每一栏的高度，即一页的高度。
+/// * `padding_top`: 顶部空白，也是每一栏第一行的起始位置。
+///
+/// returns: ()
+pub(crate) fn flow_columns(buffer: &mut [RichData], columns: usize, column_width: i32, column_height: i32, padding_top: i32) {
+    let columns = min(columns, MAX_COLUMNS);
+    if columns <= 1 || column_height <= 0 {
+        return;
+    }
+    let stride = column_width + COLUMN_GAP;
+    let (mut column, mut page, mut shift) = (0, 0, 0);
+    let mut last_row = None;
+    for rd in buffer.iter_mut() {
+        let pieces: Vec<Arc<RwLock<LinePiece>>> = rd.line_pieces.iter()
+            .chain(rd.translation_pieces.iter())
+            .chain(rd.preview_piece.iter())
+            .cloned()
+            .collect();
+        let mut bounds = *rd.v_bounds.read();
+        if pieces.is_empty() {
+            // 结构标记没有分片，跟随当前位置移动。
+            let dy = page * column_height - shift;
+            bounds.0 += dy;
+            bounds.1 += dy;
+            *rd.v_bounds.write() = bounds;
+            continue;
+        }
+        let (mut top, mut bottom) = (i32::MAX, i32::MIN);
+        for (i, piece) in pieces.iter().enumerate() {
+            let lp = &mut *piece.write();
+            if last_row != Some(lp.top_y) {
+                // 同一行的分片共用行顶坐标，以整行为单位判断当前栏是否还能容纳，栏内第一行超高时不再转栏。
+                let row_bottom = lp.top_y + max(lp.through_line.read().max_h, lp.h);
+                if lp.top_y - shift > padding_top && row_bottom - shift > padding_top + column_height {
+                    shift = lp.top_y - padding_top;
+                    column += 1;
+                    if column == columns {
+                        column = 0;
+                        page += 1;
                     }
                 }
+                last_row = Some(lp.top_y);
             }
-            DataType::Image => {
-                let h = self.image_target_height + IMAGE_PADDING_V * 2;
-                if start_x + self.image_target_width > max_width {
-                    // 本行超宽，直接定位到下一行
-                    let x = PADDING.left + IMAGE_PADDING_H;
-                    let y = top_y + last_line_piece.through_line.read().max_h + IMAGE_PADDING_V;
-                    let next_x = x + self.image_target_width + IMAGE_PADDING_H;
-                    let next_y = y - IMAGE_PADDING_V;
-                    let piece_top_y = y - IMAGE_PADDING_V;
-                    let through_line = ThroughLine::new(self.image_target_height * IMAGE_PADDING_V * 2, true);
-                    let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, next_y, 1, font, font_size, through_line, self.v_bounds.clone());
-                    self.line_pieces.push(new_piece.clone());
-                    ret = new_piece;
-                } else {
-                    let x = start_x + IMAGE_PADDING_H;
-                    let next_x = start_x + self.image_target_width + IMAGE_PADDING_H * 2 + self.piece_spacing;
-                    if last_line_piece.line.ends_with("\n") {
-                        // 定位在行首
-                        let y = top_y + IMAGE_PADDING_V;
-                        let piece_top_y = y - IMAGE_PADDING_V;
-                        let through_line = ThroughLine::new(self.image_target_height * IMAGE_PADDING_V * 2, true);
-                        let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, top_y, 1, font, font_size, through_line, self.v_bounds.clone());
-                        self.line_pieces.push(new_piece.clone());
-                        ret = new_piece;
-                    } else {
-                        // 在本行已有其他内容，需要与前一个片段协调行高
-                        let current_line_height = max(last_line_piece.h, h);
-                        let mut raw_y = top_y + IMAGE_PADDING_V;
-                        if current_line_height > last_line_piece.h {
-                            // 图形比前一个分片行高要高
-                            last_line_piece.through_line.write().set_max_h(current_line_height);
-                        } else {
-                            // 图形的高度小于等于前一个分片的行高，需要计算垂直居中位置
-                            let (up, _) = calc_v_center_offset(current_line_height, h);
-                            raw_y += up;
-                        }
-                        let y = raw_y;
-                        let piece_top_y = y - IMAGE_PADDING_V;
-                        let through_line = ThroughLine::create_or_update(PADDING.left + IMAGE_PADDING_H, x, self.image_target_height * IMAGE_PADDING_V * 2, ret, true);
-                        let new_piece = LinePiece::new("".to_string(), x, y, self.image_target_width, self.image_target_height, piece_top_y, last_line_piece.spacing, next_x, top_y + IMAGE_PADDING_V, 1, font, font_size, through_line, self.v_bounds.clone());
-                        self.line_pieces.push(new_piece.clone());
-                        ret = new_piece;
+            let (dx, dy) = (column as i32 * stride, page * column_height - shift);
+            lp.x += dx;
+            lp.next_x += dx;
+            lp.y += dy;
+            lp.top_y += dy;
+            lp.next_y += dy;
+            top = min(top, lp.y);
+            bottom = max(bottom, lp.y + lp.h);
+            if i == 0 {
+                bounds.2 += dx;
+            }
+            if i + 1 == rd.line_pieces.len() {
+                bounds.3 += dx;
+            }
+        }
+        bounds.0 = top;
+        bounds.1 = bottom;
+        *rd.v_bounds.write() = bounds;
+    }
+}
+
+/// 分栏显示的几何参数，与`flow_columns`的排列规则一致，用于按照阅读顺序比较坐标的先后，以及将滚动位置对齐到页首。
+/// 单栏时退化为按纵坐标比较。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ColumnLayout {
+    columns: usize,
+    column_width: i32,
+    column_height: i32,
+    left: i32,
+    top: i32,
+}
+
+impl Default for ColumnLayout {
+    fn default() -> Self {
+        Self { columns: 1, column_width: 1, column_height: 1, left: 0, top: 0 }
+    }
+}
+
+impl ColumnLayout {
+    /// 根据面板尺寸计算分栏参数。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns`: 分栏数量。
+    /// * `panel_width`: 面板宽度。
+    /// * `panel_height`: 面板高度。
+    /// * `padding`: 面板边界空白。
+    ///
+    /// returns: ColumnLayout
+    pub(crate) fn new(columns: usize, panel_width: i32, panel_height: i32, padding: &Padding) -> Self {
+        let columns = columns.clamp(1, MAX_COLUMNS);
+        Self {
+            columns,
+            column_width: flow_column_width(padding.drawable_width(panel_width), columns),
+            column_height: max(padding.drawable_height(panel_height), 1),
+            left: padding.left,
+            top: padding.top,
+        }
+    }
+
+    /// 是否分栏显示。
+    pub(crate) fn is_flowing(&self) -> bool {
+        self.columns > 1
+    }
+
+    /// 计算坐标所在的页和栏，单栏时总是返回`(0, 0)`。
+    pub(crate) fn cell_of(&self, x: i32, y: i32) -> (i32, usize) {
+        if !self.is_flowing() {
+            return (0, 0);
+        }
+        let page = (y - self.top).div_euclid(self.column_height);
+        let column = (x - self.left).div_euclid(self.column_width + COLUMN_GAP).clamp(0, self.columns as i32 - 1) as usize;
+        (page, column)
+    }
+
+    /// 按照阅读顺序比较两个坐标的先后：先比较所在的页和栏，同一栏内再比较纵坐标。
+    pub(crate) fn cmp_points(&self, a: (i32, i32), b: (i32, i32)) -> Ordering {
+        self.cell_of(a.0, a.1).cmp(&self.cell_of(b.0, b.1)).then(a.1.cmp(&b.1))
+    }
+
+    /// 获取纵坐标所在页的顶部位置，单栏时原样返回。
+    pub(crate) fn page_top(&self, y: i32) -> i32 {
+        if !self.is_flowing() {
+            return y;
+        }
+        self.top + (y - self.top).div_euclid(self.column_height) * self.column_height
+    }
+}
+
+/// 输入行模式下由组件管理的单行输入内容，支持本地行编辑和历史记录。插入点以字符位置表示，始终位于字素簇边界上。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PromptLine {
+    pub(crate) text: String,
+    /// 插入点所在的字符位置。
+    pub(crate) cursor: usize,
+    /// 已提交的输入，最新的在末尾。
+    history: Vec<String>,
+    /// 正在浏览的历史记录位置，为空表示正在编辑新的输入。
+    history_pos: Option<usize>,
+    /// 开始浏览历史记录前正在编辑的内容。
+    draft: String,
+}
+
+impl PromptLine {
+    /// 字符位置对应的字节位置。
+    fn byte_index(&self, pos: usize) -> usize {
+        self.text.char_indices().nth(pos).map_or(self.text.len(), |(i, _)| i)
+    }
+
+    /// 在插入点插入文字，控制字符被忽略。
+    pub(crate) fn insert(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        let i = self.byte_index(self.cursor);
+        self.text.insert_str(i, &text);
+        self.cursor += text.chars().count();
+    }
+
+    /// 处理输入法组字：先删除插入点之前`del`字节的预编辑文字，再插入新的预编辑文字或最终提交的文字。
+    pub(crate) fn compose(&mut self, del: usize, text: &str) {
+        let end = self.byte_index(self.cursor);
+        let mut start = end.saturating_sub(del);
+        while !self.text.is_char_boundary(start) {
+            start -= 1;
+        }
+        self.cursor -= self.text[start..end].chars().count();
+        self.text.replace_range(start..end, "");
+        self.insert(text);
+    }
+
+    /// 删除插入点之前的一个字素簇。
+    pub(crate) fn backspace(&mut self) {
+        let bounds = grapheme_char_bounds(&self.text);
+        if let Some(&from) = bounds.iter().rev().find(|&&b| b < self.cursor) {
+            let (i, j) = (self.byte_index(from), self.byte_index(self.cursor));
+            self.text.replace_range(i..j, "");
+            self.cursor = from;
+        }
+    }
+
+    /// 删除插入点之后的一个字素簇。
+    pub(crate) fn delete(&mut self) {
+        let bounds = grapheme_char_bounds(&self.text);
+        if let Some(&to) = bounds.iter().find(|&&b| b > self.cursor) {
+            let (i, j) = (self.byte_index(self.cursor), self.byte_index(to));
+            self.text.replace_range(i..j, "");
+        }
+    }
+
+    /// 将插入点向前或向后移动一个字素簇。
+    pub(crate) fn move_cursor(&mut self, forward: bool) {
+        let bounds = grapheme_char_bounds(&self.text);
+        let next = if forward {
+            bounds.iter().find(|&&b| b > self.cursor)
+        } else {
+            bounds.iter().rev().find(|&&b| b < self.cursor)
+        };
+        if let Some(&pos) = next {
+            self.cursor = pos;
+        }
+    }
+
+    /// 将插入点移动到行首或行尾。
+    pub(crate) fn move_to_edge(&mut self, end: bool) {
+        self.cursor = if end { self.text.chars().count() } else { 0 };
+    }
+
+    /// 替换输入内容，插入点移动到行尾。
+    pub(crate) fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.cursor = 0;
+        self.insert(text);
+    }
+
+    /// 浏览较早或较新的历史记录，回到最新位置时恢复浏览前正在编辑的内容。
+    pub(crate) fn browse_history(&mut self, older: bool) {
+        let pos = match (self.history_pos, older) {
+            (None, true) if !self.history.is_empty() => {
+                self.draft = self.text.clone();
+                Some(self.history.len() - 1)
+            }
+            (Some(pos), true) => Some(pos.saturating_sub(1)),
+            (Some(pos), false) if pos + 1 < self.history.len() => Some(pos + 1),
+            (Some(_), false) => None,
+            _ => return,
+        };
+        self.history_pos = pos;
+        let text = match pos {
+            Some(pos) => self.history[pos].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.set_text(&text);
+    }
+
+    /// 提交输入内容并清空输入行，非空内容被加入历史记录。
+    pub(crate) fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.text);
+        self.cursor = 0;
+        self.history_pos = None;
+        self.draft.clear();
+        if !line.is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > PROMPT_HISTORY_CAPACITY {
+                self.history.remove(0);
+            }
+        }
+        line
+    }
+}
+
+/// 默认字体尺寸变化后，按相同比例缩放文本数据段的字体尺寸，使用默认字体尺寸的数据段缩放后与新的默认尺寸一致。
+/// 图片数据段保持原有尺寸。缩放后需重新计算数据段的绘制位置。
+///
+/// # Arguments
+///
+/// * `rd`: 数据段。
+/// * `old_size`: 原默认字体尺寸。
+/// * `new_size`: 新默认字体尺寸。
+///
+/// returns: ()
+pub(crate) fn scale_font_size(rd: &mut RichData, old_size: i32, new_size: i32) {
+    if rd.data_type == DataType::Text && old_size > 0 {
+        rd.font_size = if rd.font_size == old_size {
+            new_size
+        } else {
+            max((rd.font_size as f32 * new_size as f32 / old_size as f32).round() as i32, 1)
+        };
+    }
+}
+
+/// 获取指定字体对应的粗体字体，没有对应粗体的字体使用`Helvetica`粗体代替。
+///
+/// # Arguments
+///
+/// * `font`: 指定字体。
+///
+/// returns: Font
+pub(crate) fn bold_font(font: Font) -> Font {
+    match font {
+        Font::HelveticaItalic | Font::HelveticaBoldItalic => Font::HelveticaBoldItalic,
+        Font::Courier | Font::CourierBold => Font::CourierBold,
+        Font::CourierItalic | Font::CourierBoldItalic => Font::CourierBoldItalic,
+        Font::Times | Font::TimesBold => Font::TimesBold,
+        Font::TimesItalic | Font::TimesBoldItalic => Font::TimesBoldItalic,
+        _ => Font::HelveticaBold,
+    }
+}
+
+/// 获取指定字体对应的斜体字体，没有对应斜体的字体使用`Helvetica`斜体代替。
+///
+/// # Arguments
+///
+/// * `font`: 指定字体。
+///
+/// returns: Font
+pub(crate) fn italic_font(font: Font) -> Font {
+    match font {
+        Font::HelveticaBold | Font::HelveticaBoldItalic => Font::HelveticaBoldItalic,
+        Font::Courier | Font::CourierItalic => Font::CourierItalic,
+        Font::CourierBold | Font::CourierBoldItalic => Font::CourierBoldItalic,
+        Font::Times | Font::TimesItalic => Font::TimesItalic,
+        Font::TimesBold | Font::TimesBoldItalic => Font::TimesBoldItalic,
+        _ => Font::HelveticaItalic,
+    }
+}
+
+/// 获取斜体字体对应的正体字体，其他字体保持不变。
+///
+/// # Arguments
+///
+/// * `font`: 字体。
+///
+/// returns: Font
+pub(crate) fn upright_font(font: Font) -> Font {
+    match font {
+        Font::HelveticaItalic => Font::Helvetica,
+        Font::HelveticaBoldItalic => Font::HelveticaBold,
+        Font::CourierItalic => Font::Courier,
+        Font::CourierBoldItalic => Font::CourierBold,
+        Font::TimesItalic => Font::Times,
+        Font::TimesBoldItalic => Font::TimesBold,
+        _ => font,
+    }
+}
+
+/// 按照制表位将文本中的`'\t'`展开为空格，使其后的文字对齐到下一个制表位，遇到换行符时列位置归零。
+///
+/// # Arguments
+///
+/// * `text`: 原始文本。
+/// * `start_col`: 文本起始位置所在的列，从0开始。
+/// * `tab_width`: 默认制表位间隔的列数。
+/// * `tab_stops`: 自定义制表位所在的列，为空时使用默认制表位。自定义制表位之后没有更多制表位时，与终端一样移动到右边界。
+/// * `right_margin`: 右边界所在的列，从0开始。
+///
+/// returns: (String, usize) 展开后的文本，以及文本结束位置所在的列。
+pub(crate) fn expand_tabs(text: &str, start_col: usize, tab_width: usize, tab_stops: Option<&BTreeSet<usize>>, right_margin: usize) -> (String, usize) {
+    let mut col = start_col;
+    let mut expanded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let next = match tab_stops {
+                    Some(stops) => stops.range((col + 1)..).next().copied().unwrap_or(max(right_margin, col)),
+                    None => {
+                        let width = max(tab_width, 1);
+                        (col / width + 1) * width
                     }
+                };
+                expanded.push_str(&" ".repeat(next - col));
+                col = next;
+            }
+            '\n' => {
+                expanded.push(c);
+                col = 0;
+            }
+            '\r' => {
+                expanded.push(c);
+            }
+            _ => {
+                expanded.push(c);
+                col += char_columns(c);
+            }
+        }
+    }
+    (expanded, col)
+}
+
+/// 字符在终端中占用的列数，规则与`wcwidth`一致：中日韩文字、全角符号和大部分表情符号占两列，
+/// 组合字符、零宽字符和控制字符不占列，其他字符占一列。
+///
+/// # Arguments
+///
+/// * `c`: 字符。
+///
+/// returns: usize
+pub(crate) fn char_columns(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+/// 文本在终端中占用的列数。
+pub(crate) fn text_columns(text: &str) -> usize {
+    text.chars().map(char_columns).sum()
+}
+
+/// 从文本开头截取至少占满指定列数的部分，宽字符跨越边界时整体截入，紧随其后的零宽字符一并截入。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `cols`: 列数。
+///
+/// returns: (usize, usize) 截取部分的字节长度，以及截取部分实际占用的列数。文本不足指定列数时返回整个文本。
+pub(crate) fn take_columns(text: &str, cols: usize) -> (usize, usize) {
+    let (mut bytes, mut taken) = (0, 0);
+    for c in text.chars() {
+        let width = char_columns(c);
+        if taken >= cols && width > 0 {
+            break;
+        }
+        bytes += c.len_utf8();
+        taken += width;
+    }
+    (bytes, taken)
+}
+
+/// 从文本开头截取不超过指定列数的部分，宽字符跨越边界时整体舍弃，已截入字符之后的零宽字符一并截入。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `cols`: 列数。
+///
+/// returns: (usize, usize) 截取部分的字节长度，以及截取部分实际占用的列数。
+pub(crate) fn fit_columns(text: &str, cols: usize) -> (usize, usize) {
+    let (mut bytes, mut taken) = (0, 0);
+    for c in text.chars() {
+        let width = char_columns(c);
+        if taken + width > cols {
+            break;
+        }
+        bytes += c.len_utf8();
+        taken += width;
+    }
+    (bytes, taken)
+}
+
+/// 文本中各字素簇的起始字符位置，末尾附加文本的字符总数。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+///
+/// returns: Vec<usize>
+pub(crate) fn grapheme_char_bounds(text: &str) -> Vec<usize> {
+    let mut bounds = Vec::new();
+    let mut chars = 0;
+    for g in text.graphemes(true) {
+        bounds.push(chars);
+        chars += g.chars().count();
+    }
+    bounds.push(chars);
+    bounds
+}
+
+/// 判断两个相邻字素簇之间是否允许断行：前一个字素簇为空白或断行标点，或后一个字素簇为空白时允许断行；
+/// 允许在中日韩文字之间断行时，相邻任一字素簇为宽字符即允许断行。
+///
+/// # Arguments
+///
+/// * `prev`: 断行位置之前的字素簇。
+/// * `next`: 断行位置之后的字素簇。
+/// * `cjk`: 是否允许在中日韩文字之间断行。
+///
+/// returns: bool
+pub(crate) fn is_break_opportunity(prev: &str, next: &str, cjk: bool) -> bool {
+    let (p, n) = match (prev.chars().next(), next.chars().next()) {
+        (Some(p), Some(n)) => (p, n),
+        _ => return true,
+    };
+    if p.is_whitespace() || n.is_whitespace() || matches!(p, '-' | '/' | ',' | '.' | ';' | ':' | '!' | '?' | ')' | ']' | '}') {
+        return true;
+    }
+    cjk && (char_columns(p) > 1 || char_columns(n) > 1)
+}
+
+/// 将字符区间扩展到完整的字素簇：起点向前对齐到所在字素簇的开头，终点向后对齐到所在字素簇的末尾。
+///
+/// # Arguments
+///
+/// * `text`: 文本。
+/// * `from`: 起始字符位置。
+/// * `to`: 结束字符位置，不包含。
+///
+/// returns: (usize, usize)
+pub(crate) fn snap_to_graphemes(text: &str, from: usize, to: usize) -> (usize, usize) {
+    let bounds = grapheme_char_bounds(text);
+    let from = match bounds.binary_search(&from) {
+        Ok(i) => bounds[i],
+        Err(i) => bounds[i.saturating_sub(1)],
+    };
+    let to = match bounds.binary_search(&to) {
+        Ok(i) => bounds[i],
+        Err(i) => *bounds.get(i).unwrap_or(&to),
+    };
+    (from, to)
+}
+
+/// 使符合过滤条件的目标数据段过期、禁用。
+///
+/// # Arguments
+///
+/// * `target`:
+///
+/// returns: ()
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub(crate) fn expire_data(buffer: Arc<RwLock<Vec<RichData>>>, target: &String) {
+    for rd in buffer.write().iter_mut() {
+        let mut should_expire = false;
+        if let Some(action) = &rd.action {
+            if let Some(cat) = &action.category {
+                if target.eq(cat) {
+                    should_expire = true;
                 }
             }
         }
+        if should_expire {
+            expire_rich_data(rd);
+        }
+    }
+}
+
+/// 数据段过期后的显示效果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExpireEffect {
+    /// 以删除线显示。
+    #[default]
+    StrikeThrough,
+    /// 以暗淡的颜色显示。
+    Dim,
+    /// 从缓存中移除。
+    Remove,
+}
+
+/// 过期数据段的筛选条件。
+#[derive(Debug, Clone)]
+pub enum ExpireFilter {
+    /// 互动行为类别与指定值完全相同，与`DocEditType::Expire`的规则一致。
+    Category(String),
+    /// 添加到组件后经过的时长超过指定值。
+    OlderThan(Duration),
+    /// 数据段ID在指定范围内。
+    IdRange(RangeInclusive<i64>),
+    /// 文本与正则表达式匹配。
+    TextMatches(Regex),
+}
+
+impl ExpireFilter {
+    /// 判断数据段是否符合筛选条件。
+    ///
+    /// # Arguments
+    ///
+    /// * `rd`: 数据段。
+    /// * `now`: 当前时刻。
+    ///
+    /// returns: bool
+    pub(crate) fn matches(&self, rd: &RichData, now: Instant) -> bool {
+        match self {
+            ExpireFilter::Category(target) => rd.action.as_ref().and_then(|action| action.category.as_ref()).is_some_and(|cat| cat == target),
+            ExpireFilter::OlderThan(age) => now.saturating_duration_since(rd.arrived_at) > *age,
+            ExpireFilter::IdRange(range) => range.contains(&rd.id),
+            ExpireFilter::TextMatches(pattern) => pattern.is_match(&rd.text),
+        }
+    }
+}
+
+/// 按照指定效果使数据段过期。`ExpireEffect::Remove`需由调用方从缓存中移除，此处按删除线效果处理。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `ids`: 目标数据段ID。
+/// * `effect`: 过期效果。
+///
+/// returns: ()
+pub(crate) fn expire_data_with_effect(buffer: &mut [RichData], ids: &HashSet<i64>, effect: ExpireEffect) {
+    for rd in buffer.iter_mut() {
+        if rd.in_ids(ids) {
+            let strike_through = rd.strike_through;
+            expire_rich_data(rd);
+            if effect == ExpireEffect::Dim {
+                rd.strike_through = strike_through;
+                rd.faint = true;
+            }
+        }
+    }
+}
 
-        let (mut _is_first_line, mut bound_start_x, mut bound_end_x) = (true, 0, 0);
-        let mut to_be_updated: Vec<(Arc<RwLock<LinePiece>>, i32)> = Vec::new();
-        for line_piece in self.line_pieces.iter() {
-            let lp = &*line_piece.read();
-            if _is_first_line {
-                bound_start_x = lp.x;
-                _is_first_line = false;
+/// 使指定ID的数据段过期、禁用。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存。
+/// * `ids`: 目标数据段ID。
+///
+/// returns: ()
+pub(crate) fn expire_data_by_ids(buffer: &mut [RichData], ids: &HashSet<i64>) {
+    for rd in buffer.iter_mut() {
+        if rd.in_ids(ids) {
+            expire_rich_data(rd);
+        }
+    }
+}
+
+/// 找出因所属线索被折叠而应隐藏的数据段ID，被折叠数据段的所有后代数据段都应隐藏，折叠的数据段自身仍然显示。
+///
+/// # Arguments
+///
+/// * `buffer`: 数据缓存，可以是多段缓存依次连接而成。
+/// * `collapsed`: 已折叠的线索根数据段ID。
+///
+/// returns: HashSet<i64>
+pub(crate) fn thread_hidden_ids<'a, I>(buffer: I, collapsed: &HashSet<i64>) -> HashSet<i64> where I: IntoIterator<Item = &'a RichData> + Clone {
+    let parents: HashMap<i64, i64> = buffer.clone().into_iter().filter_map(|rd| rd.parent_id.map(|p| (rd.id, p))).collect();
+    let mut hidden = HashSet::new();
+    for rd in buffer {
+        // 沿父数据段向上查找，任意一级祖先被折叠时隐藏。限制查找深度以防止循环引用。
+        let mut ancestor = rd.parent_id;
+        let mut depth = 0;
+        while let Some(id) = ancestor {
+            if collapsed.contains(&id) {
+                hidden.insert(rd.id);
+                break;
+            }
+            depth += 1;
+            if depth > parents.len() {
+                break;
             }
+            ancestor = parents.get(&id).copied();
+        }
+    }
+    hidden
+}
 
-            let tl = &mut *lp.through_line.write();
-            let mut max_h = 1;
-            // 找出最大的行高
-            for l in tl.ys.iter() {
-                if let Some(l) = l.upgrade() {
-                    if l.read().h > max_h {
-                        max_h = l.read().h;
+/// 使数据段过期，移除互动属性，并按照组件设置的禁用样式显示。
+fn expire_rich_data(rd: &mut RichData) {
+    rd.action = None;
+    rd.expired = true;
+    rd.clickable = false;
+    rd.disabled = true;
+    if rd.data_type == DataType::Text && rd.disabled_style == DisabledStyle::Strike {
+        rd.strike_through = true;
+    }
+    rd.refresh_inactive_image();
+}
+
+/// 加载图片文件并生成面板更新信息。
+///
+/// # Arguments
+///
+/// * `data_id`: 数据段ID。
+/// * `file_path`: 文件路径。
+/// * `target_width`: 图片目标宽度，可能与图片原始宽度不同。
+/// * `target_height`: 图片目标高度，可能与图片原始高度不同。
+///
+/// returns: RichDataOptions
+///
+/// # Examples
+///
+/// ```
+///
+/// ```
+pub fn load_image_from_file(load_opt: LoadImageOption) -> RichDataOptions {
+    let mut update_opt = RichDataOptions::new(load_opt.data_id);
+    if let Some(file_path) = load_opt.file_path {
+        if file_path.to_lowercase().ends_with(".svg") {
+            // 对于SVG格式的文件要特殊处理一下: normalize()，否则会转换出错。
+            match SvgImage::load(file_path.clone()) {
+                Ok(mut si) => {
+                    // debug!("开始转换到RGB格式，文件：{:?}", file_path);
+                    si.normalize();
+                    match si.to_rgb() {
+                        Ok(new_img) => {
+                            let mut new_action = Action::default();
+                            new_action.items.push(ActionItem::new("刷新", MXP_IMAGE_CONTEXT_MENU_REFRESH));
+                            new_action.items.push(ActionItem::new("复制地址", MXP_IMAGE_CONTEXT_MENU_COPY_URL));
+                            new_action.items.push(ActionItem::new("另存为", MXP_IMAGE_CONTEXT_MENU_SAVE_AS));
+                            update_opt = update_opt.image(Some(new_img), load_opt.target_width, load_opt.target_height)
+                                .text(String::new())
+                                .image_file_path(PathBuf::from(file_path))
+                                .change_action(new_action);
+                        }
+                        Err(e) => {
+                            error!("将SVG转换到RGB格式时失败：{:?}", e);
+                        }
                     }
                 }
+                Err(e) => {
+                    error!("加载或解码图片失败：{:?} {:?}", file_path, e);
+                    update_opt = update_opt.text("decoding failed".to_string());
+                }
             }
-            tl.max_h = max_h;
-            // 收集同一行内低于最大高度的分片。因为borrow作用域的问题，无法在一个for循环内直接处理，只能先收集再处理。
-            for one_piece in tl.ys.iter() {
-                if let Some(p) = one_piece.upgrade() {
-                    let lh = p.read().h;
-                    if lh < max_h {
-                        to_be_updated.push((p.clone(), max_h));
+        } else {
+            match SharedImage::load(file_path.clone()) {
+                Ok(si) => {
+                    // debug!("开始转换到RGB格式，文件：{:?}", file_path);
+                    match si.to_rgb() {
+                        Ok(new_img) => {
+                            let mut new_action = Action::default();
+                            new_action.items.push(ActionItem::new("刷新", MXP_IMAGE_CONTEXT_MENU_REFRESH));
+                            new_action.items.push(ActionItem::new("复制地址", MXP_IMAGE_CONTEXT_MENU_COPY_URL));
+                            new_action.items.push(ActionItem::new("另存为", MXP_IMAGE_CONTEXT_MENU_SAVE_AS));
+                            update_opt = update_opt.image(Some(new_img), load_opt.target_width, load_opt.target_height)
+                                .text(String::new())
+                                .image_file_path(PathBuf::from(file_path))
+                                .change_action(new_action);
+                        }
+                        Err(e) => {
+                            error!("将通用格式转换到RGB格式时失败：{:?}", e);
+                        }
                     }
                 }
+                Err(e) => {
+                    error!("加载或解码图片失败：{:?} {:?}", file_path, e);
+                    update_opt = update_opt.text("decoding failed".to_string());
+                }
             }
         }
 
-        // 计算文字片段字体高度小于行高时的渲染偏移量
-        self.line_pieces.iter_mut().for_each(|lp| lp.write().calc_offset());
+    } else {
+        update_opt = update_opt.text("save failed".to_string());
+    }
+    update_opt
+}
 
-        // 重新计算同一行内低于最大行高的片段的y坐标
-        for (lp, max_h) in to_be_updated {
-            let y = lp.read().y;
-            let piece_top_y = lp.read().top_y;
-            let h = lp.read().h;
+#[cfg(test)]
+mod tests {
+    use fltk::enums::{Color, ColorDepth, Font};
+    use fltk::image::RgbImage;
+    use fltk::prelude::ImageExt;
+    use std::collections::{BTreeSet, HashSet};
+    use std::time::{Duration, Instant};
+    use crate::{get_contrast_color, get_lighter_or_darker_color, WHITE, Rectangle, UserData, RichData, DataType, italic_font, expand_tabs, TerminalModes, MouseReportMode, DEFAULT_ANSI_PALETTE, word_around, url_around, urls_in, char_columns, text_columns, take_columns, fit_columns, dim_image_data, thread_hidden_ids, WrapCache, WrapKey, RecentUrls, WrapValue, CursorStyle, BlinkState, BlinkDegree, BlinkSpeed, zoom_font_size, scale_font_size, MIN_ZOOM_FONT_SIZE, MAX_ZOOM_FONT_SIZE, UnderlineStyle, grapheme_char_bounds, snap_to_graphemes, is_break_opportunity, IdlePolicy, IDLE_AFTER_SECS, Padding, PADDING, QUOTE_INDENT_WIDTH, DividerStyle, Table, TableCell, fit_column_widths, RichDataOptions, update_data_properties, bold_font, HighlightId, HighlightRule, StyleOverride, apply_highlights, id_range, expire_data_by_ids, update_origin_data, Callback, CallbackData, EventKind, NamedNotifier, ScrollInfo, HoverTracker, HOVER_MIN_INTERVAL, TooltipState, wrap_tooltip, ContextMenuHook, MenuBuilder, LinePiece, ThroughLine, selection_contains, selection_text, parse_dropped_files, drag_out_started, DRAG_OUT_THRESHOLD, AppendQueue, DocEditType, StyleDef, ExpireEffect, ExpireFilter, expire_data_with_effect, Action, ActionItem, write_session, read_session, parse_font, to_rgb8, PngEncoder, paginate, PRINT_ACCENT_COLOR, StableHasher, PagePrefetch, SearchRequest, search_positions, KeywordHighlight, keyword_ranges, SelectionAnchor, selection_range, keep_selection, clear_selected_pieces, drag_scroll_step, MAX_DRAG_SCROLL_STEP, PromptLine, echo_lines, ECHO_MAX_LINES, PinnedRows, flow_column_width, flow_columns, COLUMN_GAP, ColumnLayout, ClickPoint, locate_target_rd, _search_row_idx_in_columns, IMAGE_PADDING_H, locate_data_at, contrast_ratio, ensure_contrast, ColorVision, ColorFilter, ColorTransform, ColorTransformScope, transform_color, compose_palette, DEUTERANOPIA_ANSI_PALETTE, DisabledStyle, TextShadow, BackgroundStyle, gradient_color, BubbleAlign, BubbleStyle, ICON_GAP, detach_buffer, Theme, retheme_data, RenderOptions};
+    use crate::log_file::{scan_line_starts, segment_id, line_of, parse_ansi_line, format_log_entry, LogSink, LogSinkFormat, LogFile, PlainText};
+    use crate::markdown::{to_user_data, MarkdownStyle};
+    use crate::html::{to_user_data as html_to_user_data, HtmlStyle, parse_color, decode_entities};
+    use crate::mxp::{to_user_data as mxp_to_user_data, MxpStyle};
+    use crate::accessibility::{SpeechLines, visible_text};
 
-            if lp.read().line.ends_with("\n") {
-                let mut padding_v = 0;
-                if lp.read().through_line.read().exist_image {
-                    padding_v = IMAGE_PADDING_V;
-                }
-                lp.write().next_y = y + max_h + padding_v;
-            }
+    #[test]
+    pub fn make_rectangle_test() {
+        let rect = Rectangle::new(20, 30, 25, 25);
+        assert_eq!(rect.tup(), (20, 30, 25, 25));
 
-            let (up_offset, _) = calc_v_center_offset(max_h, h);
-            lp.write().y = piece_top_y + up_offset;
-            let lpm = &mut*lp.write();
-            let mut vb = *lpm.rd_bounds.write();
-            vb.1 = piece_top_y + up_offset + lpm.h;
-            *lpm.rd_bounds.write() = vb;
-        }
+        let rect = Rectangle::new(20, 30, -10, 25);
+        assert_eq!(rect.tup(), (10, 30, 10, 25));
 
-        // let mut pic_y = 0;
-        let v_b_top_y = if let Some(first_piece) = self.line_pieces.first() {
-            let fp = &*first_piece.read();
-            fp.top_y
-            // pic_y = fp.y;
-        } else {
-            top_y
-        };
-        let v_b_bottom_y = if let Some(last_piece) = self.line_pieces.last() {
-            let lp = &*last_piece.read();
-            let bottom_y = lp.top_y + lp.through_line.read().max_h;
-            bound_end_x = lp.x + lp.w;
-            bottom_y
-        } else {
-            v_b_top_y
-        };
-        // debug!("estimated v_b_top_y: {v_b_top_y}, v_b_bottom_y: {v_b_bottom_y}, bound_start_x: {bound_start_x}, bound_end_x: {bound_end_x}, text: {}", self.text);
-        self.set_v_bounds(v_b_top_y, v_b_bottom_y, bound_start_x, bound_end_x);
-        ret
+        let rect = Rectangle::new(20, 30, 10, -25);
+        assert_eq!(rect.tup(), (20, 5, 10, 25));
+
+        let rect = Rectangle::new(20, 30, -10, -25);
+        assert_eq!(rect.tup(), (10, 5, 10, 25));
     }
-}
 
-/// 数据片段调整属性。
-#[derive(Debug, Clone)]
-pub struct RichDataOptions {
-    pub id: i64,
-    pub clickable: Option<bool>,
-    pub underline: Option<bool>,
-    pub expired: Option<bool>,
-    pub text: Option<String>,
-    pub fg_color: Option<Color>,
-    pub bg_color: Option<Color>,
-    pub strike_through: Option<bool>,
-    pub blink: Option<bool>,
-    pub disabled: Option<bool>,
-    pub image: Option<Vec<u8>>,
-    image_width: Option<i32>,
-    image_height: Option<i32>,
-    pub image_target_width: Option<i32>,
-    pub image_target_height: Option<i32>,
-    pub image_color_depth: Option<ColorDepth>,
-    /// 图片文件临时存储路径。
-    pub image_file_path: Option<PathBuf>,
-    pub action: Option<Action>,
-}
+    #[test]
+    pub fn str_index_test() {
+        let str = String::from("我爱中国");
+        assert_eq!(str.find("中国"), Some(6));
 
-impl RichDataOptions {
-    pub fn new(id: i64) -> RichDataOptions {
-        RichDataOptions {
-            id,
-            clickable: None,
-            underline: None,
-            expired: None,
-            text: None,
-            fg_color: None,
-            bg_color: None,
-            strike_through: None,
-            blink: None,
-            disabled: None,
-            image: None,
-            image_width: None,
-            image_height: None,
-            image_target_width: None,
-            image_target_height: None,
-            image_color_depth: None,
-            image_file_path: None,
-            action: None,
+        str.rmatch_indices("中国").for_each(|(i, _)| {
+            let ni = str[0..i].chars().count();
+            assert_eq!(ni, 2);
+        })
+    }
+
+    #[test]
+    pub fn test_contrast_color_test() {
+        assert_eq!(get_contrast_color(Color::from_rgb(255, 255, 255)), Color::from_rgb(0, 0, 0));
+        assert_eq!(get_contrast_color(Color::from_rgb(0, 0, 0)), Color::from_rgb(255, 255, 255));
+        for i in 1..116 {
+            assert_ne!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
+        }
+        for i in 116..=139 {
+            assert_eq!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
+        }
+        for i in 140..=255 {
+            assert_ne!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
         }
     }
 
-    pub fn clickable(mut self, clickable: bool) -> RichDataOptions {
-        self.clickable = Some(clickable);
-        self
+    #[test]
+    pub fn get_lighter_color_test() {
+        let lighter = get_lighter_or_darker_color(Color::DarkCyan);
+        println!("{:?} -> {:?}", Color::DarkCyan.to_rgb(), lighter.to_rgb())
+    }
+
+    #[test]
+    pub fn emoji_test() {
+        let emoji = "😀";
+        println!("{:?}", emoji.chars().nth(0).unwrap());
+        assert_eq!(emoji.len(), 1);
+    }
+
+    #[test]
+    pub fn fold_chars_test() {
+        let hint = "这里是一个空旷的广场，地面上散落着一些碎纸片。";
+        let mut count = 0;
+        let new_hint = hint.chars().fold("".to_string(), |mut s, c| {
+            s.push(c);
+            count += 1;
+            if count % 8 == 0 {
+                s.push_str("\r\n");
+            }
+
+            s
+        });
+        println!("{:?}", new_hint);
+    }
+
+    #[test]
+    pub fn c1_test() {
+        let s = String::from_utf8_lossy(&[0xe2, 0x96, 0xbd]);
+        println!("{}", s);
     }
 
-    pub fn underline(mut self, underline: bool) -> RichDataOptions {
-        self.underline = Some(underline);
-        self
+    #[test]
+    pub fn marker_data_test() {
+        let ud = UserData::new_marker("read".to_string(), Some("meta".to_string()));
+        let rd: RichData = ud.into();
+        assert_eq!(rd.data_type, DataType::Marker);
+        assert!(rd.text.is_empty());
+        let marker = rd.marker.clone().unwrap();
+        assert_eq!(marker.kind, "read");
+        assert_eq!(marker.meta, Some("meta".to_string()));
+
+        let ud: UserData = (&rd).into();
+        assert_eq!(ud.data_type, DataType::Marker);
+        assert!(ud.marker.is_some());
     }
 
-    pub fn expired(mut self, expired: bool) -> RichDataOptions {
-        self.expired = Some(expired);
-        self
+    #[test]
+    pub fn retheme_data_test() {
+        let mut plain: RichData = UserData::new_text("plain".to_string()).set_font_and_size(Font::Helvetica, 14).into();
+        plain.fg_color = WHITE;
+        let italic: RichData = UserData::new_text("italic".to_string()).set_font_and_size(Font::HelveticaItalic, 14).into();
+        let other: RichData = UserData::new_text("other".to_string()).set_font_and_size(Font::Times, 14).into();
+        let mut buffer = vec![plain, italic, other];
+
+        let theme = Theme::solarized_dark();
+        assert!(!retheme_data(&mut buffer, (WHITE, Color::Black, Font::Helvetica), &theme));
+        assert_eq!(buffer[0].fg_color, theme.text_color);
+
+        let mut theme = Theme::dark();
+        theme.text_font = Font::Courier;
+        assert!(retheme_data(&mut buffer, (WHITE, Color::Black, Font::Helvetica), &theme));
+        assert_eq!(buffer[0].font, Font::Courier);
+        assert_eq!(buffer[1].font, Font::CourierItalic);
+        assert_eq!(buffer[2].font, Font::Times);
     }
 
-    pub fn text(mut self, text: String) -> RichDataOptions {
-        self.text = Some(text);
-        self
+    #[test]
+    pub fn italic_font_test() {
+        assert_eq!(italic_font(Font::Helvetica), Font::HelveticaItalic);
+        assert_eq!(italic_font(Font::CourierBold), Font::CourierBoldItalic);
+        assert_eq!(italic_font(Font::TimesItalic), Font::TimesItalic);
+        assert_eq!(italic_font(Font::Screen), Font::HelveticaItalic);
     }
 
-    pub fn fg_color(mut self, fg_color: Color) -> RichDataOptions {
-        self.fg_color = Some(fg_color);
-        self
+    #[test]
+    pub fn markdown_test() {
+        assert_eq!(bold_font(Font::Courier), Font::CourierBold);
+        assert_eq!(bold_font(Font::Screen), Font::HelveticaBold);
+
+        let style = MarkdownStyle::default();
+        let md = "## 命令\n输入 **look** 查看，\n或者点击[这里](look)。\n\n- 第一项\n  1. 嵌套 `code`\n";
+        let data = to_user_data(md, &style);
+        let texts = data.iter().map(|d| d.text.as_str()).collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["命令\n", "输入 ", "look", " 查看， 或者点击", "这里", "。\n", "\n", "• ", "第一项\n", "1. ", "嵌套 ", "code", "\n"]);
+        assert_eq!(data[0].font, Font::HelveticaBold);
+        assert_eq!(data[0].font_size, style.heading_sizes[1]);
+        assert!(data[2].strong);
+        assert!(!data[3].strong);
+        assert!(data[4].clickable);
+        assert_eq!(data[4].action.as_ref().map(|a| a.items[0].cmd.as_str()), Some("look"));
+        assert_eq!((data[7].indent_level, data[9].indent_level), (1, 2));
+        assert_eq!(data[11].font, Font::Courier);
+
+        let code = to_user_data("```\nlet a = 1;\n```", &style);
+        assert_eq!(code.len(), 1);
+        assert_eq!(code[0].text, "let a = 1;\n");
+        assert_eq!(to_user_data("*a\\*b*", &style)[0].text, "a*b\n");
+
+        let deep = format!("{}- 深层\n", " ".repeat(1000));
+        assert_eq!(to_user_data(&deep, &style)[0].indent_level, 9);
     }
 
-    pub fn bg_color(mut self, bg_color: Color) -> RichDataOptions {
-        self.bg_color = Some(bg_color);
-        self
+    #[test]
+    pub fn html_test() {
+        assert_eq!(decode_entities("a &lt;b&gt; &amp;&#65;&#x42; &bogus; &"), "a <b> &AB &bogus; &");
+        assert_eq!(parse_color("#f00"), Some(Color::from_rgb(255, 0, 0)));
+        assert_eq!(parse_color("Navy"), Some(Color::from_rgb(0, 0, 128)));
+        assert_eq!(parse_color("nonsense"), None);
+
+        let html = "<B>bold <i>both</i></b><u>under</u><font color='#00ff00'>green <font color=bad>still<br/></font></font><a href=\"look\">look</a> 1 < 2";
+        let data = html_to_user_data(html, &HtmlStyle::default(), None);
+        let texts = data.iter().map(|d| d.text.as_str()).collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["bold ", "both", "under", "green ", "still\n", "look", " 1 < 2"]);
+        assert_eq!(data[0].font, Font::HelveticaBold);
+        assert_eq!(data[1].font, Font::HelveticaBoldItalic);
+        assert!(data[2].underline);
+        assert_eq!(data[3].fg_color, Color::from_rgb(0, 255, 0));
+        assert_eq!(data[4].fg_color, Color::from_rgb(0, 255, 0));
+        assert!(data[5].clickable);
+        assert_eq!(data[5].action.as_ref().map(|a| a.items[0].cmd.as_str()), Some("look"));
+        assert!(!data[6].clickable);
     }
 
-    pub fn strike_through(mut self, strike_through: bool) -> RichDataOptions {
-        self.strike_through = Some(strike_through);
-        self
+    #[test]
+    pub fn mxp_test() {
+        let style = MxpStyle::default();
+        let mxp = "<color fore=red back=#000080>warn <B>x</B></color><send>look</send> <send href=\"say &text;\" prompt>hi</send><c green>g</c><a href='http://a'>site</a>";
+        let data = mxp_to_user_data(mxp, &style, None);
+        let texts = data.iter().map(|d| d.text.as_str()).collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["warn ", "x", "look", " ", "hi", "g", "site"]);
+        assert_eq!(data[0].fg_color, Color::from_rgb(255, 0, 0));
+        assert_eq!(data[1].bg_color, Some(Color::from_rgb(0, 0, 128)));
+        assert_eq!(data[1].font, Font::HelveticaBold);
+
+        let send = data[2].action.as_ref().unwrap();
+        assert_eq!((send.kind, send.items[0].cmd.as_str()), (style.send_kind, "look"));
+        assert_eq!(Some(data[2].fg_color), style.link_color);
+        assert!(data[3].action.is_none());
+        let prompt = data[4].action.as_ref().unwrap();
+        assert_eq!((prompt.kind, prompt.items[0].cmd.as_str()), (style.prompt_kind, "say hi"));
+        assert_eq!(data[5].fg_color, Color::from_rgb(0, 128, 0));
+        let link = data[6].action.as_ref().unwrap();
+        assert_eq!((link.kind, link.items[0].cmd.as_str()), (style.link_kind, "http://a"));
     }
 
-    pub fn blink(mut self, blink: bool) -> RichDataOptions {
-        self.blink = Some(blink);
-        self
+    #[test]
+    pub fn highlight_test() {
+        let rules = vec![
+            HighlightRule { id: HighlightId(0), pattern: regex::Regex::new("dragon").unwrap(), style: StyleOverride::new().set_fg_color(Color::Red).set_trigger(true) },
+            HighlightRule { id: HighlightId(1), pattern: regex::Regex::new(r"red \w+").unwrap(), style: StyleOverride::new().set_underline(true) },
+        ];
+        let ud = UserData::new_text("A red dragon and a dragon.\n".to_string());
+        let id = ud.id;
+        let (pieces, hits) = apply_highlights(ud, &rules);
+        let texts = pieces.iter().map(|d| d.text.as_str()).collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["A ", "red dragon", " and a ", "dragon", ".\n"]);
+        assert!(pieces.iter().all(|d| d.origin_id == Some(id)));
+        assert_eq!(pieces[0].id, id);
+        assert_eq!(pieces.iter().map(|d| d.id).collect::<HashSet<i64>>().len(), pieces.len());
+        assert!(pieces[1].underline);
+        assert_ne!(pieces[1].fg_color, Color::Red);
+        assert_eq!(pieces[3].fg_color, Color::Red);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].data_id, hits[0].matched.as_str()), (id, "dragon"));
+
+        let (pieces, hits) = apply_highlights(UserData::new_text("nothing here".to_string()), &rules);
+        assert_eq!(pieces.len(), 1);
+        assert!(hits.is_empty());
+
+        // 顶部追加等情况下缓存不按ID排序。
+        let (pieces, _) = apply_highlights(UserData::new_text("red dragon\n".to_string()), &rules);
+        let before = RichData::from(UserData::new_text("before\n".to_string()));
+        let after = RichData::from(UserData::new_text("after\n".to_string()));
+        let id = pieces[0].id;
+        let mut buffer = vec![before];
+        buffer.extend(pieces.into_iter().map(RichData::from));
+        buffer.push(after);
+        assert_eq!(id_range(&buffer, id), 1..4);
+        assert!(id_range(&buffer, -1).is_empty());
+        let second = buffer[2].id;
+        assert_eq!(id_range(&buffer, second), 2..3);
+        let ids = HashSet::from([id]);
+        expire_data_by_ids(&mut buffer, &ids);
+        assert!(buffer[1..4].iter().all(|rd| rd.expired) && !buffer[0].expired && !buffer[4].expired);
+
+        let keywords = vec![KeywordHighlight { word: "dragon".to_string(), color: Color::Red }];
+        assert!(update_origin_data(&mut buffer, &RichDataOptions::new(id).text("blue dragon\n".to_string()), &keywords));
+        let texts = buffer[1..4].iter().map(|rd| rd.text.as_str()).collect::<Vec<&str>>();
+        assert_eq!(texts, vec!["blue dragon\n", "", ""]);
+        // 替换文本后按新文本重新计算关键字高亮范围。
+        assert_eq!(buffer[1].keyword_ranges, Some(vec![(5, 11, Color::Red)]));
+        assert!(buffer[2].keyword_ranges.is_none());
     }
 
-    pub fn disabled(mut self, disabled: bool) -> RichDataOptions {
-        self.disabled = Some(disabled);
-        self
+    #[test]
+    pub fn expand_tabs_test() {
+        assert_eq!(expand_tabs("a\tb", 0, 4, None, 79), ("a   b".to_string(), 5));
+        assert_eq!(expand_tabs("abcd\tb", 0, 4, None, 79), ("abcd    b".to_string(), 9));
+        assert_eq!(expand_tabs("中文\tb", 0, 8, None, 79), ("中文    b".to_string(), 9));
+        assert_eq!(expand_tabs("\tb\n\tc", 2, 4, None, 79), ("  b\n    c".to_string(), 5));
+
+        // 最后一个制表位之后移动到右边界，已位于右边界时保持不动。
+        let stops = BTreeSet::from([3, 10]);
+        assert_eq!(expand_tabs("a\tb\tc\td", 0, 4, Some(&stops), 15), ("a  b      c    d".to_string(), 16));
+        assert_eq!(expand_tabs("\tb", 0, 4, Some(&BTreeSet::new()), 5), ("     b".to_string(), 6));
+        assert_eq!(expand_tabs("\tb", 7, 4, Some(&BTreeSet::new()), 5), ("b".to_string(), 8));
     }
 
-    /// 设置影像更新参数。
-    ///
-    /// # Arguments
-    ///
-    /// * `image`: 新的影像对象。若为 `None` 表示不替换原有的影像，只是变更目标宽高。
-    /// * `target_width`: 目标宽度。
-    /// * `target_height`: 目标高度。
-    ///
-    /// returns: RichDataOptions
-    ///
-    /// # Examples
-    ///
-    /// ```
-    ///
-    /// ```
-    pub fn image(mut self, image: Option<RgbImage>, target_width: i32, target_height: i32) -> RichDataOptions {
-        if image.is_some() {
-            let (img_data, depth, width, height) = image_to_rgb_data(&image, target_width, target_height);
-            self.image = img_data;
-            self.image_width = Some(width);
-            self.image_height = Some(height);
-            self.image_target_width = Some(target_width);
-            self.image_target_height = Some(target_height);
-            self.image_color_depth = Some(depth);
-            self
-        } else {
-            self.image_target_width = Some(target_width);
-            self.image_target_height = Some(target_height);
-            self
-        }
+    #[test]
+    pub fn column_width_test() {
+        assert_eq!(char_columns('a'), 1);
+        assert_eq!(char_columns('中'), 2);
+        assert_eq!(char_columns('😀'), 2);
+        assert_eq!(char_columns('\u{301}'), 0);
+        assert_eq!(char_columns('\n'), 0);
+        assert_eq!(text_columns("ab中文\n"), 6);
+
+        // 宽字符跨越边界时整体截入
+        assert_eq!(take_columns("a中b", 2), ("a中".len(), 3));
+        assert_eq!(take_columns("a中b", 3), ("a中".len(), 3));
+        // 组合字符随前一个字符截入
+        assert_eq!(take_columns("e\u{301}x", 1), ("e\u{301}".len(), 1));
+        assert_eq!(take_columns("ab", 5), (2, 2));
+
+        // 宽字符跨越边界时整体舍弃
+        assert_eq!(fit_columns("a中b", 2), (1, 1));
+        assert_eq!(fit_columns("a中b", 3), ("a中".len(), 3));
+        assert_eq!(fit_columns("e\u{301}x", 1), ("e\u{301}".len(), 1));
+        assert_eq!(fit_columns("ab", 5), (2, 2));
     }
 
-    pub fn image_file_path(mut self, image_file_path: PathBuf) -> RichDataOptions {
-        self.image_file_path = Some(image_file_path);
-        self
+    #[test]
+    pub fn board_wide_char_test() {
+        use crate::rewrite_board::{cell_at_column, ReWriteBoard};
+
+        let cells: Vec<(usize, char)> = "a中b\n".chars().map(|c| (0, c)).collect();
+        assert_eq!(cell_at_column(&cells, 1), 0);
+        // 宽字符的前后两半都定位到该宽字符
+        assert_eq!(cell_at_column(&cells, 2), 1);
+        assert_eq!(cell_at_column(&cells, 3), 1);
+        assert_eq!(cell_at_column(&cells, 4), 2);
+        assert_eq!(cell_at_column(&cells, 5), cells.len());
+
+        // 擦除起点落在宽字符的后半部分时，整个宽字符被擦除
+        let mut board = ReWriteBoard::new(2, 10, 0, 20, 0, PADDING);
+        board.line_data_map.insert(1, vec![UserData::new_text("a中b".to_string()).into()]);
+        board.cursor_pos.set(1, 2);
+        board.erase_in_line(0);
+        assert_eq!(board.line_data_map[&1][0].text, "a   ");
+
+        board.line_data_map.insert(1, vec![UserData::new_text("a中b".to_string()).into()]);
+        board.cursor_pos.set(1, 2);
+        board.erase_in_line(1);
+        assert_eq!(board.line_data_map[&1][0].text, "   b");
     }
 
-    pub fn change_action(mut self, action: Action) -> RichDataOptions {
-        self.action = Some(action);
-        self
+    #[test]
+    pub fn table_data_test() {
+        assert_eq!(fit_column_widths(&[40, 60], 200), vec![40, 60]);
+        assert_eq!(fit_column_widths(&[40, 60], 50), vec![20, 30]);
+        assert_eq!(fit_column_widths(&[], 50), Vec::<i32>::new());
+
+        let table = Table::new(vec![
+            vec![TableCell::new("name"), TableCell::new("hp")],
+            vec![TableCell::new("bob")],
+        ]);
+        assert_eq!(table.columns(), 2);
+        let ud = UserData::new_table(table);
+        assert_eq!(ud.data_type, DataType::Table);
+        assert_eq!(ud.text, "name\thp\nbob\n");
+        let rd: RichData = ud.into();
+        assert_eq!(rd.table.as_ref().map(|t| t.rows.len()), Some(2));
     }
-}
 
-/// 碰撞检测，检查两个矩形区域是否出现交叉。
-///
-/// # Arguments
-///
-/// * `a`: 目标区域。
-/// * `b`: 当前区域，或者鼠标拖拽选择区域。
-///
-/// returns: bool
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn is_overlap(target_area: &Rectangle, selection_area: &Rectangle) -> bool {
-    let mut overlap = target_area.0 <= (selection_area.0 + selection_area.2);
-    overlap &= (target_area.0 + target_area.2) >= selection_area.0;
-    overlap &= target_area.1 <= (selection_area.1 + selection_area.3);
-    overlap &= (target_area.1 + target_area.3) >= selection_area.1;
-    // debug!("overlap: {overlap}");
-    overlap
-    // target_area.0 < (selection_area.0 + selection_area.2)
-    //     && (target_area.0 + target_area.2) > selection_area.0
-    //     && target_area.1 < (selection_area.1 + selection_area.3)
-    //     && (target_area.1 + target_area.3) > selection_area.1
-}
+    #[test]
+    pub fn gauge_data_test() {
+        let ud = UserData::new_gauge(30.0, 120.0, 100, Color::Red, Some("HP".to_string()));
+        assert_eq!(ud.data_type, DataType::Gauge);
+        assert_eq!(ud.text, "HP 30/120");
+        let id = ud.id;
+        let mut rd: RichData = ud.into();
+        assert_eq!(rd.gauge.as_ref().map(|g| g.ratio()), Some(0.25));
 
+        update_data_properties(RichDataOptions::new(id).gauge_value(150.0), &mut rd, &[]);
+        let gauge = rd.gauge.clone().unwrap();
+        assert_eq!(gauge.ratio(), 1.0);
+        assert_eq!(rd.text, "HP 150/120");
 
-/// 复制选中片段的内容。
-///
-/// # Arguments
-///
-/// * `it`:
-/// * `selection`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-fn copy_pieces(it: Iter<Weak<RwLock<LinePiece>>>, selection: &mut String) {
-    for p in it {
-        if let Some(p) = p.upgrade() {
-            let lp = &*p.read();
-            lp.copy_selection(selection);
-        }
+        let empty = UserData::new_gauge(5.0, 0.0, 100, Color::Red, None);
+        assert_eq!(empty.text, "5/0");
+        assert_eq!(empty.gauge.unwrap().ratio(), 0.0);
     }
-}
 
-/// 清除数据片段的选中属性。
-///
-/// # Arguments
-///
-/// * `selected_pieces`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn clear_selected_pieces(selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>) {
-    for piece in selected_pieces.read().iter() {
-        if let Some(p) = piece.upgrade() {
-            p.read().deselect();
-        }
+    #[test]
+    pub fn board_gauge_test() {
+        use crate::rewrite_board::{fit_board_gauge, release_erased_gauges};
+
+        let mut rd: RichData = UserData::new_gauge(30.0, 120.0, 95, Color::Red, Some("HP".to_string())).into();
+        fit_board_gauge(&mut rd, 10);
+        assert_eq!(rd.gauge.as_ref().map(|g| g.width), Some(100));
+        assert_eq!(rd.text, "HP 30/120 ");
+
+        let mut short: RichData = UserData::new_gauge(30.0, 120.0, 40, Color::Red, Some("HP".to_string())).into();
+        fit_board_gauge(&mut short, 10);
+        assert_eq!(short.text, "HP 3");
+
+        let mut row = vec![rd, short];
+        release_erased_gauges(&mut row);
+        assert!(row.iter().all(|rd| rd.data_type == DataType::Gauge));
+
+        row[0].text = " ".repeat(10);
+        release_erased_gauges(&mut row);
+        assert_eq!(row[0].data_type, DataType::Text);
+        assert!(row[0].gauge.is_none());
+        assert_eq!(row[1].data_type, DataType::Gauge);
     }
-    selected_pieces.write().clear();
-}
 
-/// 向前或向后选择数据片段。
-///
-/// # Arguments
-///
-/// * `rd`:
-/// * `piece_index`:
-/// * `pos`:
-/// * `selected_pieces`:
-/// * `from`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-fn select_piece_from_or_to(rd: &RichData, piece_index: usize, pos: usize, selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>, from: bool) {
-    if let Some(last_piece_rc) = rd.line_pieces.get(piece_index) {
-        let piece = &*last_piece_rc.read();
-        if from {
-            piece.select_from(pos);
-        } else {
-            piece.select_to(pos);
-        }
-        selected_pieces.write().push(Arc::downgrade(last_piece_rc));
+    #[test]
+    pub fn named_notifier_test() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use parking_lot::RwLock;
+
+        let (main_count, link_count, all_count) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let listeners = Arc::new(RwLock::new(vec![
+            NamedNotifier::new("link", &[EventKind::Link, EventKind::Scroll], {
+                let count = link_count.clone();
+                move |_| { count.fetch_add(1, Ordering::SeqCst); }
+            }),
+            NamedNotifier::new("all", &[], {
+                let count = all_count.clone();
+                move |_| { count.fetch_add(1, Ordering::SeqCst); }
+            }),
+        ]));
+        // 回调函数中移除自身，不应发生死锁。
+        listeners.write().push(NamedNotifier::new("once", &[], {
+            let listeners = listeners.clone();
+            move |_| { listeners.write().retain(|l| l.name != "once"); }
+        }));
+        let mut cb = Callback::with_listeners(Arc::new(RwLock::new(Box::new({
+            let count = main_count.clone();
+            move |_| { count.fetch_add(1, Ordering::SeqCst); }
+        }))), listeners);
+
+        cb.notify(CallbackData::OpenUrl("https://example.com".to_string()));
+        // 滚动事件只发送给具名回调函数。
+        cb.notify_listeners(CallbackData::Scroll(ScrollInfo::new(10, 100)));
+        cb.notify(CallbackData::Data(UserData::new_text("x".to_string())));
+        cb.notify(CallbackData::Suppressed(2));
+        assert_eq!(main_count.load(Ordering::SeqCst), 3);
+        assert_eq!(link_count.load(Ordering::SeqCst), 2);
+        assert_eq!(all_count.load(Ordering::SeqCst), 4);
+        assert_eq!(CallbackData::Suppressed(2).kind(), EventKind::Other);
+        assert!(cb.listeners.read().iter().all(|l| l.name != "once"));
     }
-}
 
-/// 计算选中文本所在数据片段的选中属性。
-///
-/// # Arguments
-///
-/// * `from_point`: 起始位置。
-/// * `to_point`: 结束位置。
-/// * `data_buffer`: 数据缓存。
-/// * `rd_range`: 选中的数据段索引范围。
-/// * `selected_pieces`: 选中数据片段临时记录容器。
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn select_text(
-    from_point: &ClickPoint,
-    to_point: &ClickPoint,
-    data_buffer: &[RichData],
-    rd_range: RangeInclusive<usize>,
-    selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>,
-    select_from_row: usize) {
-    /*
-    选择片段的原则：应选择起点右下方的第一行片段，结束点左上方的第一行片段，以及两点之间的中间行片段。
-     */
-    // debug!("传入的fp: {:?}, tp: {:?}", from_point, to_point);
-    let drag_rect = from_point.to_rect(to_point);
-    // debug!("drag_rect: {:?}", drag_rect);
-    let (lt, br) = drag_rect.corner_rect();
-    let (mut lt_p, mut br_p) = (from_point, to_point);
-    /*
-    lt_p: 划选区左上角点击位置
-    br_p: 划选区右下角点击位置
-    lf_p_i: 左上角数据分片索引号
-    br_p_i: 右下角数据分片索引号
-     */
-    let (mut lt_p_i, mut br_p_i) = (lt_p.p_i, br_p.p_i);
-    // debug!("lt_p_i: {lt_p_i}, br_p_i: {br_p_i}, rd_range: {:?}, drag_rect: {:?}, lt_p: {:?}, br_p: {:?}", rd_range, drag_rect, lt_p, br_p);
-    // 清理上一次选择的区域
-    clear_selected_pieces(selected_pieces.clone());
+    #[test]
+    pub fn hover_tracker_test() {
+        let mut hover = HoverTracker::default();
+        let hovers = |events: Vec<CallbackData>| events.into_iter().map(|e| match e {
+            CallbackData::Hover { id, entered } => (id, entered),
+            _ => panic!("unexpected event"),
+        }).collect::<Vec<_>>();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        assert_eq!(hovers(hover.update(Some(1), at(0))), vec![(1, true)]);
+        assert!(hover.update(Some(1), at(100)).is_empty());
+        assert_eq!(hovers(hover.update(Some(2), at(200))), vec![(1, false), (2, true)]);
+        assert_eq!(hovers(hover.update(None, at(300))), vec![(2, false)]);
+        assert!(hover.update(None, at(400)).is_empty());
+
+        // 最小间隔内快速划过多个数据段，只在间隔结束后通知最终的悬停目标。
+        assert_eq!(hovers(hover.update(Some(3), at(500))), vec![(3, true)]);
+        assert!(hover.update(Some(4), at(510)).is_empty());
+        assert!(hover.update(Some(5), at(520)).is_empty());
+        let delay = hover.take_flush_delay(at(520)).unwrap();
+        assert!((delay - (HOVER_MIN_INTERVAL - 0.02)).abs() < 1e-9);
+        assert!(hover.take_flush_delay(at(520)).is_none());
+        assert_eq!(hovers(hover.flush(at(550))), vec![(3, false), (5, true)]);
+
+        // 间隔内回到原目标时不产生通知。
+        assert!(hover.update(None, at(560)).is_empty());
+        assert!(hover.update(Some(5), at(570)).is_empty());
+        assert!(hover.flush(at(600)).is_empty());
+    }
 
-    let (r_start, r_end) = (*rd_range.start(), *rd_range.end());
-    let across_rds = max(r_end as isize - r_start as isize, 0);
-    if across_rds > 0 {
-        // 超过一行
-        // debug!("选区超过一个数据段");
-        if let Some(rd) = data_buffer.get(r_start) {
-            // 选择第一个数据段起点之后的所有分片内容。
-            // debug!("第一个数据段 lt_p_i: {lt_p_i}, lt_p.c_i: {}, br_p_i: {br_p_i}, br_p.c_i: {}", lt_p.c_i, br_p.c_i);
-            let v_bounds = &*rd.v_bounds.read();
-            // 若起始点成为划选区的右下角或左下角的时候，对调起始点和结束点
-            let mut should_exchange = (br.0 == from_point.x && br.1 == from_point.y) || (lt.0 == from_point.x && br.1 == from_point.y);
-            // 若划选区在起点数据段边界内，则不应对调
-            if should_exchange && r_start == select_from_row && drag_rect.1 >= v_bounds.0 && (drag_rect.1 + drag_rect.3) <= v_bounds.1 {
-                should_exchange = false;
-            }
+    #[test]
+    pub fn tooltip_test() {
+        let mut state = TooltipState::default();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let (hide, waiting) = state.mouse_moved(Some("拿起长剑"), 10, 20, at(0));
+        assert!(!hide);
+        assert_eq!(waiting, Some(TOOLTIP_DELAY));
+        assert_eq!(state.timer_fired(at(600)), Ok(true));
+        assert_eq!(state.visible_tip().unwrap().0, "拿起长剑");
+
+        // 移动鼠标后隐藏提示，已有计时器时不再新建，到期时按推后的显示时间继续等待。
+        let (hide, waiting) = state.mouse_moved(Some("拿起长剑"), 11, 20, at(700));
+        assert!(hide && waiting.is_some());
+        let (hide, waiting) = state.mouse_moved(Some("拿起长剑"), 12, 20, at(1000));
+        assert!(!hide && waiting.is_none());
+        let wait = state.timer_fired(at(1300)).unwrap_err();
+        assert!((wait - 0.3).abs() < 1e-6);
+        assert_eq!(state.timer_fired(at(1600)), Ok(true));
+        let (hide, waiting) = state.mouse_moved(None, 0, 0, at(1700));
+        assert!(hide && waiting.is_none());
+        assert!(state.visible_tip().is_none());
+
+        state.delay = None;
+        assert!(state.mouse_moved(Some("x"), 0, 0, at(1800)).1.is_none());
+
+        Theme::solarized_dark().apply_to_tooltip(&mut state);
+        assert_eq!(state.colors.0, Theme::solarized_dark().tooltip_background);
+
+        let lines = wrap_tooltip("look at the sword\nok", 10, |t| t.chars().count() as i32);
+        assert_eq!(lines, vec!["look at", "the sword", "ok"]);
+        let lines = wrap_tooltip("abcdefghijkl", 5, |t| t.chars().count() as i32);
+        assert_eq!(lines, vec!["abcde", "fghij", "kl"]);
+    }
 
-            if should_exchange {
-                // debug!("对换坐标点");
-                lt_p = to_point;
-                br_p = from_point;
-                lt_p_i = lt_p.p_i;
-                br_p_i = br_p.p_i;
+    #[test]
+    pub fn context_menu_hook_test() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        let hook = ContextMenuHook::new(Arc::new(RwLock::new(Box::new(|data: &UserData, menu: &mut MenuBuilder| {
+            if data.action.is_none() {
+                menu.add_item("复制", |_| {});
             }
+        }))));
+        assert_eq!(hook.build(&UserData::new_text("plain".to_string())).len(), 1);
+        let mut clickable = UserData::new_text("link".to_string());
+        clickable.action = Some(Default::default());
+        assert!(hook.build(&clickable).is_empty());
+
+        let copied = Rc::new(RefCell::new(String::new()));
+        let mut menu = MenuBuilder::default();
+        menu.add_item("复制", {
+            let copied = copied.clone();
+            move |data| copied.borrow_mut().push_str(&data.text)
+        });
+        menu.run(0, &UserData::new_text("hello".to_string()));
+        menu.run(1, &UserData::new_text("ignored".to_string()));
+        assert_eq!(copied.borrow().as_str(), "hello");
+    }
 
-            select_piece_from_or_to(rd, lt_p_i, lt_p.c_i, selected_pieces.clone(), true);
-            for p in rd.line_pieces.iter().skip(lt_p_i + 1) {
-                let piece = &*p.read();
-                piece.select_all();
-                selected_pieces.write().push(Arc::downgrade(p));
-            }
-        }
+    #[test]
+    pub fn selection_drag_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        let bounds = Arc::new(RwLock::new((0, 0, 0, 0)));
+        let first = LinePiece::new("hello ".to_string(), 10, 20, 60, 20, 20, 0, 70, 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone());
+        let second = LinePiece::new("world".to_string(), 70, 20, 50, 20, 20, 0, 120, 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds);
+        first.read().select_range(2, 6);
+        second.read().select_all();
+        let selected = vec![Arc::downgrade(&first), Arc::downgrade(&second)];
+        assert_eq!(selection_text(&selected, false), "llo world");
+        assert!(selection_contains(&selected, 15, 25));
+        assert!(selection_contains(&selected, 100, 39));
+        assert!(!selection_contains(&selected, 130, 25));
+        assert!(!selection_contains(&selected, 15, 40));
+
+        second.read().deselect();
+        assert!(!selection_contains(&selected, 100, 25));
+    }
 
-        // 如果中间有更多跨行数据段，则全选这些数据段。
-        if r_end > r_start + 1 {
-            let mut piece_rcs = Vec::new();
-            for i in r_start + 1..r_end {
-                if let Some(rd) = data_buffer.get(i) {
-                    for p in rd.line_pieces.iter() {
-                        let piece = &*p.read();
-                        piece.select_all();
-                        piece_rcs.push(Arc::downgrade(p));
-                    }
-                }
-            }
-            selected_pieces.write().append(&mut piece_rcs);
-        }
+    #[test]
+    pub fn copy_soft_wraps_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        let bounds = Arc::new(RwLock::new((0, 0, 0, 0)));
+        let first = LinePiece::new("hello ".to_string(), 10, 20, 60, 20, 20, 0, 10, 40, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone());
+        let second = LinePiece::new("world".to_string(), 10, 40, 50, 20, 40, 0, 60, 40, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds);
+        second.write().soft_wrapped = true;
+        first.read().select_range(2, 6);
+        second.read().select_all();
+        let selected = vec![Arc::downgrade(&first), Arc::downgrade(&second)];
+        assert_eq!(selection_text(&selected, false), "llo world");
+        let kept = selection_text(&selected, true);
+        let only_second = selection_text(&selected[1..], true);
+        assert_eq!(kept, "llo \nworld");
+        // 选区从续行开始时不插入换行符。
+        assert_eq!(only_second, "world");
+    }
 
-        if let Some(rd) = data_buffer.get(r_end) {
-            // 选择最后一个数据段终点之前的所有内容。
-            for p in rd.line_pieces.iter().take(br_p_i) {
-                let piece = &*p.read();
-                piece.select_all();
-                selected_pieces.write().push(Arc::downgrade(p));
-            }
-            // debug!("br_p_i: {br_p_i}, br_p.c_i: {}", br_p.c_i);
-            select_piece_from_or_to(rd, br_p_i, br_p.c_i + 1, selected_pieces.clone(), false);
-        }
-    } else {
-        // 只有一行
-        // debug!("选区只有一个数据段");
+    #[test]
+    pub fn piece_hit_rect_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
 
-        // 若起始点成为划选区的右下角或左下角的时候，对调起始点和结束点
-        if (br.0 == from_point.x && br.1 == from_point.y) || (lt.0 == from_point.x && br.1 == from_point.y) {
-            // debug!("对换坐标点");
-            lt_p = to_point;
-            br_p = from_point;
-            lt_p_i = lt_p.p_i;
-            br_p_i = br_p.p_i;
-        }
+        let bounds = Arc::new(RwLock::new((0, 0, 0, 0)));
+        // 片段间距为6，下一个分片从76开始。
+        let text = LinePiece::new("hello".to_string(), 10, 20, 60, 20, 20, 0, 76, 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone());
+        assert_eq!(text.read().rect(0, 0), Rectangle::new(10, 20, 60, 20));
+        assert_eq!(text.read().hit_rect(), Rectangle::new(10, 20, 66, 20));
+        assert!(text.read().hit(72, 25));
+        assert!(!text.read().hit(76, 25));
 
-        if let Some(rd) = data_buffer.get(r_start) {
-            // debug!("br_p_i: {br_p_i}, lt_p_i: {lt_p_i}");
-            if br_p_i != lt_p_i {
-                // 超过一个分片
-                // debug!("选区超过一个分片");
-                select_piece_from_or_to(rd, lt_p_i, lt_p.c_i, selected_pieces.clone(), true);
+        // 以换行符结尾的分片不向右扩展。
+        let line_end = LinePiece::new("world\n".to_string(), 10, 20, 60, 20, 20, 0, 10, 40, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone());
+        assert_eq!(line_end.read().hit_rect(), Rectangle::new(10, 20, 60, 20));
 
-                // 超过两个分片
-                // debug!("选区超过两个分片");
-                let mut piece_rcs = Vec::new();
-                for i in lt_p_i + 1..br_p_i {
-                    if let Some(piece_rc) = rd.line_pieces.get(i) {
-                        let piece = &*piece_rc.read();
-                        piece.select_all();
-                        piece_rcs.push(Arc::downgrade(piece_rc));
-                    }
-                }
-                selected_pieces.write().append(&mut piece_rcs);
+        let image = LinePiece::new("".to_string(), 80 + IMAGE_PADDING_H, 20, 30, 30, 20, 0, 80 + 30 + IMAGE_PADDING_H * 2, 20, 1, Font::Helvetica, 16, ThroughLine::new(30, true), bounds);
+        image.write().pad_h = IMAGE_PADDING_H;
+        assert_eq!(image.read().hit_rect(), Rectangle::new(80, 20, 30 + IMAGE_PADDING_H * 2, 30));
+    }
 
-                select_piece_from_or_to(rd, br_p_i, br_p.c_i + 1, selected_pieces.clone(), false);
-            } else {
-                // 在同一个分片内
-                if let Some(piece_rc) = rd.line_pieces.get(lt_p_i) {
-                    // debug!("selected range from: {} to: {}", lt_p.c_i, br_p.c_i + 1);
-                    let (mut fci, mut tci) = (lt_p.c_i, br_p.c_i + 1);
-                    if fci >= tci {
-                        fci = br_p.c_i;
-                        tci = lt_p.c_i + 1;
-                    }
-                    piece_rc.read().select_range(fci, tci);
-                    selected_pieces.write().push(Arc::downgrade(piece_rc));
-                }
-            }
-        }
+    #[test]
+    pub fn locate_data_at_test() {
+        let data = |id: i64, pieces: &[(i32, i32, i32)]| {
+            let mut rd: RichData = UserData::new_text("text".to_string()).into();
+            rd.id = id;
+            rd.line_pieces = pieces.iter().map(|(x, y, w)| LinePiece::new("text".to_string(), *x, *y, *w, 20, *y, 0, x + w, y + 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), rd.v_bounds.clone())).collect();
+            let (_, top, _, h) = rd.piece_bounds().unwrap();
+            *rd.v_bounds.write() = (top, top + h, 0, 0);
+            rd
+        };
+        let buffer = vec![data(1, &[(10, 0, 100)]), data(2, &[(50, 20, 200), (10, 40, 30)])];
+        assert_eq!(buffer[1].piece_bounds(), Some((10, 20, 240, 40)));
+
+        // 译文和链接预览卡片计入外接矩形。
+        let mut translated = data(3, &[(10, 0, 100)]);
+        translated.translation_pieces.push(LinePiece::new("译文".to_string(), 10, 20, 150, 20, 20, 0, 160, 40, 16, Font::Helvetica, 16, ThroughLine::new(20, false), translated.v_bounds.clone()));
+        translated.preview_piece = Some(LinePiece::new("".to_string(), 10, 40, 80, 30, 40, 0, 90, 70, 16, Font::Helvetica, 16, ThroughLine::new(30, false), translated.v_bounds.clone()));
+        assert_eq!(translated.piece_bounds(), Some((10, 0, 150, 70)));
+        assert_eq!(locate_data_at(&buffer, 20, 5), Some(0));
+        assert_eq!(locate_data_at(&buffer, 20, 45), Some(1));
+        assert_eq!(locate_data_at(&buffer, 100, 45), None);
+        assert_eq!(locate_data_at(&buffer, 5, 5), None);
     }
 
-    /*
-    拷贝至剪贴板
-     */
-    let mut selection = String::new();
-    copy_pieces(selected_pieces.read().iter(), &mut selection);
-    app::copy(selection.as_str());
-}
+    #[test]
+    pub fn speech_lines_test() {
+        let mut lines = SpeechLines::default();
+        assert!(lines.push(&UserData::new_text("你站在".to_string())).is_empty());
+        assert_eq!(lines.push(&UserData::new_text("广场上。\r\n\n出口：".to_string())), vec!["你站在广场上。".to_string()]);
+        let mut hidden = UserData::new_text("密码\n".to_string());
+        hidden.conceal = true;
+        assert!(lines.push(&hidden).is_empty());
+        let mut image = UserData::new_text("图片\n".to_string());
+        image.data_type = DataType::Image;
+        assert!(lines.push(&image).is_empty());
+        assert_eq!(lines.push(&UserData::new_text("东、西\n> ".to_string())), vec!["出口：东、西".to_string()]);
+        assert_eq!(lines.flush(), Some(">".to_string()));
+        assert_eq!(lines.flush(), None);
+    }
 
-#[derive(Debug)]
-pub(crate) struct TargetRow {
-    pub(crate) row: usize,
-    pub(crate) expanded: bool,
-}
+    #[test]
+    pub fn visible_text_test() {
+        let data = |text: &str, top: i32, conceal: bool| {
+            let mut ud = UserData::new_text(text.to_string());
+            ud.conceal = conceal;
+            let rd: RichData = ud.into();
+            let piece = LinePiece::new(text.to_string(), 0, top, 50, 20, top, 0, 0, top + 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), rd.v_bounds.clone());
+            let mut rd = rd;
+            rd.line_pieces.push(piece);
+            *rd.v_bounds.write() = (top, top + 20, 0, 50);
+            rd
+        };
+        let buffer = vec![data("第一行\n", 0, false), data("第二行\n", 20, false), data("隐藏\n", 40, true), data("第四行\n", 60, false)];
+        assert_eq!(visible_text(&buffer, 25, 70), "第二行\n第四行\n");
+        assert_eq!(visible_text(&buffer, 0, 20), "第一行\n");
+    }
 
-impl TargetRow {
-    pub fn new(row: usize, expanded: bool) -> Self {
-        Self { row, expanded }
+    #[test]
+    pub fn selection_anchor_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        let piece = |line: &str, bounds: &Arc<RwLock<(i32, i32, i32, i32)>>| {
+            LinePiece::new(line.to_string(), 10, 20, 60, 20, 20, 0, 70, 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone())
+        };
+        let data = |id: i64, lines: &[&str]| {
+            let mut rd: RichData = UserData::new_text(lines.concat()).into();
+            rd.id = id;
+            rd.line_pieces = lines.iter().map(|line| piece(line, &rd.v_bounds)).collect();
+            rd
+        };
+        let mut buffer = vec![data(1, &["hello ", "world\n"]), data(2, &["foo ", "bar\n"])];
+        assert_eq!(SelectionAnchor::new(&buffer[0], 0, 1, 2), SelectionAnchor { row: 0, id: 1, char_pos: 8 });
+        assert_eq!(SelectionAnchor { row: 0, id: 1, char_pos: 8 }.locate(&buffer[0]), Some((1, 2)));
+        assert_eq!(SelectionAnchor { row: 0, id: 1, char_pos: 99 }.locate(&buffer[0]), Some((1, 5)));
+        // 记录的索引失效时从附近重新查找，不依赖数据段id的大小顺序。
+        assert_eq!(SelectionAnchor { row: 5, id: 2, char_pos: 0 }.find_row(&buffer), Some(1));
+        assert_eq!(SelectionAnchor { row: 0, id: 2, char_pos: 0 }.find_row(&buffer), Some(1));
+        assert_eq!(SelectionAnchor { row: 1, id: 9, char_pos: 0 }.find_row(&buffer), None);
+
+        buffer[0].line_pieces[1].read().select_from(2);
+        buffer[1].line_pieces[0].read().select_to(2);
+        let range = selection_range(&buffer, 1).unwrap();
+        assert_eq!(range, (SelectionAnchor { row: 0, id: 1, char_pos: 8 }, SelectionAnchor { row: 1, id: 2, char_pos: 2 }));
+        assert_eq!(selection_range(&buffer, 0), Some(range));
+
+        // 分片重建后按照记录的位置恢复选区。
+        let selection = RwLock::new(Some(range));
+        let selected_pieces = Arc::new(RwLock::new(vec![]));
+        buffer[0].line_pieces = vec![piece("hello world\n", &buffer[0].v_bounds)];
+        keep_selection(&buffer, &selection, selected_pieces.clone());
+        assert_eq!(selection_text(&selected_pieces.read(), false), "rld\nfo");
+
+        // 起点所在数据段被清理后从最早的数据开始。
+        buffer.remove(0);
+        keep_selection(&buffer, &selection, selected_pieces.clone());
+        assert_eq!(selection_text(&selected_pieces.read(), false), "fo");
+
+        // 头部插入了id更大的数据段时，终点仍能找到，不会误判为尚未加载。
+        buffer.insert(0, data(7, &["new\n"]));
+        clear_selected_pieces(selected_pieces.clone());
+        keep_selection(&buffer, &selection, selected_pieces.clone());
+        assert_eq!(selection_text(&selected_pieces.read(), false), "new\nfo");
+
+        buffer.clear();
+        keep_selection(&buffer, &selection, selected_pieces.clone());
+        assert!(selection.read().is_none());
+        assert!(selected_pieces.read().is_empty());
     }
-}
 
-/// 检测拖选范围所涵盖的数据段。
-///
-/// # Arguments
-///
-/// * `point`: 起始点。
-/// * `drag_rect`: 拖选矩形范围。
-/// * `panel_width`: 容器面板宽度。
-/// * `data_buffer`: 数据缓存。
-/// * `index_vec`: 容器面板可见范围内数据的顺序位置索引。
-///
-/// returns: Option<usize> 返回拖选结束点的数据段索引。
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn locate_target_rd(point: &mut ClickPoint, mut drag_rect: Rectangle, panel_width: i32, data_buffer: &[RichData], index_vec: Vec<usize>) -> Option<TargetRow> {
-    let point_rect = point.as_rect();
-    // debug!("index_vec: {:?}", index_vec);
-    if let Ok(idx) = index_vec.binary_search_by({
-        let point_rect_rc = point_rect.clone();
-        let point_rc = point.clone();
-        move |row| _search_row_idx(row, &data_buffer, panel_width, &drag_rect, point_rect_rc, point_rc)
-    }) {
-        // debug!("已定位当前数据段 {idx}");
-        let ret = _record_start_char_pos(data_buffer, &index_vec, idx, &point_rect, point);
-        if let Some(row) = ret {
-            Some(TargetRow::new(row, false))
-        } else {
-            None
-        }
-    } else {
-        // debug!("没找到目标数据段！向左上扩展");
-        drag_rect.2 = max(drag_rect.0 - PADDING.left, 0);
-        drag_rect.3 = max(drag_rect.1 - PADDING.top, 0);
-        drag_rect.0 = PADDING.left;
-        drag_rect.1 = PADDING.top;
-        let point_rect = drag_rect.clone();
-        let mut tmp_point = point.clone();
-        tmp_point.x = PADDING.left;
+    #[test]
+    pub fn drag_scroll_step_test() {
+        assert_eq!(drag_scroll_step(0), 0);
+        assert_eq!(drag_scroll_step(2), 5);
+        assert_eq!(drag_scroll_step(-2), -5);
+        assert!(drag_scroll_step(40) > drag_scroll_step(10));
+        assert_eq!(drag_scroll_step(1000), MAX_DRAG_SCROLL_STEP);
+        assert_eq!(drag_scroll_step(-1000), -MAX_DRAG_SCROLL_STEP);
+    }
 
-        // 先用二分法粗略定位到选区中的某个数据段，再从该数据段开始向后遍历找到最后一个位于选区内的数据段，将该数据段的末尾设定为新的选择起点。
-        if let Ok(idx) = index_vec.binary_search_by({
-            let point_rect_rc = point_rect.clone();
-            let point_rc = tmp_point.clone();
-            move |row| _search_row_idx(row, &data_buffer, panel_width, &drag_rect, point_rect_rc, point_rc)
-        }) {
-            // debug!("粗略定位到选区中的数据段 {idx}");
-            let ret = _record_start_char_pos2(data_buffer, index_vec[idx], &point_rect, &mut tmp_point);
-            if let Some(row) = ret {
-                point.x = tmp_point.x;
-                point.y = tmp_point.y;
-                point.p_i = tmp_point.p_i;
-                point.c_i = tmp_point.c_i;
-                // debug!("point: {point:?}");
-                Some(TargetRow::new(row, true))
-            } else {
-                None
-            }
-        } else {
-            None
-        }
+    #[test]
+    pub fn prompt_line_test() {
+        let mut prompt = PromptLine::default();
+        prompt.insert("look\n");
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("look", 4));
+        prompt.move_to_edge(false);
+        prompt.insert("l");
+        prompt.delete();
+        assert_eq!(prompt.text, "look");
+        prompt.move_to_edge(true);
+        prompt.insert(" 👍🏽");
+        prompt.backspace();
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("look ", 5));
+        prompt.move_cursor(false);
+        prompt.backspace();
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("loo ", 3));
+
+        assert_eq!(prompt.submit(), "loo ");
+        prompt.set_text("north");
+        assert_eq!(prompt.submit(), "north");
+        assert!(prompt.text.is_empty());
+
+        prompt.insert("dra");
+        prompt.browse_history(true);
+        assert_eq!(prompt.text, "north");
+        prompt.browse_history(true);
+        prompt.browse_history(true);
+        assert_eq!(prompt.text, "loo ");
+        prompt.browse_history(false);
+        assert_eq!(prompt.text, "north");
+        prompt.browse_history(false);
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("dra", 3));
+
+        // 输入法的预编辑文字被后续的组字结果替换。
+        prompt.compose(0, "n");
+        prompt.compose(1, "ni");
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("drani", 5));
+        prompt.compose(2, "你");
+        assert_eq!((prompt.text.as_str(), prompt.cursor), ("dra你", 4));
     }
-}
 
-fn _search_row_idx(row: &usize, data_buffer: &[RichData], panel_width: i32, drag_rect: &Rectangle, point_rect_rc: Rectangle, point_rc: ClickPoint) -> Ordering {
-    /*
-    先将不规则的数据段外形扩展为顶宽的矩形，再检测划选区是否与之重叠；
-    如果有重叠，则进一步检测其中每个分片是否与划选区有重叠，如果任意分片有重叠，说明划选区包含了该数据段的某些分片，
-    还须再进一步确定选区起点位置所在的分片，最终返回等于，否则返回大于或小于；
-    如果没有重叠，则判断其相对位置，并返回大于或小于。
-     */
-    let mut rd_extend_rect = Rectangle::zero();
-    let rd = &data_buffer[*row];
-    // debug!("检测行 {row} : {}", rd.text);
-    let (rd_top_y, rd_bottom_y, _, _) = *rd.v_bounds.read();
-    // let max_h = if let Some(first_lp) = rd.line_pieces.first() {
-    //     rd_top_y = first_lp.read().top_y;
-    //     first_lp.read().through_line.read().max_h
-    // } else {
-    //     rd_bottom_y - rd_top_y
-    // };
-    rd_extend_rect.replace(0, rd_top_y, panel_width, rd_bottom_y - rd_top_y);
-    // debug!("{point_rect:?}, rd_top_y: {}, rd_bottom_y: {}, drag_rect: {:?}", rd_top_y, rd_bottom_y, drag_rect);
+    #[test]
+    pub fn echo_lines_test() {
+        assert!(echo_lines("").is_empty());
+        assert!(echo_lines("\n\n").is_empty());
+        assert_eq!(echo_lines("say hi\r\n"), vec!["say hi".to_string()]);
+        let lines = echo_lines("1\n2\n\n4\n5\n");
+        assert_eq!(lines.len(), ECHO_MAX_LINES);
+        assert_eq!(lines, vec!["".to_string(), "4".to_string(), "5".to_string()]);
+    }
 
-    // 粗略过滤到的数据段，还须进一步检测其中的分片是否包含划选区起点。
-    if is_overlap(&rd_extend_rect, &drag_rect) {
-        // debug!("行 {row} 与划选区有重叠");
-        let mut ord = Ordering::Less;
-        for piece_rc in rd.line_pieces.iter() {
-            let piece = &*piece_rc.read();
-            let piece_rect = piece.rect(0, 0);
-            // debug!("piece_rect: {:?}, piece_top_y: {}, : {}", piece_rect, piece.top_y, piece.line);
-            if is_overlap(&piece_rect, &point_rect_rc) {
-                // 划选区起点位于分片内
-                // debug!("划选区起点位于分片内：{:?}", piece.line);
-                ord = Ordering::Equal;
-                break;
-            }
+    #[test]
+    pub fn pinned_rows_test() {
+        let mut pinned = PinnedRows::default();
+        assert_eq!(pinned.heights(), (0, 0));
+        pinned.layout(14, 600, 'a', &Padding::new(5, 8, 5, 6));
+        assert_eq!(pinned.heights(), (0, 0));
+        let [(top_y, top_h, top), (bottom_y, bottom_h, bottom)] = pinned.strips(400);
+        assert_eq!((top_y, top_h, top.len()), (8, 0, 0));
+        assert_eq!((bottom_y, bottom_h, bottom.len()), (394, 0, 0));
+
+        // 分隔线独占一行，底部两条分隔线上下排列。
+        let divider = || -> RichData { UserData::new_divider(None, DividerStyle::Solid, Color::Red).into() };
+        pinned.top = vec![divider()];
+        pinned.bottom = vec![divider(), divider()];
+        pinned.layout(14, 600, 'a', &Padding::new(5, 8, 5, 6));
+        let h = (pinned.top[0].font_size as f32 * pinned.top[0].line_height_factor).ceil() as i32;
+        assert_eq!(pinned.heights(), (h, h * 2));
+        let (row_top, row_bottom, _, _) = *pinned.bottom[1].v_bounds.read();
+        assert_eq!((row_top, row_bottom), (h, h * 2));
+        let [(top_y, top_h, top), (bottom_y, bottom_h, bottom)] = pinned.strips(400);
+        assert_eq!((top_y, top_h, top.len()), (8, h, 1));
+        assert_eq!((bottom_y, bottom_h, bottom.len()), (394 - h * 2, h * 2, 2));
+    }
+
+    #[test]
+    pub fn flow_columns_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        assert_eq!(flow_column_width(600, 1), 600);
+        assert_eq!(flow_column_width(600, 2), (600 - COLUMN_GAP) / 2);
+        assert_eq!(flow_column_width(600, 9), flow_column_width(600, 3));
+
+        // 每行高20，每栏可容纳3行，两栏排满后在下一页继续排列。
+        let row = |y: i32, bounds: &Arc<RwLock<(i32, i32, i32, i32)>>| {
+            LinePiece::new("line\n".to_string(), 10, y, 60, 20, y, 0, 10, y + 20, 16, Font::Helvetica, 16, ThroughLine::new(20, false), bounds.clone())
+        };
+        let mut buffer: Vec<RichData> = (0..8).map(|i| {
+            let mut rd: RichData = UserData::new_text("line\n".to_string()).into();
+            rd.line_pieces = vec![row(5 + i * 20, &rd.v_bounds)];
+            *rd.v_bounds.write() = (5 + i * 20, 25 + i * 20, 10, 70);
+            rd
+        }).collect();
+        let width = flow_column_width(300, 2);
+        flow_columns(&mut buffer, 2, width, 60, 5);
+        let positions: Vec<(i32, i32)> = buffer.iter().map(|rd| {
+            let piece = rd.line_pieces[0].read();
+            (piece.x, piece.y)
+        }).collect();
+        let right = 10 + width + COLUMN_GAP;
+        assert_eq!(positions, vec![(10, 5), (10, 25), (10, 45), (right, 5), (right, 25), (right, 45), (10, 65), (10, 85)]);
+        assert_eq!(*buffer[3].v_bounds.read(), (5, 25, right, right + 60));
+
+        // 按阅读顺序比较坐标：右栏顶部在左栏底部之后，下一页在两栏之后。
+        let padding = Padding::new(10, 5, 0, 0);
+        let layout = ColumnLayout::new(2, 310, 65, &padding);
+        assert!(layout.is_flowing());
+        assert_eq!(layout.cmp_points((right, 10), (20, 50)), std::cmp::Ordering::Greater);
+        assert_eq!(layout.cmp_points((20, 70), (right, 50)), std::cmp::Ordering::Greater);
+        assert_eq!(layout.page_top(90), 65);
+        assert_eq!(ColumnLayout::default().page_top(90), 90);
+
+        // 点击左栏第三行时，右栏的数据段在其之后，左栏第二行在其之前。
+        let point = ClickPoint::new(20, 50);
+        assert_eq!(_search_row_idx_in_columns(&buffer[3], &layout, &point.as_rect(), &point), std::cmp::Ordering::Greater);
+        assert_eq!(_search_row_idx_in_columns(&buffer[1], &layout, &point.as_rect(), &point), std::cmp::Ordering::Less);
+        assert_eq!(_search_row_idx_in_columns(&buffer[2], &layout, &point.as_rect(), &point), std::cmp::Ordering::Equal);
+
+        // 点击分片以外的空白时，选区端点落在阅读顺序中位于其前的最后一个分片末尾。
+        let mut point = ClickPoint::new(100, 50);
+        let target = locate_target_rd(&mut point, point.as_rect(), 310, &buffer, (0..buffer.len()).collect(), &padding, &layout).unwrap();
+        assert_eq!((target.row, point.x), (2, 70));
+        let mut point = ClickPoint::new(right + 100, 30);
+        let target = locate_target_rd(&mut point, point.as_rect(), 310, &buffer, (0..buffer.len()).collect(), &padding, &layout).unwrap();
+        assert_eq!((target.row, point.x), (4, right + 60));
+    }
+
+    #[test]
+    pub fn dropped_files_test() {
+        use std::path::PathBuf;
+
+        let files = parse_dropped_files("file:///home/user/my%20log.txt\r\nfile:///tmp/a.png\n");
+        assert_eq!(files, vec![PathBuf::from("/home/user/my log.txt"), PathBuf::from("/tmp/a.png")]);
+        assert_eq!(parse_dropped_files("file:///C:/logs/%E6%97%A5%E5%BF%97.txt"), vec![PathBuf::from("C:/logs/日志.txt")]);
+        assert_eq!(parse_dropped_files("file://localhost/tmp/100%"), vec![PathBuf::from("/tmp/100%")]);
+        assert_eq!(parse_dropped_files("file://LOCALHOST/tmp/a.png"), vec![PathBuf::from("/tmp/a.png")]);
+        // 其他主机上的文件不是本机路径，视为拖放文字。
+        assert!(parse_dropped_files("file://server/share/a.png").is_empty());
+        assert!(parse_dropped_files("file://localhost").is_empty());
+        assert!(parse_dropped_files("look sword\nfile:///tmp/a.png").is_empty());
+        assert!(parse_dropped_files("").is_empty());
+    }
+
+    #[test]
+    pub fn drag_out_started_test() {
+        assert!(!drag_out_started((100, 100), (100, 100)));
+        assert!(!drag_out_started((100, 100), (103, 97)));
+        assert!(drag_out_started((100, 100), (100 + DRAG_OUT_THRESHOLD, 100)));
+        assert!(drag_out_started((100, 100), (99, 100 - DRAG_OUT_THRESHOLD)));
+    }
+
+    #[test]
+    pub fn append_queue_test() {
+        let now = Instant::now();
+        let mut queue = AppendQueue::new(3);
+        assert_eq!(queue.frame_delay(25, now), 0.0);
+        assert!(queue.push(DocEditType::Data(UserData::new_text("a\n".to_string()))));
+        for text in ["b\n", "c\n", "d\n"] {
+            assert!(!queue.push(DocEditType::Data(UserData::new_text(text.to_string()))));
         }
-        // 如果划选起点不在重叠数据段的任意分片内部，则还须判断当前数据段在起点的前面或后面，为查找算法提供判断依据。
-        if ord != Ordering::Equal {
-            if let Some(first_piece_rc) = rd.line_pieces.first() {
-                let piece = &*first_piece_rc.read();
-                // debug!("piece: {:?}", piece);
-                if point_rc.x < piece.x && point_rc.y < piece.top_y + piece.through_line.read().max_h {
-                    ord = Ordering::Greater;
-                } else {
-                    ord = Ordering::Less;
-                }
-            }
+        assert!(!queue.is_empty());
+
+        let (items, dropped) = queue.take_frame(now);
+        assert_eq!(dropped, 1);
+        let texts: Vec<String> = items.into_iter().filter_map(|item| match item {
+            DocEditType::Data(ud) => Some(ud.text),
+            _ => None,
+        }).collect();
+        assert_eq!(texts, vec!["b\n", "c\n", "d\n"]);
+        assert!(queue.is_empty());
+
+        assert!((queue.frame_delay(25, now + Duration::from_millis(10)) - 0.03).abs() < 1e-9);
+        assert_eq!(queue.frame_delay(25, now + Duration::from_millis(50)), 0.0);
+        assert!(queue.push(DocEditType::Data(UserData::new_text("e\n".to_string()))));
+        assert_eq!(queue.take_frame(now).1, 0);
+
+        for text in ["f\n", "g\n", "h\n"] {
+            queue.push(DocEditType::Data(UserData::new_text(text.to_string())));
         }
-        // debug!("行 {row}: ord: {:?}", ord);
-        ord
-    } else {
-        if rd_extend_rect.is_below(&drag_rect) {
-            // debug!("行 {row}: 大于");
-            Ordering::Greater
-        } else {
-            // debug!("行 {row}: 小于");
-            Ordering::Less
+        queue.set_capacity(1);
+        let (items, dropped) = queue.take_frame(now);
+        assert_eq!((items.len(), dropped), (1, 2));
+
+        // 控制操作不会被丢弃，队列满时丢弃最早的数据段。
+        queue.set_capacity(3);
+        queue.push(DocEditType::EraseInDisplay(2));
+        queue.push(DocEditType::Data(UserData::new_text("i\n".to_string())));
+        queue.push(DocEditType::AlternateScreen(true));
+        queue.push(DocEditType::Data(UserData::new_text("j\n".to_string())));
+        let (items, dropped) = queue.take_frame(now);
+        assert_eq!(dropped, 1);
+        assert!(matches!(items[0], DocEditType::EraseInDisplay(2)));
+        assert!(matches!(items[1], DocEditType::AlternateScreen(true)));
+        assert!(matches!(&items[2], DocEditType::Data(ud) if ud.text == "j\n"));
+
+        for _ in 0..5 {
+            queue.push(DocEditType::CursorUp(1));
         }
+        queue.set_capacity(2);
+        let (items, dropped) = queue.take_frame(now);
+        assert_eq!((items.len(), dropped), (5, 0));
+    }
+
+    #[test]
+    pub fn style_def_test() {
+        let base = StyleDef::new().set_font(Font::Courier).set_font_size(14).set_fg_color(Color::White);
+        let def = StyleDef::new().set_fg_color(Color::Red).set_font_size(20).set_blink(true).set_bg_color(Color::Blue);
+        let ud = def.apply(UserData::new_text("error\n".to_string()).with_style("error").set_italic(true), &base);
+        assert_eq!(ud.fg_color, Color::Red);
+        assert_eq!(ud.font_size, 20);
+        // 只设置字号时沿用组件的默认字体。
+        assert_eq!(ud.font, Font::Courier);
+        assert!(ud.blink && ud.italic && ud.custom_font_color && ud.custom_font_text);
+
+        let mut rd: RichData = ud.into();
+        assert_eq!(rd.font, Font::CourierItalic);
+        assert_eq!(rd.style_name.as_deref(), Some("error"));
+        let new_def = StyleDef::new().set_fg_color(Color::Magenta).set_blink(false);
+        new_def.restyle(Some(&def), &base, &mut rd);
+        assert_eq!(rd.fg_color, Color::Magenta);
+        // 旧定义设置过而新定义未设置的字号和背景色恢复为默认值。
+        assert_eq!(rd.font_size, 14);
+        assert_eq!(rd.bg_color, None);
+        assert!(!rd.blink && rd.italic);
+        assert_eq!(rd.font, Font::CourierItalic);
+
+        // 取消斜体时换回对应的正体字体，指定字体时斜体数据段使用其斜体。
+        StyleDef::new().set_italic(false).restyle(Some(&new_def), &base, &mut rd);
+        assert!(!rd.italic);
+        assert_eq!(rd.font, Font::Courier);
+        StyleDef::new().set_italic(true).set_font(Font::Times).restyle(None, &base, &mut rd);
+        assert_eq!(rd.font, Font::TimesItalic);
+        assert_eq!(UserData::from(&rd).style_name.as_deref(), Some("error"));
+    }
+
+    #[test]
+    pub fn expire_filter_test() {
+        let mut action = crate::Action::default();
+        action.category = Some("chat".to_string());
+        let chat: RichData = UserData::new_text("[广告] 大甩卖\n".to_string()).set_action(action).into();
+        let mut plain = UserData::new_text("你好\n".to_string());
+        plain.strike_through = true;
+        let plain: RichData = plain.into();
+        let now = chat.arrived_at + Duration::from_secs(30);
+
+        assert!(ExpireFilter::Category("chat".to_string()).matches(&chat, now));
+        assert!(!ExpireFilter::Category("chat".to_string()).matches(&plain, now));
+        assert!(ExpireFilter::OlderThan(Duration::from_secs(10)).matches(&chat, now));
+        assert!(!ExpireFilter::OlderThan(Duration::from_secs(60)).matches(&chat, now));
+        assert!(ExpireFilter::IdRange(chat.id..=chat.id).matches(&chat, now));
+        assert!(ExpireFilter::TextMatches(regex::Regex::new(r"^\[广告\]").unwrap()).matches(&chat, now));
+        assert!(!ExpireFilter::TextMatches(regex::Regex::new(r"^\[广告\]").unwrap()).matches(&plain, now));
+
+        let ids = HashSet::from([chat.id, plain.id]);
+        let mut buffer = vec![chat, plain];
+        expire_data_with_effect(&mut buffer, &ids, ExpireEffect::Dim);
+        assert!(buffer.iter().all(|rd| rd.expired && rd.faint && rd.action.is_none()));
+        assert!(!buffer[0].strike_through);
+        assert!(buffer[1].strike_through);
+        assert_eq!(ExpireEffect::default(), ExpireEffect::StrikeThrough);
+    }
+
+    #[test]
+    pub fn sender_thread_safety_test() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<crate::rich_text::RichTextSender>();
+        assert_send_sync::<DocEditType>();
+    }
+
+    #[test]
+    pub fn meta_test() {
+        let ud = UserData::new_text("hi\n".to_string()).set_meta("channel", "chat").set_meta("channel", "tell");
+        assert_eq!(ud.meta_value("channel"), Some("tell"));
+        assert_eq!(ud.meta_value("speaker"), None);
+        let plain_hash = UserData::new_text("hi\n".to_string()).content_hash();
+        assert_eq!(ud.content_hash(), plain_hash);
+
+        let rd: RichData = ud.into();
+        let back = UserData::from(&rd);
+        assert_eq!(back.meta_value("channel"), Some("tell"));
+        assert!(UserData::new_text("x".to_string()).meta.is_none());
     }
-}
 
+    #[test]
+    pub fn divider_data_test() {
+        let ud = UserData::new_divider(Some("new session".to_string()), DividerStyle::Dashed, Color::Red);
+        assert_eq!(ud.data_type, DataType::Divider);
+        assert_eq!(ud.text, "\n");
+        let plain = UserData::new_divider(None, DividerStyle::Dashed, Color::Red);
+        assert_ne!(ud.content_hash(), plain.content_hash());
 
-fn _record_start_char_pos2(data_buffer: &[RichData], from_row: usize, point_rect: &Rectangle, point: &mut ClickPoint) -> Option<usize> {
-    // 从指定位置开始向后查找选区内的数据段
-    let mut ret = from_row;
-    let bottom_y = point_rect.1 + point_rect.3;
-    'OUTER: for row in from_row..data_buffer.len() {
-        let rd = &data_buffer[row];
-        if rd.data_type != DataType::Image {
-            if rd.v_bounds.read().0 > bottom_y {
-                // 直到数据段上边界大于选区的下边界时，结束遍历。
-                break 'OUTER;
-            } else {
-                for (p_i, piece_rc) in rd.line_pieces.iter().enumerate() {
-                    let piece = &*piece_rc.read();
-                    let piece_rect = piece.rect(0, 0);
-                    // 将最后一个选区内的数据片段末尾位置，当作选区的起点
-                    if is_overlap(&piece_rect, &point_rect) {
-                        point.x = piece_rect.0 + piece_rect.2;
-                        point.y = piece_rect.1 + piece_rect.3 / 2;
-                        point.p_i = p_i;
-                        point.c_i = piece.line.trim().len();
-                        ret = row;
-                    }
-                }
-            }
-        }
+        let rd: RichData = ud.into();
+        let divider = rd.divider.clone().unwrap();
+        assert_eq!(divider.label.as_deref(), Some("new session"));
+        assert_eq!(divider.style, DividerStyle::Dashed);
+        assert!(UserData::from(&rd).divider.is_some());
     }
-    Some(ret)
-}
 
-fn _record_start_char_pos(data_buffer: &[RichData], index_vec: &Vec<usize>, idx: usize, point_rect: &Rectangle, point: &mut ClickPoint) -> Option<usize> {
-    let rd = &data_buffer[index_vec[idx]];
-    if rd.data_type != DataType::Image {
-        // debug!("找到目标点所在数据段： {}", rd.text);
-        for (p_i, piece_rc) in rd.line_pieces.iter().enumerate() {
-            let piece = &*piece_rc.read();
-            let piece_rect = piece.rect(0, 0);
-            // debug!("point_rect: {:?}, piece_rect: {:?}, line: {}", point_rect, piece_rect, piece.line);
-            if is_overlap(&piece_rect, &point_rect) {
-                // 划选区起点位于分片内
-                point.p_i = p_i;
-                // debug!("目标点位于分片:{} 内: {}", p_i, piece.line);
-                search_index_of_piece(piece, point);
-                break;
-            }
+    #[test]
+    pub fn quote_indent_test() {
+        let data = UserData::new_text("引用\n".to_string()).set_indent(2, Some(Color::Red));
+        let plain_hash = UserData::new_text("引用\n".to_string()).content_hash();
+        assert_ne!(data.content_hash(), plain_hash);
+
+        let rd: RichData = data.into();
+        assert_eq!(rd.indent_level, 2);
+        assert_eq!(rd.line_start_x(), PADDING.left + 2 * QUOTE_INDENT_WIDTH);
+        assert_eq!(UserData::from(&rd).quote_color, Some(Color::Red));
+
+        let mut deep: RichData = UserData::new_text("引用
+".to_string()).set_indent(u8::MAX, None).into();
+        deep.indent_limit = 100;
+        assert_eq!(deep.indent_start_x(), PADDING.left + 100);
+    }
+
+    #[test]
+    pub fn detach_buffer_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        let mut a: RichData = UserData::new_text("a".to_string()).into();
+        let mut b: RichData = UserData::new_text("b".to_string()).into();
+        let through_line = ThroughLine::new(20, false);
+        for (rd, x) in [(&mut a, 5), (&mut b, 20)] {
+            let piece = LinePiece::new(rd.text.clone(), x, 0, 10, 20, 0, 0, x + 10, 0, 20, Font::Helvetica, 16, through_line.clone(), Arc::new(RwLock::new((0, 20, x, x + 10))));
+            rd.line_pieces.push(piece);
         }
-        return Some(idx);
-    } else {
-        // 选择了图片，不予处理。
-        // debug!("选择了图片")
-        None
+        let buffer = vec![a, b];
+        let copy = detach_buffer(&buffer);
+
+        // 原缓存重新排版后副本保持不变。
+        buffer[0].line_pieces[0].write().x = 100;
+        *buffer[0].v_bounds.write() = (40, 60, 100, 110);
+        assert_eq!(copy[0].line_pieces[0].read().x, 5);
+        assert_eq!(*copy[0].v_bounds.read(), (0, 0, 0, 0));
+
+        // 同一行的分片在副本中共享新的行信息。
+        let (tl_a, tl_b) = (copy[0].line_pieces[0].read().through_line.clone(), copy[1].line_pieces[0].read().through_line.clone());
+        assert!(Arc::ptr_eq(&tl_a, &tl_b));
+        assert!(!Arc::ptr_eq(&tl_a, &through_line));
+        assert_eq!(tl_a.read().ys.len(), 2);
+        assert_eq!(tl_a.read().max_h, 20);
     }
-}
 
-/// 更新拖选结束点所在数段的位置属性，用于拖选过程准实时检测结束点位置。
-///
-/// # Arguments
-///
-/// * `push_from_point`: 起始点。
-/// * `select_from_row`: 起始点所在数据段的顺序索引号。
-/// * `current_point`: 当前结束点。
-/// * `data_buffer_slice`: 数据缓存。
-/// * `selected_pieces`: 临时保存选中数据片段的容器。
-/// * `panel`: 当前容器面板。
-///
-/// returns: bool
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn update_selection_when_drag(
-    push_from_point: ClickPoint,
-    select_from_row: usize,
-    current_point: &mut ClickPoint,
-    data_buffer_slice: &[RichData],
-    selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>,
-    panel: &mut impl WidgetBase,) {
+    #[test]
+    pub fn icon_gutter_test() {
+        let avatar = RgbImage::new(&[255u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+        let data = UserData::new_text("头像\n".to_string()).set_icon(avatar, 0).set_indent(1, None);
+        assert_eq!(data.icon.as_ref().map(|i| i.size), Some(1));
+        let plain_hash = UserData::new_text("头像\n".to_string()).set_indent(1, None).content_hash();
+        assert_ne!(data.content_hash(), plain_hash);
 
-    let mut down = true;
-    let index_vec = if current_point.y >= push_from_point.y {
-        // 向下选择
-        (select_from_row..data_buffer_slice.len()).collect::<Vec<usize>>()
-    } else {
-        // 向上选择
-        // debug!("向上划选");
-        down = false;
-        /*
-        这里引入一个魔法数字100,表示查找范围从0到起点行再向后扩展100个数据段，
-        这是为了临时解决划选区从起点数据段向右侧划选时，跨入同行内后面的数据段无法被查找到的问题，
-        数字100表示允许向起点数据段之后延伸查找最多100个数据段。
-         */
-        let max_rows = min(select_from_row + 100, data_buffer_slice.len() - 1);
-        (0..=max_rows).collect::<Vec<usize>>()
-    };
-    // debug!("开始查找结束点所在数据段: {:?}", index_vec);
-    // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
-    if let Some(select_to_row) = locate_target_rd(current_point, current_point.as_rect(), panel.w(), data_buffer_slice, index_vec) {
-        // debug!("select_from_row: {select_from_row}, select_to_row: {select_to_row:?}");
-        /*
-        向下选择时，select_to_row表示相对于select_from_row的偏移量或绝对位置；
-        向上选择时，select_to_row表示相对于0的偏移量也就是绝对位置。
-         */
-        let rd_range = if down {
-            if !select_to_row.expanded {
-                select_from_row..=(select_from_row + select_to_row.row)
-            } else {
-                select_from_row..=select_to_row.row
-            }
-        } else {
-            if select_from_row < select_to_row.row {
-                select_from_row..=select_to_row.row
-            } else {
-                select_to_row.row..=select_from_row
-            }
-        };
-        // let rd_range = select_from_row..=(select_from_row + select_to_row);
-        // debug!("rd_range: {:?}", rd_range);
-        // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
-        select_text(&push_from_point, current_point, data_buffer_slice, rd_range, selected_pieces, select_from_row);
-        // debug!("push_from: {:?}, current_point: {:?}", push_from_point, current_point);
-        panel.set_damage(true);
+        let avatar = RgbImage::new(&[255u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+        let rd: RichData = data.set_icon(avatar, 20).into();
+        // 图标位于引用缩进之后，后续行从图标右侧开始。
+        assert_eq!(rd.indent_start_x(), PADDING.left + QUOTE_INDENT_WIDTH);
+        assert_eq!(rd.line_start_x(), PADDING.left + QUOTE_INDENT_WIDTH + 20 + ICON_GAP);
+        assert_eq!(UserData::from(&rd).icon.map(|i| i.size), Some(20));
     }
-}
 
+    #[test]
+    pub fn padding_test() {
+        let padding = Padding::new(10, 8, -2, 4);
+        assert_eq!(padding, Padding { left: 10, top: 8, right: 0, bottom: 4 });
+        assert_eq!(padding.drawable_width(100), 90);
+        assert_eq!(padding.drawable_height(100), 88);
+    }
 
-/// 测量鼠标点击的片段内容字符索引位置。
-///
-/// # Arguments
-///
-/// * `piece`:
-/// * `point`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn search_index_of_piece(piece: &LinePiece, point: &mut ClickPoint) {
-    let len = piece.line.chars().count();
-    if let Ok(c_i) = (0..len).collect::<Vec<usize>>().binary_search_by({
-        set_font(piece.font, piece.font_size);
-        let text = piece.line.clone();
-        let x = point.x;
-        let start_x = piece.x;
-        move |pos| {
-            let (mut pw1, _) = measure(text.chars().take(*pos + 1).collect::<String>().as_str(), false);
-            let (mut pw2, _) = measure(text.chars().take(*pos).collect::<String>().as_str(), false);
-            pw1 += start_x;
-            pw2 += start_x;
-            if x > pw2 && x <= pw1 {
-                Ordering::Equal
-            } else if x <= pw2 {
-                Ordering::Greater
-            } else {
-                Ordering::Less
-            }
-        }
-    }) {
-        point.c_i = c_i;
-        // debug!("目标字符：{}，位置：{}, point: {point:?}", piece.line.chars().nth(c_i).unwrap(), c_i);
-    } else {
-        // debug!("没找到目标字符！")
+    #[test]
+    pub fn idle_policy_test() {
+        let policy = IdlePolicy::default();
+        let after = Duration::from_secs(IDLE_AFTER_SECS);
+        assert!(!policy.is_idle(after - Duration::from_secs(1), false));
+        assert!(policy.is_idle(after, false));
+        assert!(!policy.is_idle(after, true));
+
+        let strict = IdlePolicy { require_unfocused: false, ..policy };
+        assert!(strict.is_idle(after, true));
+
+        assert!(!IdlePolicy::disabled().is_idle(Duration::from_secs(3600), false));
+
+        let mut state = BlinkState::new();
+        assert!(!state.settle());
+        state.on();
+        state.toggle_when_on();
+        assert!(state.settle());
+        assert_eq!(state.degree(BlinkSpeed::Slow), BlinkDegree::Normal);
     }
-}
 
-/// 选择段落。
-/// 段落的定义：在目标点前后两个换行符之间的所有数据片段，可能跨越多个数据段。
-///
-/// # Arguments
-///
-/// * `anchor_row`: 目标点所在数据段索引。
-/// * `data_buffer`: 数据缓存片段。
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn select_paragraph(anchor_row: usize, push_from_point: &mut ClickPoint, data_buffer: &[RichData], selected_pieces: Arc<RwLock<Vec<Weak<RwLock<LinePiece>>>>>) {
-    let (mut from_point, mut to_point) = (ClickPoint::new(0, 0), ClickPoint::new(0, 0));
-    let (mut from_row, mut to_row) = (0, 0);
+    #[test]
+    pub fn break_opportunity_test() {
+        assert!(is_break_opportunity(" ", "a", false));
+        assert!(is_break_opportunity("a", " ", false));
+        assert!(is_break_opportunity("-", "b", false));
+        assert!(!is_break_opportunity("a", "b", false));
+        assert!(!is_break_opportunity("中", "文", false));
+        assert!(is_break_opportunity("中", "文", true));
+        assert!(is_break_opportunity("a", "中", true));
+        assert!(!is_break_opportunity("a", "b", true));
+    }
 
-    'BEFORE: for i in (0..=anchor_row).rev() {
-        let mut piece_range = 0..data_buffer[i].line_pieces.len();
-        if i == anchor_row {
-            if push_from_point.p_i > 0 {
-                piece_range = 0..push_from_point.p_i;
-            } else {
-                continue;
-            }
-        }
-        for j in piece_range.rev() {
-            if let Some(lp_arc) = data_buffer[i].line_pieces.get(j) {
-                let lp = &*lp_arc.read();
-                if lp.line.ends_with('\n') {
-                    from_row = i;
-                    from_point.p_i = j + 1;
-                    from_point.c_i = 0;
-                    from_point.x = lp.next_x;
-                    from_point.y = lp.next_y;
-                    break 'BEFORE;
-                }
-            }
-        }
+    #[test]
+    pub fn grapheme_bounds_test() {
+        // 组合字符和ZWJ表情序列各自构成一个字素簇
+        let text = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}x";
+        assert_eq!(grapheme_char_bounds(text), vec![0, 2, 7, 8]);
+        assert_eq!(grapheme_char_bounds(""), vec![0]);
+
+        assert_eq!(snap_to_graphemes(text, 1, 1), (0, 2));
+        assert_eq!(snap_to_graphemes(text, 3, 4), (2, 7));
+        assert_eq!(snap_to_graphemes(text, 2, 8), (2, 8));
+        assert_eq!(snap_to_graphemes(text, 7, 8), (7, 8));
     }
 
-    'AFTER: for i in anchor_row..data_buffer.len() {
-        let piece_range = if i == anchor_row {
-            push_from_point.p_i..data_buffer[i].line_pieces.len()
-        } else {
-            0..data_buffer[i].line_pieces.len()
-        };
-        for j in piece_range {
-            if let Some(lp_arc) = data_buffer[i].line_pieces.get(j) {
-                let lp = &*lp_arc.read();
-                if lp.line.ends_with('\n') {
-                    to_row = i;
-                    to_point.p_i = j;
-                    to_point.c_i = lp.line.chars().count() - 1;
-                    to_point.x = lp.next_x;
-                    to_point.y = lp.next_y;
-                    break 'AFTER;
-                }
+    #[test]
+    pub fn encode_mouse_test() {
+        let mut modes = TerminalModes::default();
+        assert_eq!(modes.encode_mouse(0, 1, 1, true), None);
+
+        modes.mouse_tracking = Some(MouseReportMode::Normal);
+        assert_eq!(modes.encode_mouse(0, 1, 1, true), Some(b"\x1b[M !!".to_vec()));
+        assert_eq!(modes.encode_mouse(2, 10, 5, false), Some(b"\x1b[M#*%".to_vec()));
+        // 超过95的行列号以单字节表示，不会被编码为多字节字符。
+        assert_eq!(modes.encode_mouse(0, 100, 223, true), Some(vec![0x1b, b'[', b'M', 32, 132, 255]));
+        assert_eq!(modes.encode_mouse(0, 1, 224, true), None);
+
+        modes.mouse_tracking = Some(MouseReportMode::X10);
+        assert_eq!(modes.encode_mouse(0, 1, 1, false), None);
+
+        modes.sgr_mouse = true;
+        assert_eq!(modes.encode_mouse(2, 300, 5, true), Some(b"\x1b[<2;300;5M".to_vec()));
+    }
+
+    #[test]
+    pub fn sgr_attributes_test() {
+        let ud = UserData::new_text("abc".to_string())
+            .set_fg_color(Color::Red)
+            .set_bg_color(Some(Color::Blue))
+            .set_italic(true)
+            .set_reverse(true);
+        let rd: RichData = ud.into();
+        assert_eq!(rd.font, Font::HelveticaItalic);
+        assert_eq!(rd.draw_colors(), (Color::Blue, Some(Color::Red)));
+
+        let ud: UserData = (&rd).into();
+        assert!(ud.italic && ud.reverse && !ud.faint && !ud.conceal);
+    }
+
+    #[test]
+    pub fn apply_palette_test() {
+        let mut palette = DEFAULT_ANSI_PALETTE;
+        palette[1] = Color::from_rgb(220, 50, 47);
+        let mut rd: RichData = UserData::new_text("red".to_string()).set_fg_color_index(2).into();
+        rd.apply_palette(&palette);
+        assert_eq!(rd.fg_color, Color::from_rgb(220, 50, 47));
+        assert_eq!(rd.bg_color, None);
+
+        let mut rd: RichData = UserData::new_text("plain".to_string()).set_fg_color(Color::Green).into();
+        rd.apply_palette(&palette);
+        assert_eq!(rd.fg_color, Color::Green);
+    }
+
+    #[test]
+    pub fn word_and_url_around_test() {
+        let text = "look at https://example.com/a?b=1, it's fine";
+        assert_eq!(word_around(text, 1), Some("look".to_string()));
+        assert_eq!(word_around(text, 4), None);
+        assert_eq!(word_around(text, 36), Some("it's".to_string()));
+        assert_eq!(url_around(text, 15), Some("https://example.com/a?b=1".to_string()));
+        assert_eq!(url_around(text, 1), None);
+        assert_eq!(url_around("(see www.foo.org)", 8), Some("www.foo.org".to_string()));
+        assert_eq!(urls_in("see https://a.com/x, and (www.b.org) or www."), vec!["https://a.com/x".to_string(), "www.b.org".to_string()]);
+        assert!(urls_in("no links here").is_empty());
+    }
+
+    #[test]
+    pub fn cursor_style_test() {
+        assert_eq!(CursorStyle::from_decscusr(0), Some((CursorStyle::Block, true)));
+        assert_eq!(CursorStyle::from_decscusr(4), Some((CursorStyle::Underline, false)));
+        assert_eq!(CursorStyle::from_decscusr(7), None);
+
+        let mut bs = BlinkState::new();
+        assert!(bs.toggle_cursor(true));
+        assert_eq!(bs.cursor_next, BlinkDegree::Contrast);
+        assert!(bs.toggle_cursor(false));
+        assert_eq!(bs.cursor_next, BlinkDegree::Normal);
+        assert!(!bs.toggle_cursor(false));
+
+        bs.set_cursor_style(CursorStyle::Bar, false);
+        assert!(!bs.toggle_cursor(true));
+    }
+
+    #[test]
+    pub fn reduced_motion_test() {
+        let mut bs = BlinkState::new();
+        let mut options = RenderOptions::default();
+        bs.on();
+        bs.set_rapid(true);
+        assert!(bs.advance(BlinkSpeed::Slow, &options));
+        assert!(bs.advance(BlinkSpeed::Rapid, &options));
+        options.set_reduced_motion(true);
+        assert!(options.reduced_motion());
+        assert!(bs.advance(BlinkSpeed::Slow, &options));
+        assert_eq!(bs.degree(BlinkSpeed::Slow), BlinkDegree::Normal);
+        assert_eq!(bs.degree(BlinkSpeed::Rapid), BlinkDegree::Normal);
+        assert!(!bs.advance(BlinkSpeed::Slow, &options));
+        assert!(!bs.advance(BlinkSpeed::Rapid, &options));
+
+        options.set_reduced_motion(false);
+        assert!(bs.advance(BlinkSpeed::Slow, &options));
+    }
+
+    #[test]
+    pub fn bubble_style_test() {
+        let mut style = BubbleStyle::new(BubbleAlign::Left, Color::from_rgb(40, 40, 40));
+        assert_eq!(style.inner_width(10, 410), 300 - 12);
+        assert_eq!(style.offset_x(10, 110, 10, 410), 6);
+
+        style.align = BubbleAlign::Right;
+        assert_eq!(style.offset_x(10, 110, 10, 410), 410 - 6 - 110);
+        // 内容比可绘制宽度更宽时仍从行首开始。
+        assert_eq!(style.offset_x(10, 500, 10, 410), 6);
+        style.max_width_percent = 0;
+        assert_eq!(style.inner_width(10, 410), 40 - 12);
+
+        let data = UserData::new_text("hello".to_string()).set_bubble(style);
+        let rd: RichData = data.clone().into();
+        assert_eq!(rd.bubble, Some(style));
+        assert_eq!(UserData::from(&rd).bubble, Some(style));
+        assert_ne!(data.content_hash(), UserData::new_text("hello".to_string()).content_hash());
+
+        let buffer = vec![rd];
+        let mut session = Vec::new();
+        write_session(&buffer, &mut session).unwrap();
+        let restored = read_session(session.as_slice()).unwrap();
+        assert_eq!(restored[0].bubble, Some(style));
+    }
+
+    #[test]
+    pub fn background_style_test() {
+        let (start, end) = (Color::from_rgb(200, 0, 0), Color::from_rgb(0, 0, 200));
+        assert_eq!(gradient_color(start, end, 0, 11), start);
+        assert_eq!(gradient_color(start, end, 10, 11), end);
+        assert_eq!(gradient_color(start, end, 3, 1), start);
+        let (r, _, b) = gradient_color(start, end, 5, 11).to_rgb();
+        assert!(r.abs_diff(100) <= 1 && b.abs_diff(100) <= 1);
+
+        let style = BackgroundStyle::VerticalGradient(end);
+        assert_eq!(style.gradient(), Some((true, end)));
+        assert_eq!(BackgroundStyle::from_gradient(true, end), style);
+        assert_eq!(style.map_color(|_| start), BackgroundStyle::VerticalGradient(start));
+        assert_eq!(BackgroundStyle::Solid.gradient(), None);
+
+        let data = UserData::new_text("banner\n".to_string()).set_bg_color(Some(start)).set_bg_style(style);
+        let rd: RichData = data.clone().into();
+        assert_eq!(rd.bg_style, style);
+        assert_eq!(UserData::from(&rd).bg_style, style);
+        assert_ne!(data.content_hash(), UserData::new_text("banner\n".to_string()).set_bg_color(Some(start)).content_hash());
+    }
+
+    #[test]
+    pub fn text_shadow_test() {
+        let shadow = TextShadow::Shadow { offset_x: 1, offset_y: 2, color: Color::Black };
+        assert_eq!(shadow.offsets(), vec![(1, 2)]);
+        assert_eq!(shadow.color(), Color::Black);
+        assert!(TextShadow::Shadow { offset_x: 0, offset_y: 0, color: Color::Black }.offsets().is_empty());
+
+        let outline = TextShadow::Outline { width: 0, color: Color::White };
+        let offsets = outline.offsets();
+        assert_eq!(offsets.len(), 8);
+        assert!(!offsets.contains(&(0, 0)));
+        assert!(offsets.contains(&(-1, -1)) && offsets.contains(&(1, 1)));
+
+        let mut options = RenderOptions::default();
+        options.set_text_shadow(Some(outline));
+        assert_eq!(options.on_background(Color::White).text_shadow, Some(outline));
+    }
+
+    #[test]
+    pub fn color_transform_test() {
+        use std::sync::Arc;
+        use parking_lot::RwLock;
+
+        // 灰色不受色觉障碍调整影响。
+        for vision in [ColorVision::Deuteranopia, ColorVision::Protanopia] {
+            let (r, g, b) = vision.correct(Color::from_rgb(128, 128, 128)).to_rgb();
+            assert!(r.abs_diff(128) <= 1 && g.abs_diff(128) <= 1 && b.abs_diff(128) <= 1);
+        }
+        assert_eq!(ColorVision::Deuteranopia.ansi_palette(), DEUTERANOPIA_ANSI_PALETTE);
+        let red = Color::from_rgb(255, 0, 0);
+        assert_ne!(ColorVision::Deuteranopia.correct(red), red);
+
+        // 自定义调色板叠加色觉障碍配色后不会被内置调色板替换。
+        assert_eq!(compose_palette(None, Some(ColorVision::Deuteranopia)), DEUTERANOPIA_ANSI_PALETTE);
+        let mut custom = DEFAULT_ANSI_PALETTE;
+        custom[1] = red;
+        assert_eq!(compose_palette(Some(custom), None), custom);
+        let composed = compose_palette(Some(custom), Some(ColorVision::Deuteranopia));
+        assert_eq!(composed[1], ColorVision::Deuteranopia.correct(red));
+        assert_ne!(composed, DEUTERANOPIA_ANSI_PALETTE);
+
+        let invert = ColorFilter::new(Arc::new(RwLock::new(Box::new(|c: Color| {
+            let (r, g, b) = c.to_rgb();
+            Color::from_rgb(255 - r, 255 - g, 255 - b)
+        }))));
+        let transform = ColorTransform { filter: Some(invert), vision: Some(ColorVision::Deuteranopia) };
+        // 调色板颜色只经过变换函数处理。
+        assert_eq!(transform.apply(red, true), Color::from_rgb(0, 255, 255));
+
+        let mut rd: RichData = UserData::new_text("text".to_string()).set_fg_color(Color::from_rgb(10, 20, 30)).into();
+        rd.bg_color = None;
+        assert_eq!(transform_color(red, false), red);
+        {
+            let _scope = ColorTransformScope::enter(&ColorTransform { filter: transform.filter.clone(), vision: None });
+            assert_eq!(rd.draw_colors(), (Color::from_rgb(245, 235, 225), None));
+            {
+                let _inner = ColorTransformScope::enter(&ColorTransform::default());
+                assert_eq!(transform_color(red, false), red);
             }
+            assert_eq!(transform_color(red, true), Color::from_rgb(0, 255, 255));
         }
+        assert_eq!(transform_color(red, false), red);
+        assert_eq!(rd.draw_colors().0, Color::from_rgb(10, 20, 30));
     }
 
-    let rd_range = from_row..=to_row;
-    select_text(&from_point, &to_point, data_buffer, rd_range, selected_pieces, anchor_row);
-}
+    #[test]
+    pub fn disabled_style_test() {
+        let (fg, bg) = (Color::from_rgb(200, 100, 0), Color::from_rgb(0, 0, 0));
+        assert_eq!(DisabledStyle::Strike.text_color(fg, bg), fg);
+        assert_eq!(DisabledStyle::None.text_color(fg, bg), fg);
+        let (r, g, b) = DisabledStyle::Desaturate.text_color(fg, bg).to_rgb();
+        assert!(r == g && g == b);
+        let (r, _, _) = DisabledStyle::Dim(0.5).text_color(fg, bg).to_rgb();
+        assert!(r < 200 && r > 0);
+
+        // 删除线随禁用样式切换。
+        let mut rd: RichData = UserData::new_text("link".to_string()).into();
+        rd.disabled = true;
+        rd.strike_through = true;
+        rd.set_disabled_style(DisabledStyle::Dim(0.5));
+        assert!(!rd.strike_through);
+        rd.set_disabled_style(DisabledStyle::Strike);
+        assert!(rd.strike_through);
+
+        // 过期的数据段同样按照禁用样式显示。
+        let mut expired: RichData = UserData::new_text("link".to_string()).into();
+        expired.set_disabled_style(DisabledStyle::Dim(0.5));
+        let ids = HashSet::from([expired.id]);
+        let mut buffer = vec![expired];
+        expire_data_by_ids(&mut buffer, &ids);
+        assert!(buffer[0].expired && !buffer[0].strike_through);
+        buffer[0].set_disabled_style(DisabledStyle::Strike);
+        assert!(buffer[0].strike_through);
+
+        let image = RgbImage::new(&[255u8, 0, 0, 255, 0, 0, 255, 0, 0, 255, 0, 0], 2, 2, ColorDepth::Rgb8).unwrap();
+        let mut rd: RichData = UserData::new_image(image, 2, 2, 20, 20, None).into();
+        rd.disabled = true;
+        rd.refresh_inactive_image();
+        assert_eq!(rd.image_inactive.as_ref().map(|data| data.len()), Some(4));
+        rd.set_disabled_style(DisabledStyle::Dim(0.5));
+        assert_eq!(rd.image_inactive.as_ref().map(|data| data.len()), Some(12));
+        rd.set_disabled_style(DisabledStyle::None);
+        assert!(rd.image_inactive.is_none());
+        assert!(!rd.strike_through);
+    }
 
-/// 获取指定颜色的对比色。若指定颜色为中等灰色(R/G/B值相等且在116-139之间)，则返回白色。
-///
-/// # Arguments
-///
-/// * `color`: 指定颜色。
-///
-/// returns: Color 返回其对比色。
-///
-/// # Examples
-///
-/// ```
-/// use fltk::enums::Color;
-/// use fltkrs_richdisplay::get_contrast_color;
-///
-/// assert_eq!(get_contrast_color(Color::from_rgb(255, 255, 255)), Color::from_rgb(0, 0, 0));
-/// assert_eq!(get_contrast_color(Color::from_rgb(0, 0, 0)), Color::from_rgb(255, 255, 255));
-/// assert_eq!(get_contrast_color(Color::from_rgb(255, 0, 0)), Color::from_rgb(0, 255, 255));
-/// assert_eq!(get_contrast_color(Color::from_rgb(120, 120, 120)), Color::from_rgb(255, 255, 255));
-/// ```
-pub fn get_contrast_color(color: Color) -> Color {
-    let (r, g, b) = color.to_rgb();
-    let (cr, cg, cb) = (255 - r, 255 - g, 255 - b);
-    if (cr == cg && cg == cb) && ((cr as i16) - (r as i16)).abs() < 25 {
-        WHITE
-    } else {
-        Color::from_rgb(cr, cg, cb)
+    #[test]
+    pub fn minimum_contrast_test() {
+        let (black, white) = (Color::from_rgb(0, 0, 0), Color::from_rgb(255, 255, 255));
+        assert!((contrast_ratio(white, black) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(black, white) - 21.0).abs() < 0.01);
+
+        // 已满足对比度要求时保持原色。
+        let gray = Color::from_rgb(200, 200, 200);
+        assert_eq!(ensure_contrast(gray, black, 4.5), gray);
+
+        // 深色背景上的深蓝文字向白色靠拢。
+        let navy = Color::from_rgb(0, 0, 128);
+        let fixed = ensure_contrast(navy, black, 4.5);
+        assert!(contrast_ratio(fixed, black) >= 4.5);
+        let (r, g, b) = fixed.to_rgb();
+        assert!(r > 0 && g > 0 && b > 128);
+
+        // 浅色背景上的浅黄文字向黑色靠拢。
+        let fixed = ensure_contrast(Color::from_rgb(255, 255, 150), white, 4.5);
+        assert!(contrast_ratio(fixed, white) >= 4.5);
+
+        // 中灰背景无法达到21时取对比度较高的一方。
+        assert_eq!(ensure_contrast(Color::from_rgb(120, 120, 120), Color::from_rgb(118, 118, 118), 21.0), black);
+
+        let mut options = RenderOptions::default();
+        assert_eq!(options.legible(navy, None), navy);
+        options.set_minimum_contrast(4.5);
+        let on_black = options.on_background(black);
+        let fixed = on_black.legible(navy, None);
+        assert!(contrast_ratio(fixed, black) >= 4.5);
+        assert!(contrast_ratio(options.legible(navy, Some(white)), white) >= 4.5);
+        assert_eq!(options.legible(navy, Some(white)), navy);
+
+        // 调整结果按前景色和背景色缓存，各背景色下的副本共用缓存，对比度变化时清空。
+        assert_eq!(options.contrast_cache.read().get(&(navy.bits(), black.bits())), Some(&fixed));
+        options.clear_cache();
+        assert!(on_black.contrast_cache.read().is_empty());
+        assert_eq!(on_black.legible(navy, None), fixed);
+        options.set_minimum_contrast(1.0);
+        assert!(on_black.contrast_cache.read().is_empty());
+        assert_eq!(options.legible(navy, None), navy);
     }
-}
 
-/// 获取指定颜色的亮色或暗色，若指定颜色的R/G/B值其中最大的超过128，则获取暗色，否则获取亮色。
-///
-/// # Arguments
-///
-/// * `color`: 指定颜色。
-///
-/// returns: Color 返回对应的亮色或暗色。
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub fn get_lighter_or_darker_color(color: Color) -> Color {
-    let (r, g, b) = color.to_rgb();
+    #[test]
+    pub fn zoom_font_size_test() {
+        assert_eq!(zoom_font_size(16, true), 18);
+        assert_eq!(zoom_font_size(16, false), 14);
+        assert_eq!(zoom_font_size(MIN_ZOOM_FONT_SIZE, false), MIN_ZOOM_FONT_SIZE);
+        assert_eq!(zoom_font_size(MAX_ZOOM_FONT_SIZE, true), MAX_ZOOM_FONT_SIZE);
 
-    let total = r as u16 + g as u16 + b as u16;
-    let max_c = max(r, max(g, b));
-    if total >= 383 || max_c as u16 + 127 > 255u16 {
-        // 当三原色合计值超过最大合计值的一半时，或者某项原色值超过128，降低各原色数值。效果是变暗。
-        let (cr, cg, cb) = (max(0i16, r as i16 - 127), max(0i16, g as i16 - 127), max(0i16, b as i16 - 127));
-        Color::from_rgb(cr as u8, cg as u8, cb as u8)
-    } else {
-        // 当三原色合计值小于最大合计值的一半时，提高各原色数值。效果是变亮。
-        let (cr, cg, cb) = (min(255i16, r as i16 + 127), min(255i16, g as i16 + 127), min(255i16, b as i16 + 127));
-        Color::from_rgb(cr as u8, cg as u8, cb as u8)
+        let mut rd: RichData = UserData::new_text("正文\n".to_string()).set_font_and_size(Font::Courier, 24).into();
+        scale_font_size(&mut rd, 16, 20);
+        assert_eq!(rd.font_size, 30);
     }
-}
 
-/// 使符合过滤条件的目标数据段过期、禁用。
-///
-/// # Arguments
-///
-/// * `target`:
-///
-/// returns: ()
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub(crate) fn expire_data(buffer: Arc<RwLock<Vec<RichData>>>, target: &String) {
-    for rd in buffer.write().iter_mut() {
-        let mut should_expire = false;
-        if let Some(action) = &rd.action {
-            if let Some(cat) = &action.category {
-                if target.eq(cat) {
-                    should_expire = true;
-                }
-            }
-        }
-        if should_expire {
-            rd.action = None;
-            rd.expired = true;
-            rd.clickable = false;
-            rd.disabled = true;
-            rd.strike_through = true;
-        }
+    #[test]
+    pub fn rapid_blink_test() {
+        let mut bs = BlinkState::new();
+        bs.on();
+        assert!(!bs.toggle_rapid_when_on());
+        bs.set_rapid(true);
+        assert!(bs.toggle_rapid_when_on());
+        assert_eq!(bs.degree(BlinkSpeed::Rapid), BlinkDegree::Contrast);
+        assert_eq!(bs.degree(BlinkSpeed::Slow), BlinkDegree::Normal);
+        bs.reset_phase();
+        assert_eq!(bs.degree(BlinkSpeed::Rapid), BlinkDegree::Normal);
+
+        let rd: RichData = UserData::new_text("警告\n".to_string()).set_blink_speed(BlinkSpeed::Rapid).into();
+        assert!(rd.blink);
+        assert_eq!(rd.blink_speed, BlinkSpeed::Rapid);
     }
-}
 
-/// 加载图片文件并生成面板更新信息。
-///
-/// # Arguments
-///
-/// * `data_id`: 数据段ID。
-/// * `file_path`: 文件路径。
-/// * `target_width`: 图片目标宽度，可能与图片原始宽度不同。
-/// * `target_height`: 图片目标高度，可能与图片原始高度不同。
-///
-/// returns: RichDataOptions
-///
-/// # Examples
-///
-/// ```
-///
-/// ```
-pub fn load_image_from_file(load_opt: LoadImageOption) -> RichDataOptions {
-    let mut update_opt = RichDataOptions::new(load_opt.data_id);
-    if let Some(file_path) = load_opt.file_path {
-        if file_path.to_lowercase().ends_with(".svg") {
-            // 对于SVG格式的文件要特殊处理一下: normalize()，否则会转换出错。
-            match SvgImage::load(file_path.clone()) {
-                Ok(mut si) => {
-                    // debug!("开始转换到RGB格式，文件：{:?}", file_path);
-                    si.normalize();
-                    match si.to_rgb() {
-                        Ok(new_img) => {
-                            let mut new_action = Action::default();
-                            new_action.items.push(ActionItem::new("刷新", MXP_IMAGE_CONTEXT_MENU_REFRESH));
-                            new_action.items.push(ActionItem::new("复制地址", MXP_IMAGE_CONTEXT_MENU_COPY_URL));
-                            new_action.items.push(ActionItem::new("另存为", MXP_IMAGE_CONTEXT_MENU_SAVE_AS));
-                            update_opt = update_opt.image(Some(new_img), load_opt.target_width, load_opt.target_height)
-                                .text(String::new())
-                                .image_file_path(PathBuf::from(file_path))
-                                .change_action(new_action);
-                        }
-                        Err(e) => {
-                            error!("将SVG转换到RGB格式时失败：{:?}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("加载或解码图片失败：{:?} {:?}", file_path, e);
-                    update_opt = update_opt.text("decoding failed".to_string());
-                }
-            }
-        } else {
-            match SharedImage::load(file_path.clone()) {
-                Ok(si) => {
-                    // debug!("开始转换到RGB格式，文件：{:?}", file_path);
-                    match si.to_rgb() {
-                        Ok(new_img) => {
-                            let mut new_action = Action::default();
-                            new_action.items.push(ActionItem::new("刷新", MXP_IMAGE_CONTEXT_MENU_REFRESH));
-                            new_action.items.push(ActionItem::new("复制地址", MXP_IMAGE_CONTEXT_MENU_COPY_URL));
-                            new_action.items.push(ActionItem::new("另存为", MXP_IMAGE_CONTEXT_MENU_SAVE_AS));
-                            update_opt = update_opt.image(Some(new_img), load_opt.target_width, load_opt.target_height)
-                                .text(String::new())
-                                .image_file_path(PathBuf::from(file_path))
-                                .change_action(new_action);
-                        }
-                        Err(e) => {
-                            error!("将通用格式转换到RGB格式时失败：{:?}", e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    error!("加载或解码图片失败：{:?} {:?}", file_path, e);
-                    update_opt = update_opt.text("decoding failed".to_string());
-                }
-            }
-        }
+    #[test]
+    pub fn scan_line_starts_test() {
+        let data = b"first\r\nsecond\n\nlast";
+        assert_eq!(scan_line_starts(data, 0, 10), (vec![7, 14, 15], data.len()));
+        assert_eq!(scan_line_starts(data, 0, 2), (vec![7, 14], 14));
+        assert_eq!(scan_line_starts(b"end\n", 0, 10), (vec![], 4));
 
-    } else {
-        update_opt = update_opt.text("save failed".to_string());
+        let id = segment_id(41, 3);
+        assert_eq!(line_of(id), Some(41));
+        assert_eq!(line_of(segment_id(0, 0)), Some(0));
+        assert_eq!(line_of(0), None);
     }
-    update_opt
-}
 
-#[cfg(test)]
-mod tests {
-    use fltk::enums::Color;
-    use crate::{get_contrast_color, get_lighter_or_darker_color, WHITE, Rectangle};
+    #[test]
+    pub fn parse_ansi_line_test() {
+        let data = parse_ansi_line("plain \x1b[1;31mred\x1b[22m thin\x1b[0m \x1b[4:3;38;5;196mcurly\x1b[K");
+        assert_eq!(data.len(), 5);
+        assert_eq!(data[0].text, "plain ");
+        assert_eq!(data[1].text, "red");
+        assert!(data[1].strong);
+        assert_eq!(data[1].fg_color_index, 2);
+        assert_eq!(data[1].fg_color, DEFAULT_ANSI_PALETTE[1]);
+        assert_eq!(data[2].text, " thin");
+        assert!(!data[2].strong);
+        assert_eq!(data[2].fg_color_index, 2);
+        assert_eq!(data[3].fg_color_index, 0);
+        assert_eq!(data[4].text, "curly\n");
+        assert_eq!(data[4].underline_style, UnderlineStyle::Curly);
+        assert_eq!(data[4].fg_color, Color::from_rgb(255, 0, 0));
+    }
 
     #[test]
-    pub fn make_rectangle_test() {
-        let rect = Rectangle::new(20, 30, 25, 25);
-        assert_eq!(rect.tup(), (20, 30, 25, 25));
+    pub fn wrap_cache_test() {
+        let mut cache = WrapCache::new(2);
+        let a = WrapKey::new("a", Font::Helvetica, 16, None);
+        let b = WrapKey::new("b", Font::Helvetica, 16, None);
+        let c = WrapKey::new("a", Font::Helvetica, 16, Some(100));
+        assert_ne!(a, c);
 
-        let rect = Rectangle::new(20, 30, -10, 25);
-        assert_eq!(rect.tup(), (10, 30, 10, 25));
+        assert_eq!(cache.get(&a), None);
+        cache.insert(a.clone(), WrapValue::Measure(10, 20));
+        cache.insert(b.clone(), WrapValue::Measure(12, 20));
+        assert_eq!(cache.get(&a), Some(WrapValue::Measure(10, 20)));
 
-        let rect = Rectangle::new(20, 30, 10, -25);
-        assert_eq!(rect.tup(), (20, 5, 10, 25));
+        // b最久未使用，被淘汰
+        cache.insert(c.clone(), WrapValue::Break(Some((3, 30))));
+        assert_eq!(cache.get(&b), None);
+        assert_eq!(cache.get(&c), Some(WrapValue::Break(Some((3, 30)))));
 
-        let rect = Rectangle::new(20, 30, -10, -25);
-        assert_eq!(rect.tup(), (10, 5, 10, 25));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.len, stats.capacity), (2, 2, 2, 2));
+        assert_eq!(stats.hit_rate(), 0.5);
+
+        cache.set_capacity(0);
+        assert_eq!(cache.stats().len, 0);
+        assert_eq!(cache.get(&c), None);
     }
 
     #[test]
-    pub fn str_index_test() {
-        let str = String::from("我爱中国");
-        assert_eq!(str.find("中国"), Some(6));
+    pub fn recent_urls_test() {
+        let mut urls = RecentUrls::new(2);
+        assert!(!urls.touch("https://a.com"));
+        assert!(!urls.touch("https://b.com"));
+        assert!(urls.touch("https://a.com"));
 
-        str.rmatch_indices("中国").for_each(|(i, _)| {
-            let ni = str[0..i].chars().count();
-            assert_eq!(ni, 2);
-        })
+        // b最久未出现，被淘汰
+        assert!(!urls.touch("https://c.com"));
+        assert_eq!(urls.len(), 2);
+        assert!(urls.touch("https://a.com"));
+        assert!(!urls.touch("https://b.com"));
     }
 
     #[test]
-    pub fn test_contrast_color_test() {
-        assert_eq!(get_contrast_color(Color::from_rgb(255, 255, 255)), Color::from_rgb(0, 0, 0));
-        assert_eq!(get_contrast_color(Color::from_rgb(0, 0, 0)), Color::from_rgb(255, 255, 255));
-        for i in 1..116 {
-            assert_ne!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
-        }
-        for i in 116..=139 {
-            assert_eq!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
+    pub fn thread_hidden_ids_test() {
+        let topic = UserData::new_text("topic\n".to_string());
+        let reply = UserData::new_text("reply\n".to_string()).set_parent(topic.id);
+        let nested = UserData::new_text("nested\n".to_string()).set_parent(reply.id);
+        let other = UserData::new_text("other\n".to_string());
+        let (topic_id, reply_id, nested_id) = (topic.id, reply.id, nested.id);
+        let buffer: Vec<RichData> = vec![topic.into(), reply.into(), nested.into(), other.into()];
+
+        let hidden = thread_hidden_ids(&buffer, &HashSet::from([topic_id]));
+        assert_eq!(hidden, HashSet::from([reply_id, nested_id]));
+
+        let hidden = thread_hidden_ids(&buffer, &HashSet::from([reply_id]));
+        assert_eq!(hidden, HashSet::from([nested_id]));
+
+        assert!(thread_hidden_ids(&buffer, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    pub fn dim_image_data_test() {
+        let mut data = vec![200u8, 100, 0, 255];
+        dim_image_data(&mut data, ColorDepth::Rgba8, Color::from_rgb(0, 0, 0), 0.5);
+        assert_eq!(data, vec![100, 50, 0, 255]);
+
+        let mut gray = vec![0u8, 128];
+        dim_image_data(&mut gray, ColorDepth::La8, Color::from_rgb(255, 255, 255), 1.0);
+        assert_eq!(gray, vec![255, 128]);
+
+        let mut data = vec![10u8, 20, 30];
+        dim_image_data(&mut data, ColorDepth::Rgb8, Color::from_rgb(255, 255, 255), 0.0);
+        assert_eq!(data, vec![10, 20, 30]);
+    }
+
+    #[test]
+    pub fn session_round_trip_test() {
+        let mut action = Action::default();
+        action.items.push(ActionItem::new("查看", "look"));
+        let text = UserData::new_text("你好\n".to_string())
+            .set_fg_color(Color::from_rgb(255, 0, 0))
+            .set_bg_color(Some(Color::from_rgb(0, 0, 255)))
+            .set_action(action);
+        let table = UserData::new_table(Table::new(vec![vec![TableCell::new("a"), TableCell::new("b")]]));
+        let image = RgbImage::new(&[255u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+        let image = UserData::new_image(image, 2, 2, 20, 20, None);
+        let avatar = RgbImage::new(&[0u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+        let text = text.set_icon(avatar, 16);
+        let buffer: Vec<RichData> = vec![text.into(), table.into(), image.into()];
+
+        let mut session = Vec::new();
+        write_session(&buffer, &mut session).unwrap();
+        let data = read_session(session.as_slice()).unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0].text, "你好\n");
+        assert_eq!(data[0].fg_color, Color::from_rgb(255, 0, 0));
+        assert_eq!(data[0].bg_color, Some(Color::from_rgb(0, 0, 255)));
+        assert_eq!(data[0].action.as_ref().unwrap().items[0].cmd, "look");
+        let icon = data[0].icon.as_ref().unwrap();
+        assert_eq!((icon.size, icon.width, icon.height, icon.data.len()), (16, 2, 2, 12));
+        assert!(data[1].icon.is_none());
+        assert_eq!(data[1].table.as_ref().unwrap().rows[0][1].text, "b");
+        let restored = data[2].image.as_ref().unwrap();
+        assert_eq!((restored.data_w(), restored.data_h()), (2, 2));
+        assert_eq!((data[2].image_target_width, data[2].image_target_height), (20, 20));
+
+        // 共用同一图标的数据段只保存一份图标数据，读取后仍共享图标数据。
+        let buffer: Vec<RichData> = vec![data[0].clone().into(), data[0].clone().into()];
+        let mut session = Vec::new();
+        write_session(&buffer, &mut session).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&session).unwrap();
+        assert_eq!(json["icons"].as_array().map(Vec::len), Some(1));
+        let data = read_session(session.as_slice()).unwrap();
+        let (a, b) = (data[0].icon.as_ref().unwrap(), data[1].icon.as_ref().unwrap());
+        assert!(std::sync::Arc::ptr_eq(&a.data, &b.data));
+        assert_eq!(a.size, 16);
+
+        // 图标内容不同的数据段不会被视为重复数据。
+        let red = UserData::new_text("你好\n".to_string()).set_icon(RgbImage::new(&[255u8; 12], 2, 2, ColorDepth::Rgb8).unwrap(), 16);
+        let black = UserData::new_text("你好\n".to_string()).set_icon(RgbImage::new(&[0u8; 12], 2, 2, ColorDepth::Rgb8).unwrap(), 16);
+        assert_ne!(red.content_hash(), black.content_hash());
+
+        assert!(read_session(r#"{"version":99,"data":[]}"#.as_bytes()).is_err());
+        let mut json = serde_json::to_value(UserData::new_text("颜色\n".to_string())).unwrap();
+        json["fg_color"] = serde_json::Value::String("not a color".to_string());
+        assert!(serde_json::from_value::<UserData>(json).is_err());
+        assert_eq!(parse_font("Courier(4)"), Font::Courier);
+    }
+
+    #[test]
+    pub fn log_sink_test() {
+        let data = UserData::new_text("警告\n".to_string()).set_fg_color_index(1).set_strong(true);
+        let line = format_log_entry(&data, LogSinkFormat::Ansi).unwrap();
+        assert_eq!(line, "\x1b[1;30m警告\x1b[0m\n");
+        let parsed = parse_ansi_line(line.trim_end());
+        assert!(parsed[0].strong);
+        assert_eq!(parsed[0].fg_color_index, 1);
+        assert_eq!(format_log_entry(&data, LogSinkFormat::PlainText).unwrap(), "警告\n");
+        assert!(format_log_entry(&UserData::new_marker("prompt".to_string(), None), LogSinkFormat::PlainText).is_none());
+        assert!(format_log_entry(&data, LogSinkFormat::JsonLines).unwrap().ends_with("}\n"));
+
+        let dir = std::env::temp_dir().join(format!("richdisplay_log_sink_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("session.log");
+        let mut sink = LogSink::new(&path, LogSinkFormat::PlainText).with_rotation(8, 2);
+        for text in ["aaaa\n", "bbbb\n", "cccc\n", "dddd\n"] {
+            sink.write(&UserData::new_text(text.to_string())).unwrap();
         }
-        for i in 140..=255 {
-            assert_ne!(get_contrast_color(Color::from_rgb(i, i, i)), WHITE);
+        // 最后一段仍在缓冲区中，轮转时已写入的文件内容完整。
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        sink.flush().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "dddd\n");
+        assert_eq!(std::fs::read_to_string(dir.join("session.log.1")).unwrap(), "cccc\n");
+        assert_eq!(std::fs::read_to_string(dir.join("session.log.2")).unwrap(), "bbbb\n");
+        assert!(!dir.join("session.log.3").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn encode_png_test() {
+        let rgb = to_rgb8(&[255, 0, 0, 128, 0, 0, 255, 255], ColorDepth::Rgba8, Color::from_rgb(0, 0, 0));
+        assert_eq!(rgb, vec![128, 0, 0, 0, 0, 255]);
+        assert_eq!(to_rgb8(&[7], ColorDepth::L8, Color::White), vec![7, 7, 7]);
+
+        let mut encoder = PngEncoder::new(Vec::new(), 2, 1).unwrap();
+        encoder.write_rows(&rgb).unwrap();
+        let png = encoder.finish().unwrap();
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        // IEND数据块的CRC是固定值。
+        assert_eq!(&png[png.len() - 8..], &[b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]);
+
+        // 分块写入的单色图片被压缩到远小于原始数据的大小。
+        let (width, height) = (200u32, 300u32);
+        let mut encoder = PngEncoder::new(Vec::new(), width, height).unwrap();
+        let strip = vec![0x33u8; width as usize * 3 * 100];
+        for _ in 0..3 {
+            encoder.write_rows(&strip).unwrap();
         }
+        // 超出图片高度的行被忽略。
+        encoder.write_rows(&strip).unwrap();
+        let png = encoder.finish().unwrap();
+        assert!(png.len() < (width * height * 3) as usize / 50);
+        assert_eq!(png.windows(4).filter(|w| *w == b"IDAT").count(), 4);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
     }
 
     #[test]
-    pub fn get_lighter_color_test() {
-        let lighter = get_lighter_or_darker_color(Color::DarkCyan);
-        println!("{:?} -> {:?}", Color::DarkCyan.to_rgb(), lighter.to_rgb())
+    pub fn stable_hasher_test() {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = StableHasher::default();
+        hasher.write(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+
+        // 整数按小端字节序写入，与平台无关。
+        let (mut a, mut b) = (StableHasher::default(), StableHasher::default());
+        7usize.hash(&mut a);
+        b.write(&7u64.to_le_bytes());
+        assert_eq!(a.finish(), b.finish());
     }
 
     #[test]
-    pub fn emoji_test() {
-        let emoji = "😀";
-        println!("{:?}", emoji.chars().nth(0).unwrap());
-        assert_eq!(emoji.len(), 1);
+    pub fn print_colors_test() {
+        let mut divider: RichData = UserData::new_divider(None, DividerStyle::Solid, Color::Red).into();
+        divider.to_print_colors();
+        assert_eq!(divider.divider.as_ref().map(|d| d.color), Some(Color::Black));
+
+        let mut gauge: RichData = UserData::new_gauge(30.0, 100.0, 80, Color::Red, None).set_indent(1, Some(Color::Green)).into();
+        gauge.to_print_colors();
+        assert_eq!(gauge.gauge.as_ref().map(|g| g.color), Some(PRINT_ACCENT_COLOR));
+        assert_eq!(gauge.quote_color, Some(PRINT_ACCENT_COLOR));
+
+        let table = Table::new(vec![vec![TableCell::new("a").set_fg_color(Color::Yellow).set_bg_color(Color::Blue)]]).set_border_color(Color::Red);
+        let mut table: RichData = UserData::new_table(table).into();
+        table.to_print_colors();
+        let table = table.table.unwrap();
+        assert_eq!(table.border_color, Some(Color::Black));
+        assert_eq!((table.rows[0][0].fg_color, table.rows[0][0].bg_color), (None, None));
     }
 
     #[test]
-    pub fn fold_chars_test() {
-        let hint = "这里是一个空旷的广场，地面上散落着一些碎纸片。";
-        let mut count = 0;
-        let new_hint = hint.chars().fold("".to_string(), |mut s, c| {
-            s.push(c);
-            count += 1;
-            if count % 8 == 0 {
-                s.push_str("\r\n");
-            }
+    pub fn paginate_test() {
+        let rows = [(0, 20), (20, 20), (40, 20), (60, 20)];
+        // 第三行跨越分页处，整行移到下一页。
+        assert_eq!(paginate(&rows, 80, 50), vec![(0, 40), (40, 80)]);
+        assert_eq!(paginate(&rows, 80, 80), vec![(0, 80)]);
+        // 超过一页高度的行被强制截断。
+        assert_eq!(paginate(&[(0, 120)], 120, 50), vec![(0, 50), (50, 100), (100, 120)]);
+        assert!(paginate(&[], 0, 50).is_empty());
+    }
 
-            s
-        });
-        println!("{:?}", new_hint);
+    #[test]
+    pub fn page_prefetch_test() {
+        let mut prefetch = PagePrefetch { screens: 2.0, ..Default::default() };
+        assert_eq!(prefetch.near_edge(1000, 5000, 300), None);
+        assert_eq!(prefetch.near_edge(500, 5000, 300), Some(false));
+        assert_eq!(prefetch.near_edge(4500, 5000, 300), Some(true));
+
+        let now = Instant::now();
+        assert!(prefetch.begin(true, now));
+        // 等待期间不重复请求。
+        assert!(!prefetch.begin(false, now + Duration::from_millis(100)));
+        assert_eq!(prefetch.pending(now + Duration::from_millis(100)), Some((true, Duration::from_millis(100))));
+        prefetch.complete();
+        assert!(prefetch.pending(now).is_none());
+
+        assert!(prefetch.begin(false, now));
+        // 超时后允许再次请求。
+        assert!(prefetch.pending(now + prefetch.timeout).is_none());
+        assert!(prefetch.begin(true, now + prefetch.timeout));
+
+        // 收到空页后该方向不再请求，另一方向不受影响。
+        let later = now + prefetch.timeout * 2;
+        prefetch.complete();
+        prefetch.record_page(&crate::PageOptions::NextPage(1), true);
+        assert!(prefetch.is_exhausted(true));
+        assert!(!prefetch.begin(true, later));
+        assert!(prefetch.begin(false, later));
+        prefetch.complete();
+        // 用户在边缘继续滚动时重新允许请求，跳转后两个方向都重新允许请求。
+        prefetch.retry(true);
+        assert!(!prefetch.is_exhausted(true));
+        prefetch.record_page(&crate::PageOptions::PrevPage(1), true);
+        prefetch.record_page(&crate::PageOptions::At(1), false);
+        assert!(!prefetch.is_exhausted(false) && !prefetch.is_exhausted(true));
     }
 
     #[test]
-    pub fn c1_test() {
-        let s = String::from_utf8_lossy(&[0xe2, 0x96, 0xbd]);
-        println!("{}", s);
+    pub fn log_file_page_at_test() {
+        let path = std::env::temp_dir().join(format!("richdisplay_page_at_{}.log", std::process::id()));
+        let text: String = (0..100).map(|i| format!("{}\n", i)).collect();
+        std::fs::write(&path, text).unwrap();
+        let log_file = LogFile::open(&path, Box::new(PlainText)).unwrap();
+
+        let page = log_file.page(&crate::PageOptions::At(segment_id(50, 0)), 10);
+        assert_eq!(page.len(), 10);
+        assert_eq!(page[0].text, "45\n");
+        let page = log_file.page(&crate::PageOptions::At(segment_id(2, 0)), 10);
+        assert_eq!(page[0].text, "0\n");
+        drop(log_file);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn search_positions_test() {
+        assert_eq!(search_positions("中文abc中文", "中文"), vec![(5, 7), (0, 2)]);
+        assert!(search_positions("abc", "d").is_empty());
+    }
+
+    #[test]
+    pub fn keyword_ranges_test() {
+        let keywords = vec![
+            KeywordHighlight { word: "张三".to_string(), color: Color::Red },
+            KeywordHighlight { word: "三丰".to_string(), color: Color::Blue },
+            KeywordHighlight { word: "".to_string(), color: Color::Green },
+        ];
+        let ranges = keyword_ranges("张三丰说：张三来了，三丰走了", &keywords).unwrap();
+        assert_eq!(ranges, vec![(0, 2, Color::Red), (5, 7, Color::Red), (10, 12, Color::Blue)]);
+        assert!(keyword_ranges("李四", &keywords).is_none());
+
+        let mut rd: RichData = UserData::new_text("张三\n".to_string()).into();
+        rd.apply_keywords(&keywords);
+        assert_eq!(rd.keyword_ranges, Some(vec![(0, 2, Color::Red)]));
+        update_data_properties(RichDataOptions::new(rd.id).text("李四和张三\n".to_string()), &mut rd, &keywords);
+        assert_eq!(rd.keyword_ranges, Some(vec![(3, 5, Color::Red)]));
+        rd.apply_keywords(&[]);
+        assert!(rd.keyword_ranges.is_none());
+    }
+
+    #[test]
+    pub fn log_file_search_test() {
+        let path = std::env::temp_dir().join(format!("richdisplay_search_{}.log", std::process::id()));
+        let text: String = (0..100).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, text).unwrap();
+        let log_file = LogFile::open(&path, Box::new(PlainText)).unwrap();
+
+        let request = |pattern: &str, from_id: i64, forward: bool| SearchRequest { pattern: pattern.to_string(), from_id, forward };
+        assert_eq!(log_file.search(&request("line 7", 0, true)), Some(segment_id(7, 0)));
+        assert_eq!(log_file.search(&request("line 7", segment_id(7, 0), true)), Some(segment_id(70, 0)));
+        assert_eq!(log_file.search(&request("line 7", segment_id(70, 0), false)), Some(segment_id(7, 0)));
+        assert_eq!(log_file.search(&request("line 7", segment_id(7, 0), false)), None);
+        assert_eq!(log_file.search(&request("missing", 0, true)), None);
+        drop(log_file);
+        std::fs::remove_file(&path).unwrap();
     }
 }
@@ -0,0 +1,859 @@
+//! 大型日志文件的按需加载。`RichReviewer::open_log_file`以内存映射的方式打开日志文件，在后台线程中索引各行的起始位置，
+//! 浏览时通过懒加载分页机制按需解析当前页附近的行，无需一次性将整个文件转换为数据段。
+//!
+//! 日志文件中的每一行由`LogFormat`转换为一个或多个数据段。数据段的ID由行号和段序号组合而成，
+//! 分页回调可据此找到当前页首尾所在的行。内置的格式适配器有：
+//!
+//! * `PlainText`: 纯文本，每行原样显示。
+//! * `AnsiText`: 带有ANSI/CSI/SGR颜色和样式控制序列的文本，如终端会话记录。
+//! * `JsonLines`: 每行一个JSON对象，可将指定字段映射为显示样式和类别。
+//!
+//! `LogSink`是对应的写入端，由`RichText::set_logger`设置后，每个新添加的数据段都会写入会话日志文件，
+//! 写入的文件可再由上述格式适配器打开浏览。
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use log::debug;
+use memmap2::Mmap;
+use parking_lot::RwLock;
+use fltk::enums::Color;
+use serde_json::Value;
+use crate::{Action, BlinkSpeed, DataType, DEFAULT_ANSI_PALETTE, PageOptions, SearchRequest, UnderlineStyle, UserData};
+
+/// 数据段ID中用于区分同一行内不同数据段的位数，单行最多转换出4096个数据段。
+const SEGMENT_BITS: u32 = 12;
+
+/// 打开文件时同步索引的行数，保证可以立即显示第一页，其余的行在后台线程中索引。
+const INITIAL_INDEX_LINES: usize = 1000;
+
+/// 后台线程每批索引的行数，每批完成后合并到共享索引中。
+const INDEX_BATCH_LINES: usize = 65536;
+
+/// 会话日志缓冲内容写入文件的间隔时间，单位秒。
+pub(crate) const LOG_FLUSH_INTERVAL: f64 = 1.0;
+
+/// 日志格式，负责将日志文件中的一行转换为数据段。
+pub trait LogFormat: Send + Sync {
+    /// 将一行日志转换为数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `line`: 日志行，不包含行尾的换行符。
+    ///
+    /// returns: Vec<UserData, Global> 转换后的数据段，为空时该行不显示。最后一个数据段应以换行符结尾。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::log_file::{LogFormat, PlainText};
+    ///
+    /// let data = PlainText.parse_line("hello");
+    /// assert_eq!(data[0].text, "hello\n");
+    /// ```
+    fn parse_line(&self, line: &str) -> Vec<UserData>;
+}
+
+/// 纯文本日志，每行原样显示为一个使用默认样式的数据段。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlainText;
+
+impl LogFormat for PlainText {
+    fn parse_line(&self, line: &str) -> Vec<UserData> {
+        vec![UserData::new_text(format!("{line}\n"))]
+    }
+}
+
+/// 带有ANSI/CSI/SGR控制序列的文本日志，按照控制序列设置文字的颜色和样式，其他控制序列被忽略。
+/// 分页加载时各行相互独立，每行开始时样式均恢复为默认值。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AnsiText;
+
+/// SGR控制序列设置的当前样式。
+#[derive(Debug, Clone, Copy, Default)]
+struct SgrStyle {
+    fg_color: Option<Color>,
+    fg_color_index: u8,
+    bg_color: Option<Color>,
+    bg_color_index: u8,
+    strong: bool,
+    faint: bool,
+    italic: bool,
+    underline: Option<UnderlineStyle>,
+    blink: Option<BlinkSpeed>,
+    reverse: bool,
+    conceal: bool,
+    strike_through: bool,
+}
+
+impl SgrStyle {
+    /// 依次应用一个SGR控制序列中的所有参数。
+    fn apply(&mut self, params: &str) {
+        let mut params = params.split(';').map(|p| p.to_string()).collect::<Vec<String>>().into_iter();
+        while let Some(param) = params.next() {
+            // 下划线样式使用`4:n`的子参数形式。
+            let (code, sub) = match param.split_once(':') {
+                Some((code, sub)) => (code.parse::<u16>().unwrap_or(0), sub.parse::<u8>().ok()),
+                None => (param.parse::<u16>().unwrap_or(0), None),
+            };
+            match code {
+                0 => *self = SgrStyle::default(),
+                1 => self.strong = true,
+                2 => self.faint = true,
+                3 => self.italic = true,
+                4 => {
+                    self.underline = match sub {
+                        Some(0) => None,
+                        Some(2) => Some(UnderlineStyle::Double),
+                        Some(3) => Some(UnderlineStyle::Curly),
+                        Some(4) => Some(UnderlineStyle::Dotted),
+                        _ => Some(UnderlineStyle::Single),
+                    }
+                }
+                5 => self.blink = Some(BlinkSpeed::Slow),
+                6 => self.blink = Some(BlinkSpeed::Rapid),
+                7 => self.reverse = true,
+                8 => self.conceal = true,
+                9 => self.strike_through = true,
+                21 => self.underline = Some(UnderlineStyle::Double),
+                22 => {
+                    self.strong = false;
+                    self.faint = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = None,
+                25 => self.blink = None,
+                27 => self.reverse = false,
+                28 => self.conceal = false,
+                29 => self.strike_through = false,
+                30..=37 => self.set_fg_index((code - 29) as u8),
+                90..=97 => self.set_fg_index((code - 81) as u8),
+                39 => {
+                    self.fg_color = None;
+                    self.fg_color_index = 0;
+                }
+                40..=47 => self.set_bg_index((code - 39) as u8),
+                100..=107 => self.set_bg_index((code - 91) as u8),
+                49 => {
+                    self.bg_color = None;
+                    self.bg_color_index = 0;
+                }
+                38 | 48 => {
+                    let color = match params.next().as_deref() {
+                        Some("5") => params.next().and_then(|n| n.parse::<u8>().ok()).map(xterm_color),
+                        Some("2") => {
+                            let rgb = (0..3).filter_map(|_| params.next().and_then(|n| n.parse::<u8>().ok())).collect::<Vec<u8>>();
+                            if rgb.len() == 3 { Some(Color::from_rgb(rgb[0], rgb[1], rgb[2])) } else { None }
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.fg_color = color;
+                        self.fg_color_index = 0;
+                    } else {
+                        self.bg_color = color;
+                        self.bg_color_index = 0;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_fg_index(&mut self, index: u8) {
+        self.fg_color = Some(DEFAULT_ANSI_PALETTE[index as usize - 1]);
+        self.fg_color_index = index;
+    }
+
+    fn set_bg_index(&mut self, index: u8) {
+        self.bg_color = Some(DEFAULT_ANSI_PALETTE[index as usize - 1]);
+        self.bg_color_index = index;
+    }
+
+    /// 生成使用当前样式的数据段。
+    fn to_data(self, text: String) -> UserData {
+        let mut data = UserData::new_text(text)
+            .set_bg_color(self.bg_color)
+            .set_fg_color_index(self.fg_color_index)
+            .set_bg_color_index(self.bg_color_index)
+            .set_strong(self.strong)
+            .set_faint(self.faint)
+            .set_italic(self.italic)
+            .set_reverse(self.reverse)
+            .set_conceal(self.conceal);
+        data.strike_through = self.strike_through;
+        if let Some(fg_color) = self.fg_color {
+            data = data.set_fg_color(fg_color);
+        }
+        if let Some(style) = self.underline {
+            data = data.set_underline_style(style, None);
+        }
+        if let Some(speed) = self.blink {
+            data = data.set_blink_speed(speed);
+        }
+        data
+    }
+}
+
+/// 将xterm的256色序号转换为颜色。0到15为基本色及其高亮色，16到231为6x6x6色立方，232到255为灰阶。
+fn xterm_color(n: u8) -> Color {
+    match n {
+        0..=15 => DEFAULT_ANSI_PALETTE[n as usize],
+        16..=231 => {
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            let n = n - 16;
+            Color::from_rgb(level(n / 36), level(n / 6 % 6), level(n % 6))
+        }
+        _ => {
+            let gray = 8 + (n - 232) * 10;
+            Color::from_rgb(gray, gray, gray)
+        }
+    }
+}
+
+/// 将带有ANSI控制序列的文本拆分为样式各不相同的数据段。
+///
+/// # Arguments
+///
+/// * `line`: 日志行。
+///
+/// returns: Vec<UserData, Global>
+pub(crate) fn parse_ansi_line(line: &str) -> Vec<UserData> {
+    let mut data = vec![];
+    let mut style = SgrStyle::default();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            text.push(c);
+            continue;
+        }
+        if chars.peek() != Some(&'[') {
+            // 非CSI的转义序列只跳过下一个字符。
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let mut params = String::new();
+        let mut command = None;
+        for c in chars.by_ref() {
+            if ('\x40'..='\x7e').contains(&c) {
+                command = Some(c);
+                break;
+            }
+            params.push(c);
+        }
+        if command == Some('m') {
+            if !text.is_empty() {
+                data.push(style.to_data(std::mem::take(&mut text)));
+            }
+            style.apply(if params.is_empty() { "0" } else { &params });
+        }
+    }
+    text.push('\n');
+    data.push(style.to_data(text));
+    data
+}
+
+impl LogFormat for AnsiText {
+    fn parse_line(&self, line: &str) -> Vec<UserData> {
+        parse_ansi_line(line)
+    }
+}
+
+/// JSON日志行中字段取值对应的显示样式。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogStyle {
+    /// 前景色，为空时使用默认颜色。
+    pub fg_color: Option<Color>,
+    /// 背景色，为空时不绘制背景。
+    pub bg_color: Option<Color>,
+    /// 加强显示。
+    pub strong: bool,
+    /// 斜体。
+    pub italic: bool,
+    /// 下划线。
+    pub underline: bool,
+}
+
+impl LogStyle {
+    fn apply(&self, mut data: UserData) -> UserData {
+        if let Some(fg_color) = self.fg_color {
+            data = data.set_fg_color(fg_color);
+        }
+        data.set_bg_color(self.bg_color)
+            .set_strong(self.strong)
+            .set_italic(self.italic)
+            .set_underline(self.underline)
+    }
+}
+
+/// 每行一个JSON对象的日志。正文取自指定字段，可选择在正文前以暗淡样式显示其他字段，如时间戳；
+/// 可按字段取值设置正文的显示样式，并将字段取值作为数据段的类别，供`RichReviewer::set_filter`等按类别过滤。
+/// 无法解析为JSON对象的行按纯文本显示。
+///
+/// # Examples
+///
+/// ```
+/// use fltk::enums::Color;
+/// use fltkrs_richdisplay::log_file::{JsonLines, LogFormat, LogStyle};
+///
+/// let format = JsonLines::new("msg")
+///     .with_prefix_field("time")
+///     .with_category_field("channel")
+///     .map_style("level", "error", LogStyle { fg_color: Some(Color::Red), strong: true, ..Default::default() });
+/// let data = format.parse_line(r#"{"time":"12:00","level":"error","channel":"chat","msg":"disconnected"}"#);
+/// assert_eq!(data.len(), 2);
+/// assert_eq!(data[1].text, "disconnected\n");
+/// assert_eq!(data[1].fg_color, Color::Red);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JsonLines {
+    text_field: String,
+    prefix_fields: Vec<String>,
+    category_field: Option<String>,
+    styles: HashMap<String, HashMap<String, LogStyle>>,
+}
+
+impl JsonLines {
+    /// 构建JSON日志格式。
+    ///
+    /// # Arguments
+    ///
+    /// * `text_field`: 正文所在的字段名。
+    ///
+    /// returns: JsonLines
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::log_file::{JsonLines, LogFormat};
+    ///
+    /// let format = JsonLines::new("msg");
+    /// let data = format.parse_line(r#"{"msg":"hello"}"#);
+    /// assert_eq!(data[0].text, "hello\n");
+    /// ```
+    pub fn new(text_field: &str) -> Self {
+        Self { text_field: text_field.to_string(), ..Default::default() }
+    }
+
+    /// 添加在正文之前显示的字段，多个字段按添加顺序显示，缺少的字段被忽略。
+    pub fn with_prefix_field(mut self, field: &str) -> Self {
+        self.prefix_fields.push(field.to_string());
+        self
+    }
+
+    /// 设置类别字段，其取值记录在正文数据段互动属性的`category`中。
+    pub fn with_category_field(mut self, field: &str) -> Self {
+        self.category_field = Some(field.to_string());
+        self
+    }
+
+    /// 设置字段取值对应的正文样式。多个字段都有匹配的样式时，按字段名顺序使用第一个。
+    ///
+    /// # Arguments
+    ///
+    /// * `field`: 字段名。
+    /// * `value`: 字段取值。
+    /// * `style`: 正文样式。
+    ///
+    /// returns: JsonLines
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::log_file::{JsonLines, LogFormat, LogStyle};
+    ///
+    /// let format = JsonLines::new("msg").map_style("level", "warn", LogStyle { fg_color: Some(Color::Yellow), ..Default::default() });
+    /// let data = format.parse_line(r#"{"level":"warn","msg":"low memory"}"#);
+    /// assert_eq!(data[0].fg_color, Color::Yellow);
+    /// ```
+    pub fn map_style(mut self, field: &str, value: &str, style: LogStyle) -> Self {
+        self.styles.entry(field.to_string()).or_default().insert(value.to_string(), style);
+        self
+    }
+}
+
+/// 将JSON字段取值转换为显示文本，字符串不带引号。
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+impl LogFormat for JsonLines {
+    fn parse_line(&self, line: &str) -> Vec<UserData> {
+        let Ok(Value::Object(obj)) = serde_json::from_str::<Value>(line) else {
+            return PlainText.parse_line(line);
+        };
+        let mut data = vec![];
+        let prefix = self.prefix_fields.iter().filter_map(|f| obj.get(f).map(json_text)).collect::<Vec<String>>();
+        if !prefix.is_empty() {
+            data.push(UserData::new_text(format!("{} ", prefix.join(" "))).set_faint(true));
+        }
+
+        let text = obj.get(&self.text_field).map(json_text).unwrap_or_default();
+        let mut body = UserData::new_text(format!("{text}\n"));
+        let mut fields = self.styles.keys().collect::<Vec<&String>>();
+        fields.sort();
+        let style = fields.into_iter().find_map(|f| obj.get(f).and_then(|v| self.styles[f].get(&json_text(v))));
+        if let Some(style) = style {
+            body = style.apply(body);
+        }
+        if let Some(category) = self.category_field.as_ref().and_then(|f| obj.get(f)) {
+            body.action = Some(Action { category: Some(json_text(category)), ..Default::default() });
+        }
+        data.push(body);
+        data
+    }
+}
+
+/// 会话日志的写入格式。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogSinkFormat {
+    /// 纯文本，只写入文字和表格内容。
+    #[default]
+    PlainText,
+    /// 以SGR控制序列保留文字颜色和样式的文本，可由`AnsiText`打开浏览。
+    Ansi,
+    /// 每个数据段一行JSON对象，保留全部属性，可由`JsonLines::new("text")`打开浏览。
+    JsonLines,
+}
+
+/// 会话日志。数据段到达时先写入缓冲区，由主视图按照`LOG_FLUSH_INTERVAL`定时写入文件，避免每个数据段都在界面线程中写入磁盘；
+/// 程序异常退出时最多丢失最后一个间隔内的内容。替换或停止会话日志时，缓冲的内容会全部写入文件。
+/// 设置轮转后，文件超过指定大小时依次重命名为`文件名.1`、`文件名.2`等，超出保留数量的旧文件被删除。
+///
+/// # Examples
+///
+/// ```
+/// use fltkrs_richdisplay::log_file::{LogSink, LogSinkFormat};
+///
+/// let sink = LogSink::new(std::env::temp_dir().join("session.log"), LogSinkFormat::Ansi).with_rotation(1024 * 1024, 5);
+/// ```
+#[derive(Debug)]
+pub struct LogSink {
+    path: PathBuf,
+    format: LogSinkFormat,
+    /// 单个文件的最大字节数，为0时不轮转。
+    max_bytes: u64,
+    /// 轮转后保留的旧文件数量。
+    max_files: usize,
+    file: Option<BufWriter<File>>,
+    /// 当前文件已写入的字节数，包括尚在缓冲区中的内容。
+    written: u64,
+}
+
+impl LogSink {
+    /// 构建会话日志，文件在第一次写入时以追加方式打开。
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: 日志文件路径。
+    /// * `format`: 写入格式。
+    ///
+    /// returns: LogSink
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use fltkrs_richdisplay::log_file::{LogSink, LogSinkFormat};
+    ///
+    /// let sink = LogSink::new("session.jsonl", LogSinkFormat::JsonLines);
+    /// assert_eq!(sink.path(), Path::new("session.jsonl"));
+    /// ```
+    pub fn new(path: impl Into<PathBuf>, format: LogSinkFormat) -> Self {
+        Self { path: path.into(), format, max_bytes: 0, max_files: 0, file: None, written: 0 }
+    }
+
+    /// 设置日志轮转。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes`: 单个文件的最大字节数，为0时不轮转。
+    /// * `max_files`: 保留的旧文件数量，为0时超出大小的文件直接被删除。
+    ///
+    /// returns: LogSink
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::log_file::{LogSink, LogSinkFormat};
+    ///
+    /// // 每个文件最多1MB，保留最近的3个旧文件。
+    /// let sink = LogSink::new(std::env::temp_dir().join("session.log"), LogSinkFormat::PlainText).with_rotation(1024 * 1024, 3);
+    /// ```
+    pub fn with_rotation(mut self, max_bytes: u64, max_files: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self.max_files = max_files;
+        self
+    }
+
+    /// 日志文件路径。
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 按照写入格式将一个数据段写入缓冲区，没有可写入的内容时忽略。缓冲的内容需要调用`flush`写入文件。
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 数据段。
+    ///
+    /// returns: Result<(), Error>
+    pub(crate) fn write(&mut self, data: &UserData) -> io::Result<()> {
+        let Some(entry) = format_log_entry(data, self.format) else {
+            return Ok(());
+        };
+        if self.max_bytes > 0 && self.written > 0 && self.written + entry.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+                self.written = file.metadata()?.len();
+                self.file.insert(BufWriter::new(file))
+            }
+        };
+        file.write_all(entry.as_bytes())?;
+        self.written += entry.len() as u64;
+        Ok(())
+    }
+
+    /// 将缓冲的内容写入文件。
+    ///
+    /// returns: Result<(), Error>
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// 关闭当前文件并依次重命名旧文件，下次写入时重新创建日志文件。
+    fn rotate(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.file = None;
+        self.written = 0;
+        let rotated = |n: usize| {
+            let mut name = self.path.clone().into_os_string();
+            name.push(format!(".{}", n));
+            PathBuf::from(name)
+        };
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let oldest = rotated(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for n in (1..self.max_files).rev() {
+            let from = rotated(n);
+            if from.exists() {
+                fs::rename(&from, rotated(n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(1))
+    }
+}
+
+/// 将数据段转换为日志内容。纯文本和ANSI格式只写入文字和表格，JSON格式写入除图片数据外的全部属性。
+///
+/// # Arguments
+///
+/// * `data`: 数据段。
+/// * `format`: 写入格式。
+///
+/// returns: Option<String>
+pub(crate) fn format_log_entry(data: &UserData, format: LogSinkFormat) -> Option<String> {
+    let text = match data.data_type {
+        DataType::Text => data.text.clone(),
+        DataType::Table => data.table.as_ref()?.row_texts().concat(),
+        _ if format == LogSinkFormat::JsonLines => String::new(),
+        _ => return None,
+    };
+    match format {
+        LogSinkFormat::PlainText => Some(text),
+        LogSinkFormat::Ansi => Some(ansi_text(data, &text)),
+        LogSinkFormat::JsonLines => serde_json::to_string(data).ok().map(|line| line + "\n"),
+    }
+}
+
+/// 以SGR控制序列包裹文字，每一行单独设置样式，保证按行分页浏览时样式不丢失。
+fn ansi_text(data: &UserData, text: &str) -> String {
+    let mut params: Vec<String> = vec![];
+    let flags = [(data.strong, 1), (data.faint, 2), (data.italic, 3), (data.underline, 4), (data.blink, 5), (data.reverse, 7), (data.conceal, 8), (data.strike_through, 9)];
+    params.extend(flags.iter().filter(|(on, _)| *on).map(|(_, code)| code.to_string()));
+    match data.fg_color_index {
+        1..=8 => params.push((29 + data.fg_color_index as u16).to_string()),
+        9..=16 => params.push((81 + data.fg_color_index as u16).to_string()),
+        _ if data.custom_font_color => {
+            let (r, g, b) = data.fg_color.to_rgb();
+            params.push(format!("38;2;{};{};{}", r, g, b));
+        }
+        _ => {}
+    }
+    match (data.bg_color_index, data.bg_color) {
+        (1..=8, _) => params.push((39 + data.bg_color_index as u16).to_string()),
+        (9..=16, _) => params.push((91 + data.bg_color_index as u16).to_string()),
+        (_, Some(bg_color)) => {
+            let (r, g, b) = bg_color.to_rgb();
+            params.push(format!("48;2;{};{};{}", r, g, b));
+        }
+        _ => {}
+    }
+    if params.is_empty() {
+        return text.to_string();
+    }
+    let sgr = format!("\x1b[{}m", params.join(";"));
+    text.split_inclusive('\n').map(|line| match line.strip_suffix('\n') {
+        Some(line) if !line.is_empty() => format!("{}{}\x1b[0m\n", sgr, line),
+        Some(_) => "\n".to_string(),
+        None => format!("{}{}\x1b[0m", sgr, line),
+    }).collect()
+}
+
+/// 行起始位置索引。
+#[derive(Debug, Default)]
+struct LineIndex {
+    /// 已找到的各行起始位置。
+    starts: Vec<usize>,
+    /// 是否已索引到文件末尾。
+    done: bool,
+}
+
+/// 以内存映射方式打开的日志文件。
+pub(crate) struct LogFile {
+    mmap: Arc<Mmap>,
+    index: Arc<RwLock<LineIndex>>,
+    format: Box<dyn LogFormat>,
+    /// 通知后台索引线程停止，日志文件关闭时设置。
+    cancel: Arc<AtomicBool>,
+}
+
+impl Drop for LogFile {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Debug for LogFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let index = self.index.read();
+        write!(f, "LogFile size: {}, indexed lines: {}, done: {}", self.mmap.len(), index.starts.len(), index.done)
+    }
+}
+
+impl LogFile {
+    /// 打开日志文件，同步索引开头的部分行后，在后台线程中继续索引其余的行。
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: 文件路径。
+    /// * `format`: 日志格式。
+    ///
+    /// returns: Result<LogFile, Error>
+    pub fn open<P: AsRef<Path>>(path: P, format: Box<dyn LogFormat>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // 映射期间文件被截断会导致访问越界，日志文件在浏览期间应只追加或保持不变。
+        let mmap = Arc::new(unsafe { Mmap::map(&file)? });
+        let mut index = LineIndex::default();
+        if !mmap.is_empty() {
+            index.starts.push(0);
+        }
+        let (mut starts, next) = scan_line_starts(&mmap, 0, INITIAL_INDEX_LINES);
+        index.starts.append(&mut starts);
+        index.done = next >= mmap.len();
+        let index = Arc::new(RwLock::new(index));
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        if !index.read().done {
+            let mmap_rc = mmap.clone();
+            let index_rc = index.clone();
+            let cancel_rc = cancel.clone();
+            thread::spawn(move || {
+                let mut from = next;
+                while from < mmap_rc.len() {
+                    if cancel_rc.load(Ordering::Relaxed) {
+                        // 日志文件已关闭，放弃其余的索引工作。
+                        debug!("日志文件已关闭，停止索引");
+                        return;
+                    }
+                    let (mut starts, next) = scan_line_starts(&mmap_rc, from, INDEX_BATCH_LINES);
+                    index_rc.write().starts.append(&mut starts);
+                    from = next;
+                }
+                let mut index = index_rc.write();
+                index.done = true;
+                debug!("日志文件索引完成，共{}行", index.starts.len());
+            });
+        }
+
+        Ok(Self { mmap, index, format, cancel })
+    }
+
+    /// 当前可以读取的完整行数。后台索引未完成时，最后一个已知行可能尚未结束，不计算在内。
+    pub fn available_lines(&self) -> usize {
+        let index = self.index.read();
+        if index.done {
+            index.starts.len()
+        } else {
+            index.starts.len().saturating_sub(1)
+        }
+    }
+
+    /// 读取指定行的文本，不包含行尾的换行符。
+    fn line(&self, line_idx: usize) -> Option<String> {
+        let (start, end) = {
+            let index = self.index.read();
+            let start = *index.starts.get(line_idx)?;
+            (start, index.starts.get(line_idx + 1).copied().unwrap_or(self.mmap.len()))
+        };
+        let mut bytes = &self.mmap[start..end];
+        if let Some(stripped) = bytes.strip_suffix(b"\n") {
+            bytes = stripped;
+        }
+        if let Some(stripped) = bytes.strip_suffix(b"\r") {
+            bytes = stripped;
+        }
+        Some(String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// 读取从指定行开始的若干行，转换为数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `from`: 起始行号，从0开始。
+    /// * `count`: 行数。
+    ///
+    /// returns: Vec<UserData, Global>
+    pub fn lines(&self, from: usize, count: usize) -> Vec<UserData> {
+        let to = (from + count).min(self.available_lines());
+        let mut page = vec![];
+        for line_idx in from..to {
+            if let Some(line) = self.line(line_idx) {
+                for (seg, mut ud) in self.format.parse_line(&line).into_iter().take(1 << SEGMENT_BITS).enumerate() {
+                    ud.id = segment_id(line_idx, seg);
+                    page.push(ud);
+                }
+            }
+        }
+        page
+    }
+
+    /// 读取分页请求对应的页数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `opt`: 分页请求，附带当前页首尾数据段的ID，或跳转目标数据段的ID。
+    /// * `page_size`: 每页的行数。
+    ///
+    /// returns: Vec<UserData, Global>
+    pub fn page(&self, opt: &PageOptions, page_size: usize) -> Vec<UserData> {
+        match *opt {
+            PageOptions::NextPage(last_id) => {
+                let from = line_of(last_id).map(|line_idx| line_idx + 1).unwrap_or(0);
+                self.lines(from, page_size)
+            }
+            PageOptions::PrevPage(first_id) => {
+                match line_of(first_id) {
+                    Some(to) if to > 0 => {
+                        let from = to.saturating_sub(page_size);
+                        self.lines(from, to - from)
+                    }
+                    _ => vec![],
+                }
+            }
+            PageOptions::At(target_id) => {
+                // 以目标行为中心载入一页。
+                let from = line_of(target_id).map(|line_idx| line_idx.saturating_sub(page_size / 2)).unwrap_or(0);
+                self.lines(from, page_size)
+            }
+        }
+    }
+
+    /// 从查找起点所在行的下一行或上一行开始逐行查找目标字符串，只查找已索引的行。
+    ///
+    /// # Arguments
+    ///
+    /// * `request`: 后台查找请求。
+    ///
+    /// returns: Option<i64> 第一个包含目标的数据段ID，反向查找时为该行中最后一个包含目标的数据段。
+    pub fn search(&self, request: &SearchRequest) -> Option<i64> {
+        if request.pattern.is_empty() {
+            return None;
+        }
+        let from = line_of(request.from_id);
+        let lines: Box<dyn Iterator<Item = usize>> = if request.forward {
+            Box::new(from.map_or(0, |line_idx| line_idx + 1)..self.available_lines())
+        } else {
+            Box::new((0..from.unwrap_or(0)).rev())
+        };
+        for line_idx in lines {
+            let Some(line) = self.line(line_idx) else {
+                continue;
+            };
+            if !line.contains(&request.pattern) {
+                continue;
+            }
+            let segments = self.format.parse_line(&line);
+            let mut matched = segments.iter().enumerate().take(1 << SEGMENT_BITS).filter(|(_, ud)| ud.text.contains(&request.pattern)).map(|(seg, _)| seg);
+            let seg = if request.forward { matched.next() } else { matched.last() };
+            if let Some(seg) = seg {
+                return Some(segment_id(line_idx, seg));
+            }
+        }
+        None
+    }
+}
+
+/// 从指定位置开始查找后续行的起始位置。
+///
+/// # Arguments
+///
+/// * `data`: 文件内容。
+/// * `from`: 开始查找的位置。
+/// * `max_lines`: 最多查找的行数。
+///
+/// returns: (Vec<usize, Global>, usize) 找到的行起始位置，以及下次继续查找的位置，等于内容长度时表示已查找完毕。
+pub(crate) fn scan_line_starts(data: &[u8], from: usize, max_lines: usize) -> (Vec<usize>, usize) {
+    let mut starts = vec![];
+    let mut pos = from;
+    while starts.len() < max_lines {
+        match data[pos..].iter().position(|b| *b == b'\n') {
+            Some(offset) => {
+                pos += offset + 1;
+                if pos < data.len() {
+                    starts.push(pos);
+                } else {
+                    break;
+                }
+            }
+            None => {
+                pos = data.len();
+                break;
+            }
+        }
+    }
+    (starts, pos)
+}
+
+/// 由行号和行内数据段序号生成数据段ID，ID从1开始。
+pub(crate) fn segment_id(line_idx: usize, seg: usize) -> i64 {
+    (((line_idx + 1) as i64) << SEGMENT_BITS) | seg as i64
+}
+
+/// 从数据段ID中取出行号，ID不是由日志行生成时返回`None`。
+pub(crate) fn line_of(id: i64) -> Option<usize> {
+    let line = id >> SEGMENT_BITS;
+    if line > 0 { Some((line - 1) as usize) } else { None }
+}
@@ -0,0 +1,321 @@
+//! Markdown文本的输入适配。`to_user_data`将Markdown文本转换为带有样式的数据段，应用程序无需自行编写转换逻辑即可显示帮助说明等富文本内容。
+//! 支持的语法有：
+//!
+//! * 标题：以`#`至`######`开头的行。
+//! * 粗体和斜体：`**粗体**`、`__粗体__`和`*斜体*`。
+//! * 代码：以反引号包围的行内代码，以及以三个反引号开始和结束的代码块。
+//! * 链接：`[文字](地址)`，转换为可点击的数据段，默认动作的指令为链接地址。
+//! * 列表：以`-`、`*`、`+`或`数字.`开头的行，每两个前导空格表示一级嵌套，最多嵌套8级。
+//!
+//! 连续的普通文本行合并为一个段落，空行用于分隔段落。
+
+use fltk::enums::{Color, Font};
+use crate::{Action, ActionItem, bold_font, DEFAULT_FONT_SIZE, italic_font, UserData};
+
+/// Markdown转换样式。
+#[derive(Debug, Clone)]
+pub struct MarkdownStyle {
+    /// 正文字号，用于粗体、斜体和代码。普通正文使用组件的默认字体和字号。
+    pub text_size: i32,
+    /// 正文字体，粗体和标题使用其对应的粗体字体。
+    pub text_font: Font,
+    /// 一级至六级标题的字号。
+    pub heading_sizes: [i32; 6],
+    /// 标题颜色，为空时使用组件的默认颜色。
+    pub heading_color: Option<Color>,
+    /// 代码字体。
+    pub code_font: Font,
+    /// 代码文字颜色，为空时使用组件的默认颜色。
+    pub code_color: Option<Color>,
+    /// 代码背景色。
+    pub code_bg_color: Option<Color>,
+    /// 链接颜色，为空时使用组件的默认颜色。
+    pub link_color: Option<Color>,
+    /// 链接互动行为的类型，即`Action::kind`。
+    pub link_kind: u8,
+    /// 无序列表的项目符号。
+    pub bullet: String,
+}
+
+impl Default for MarkdownStyle {
+    fn default() -> Self {
+        Self {
+            text_size: DEFAULT_FONT_SIZE,
+            text_font: Font::Helvetica,
+            heading_sizes: [DEFAULT_FONT_SIZE + 12, DEFAULT_FONT_SIZE + 8, DEFAULT_FONT_SIZE + 4, DEFAULT_FONT_SIZE + 2, DEFAULT_FONT_SIZE, DEFAULT_FONT_SIZE],
+            heading_color: None,
+            code_font: Font::Courier,
+            code_color: Some(Color::from_rgb(230, 180, 80)),
+            code_bg_color: Some(Color::from_rgb(48, 48, 48)),
+            link_color: Some(Color::from_rgb(80, 160, 255)),
+            link_kind: 0,
+            bullet: "• ".to_string(),
+        }
+    }
+}
+
+/// 列表的最大嵌套层级，更深的列表项按此层级缩进。
+const MAX_LIST_DEPTH: usize = 8;
+
+/// 文本块的基础样式，行内样式在此基础上叠加。
+#[derive(Debug, Clone, Copy, Default)]
+struct BlockStyle {
+    /// 标题级别，从1开始。
+    heading: Option<usize>,
+    /// 缩进层级。
+    indent: u8,
+}
+
+/// 将Markdown文本转换为数据段。
+///
+/// # Arguments
+///
+/// * `markdown`: Markdown文本。
+/// * `style`: 转换样式。
+///
+/// returns: Vec<UserData, Global> 转换后的数据段，每个文本块的最后一个数据段以换行符结尾。
+///
+/// # Examples
+///
+/// ```
+/// use fltkrs_richdisplay::markdown::{MarkdownStyle, to_user_data};
+///
+/// let data = to_user_data("# 帮助\n输入 `look` 查看[周围环境](look)。", &MarkdownStyle::default());
+/// assert_eq!(data[0].text, "帮助\n");
+/// assert!(data.iter().any(|d| d.clickable && d.text == "周围环境"));
+/// ```
+pub fn to_user_data(markdown: &str, style: &MarkdownStyle) -> Vec<UserData> {
+    let mut result: Vec<UserData> = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut in_code_block = false;
+    let mut separate = false;
+
+    for line in markdown.lines() {
+        if line.trim_start().starts_with("```") {
+            flush_paragraph(&mut paragraph, style, &mut result);
+            if !in_code_block {
+                start_block(&mut separate, &mut result);
+            }
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            result.push(code_data(&format!("{}\n", line), BlockStyle::default(), style));
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, style, &mut result);
+            separate = true;
+        } else if let Some((level, content)) = heading(trimmed) {
+            flush_paragraph(&mut paragraph, style, &mut result);
+            start_block(&mut separate, &mut result);
+            let block = BlockStyle { heading: Some(level), indent: 0 };
+            let from = result.len();
+            inline_data(content, block, style, &mut result);
+            end_line(from, block, &mut result);
+        } else if let Some((depth, ordinal, content)) = list_item(line) {
+            flush_paragraph(&mut paragraph, style, &mut result);
+            start_block(&mut separate, &mut result);
+            let block = BlockStyle { heading: None, indent: (depth.min(MAX_LIST_DEPTH) as u8).saturating_add(1) };
+            let from = result.len();
+            let prefix = match ordinal {
+                Some(n) => format!("{}. ", n),
+                None => style.bullet.clone(),
+            };
+            result.push(text_data(&prefix, false, false, block, style));
+            inline_data(content, block, style, &mut result);
+            end_line(from, block, &mut result);
+        } else {
+            if paragraph.is_empty() {
+                start_block(&mut separate, &mut result);
+            }
+            paragraph.push(trimmed);
+        }
+    }
+    flush_paragraph(&mut paragraph, style, &mut result);
+    result
+}
+
+/// 开始新的文本块，前面有空行时先插入一个空行。
+fn start_block(separate: &mut bool, result: &mut Vec<UserData>) {
+    if *separate && !result.is_empty() {
+        result.push(UserData::new_text("\n".to_string()));
+    }
+    *separate = false;
+}
+
+/// 将已收集的段落文本行合并转换为数据段。
+fn flush_paragraph(paragraph: &mut Vec<&str>, style: &MarkdownStyle, result: &mut Vec<UserData>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let from = result.len();
+    inline_data(&paragraph.join(" "), BlockStyle::default(), style, result);
+    end_line(from, BlockStyle::default(), result);
+    paragraph.clear();
+}
+
+/// 结束当前文本块。最后一个数据段为普通文本时直接追加换行符，否则追加一个换行数据段，避免代码背景色和链接下划线延伸到行尾。
+fn end_line(from: usize, block: BlockStyle, result: &mut Vec<UserData>) {
+    if result.len() > from {
+        if let Some(last) = result.last_mut() {
+            if last.action.is_none() && last.bg_color.is_none() {
+                last.text.push('\n');
+                return;
+            }
+        }
+    }
+    result.push(UserData::new_text("\n".to_string()).set_indent(block.indent, None));
+}
+
+/// 解析标题行，返回标题级别和标题内容。
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() || rest.starts_with(' ') {
+        Some((level, rest.trim()))
+    } else {
+        None
+    }
+}
+
+/// 解析列表项，返回嵌套深度、有序列表的序号和列表项内容。
+fn list_item(line: &str) -> Option<(usize, Option<&str>, &str)> {
+    let rest = line.trim_start_matches(' ');
+    let depth = (line.len() - rest.len()) / 2;
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(content) = rest.strip_prefix(marker) {
+            return Some((depth, None, content.trim()));
+        }
+    }
+    let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(content) = rest[digits..].strip_prefix(". ") {
+            return Some((depth, Some(&rest[..digits]), content.trim()));
+        }
+    }
+    None
+}
+
+/// 解析行内的粗体、斜体、代码和链接，转换为数据段。
+fn inline_data(text: &str, block: BlockStyle, style: &MarkdownStyle, result: &mut Vec<UserData>) {
+    let chars: Vec<char> = text.chars().collect();
+    let (mut bold, mut italic) = (false, false);
+    let mut run = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if i + 1 < chars.len() => {
+                run.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            '*' | '_' if chars.get(i + 1) == Some(&c) => {
+                push_run(&mut run, bold, italic, block, style, result);
+                bold = !bold;
+                i += 2;
+                continue;
+            }
+            '*' => {
+                push_run(&mut run, bold, italic, block, style, result);
+                italic = !italic;
+                i += 1;
+                continue;
+            }
+            '`' => {
+                if let Some(end) = chars[i + 1..].iter().position(|c| *c == '`') {
+                    push_run(&mut run, bold, italic, block, style, result);
+                    let code: String = chars[i + 1..i + 1 + end].iter().collect();
+                    result.push(code_data(&code, block, style));
+                    i += end + 2;
+                    continue;
+                }
+            }
+            '[' => {
+                if let Some((label, url, next)) = link(&chars, i) {
+                    push_run(&mut run, bold, italic, block, style, result);
+                    result.push(link_data(&label, &url, bold, italic, block, style));
+                    i = next;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        run.push(c);
+        i += 1;
+    }
+    push_run(&mut run, bold, italic, block, style, result);
+}
+
+/// 解析从`start`位置开始的链接，返回链接文字、链接地址和链接之后的位置。
+fn link(chars: &[char], start: usize) -> Option<(String, String, usize)> {
+    let close = start + 1 + chars[start + 1..].iter().position(|c| *c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 2 + chars[close + 2..].iter().position(|c| *c == ')')?;
+    let label: String = chars[start + 1..close].iter().collect();
+    let url: String = chars[close + 2..end].iter().collect();
+    Some((label, url.trim().to_string(), end + 1))
+}
+
+/// 将已收集的文字转换为数据段并清空。
+fn push_run(run: &mut String, bold: bool, italic: bool, block: BlockStyle, style: &MarkdownStyle, result: &mut Vec<UserData>) {
+    if !run.is_empty() {
+        result.push(text_data(run, bold, italic, block, style));
+        run.clear();
+    }
+}
+
+/// 生成普通文字数据段。
+fn text_data(text: &str, bold: bool, italic: bool, block: BlockStyle, style: &MarkdownStyle) -> UserData {
+    let mut data = UserData::new_text(text.to_string()).set_strong(bold).set_indent(block.indent, None);
+    let size = block.heading.map(|level| style.heading_sizes[level - 1]);
+    if bold || size.is_some() {
+        // 标题总是使用粗体，自定义字体后斜体需要通过字体体现。
+        let font = bold_font(style.text_font);
+        let font = if italic { italic_font(font) } else { font };
+        data = data.set_font_and_size(font, size.unwrap_or(style.text_size));
+    } else if italic {
+        data = data.set_italic(true);
+    }
+    if let (Some(_), Some(color)) = (block.heading, style.heading_color) {
+        data = data.set_fg_color(color);
+    }
+    data
+}
+
+/// 生成代码数据段。
+fn code_data(code: &str, block: BlockStyle, style: &MarkdownStyle) -> UserData {
+    let size = block.heading.map(|level| style.heading_sizes[level - 1]).unwrap_or(style.text_size);
+    let mut data = UserData::new_text(code.to_string())
+        .set_font_and_size(style.code_font, size)
+        .set_bg_color(style.code_bg_color)
+        .set_indent(block.indent, None);
+    if let Some(color) = style.code_color {
+        data = data.set_fg_color(color);
+    }
+    data
+}
+
+/// 生成链接数据段，默认动作的指令为链接地址。
+fn link_data(label: &str, url: &str, bold: bool, italic: bool, block: BlockStyle, style: &MarkdownStyle) -> UserData {
+    let action = Action {
+        title: url.to_string(),
+        kind: style.link_kind,
+        items: vec![ActionItem::new(label, url)],
+        default_item: Some(0),
+        ..Default::default()
+    };
+    let mut data = text_data(label, bold, italic, block, style).set_action(action);
+    if let Some(color) = style.link_color {
+        data = data.set_fg_color(color);
+    }
+    data
+}
@@ -0,0 +1,255 @@
+//! MXP(MUD eXtension Protocol)标签的输入适配。`to_user_data`将常用的MXP标签转换为数据段，客户端无需各自实现协议到组件的转换：
+//!
+//! * `<send>`：可点击的命令。`href`中以`|`分隔多个命令时弹出命令菜单，`hint`中以`|`分隔的提示依次作为菜单项的描述，
+//!   提示比命令多一个时第一个提示作为鼠标悬停提示。没有`href`时以标签内的文字作为命令，命令中的`&text;`会被替换为标签内的文字。
+//!   带有`prompt`参数时互动类型为`MxpStyle::prompt_kind`，表示命令应填入输入框而不是直接发送。
+//! * `<a href="...">`：可点击的链接，默认动作的指令为链接地址。
+//! * `<color fore="..." back="...">`或`<c>`：前景色和背景色，也可以不写参数名按顺序给出颜色。
+//! * `<b>`、`<i>`、`<u>`及其全称形式：粗体、斜体和下划线。
+//! * `<image fname="..." url="..." w="..." h="...">`：图片，先以占位图显示，图片地址交由`ImageLoader`异步加载，
+//!   右键菜单提供刷新、复制地址和另存为。
+//! * `<br>`：换行。
+//!
+//! 其他标签被忽略，标签内的文字按照当前样式显示。`ESC[nz`等行模式控制序列应由上层应用在调用前处理。
+
+use fltk::enums::{Color, Font};
+use crate::{Action, ActionItem, DataType, DEFAULT_FONT_SIZE, MXP_IMAGE_CONTEXT_MENU_COPY_URL, MXP_IMAGE_CONTEXT_MENU_REFRESH, MXP_IMAGE_CONTEXT_MENU_SAVE_AS, UserData};
+use crate::html::{attr, decode_entities, ImageLoader, parse_color, parse_tag, placeholder_image, size_attr, styled_text};
+
+/// MXP转换样式。
+#[derive(Debug, Clone)]
+pub struct MxpStyle {
+    /// 正文字体，粗体使用其对应的粗体字体。普通文字使用组件的默认字体和字号。
+    pub text_font: Font,
+    /// 粗体文字的字号。
+    pub text_size: i32,
+    /// 命令和链接的颜色，为空时使用组件的默认颜色。`<color>`标签设置的颜色优先。
+    pub link_color: Option<Color>,
+    /// `<send>`命令互动行为的类型，即`Action::kind`。
+    pub send_kind: u8,
+    /// 带有`prompt`参数的`<send>`命令互动行为的类型。
+    pub prompt_kind: u8,
+    /// `<a>`链接互动行为的类型。
+    pub link_kind: u8,
+    /// `<image>`标签未指定宽度时使用的宽度。
+    pub image_width: i32,
+    /// `<image>`标签未指定高度时使用的高度。
+    pub image_height: i32,
+    /// 图片加载完成前显示在占位图上的文字。
+    pub image_placeholder: String,
+}
+
+impl Default for MxpStyle {
+    fn default() -> Self {
+        Self {
+            text_font: Font::Helvetica,
+            text_size: DEFAULT_FONT_SIZE,
+            link_color: Some(Color::from_rgb(80, 160, 255)),
+            send_kind: 0,
+            prompt_kind: 1,
+            link_kind: 2,
+            image_width: 100,
+            image_height: 100,
+            image_placeholder: "loading...".to_string(),
+        }
+    }
+}
+
+/// 可点击的标签。
+#[derive(Debug, Clone)]
+enum LinkTag {
+    Send { href: Option<String>, hint: Option<String>, prompt: bool },
+    Url(String),
+}
+
+/// 转换过程中的样式状态。
+#[derive(Debug, Default)]
+struct MxpState {
+    bold: usize,
+    italic: usize,
+    underline: usize,
+    /// `<color>`标签的前景色和背景色栈。
+    colors: Vec<(Option<Color>, Option<Color>)>,
+    /// 当前所在的可点击标签，以及该标签内第一个数据段的位置。
+    link: Option<(LinkTag, usize)>,
+}
+
+impl MxpState {
+    fn fg_color(&self) -> Option<Color> {
+        self.colors.iter().rev().find_map(|(fg, _)| *fg)
+    }
+
+    fn bg_color(&self) -> Option<Color> {
+        self.colors.iter().rev().find_map(|(_, bg)| *bg)
+    }
+}
+
+/// 将带有MXP标签的文本转换为数据段。
+///
+/// # Arguments
+///
+/// * `text`: 带有MXP标签的文本。
+/// * `style`: 转换样式。
+/// * `loader`: 图片加载器，为空时图片只显示占位图。
+///
+/// returns: Vec<UserData, Global>
+///
+/// # Examples
+///
+/// ```
+/// use fltkrs_richdisplay::mxp::{MxpStyle, to_user_data};
+///
+/// let data = to_user_data("你看到一把<send href=\"get sword|look sword\" hint=\"长剑|拿起|查看\">长剑</send>。\n", &MxpStyle::default(), None);
+/// let action = data[1].action.as_ref().unwrap();
+/// assert_eq!(action.title, "长剑");
+/// assert_eq!(action.items[1].cmd, "look sword");
+/// ```
+pub fn to_user_data(text: &str, style: &MxpStyle, loader: Option<&dyn ImageLoader>) -> Vec<UserData> {
+    let mut result: Vec<UserData> = vec![];
+    let mut state = MxpState::default();
+    let mut run = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        run.push_str(&decode_entities(&rest[..start]));
+        let (name, closing, attrs) = parse_tag(&rest[start + 1..start + len]);
+        rest = &rest[start + len + 1..];
+
+        if name == "br" {
+            run.push('\n');
+            continue;
+        }
+        push_run(&mut run, &state, style, &mut result);
+        match (name.as_str(), closing) {
+            ("b" | "bold" | "strong", false) => state.bold += 1,
+            ("b" | "bold" | "strong", true) => state.bold = state.bold.saturating_sub(1),
+            ("i" | "italic" | "em", false) => state.italic += 1,
+            ("i" | "italic" | "em", true) => state.italic = state.italic.saturating_sub(1),
+            ("u" | "underline", false) => state.underline += 1,
+            ("u" | "underline", true) => state.underline = state.underline.saturating_sub(1),
+            ("color" | "c", false) => {
+                // 没有参数名的参数依次作为前景色和背景色。
+                let mut positional = attrs.iter().filter(|(n, v)| n.is_empty() || v.is_empty()).map(|(n, v)| if n.is_empty() { v } else { n });
+                let fore = attr(&attrs, "fore").or_else(|| positional.next()).and_then(|c| parse_color(c));
+                let back = attr(&attrs, "back").or_else(|| positional.next()).and_then(|c| parse_color(c));
+                state.colors.push((fore, back));
+            }
+            ("color" | "c", true) => {
+                state.colors.pop();
+            }
+            ("send", false) => {
+                let href = attr(&attrs, "href").or_else(|| attrs.iter().find(|(n, _)| n.is_empty()).map(|(_, v)| v)).cloned();
+                let hint = attr(&attrs, "hint").cloned();
+                let prompt = attrs.iter().any(|(n, v)| n.eq_ignore_ascii_case("prompt") && v.is_empty());
+                state.link = Some((LinkTag::Send { href, hint, prompt }, result.len()));
+            }
+            ("a", false) => {
+                if let Some(href) = attr(&attrs, "href") {
+                    state.link = Some((LinkTag::Url(href.clone()), result.len()));
+                }
+            }
+            ("send" | "a", true) => {
+                if let Some((tag, from)) = state.link.take() {
+                    close_link(tag, &mut result[from..], style);
+                }
+            }
+            ("image", false) => {
+                if let Some(data) = image_data(&attrs, style, loader) {
+                    result.push(data);
+                }
+            }
+            _ => {}
+        }
+    }
+    run.push_str(&decode_entities(rest));
+    push_run(&mut run, &state, style, &mut result);
+    if let Some((tag, from)) = state.link.take() {
+        // 未闭合的可点击标签延续到文本末尾。
+        close_link(tag, &mut result[from..], style);
+    }
+    result
+}
+
+/// 将已收集的文字按照当前样式转换为数据段并清空。可点击标签的互动行为在标签结束时统一设置。
+fn push_run(run: &mut String, state: &MxpState, style: &MxpStyle, result: &mut Vec<UserData>) {
+    if run.is_empty() {
+        return;
+    }
+    let mut data = styled_text(run, state.bold > 0, state.italic > 0, state.underline > 0, style.text_font, style.text_size);
+    if let (Some(_), Some(color)) = (&state.link, style.link_color) {
+        data = data.set_fg_color(color);
+    }
+    if let Some(color) = state.fg_color() {
+        data = data.set_fg_color(color);
+    }
+    if let Some(color) = state.bg_color() {
+        data = data.set_bg_color(Some(color));
+    }
+    result.push(data);
+    run.clear();
+}
+
+/// 可点击标签结束时，根据标签内的文字生成互动行为，设置到标签内的所有数据段上。
+fn close_link(tag: LinkTag, data: &mut [UserData], style: &MxpStyle) {
+    let text: String = data.iter().filter(|d| d.data_type == DataType::Text).map(|d| d.text.as_str()).collect();
+    let action = match tag {
+        LinkTag::Send { href, hint, prompt } => send_action(href.as_deref(), hint.as_deref(), prompt, &text, style),
+        LinkTag::Url(href) => Action {
+            title: href.clone(),
+            kind: style.link_kind,
+            items: vec![ActionItem::new(&text, &href)],
+            default_item: Some(0),
+            ..Default::default()
+        },
+    };
+    for d in data.iter_mut() {
+        d.change_action(Some(action.clone()));
+    }
+}
+
+/// 生成`<send>`命令的互动行为。
+///
+/// # Arguments
+///
+/// * `href`: 以`|`分隔的命令，为空时以标签内的文字作为命令。
+/// * `hint`: 以`|`分隔的提示。
+/// * `prompt`: 命令是否应填入输入框。
+/// * `text`: 标签内的文字。
+/// * `style`: 转换样式。
+///
+/// returns: Action
+fn send_action(href: Option<&str>, hint: Option<&str>, prompt: bool, text: &str, style: &MxpStyle) -> Action {
+    let commands: Vec<String> = href.unwrap_or(text).split('|').map(|cmd| cmd.replace("&text;", text)).collect();
+    let mut hints: Vec<&str> = hint.map(|hint| hint.split('|').collect()).unwrap_or_default();
+    let title = if hints.len() > commands.len() {
+        hints.remove(0).to_string()
+    } else {
+        hints.first().map(|hint| hint.to_string()).unwrap_or_else(|| commands[0].clone())
+    };
+    let items = commands.iter().enumerate().map(|(i, cmd)| ActionItem::new(hints.get(i).copied().unwrap_or(cmd), cmd)).collect();
+    Action {
+        title,
+        kind: if prompt { style.prompt_kind } else { style.send_kind },
+        items,
+        default_item: Some(0),
+        ..Default::default()
+    }
+}
+
+/// 根据`<image>`标签生成占位图片数据段，图片地址由`url`和`fname`拼接而成。没有图片地址时返回空。
+fn image_data(attrs: &[(String, String)], style: &MxpStyle, loader: Option<&dyn ImageLoader>) -> Option<UserData> {
+    let fname = attr(attrs, "fname").or_else(|| attrs.iter().find(|(n, _)| n.is_empty()).map(|(_, v)| v));
+    let src = format!("{}{}", attr(attrs, "url").map_or("", String::as_str), fname.map_or("", String::as_str));
+    if src.is_empty() {
+        return None;
+    }
+    let (width, height) = (size_attr(attrs, "w", style.image_width), size_attr(attrs, "h", style.image_height));
+    let mut action = Action::default();
+    action.items.push(ActionItem::new("刷新", MXP_IMAGE_CONTEXT_MENU_REFRESH));
+    action.items.push(ActionItem::new("复制地址", MXP_IMAGE_CONTEXT_MENU_COPY_URL));
+    action.items.push(ActionItem::new("另存为", MXP_IMAGE_CONTEXT_MENU_SAVE_AS));
+    Some(placeholder_image(&src, &src, width, height, &style.image_placeholder, loader).set_action(action))
+}
@@ -0,0 +1,100 @@
+//! 扩展插件接口。第三方可实现`RichDisplayPlugin`特征，通过`RichText::register_plugin`注册到组件上，
+//! 在不修改组件源码的情况下实现触发器、小地图、日志记录等附加功能。
+//!
+//! 插件只能通过只读的`PanelView`了解主视图的状态，不能直接访问组件内部的数据缓存。
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use fltk::enums::Event;
+use parking_lot::RwLock;
+use crate::{ShapeData, UserData};
+
+/// 主视图状态的只读视图，在每次调用插件钩子时生成。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PanelView {
+    /// 主视图左上角在窗口中的横坐标。
+    pub x: i32,
+    /// 主视图左上角在窗口中的纵坐标。
+    pub y: i32,
+    /// 主视图宽度。
+    pub w: i32,
+    /// 主视图高度。
+    pub h: i32,
+    /// 当前数据内容超出主视图的高度，即最底部可见位置距内容顶部的滚动距离。
+    pub scroll_y: i32,
+    /// 当前缓存中的数据段数量。
+    pub data_len: usize,
+    /// 按照默认字体计算出的可见行数。
+    pub rows: usize,
+    /// 按照默认字体计算出的单行列数。
+    pub cols: usize,
+}
+
+/// 组件扩展插件。所有钩子均有空的默认实现，插件只需实现关心的部分。
+/// 钩子在`fltk`主线程中调用，不应执行耗时操作。调用钩子时不持有插件列表的锁，钩子中可以注册或移除插件，
+/// 变更从下一次调用钩子时生效。
+pub trait RichDisplayPlugin: Send + Sync {
+    /// 插件名称，用于日志和调试。
+    fn name(&self) -> &str;
+
+    /// 新数据被添加到主视图之前调用，被重复检测抑制的数据不会触发该钩子。
+    ///
+    /// # Arguments
+    ///
+    /// * `data`: 新数据。
+    /// * `view`: 主视图状态。
+    ///
+    /// returns: ()
+    fn on_append(&mut self, _data: &UserData, _view: &PanelView) {}
+
+    /// 主视图绘制完成后调用，插件可使用`fltk::draw`中的函数在主视图上叠加绘制内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `view`: 主视图状态。
+    ///
+    /// returns: ()
+    fn on_draw_overlay(&mut self, _view: &PanelView) {}
+
+    /// 主视图收到事件时调用，先于组件自身的事件处理逻辑执行。
+    ///
+    /// # Arguments
+    ///
+    /// * `event`: 事件类型，鼠标位置等信息可通过`fltk::app`中的函数获取。
+    /// * `view`: 主视图状态。
+    ///
+    /// returns: bool 返回`true`表示插件已处理该事件，组件和后续插件不再处理。
+    fn on_event(&mut self, _event: Event, _view: &PanelView) -> bool {
+        false
+    }
+
+    /// 主视图尺寸发生变化后调用。
+    ///
+    /// # Arguments
+    ///
+    /// * `shape`: 尺寸变化前后的信息。
+    /// * `view`: 主视图状态。
+    ///
+    /// returns: ()
+    fn on_resize(&mut self, _shape: &ShapeData, _view: &PanelView) {}
+}
+
+impl Debug for dyn RichDisplayPlugin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RichDisplayPlugin: {}", self.name())
+    }
+}
+
+/// 已注册的插件及其名称，调用钩子时各自加锁。名称单独保存，钩子执行期间也能按名称查找插件。
+pub(crate) type SharedPlugin = (String, Arc<RwLock<Box<dyn RichDisplayPlugin>>>);
+
+/// 复制插件列表的快照，以便在不持有列表锁的情况下调用插件钩子。
+///
+/// # Arguments
+///
+/// * `plugins`: 插件列表。
+///
+/// returns: Vec<Arc<RwLock<Box<dyn RichDisplayPlugin>>>>
+pub(crate) fn snapshot_plugins(plugins: &RwLock<Vec<SharedPlugin>>) -> Vec<Arc<RwLock<Box<dyn RichDisplayPlugin>>>> {
+    plugins.read().iter().map(|(_, plugin)| plugin.clone()).collect()
+}
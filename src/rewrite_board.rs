@@ -1,8 +1,9 @@
-use std::cmp::{max};
-use std::collections::{BTreeMap};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::sync::Arc;
 use parking_lot::RwLock;
-use crate::{LinedData, LinePiece, RichData};
+use fltk::draw;
+use crate::{char_columns, DataType, fit_columns, KeywordHighlight, LinedData, LinePiece, Padding, RichData, RichDataOptions, take_columns, text_columns, update_data_properties};
 
 /// 屏幕光标位置信息，以行、列的方式表示。
 /// 参照`ANSI/CSI`的标准设计，行、列均从1开始。
@@ -180,8 +181,37 @@ impl CursorPos {
     }
 }
 
+/// 定位面板自上次绘制以来的变化，用于以复制像素的方式增量刷新主视图的离线缓存。
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BoardDamage {
+    /// 是否需要完整重绘。
+    pub full: bool,
+    /// 按发生顺序记录的区域滚动，每项为滚动区域的顶部行号、底部行号和上移的行数。
+    pub scrolls: Vec<(usize, usize, usize)>,
+    /// 内容发生变化需要重绘的行号。
+    pub dirty_rows: BTreeSet<usize>,
+}
+
+impl BoardDamage {
+    /// 是否可以只复制滚动区域并重绘变化的行。
+    pub fn is_partial(&self) -> bool {
+        !self.full && (!self.scrolls.is_empty() || !self.dirty_rows.is_empty())
+    }
+
+    /// 区域内的行号随滚动上移，移出区域顶部时返回`None`。
+    fn shift_up(row: usize, top: usize, bottom: usize, n: usize) -> Option<usize> {
+        if row < top || row > bottom {
+            Some(row)
+        } else if row >= top + n {
+            Some(row - n)
+        } else {
+            None
+        }
+    }
+}
+
 /// 可反复擦写的光标定位显示板，用于CSI光标控制输出位置的场景。
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct  ReWriteBoard {
     /// 行数
     pub max_rows: usize,
@@ -198,10 +228,18 @@ pub struct  ReWriteBoard {
     /// 数据行容器，key为行号，value为行数据。
     pub line_data_map: BTreeMap<usize, Vec<RichData>>,
     pub cursor_pos: CursorPos,
+    /// 滚动区域的顶部行号和底部行号，为空时表示整个面板。
+    pub scroll_region: Option<(usize, usize)>,
+    /// 自上次绘制以来的变化。
+    pub(crate) damage: BoardDamage,
+    /// 上次绘制时光标所在的行，增量刷新时需要擦除旧光标。
+    pub(crate) cursor_row: Option<usize>,
+    /// 所在面板的边界空白。
+    pub(crate) padding: Padding,
 }
 
 impl ReWriteBoard {
-    pub fn new(max_rows: usize, max_cols: usize, top_y: usize, line_height: usize, line_space: usize) -> Self {
+    pub(crate) fn new(max_rows: usize, max_cols: usize, top_y: usize, line_height: usize, line_space: usize, padding: Padding) -> Self {
         let bottom_y = top_y + max_rows * line_height + line_space * (max_rows - 1);
         let line_data_map = BTreeMap::new();
         let cursor_pos = CursorPos::new(1, 1, max_rows, max_cols);
@@ -215,14 +253,261 @@ impl ReWriteBoard {
             line_space,
             line_data_map,
             cursor_pos,
+            scroll_region: None,
+            damage: BoardDamage { full: true, ..Default::default() },
+            cursor_row: None,
+            padding,
         }
     }
 
 
+    /// 创建与当前面板互不共享数据分片的副本。
+    pub(crate) fn detached(&self) -> Self {
+        let mut board = self.clone();
+        let mut lines = HashMap::new();
+        board.line_data_map = self.line_data_map.iter().map(|(row, rds)| (*row, rds.iter().map(|rd| rd.detached(&mut lines)).collect())).collect();
+        board
+    }
+
     pub fn resize(&mut self, rows: usize, cols: usize) {
         self.max_rows = rows;
         self.max_cols = cols;
         self.bottom_y = self.top_y + rows * self.line_height + self.line_space * (rows - 1);
+        if self.scroll_region.is_some_and(|(_, bottom)| bottom > rows) {
+            self.scroll_region = None;
+        }
+        self.invalidate();
+    }
+
+    /// 标记面板需要完整重绘。直接修改面板数据后应调用此方法。
+    pub(crate) fn invalidate(&mut self) {
+        self.damage.full = true;
+    }
+
+    /// 取出自上次绘制以来的变化，同时清空记录。
+    pub(crate) fn take_damage(&mut self) -> BoardDamage {
+        std::mem::take(&mut self.damage)
+    }
+
+    /// 获取纵坐标所在的行号，超出面板范围时返回`None`。
+    ///
+    /// # Arguments
+    ///
+    /// * `y`: 内容中的纵坐标。
+    ///
+    /// returns: Option<usize>
+    pub(crate) fn row_at(&self, y: i32) -> Option<usize> {
+        let offset = y - self.row_y(1);
+        if offset < 0 {
+            return None;
+        }
+        let row = offset as usize / (self.line_height + self.line_space) + 1;
+        if row <= self.max_rows { Some(row) } else { None }
+    }
+
+    /// 设置滚动区域，对应`DECSTBM`控制序列。在区域底部行换行时，只有区域内的行向上滚动，区域外的行保持不动。
+    ///
+    /// # Arguments
+    ///
+    /// * `top`: 顶部行号，从1开始，0表示第一行。
+    /// * `bottom`: 底部行号，0或超过面板行数时表示最后一行。
+    ///
+    /// returns: ()
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let top = max(top, 1);
+        let bottom = if bottom == 0 || bottom > self.max_rows { self.max_rows } else { bottom };
+        self.scroll_region = if top >= bottom || (top == 1 && bottom == self.max_rows) {
+            None
+        } else {
+            Some((top, bottom))
+        };
+    }
+
+    /// 将滚动区域内的行整体上移一行，顶部行被丢弃，底部行留空。
+    /// 记录本次滚动，绘制时可直接复制区域内已绘制的像素，只需重绘底部的新行。
+    fn scroll_region_up(&mut self, top: usize, bottom: usize) {
+        self.remove_rows(top, bottom, 1);
+        if !self.damage.full {
+            let dirty_rows = std::mem::take(&mut self.damage.dirty_rows);
+            self.damage.dirty_rows = dirty_rows.into_iter().filter_map(|r| BoardDamage::shift_up(r, top, bottom, 1)).collect();
+            self.damage.dirty_rows.insert(bottom);
+            self.damage.scrolls.push((top, bottom, 1));
+        }
+        // 旧光标的像素随区域一起上移。
+        self.cursor_row = self.cursor_row.and_then(|r| BoardDamage::shift_up(r, top, bottom, 1));
+    }
+
+    /// 当前的滚动区域，未设置时为整个面板。
+    fn region(&self) -> (usize, usize) {
+        self.scroll_region.unwrap_or((1, self.max_rows))
+    }
+
+    /// 移除从第`row`行开始的n行，下方直到第`bottom`行的内容依次上移，底部留空。
+    fn remove_rows(&mut self, row: usize, bottom: usize, n: usize) {
+        let step = ((self.line_height + self.line_space) * n) as i32;
+        for r in row..(row + n) {
+            self.line_data_map.remove(&r);
+        }
+        for r in (row + n)..=bottom {
+            if let Some(mut rds) = self.line_data_map.remove(&r) {
+                rds.iter_mut().for_each(|rd| rd.shift_y(-step));
+                self.line_data_map.insert(r - n, rds);
+            }
+        }
+    }
+
+    /// 在光标所在行插入n个空行，对应`CSI n L`。光标所在行及其下方直到滚动区域底部的内容依次下移，超出区域底部的行被丢弃。
+    /// 光标不在滚动区域内时不做处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: 插入的行数。
+    ///
+    /// returns: ()
+    pub fn insert_lines(&mut self, n: usize) {
+        let (row, _) = self.cursor_pos.get();
+        let (top, bottom) = self.region();
+        if row < top || row > bottom {
+            return;
+        }
+        let n = min(max(n, 1), bottom - row + 1);
+        let step = ((self.line_height + self.line_space) * n) as i32;
+        for r in (bottom + 1 - n)..=bottom {
+            self.line_data_map.remove(&r);
+        }
+        for r in (row..(bottom + 1 - n)).rev() {
+            if let Some(mut rds) = self.line_data_map.remove(&r) {
+                rds.iter_mut().for_each(|rd| rd.shift_y(step));
+                self.line_data_map.insert(r + n, rds);
+            }
+        }
+        self.invalidate();
+        self.cursor_pos.set_m(1);
+    }
+
+    /// 删除光标所在行开始的n行，对应`CSI n M`。下方直到滚动区域底部的内容依次上移，区域底部留空。
+    /// 光标不在滚动区域内时不做处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: 删除的行数。
+    ///
+    /// returns: ()
+    pub fn delete_lines(&mut self, n: usize) {
+        let (row, _) = self.cursor_pos.get();
+        let (top, bottom) = self.region();
+        if row < top || row > bottom {
+            return;
+        }
+        let n = min(max(n, 1), bottom - row + 1);
+        self.remove_rows(row, bottom, n);
+        self.invalidate();
+        self.cursor_pos.set_m(1);
+    }
+
+    /// 在光标位置插入n个空格，对应`CSI n @`。光标后的字符依次右移，超出面板列数的字符被丢弃。
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: 插入的字符数。
+    /// * `drawable_max_width`: 面板可绘制的最大宽度。
+    /// * `basic_char`: 基本字符。
+    ///
+    /// returns: ()
+    pub fn insert_chars(&mut self, n: usize, drawable_max_width: i32, basic_char: char) {
+        let (row, col) = self.cursor_pos.get();
+        let max_cols = self.max_cols;
+        self.edit_row(row, drawable_max_width, basic_char, |cells| {
+            let pos = cell_at_column(cells, col);
+            if pos >= cells.len() {
+                return;
+            }
+            let seg = cells[pos].0;
+            for _ in 0..max(n, 1) {
+                cells.insert(pos, (seg, ' '));
+            }
+            // 丢弃超出面板列数的字符，保留行尾的换行符。
+            while cells.iter().map(|(_, c)| char_columns(*c)).sum::<usize>() > max_cols {
+                if let Some(idx) = cells.iter().rposition(|(_, c)| *c != '\n') {
+                    cells.remove(idx);
+                }
+            }
+        });
+    }
+
+    /// 删除光标位置开始的n个字符，对应`CSI n P`。光标后的字符依次左移，行尾的换行符不会被删除。
+    ///
+    /// # Arguments
+    ///
+    /// * `n`: 删除的字符数。
+    /// * `drawable_max_width`: 面板可绘制的最大宽度。
+    /// * `basic_char`: 基本字符。
+    ///
+    /// returns: ()
+    pub fn delete_chars(&mut self, n: usize, drawable_max_width: i32, basic_char: char) {
+        let (row, col) = self.cursor_pos.get();
+        self.edit_row(row, drawable_max_width, basic_char, |cells| {
+            let pos = cell_at_column(cells, col);
+            let mut removed = 0;
+            while removed < max(n, 1) && pos < cells.len() && cells[pos].1 != '\n' {
+                removed += char_columns(cells.remove(pos).1);
+            }
+        });
+    }
+
+    /// 以字符为单位编辑指定行的内容，然后重新计算该行数据的绘制位置。
+    /// 编辑函数的参数是该行所有字符及其所属数据段的序号，编辑后字符全部被删除的数据段会被移除。
+    fn edit_row<F>(&mut self, row: usize, drawable_max_width: i32, basic_char: char, edit: F) where F: FnOnce(&mut Vec<(usize, char)>) {
+        let y = self.row_y(row);
+        self.damage.dirty_rows.insert(row);
+        let Some(rds) = self.line_data_map.get_mut(&row) else {
+            return;
+        };
+        let Some((start_x, font_size)) = rds.first().and_then(|rd| rd.line_pieces.first().map(|p| (p.read().x, rd.font_size))) else {
+            return;
+        };
+
+        let mut cells: Vec<(usize, char)> = rds.iter().enumerate().flat_map(|(i, rd)| rd.text.chars().map(move |c| (i, c))).collect();
+        edit(&mut cells);
+        rds.iter_mut().for_each(|rd| rd.text.clear());
+        for (i, c) in cells {
+            rds[i].text.push(c);
+        }
+        rds.retain(|rd| !rd.text.is_empty());
+        release_erased_gauges(rds);
+
+        let cursor_piece = LinePiece::init_piece(font_size, &self.padding);
+        cursor_piece.write().move_cursor_to(start_x, y);
+        let mut last_piece = cursor_piece;
+        for rd in rds.iter_mut() {
+            last_piece = rd.estimate(last_piece, drawable_max_width, basic_char);
+        }
+    }
+
+    /// 缩放字体后更新标准行高，并重新计算所有行数据的绘制位置。
+    ///
+    /// # Arguments
+    ///
+    /// * `line_height`: 新的标准行高。
+    /// * `drawable_max_width`: 面板可绘制的最大宽度。
+    /// * `basic_char`: 基本字符。
+    /// * `f`: 重新计算前对每个数据段执行的处理。
+    ///
+    /// returns: ()
+    pub(crate) fn rescale<F>(&mut self, line_height: usize, drawable_max_width: i32, basic_char: char, mut f: F) where F: FnMut(&mut RichData) {
+        self.line_height = line_height;
+        self.bottom_y = self.top_y + self.max_rows * line_height + self.line_space * (self.max_rows - 1);
+        self.line_data_map.values_mut().flatten().for_each(&mut f);
+        let rows = self.line_data_map.keys().copied().collect::<Vec<usize>>();
+        for row in rows {
+            self.edit_row(row, drawable_max_width, basic_char, |_| {});
+        }
+        self.invalidate();
+    }
+
+    /// 第n行的顶部y坐标。
+    pub(crate) fn row_y(&self, row: usize) -> i32 {
+        self.padding.top + (self.top_y + (self.line_height + self.line_space) * (row - 1)) as i32
     }
 
     /// 向面板中添加数据。
@@ -233,6 +518,7 @@ impl ReWriteBoard {
     /// * `cursor_piece`: 当前虚拟光标信息。
     /// * `drawable_max_width`: 面板可绘制的最大宽度。
     /// * `basic_char`: 基本字符。
+    /// * `keywords`: 关键字高亮列表，按拆分到各行后的文本计算高亮范围。
     ///
     /// returns: Option<Vec<RichData, Global>> 返回面板上所有的数据和超出面板的数据。
     /// 这些数据中的文本中已经去除了`"\r"`字符。
@@ -242,7 +528,7 @@ impl ReWriteBoard {
     /// ```
     ///
     /// ```
-    pub fn add_data(&mut self, data: RichData, cursor_piece: Arc<RwLock<LinePiece>>, drawable_max_width: i32, basic_char: char) -> Vec<RichData> {
+    pub fn add_data(&mut self, data: RichData, cursor_piece: Arc<RwLock<LinePiece>>, drawable_max_width: i32, basic_char: char, keywords: &[KeywordHighlight]) -> Vec<RichData> {
         let mut exceed_board_data: Vec<RichData> = vec![];
         // {
         //     let (current_row, current_col) = self.cursor_pos.get();
@@ -254,8 +540,10 @@ impl ReWriteBoard {
             // 将超出面板范围的行返回给上一级调用者
             let content = line.replace("\r", "");
             if current_row > self.max_rows {
+                self.invalidate();
                 let mut rd = data.clone();
                 rd.text = content;
+                rd.apply_keywords(keywords);
                 *cursor_piece.write() = rd.estimate(cursor_piece.clone(), drawable_max_width, basic_char).read().get_cursor();
                 exceed_board_data.push(rd);
                 // debug!("光标位置超出定位面板范围，即将退出定位面板。");
@@ -265,11 +553,18 @@ impl ReWriteBoard {
             // 将行数据存入对应行数据格子中。
             let mut rd = data.clone();
             rd.text = content.to_string();
+            if rd.data_type == DataType::Gauge {
+                // 进度条按像素宽度绘制，在面板中需要换算为整数列，才能与光标定位和擦除操作对齐。
+                draw::set_font(rd.font, rd.font_size);
+                let (char_width, _) = draw::measure(&basic_char.to_string(), false);
+                fit_board_gauge(&mut rd, char_width);
+            }
+            rd.apply_keywords(keywords);
             rd.rewrite_board_data = true;
 
             *cursor_piece.write() = rd.estimate(cursor_piece.clone(), drawable_max_width, basic_char).read().get_cursor();
             if !content.trim().is_empty() {
-                let char_len = rd.text.chars().count();
+                let char_len = text_columns(&rd.text);
                 if let Some(line) = self.line_data_map.get_mut(&current_row) {
                     if current_col == 1 {
                         // 如果实在行首添加数据，则将本行数据清空后再添加。
@@ -280,23 +575,61 @@ impl ReWriteBoard {
                 } else {
                     self.line_data_map.insert(current_row, vec![rd]);
                 }
+                self.damage.dirty_rows.insert(current_row);
                 self.cursor_pos.add_m(char_len);
             }
 
-            // 如果文本以换行符结尾，则将光标下移一行。
+            // 如果文本以换行符结尾，则将光标下移一行。若光标位于滚动区域底部，则滚动区域内容上移一行，光标保持在区域底部。
             if content.ends_with("\n") {
-                self.cursor_pos.add_n(1);
+                match self.scroll_region {
+                    Some((top, bottom)) if current_row == bottom => {
+                        self.scroll_region_up(top, bottom);
+                        cursor_piece.write().move_cursor_to(self.padding.left, self.row_y(bottom));
+                    }
+                    _ => {
+                        self.cursor_pos.add_n(1);
+                    }
+                }
                 self.cursor_pos.set_m(1);
             }
         }
 
-        let mut all = self.line_data_map.values().cloned().flatten().collect::<Vec<RichData>>();
+        let mut all = self.all_data();
         all.append(&mut exceed_board_data);
         all
     }
 
+    /// 更新面板上指定数据段的属性，同一数据段被拆分到多行时全部更新。
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 更新参数。
+    /// * `keywords`: 关键字高亮列表。
+    ///
+    /// returns: ()
+    pub(crate) fn update_data(&mut self, options: &RichDataOptions, keywords: &[KeywordHighlight]) {
+        for (row, line) in self.line_data_map.iter_mut() {
+            for rd in line.iter_mut().filter(|rd| rd.id == options.id) {
+                let columns = text_columns(&rd.text);
+                update_data_properties(options.clone(), rd, keywords);
+                if rd.data_type == DataType::Gauge {
+                    // 进度变化后保持原有的列数，避免挤占同一行中其他数据的位置。
+                    rd.text = fit_columns(&rd.text, columns);
+                    rd.apply_keywords(keywords);
+                }
+                self.damage.dirty_rows.insert(*row);
+            }
+        }
+    }
+
+    /// 按行顺序获取面板上的所有数据。
+    pub fn all_data(&self) -> Vec<RichData> {
+        self.line_data_map.values().cloned().flatten().collect::<Vec<RichData>>()
+    }
+
     pub fn erase_in_line(&mut self, erase_mode: u8) {
         let (row, col) = self.cursor_pos.get();
+        self.damage.dirty_rows.insert(row);
         // let col_idx = col - 1;
         match erase_mode {
             1 => {
@@ -305,11 +638,9 @@ impl ReWriteBoard {
                 if let Some(rds) = self.line_data_map.get_mut(&row) {
                     let mut char_count_sum = 0;
                     for rd in rds.iter_mut() {
-                        let chars = rd.text.chars();
-                        let chars_len = chars.count();
+                        let chars_len = text_columns(&rd.text);
                         if char_count_sum + chars_len > col && char_count_sum < col {
-                            let sub_char_len = col - char_count_sum;
-                            let sub_text_len = rd.text.chars().take(sub_char_len).collect::<String>().len();
+                            let (sub_text_len, sub_char_len) = take_columns(&rd.text, col - char_count_sum);
                             rd.text.replace_range(..sub_text_len, " ".repeat(sub_char_len).as_str());
                             if let Some(fp) = rd.line_pieces.first_mut() {
                                 fp.write().line = rd.text.clone();
@@ -350,15 +681,15 @@ impl ReWriteBoard {
                         let (mut drain, mut idx) = (false, 0);
                         let mut char_count_sum = 0;
                         for (rd_idx, rd) in rds.iter_mut().enumerate() {
-                            let chars = rd.text.chars();
-                            let char_len = chars.count();
+                            let char_len = text_columns(&rd.text);
                             let text_len = rd.text.len();
                             // debug!("擦除到行尾时：col:{col}, char_count_sum:{char_count_sum}");
 
                             if char_count_sum + char_len > col {
                                 if col >= char_count_sum {
-                                    let sub_len = rd.text.chars().take(col - char_count_sum).collect::<String>().len();
-                                    rd.text.replace_range(sub_len..text_len, " ".repeat(char_count_sum + char_len - col).as_str());
+                                    // 宽字符的后半部分被擦除时整个宽字符一并擦除。
+                                    let (sub_len, kept) = fit_columns(&rd.text, col - char_count_sum);
+                                    rd.text.replace_range(sub_len..text_len, " ".repeat(char_len - kept).as_str());
                                     if let Some(fp) = rd.line_pieces.first_mut() {
                                         fp.write().line = rd.text.clone();
                                     }
@@ -377,11 +708,15 @@ impl ReWriteBoard {
                 }
             }
         }
+        if let Some(rds) = self.line_data_map.get_mut(&row) {
+            release_erased_gauges(rds);
+        }
     }
 
     pub fn erase_in_display(&mut self, erase_mode: u8) {
         // debug!("擦除屏幕 {erase_mode}");
         let (mut row, col) = self.cursor_pos.get();
+        self.invalidate();
         // let col_idx = col - 1;
         match erase_mode {
             1 => {
@@ -390,11 +725,9 @@ impl ReWriteBoard {
                 if let Some(rds) = self.line_data_map.get_mut(&row) {
                     let mut char_count_sum = 0;
                     for rd in rds.iter_mut() {
-                        let chars = rd.text.chars();
-                        let chars_len = chars.count();
+                        let chars_len = text_columns(&rd.text);
                         if char_count_sum + chars_len > col && char_count_sum < col {
-                            let sub_char_len = col - char_count_sum;
-                            let sub_text_len = rd.text.chars().take(sub_char_len).collect::<String>().len();
+                            let (sub_text_len, sub_char_len) = take_columns(&rd.text, col - char_count_sum);
                             rd.text.replace_range(..sub_text_len, " ".repeat(sub_char_len).as_str());
                             if let Some(fp) = rd.line_pieces.first_mut() {
                                 fp.write().line = rd.text.clone();
@@ -446,15 +779,15 @@ impl ReWriteBoard {
                     let (mut drain, mut idx) = (false, 0);
                     let mut char_count_sum = 0;
                     for (rd_idx, rd) in rds.iter_mut().enumerate() {
-                        let chars = rd.text.chars();
-                        let char_len = chars.count();
+                        let char_len = text_columns(&rd.text);
                         let text_len = rd.text.len();
                         // debug!("擦除到行尾时：col:{col}, char_count_sum:{char_count_sum}");
 
                         if char_count_sum + char_len > col {
                             if col >= char_count_sum {
-                                let sub_len = rd.text.chars().take(col - char_count_sum).collect::<String>().len();
-                                rd.text.replace_range(sub_len..text_len, " ".repeat(char_count_sum + char_len - col).as_str());
+                                // 宽字符的后半部分被擦除时整个宽字符一并擦除。
+                                let (sub_len, kept) = fit_columns(&rd.text, col - char_count_sum);
+                                rd.text.replace_range(sub_len..text_len, " ".repeat(char_len - kept).as_str());
                                 if let Some(fp) = rd.line_pieces.first_mut() {
                                     fp.write().line = rd.text.clone();
                                 }
@@ -485,5 +818,76 @@ impl ReWriteBoard {
                 }
             }
         }
+        self.line_data_map.values_mut().for_each(|rds| release_erased_gauges(rds));
     }
-}
\ No newline at end of file
+}
+
+/// 获取覆盖指定列的字符在行字符列表中的位置。光标位于宽字符的后半部分时返回该宽字符的位置，超出行内容时返回列表长度。
+///
+/// # Arguments
+///
+/// * `cells`: 行中所有字符及其所属数据段的序号。
+/// * `col`: 列，从1开始。
+///
+/// returns: usize
+pub(crate) fn cell_at_column(cells: &[(usize, char)], col: usize) -> usize {
+    let mut start = 1;
+    for (i, (_, c)) in cells.iter().enumerate() {
+        let width = char_columns(*c);
+        if width > 0 && start + width > col {
+            return i;
+        }
+        start += width;
+    }
+    cells.len()
+}
+
+/// 将面板中的进度条宽度换算为整数列，进度条文本替换为占用相同列数的文本，使光标定位、擦除和插入删除字符都可以按列计算。
+///
+/// # Arguments
+///
+/// * `rd`: 进度条数据段。
+/// * `char_width`: 基本字符的宽度。
+///
+/// returns: ()
+pub(crate) fn fit_board_gauge(rd: &mut RichData, char_width: i32) {
+    let char_width = max(char_width, 1);
+    if let Some(gauge) = rd.gauge.as_mut() {
+        let columns = max((gauge.width + char_width - 1) / char_width, 1);
+        gauge.width = columns * char_width;
+        rd.text = fit_columns(&rd.text, columns as usize);
+    }
+}
+
+/// 截取或以空格补齐文本，使其正好占用指定的列数。
+fn fit_columns(text: &str, columns: usize) -> String {
+    let mut fitted = String::new();
+    let mut taken = 0;
+    for c in text.chars().filter(|c| *c != '\n') {
+        let width = char_columns(c);
+        if taken + width > columns {
+            break;
+        }
+        fitted.push(c);
+        taken += width;
+    }
+    fitted.push_str(&" ".repeat(columns - taken));
+    fitted
+}
+
+/// 被擦除或被插入删除字符破坏的进度条不再绘制为进度条，改为按照剩余的文本显示。
+///
+/// # Arguments
+///
+/// * `rds`: 面板中一行的数据。
+///
+/// returns: ()
+pub(crate) fn release_erased_gauges(rds: &mut [RichData]) {
+    for rd in rds.iter_mut().filter(|rd| rd.data_type == DataType::Gauge) {
+        let intact = rd.gauge.as_ref().is_some_and(|gauge| rd.text == fit_columns(&gauge.text(), text_columns(&rd.text)));
+        if !intact {
+            rd.data_type = DataType::Text;
+            rd.gauge = None;
+        }
+    }
+}
@@ -166,6 +166,13 @@
 //!                         warn!("未找到目标数据: {}", first_uid);
 //!                     }
 //!                 }
+//!                 PageOptions::At(target) => {
+//!                     // 以目标数据为中心载入一页数据。
+//!                     let from = data_buffer_rc.read().partition_point(|d| d.id < target).saturating_sub(ps / 2);
+//!                     let page_data: Vec<UserData> = data_buffer_rc.read().iter().skip(from).take(ps).cloned().collect();
+//!                     reviewer_rc.load_page_now(page_data, opt);
+//!                 }
+//!                 _ => {}
 //!             }
 //!         }
 //!     };
@@ -186,30 +193,34 @@
 //! }
 //! ```
 
-use std::cell::{Cell};
+use std::cell::{Cell, RefCell};
 use std::cmp::{max, min, Ordering};
-use std::collections::{HashMap};
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
-use std::sync::{Arc, OnceLock, Weak};
+use std::sync::{Arc, Weak};
 use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize};
 use std::sync::atomic::Ordering::Relaxed;
 use std::time::{Duration};
-use debounce_fltk::throttle_check;
-use fltk::draw::{draw_rect_fill, draw_xyline, LineStyle, Offscreen, set_draw_color, set_line_style};
+use fltk::draw::{draw_line, draw_rect_fill, draw_xyline, LineStyle, Offscreen, set_draw_color, set_line_style};
 use fltk::enums::{Align, Color, Cursor, Event, Font};
 use fltk::group::{Scroll, ScrollType};
-use fltk::prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt};
+use fltk::prelude::{GroupExt, MenuExt, ValuatorExt, WidgetBase, WidgetExt};
 use fltk::{app, draw, widget_extends};
 use fltk::app::{awake_callback, MouseButton, MouseWheel};
 use fltk::menu::{MenuButton, MenuButtonType};
 use fltk::widget::Widget;
-use idgenerator_thin::YitIdHelper;
 use log::{error};
 use parking_lot::RwLock;
-use crate::{Rectangle, disable_data, LinedData, LinePiece, LocalEvent, mouse_enter, PADDING, RichData, RichDataOptions, update_data_properties, UserData, ClickPoint, clear_selected_pieces, BlinkState, BLINK_INTERVAL, Callback, CallPage, PageOptions, DEFAULT_FONT_SIZE, WHITE, locate_target_rd, update_selection_when_drag, CallbackData, BASIC_UNIT_CHAR, DataType, ImageEventData, IMAGE_PADDING_V, expire_data, select_paragraph};
+use crate::{Padding, Rectangle, disable_data, LinedData, LinePiece, LocalEvent, mouse_enter, PADDING, RichData, RichDataOptions, UserData, ClickPoint, clear_selected_pieces, BlinkState, BLINK_INTERVAL, Callback, CallPage, PageOptions, DEFAULT_FONT_SIZE, WHITE, locate_target_rd, update_selection_when_drag, ColumnLayout, CallbackData, BASIC_UNIT_CHAR, DataType, ImageEventData, IMAGE_PADDING_V, expire_data, select_paragraph, DataFilter, LINE_NUMBER_COLOR, LINE_NUMBER_DIGITS, IMAGE_PADDING_H, ScrollInfo, ScrollNotifier, expire_data_by_ids, italic_font, retheme_data, Theme, RAPID_BLINK_INTERVAL, BlinkSpeed, zoom_font_size, scale_font_size, WrapPolicy, WrapCache, DEFAULT_WRAP_CACHE_CAPACITY, LINE_HEIGHT_FACTOR, HoverTracker, track_hover, TooltipState, track_tooltip, draw_tooltip, ContextMenuHook, locate_char_at, popup_blank_menu, selection_contains, selection_text, start_drag_out, drag_out_started, StyleDef, ExpireEffect, expire_data_with_effect, PagePrefetch, draw_page_spinner, PAGE_SPINNER_INTERVAL, CallSearch, SearchRequest, search_positions, KeywordHighlight, SelectionAnchor, SelectionRange, selection_range, keep_selection, follow_push_anchor, DRAG_SCROLL_INTERVAL, drag_scroll_step, flow_column_width, flow_columns, MAX_COLUMNS, ColorTransform, ColorTransformScope, transform_color, DisabledStyle, TextShadow, id_range, update_origin_data, thread_hidden_ids, TimerSet, BLINK_TIMER, RAPID_BLINK_TIMER, RenderOptions};
+use crate::deterministic;
 use crate::rich_text::{PANEL_PADDING};
+use crate::log_file::{LogFile, LogFormat};
 
-static LOAD_PAGE_TASK_ID: OnceLock<i64> = OnceLock::new();
+thread_local! {
+    /// 已打开日志文件的回顾区，以数据缓存的地址为键，仅在`fltk`主线程中访问。
+    /// 分页与查找回调只持有键，避免回调与回顾区互相持有形成循环引用。
+    static LOG_TARGETS: RefCell<HashMap<usize, RichReviewer>> = RefCell::new(HashMap::new());
+}
 
 #[derive(Clone, Debug)]
 pub struct RichReviewer {
@@ -226,7 +237,19 @@ pub struct RichReviewer {
     /// 查找结果，保存查询到的目标数据段在data_buffer中的索引编号。
     search_results: Arc<RwLock<Vec<usize>>>,
     current_highlight_focus: Arc<RwLock<Option<(usize, usize)>>>,
+    /// 后台查找回调。
+    search_notifier: Arc<RwLock<Option<CallSearch>>>,
+    /// 等待中的后台查找，记录查找方向，以及已知但尚未载入的目标数据id。
+    remote_search: Arc<RwLock<Option<(bool, Option<i64>)>>>,
+    /// 持久高亮的关键字，按添加顺序排列。
+    keywords: Arc<RwLock<Vec<KeywordHighlight>>>,
     blink_flag: Arc<RwLock<BlinkState>>,
+    /// 与闪烁无关的绘制选项。
+    render_options: Arc<RwLock<RenderOptions>>,
+    /// 可暂停的闪烁定时器。
+    timers: Arc<RwLock<TimerSet>>,
+    /// 闪烁定时器是否因主视图空闲而暂停。
+    suspended: Arc<AtomicBool>,
     /// true表示历史记录模式，默认false表示在线回顾模式。
     history_mode: Arc<AtomicBool>,
     /// 历史模式下，分页数据大小。
@@ -236,7 +259,59 @@ pub struct RichReviewer {
     text_size: Arc<AtomicI32>,
     piece_spacing: Arc<AtomicI32>,
     enable_blink: Arc<AtomicBool>,
+    /// 慢速闪烁内容的强度切换间隔时间。
+    blink_interval: Arc<RwLock<f64>>,
+    /// 快速闪烁内容的强度切换间隔时间。
+    rapid_blink_interval: Arc<RwLock<f64>>,
     basic_char: Arc<RwLock<char>>,
+    /// 左侧行号栏宽度，为0时不显示行号。
+    gutter_width: Arc<AtomicI32>,
+    /// 数据过滤器，仅显示符合过滤条件的数据段。
+    filter: Arc<RwLock<Option<DataFilter>>>,
+    /// 被过滤器暂时隐藏的数据段。
+    hidden_data: Arc<RwLock<Vec<RichData>>>,
+    /// 被隐藏数据段在原有顺序中紧邻的前一个可见数据段ID，恢复显示时据此还原顺序。
+    hidden_anchors: Arc<RwLock<HashMap<i64, Option<i64>>>>,
+    /// 滚动位置变化的回调函数。
+    scroll_notifier: Arc<RwLock<Option<ScrollNotifier>>>,
+    /// 互动行为提示信息的显示状态。
+    tooltip: Arc<RwLock<TooltipState>>,
+    /// 右键菜单钩子函数。
+    context_menu_hook: Arc<RwLock<Option<ContextMenuHook>>>,
+    /// 在空白处右击时是否弹出组件内置菜单。
+    blank_menu: Arc<AtomicBool>,
+    /// 最近一次汇报的滚动高度和最大可滚动高度。
+    last_scroll: Arc<RwLock<(i32, i32)>>,
+    /// ANSI 16色调色板，为空时按照数据段自带的颜色绘制。
+    ansi_palette: Arc<RwLock<Option<[Color; 16]>>>,
+    /// 是否在新数据到达时保持当前查找目标垂直居中。
+    follow_search_focus: Arc<AtomicBool>,
+    /// 正在跟随的查找目标所在数据段的ID，以及最近一次居中后的滚动位置。用户滚动到其他位置或清除查找结果后停止跟随。
+    following_focus: Arc<RwLock<Option<(i64, i32)>>>,
+    /// 行高与字体尺寸的比例。
+    line_height_factor: Arc<RwLock<f32>>,
+    /// 逻辑行结束后额外增加的段落间距。
+    paragraph_spacing: Arc<AtomicI32>,
+    /// 内容与面板边界之间的空白。
+    padding: Arc<RwLock<Padding>>,
+    /// 懒加载分页的预取状态。
+    prefetch: Arc<RwLock<PagePrefetch>>,
+    /// 分栏显示的栏数，为1时不分栏。
+    columns: Arc<AtomicUsize>,
+    /// 绘制时使用的颜色变换。
+    color_transform: Arc<RwLock<ColorTransform>>,
+    /// 禁用数据段的显示样式。
+    disabled_style: Arc<RwLock<DisabledStyle>>,
+    /// 自动换行的断行策略。
+    wrap_policy: Arc<RwLock<WrapPolicy>>,
+    /// 自动换行后续行的悬挂缩进宽度。
+    hanging_indent: Arc<AtomicI32>,
+    /// 换行试算缓存，由主视图打开时与主视图共享。
+    wrap_cache: Arc<RwLock<WrapCache>>,
+    /// 复制选中文字时是否在自动折行处插入换行符。
+    copy_soft_wraps: Arc<AtomicBool>,
+    /// 数据缓存是否与主视图共享。共享的缓存属于主视图，回顾区不能移出或重排其中的数据。
+    shared_buffer: bool,
 }
 widget_extends!(RichReviewer, Scroll, scroller);
 
@@ -246,6 +321,25 @@ impl RichReviewer {
 
     pub fn new<T>(x: i32, y: i32, w: i32, h: i32, title: T) -> Self
         where T: Into<Option<&'static str>> + Clone {
+        let mut reviewer = Self::with_buffer(x, y, w, h, title, Arc::new(RwLock::new(vec![])));
+        reviewer.shared_buffer = false;
+        reviewer
+    }
+
+    /// 使用指定的数据缓存创建回顾区组件，用于与主视图共享同一份数据缓存的分屏回看模式。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`:
+    /// * `y`:
+    /// * `w`:
+    /// * `h`:
+    /// * `title`:
+    /// * `data_buffer`: 数据缓存。
+    ///
+    /// returns: RichReviewer
+    pub(crate) fn with_buffer<T>(x: i32, y: i32, w: i32, h: i32, title: T, data_buffer: Arc<RwLock<Vec<RichData>>>) -> Self
+        where T: Into<Option<&'static str>> + Clone {
 
         let mut scroller = Scroll::new(x, y, w, h, title);
         scroller.set_type(ScrollType::Vertical);
@@ -260,7 +354,6 @@ impl RichReviewer {
         let mut panel = Widget::new(x, y, w, h, None);
         scroller.add_resizable(&panel);
 
-        let data_buffer: Arc<RwLock<Vec<RichData>>> = Arc::new(RwLock::new(vec![]));
         let background_color = Arc::new(RwLock::new(Color::Black));
         let visible_lines = Arc::new(RwLock::new(HashMap::<Rectangle, LinePiece>::new()));
         let clickable_data = Arc::new(RwLock::new(HashMap::<Rectangle, usize>::new()));
@@ -273,16 +366,45 @@ impl RichReviewer {
         let page_size = Arc::new(AtomicUsize::new(10));
         let piece_spacing = Arc::new(AtomicI32::new(0));
         let enable_blink = Arc::new(AtomicBool::new(false));
+        let blink_interval = Arc::new(RwLock::new(BLINK_INTERVAL));
+        let rapid_blink_interval = Arc::new(RwLock::new(RAPID_BLINK_INTERVAL));
 
         let search_results = Arc::new(RwLock::new(Vec::<usize>::new()));
         let search_str = Arc::new(RwLock::new(None::<String>));
         let current_highlight_focus = Arc::new(RwLock::new(None::<(usize, usize)>));
         let basic_char = Arc::new(RwLock::new(BASIC_UNIT_CHAR));
+        let gutter_width = Arc::new(AtomicI32::new(0));
+        let filter: Arc<RwLock<Option<DataFilter>>> = Arc::new(RwLock::new(None));
+        let hidden_data: Arc<RwLock<Vec<RichData>>> = Arc::new(RwLock::new(vec![]));
+        let hidden_anchors: Arc<RwLock<HashMap<i64, Option<i64>>>> = Arc::new(RwLock::new(HashMap::new()));
+        let scroll_notifier: Arc<RwLock<Option<ScrollNotifier>>> = Arc::new(RwLock::new(None));
+        let tooltip = Arc::new(RwLock::new(TooltipState::default()));
+        let context_menu_hook: Arc<RwLock<Option<ContextMenuHook>>> = Arc::new(RwLock::new(None));
+        let blank_menu = Arc::new(AtomicBool::new(false));
+        let last_scroll = Arc::new(RwLock::new((0, 0)));
+        let padding = Arc::new(RwLock::new(PADDING));
+        let copy_soft_wraps = Arc::new(AtomicBool::new(false));
+        let prefetch = Arc::new(RwLock::new(PagePrefetch::default()));
+        let columns = Arc::new(AtomicUsize::new(1));
+        let color_transform = Arc::new(RwLock::new(ColorTransform::default()));
+        let selected_pieces = Arc::new(RwLock::new(Vec::<Weak<RwLock<LinePiece>>>::new()));
+        // 选区两端所在的数据段id和字符位置，重新加载分页数据后据此恢复选区。
+        let selection: Arc<RwLock<Option<SelectionRange>>> = Arc::new(RwLock::new(None));
+        // 划选时鼠标超出可视区域上下边界的距离，以及是否正在自动滚动。
+        let drag_overflow = Arc::new(AtomicI32::new(0));
+        let drag_scrolling = Arc::new(AtomicBool::new(false));
 
         let blink_flag = Arc::new(RwLock::new(BlinkState::new()));
+        let render_options = Arc::new(RwLock::new(RenderOptions::default()));
+        let timers = Arc::new(RwLock::new(TimerSet::default()));
+        let suspended = Arc::new(AtomicBool::new(false));
         let blink_handler = {
             let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
             let enable_blink_rc = enable_blink.clone();
+            let blink_interval_rc = blink_interval.clone();
+            let timers_rc = timers.clone();
+            let suspended_rc = suspended.clone();
 
             #[cfg(target_os = "linux")]
             let scroller_rc = scroller.clone();
@@ -292,8 +414,14 @@ impl RichReviewer {
 
             move |handler| {
                 if !scroller_rc.was_deleted() {
+                    if suspended_rc.load(Relaxed) {
+                        // 主视图空闲时暂停定时器，退出空闲状态时再启动。
+                        timers_rc.write().park(BLINK_TIMER);
+                        app::remove_timeout3(handler);
+                        return;
+                    }
                     if enable_blink_rc.load(Relaxed) {
-                        let should_toggle = blink_flag_rc.write().toggle_when_on();
+                        let should_toggle = blink_flag_rc.write().advance(BlinkSpeed::Slow, &render_options_rc.read());
                         if should_toggle {
                             // blink_flag_rc.set(bs);
                             // debug!("from reviewer blink flag: {:?}", blink_flag_rc.get());
@@ -307,13 +435,52 @@ impl RichReviewer {
                             scroller_rc.set_damage(true);
                         }
                     }
-                    app::repeat_timeout3(BLINK_INTERVAL, handler);
+                    app::repeat_timeout3(*blink_interval_rc.read(), handler);
+                } else {
+                    app::remove_timeout3(handler);
+                }
+            }
+        };
+        timers.write().start(BLINK_TIMER, BLINK_INTERVAL, blink_handler);
+
+        let rapid_blink_handler = {
+            let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
+            let enable_blink_rc = enable_blink.clone();
+            let rapid_blink_interval_rc = rapid_blink_interval.clone();
+            let timers_rc = timers.clone();
+            let suspended_rc = suspended.clone();
+
+            #[cfg(target_os = "linux")]
+            let scroller_rc = scroller.clone();
+
+            #[cfg(not(target_os = "linux"))]
+            let mut scroller_rc = scroller.clone();
+
+            move |handler| {
+                if !scroller_rc.was_deleted() {
+                    if suspended_rc.load(Relaxed) || !blink_flag_rc.read().rapid_on {
+                        // 主视图空闲或可视区域没有快速闪烁内容时暂停定时器，绘制出快速闪烁内容或退出空闲状态时再启动。
+                        timers_rc.write().park(RAPID_BLINK_TIMER);
+                        app::remove_timeout3(handler);
+                        return;
+                    }
+                    if enable_blink_rc.load(Relaxed) && blink_flag_rc.write().advance(BlinkSpeed::Rapid, &render_options_rc.read()) {
+                        #[cfg(target_os = "linux")]
+                        if let Some(mut parent) = scroller_rc.parent() {
+                            parent.set_damage(true);
+                        }
+
+                        #[cfg(not(target_os = "linux"))]
+                        scroller_rc.set_damage(true);
+                    }
+                    app::repeat_timeout3(*rapid_blink_interval_rc.read(), handler);
                 } else {
                     app::remove_timeout3(handler);
                 }
             }
         };
-        app::add_timeout3(BLINK_INTERVAL, blink_handler);
+        timers.write().register(RAPID_BLINK_TIMER, RAPID_BLINK_INTERVAL, rapid_blink_handler);
 
         panel.draw({
             let data_buffer_rc = data_buffer.clone();
@@ -323,14 +490,59 @@ impl RichReviewer {
             let bg_rc = background_color.clone();
             let screen_rc = reviewer_screen.clone();
             let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
             let history_mode_rc = history_mode.clone();
-            move |_| {
+            let scroll_notifier_rc = scroll_notifier.clone();
+            let notifier_rc = notifier.clone();
+            let last_scroll_rc = last_scroll.clone();
+            let padding_rc = padding.clone();
+            let tooltip_rc = tooltip.clone();
+            let prefetch_rc = prefetch.clone();
+            let text_color_rc = text_color.clone();
+            let columns_rc = columns.clone();
+            let color_transform_rc = color_transform.clone();
+            let selected_pieces_rc = selected_pieces.clone();
+            let selection_rc = selection.clone();
+            let timers_rc = timers.clone();
+            let suspended_rc = suspended.clone();
+            move |ctx| {
+                keep_selection(data_buffer_rc.read().as_slice(), &selection_rc, selected_pieces_rc.clone());
+                let _transform = ColorTransformScope::enter(&color_transform_rc.read());
                 /*
                 先离线绘制内容面板，再根据面板大小复制所需区域内容。这样做是为了避免在线绘制时，会出现绘制内容超出面板边界的问题。
                  */
-                Self::draw_offline(screen_rc.clone(), &scroll_rc, visible_lines_rc.clone(), clickable_data_rc.clone(), data_buffer_rc.clone(), *bg_rc.read(), blink_flag_rc.clone(), history_mode_rc.load(Relaxed));
+                Self::draw_offline(screen_rc.clone(), &scroll_rc, visible_lines_rc.clone(), clickable_data_rc.clone(), data_buffer_rc.clone(), *bg_rc.read(), blink_flag_rc.clone(), &render_options_rc.read(), history_mode_rc.load(Relaxed), *padding_rc.read(), columns_rc.load(Relaxed));
+                if blink_flag_rc.read().rapid_on && !suspended_rc.load(Relaxed) {
+                    timers_rc.write().wake(RAPID_BLINK_TIMER);
+                }
 
                 screen_rc.read().copy(scroll_rc.x(), scroll_rc.y(), scroll_rc.width(), scroll_rc.height(), 0, 0);
+
+                // 无论通过滚动条、滚轮、按键还是程序调用改变了滚动位置，都会触发重绘，因此在这里统一检测并汇报滚动位置的变化。
+                let current = (scroll_rc.yposition(), max(ctx.height() - scroll_rc.height(), 0));
+                let changed = {
+                    let mut last = last_scroll_rc.write();
+                    let changed = *last != current;
+                    *last = current;
+                    changed
+                };
+                if changed {
+                    if let Some(notifier) = &mut *scroll_notifier_rc.write() {
+                        notifier.notify(ScrollInfo::new(current.0, current.1));
+                    }
+                    if let Some(cb) = notifier_rc.read().as_ref() {
+                        cb.notify_listeners(CallbackData::Scroll(ScrollInfo::new(current.0, current.1)));
+                    }
+                }
+                if history_mode_rc.load(Relaxed) {
+                    // 等待页数据期间显示加载提示。
+                    let pending = prefetch_rc.read().pending(deterministic::now());
+                    if let Some((next, waited)) = pending {
+                        let frame = (waited.as_secs_f64() / PAGE_SPINNER_INTERVAL) as usize;
+                        draw_page_spinner((scroll_rc.x(), scroll_rc.y(), scroll_rc.w() - Self::SCROLL_BAR_WIDTH, scroll_rc.h()), next, frame, *bg_rc.read(), *text_color_rc.read());
+                    }
+                }
+                draw_tooltip(&tooltip_rc.read(), (scroll_rc.x(), scroll_rc.y(), scroll_rc.w(), scroll_rc.h()));
             }
         });
 
@@ -372,9 +584,27 @@ impl RichReviewer {
             let clickable_data_rc = clickable_data.clone();
             let mut push_from_point = ClickPoint::new(0, 0);
             let mut select_from_row = 0;
-            let selected_pieces = Arc::new(RwLock::new(Vec::<Weak<RwLock<LinePiece>>>::new()));
+            let mut push_anchor: Option<(SelectionAnchor, i32)> = None;
+            let mut shift_extended = false;
+            // 是否正在读取PRIMARY选区，用于区分中键粘贴产生的`Event::Paste`事件。
+            let mut pasting_primary = false;
+            let selected_pieces = selected_pieces.clone();
+            let selection_rc = selection.clone();
+            let drag_overflow_rc = drag_overflow.clone();
+            let drag_scrolling_rc = drag_scrolling.clone();
             let basic_char_rc = basic_char.clone();
             let text_size_rc = text_size.clone();
+            let hidden_data_rc = hidden_data.clone();
+            let padding_rc = padding.clone();
+            let copy_soft_wraps_rc = copy_soft_wraps.clone();
+            let columns_rc = columns.clone();
+            let tooltip_rc = tooltip.clone();
+            let context_menu_hook_rc = context_menu_hook.clone();
+            let blank_menu_rc = blank_menu.clone();
+            let prefetch_rc = prefetch.clone();
+            let hover = Arc::new(RwLock::new(HoverTracker::default()));
+            // 按下鼠标时检测到的可拖出内容，以及该内容是否为选中的文字。
+            let mut drag_out: Option<(String, bool, (i32, i32))> = None;
             move |scroller, evt| {
                 match evt {
                     // Event::Close => {
@@ -391,16 +621,21 @@ impl RichReviewer {
                             let old_scroll_y = scroller.yposition();
 
                             let mut new_panel_height = current_height;
-                            if last_width != current_width {
-                                // 当窗口宽度发生变化时，需要重新计算数据分片坐标信息。
-                                let drawable_max_width = current_width - PADDING.left - PADDING.right;
-                                let mut last_piece = LinePiece::init_piece(text_size_rc.load(Relaxed));
-                                for rich_data in buffer_rc.write().iter_mut() {
+                            let columns = columns_rc.load(Relaxed);
+                            if last_width != current_width || (columns > 1 && last_height != current_height) {
+                                // 当窗口宽度发生变化时，需要重新计算数据分片坐标信息。分栏显示时栏高随窗口高度变化，也需要重新排列。
+                                let padding = *padding_rc.read();
+                                let drawable_max_width = flow_column_width(padding.drawable_width(current_width), columns);
+                                let mut last_piece = LinePiece::init_piece(text_size_rc.load(Relaxed), &padding);
+                                let mut buffer = buffer_rc.write();
+                                for rich_data in buffer.iter_mut() {
                                     rich_data.line_pieces.clear();
                                     last_piece = rich_data.estimate(last_piece, drawable_max_width, *basic_char_rc.read());
                                 }
+                                flow_columns(&mut buffer, columns, drawable_max_width, padding.drawable_height(current_height), padding.top);
+                                drop(buffer);
 
-                                new_panel_height = Self::calc_panel_height(buffer_rc.clone(), current_height);
+                                new_panel_height = Self::calc_panel_height(buffer_rc.clone(), current_height, &padding);
 
                                 // 同步缩放回顾内容面板
                                 *resize_panel_after_resize_rc.write() = (scroller.x(), scroller.y(), current_width, new_panel_height);
@@ -432,18 +667,57 @@ impl RichReviewer {
                         }
                     }
                     Event::Move => {
-                        // 检测鼠标进入可互动区域，改变鼠标样式
-                        let (entered, _idx) = mouse_enter(clickable_data_rc.clone());
+                        // 检测鼠标进入可互动区域，改变鼠标样式并汇报悬停的数据段
+                        let (entered, idx) = mouse_enter(clickable_data_rc.clone());
                         if entered {
                             draw::set_cursor(Cursor::Hand);
                         } else {
                             draw::set_cursor(Cursor::Default);
                         }
+                        let target = if entered { buffer_rc.read().get(idx).map(|rd| (rd.id, rd.action.as_ref().map(|a| a.title.clone()))) } else { None };
+                        track_hover(&hover, target.as_ref().map(|(id, _)| *id), &notifier_rc);
+                        track_tooltip(&tooltip_rc, target.as_ref().and_then(|(_, title)| title.as_deref()), &panel_rc);
                     }
                     Event::Leave => {
                         draw::set_cursor(Cursor::Default);
+                        track_hover(&hover, None, &notifier_rc);
+                        track_tooltip(&tooltip_rc, None, &panel_rc);
+                    }
+                    Event::Paste if pasting_primary => {
+                        pasting_primary = false;
+                        if let Some(cb) = notifier_rc.write().as_mut() {
+                            cb.notify(CallbackData::PrimaryPaste(app::event_text()));
+                        }
+                        return true;
                     }
                     Event::Released => {
+                        drag_overflow_rc.store(0, Relaxed);
+                        if std::mem::take(&mut shift_extended) {
+                            // 按住Shift键点击扩展选区时不再执行常规的点击操作。
+                            return true;
+                        }
+                        // 在选中的文字上点击而未拖动时，按照普通点击清除选区。
+                        if let Some((_, true, _)) = drag_out.take() {
+                            clear_selected_pieces(selected_pieces.clone());
+                            selection_rc.write().take();
+                            scroller.set_damage(true);
+                        }
+                        // 点击链接预览卡片时汇报对应的网址。
+                        if app::event_mouse_button() == MouseButton::Left {
+                            let (app_x, app_y) = app::event_coords();
+                            let mut offset_y = scroller.yposition() - PANEL_PADDING;
+                            if let Some(first) = buffer_rc.read().first() {
+                                offset_y += first.v_bounds.read().0;
+                            }
+                            let url = buffer_rc.read().iter().rev().find_map(|rd| rd.preview_url_at(app_x - scroller.x(), app_y + offset_y - scroller.y()));
+                            if let Some(url) = url {
+                                if let Some(cb) = notifier_rc.write().as_mut() {
+                                    cb.notify(CallbackData::OpenUrl(url));
+                                }
+                                return true;
+                            }
+                        }
+
                         // 检测鼠标点击可互动区域，执行用户自定义操作
                         let mut target_opt: Option<UserData> = None;
                         let mut target_rd_v_bounds: Option<(i32, i32, i32, i32)> = None;
@@ -462,11 +736,26 @@ impl RichReviewer {
                             }
                         }
                         if app::event_mouse_button() == MouseButton::Right {
+                            let clickable = target_opt.is_some();
+                            if !clickable && context_menu_hook_rc.read().is_some() {
+                                // 在非互动数据段上右击时，同样为钩子函数提供所在的数据段。
+                                let (app_x, app_y) = app::event_coords();
+                                let scroll_y = scroller.yposition() - PANEL_PADDING + buffer_rc.read().first().map_or(0, |first| first.v_bounds.read().0);
+                                if let Some((idx, _)) = locate_char_at(buffer_rc.read().as_slice(), app_x - scroller.x(), app_y - scroller.y() + scroll_y) {
+                                    target_opt = buffer_rc.read().get(idx).map(UserData::from);
+                                }
+                            }
+                            let mut handled = false;
                             if let Some(ud) = target_opt {
-                                if ud.action.is_some() {
+                                let extra = context_menu_hook_rc.read().as_ref().map(|hook| hook.build(&ud)).unwrap_or_default();
+                                if ud.action.is_some() || !extra.is_empty() {
                                     // 右键弹出互动菜单
+                                    handled = true;
                                     let ud_rc = Rc::new(ud);
-                                    if let Some(action) = &ud_rc.action {
+                                    let action_len = ud_rc.action.as_ref().map_or(0, |action| action.items.len());
+                                    let extra_rc = Rc::new(RefCell::new(extra));
+                                    {
+                                        let action = ud_rc.action.clone().unwrap_or_default();
                                         let mut popup_menu_rc = MenuButton::new(0, 0, 0, 0, None);
                                         // popup_menu_rc.clear();
                                         popup_menu_rc.set_type(MenuButtonType::Popup1);
@@ -492,15 +781,19 @@ impl RichReviewer {
                                         for item in action.items.iter() {
                                             popup_menu_rc.add_choice(item.desc.as_str());
                                         }
+                                        extra_rc.borrow().append_to(&mut popup_menu_rc);
                                         // 用户选中的菜单项后将其附带到目标数据段中回传到上层应用。
                                         if ud_rc.data_type == DataType::Text {
                                             // 文字类型
                                             popup_menu_rc.set_callback({
                                                 let ud_rc_2 = ud_rc.clone();
                                                 let notifier_rc = notifier_rc.clone();
+                                                let extra_rc = extra_rc.clone();
                                                 move |menu| {
                                                     let selected_idx = menu.value();
-                                                    if selected_idx >= 0 {
+                                                    if selected_idx >= action_len as i32 {
+                                                        extra_rc.borrow_mut().run(selected_idx as usize - action_len, &ud_rc_2);
+                                                    } else if selected_idx >= 0 {
                                                         let mut ud = ud_rc_2.as_ref().clone();
                                                         if let Some(action) = &mut ud.action {
                                                             if let Some(item) = action.items.get(selected_idx as usize) {
@@ -535,9 +828,12 @@ impl RichReviewer {
                                             popup_menu_rc.set_callback({
                                                 let ud_rc_2 = ud_rc.clone();
                                                 let notifier_rc = notifier_rc.clone();
+                                                let extra_rc = extra_rc.clone();
                                                 move |menu| {
                                                     let selected_idx = menu.value();
-                                                    if selected_idx >= 0 {
+                                                    if selected_idx >= action_len as i32 {
+                                                        extra_rc.borrow_mut().run(selected_idx as usize - action_len, &ud_rc_2);
+                                                    } else if selected_idx >= 0 {
                                                         let mut ud = ud_rc_2.as_ref().clone();
                                                         if let Some(action) = &mut ud.action {
                                                             if let Some(item) = action.items.get(selected_idx as usize) {
@@ -553,20 +849,73 @@ impl RichReviewer {
 
                                         popup_menu_rc.popup();
                                     }
+                                } else if clickable {
+                                    handled = true;
+                                    if let Some(cb) = notifier_rc.write().as_mut() {
+                                        // 直接返回当前目标数据
+                                        cb.notify(CallbackData::Data(ud));
+                                    }
+                                }
+                            }
+                            if !handled {
+                                if blank_menu_rc.load(Relaxed) {
+                                    popup_blank_menu(buffer_rc.clone(), selected_pieces.clone(), copy_soft_wraps_rc.load(Relaxed), {
+                                        let mut panel = panel_rc.clone();
+                                        let buffer_rc = buffer_rc.clone();
+                                        let selection_rc = selection_rc.clone();
+                                        move || {
+                                            // 全选时选区从第一个数据段开始。
+                                            *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), 0);
+                                            panel.redraw();
+                                        }
+                                    });
                                 } else if let Some(cb) = notifier_rc.write().as_mut() {
-                                    // 直接返回当前目标数据
-                                    cb.notify(CallbackData::Data(ud));
+                                    let (app_x, app_y) = app::event_coords();
+                                    cb.notify(CallbackData::ContextMenu(app_x, app_y));
                                 }
                             }
                         } else if app::event_mouse_button() == MouseButton::Left {
                             if app::event_clicks() {
                                 // debug!("双击");
-                                select_paragraph(select_from_row, &mut push_from_point, buffer_rc.read().as_slice(), selected_pieces.clone());
+                                let layout = ColumnLayout::new(columns_rc.load(Relaxed), scroller.w(), scroller.h(), &padding_rc.read());
+                                select_paragraph(select_from_row, &mut push_from_point, buffer_rc.read().as_slice(), selected_pieces.clone(), &layout, copy_soft_wraps_rc.load(Relaxed));
+                                *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), select_from_row);
                                 scroller.set_damage(true);
-                            } else if let Some(ud) = target_opt {
-                                // 左键弹出提示信息
-                                // debug!("左键点击：{:?}", ud);
-                                if let Some(action) = &ud.action {
+                            } else if let Some(mut ud) = target_opt {
+                                let default_item = ud.action.as_ref().and_then(|action| {
+                                    action.default_item.and_then(|idx| action.items.get(idx).cloned())
+                                });
+                                if let Some(item) = default_item {
+                                    // 左键直接执行默认动作
+                                    if ud.data_type == DataType::Text {
+                                        if let Some(action) = &mut ud.action {
+                                            action.active.replace(item.cmd.clone());
+                                        }
+                                        if let Some(cb) = notifier_rc.write().as_mut() {
+                                            cb.notify(CallbackData::Data(ud));
+                                        }
+                                    } else {
+                                        let click_point = if let Some(v_bounds) = target_rd_v_bounds {
+                                            let (app_x, app_y) = app::event_coords();
+                                            let mut offset_y = scroller.yposition() - PANEL_PADDING;
+                                            // 处理数据相对位移
+                                            if let Some(first) = buffer_rc.read().first() {
+                                                offset_y += first.v_bounds.read().0;
+                                            }
+                                            if offset_y < 0 {offset_y = 0;}
+                                            let click_at_x = app_x - scroller.x() - v_bounds.2;
+                                            let click_at_y = app_y - scroller.y() + offset_y - v_bounds.0 + IMAGE_PADDING_V;
+                                            (click_at_x, click_at_y)
+                                        } else {
+                                            (0, 0)
+                                        };
+                                        if let Some(cb) = notifier_rc.write().as_mut() {
+                                            cb.notify(CallbackData::Image(ImageEventData::new(click_point, ud.image_src_url, ud.id, item.cmd.clone(), ud.image_file_path.clone(), (ud.image_target_width, ud.image_target_height))));
+                                        }
+                                    }
+                                } else if let Some(action) = &ud.action {
+                                    // 左键弹出提示信息
+                                    // debug!("左键点击：{:?}", ud);
                                     let mut popup_menu_rc = MenuButton::new(0, 0, 0, 0, None);
                                     popup_menu_rc.set_type(MenuButtonType::Popup1);
                                     if !action.items.is_empty() {
@@ -597,11 +946,59 @@ impl RichReviewer {
                     }
                     Event::Push => {
                         let (push_from_x, push_from_y) = app::event_coords();
+                        drag_out = None;
+                        // 上一次中键点击没有收到粘贴事件时不再等待，避免之后的粘贴被误报为PRIMARY选区内容。
+                        pasting_primary = false;
+                        drag_overflow_rc.store(0, Relaxed);
+                        if app::event_mouse_button() == MouseButton::Right {
+                            // 右键点击不改变选区，以便右键菜单复制选中内容。
+                            return true;
+                        }
+                        #[cfg(target_os = "linux")]
+                        if app::event_mouse_button() == MouseButton::Middle {
+                            // 中键点击不改变选区，读取PRIMARY选区后在随后的粘贴事件中汇报。
+                            if notifier_rc.read().is_some() {
+                                pasting_primary = true;
+                                app::paste_text2(&*scroller);
+                            }
+                            return true;
+                        }
+                        if app::event_mouse_button() == MouseButton::Left && app::is_event_shift() && push_anchor.is_some() {
+                            // 按住Shift键点击时，选择上次点击位置与当前位置之间的全部内容。
+                            let mut offset_y = scroller.yposition() - PANEL_PADDING;
+                            if let Some(first) = buffer_rc.read().first() {
+                                offset_y += first.v_bounds.read().0;
+                            }
+                            if offset_y < 0 {offset_y = 0;}
+                            let mut current_point = ClickPoint::new(push_from_x - scroller.x(), push_from_y + offset_y - scroller.y());
+                            current_point.align(scroller.w(), scroller.h(), offset_y, &padding_rc.read());
+                            follow_push_anchor(buffer_rc.read().as_slice(), &mut push_anchor, &mut push_from_point, &mut select_from_row);
+                            let layout = ColumnLayout::new(columns_rc.load(Relaxed), scroller.w(), scroller.h(), &padding_rc.read());
+                            update_selection_when_drag(push_from_point, select_from_row, &mut current_point, buffer_rc.read().as_slice(), selected_pieces.clone(), &mut scroller.as_base_widget(), &padding_rc.read(), &layout, copy_soft_wraps_rc.load(Relaxed));
+                            *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), select_from_row);
+                            shift_extended = true;
+                            return true;
+                        }
+                        if app::event_mouse_button() == MouseButton::Left {
+                            let mut offset_y = scroller.yposition() - PANEL_PADDING;
+                            if let Some(first) = buffer_rc.read().first() {
+                                offset_y += first.v_bounds.read().0;
+                            }
+                            let (data_x, data_y) = (push_from_x - scroller.x(), push_from_y + offset_y - scroller.y());
+                            if selection_contains(&selected_pieces.read(), data_x, data_y) {
+                                // 在选中的文字上按下鼠标时保留选区，准备拖出选中内容。
+                                drag_out = Some((selection_text(&selected_pieces.read(), copy_soft_wraps_rc.load(Relaxed)), true, (push_from_x, push_from_y)));
+                                return true;
+                            }
+                            drag_out = buffer_rc.read().iter().find_map(|rd| rd.image_drag_payload_at(data_x, data_y)).map(|path| (path, false, (push_from_x, push_from_y)));
+                        }
 
                         // debug!("清除选区");
                         clear_selected_pieces(selected_pieces.clone());
+                        selection_rc.write().take();
                         scroller.set_damage(true);
                         select_from_row = 0;
+                        push_anchor = None;
 
                         let (p_offset_x, p_offset_y) = (scroller.x(), scroller.y());
                         let mut offset_y = scroller.yposition() - PANEL_PADDING;
@@ -611,13 +1008,16 @@ impl RichReviewer {
                         }
                         push_from_point.x = push_from_x - p_offset_x;
                         push_from_point.y = push_from_y + offset_y - p_offset_y;
-                        push_from_point.align(scroller.width(), scroller.height(), offset_y);
+                        push_from_point.align(scroller.width(), scroller.height(), offset_y, &padding_rc.read());
 
                         // 尝试检测起始点击位置是否位于某个数据段内，可减少后续划选过程中的检测目标范围
                         let index_vec = (0..buffer_rc.read().len()).collect::<Vec<usize>>();
                         let rect = push_from_point.as_rect();
-                        if let Some(tr) = locate_target_rd(&mut push_from_point, rect, scroller.w(), buffer_rc.read().as_slice(), index_vec) {
+                        let layout = ColumnLayout::new(columns_rc.load(Relaxed), scroller.w(), scroller.h(), &padding_rc.read());
+                        if let Some(tr) = locate_target_rd(&mut push_from_point, rect, scroller.w(), buffer_rc.read().as_slice(), index_vec, &padding_rc.read(), &layout) {
                             select_from_row = tr.row;
+                            // 以数据段id记录起点，划选过程中加载分页数据时仍能找到起点。
+                            push_anchor = buffer_rc.read().get(select_from_row).map(|rd| (SelectionAnchor::new(rd, select_from_row, push_from_point.p_i, push_from_point.c_i), rd.v_bounds.read().0));
                         }
 
                         #[cfg(target_os = "linux")]
@@ -628,16 +1028,30 @@ impl RichReviewer {
                         return true;
                     }
                     Event::Drag => {
-                        let yp = scroller.yposition();
-                        let cy = app::event_y();
-                        let max_scroll = panel_rc.height() - scroller.height();
+                        if let Some((_, _, origin)) = drag_out.as_ref() {
+                            if !drag_out_started(*origin, app::event_coords()) {
+                                // 移动距离不足时仍视为点击。
+                                return true;
+                            }
+                        }
+                        if let Some((payload, _, _)) = drag_out.take() {
+                            // 拖动选中的文字或图片时，将其拖出组件。
+                            start_drag_out(&payload);
+                            return true;
+                        }
                         let (current_x, current_y) = app::event_coords();
 
-                        // 拖动时如果鼠标超出scroll组件边界，但滚动条未到达底部或顶部时，自动滚动内容。
-                        if cy > (scroller.y() + scroller.h()) && yp < max_scroll {
-                            scroller.scroll_to(0, min(yp + 10, max_scroll));
-                        } else if cy < scroller.y() && yp > 0 {
-                            scroller.scroll_to(0, max(yp - 10, 0));
+                        // 拖动时如果鼠标超出scroll组件上下边界，定时自动滚动内容并扩展选区，超出边界越远滚动越快。
+                        let overflow = if current_y > scroller.y() + scroller.h() {
+                            current_y - scroller.y() - scroller.h()
+                        } else if current_y < scroller.y() {
+                            current_y - scroller.y()
+                        } else {
+                            0
+                        };
+                        drag_overflow_rc.store(overflow, Relaxed);
+                        if overflow != 0 && !drag_scrolling_rc.swap(true, Relaxed) {
+                            Self::start_drag_scroll(scroller, &panel_rc, drag_overflow_rc.clone(), drag_scrolling_rc.clone());
                         }
 
                         let (p_offset_x, p_offset_y) = (scroller.x(), scroller.y());
@@ -653,7 +1067,9 @@ impl RichReviewer {
                             current_x - p_offset_x,
                             current_y + offset_y - p_offset_y
                         );
-                        current_point.align(scroller.w(), scroller.h(), offset_y);
+                        current_point.align(scroller.w(), scroller.h(), offset_y, &padding_rc.read());
+                        follow_push_anchor(data_buffer_slice, &mut push_anchor, &mut push_from_point, &mut select_from_row);
+                        let layout = ColumnLayout::new(columns_rc.load(Relaxed), scroller.w(), scroller.h(), &padding_rc.read());
                         update_selection_when_drag(
                             push_from_point,
                             select_from_row,
@@ -661,7 +1077,11 @@ impl RichReviewer {
                             data_buffer_slice,
                             selected_pieces.clone(),
                             &mut scroller.as_base_widget(),
+                            &padding_rc.read(),
+                            &layout,
+                            copy_soft_wraps_rc.load(Relaxed),
                         );
+                        *selection_rc.write() = selection_range(data_buffer_slice, select_from_row);
 
                         // selected = !selected_pieces.read().is_empty();
                         // debug!("拖选结果：{selected}");
@@ -671,41 +1091,47 @@ impl RichReviewer {
                         }
                         return true;
                     }
+                    Event::MouseWheel if app::is_event_ctrl() => {
+                        // 按住Ctrl键滚动鼠标滚轮时缩放字体，不再滚动内容。
+                        if app::event_inside_widget(scroller) {
+                            let new_size = zoom_font_size(text_size_rc.load(Relaxed), app::event_dy() == MouseWheel::Down);
+                            let zoomed = Self::apply_zoom(
+                                new_size,
+                                text_size_rc.clone(),
+                                buffer_rc.clone(),
+                                hidden_data_rc.clone(),
+                                &mut panel_rc.clone(),
+                                scroller.clone(),
+                                basic_char_rc.clone(),
+                                *padding_rc.read(),
+                                columns_rc.load(Relaxed),
+                            );
+                            if zoomed {
+                                scroller.set_damage(true);
+                                if let Some(cb) = notifier_rc.write().as_mut() {
+                                    cb.notify(CallbackData::Zoom(new_size));
+                                }
+                            }
+                            return true;
+                        }
+                    }
                     Event::MouseWheel => {
                         if app::event_inside_widget(scroller) {
-                            let mut id = 0i64;
+                            // 已经滚动到边缘时滚动位置不再变化，需要在这里补充检查是否请求相邻页。
+                            // 用户在边缘继续滚动时重新询问一次，以便取到之前没有更多数据的方向上新产生的数据。
+                            let distance = (prefetch_rc.read().screens * scroller.h() as f32) as i32;
                             if app::event_dy() == MouseWheel::Down {
                                 // 向上滚动
-                                if scroller.yposition() < (scroller.h() / 4) {
-                                    // debug!("请求前一页");
-                                    // 获取id与执行回调之间分开处理，避免buffer_rc的嵌套借用出现问题
-                                    if let Some(rd) = buffer_rc.read().first() {
-                                        id = rd.id;
-                                    }
-
-                                    if id != 0 {
-                                        if let Some(cb) = &mut *page_notifier_rc.write() {
-                                            // cb.notify(PageOptions::PrevPage(id));
-                                            Self::load_page(cb, PageOptions::PrevPage(id));
-                                        };
-                                    };
+                                if scroller.yposition() < distance {
+                                    prefetch_rc.write().retry(false);
+                                    Self::request_page(&prefetch_rc, &page_notifier_rc, &buffer_rc, false, scroller);
                                 }
                             } else if app::event_dy() == MouseWheel::Up {
                                 // 向下滚动
                                 if !Self::should_hide(scroller, &panel_rc) {
-                                    if scroller.yposition() > panel_rc.height() - scroller.h() - (scroller.h() / 4) {
-                                        // debug!("请求后一页");
-                                        // 获取id与执行回调之间分开处理，避免buffer_rc的嵌套借用出现问题
-                                        if let Some(rd) = buffer_rc.read().last() {
-                                            id = rd.id;
-                                        }
-
-                                        if id != 0 {
-                                            if let Some(cb) = &mut *page_notifier_rc.write() {
-                                                // cb.notify(PageOptions::NextPage(id, false));
-                                                Self::load_page(cb, PageOptions::NextPage(id));
-                                            };
-                                        }
+                                    if scroller.yposition() > panel_rc.height() - scroller.h() - distance {
+                                        prefetch_rc.write().retry(true);
+                                        Self::request_page(&prefetch_rc, &page_notifier_rc, &buffer_rc, true, scroller);
                                     }
                                 }
                             }
@@ -717,11 +1143,41 @@ impl RichReviewer {
             }
         });
 
+        // 滚动条、滚轮和按键改变滚动位置时都经过滚动条回调，在这里检查是否进入预取距离，而不是在绘制过程中检查。
+        scroller.scrollbar().set_callback({
+            let mut scroller_rc = scroller.clone();
+            let panel_rc = panel.clone();
+            let buffer_rc = data_buffer.clone();
+            let history_mode_rc = history_mode.clone();
+            let prefetch_rc = prefetch.clone();
+            let page_notifier_rc = page_notifier.clone();
+            move |scrollbar| {
+                if scroller_rc.was_deleted() {
+                    return;
+                }
+                let x = scroller_rc.xposition();
+                scroller_rc.scroll_to(x, scrollbar.value() as i32);
+                if history_mode_rc.load(Relaxed) {
+                    let max_y = max(panel_rc.height() - scroller_rc.height(), 0);
+                    let edge = prefetch_rc.read().near_edge(scroller_rc.yposition(), max_y, scroller_rc.height());
+                    if let Some(next) = edge {
+                        Self::request_page(&prefetch_rc, &page_notifier_rc, &buffer_rc, next, &scroller_rc);
+                    }
+                }
+            }
+        });
+
         Self {
             scroller, panel, data_buffer, background_color, visible_lines, clickable_data,
             reviewer_screen, notifier, page_notifier, search_string: search_str, search_results,
-            current_highlight_focus, blink_flag, history_mode, page_size, text_font, text_color,
-            text_size, piece_spacing, enable_blink, basic_char }
+            current_highlight_focus, search_notifier: Arc::new(RwLock::new(None)), remote_search: Arc::new(RwLock::new(None)), keywords: Arc::new(RwLock::new(vec![])), blink_flag, render_options, history_mode, page_size, text_font, text_color,
+            text_size, piece_spacing, enable_blink, blink_interval, rapid_blink_interval, basic_char, gutter_width, filter, hidden_data, hidden_anchors,
+            scroll_notifier, tooltip, context_menu_hook, blank_menu, last_scroll, ansi_palette: Arc::new(RwLock::new(None)),
+            follow_search_focus: Arc::new(AtomicBool::new(false)), following_focus: Arc::new(RwLock::new(None)),
+            line_height_factor: Arc::new(RwLock::new(LINE_HEIGHT_FACTOR)), paragraph_spacing: Arc::new(AtomicI32::new(0)),
+            padding, prefetch, columns, color_transform, disabled_style: Arc::new(RwLock::new(DisabledStyle::default())),
+            wrap_policy: Arc::new(RwLock::new(WrapPolicy::Anywhere)), hanging_indent: Arc::new(AtomicI32::new(0)),
+            wrap_cache: Arc::new(RwLock::new(WrapCache::new(DEFAULT_WRAP_CACHE_CAPACITY))), copy_soft_wraps, shared_buffer: true, timers, suspended }
     }
 
     fn should_hide(scroller: &Scroll, panel: &Widget) -> bool {
@@ -746,22 +1202,173 @@ impl RichReviewer {
     ///
     /// ```
     pub(crate) fn set_data(&mut self, mut data: Vec<RichData>) {
+        // 共享缓存中的数据属于主视图，不能被快照替换。
+        debug_assert!(!self.shared_buffer);
         // 更新回看数据
+        let gutter_width = self.gutter_width.load(Relaxed);
+        data.iter_mut().for_each(|rd| {
+            rd.gutter_width = gutter_width;
+            rd.detach_bounds();
+        });
+        self.hidden_data.write().clear();
+        self.hidden_anchors.write().clear();
+        self.prefetch.write().reset();
+        if let Some(filter) = self.filter.write().as_mut() {
+            let (visible, mut hidden) = partition_in_order(data, filter, None, &mut self.hidden_anchors.write());
+            data = visible;
+            self.hidden_data.write().append(&mut hidden);
+        }
         self.data_buffer.write().clear();
         self.data_buffer.write().append(&mut data);
+        if self.columns.load(Relaxed) > 1 {
+            // 快照数据的分片沿用主视图的单栏排版，分栏显示时需要重新排列。
+            self.relayout_data(|_| {});
+            return;
+        }
 
         let (scroller_width, scroller_height) = (self.panel.width(), self.scroller.height());
 
         // 设置新的窗口尺寸
-        let panel_height = Self::calc_panel_height(self.data_buffer.clone(), scroller_height);
+        let panel_height = Self::calc_panel_height(self.data_buffer.clone(), scroller_height, &self.padding.read());
         self.panel.resize(self.panel.x(), self.panel.y(), scroller_width, panel_height);
     }
 
 
+    /// 共享的数据缓存发生变化后，重新计算内容面板高度，并保持当前的滚动位置。
+    pub(crate) fn refresh_panel_height(&mut self) {
+        let (scroller_width, scroller_height) = (self.panel.width(), self.scroller.height());
+        let panel_height = Self::calc_panel_height(self.data_buffer.clone(), scroller_height, &self.padding.read());
+        if panel_height != self.panel.height() {
+            let scroll_y = self.scroller.yposition();
+            self.panel.resize(self.panel.x(), self.panel.y(), scroller_width, panel_height);
+            self.scroller.scroll_to(0, scroll_y);
+        }
+        self.follow_focus();
+        self.scroller.set_damage(true);
+    }
+
+    /// 新数据到达后重新居中显示正在跟随的查找目标。用户已滚动到其他位置，或目标数据段已被移出缓存时停止跟随。
+    fn follow_focus(&mut self) {
+        let Some((id, scroll_y)) = *self.following_focus.read() else {
+            return;
+        };
+        if self.scroller.yposition() != scroll_y {
+            self.following_focus.write().take();
+            return;
+        }
+        // 数据段id不保证有序，目标只会因缓存头部的数据被移除而前移，从原位置向前查找。
+        let found = {
+            let buffer = self.data_buffer.read();
+            let from = self.current_highlight_focus.read().map_or(buffer.len(), |(old_idx, _)| old_idx + 1).min(buffer.len());
+            buffer[..from].iter().rposition(|rd| rd.id == id).or_else(|| buffer.iter().position(|rd| rd.id == id))
+        };
+        let Some(rd_idx) = found else {
+            self.following_focus.write().take();
+            return;
+        };
+        // 缓存头部的数据被移除后，查找结果的索引随之前移。
+        let shift = self.current_highlight_focus.read().map_or(0, |(old_idx, _)| old_idx.saturating_sub(rd_idx));
+        if shift > 0 {
+            self.search_results.write().retain_mut(|idx| {
+                if *idx >= shift {
+                    *idx -= shift;
+                    true
+                } else {
+                    false
+                }
+            });
+            if let Some((idx, _)) = self.current_highlight_focus.write().as_mut() {
+                *idx = rd_idx;
+            }
+        }
+        if let Some((rd_idx, piece_idx)) = self.focused_piece() {
+            self.center_piece(rd_idx, piece_idx);
+        }
+    }
+
+    /// 设置是否在新数据到达时保持当前查找目标垂直居中，便于在分屏回看模式下持续观察实时数据流中反复出现的内容。
+    /// 每次定位到查找目标后开始跟随，用户滚动到其他位置或清除查找结果后停止跟随。
+    ///
+    /// # Arguments
+    ///
+    /// * `follow`: 是否跟随。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_follow_search_focus(true);
+    /// ```
+    pub fn set_follow_search_focus(&mut self, follow: bool) {
+        self.follow_search_focus.store(follow, Relaxed);
+        if !follow {
+            self.following_focus.write().take();
+        }
+    }
+
     pub fn scroll_to_bottom(&mut self) {
         self.scroller.scroll_to(0, self.panel.height() - self.scroller.height());
     }
 
+    pub fn scroll_to_top(&mut self) {
+        self.scroller.scroll_to(0, 0);
+    }
+
+    /// 设置滚动位置变化的回调函数，可用于将回顾区与外部的时间轴、缩略图等组件，或者另一个回顾区保持同步滚动。
+    ///
+    /// # Arguments
+    ///
+    /// * `cb`: 回调函数，参数为变化后的滚动位置信息。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// // 记录最新的滚动比例，由外部的时间轴组件在下一次刷新时读取。
+    /// let position = Arc::new(Mutex::new(0.0));
+    /// let position_rc = position.clone();
+    /// reviewer.on_scroll(move |info| {
+    ///     *position_rc.lock().unwrap() = info.percent;
+    /// });
+    /// ```
+    pub fn on_scroll<F>(&mut self, cb: F) where F: FnMut(ScrollInfo) + Sync + Send + 'static {
+        let notifier = ScrollNotifier::new(Arc::new(RwLock::new(Box::new(cb))));
+        self.scroll_notifier.write().replace(notifier);
+    }
+
+    /// 按照比例滚动到指定位置。通过该方法产生的滚动不会再次触发`on_scroll`回调，避免同步滚动的组件之间相互循环通知。
+    ///
+    /// # Arguments
+    ///
+    /// * `percent`: 滚动位置占可滚动高度的比例，取值范围0.0~1.0。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// // 滚动到正中间，不会触发`on_scroll`回调。
+    /// reviewer.sync_scroll_from(0.5);
+    /// ```
+    pub fn sync_scroll_from(&mut self, percent: f64) {
+        let max_y = max(self.panel.height() - self.scroller.height(), 0);
+        let y = (max_y as f64 * percent.clamp(0.0, 1.0)).round() as i32;
+        *self.last_scroll.write() = (y, max_y);
+        self.scroller.scroll_to(0, y);
+    }
+
 
     fn draw_offline(
         screen: Arc<RwLock<Offscreen>>,
@@ -771,7 +1378,10 @@ impl RichReviewer {
         data_buffer: Arc<RwLock<Vec<RichData>>>,
         background_color: Color,
         blink_flag: Arc<RwLock<BlinkState>>,
-        history_mode: bool
+        render_options: &RenderOptions,
+        history_mode: bool,
+        padding: Padding,
+        columns: usize,
         ) {
 
         screen.read().begin();
@@ -798,9 +1408,10 @@ impl RichReviewer {
             bottom_y += y;
         }
 
-        let offset_y = top_y - PADDING.top;
+        let offset_y = top_y - padding.top;
 
         // 填充背景色
+        let background_color = transform_color(background_color, false);
         draw_rect_fill(0, 0, window_width, window_height, background_color);
 
         let data = &*data_buffer.read();
@@ -821,6 +1432,10 @@ impl RichReviewer {
             }
 
             if begin_check_from_index && !rich_data.is_visible(top_y, bottom_y) {
+                if columns > 1 && rich_data.v_bounds.read().1 + window_height > top_y {
+                    // 分栏显示时同一页中右侧栏的数据排在左侧栏之后，左侧栏中仍可能有可见的数据。
+                    continue;
+                }
                 // 待绘制内容已经向上超出窗口顶部边界，可以停止处理前面的数据了。
                 from_index = total_len - seq;
                 break;
@@ -828,14 +1443,26 @@ impl RichReviewer {
         }
         // debug!("离线绘制， from_index:{from_index}, to_index:{to_index}");
         let mut need_blink = false;
+        let mut need_rapid_blink = false;
+        let mut gutter_width = 0;
+        let options = render_options.on_background(background_color);
         for (idx, rich_data) in data[from_index..to_index].iter().enumerate() {
             // debug!("回顾区离线绘制， idx:{idx}, type: {:?}, rich_data:{:?}", rich_data.data_type, rich_data.text);
-            rich_data.draw(offset_y, &*blink_flag.read());
+            rich_data.draw(offset_y, &blink_flag.read(), &options);
+            if rich_data.gutter_width > 0 {
+                let data_idx = idx + from_index;
+                let line_start = data[..data_idx].iter().rev().find(|d| d.data_type != DataType::Marker).map(|d| d.text.ends_with('\n')).unwrap_or(true);
+                rich_data.draw_line_numbers(offset_y, line_start, LINE_NUMBER_COLOR);
+                gutter_width = rich_data.gutter_width;
+            }
 
-            if !need_blink && (rich_data.blink || rich_data.search_highlight_pos.is_some()) {
+            if !need_blink && ((rich_data.blink && rich_data.blink_speed == BlinkSpeed::Slow) || rich_data.search_highlight_pos.is_some()) {
                 // debug!("需要闪烁");
                 need_blink = true;
             }
+            if rich_data.blink && rich_data.blink_speed == BlinkSpeed::Rapid {
+                need_rapid_blink = true;
+            }
 
             for piece in rich_data.line_pieces.iter() {
                 let piece = &*piece.read();
@@ -848,6 +1475,12 @@ impl RichReviewer {
             }
         }
 
+        if gutter_width > 0 {
+            // 绘制行号栏分隔线
+            set_draw_color(LINE_NUMBER_COLOR);
+            draw_line(padding.left + gutter_width - IMAGE_PADDING_H, 0, padding.left + gutter_width - IMAGE_PADDING_H, drawable_height);
+        }
+
         /*
         绘制分界线
          */
@@ -858,11 +1491,11 @@ impl RichReviewer {
             draw_xyline(0, drawable_height + (PANEL_PADDING / 2), scroller_x + window_width);
             set_line_style(LineStyle::Solid, 1);
         } else {
-            draw_rect_fill(0, scroller.h() - PADDING.bottom, window_width, PADDING.bottom, background_color);
+            draw_rect_fill(0, scroller.h() - padding.bottom, window_width, padding.bottom, background_color);
         }
 
         // 填充顶部边界空白
-        draw_rect_fill(0, 0, window_width, PADDING.top, background_color);
+        draw_rect_fill(0, 0, window_width, padding.top, background_color);
 
         screen.read().end();
 
@@ -872,6 +1505,7 @@ impl RichReviewer {
         } else {
             blink_flag.write().off();
         }
+        blink_flag.write().set_rapid(need_rapid_blink);
     }
 
     /// 设置互动消息发送器。
@@ -909,7 +1543,78 @@ impl RichReviewer {
         self.page_notifier.write().replace(call_page);
     }
 
+    /// 设置后台查找回调函数。历史模式下已载入的数据中没有更多查找目标时，回顾区通过该回调请求应用程序在自己的数据存储中继续查找，
+    /// 应用程序应在查找完成后调用`load_search_result`。回调在`fltk`主线程中执行，耗时的查找应在后台线程中完成，再回到主线程提供结果。
+    ///
+    /// # Arguments
+    ///
+    /// * `cb`: 回调函数，参数为查找请求。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None).lazy_page_mode();
+    /// let requests = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+    /// reviewer.set_search_notifier({
+    ///     let requests = requests.clone();
+    ///     move |request| requests.lock().unwrap().push(request)
+    /// });
+    /// ```
+    pub fn set_search_notifier<F>(&mut self, cb: F) where F: FnMut(SearchRequest) + Sync + Send + 'static {
+        let call_search = CallSearch::new(Arc::new(RwLock::new(Box::new(cb))));
+        self.search_notifier.write().replace(call_search);
+    }
+
+    /// 提供后台查找的结果。目标数据已载入时直接高亮，否则通过分页回调请求`PageOptions::At`跳转到目标数据所在页，
+    /// 载入后重新查找并高亮该数据中的目标。没有更多目标时回到已载入数据的另一端继续循环查找。
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: 下一个目标所在数据的id，为空表示没有更多目标。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None).lazy_page_mode();
+    /// // 应用程序收到查找请求并在自己的数据存储中查找完成后，在主线程中提供结果，这里表示没有更多目标。
+    /// reviewer.load_search_result(None);
+    /// ```
+    pub fn load_search_result(&mut self, target: Option<i64>) {
+        let Some((forward, _)) = self.remote_search.write().take() else {
+            return;
+        };
+        match target {
+            Some(id) => {
+                let rd_idx = self.data_buffer.read().iter().position(|rd| rd.id == id);
+                if let Some(rd_idx) = rd_idx {
+                    self.focus_search_result(rd_idx, forward);
+                } else {
+                    self.remote_search.write().replace((forward, Some(id)));
+                    self.request_page_at(id);
+                }
+            }
+            None => {
+                if forward {
+                    self.highlight_next();
+                } else {
+                    self.highlight_previous();
+                }
+                self.show_search_results();
+            }
+        }
+        self.scroller.set_damage(true);
+    }
+
     fn draw_offline2(&self) {
+        let _transform = ColorTransformScope::enter(&self.color_transform.read());
         Self::draw_offline(
             self.reviewer_screen.clone(),
             &self.scroller,
@@ -918,7 +1623,10 @@ impl RichReviewer {
             self.data_buffer.clone(),
             *self.background_color.read(),
             self.blink_flag.clone(),
-            self.history_mode.load(Relaxed)
+            &self.render_options.read(),
+            self.history_mode.load(Relaxed),
+            *self.padding.read(),
+            self.columns.load(Relaxed),
         );
     }
 
@@ -946,18 +1654,13 @@ impl RichReviewer {
             return;
         }
 
-        let mut find_out = false;
-        let mut target_idx = 0;
-        if let Ok(idx) = self.data_buffer.read().binary_search_by_key(&options.id, |rd| rd.id) {
-            target_idx = idx;
-            find_out = true;
-        }
-
+        // 高亮规则拆分出的片段记录了原ID，一并更新。
+        let keywords = self.keywords.read().clone();
+        let find_out = update_origin_data(&mut self.data_buffer.write(), &options, &keywords);
         if find_out {
-            if let Some(rd) = self.data_buffer.write().get_mut(target_idx) {
-                update_data_properties(options, rd);
-            }
             self.draw_offline2();
+        } else {
+            update_origin_data(&mut self.hidden_data.write(), &options, &keywords);
         }
     }
 
@@ -966,19 +1669,19 @@ impl RichReviewer {
             return;
         }
 
-        let mut find_out = false;
-        let mut target_idx = 0;
-        if let Ok(idx) = self.data_buffer.read().binary_search_by_key(&id, |rd| rd.id) {
-            target_idx = idx;
-            find_out = true;
-        }
+        let find_out = {
+            let mut buffer = self.data_buffer.write();
+            let range = id_range(&buffer, id);
+            buffer[range.clone()].iter_mut().for_each(disable_data);
+            !range.is_empty()
+        };
 
         if find_out {
-            if let Some(rd) = self.data_buffer.write().get_mut(target_idx) {
-                disable_data(rd);
-            }
-
             self.draw_offline2();
+        } else {
+            let mut hidden = self.hidden_data.write();
+            let range = id_range(&hidden, id);
+            hidden[range].iter_mut().for_each(disable_data);
         }
     }
 
@@ -986,7 +1689,7 @@ impl RichReviewer {
     ///
     /// # Arguments
     ///
-    /// * `search_str`: 目标字符串。
+    /// * `search_str`: 目标字符串，为空时清除查找结果。
     /// * `forward`: true正向，false反向查找。
     ///
     /// returns: bool 是否找到目标。
@@ -994,9 +1697,19 @@ impl RichReviewer {
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::{PageOptions, UserData};
     ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None).lazy_page_mode();
+    /// reviewer.load_page_now(vec![UserData::new_text("连接服务器失败".to_string())], PageOptions::NextPage(0));
+    /// assert!(reviewer.search_str(Some("失败".to_string()), true));
+    /// reviewer.search_str(None, true);
     /// ```
-    pub(crate) fn search_str(&mut self, search_str: String, forward: bool) -> bool {
+    pub fn search_str(&mut self, search_str: Option<String>, forward: bool) -> bool {
+        let Some(search_str) = search_str.filter(|s| !s.is_empty()) else {
+            self.clear_search_results();
+            return false;
+        };
         let old_str_opt = self.search_string.read().as_ref().map(|s| s.clone());
         let find_out = if let Some(old) = old_str_opt {
             if old.eq(&search_str) {
@@ -1010,6 +1723,10 @@ impl RichReviewer {
         };
 
         if find_out {
+            // 已载入的数据中没有更多目标时先请求后台查找，没有后台查找回调时循环到另一端。
+            if self.at_search_edge(forward) && self.request_remote_search(forward) {
+                return true;
+            }
             // debug!("找到目标字符串，定位并显示");
             if forward {
                 self.highlight_next();
@@ -1017,29 +1734,123 @@ impl RichReviewer {
                 self.highlight_previous();
             }
             self.show_search_results();
+        } else {
+            self.request_remote_search(forward);
         }
         find_out
     }
 
-    /// 倒序(从下向上，从右向左)查找高亮下一个目标。
-    fn highlight_previous(&mut self) {
-        // debug!("查询目标：\"{:?}\"，已知的目标数据段：{:?}", self.search_string, self.search_results);
-        let oo = *self.current_highlight_focus.read();
-        if let Some((old_rd_idx, old_result_idx)) = oo {
-            // debug!("上一次定位的数据段索引：{}，目标编号：{}", old_rd_idx, old_result_idx);
-            let (mut scroll_to_next, mut next_rd_pos) = (false, 0);
-            if let Some(rd) = self.data_buffer.write().get_mut(old_rd_idx) {
-                if let Some(ref result_pos_vec) = rd.search_result_positions {
-                    let next_result_idx = old_result_idx + 1;
-                    if result_pos_vec.get(next_result_idx).is_some() {
-                        // 在当前数据段中定位到下一个目标位置
-                        // debug!("在当前数据段中定位到下一个目标位置");
-                        self.current_highlight_focus.write().replace((old_rd_idx, next_result_idx));
-                        rd.search_highlight_pos.replace(next_result_idx);
-                    } else {
-                        // 在当前数据段中已经没有更多目标，则跳到下一个数据段；如果没有更多数据段则跳到第一个数据段。
-                        // debug!("在当前数据段中已经没有更多目标，则跳到下一个数据段；如果没有更多数据段则跳到第一个数据段。");
-                        let next_idx  = if let Ok(old_idx) = self.binary_search_with_desc_order(old_rd_idx) {
+    /// 当前高亮的目标是否已是已载入数据中指定方向上的最后一个目标。
+    fn at_search_edge(&self, forward: bool) -> bool {
+        let Some((rd_idx, result_idx)) = *self.current_highlight_focus.read() else {
+            return false;
+        };
+        let search_results = self.search_results.read();
+        if forward {
+            search_results.first() == Some(&rd_idx) && result_idx == 0
+        } else {
+            let count = self.data_buffer.read().get(rd_idx).and_then(|rd| rd.search_result_positions.as_ref().map(Vec::len)).unwrap_or(0);
+            search_results.last() == Some(&rd_idx) && result_idx + 1 >= count
+        }
+    }
+
+    /// 通过后台查找回调请求在已载入数据之外查找，回调在下一个事件循环中执行。
+    ///
+    /// # Arguments
+    ///
+    /// * `forward`: true向更新的数据查找，false向更早的数据查找。
+    ///
+    /// returns: bool 是否已有或新发出了后台查找请求。
+    fn request_remote_search(&mut self, forward: bool) -> bool {
+        if self.search_notifier.read().is_none() {
+            return false;
+        }
+        if self.remote_search.read().is_some() {
+            return true;
+        }
+        let Some(pattern) = self.search_string.read().clone() else {
+            return false;
+        };
+        let from_id = {
+            let buffer = self.data_buffer.read();
+            let rd = if forward { buffer.last() } else { buffer.first() };
+            rd.map_or(0, |rd| rd.id)
+        };
+        self.remote_search.write().replace((forward, None));
+        let search_notifier = self.search_notifier.clone();
+        awake_callback(move || {
+            if let Some(cs) = &mut *search_notifier.write() {
+                cs.notify(SearchRequest { pattern: pattern.clone(), from_id, forward });
+            }
+        });
+        true
+    }
+
+    /// 高亮指定数据段中的第一个(正向)或最后一个(反向)目标，并显示在可见区域。
+    fn focus_search_result(&mut self, rd_idx: usize, forward: bool) {
+        let old = self.current_highlight_focus.write().take();
+        let pos = {
+            let mut buffer = self.data_buffer.write();
+            if let Some((old_rd_idx, _)) = old {
+                if let Some(rd) = buffer.get_mut(old_rd_idx) {
+                    rd.search_highlight_pos = None;
+                }
+            }
+            let Some(rd) = buffer.get_mut(rd_idx) else {
+                return;
+            };
+            let Some(len) = rd.search_result_positions.as_ref().map(Vec::len) else {
+                return;
+            };
+            let pos = if forward { len - 1 } else { 0 };
+            rd.search_highlight_pos = Some(pos);
+            pos
+        };
+        self.current_highlight_focus.write().replace((rd_idx, pos));
+        self.show_search_results();
+    }
+
+    /// 跳转到后台查找的目标数据所在页后，在新载入的数据中重新查找并高亮目标。
+    fn resume_remote_search(&mut self, target: i64) {
+        let pending = *self.remote_search.read();
+        let Some((forward, Some(id))) = pending else {
+            return;
+        };
+        if id != target {
+            return;
+        }
+        let Some(pattern) = self.search_string.read().clone() else {
+            return;
+        };
+        // 跳转时已载入的数据被全部替换，旧的查找结果索引已失效。
+        self.search_results.write().clear();
+        *self.current_highlight_focus.write() = None;
+        self._search_target(pattern);
+        let rd_idx = self.data_buffer.read().iter().position(|rd| rd.id == id);
+        if let Some(rd_idx) = rd_idx {
+            self.focus_search_result(rd_idx, forward);
+        }
+    }
+
+    /// 倒序(从下向上，从右向左)查找高亮下一个目标。
+    fn highlight_previous(&mut self) {
+        // debug!("查询目标：\"{:?}\"，已知的目标数据段：{:?}", self.search_string, self.search_results);
+        let oo = *self.current_highlight_focus.read();
+        if let Some((old_rd_idx, old_result_idx)) = oo {
+            // debug!("上一次定位的数据段索引：{}，目标编号：{}", old_rd_idx, old_result_idx);
+            let (mut scroll_to_next, mut next_rd_pos) = (false, 0);
+            if let Some(rd) = self.data_buffer.write().get_mut(old_rd_idx) {
+                if let Some(ref result_pos_vec) = rd.search_result_positions {
+                    let next_result_idx = old_result_idx + 1;
+                    if result_pos_vec.get(next_result_idx).is_some() {
+                        // 在当前数据段中定位到下一个目标位置
+                        // debug!("在当前数据段中定位到下一个目标位置");
+                        self.current_highlight_focus.write().replace((old_rd_idx, next_result_idx));
+                        rd.search_highlight_pos.replace(next_result_idx);
+                    } else {
+                        // 在当前数据段中已经没有更多目标，则跳到下一个数据段；如果没有更多数据段则跳到第一个数据段。
+                        // debug!("在当前数据段中已经没有更多目标，则跳到下一个数据段；如果没有更多数据段则跳到第一个数据段。");
+                        let next_idx  = if let Ok(old_idx) = self.binary_search_with_desc_order(old_rd_idx) {
                             old_idx + 1
                         } else {
                             0
@@ -1181,18 +1992,13 @@ impl RichReviewer {
         self._clear_search_results();
         let s = search_str.as_str();
 
-        let len = s.chars().count();
         {
             let sr = &mut *self.search_results.write();
             for (idx, rd) in self.data_buffer.write().iter_mut().enumerate() {
                 if rd.text.contains(s) {
                     find_out = true;
                     sr.push(idx);
-                    let mut s_idx_vec: Vec<(usize, usize)> = vec![];
-                    rd.text.rmatch_indices(s).for_each(|(s_idx, _)| {
-                        let chars = rd.text[0..s_idx].chars().count();
-                        s_idx_vec.push((chars, chars + len))
-                    });
+                    let s_idx_vec = search_positions(&rd.text, s);
                     if !s_idx_vec.is_empty() {
                         rd.search_result_positions = Some(s_idx_vec);
                     }
@@ -1218,10 +2024,12 @@ impl RichReviewer {
         });
         self.search_results.write().clear();
         *self.current_highlight_focus.write() = None;
+        self.following_focus.write().take();
+        self.remote_search.write().take();
     }
 
     /// 清除查询缓存，并刷新界面。
-    pub(crate) fn clear_search_results(&mut self) {
+    pub fn clear_search_results(&mut self) {
         self._clear_search_results();
         *self.search_string.write() = None;
         self.scroller.set_damage(true);
@@ -1229,28 +2037,73 @@ impl RichReviewer {
 
     /// 定位到下一个查询目标并显示在可见区域。
     fn show_search_results(&mut self) {
-        let rr = *self.current_highlight_focus.read();
-        if let Some((rd_idx, result_idx)) = rr {
-            let mut piece_idx = 0;
-            if let Some(rd) = self.data_buffer.read().get(rd_idx) {
-                if let Some(s) = self.search_string.read().as_ref() {
-                    // debug!("正向定位到第{}个目标", result_idx);
-                    if let Some((pos, _)) =  rd.text.rmatch_indices(s).nth(result_idx) {
-                        let mut processed_len = 0usize;
-                        for (i, piece_rc) in rd.line_pieces.iter().enumerate() {
-                            let piece = &*piece_rc.read();
-                            let pl = piece.line.len();
-                            if pos >= processed_len && pos < processed_len + pl {
-                                piece_idx = i;
-                                break;
-                            }
-                            processed_len += pl;
+        if let Some((rd_idx, piece_idx)) = self.focused_piece() {
+            // debug!("当前定位的数据段索引：{}，位于分片{}内", rd_idx, piece_idx);
+            if self.follow_search_focus.load(Relaxed) {
+                self.center_piece(rd_idx, piece_idx);
+            } else {
+                self.show_piece(rd_idx, piece_idx);
+            }
+        }
+    }
+
+    /// 获取当前查找目标所在的数据段索引和分片索引。
+    fn focused_piece(&self) -> Option<(usize, usize)> {
+        let (rd_idx, result_idx) = (*self.current_highlight_focus.read())?;
+        let mut piece_idx = 0;
+        if let Some(rd) = self.data_buffer.read().get(rd_idx) {
+            if let Some(s) = self.search_string.read().as_ref() {
+                // debug!("正向定位到第{}个目标", result_idx);
+                if let Some((pos, _)) =  rd.text.rmatch_indices(s).nth(result_idx) {
+                    let mut processed_len = 0usize;
+                    for (i, piece_rc) in rd.line_pieces.iter().enumerate() {
+                        let piece = &*piece_rc.read();
+                        let pl = piece.line.len();
+                        if pos >= processed_len && pos < processed_len + pl {
+                            piece_idx = i;
+                            break;
                         }
+                        processed_len += pl;
                     }
                 }
             }
-            // debug!("当前定位的数据段索引：{}，目标顺序：{}，位于分片{}内", rd_idx, result_idx, piece_idx);
-            self.show_piece(rd_idx, piece_idx);
+        }
+        Some((rd_idx, piece_idx))
+    }
+
+    /// 滚动显示区域，使指定数据段下的数据分片垂直居中，并记录跟随状态。
+    ///
+    /// # Arguments
+    ///
+    /// * `rd_idx`: 数据段索引。
+    /// * `piece_idx`: 分片索引。
+    ///
+    /// returns: ()
+    fn center_piece(&mut self, rd_idx: usize, piece_idx: usize) {
+        let mut offset_y = 0;
+        if let Some(rd) = self.data_buffer.read().first() {
+            offset_y = rd.v_bounds.read().0;
+        }
+
+        let target = self.data_buffer.read().get(rd_idx).and_then(|rd| {
+            rd.line_pieces.get(piece_idx).map(|piece_rc| {
+                let piece = &*piece_rc.read();
+                (rd.id, piece.y + piece.h / 2)
+            })
+        });
+        if let Some((id, center_y)) = target {
+            let max_scroll = max(self.panel.h() - self.scroller.h(), 0);
+            let layout = self.column_layout();
+            let scroll_to_y = if layout.is_flowing() {
+                // 分栏显示时滚动到分片所在页的页首，使各栏完整可见。
+                min(max(layout.page_top(center_y) - offset_y, 0), max_scroll)
+            } else {
+                min(max(center_y - offset_y + self.padding.read().top - self.scroller.h() / 2, 0), max_scroll)
+            };
+            if scroll_to_y != self.scroller.yposition() {
+                self.scroller.scroll_to(0, scroll_to_y);
+            }
+            self.following_focus.write().replace((id, self.scroller.yposition()));
         }
     }
 
@@ -1281,8 +2134,16 @@ impl RichReviewer {
                     let piece = &*piece_rc.read();
                     // debug!("piece.top_y: {}, panel_height: {}, scroller.yposition: {}, piece.line: {}", piece.top_y, self.panel.h(), self.scroller.yposition(), piece.line);
                     let scroller_y = self.scroller.yposition();
-                    if piece.y < scroller_y || piece.y + piece.h >= scroller_y + self.scroller.h() {
-                        let mut scroll_to_y = piece.y - self.scroller.h() + piece.h * 2 + PADDING.top + 3 - offset_y;
+                    let layout = self.column_layout();
+                    if layout.is_flowing() {
+                        // 分栏显示时滚动到分片所在页的页首。
+                        let max_scroll = max(self.panel.h() - self.scroller.h(), 0);
+                        let scroll_to_y = min(max(layout.page_top(piece.y) - offset_y, 0), max_scroll);
+                        if scroll_to_y != scroller_y {
+                            self.scroller.scroll_to(0, scroll_to_y);
+                        }
+                    } else if piece.y < scroller_y || piece.y + piece.h >= scroller_y + self.scroller.h() {
+                        let mut scroll_to_y = piece.y - self.scroller.h() + piece.h * 2 + self.padding.read().top + 3 - offset_y;
                         if scroll_to_y < 0 {
                             scroll_to_y = 0;
                         } else if scroll_to_y > self.panel.h() - self.scroller.h() {
@@ -1331,8 +2192,18 @@ impl RichReviewer {
     /// ```
     pub fn load_page_now(&mut self, user_data_page: Vec<UserData>, direction: PageOptions) {
         // debug!("已载入页数据");
-        let window_width = self.panel.width();
-        let drawable_max_width = window_width - PADDING.left - PADDING.right;
+        let empty_page = user_data_page.is_empty();
+        {
+            let mut prefetch = self.prefetch.write();
+            prefetch.complete();
+            prefetch.record_page(&direction, empty_page);
+        }
+        if let PageOptions::At(_) = direction {
+            self.hidden_data.write().clear();
+            self.hidden_anchors.write().clear();
+        }
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
 
         let mut page_buffer = Vec::<RichData>::new();
         for ud in user_data_page {
@@ -1340,21 +2211,57 @@ impl RichReviewer {
             let default_font_color = !ud.custom_font_color;
             let mut rich_data: RichData = ud.into();
             rich_data.set_piece_spacing(self.piece_spacing.load(Relaxed));
+            rich_data.gutter_width = self.gutter_width.load(Relaxed);
+            rich_data.line_height_factor = *self.line_height_factor.read();
+            rich_data.paragraph_spacing = self.paragraph_spacing.load(Relaxed);
+            rich_data.disabled_style = *self.disabled_style.read();
+            rich_data.wrap_policy = *self.wrap_policy.read();
+            rich_data.hanging_indent = self.hanging_indent.load(Relaxed);
+            rich_data.wrap_cache = Some(self.wrap_cache.clone());
+            rich_data.padding_left = padding.left;
             if default_font_text {
-                rich_data.font = *self.text_font.read();
+                rich_data.font = if rich_data.italic { italic_font(*self.text_font.read()) } else { *self.text_font.read() };
                 rich_data.font_size = self.text_size.load(Relaxed);
             }
             if default_font_color {
                 rich_data.fg_color = *self.text_color.read();
             }
+            if let Some(palette) = self.ansi_palette.read().as_ref() {
+                rich_data.apply_palette(palette);
+            }
+            rich_data.apply_keywords(&self.keywords.read());
             page_buffer.push(rich_data);
         }
 
-        // 在尾部或头部添加页数据
+        if let Some(filter) = self.filter.write().as_mut() {
+            // 向后翻页的隐藏数据接续在已有可见数据之后，向前翻页的则位于最前面。
+            let prev = match direction {
+                PageOptions::NextPage(_) => self.data_buffer.read().last().map(|rd| rd.id),
+                _ => None,
+            };
+            let (visible, mut hidden) = partition_in_order(page_buffer, filter, prev, &mut self.hidden_anchors.write());
+            page_buffer = visible;
+            let mut hidden_data = self.hidden_data.write();
+            if let PageOptions::PrevPage(_) = direction {
+                hidden.append(&mut hidden_data);
+            }
+            hidden_data.append(&mut hidden);
+        }
+
+        // 向前翻页时记录当前可见的第一行，头部插入数据后保持其在屏幕上的位置不变。
+        let anchor = match direction {
+            PageOptions::PrevPage(_) => self.scroll_anchor(),
+            _ => None,
+        };
+
+        // 在尾部或头部添加页数据，跳转时替换全部数据
         match direction {
             PageOptions::NextPage(_) => {
                 self.data_buffer.write().append(&mut page_buffer);
             }
+            PageOptions::At(_) => {
+                *self.data_buffer.write() = page_buffer;
+            }
             PageOptions::PrevPage(_) => {
                 let mut buffer = self.data_buffer.write();
                 buffer.reverse();
@@ -1365,6 +2272,13 @@ impl RichReviewer {
         }
         // debug!("缓存数据已变化");
 
+        // 历史数据按载入顺序重新编排行号
+        let mut line_no = 1;
+        for rd in self.data_buffer.write().iter_mut() {
+            rd.line_no = line_no;
+            line_no += rd.text.matches('\n').count();
+        }
+
         // 重新计算数据绘制坐标，并检测是否需要继续补充页数据。
         let (need_more, panel_height) = Self::recalculate_data_buffer_position(
             self.data_buffer.clone(),
@@ -1372,8 +2286,13 @@ impl RichReviewer {
             &mut self.panel,
             self.scroller.clone(),
             self.basic_char.clone(),
-            self.text_size.clone());
-        if need_more {
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        if let Some(anchor) = anchor {
+            self.restore_scroll_anchor(anchor);
+        }
+        if need_more && !empty_page {
             // debug!("需要更多数据");
             let load_more_fn = {
                 let buffer_rc = self.data_buffer.clone();
@@ -1388,7 +2307,7 @@ impl RichReviewer {
                         // debug!("执行回调");
                         if let Some(cp) = &mut *page_notifier_rc.write() {
                             match dir {
-                                PageOptions::NextPage(_) => {
+                                PageOptions::NextPage(_) | PageOptions::At(_) => {
                                     cp.notify(PageOptions::NextPage(id));
                                 }
                                 PageOptions::PrevPage(_) => {
@@ -1415,6 +2334,7 @@ impl RichReviewer {
                             let mut panel_rc = self.panel.clone();
                             let basic_char_rc = self.basic_char.clone();
                             let text_size_rc = self.text_size.clone();
+                            let columns = self.columns.load(Relaxed);
                             move || {
                                 let mut last_height = 0;
                                 {
@@ -1428,7 +2348,7 @@ impl RichReviewer {
                                     buffer.reverse();
                                 }
 
-                                Self::recalculate_data_buffer_position(buffer_rc.clone(), drawable_max_width, &mut panel_rc, scroll_rc.clone(), basic_char_rc.clone(), text_size_rc.clone());
+                                Self::recalculate_data_buffer_position(buffer_rc.clone(), drawable_max_width, &mut panel_rc, scroll_rc.clone(), basic_char_rc.clone(), text_size_rc.clone(), padding, columns);
                                 panel_rc.set_damage(true);
                                 // debug!("清除远端数据完成！");
 
@@ -1450,24 +2370,20 @@ impl RichReviewer {
                             let mut panel_rc = self.panel.clone();
                             let basic_char_rc = self.basic_char.clone();
                             let text_size_rc = self.text_size.clone();
+                            let columns = self.columns.load(Relaxed);
                             move || {
-                                let mut last_height = 0;
                                 {
-                                    let len = buffer_rc.read().len();
                                     let mut buffer = buffer_rc.write();
-                                    if let Some(rd) = buffer.get(page_size - 1) {
-                                        last_height = rd.v_bounds.read().1
-                                    }
-                                    // buffer.reverse();
+                                    let len = buffer.len();
                                     buffer.truncate(len - page_size);
-                                    // buffer.reverse();
                                 }
 
-                                Self::recalculate_data_buffer_position(buffer_rc.clone(), drawable_max_width, &mut panel_rc, scroll_rc.clone(), basic_char_rc.clone(), text_size_rc.clone());
+                                Self::recalculate_data_buffer_position(buffer_rc.clone(), drawable_max_width, &mut panel_rc, scroll_rc.clone(), basic_char_rc.clone(), text_size_rc.clone(), padding, columns);
                                 panel_rc.set_damage(true);
                                 // debug!("清除远端数据完成！");
 
-                                Self::scroll_page(panel_rc.clone(), scroll_rc.clone(), (true, -last_height));
+                                // 移除的是尾部数据，可见位置之上的内容不变，只需修正超出范围的滚动位置。
+                                Self::scroll_page(panel_rc.clone(), scroll_rc.clone(), (false, 0));
                             }
                         })
                     } else {
@@ -1475,14 +2391,24 @@ impl RichReviewer {
                         self.panel.set_damage(true);
                     }
                 }
+                PageOptions::At(_) => {
+                    self.panel.set_damage(true);
+                }
             }
 
         }
+        if let PageOptions::At(target) = direction {
+            self.scroll_to_id(target);
+            self.resume_remote_search(target);
+        }
     }
 
 
     pub fn clear(&mut self) {
+        self.prefetch.write().reset();
         self.data_buffer.write().clear();
+        self.hidden_data.write().clear();
+        self.hidden_anchors.write().clear();
         self.panel.resize(self.scroller.x(), self.scroller.y(), self.panel.w(), self.scroller.h());
         self.scroller.set_damage(true);
     }
@@ -1504,13 +2430,257 @@ impl RichReviewer {
         self.page_size.store(new_size, Relaxed);
     }
 
+    /// 以历史模式打开大型日志文件。文件以内存映射的方式打开，各行的起始位置在后台线程中索引，
+    /// 浏览时通过懒加载分页机制按需解析当前页附近的行，适合浏览数百MB的会话记录。
+    /// 打开后会清空已有数据并替换分页回调，然后载入第一页。
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: 日志文件路径。
+    /// * `format`: 日志格式，负责将每一行转换为数据段。
+    ///
+    /// returns: Result<(), Error>
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use fltkrs_richdisplay::log_file::PlainText;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.open_log_file("logs/session.log", PlainText).unwrap();
+    /// ```
+    pub fn open_log_file<P, F>(&mut self, path: P, format: F) -> std::io::Result<()> where P: AsRef<std::path::Path>, F: LogFormat + 'static {
+        let log_file = Arc::new(LogFile::open(path, Box::new(format))?);
+        self.history_mode.store(true, Relaxed);
+        self.clear();
+        let key = Arc::as_ptr(&self.data_buffer) as usize;
+        LOG_TARGETS.with(|targets| {
+            let mut targets = targets.borrow_mut();
+            targets.retain(|_, rv| !rv.scroller.was_deleted());
+            targets.insert(key, self.clone());
+        });
+        self.set_page_notifier({
+            let log_file = log_file.clone();
+            move |opt| {
+                if let Some(mut reviewer) = Self::log_target(key) {
+                    let page = log_file.page(&opt, reviewer.page_size.load(Relaxed));
+                    if !page.is_empty() {
+                        reviewer.load_page_now(page, opt);
+                    }
+                }
+            }
+        });
+        self.set_search_notifier({
+            let log_file = log_file.clone();
+            move |request| {
+                // 逐行查找可能扫描整个文件，在后台线程中执行，完成后回到主线程提供结果。
+                let log_file = log_file.clone();
+                std::thread::spawn(move || {
+                    let target = log_file.search(&request);
+                    awake_callback(move || {
+                        if let Some(mut reviewer) = Self::log_target(key) {
+                            reviewer.load_search_result(target);
+                        }
+                    });
+                });
+            }
+        });
+        let first_page = log_file.lines(0, self.page_size.load(Relaxed));
+        if !first_page.is_empty() {
+            self.load_page_now(first_page, PageOptions::NextPage(0));
+        }
+        Ok(())
+    }
+
+
+    /// 查找已打开日志文件的回顾区，组件已被删除时移除记录。
+    fn log_target(key: usize) -> Option<RichReviewer> {
+        let target = LOG_TARGETS.with(|targets| targets.borrow().get(&key).cloned())?;
+        if target.scroller.was_deleted() {
+            LOG_TARGETS.with(|targets| targets.borrow_mut().remove(&key));
+            return None;
+        }
+        Some(target)
+    }
+
+    /// 请求跳转到指定位置，用于在历史模式下实现按日期或按ID定位，无需逐页翻动。
+    /// 分页回调会在下一个事件循环中收到`PageOptions::At`请求，应用程序应通过`load_page_now`提供包含目标记录的一页数据，
+    /// 回顾区替换已载入的全部数据后滚动到id与定位值相同的数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: 目标记录的id，或由应用程序自行解释的时间戳等定位值。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None).lazy_page_mode();
+    /// reviewer.request_page_at(1_700_000_000);
+    /// ```
+    pub fn request_page_at(&mut self, target: i64) {
+        self.prefetch.write().complete();
+        // 回调可能再次调用回顾区的方法，在下一个事件循环中执行，且不持有回调锁。
+        let page_notifier_rc = self.page_notifier.clone();
+        awake_callback(move || Self::notify_page(&page_notifier_rc, PageOptions::At(target)));
+    }
+
+    /// 复制分页回调后释放锁再执行，回调中可以再次请求分页或更换分页回调。
+    fn notify_page(page_notifier: &Arc<RwLock<Option<CallPage>>>, opt: PageOptions) {
+        let cb = page_notifier.read().clone();
+        if let Some(mut cb) = cb {
+            cb.notify(opt);
+        }
+    }
+
+    /// 将指定ID的数据段滚动到回顾区顶部，分栏显示时滚动到数据段所在页的页首，没有该数据段时不滚动。
+    fn scroll_to_id(&mut self, id: i64) {
+        self.restore_scroll_anchor((id, 0));
+    }
+
+    /// 按照当前的分栏数量和回顾区尺寸计算分栏参数。
+    fn column_layout(&self) -> ColumnLayout {
+        ColumnLayout::new(self.columns.load(Relaxed), self.scroller.w(), self.scroller.h(), &self.padding.read())
+    }
+
+    /// 获取当前可见的第一条数据的`id`，以及其顶部相对于可见区域顶部的偏移量。
+    fn scroll_anchor(&self) -> Option<(i64, i32)> {
+        let buffer = self.data_buffer.read();
+        let first_y = buffer.first()?.v_bounds.read().0;
+        let yposition = self.scroller.yposition();
+        buffer.iter().find_map(|rd| {
+            let (top, bottom, _, _) = *rd.v_bounds.read();
+            if bottom - first_y > yposition {
+                Some((rd.id, top - first_y - yposition))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// 滚动到使锚点数据回到记录时所在屏幕位置的地方。分栏显示时各栏重新排列后屏幕位置没有意义，改为滚动到锚点数据所在页的页首。
+    fn restore_scroll_anchor(&mut self, (id, offset): (i64, i32)) {
+        let layout = self.column_layout();
+        let target_y = {
+            let buffer = self.data_buffer.read();
+            let Some(first_y) = buffer.first().map(|rd| rd.v_bounds.read().0) else {
+                return;
+            };
+            match buffer.iter().find(|rd| rd.id == id) {
+                Some(rd) if layout.is_flowing() => layout.page_top(rd.v_bounds.read().0) - first_y,
+                Some(rd) => rd.v_bounds.read().0 - first_y - offset,
+                None => return,
+            }
+        };
+        let max_y = max(self.panel.h() - self.scroller.h(), 0);
+        self.scroller.scroll_to(0, target_y.clamp(0, max_y));
+        self.scroller.set_damage(true);
+    }
+
+    /// 设置懒加载模式下的分页预取参数。滚动位置距离已载入内容的边缘小于预取距离时提前请求相邻页，
+    /// 请求发出后等待页数据期间不再重复请求，并在回顾区顶部或底部显示加载提示。
+    ///
+    /// # Arguments
+    ///
+    /// * `screens`: 预取距离，以回顾区高度为单位，例如`2.0`表示距离边缘两屏以内时请求相邻页。默认为`0.25`。
+    /// * `timeout`: 请求超时时间，超时仍未通过`load_page_now`收到页数据时允许再次请求。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None).lazy_page_mode();
+    /// reviewer.set_prefetch(2.0, Duration::from_secs(5));
+    /// ```
+    pub fn set_prefetch(&mut self, screens: f32, timeout: Duration) {
+        let mut prefetch = self.prefetch.write();
+        prefetch.screens = screens.max(0.0);
+        prefetch.timeout = timeout;
+    }
+
+    /// 划选超出可视区域上下边界时启动自动滚动，每次滚动后以当前鼠标位置模拟一次拖动以扩展选区。鼠标回到可视区域或松开按键后停止。
+    ///
+    /// # Arguments
+    ///
+    /// * `scroller`: 回顾区滚动组件。
+    /// * `panel`: 内容面板。
+    /// * `overflow`: 鼠标超出上边界(负值)或下边界(正值)的距离。
+    /// * `scrolling`: 是否正在自动滚动。
+    ///
+    /// returns: ()
+    fn start_drag_scroll(scroller: &Scroll, panel: &Widget, overflow: Arc<AtomicI32>, scrolling: Arc<AtomicBool>) {
+        app::add_timeout3(DRAG_SCROLL_INTERVAL, {
+            let mut scroller_rc = scroller.clone();
+            let panel_rc = panel.clone();
+            move |handler| {
+                let step = drag_scroll_step(overflow.load(Relaxed));
+                if scroller_rc.was_deleted() || step == 0 {
+                    scrolling.store(false, Relaxed);
+                    app::remove_timeout3(handler);
+                    return;
+                }
+                let max_scroll = max(panel_rc.height() - scroller_rc.height(), 0);
+                let yp = scroller_rc.yposition();
+                let target = (yp + step).clamp(0, max_scroll);
+                if target != yp {
+                    scroller_rc.scroll_to(0, target);
+                    scroller_rc.handle_event(Event::Drag);
+                }
+                app::repeat_timeout3(DRAG_SCROLL_INTERVAL, handler);
+            }
+        });
+    }
 
-    fn load_page(callpage: &mut CallPage, opt: PageOptions) {
-        let task_id = LOAD_PAGE_TASK_ID.get_or_init(|| YitIdHelper::next_id());
-        if !throttle_check(*task_id, Duration::from_millis(500)) {
+    /// 请求相邻页。已有等待中的请求时忽略，回调在下一个事件循环中执行，避免在绘制或事件处理过程中修改数据缓存。
+    ///
+    /// # Arguments
+    ///
+    /// * `prefetch`: 预取状态。
+    /// * `page_notifier`: 分页回调。
+    /// * `data_buffer`: 数据缓存，以首尾数据段的ID作为请求参数。
+    /// * `next`: 是否请求下一页。
+    /// * `scroller`: 回顾区滚动容器，用于刷新加载提示。
+    ///
+    /// returns: ()
+    fn request_page(prefetch: &Arc<RwLock<PagePrefetch>>, page_notifier: &Arc<RwLock<Option<CallPage>>>, data_buffer: &Arc<RwLock<Vec<RichData>>>, next: bool, scroller: &Scroll) {
+        let id = {
+            let buffer = data_buffer.read();
+            let edge = if next { buffer.last() } else { buffer.first() };
+            edge.map(|rd| rd.id).unwrap_or(0)
+        };
+        if id == 0 || page_notifier.read().is_none() || !prefetch.write().begin(next, deterministic::now()) {
             return;
         }
-        callpage.notify(opt);
+
+        // 等待期间定时刷新，使加载提示转动，收到数据或超时后最后刷新一次以清除提示。
+        app::add_timeout3(PAGE_SPINNER_INTERVAL, {
+            let prefetch_rc = prefetch.clone();
+            let mut scroller_rc = scroller.clone();
+            move |handler| {
+                if scroller_rc.was_deleted() {
+                    app::remove_timeout3(handler);
+                    return;
+                }
+                scroller_rc.set_damage(true);
+                if prefetch_rc.read().pending(deterministic::now()).is_some() {
+                    app::repeat_timeout3(PAGE_SPINNER_INTERVAL, handler);
+                } else {
+                    app::remove_timeout3(handler);
+                }
+            }
+        });
+        awake_callback({
+            let page_notifier_rc = page_notifier.clone();
+            move || Self::notify_page(&page_notifier_rc, if next { PageOptions::NextPage(id) } else { PageOptions::PrevPage(id) })
+        });
     }
 
     fn recalculate_data_buffer_position(
@@ -1519,29 +2689,24 @@ impl RichReviewer {
         panel: &mut Widget,
         scroller: Scroll,
         basic_char: Arc<RwLock<char>>,
-        text_size: Arc<AtomicI32>) -> (bool, i32) {
-        let _empty = RichData::empty();
-        let mut last_rd = &_empty;
-        let mut is_first_data = true;
-
+        text_size: Arc<AtomicI32>,
+        padding: Padding,
+        columns: usize) -> (bool, i32) {
         {
             let mut buffer = data_buffer.write();
+            let drawable_max_width = flow_column_width(drawable_max_width, columns);
+            // 结构标记数据段不产生分片，因此以试算返回的分片作为后续数据的起点。
+            let mut last_piece = LinePiece::init_piece(text_size.load(Relaxed), &padding);
             for rd in buffer.iter_mut() {
-                let last_piece = if is_first_data {
-                    is_first_data = false;
-                    LinePiece::init_piece(text_size.load(Relaxed))
-                } else {
-                    last_rd.line_pieces.last().unwrap().clone()
-                };
-                rd.estimate(last_piece, drawable_max_width, *basic_char.read());
+                last_piece = rd.estimate(last_piece, drawable_max_width, *basic_char.read());
                 // debug!("rd.text: {}, rd.v_bounds: {:?}", rd.text, rd.v_bounds);
-                last_rd = rd;
             }
+            flow_columns(&mut buffer, columns, drawable_max_width, padding.drawable_height(scroller.height()), padding.top);
         }
 
         // 设置新的窗口尺寸
         let (scroller_width, scroller_height) = (panel.width(), scroller.height());
-        let panel_height = Self::calc_panel_height(data_buffer.clone(), scroller_height);
+        let panel_height = Self::calc_panel_height(data_buffer.clone(), scroller_height, &padding);
         panel.resize(panel.x(), panel.y(), scroller_width, panel_height);
         // debug!("panel_height: {}, scroller_height: {}", panel_height, scroller_height);
         if let Some(rd) = data_buffer.read().last() {
@@ -1552,16 +2717,55 @@ impl RichReviewer {
         }
     }
 
-    fn calc_panel_height(buffer_rc: Arc<RwLock<Vec<RichData>>>, scroller_height: i32) -> i32 {
+    /// 缩放默认字体尺寸，并按相同比例缩放已载入的文本数据段，然后重新计算绘制位置。
+    ///
+    /// # Arguments
+    ///
+    /// * `new_size`: 新的默认字体尺寸。
+    /// * `text_size`: 默认字体尺寸。
+    /// * `data_buffer`: 数据缓存。
+    /// * `hidden_data`: 被过滤器暂时隐藏的数据段。
+    /// * `panel`: 内容面板。
+    /// * `scroller`: 滚动容器。
+    /// * `basic_char`: 基本字符。
+    /// * `padding`: 内容与面板边界之间的空白。
+    ///
+    /// returns: bool 字体尺寸是否发生了变化。
+    fn apply_zoom(
+        new_size: i32,
+        text_size: Arc<AtomicI32>,
+        data_buffer: Arc<RwLock<Vec<RichData>>>,
+        hidden_data: Arc<RwLock<Vec<RichData>>>,
+        panel: &mut Widget,
+        scroller: Scroll,
+        basic_char: Arc<RwLock<char>>,
+        padding: Padding,
+        columns: usize) -> bool {
+        let old_size = text_size.swap(new_size, Relaxed);
+        if old_size == new_size {
+            return false;
+        }
+        hidden_data.write().iter_mut().for_each(|rd| scale_font_size(rd, old_size, new_size));
+        data_buffer.write().iter_mut().for_each(|rd| {
+            scale_font_size(rd, old_size, new_size);
+            rd.line_pieces.clear();
+        });
+        let drawable_max_width = padding.drawable_width(panel.width());
+        Self::recalculate_data_buffer_position(data_buffer, drawable_max_width, panel, scroller, basic_char, text_size, padding, columns);
+        true
+    }
+
+    fn calc_panel_height(buffer_rc: Arc<RwLock<Vec<RichData>>>, scroller_height: i32, padding: &Padding) -> i32 {
         let buffer = &*buffer_rc.read();
         let (mut top, mut bottom) = (0, 0);
         if let Some(first) = buffer.first() {
             top = first.v_bounds.read().0;
         }
-        if let Some(last) = buffer.last() {
-            bottom = last.v_bounds.read().1;
+        if let Some(bottom_y) = buffer.iter().map(|rd| rd.v_bounds.read().1).max() {
+            // 分栏显示时最后一个数据段不一定位于最下方。
+            bottom = bottom_y;
         }
-        let content_height = bottom - top + PADDING.bottom + PADDING.top;
+        let content_height = bottom - top + padding.bottom + padding.top;
         if content_height > scroller_height {
             content_height
         } else {
@@ -1651,6 +2855,40 @@ impl RichReviewer {
         self.text_size.store(size, Relaxed);
     }
 
+    /// 缩放字体。设置新的默认字体尺寸，并按相同比例缩放已载入的文本数据段，然后重新计算绘制位置。
+    /// 按住Ctrl键滚动鼠标滚轮时也会执行缩放，并通过回调函数发送`CallbackData::Zoom`。
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: 新的默认字体尺寸。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.zoom(20);
+    /// ```
+    pub fn zoom(&mut self, size: i32) {
+        let zoomed = Self::apply_zoom(
+            size,
+            self.text_size.clone(),
+            self.data_buffer.clone(),
+            self.hidden_data.clone(),
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            *self.padding.read(),
+            self.columns.load(Relaxed),
+        );
+        if zoomed {
+            self.scroller.set_damage(true);
+        }
+    }
+
     /// 获取默认的字体尺寸。
     pub fn text_size(&self) -> i32 {
         self.text_size.load(Relaxed)
@@ -1708,20 +2946,64 @@ impl RichReviewer {
         self.enable_blink.store(enable, Relaxed);
     }
 
-    pub fn set_search_focus_color(&mut self, color: Color) {
-        self.blink_flag.write().focus_boarder_color = color;
-    }
-
-    pub fn set_search_focus_contrast(&mut self, contrast: Color) {
-        self.blink_flag.write().focus_boarder_contrast_color = contrast;
-    }
-
-    pub fn set_search_focus_color_and_contrast(&mut self, color: Color, contrast: Color) {
-        let mut bf = self.blink_flag.write();
-        bf.focus_boarder_color = color;
-        bf.focus_boarder_contrast_color = contrast;
-    }
-
+    /// 设置慢速闪烁内容的强度切换间隔时间。
+    ///
+    /// # Arguments
+    ///
+    /// * `interval`: 闪烁间隔时间，单位秒，小于等于0时忽略。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_blink_interval(0.8);
+    /// ```
+    pub fn set_blink_interval(&mut self, interval: f64) {
+        if interval > 0.0 {
+            *self.blink_interval.write() = interval;
+        }
+    }
+
+    /// 设置快速闪烁内容的强度切换间隔时间。
+    ///
+    /// # Arguments
+    ///
+    /// * `interval`: 闪烁间隔时间，单位秒，小于等于0时忽略。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_rapid_blink_interval(0.2);
+    /// ```
+    pub fn set_rapid_blink_interval(&mut self, interval: f64) {
+        if interval > 0.0 {
+            *self.rapid_blink_interval.write() = interval;
+        }
+    }
+
+    pub fn set_search_focus_color(&mut self, color: Color) {
+        self.blink_flag.write().focus_boarder_color = color;
+    }
+
+    pub fn set_search_focus_contrast(&mut self, contrast: Color) {
+        self.blink_flag.write().focus_boarder_contrast_color = contrast;
+    }
+
+    pub fn set_search_focus_color_and_contrast(&mut self, color: Color, contrast: Color) {
+        let mut bf = self.blink_flag.write();
+        bf.focus_boarder_color = color;
+        bf.focus_boarder_contrast_color = contrast;
+    }
+
     pub fn set_search_focus_width(&mut self, width: u8) {
         self.blink_flag.write().focus_boarder_width = width as i32;
     }
@@ -1747,21 +3029,984 @@ impl RichReviewer {
         *self.basic_char.write() = basic_char;
     }
 
-    /// 使符合过滤条件的目标数据段过期、禁用。
+    /// 设置数据过滤器，仅显示过滤函数返回true的数据段，其余数据段被暂时隐藏，可见数据段重新紧密排列。
+    /// 再次设置时以新的过滤器替换旧的过滤器，并对全部数据重新过滤。
+    /// 分屏回看模式下回顾区与主视图共享数据缓存，过滤会改变主视图的内容，因此不支持过滤。
     ///
     /// # Arguments
     ///
-    /// * `target`:
+    /// * `filter`: 过滤函数，返回true表示显示该数据段。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
     ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_filter(|ud| ud.text.contains("错误"));
     /// ```
-    pub(crate) fn expire_review_data(&mut self, target: &String) {
-        expire_data(self.data_buffer.clone(), target);
-        self.panel.set_damage(true);
+    pub fn set_filter<F>(&mut self, filter: F) where F: FnMut(&UserData) -> bool + Sync + Send + 'static {
+        if self.shared_buffer {
+            error!("分屏回看区与主视图共享数据缓存，不支持过滤");
+            return;
+        }
+        let mut data_filter = DataFilter::new(Arc::new(RwLock::new(Box::new(filter))));
+        self._clear_search_results();
+        let data = self.restore_hidden_data();
+        let (mut visible, mut hidden) = partition_in_order(data, &mut data_filter, None, &mut self.hidden_anchors.write());
+        self.data_buffer.write().append(&mut visible);
+        self.hidden_data.write().append(&mut hidden);
+        self.filter.write().replace(data_filter);
+        self.relayout_filtered_data();
+    }
+
+    /// 清除数据过滤器，恢复显示全部数据。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_filter(|ud| ud.text.contains("错误"));
+    /// reviewer.clear_filter();
+    /// ```
+    pub fn clear_filter(&mut self) {
+        if self.filter.write().take().is_none() {
+            return;
+        }
+        self._clear_search_results();
+        let mut data = self.restore_hidden_data();
+        self.data_buffer.write().append(&mut data);
+        self.relayout_filtered_data();
+    }
+
+    /// 取出可见数据与隐藏数据，按照过滤前的顺序合并。
+    /// 历史模式下可见数据可能已被分页移除，此时仅保留位于可见数据范围内的隐藏数据。
+    /// 只能用于回顾区自有的数据缓存，共享缓存中的数据属于主视图。
+    fn restore_hidden_data(&mut self) -> Vec<RichData> {
+        debug_assert!(!self.shared_buffer);
+        let mut data = Vec::<RichData>::new();
+        data.append(&mut *self.data_buffer.write());
+        let mut hidden = Vec::<RichData>::new();
+        hidden.append(&mut *self.hidden_data.write());
+        if self.history_mode.load(Relaxed) {
+            if let (Some(first), Some(last)) = (data.first(), data.last()) {
+                let (first_id, last_id) = (first.id, last.id);
+                hidden.retain(|rd| rd.id >= first_id && rd.id <= last_id);
+            }
+        }
+        let anchors = std::mem::take(&mut *self.hidden_anchors.write());
+        merge_in_order(data, hidden, &anchors)
+    }
+
+    /// 过滤条件变化后，重新计算可见数据的绘制位置。
+    fn relayout_filtered_data(&mut self) {
+        self.data_buffer.write().iter_mut().for_each(|rd| rd.line_pieces.clear());
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroll_to_bottom();
+        self.scroller.set_damage(true);
+    }
+
+    /// 设置是否在左侧显示逻辑行号。行号不参与点击与选择。
+    ///
+    /// # Arguments
+    ///
+    /// * `show`: true显示行号，false隐藏行号。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_show_line_numbers(true);
+    /// ```
+    pub fn set_show_line_numbers(&mut self, show: bool) {
+        let gutter_width = if show {
+            draw::set_font(*self.text_font.read(), self.text_size.load(Relaxed));
+            let (tw, _) = draw::measure(&"0".repeat(LINE_NUMBER_DIGITS), false);
+            tw + IMAGE_PADDING_H * 4
+        } else {
+            0
+        };
+        if self.gutter_width.swap(gutter_width, Relaxed) == gutter_width {
+            return;
+        }
+
+        self.data_buffer.write().iter_mut().for_each(|rd| {
+            rd.gutter_width = gutter_width;
+            rd.line_pieces.clear();
+        });
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroller.set_damage(true);
+    }
+
+    /// 设置是否显示所有数据段的译文。
+    ///
+    /// # Arguments
+    ///
+    /// * `show`: true显示译文，false隐藏译文。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.show_translations(true);
+    /// ```
+    pub fn show_translations(&mut self, show: bool) {
+        self.hidden_data.write().iter_mut().for_each(|rd| rd.show_translation = show);
+        self.data_buffer.write().iter_mut().for_each(|rd| {
+            rd.show_translation = show;
+            rd.line_pieces.clear();
+        });
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroller.set_damage(true);
+    }
+
+    /// 暂停或恢复闪烁定时器。主视图进入空闲状态时暂停，退出空闲状态时恢复。
+    ///
+    /// # Arguments
+    ///
+    /// * `suspend`: true暂停，false恢复。
+    ///
+    /// returns: ()
+    pub(crate) fn suspend_timers(&mut self, suspend: bool) {
+        if self.suspended.swap(suspend, Relaxed) == suspend {
+            return;
+        }
+        if suspend {
+            // 停留在正常显示强度。
+            if self.blink_flag.write().settle() {
+                self.scroller.set_damage(true);
+            }
+        } else {
+            let mut timers = self.timers.write();
+            timers.wake(BLINK_TIMER);
+            timers.wake(RAPID_BLINK_TIMER);
+        }
+    }
+
+    /// 为指定数据段设置译文，译文显示在该数据段最后一个片段的下方。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据段ID。
+    /// * `translation`: 译文，为空时清除译文。
+    /// * `show`: 是否显示译文。
+    ///
+    /// returns: ()
+    pub(crate) fn set_translation(&mut self, id: i64, translation: Option<String>, show: bool) {
+        if self.shared_buffer {
+            // 共享的缓存已由主视图更新并重排。
+            self.scroller.set_damage(true);
+            return;
+        }
+        let is_target = |rd: &RichData| rd.has_origin(id) && rd.data_type == DataType::Text;
+        let mut data_buffer = self.data_buffer.write();
+        if let Some(rd) = data_buffer.iter_mut().rev().find(|rd| is_target(rd)) {
+            rd.translation = translation;
+            rd.show_translation = show;
+            data_buffer.iter_mut().for_each(|rd| rd.line_pieces.clear());
+        } else {
+            // 目标数据段被过滤器隐藏时只更新数据，不影响当前排版。
+            if let Some(rd) = self.hidden_data.write().iter_mut().rev().find(|rd| is_target(rd)) {
+                rd.translation = translation;
+                rd.show_translation = show;
+            }
+            return;
+        }
+        drop(data_buffer);
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroller.set_damage(true);
+    }
+
+    /// 按照线索的折叠状态更新数据段的可见性，包括被过滤器隐藏的数据段，可见性发生变化时重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `collapsed`: 已折叠的线索根数据段ID。
+    ///
+    /// returns: ()
+    pub(crate) fn refresh_threads(&mut self, collapsed: &HashSet<i64>) {
+        if self.shared_buffer {
+            // 共享的缓存已由主视图更新并重排。
+            self.scroller.set_damage(true);
+            return;
+        }
+        let hidden = {
+            let hidden_data = self.hidden_data.read();
+            let data_buffer = self.data_buffer.read();
+            thread_hidden_ids(hidden_data.iter().chain(data_buffer.iter()), collapsed)
+        };
+        self.hidden_data.write().iter_mut().for_each(|rd| rd.hidden = hidden.contains(&rd.id));
+        if self.data_buffer.read().iter().any(|rd| rd.hidden != hidden.contains(&rd.id)) {
+            self.relayout_data(|rd| rd.hidden = hidden.contains(&rd.id));
+        }
+    }
+
+    /// 设置行高与字体尺寸的比例，已经载入的数据按新行高重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: 行高比例，默认为`LINE_HEIGHT_FACTOR`，不小于1。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_line_height_factor(1.5);
+    /// ```
+    pub fn set_line_height_factor(&mut self, factor: f32) {
+        let factor = factor.max(1.0);
+        *self.line_height_factor.write() = factor;
+        self.relayout_data(|rd| rd.line_height_factor = factor);
+    }
+
+    /// 设置逻辑行结束后额外增加的段落间距，已经载入的数据按新间距重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `spacing`: 段落间距，单位为像素，默认为0。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_paragraph_spacing(6);
+    /// ```
+    pub fn set_paragraph_spacing(&mut self, spacing: i32) {
+        let spacing = spacing.max(0);
+        self.paragraph_spacing.store(spacing, Relaxed);
+        self.relayout_data(|rd| rd.paragraph_spacing = spacing);
+    }
+
+    /// 设置鼠标在可互动数据段上停留多久后显示互动行为的提示信息。
+    ///
+    /// # Arguments
+    ///
+    /// * `delay`: 停留时间，单位秒。为空时不显示提示信息。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_tooltip_delay(Some(0.5));
+    /// // 不再显示提示信息。
+    /// reviewer.set_tooltip_delay(None);
+    /// ```
+    pub fn set_tooltip_delay(&mut self, delay: Option<f64>) {
+        self.tooltip.write().delay = delay;
+    }
+
+    /// 设置右键菜单钩子函数。
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: 钩子函数载体。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use fltk::app;
+    /// use parking_lot::RwLock;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::{ContextMenuHook, MenuBuilder, UserData};
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// let hook: Box<dyn FnMut(&UserData, &mut MenuBuilder) + Send + Sync> = Box::new(|_data, menu| {
+    ///     menu.add_item("复制", |data| app::copy(&data.text));
+    /// });
+    /// reviewer.set_context_menu_hook(ContextMenuHook::new(Arc::new(RwLock::new(hook))));
+    /// ```
+    pub fn set_context_menu_hook(&mut self, hook: ContextMenuHook) {
+        self.context_menu_hook.write().replace(hook);
+    }
+
+    /// 设置在空白处右击时是否弹出组件内置菜单，未启用时通过回调函数发送`CallbackData::ContextMenu`。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`: 是否启用组件内置菜单。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_blank_context_menu(false);
+    /// ```
+    pub fn set_blank_context_menu(&mut self, enable: bool) {
+        self.blank_menu.store(enable, Relaxed);
+    }
+
+    /// 设置内容与面板边界之间的空白，已经载入的数据按新的可绘制宽度重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `padding`: 边界空白。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::Padding;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_padding(Padding::new(10, 8, 10, 8));
+    /// ```
+    pub fn set_padding(&mut self, padding: Padding) {
+        if *self.padding.read() == padding {
+            return;
+        }
+        *self.padding.write() = padding;
+        self.relayout_data(|rd| rd.padding_left = padding.left);
     }
-}
\ No newline at end of file
+
+    /// 设置分栏显示的栏数，适合在很宽的窗口中阅读历史记录。分栏后内容像报纸一样先自上而下填满一栏，再转入右侧的下一栏，
+    /// 各栏都填满后在下方开始新的一页，每页高度与回顾区可视高度相同。选择和复制仍然按照阅读顺序进行。
+    /// 栏数限制在1到`MAX_COLUMNS`之间，为1时不分栏。与主视图共享数据缓存的分屏回看模式不支持分栏。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns`: 栏数。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 600, None);
+    /// reviewer.set_columns(2);
+    /// assert_eq!(reviewer.columns(), 2);
+    /// ```
+    pub fn set_columns(&mut self, columns: usize) {
+        if self.shared_buffer {
+            return;
+        }
+        let columns = columns.clamp(1, MAX_COLUMNS);
+        if self.columns.swap(columns, Relaxed) != columns {
+            self.relayout_data(|_| {});
+        }
+    }
+
+    /// 获取分栏显示的栏数。
+    ///
+    /// returns: usize
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let reviewer = RichReviewer::new(100, 60, 1600, 600, None);
+    /// assert_eq!(reviewer.columns(), 1);
+    /// ```
+    pub fn columns(&self) -> usize {
+        self.columns.load(Relaxed)
+    }
+
+    /// 丢弃离线绘图板，清空字形测量及换行试算缓存、对比度调整缓存和图标缩放缓存，重新计算已载入数据的绘制位置后重绘。
+    pub(crate) fn invalidate_all(&mut self) {
+        self.wrap_cache.write().clear();
+        self.render_options.read().clear_cache();
+        if let Some(offs) = Offscreen::new(max(self.scroller.width(), 1), max(self.scroller.height(), 1)) {
+            *self.reviewer_screen.write() = offs;
+        }
+        self.relayout_data(|rd| rd.clear_image_cache());
+    }
+
+    /// 调整全部数据段的属性后重新计算绘制位置，包括被过滤器隐藏的数据段。
+    fn relayout_data<F>(&mut self, f: F) where F: Fn(&mut RichData) {
+        self.hidden_data.write().iter_mut().for_each(&f);
+        self.data_buffer.write().iter_mut().for_each(|rd| {
+            f(rd);
+            rd.line_pieces.clear();
+        });
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroller.set_damage(true);
+    }
+
+    /// 按照新的具名样式定义更新引用该样式的数据段，并重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 样式名称。
+    /// * `previous`: 原先的样式定义。
+    /// * `def`: 新的样式定义。
+    ///
+    /// returns: ()
+    pub(crate) fn restyle(&mut self, name: &str, previous: Option<&StyleDef>, def: &StyleDef) {
+        let in_use = |buffer: &[RichData]| buffer.iter().any(|rd| rd.style_name.as_deref() == Some(name));
+        if !in_use(&self.data_buffer.read()) && !in_use(&self.hidden_data.read()) {
+            return;
+        }
+        // 回顾区的数据段没有背景色时不绘制背景，撤销的背景色不需要替换为组件背景色。
+        let base = StyleDef::new()
+            .set_font(*self.text_font.read())
+            .set_font_size(self.text_size.load(Relaxed))
+            .set_fg_color(*self.text_color.read());
+        self.relayout_data(|rd| {
+            if rd.style_name.as_deref() == Some(name) {
+                def.restyle(previous, &base, rd);
+            }
+        });
+    }
+
+    /// 设置后续载入的分页数据使用的断行策略和悬挂缩进，不重新排版已经载入的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 断行策略。
+    /// * `hanging_indent`: 后续行的悬挂缩进宽度。
+    /// * `wrap_cache`: 主视图的换行试算缓存。
+    ///
+    /// returns: ()
+    pub(crate) fn init_wrap_settings(&mut self, policy: WrapPolicy, hanging_indent: i32, wrap_cache: Arc<RwLock<WrapCache>>) {
+        *self.wrap_policy.write() = policy;
+        self.hanging_indent.store(hanging_indent, Relaxed);
+        self.wrap_cache = wrap_cache;
+    }
+
+    /// 设置复制选中文字时是否保留自动折行。默认不保留，被自动折行的内容按照接收时的原样合并为一行；
+    /// 保留时在每个自动折行处插入换行符，复制结果与屏幕显示一致。
+    ///
+    /// # Arguments
+    ///
+    /// * `keep`: 是否保留自动折行。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_copy_soft_wraps(true);
+    /// ```
+    pub fn set_copy_soft_wraps(&mut self, keep: bool) {
+        self.copy_soft_wraps.store(keep, Relaxed);
+    }
+
+    /// 设置自动换行的断行策略和悬挂缩进，已经载入的数据按新设置重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 断行策略。
+    /// * `hanging_indent`: 后续行的悬挂缩进宽度。
+    ///
+    /// returns: ()
+    pub(crate) fn set_wrap_settings(&mut self, policy: WrapPolicy, hanging_indent: i32) {
+        *self.wrap_policy.write() = policy;
+        self.hanging_indent.store(hanging_indent, Relaxed);
+        self.hidden_data.write().iter_mut().for_each(|rd| {
+            rd.wrap_policy = policy;
+            rd.hanging_indent = hanging_indent;
+        });
+        self.data_buffer.write().iter_mut().for_each(|rd| {
+            rd.wrap_policy = policy;
+            rd.hanging_indent = hanging_indent;
+            rd.line_pieces.clear();
+        });
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        Self::recalculate_data_buffer_position(
+            self.data_buffer.clone(),
+            drawable_max_width,
+            &mut self.panel,
+            self.scroller.clone(),
+            self.basic_char.clone(),
+            self.text_size.clone(),
+            padding,
+            self.columns.load(Relaxed));
+        self.scroller.set_damage(true);
+    }
+
+    /// 设置ANSI 16色调色板，设置了颜色序号的数据段按照调色板中的颜色重新绘制，包括已经载入的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `palette`: 依次为黑、红、绿、黄、蓝、品红、青、白及其高亮色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::DEFAULT_ANSI_PALETTE;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// let mut palette = DEFAULT_ANSI_PALETTE;
+    /// // 使用更柔和的红色。
+    /// palette[1] = Color::from_rgb(205, 92, 92);
+    /// reviewer.set_ansi_palette(palette);
+    /// ```
+    pub fn set_ansi_palette(&mut self, palette: [Color; 16]) {
+        self.ansi_palette.write().replace(palette);
+        self.data_buffer.write().iter_mut().for_each(|rd| rd.apply_palette(&palette));
+        self.hidden_data.write().iter_mut().for_each(|rd| rd.apply_palette(&palette));
+        self.panel.set_damage(true);
+    }
+
+    /// 设置禁用数据段的显示样式，已禁用的数据段按照新样式重新显示。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 禁用样式。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::DisabledStyle;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_disabled_style(DisabledStyle::Dim(0.6));
+    /// ```
+    pub fn set_disabled_style(&mut self, style: DisabledStyle) {
+        *self.disabled_style.write() = style;
+        self.data_buffer.write().iter_mut().for_each(|rd| rd.set_disabled_style(style));
+        self.hidden_data.write().iter_mut().for_each(|rd| rd.set_disabled_style(style));
+        self.panel.set_damage(true);
+    }
+
+    /// 添加持久高亮的关键字，已载入和此后载入的数据中所有出现该关键字的位置都以指定背景色绘制，直到被移除。
+    /// 关键字已存在时更新其颜色，多个关键字重叠时先添加的关键字优先。
+    ///
+    /// # Arguments
+    ///
+    /// * `word`: 关键字。
+    /// * `color`: 背景色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.add_keyword_highlight("张三", Color::DarkRed);
+    /// ```
+    pub fn add_keyword_highlight(&mut self, word: &str, color: Color) {
+        let mut keywords = self.keywords.read().clone();
+        match keywords.iter_mut().find(|keyword| keyword.word == word) {
+            Some(keyword) => keyword.color = color,
+            None => keywords.push(KeywordHighlight { word: word.to_string(), color }),
+        }
+        self.set_keyword_highlights(keywords);
+    }
+
+    /// 移除持久高亮的关键字，已载入数据中的高亮同时清除。
+    ///
+    /// # Arguments
+    ///
+    /// * `word`: 关键字。
+    ///
+    /// returns: bool 关键字不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.add_keyword_highlight("张三", Color::DarkRed);
+    /// assert!(reviewer.remove_keyword_highlight("张三"));
+    /// assert!(!reviewer.remove_keyword_highlight("李四"));
+    /// ```
+    pub fn remove_keyword_highlight(&mut self, word: &str) -> bool {
+        let mut keywords = self.keywords.read().clone();
+        let len = keywords.len();
+        keywords.retain(|keyword| keyword.word != word);
+        let removed = keywords.len() != len;
+        if removed {
+            self.set_keyword_highlights(keywords);
+        }
+        removed
+    }
+
+    /// 替换全部持久高亮的关键字，并重新计算已载入数据的高亮位置。
+    pub(crate) fn set_keyword_highlights(&mut self, keywords: Vec<KeywordHighlight>) {
+        self.data_buffer.write().iter_mut().for_each(|rd| rd.apply_keywords(&keywords));
+        self.hidden_data.write().iter_mut().for_each(|rd| rd.apply_keywords(&keywords));
+        *self.keywords.write() = keywords;
+        self.panel.set_damage(true);
+    }
+
+    /// 应用主题。已载入数据中使用旧默认颜色的部分改用新主题的颜色，设置了颜色序号的数据段按照新主题的调色板着色。
+    ///
+    /// # Arguments
+    ///
+    /// * `theme`: 主题。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::Theme;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.apply_theme(&Theme::solarized_dark());
+    /// ```
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        let old = (*self.text_color.read(), *self.background_color.read(), *self.text_font.read());
+        *self.background_color.write() = theme.background_color;
+        *self.text_color.write() = theme.text_color;
+        *self.text_font.write() = theme.text_font;
+        theme.apply_to_blink_state(&mut self.blink_flag.write());
+        theme.apply_to_tooltip(&mut self.tooltip.write());
+        self.ansi_palette.write().replace(theme.ansi_palette);
+        retheme_data(&mut self.hidden_data.write(), old, theme);
+        if self.shared_buffer {
+            // 共享的缓存已由主视图更新并重排。
+            self.panel.set_damage(true);
+            return;
+        }
+        if retheme_data(&mut self.data_buffer.write(), old, theme) {
+            self.relayout_data(|_| {});
+        }
+        self.panel.set_damage(true);
+    }
+
+    /// 设置自动折行续行标记的颜色，为空时不绘制标记。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`: 标记颜色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_wrap_marker(Some(Color::from_rgb(90, 90, 90)));
+    /// ```
+    pub fn set_wrap_marker(&mut self, color: Option<Color>) {
+        self.blink_flag.write().set_wrap_marker(color);
+        self.panel.set_damage(true);
+    }
+
+    /// 开启或关闭减少动态效果，开启后内容和光标均不闪烁。
+    ///
+    /// # Arguments
+    ///
+    /// * `on`: 是否开启。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_reduced_motion(true);
+    /// ```
+    pub fn set_reduced_motion(&mut self, on: bool) {
+        self.render_options.write().set_reduced_motion(on);
+        if on {
+            self.blink_flag.write().reset_phase();
+        }
+        self.panel.set_damage(true);
+    }
+
+    /// 设置文字与背景的最低对比度，小于等于1时不调整文字颜色。
+    ///
+    /// # Arguments
+    ///
+    /// * `ratio`: 最低对比度。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_minimum_contrast(4.5);
+    /// ```
+    pub fn set_minimum_contrast(&mut self, ratio: f32) {
+        self.render_options.write().set_minimum_contrast(ratio);
+        self.panel.set_damage(true);
+    }
+
+    /// 设置文字阴影或描边效果。
+    ///
+    /// # Arguments
+    ///
+    /// * `shadow`: 阴影或描边效果，为空时不绘制。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    /// use fltkrs_richdisplay::TextShadow;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_text_shadow(Some(TextShadow::Outline { width: 1, color: Color::Black }));
+    /// ```
+    pub fn set_text_shadow(&mut self, shadow: Option<TextShadow>) {
+        self.render_options.write().set_text_shadow(shadow);
+        self.panel.set_damage(true);
+    }
+
+    /// 设置绘制时使用的颜色变换。
+    ///
+    /// # Arguments
+    ///
+    /// * `transform`: 颜色变换。
+    ///
+    /// returns: ()
+    pub(crate) fn set_color_transform(&mut self, transform: ColorTransform) {
+        *self.color_transform.write() = transform;
+        self.panel.set_damage(true);
+    }
+
+    /// 设置选中文字的背景色和前景色。
+    ///
+    /// # Arguments
+    ///
+    /// * `bg`: 选中文字的背景色。
+    /// * `fg`: 选中文字的前景色，为空时保持文字原有颜色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_reviewer::RichReviewer;
+    ///
+    /// let mut reviewer = RichReviewer::new(100, 60, 1600, 800, None);
+    /// reviewer.set_selection_colors(Color::from_rgb(38, 79, 120), Some(Color::White));
+    /// ```
+    pub fn set_selection_colors(&mut self, bg: Color, fg: Option<Color>) {
+        self.blink_flag.write().set_selection_colors(bg, fg);
+        self.panel.set_damage(true);
+    }
+
+    /// 使符合过滤条件的目标数据段过期、禁用。
+    ///
+    /// # Arguments
+    ///
+    /// * `target`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub(crate) fn expire_review_data(&mut self, target: &String) {
+        expire_data(self.data_buffer.clone(), target);
+        expire_data(self.hidden_data.clone(), target);
+        self.panel.set_damage(true);
+    }
+
+    /// 使指定ID的数据段过期、禁用。
+    ///
+    /// # Arguments
+    ///
+    /// * `ids`: 目标数据段ID。
+    ///
+    /// returns: ()
+    pub(crate) fn expire_review_data_by_ids(&mut self, ids: &HashSet<i64>) {
+        expire_data_by_ids(&mut self.data_buffer.write(), ids);
+        expire_data_by_ids(&mut self.hidden_data.write(), ids);
+        self.panel.set_damage(true);
+    }
+
+    /// 找出满足判断条件的数据段ID，包括被过滤器隐藏的数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `matches`: 判断条件。
+    ///
+    /// returns: Vec<i64, Global>
+    pub(crate) fn matching_ids<F>(&self, matches: &mut F) -> Vec<i64> where F: FnMut(&RichData) -> bool {
+        let mut ids: Vec<i64> = self.data_buffer.read().iter().filter(|rd| matches(rd)).map(|rd| rd.id).collect();
+        ids.extend(self.hidden_data.read().iter().filter(|rd| matches(rd)).map(|rd| rd.id));
+        ids
+    }
+
+    /// 按照指定效果使数据段过期。
+    ///
+    /// # Arguments
+    ///
+    /// * `ids`: 目标数据段ID。
+    /// * `effect`: 过期效果，不能为`ExpireEffect::Remove`。
+    ///
+    /// returns: ()
+    pub(crate) fn expire_review_data_with_effect(&mut self, ids: &HashSet<i64>, effect: ExpireEffect) {
+        expire_data_with_effect(&mut self.data_buffer.write(), ids, effect);
+        expire_data_with_effect(&mut self.hidden_data.write(), ids, effect);
+        self.panel.set_damage(true);
+    }
+
+    /// 移除指定ID的数据段并重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `ids`: 目标数据段ID。
+    ///
+    /// returns: ()
+    pub(crate) fn remove_review_data(&mut self, ids: &HashSet<i64>) {
+        self.hidden_data.write().retain(|rd| !rd.in_ids(ids));
+        self.data_buffer.write().retain(|rd| !rd.in_ids(ids));
+        self.relayout_data(|_| {});
+    }
+}
+/// 按过滤条件拆分数据，并记录每个隐藏数据段紧邻的前一个可见数据段ID。
+///
+/// # Arguments
+///
+/// * `data`: 待拆分的数据段，按显示顺序排列。
+/// * `filter`: 数据过滤器。
+/// * `prev`: 位于这批数据之前的最后一个可见数据段ID。
+/// * `anchors`: 记录隐藏数据段位置的映射表。
+///
+/// returns: (Vec<RichData>, Vec<RichData>) 可见数据与隐藏数据。
+fn partition_in_order(data: Vec<RichData>, filter: &mut DataFilter, mut prev: Option<i64>, anchors: &mut HashMap<i64, Option<i64>>) -> (Vec<RichData>, Vec<RichData>) {
+    let (mut visible, mut hidden) = (Vec::with_capacity(data.len()), vec![]);
+    for rd in data {
+        if filter.check(&rd) {
+            prev = Some(rd.id);
+            visible.push(rd);
+        } else {
+            anchors.insert(rd.id, prev);
+            hidden.push(rd);
+        }
+    }
+    (visible, hidden)
+}
+
+/// 将隐藏数据按记录的位置插回可见数据之间，不依赖数据ID的大小顺序。
+/// 前一个可见数据段已不存在的隐藏数据放在最前面。
+///
+/// # Arguments
+///
+/// * `visible`: 可见数据。
+/// * `hidden`: 隐藏数据，保持拆分时的相对顺序。
+/// * `anchors`: 隐藏数据段紧邻的前一个可见数据段ID。
+///
+/// returns: Vec<RichData> 合并后的数据。
+fn merge_in_order(visible: Vec<RichData>, hidden: Vec<RichData>, anchors: &HashMap<i64, Option<i64>>) -> Vec<RichData> {
+    let visible_ids: HashSet<i64> = visible.iter().map(|rd| rd.id).collect();
+    let mut following: HashMap<i64, Vec<RichData>> = HashMap::new();
+    let mut merged = Vec::with_capacity(visible.len() + hidden.len());
+    for rd in hidden {
+        match anchors.get(&rd.id).copied().flatten() {
+            Some(anchor) if visible_ids.contains(&anchor) => following.entry(anchor).or_default().push(rd),
+            _ => merged.push(rd),
+        }
+    }
+    for rd in visible {
+        let id = rd.id;
+        merged.push(rd);
+        if let Some(mut rest) = following.remove(&id) {
+            merged.append(&mut rest);
+        }
+    }
+    merged
+}
@@ -1,28 +1,39 @@
 //! 富文本查看器组件。
 
-use std::cmp::{max};
-use std::collections::{HashMap};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt::{Debug};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::cell::RefCell;
 use std::rc::{Rc};
 use std::sync::{Arc, Weak};
-use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicUsize, Ordering};
-use std::time::{Duration};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use debounce_fltk::TokioDebounce;
 
 use fltk::draw::{draw_line, draw_rect_fill, measure, Offscreen, set_draw_color};
-use fltk::enums::{Color, Cursor, Event, Font};
-use fltk::prelude::{FltkError, GroupExt, MenuExt, WidgetBase, WidgetExt};
+use fltk::enums::{Align, Color, Cursor, Event, Font, Key};
+use fltk::prelude::{FltkError, GroupExt, ImageExt, MenuExt, WidgetBase, WidgetExt};
 use fltk::{app, draw, widget_extends};
 use fltk::app::{MouseButton, MouseWheel};
 use fltk::frame::Frame;
 use fltk::group::{Flex};
+use fltk::image::RgbImage;
 use fltk::menu::{MenuButton, MenuButtonType};
-use crate::{Rectangle, disable_data, LinedData, LinePiece, LocalEvent, mouse_enter, PADDING, RichData, RichDataOptions, update_data_properties, UserData, BLINK_INTERVAL, BlinkState, Callback, DEFAULT_FONT_SIZE, WHITE, clear_selected_pieces, ClickPoint, locate_target_rd, update_selection_when_drag, CallbackData, ShapeData, LINE_HEIGHT_FACTOR, BASIC_UNIT_CHAR, DEFAULT_TAB_WIDTH, DocEditType, BlinkDegree, DataType, ImageEventData, IMAGE_PADDING_V, expire_data, select_paragraph};
+use fltk::printer::Printer;
+use crate::{Padding, Rectangle, disable_data, LinedData, LinePiece, LocalEvent, mouse_enter, PADDING, RichData, RichDataOptions, UserData, BLINK_INTERVAL, BlinkState, Callback, DEFAULT_FONT_SIZE, WHITE, clear_selected_pieces, ClickPoint, locate_target_rd, update_selection_when_drag, ColumnLayout, CallbackData, ShapeData, LINE_HEIGHT_FACTOR, BASIC_UNIT_CHAR, DEFAULT_TAB_WIDTH, DocEditType, BlinkDegree, DataType, ImageEventData, IMAGE_PADDING_V, expire_data, select_paragraph, Direction, LINE_NUMBER_COLOR, LINE_NUMBER_DIGITS, IMAGE_PADDING_H, expand_tabs, expire_data_by_ids, AUTO_EXPIRE_INTERVAL, TerminalModes, MouseReportMode, italic_font, FADE_IN_INTERVAL, locate_char_at, word_around, url_around, Modifier, ClickBinding, ModifierClickData, BackgroundImage, BackgroundMode, thread_hidden_ids, WrapCacheStats, WrapCache, DEFAULT_WRAP_CACHE_CAPACITY, CursorStyle, RAPID_BLINK_INTERVAL, BlinkSpeed, zoom_font_size, scale_font_size, PreviewProvider, LinkPreview, urls_in, text_columns, take_columns, WatchId, WatchHit, CategoryStats, WrapPolicy, IdlePolicy, HighlightId, HighlightRule, StyleOverride, apply_highlights, EventKind, NamedNotifier, HoverTracker, track_hover, TooltipState, track_tooltip, draw_tooltip, ContextMenuHook, MenuBuilder, popup_blank_menu, selection_contains, selection_text, start_drag_out, drag_out_started, parse_dropped_files, draw_pause_indicator, AppendQueue, StyleDef, ExpireEffect, ExpireFilter, expire_data_with_effect, write_session, read_session, SnapshotRange, to_rgb8, PngEncoder, PrintOptions, paginate, search_positions, KeywordHighlight, PromptLine, draw_prompt_line, SelectionAnchor, SelectionRange, selection_range, keep_selection, follow_push_anchor, echo_lines, draw_echo_area, PinnedRows, MAX_COLUMNS, locate_data_at, ColorFilter, ColorVision, ColorTransform, ColorTransformScope, transform_color, compose_palette, DisabledStyle, TextShadow, detach_buffer, RecentUrls, PREVIEWED_URLS_CAPACITY, id_range, update_origin_data, TimerSet, BLINK_TIMER, RAPID_BLINK_TIMER, RenderOptions};
 
 use log::{debug, error};
 use parking_lot::RwLock;
-use crate::rewrite_board::ReWriteBoard;
+use regex::Regex;
+use crate::rewrite_board::{BoardDamage, CursorPos, ReWriteBoard};
 use crate::rich_reviewer::RichReviewer;
+use crate::plugin::{snapshot_plugins, PanelView, RichDisplayPlugin, SharedPlugin};
+use crate::accessibility::{SpeechHook, SpeechLines, visible_text};
+use crate::log_file::{LOG_FLUSH_INTERVAL, LogSink};
+use crate::{retheme_data, Theme};
+use crate::deterministic::{self, LayoutRecord};
 
 
 pub const MAIN_PANEL_FIX_HEIGHT: i32 = 200;
@@ -30,8 +41,349 @@ pub const PANEL_PADDING: i32 = 8;
 
 pub const MAX_SIZE_OF_TEMP_BUFFER: usize = 1024 * 1024 * 10;
 
+/// 完整截图时每次离线绘制的最大高度，避免为过高的内容创建超出图形系统限制的绘图板。
+const SNAPSHOT_TILE_HEIGHT: i32 = 1024;
+
+/// 暂停输出期间缓存允许增长到的倍数，超出后仍移除最早的数据，避免长时间暂停时内存无限增长。
+pub const PAUSED_BUFFER_FACTOR: usize = 2;
+
 // static FULL_DRAW: Lazy<Arc<AtomicBool>> = Lazy::new(|| Arc::new(AtomicBool::new(false)));
 
+thread_local! {
+    /// 已创建数据发送端的组件，以待合并队列的地址为键，仅在`fltk`主线程中访问。
+    static SENDER_TARGETS: RefCell<HashMap<usize, RichText>> = RefCell::new(HashMap::new());
+}
+
+/// 线程安全的数据发送端，通过`RichText::sender`获得，可在`tokio`任务等非界面线程中直接添加数据，
+/// 无需应用程序自行建立`app::channel`循环。数据先进入组件的待合并队列，再通过`app::awake_callback`在`fltk`主线程中处理。
+#[derive(Debug, Clone)]
+pub struct RichTextSender {
+    key: usize,
+    queue: Arc<RwLock<AppendQueue>>,
+}
+
+impl RichTextSender {
+    /// 发送一条数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `user_data`: 数据。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let sender = rich_text.sender();
+    /// sender.send(UserData::new_text("hello\n".to_string()));
+    /// ```
+    pub fn send(&self, user_data: UserData) {
+        self.push(vec![DocEditType::Data(user_data)]);
+    }
+
+    /// 发送一个数据或操作。
+    ///
+    /// # Arguments
+    ///
+    /// * `edit`: 数据或操作。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::DocEditType;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let sender = rich_text.sender();
+    /// // 清除整个屏幕。
+    /// sender.send_edit(DocEditType::EraseInDisplay(2));
+    /// ```
+    pub fn send_edit(&self, edit: DocEditType) {
+        self.push(vec![edit]);
+    }
+
+    /// 批量发送数据或操作，按顺序处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `batch`: 批次数据。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{DocEditType, UserData};
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let sender = rich_text.sender();
+    /// sender.send_batch(vec![
+    ///     DocEditType::EraseInDisplay(2),
+    ///     DocEditType::Data(UserData::new_text("hello\n".to_string())),
+    /// ]);
+    /// ```
+    pub fn send_batch(&self, batch: Vec<DocEditType>) {
+        self.push(batch);
+    }
+
+    fn push(&self, items: Vec<DocEditType>) {
+        let mut schedule = false;
+        {
+            let mut queue = self.queue.write();
+            for item in items {
+                schedule |= queue.push(item);
+            }
+        }
+        if schedule {
+            let key = self.key;
+            app::awake_callback(move || RichText::on_sender_awake(key));
+        }
+    }
+}
+
+/// 重复数据检测窗口，记录最近添加的数据段内容哈希值及其添加时间。
+#[derive(Debug, Default)]
+struct DedupWindow {
+    /// 参与比较的最近数据段数量，为0时不检测。
+    size: usize,
+    /// 判定为重复发送的最大时间间隔。
+    within: Duration,
+    recent: VecDeque<(u64, Instant)>,
+}
+
+impl DedupWindow {
+    /// 检查内容哈希值是否与窗口内的数据重复，不重复时记录该哈希值。
+    fn is_duplicate(&mut self, hash: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let now = deterministic::now();
+        let within = self.within;
+        self.recent.retain(|(_, t)| now.duration_since(*t) <= within);
+        if self.recent.iter().any(|(h, _)| *h == hash) {
+            return true;
+        }
+        self.recent.push_back((hash, now));
+        while self.recent.len() > self.size {
+            self.recent.pop_front();
+        }
+        false
+    }
+}
+
+/// 清屏操作日志，保存清屏前的会话快照，用于撤销清屏。
+#[derive(Debug, Default)]
+struct EraseJournal {
+    /// 保留的最大快照数量，为0时不记录。
+    depth: usize,
+    snapshots: VecDeque<SessionSnapshot>,
+}
+
+impl EraseJournal {
+    /// 记录快照，超出保留数量时丢弃最早的快照。
+    fn record(&mut self, snapshot: SessionSnapshot) {
+        self.snapshots.push_back(snapshot);
+        while self.snapshots.len() > self.depth {
+            self.snapshots.pop_front();
+        }
+    }
+}
+
+/// 粗粒度的过期时间轮，按照`AUTO_EXPIRE_INTERVAL`的间隔将待过期的数据段ID分组。
+#[derive(Debug)]
+struct ExpireWheel {
+    start: Instant,
+    /// key为过期时刻所在的时间格序号，value为该时间格内待过期的数据段ID。
+    slots: BTreeMap<u64, Vec<i64>>,
+}
+
+impl Default for ExpireWheel {
+    fn default() -> Self {
+        Self { start: deterministic::now(), slots: BTreeMap::new() }
+    }
+}
+
+impl ExpireWheel {
+    /// 登记数据段在ttl之后过期。
+    fn schedule(&mut self, id: i64, ttl: Duration) {
+        let slot = ((deterministic::elapsed_since(self.start) + ttl).as_secs_f64() / AUTO_EXPIRE_INTERVAL).ceil() as u64;
+        self.slots.entry(slot).or_default().push(id);
+    }
+
+    /// 是否没有待过期的数据段。
+    fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// 取出所有已到期的数据段ID。
+    fn take_due(&mut self) -> Vec<i64> {
+        let current = (deterministic::elapsed_since(self.start).as_secs_f64() / AUTO_EXPIRE_INTERVAL).floor() as u64;
+        let pending = self.slots.split_off(&(current + 1));
+        std::mem::replace(&mut self.slots, pending).into_values().flatten().collect()
+    }
+}
+
+/// 会话标签的标识。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SessionId(u64);
+
+/// 单个会话标签的状态。
+#[derive(Debug)]
+struct SessionSlot {
+    name: String,
+    /// 非活动会话的快照，活动会话的状态保存在组件中，此处为空。
+    snapshot: Option<SessionSnapshot>,
+    /// 会话处于非活动状态时收到的数据，切换到该会话时再添加到视图中。
+    pending: VecDeque<UserData>,
+}
+
+/// 组件内的全部会话标签。
+#[derive(Debug)]
+struct SessionTabs {
+    next_id: u64,
+    active: SessionId,
+    slots: BTreeMap<SessionId, SessionSlot>,
+}
+
+impl Default for SessionTabs {
+    fn default() -> Self {
+        let mut slots = BTreeMap::new();
+        slots.insert(SessionId(0), SessionSlot { name: "default".to_string(), snapshot: None, pending: VecDeque::new() });
+        Self { next_id: 1, active: SessionId(0), slots }
+    }
+}
+
+/// 淡入效果定时器的名称。
+const FADE_TIMER: &str = "fade";
+/// 自动过期定时器的名称。
+const EXPIRE_TIMER: &str = "expire";
+/// 光标闪烁定时器的名称。
+const CURSOR_BLINK_TIMER: &str = "cursor_blink";
+/// 会话日志定时写入定时器的名称。
+const LOG_FLUSH_TIMER: &str = "log_flush";
+
+/// 主视图刷新的防抖间隔。
+const PANEL_UPDATE_DEBOUNCE: Duration = Duration::from_millis(20);
+
+/// 组件所在的顶层窗口是否持有键盘焦点。窗口失去焦点时`fltk`不再持有焦点组件，焦点位于同一程序的其他窗口时也视为失去焦点。
+fn window_focused(panel: &Frame) -> bool {
+    let Some(focus) = app::focus() else {
+        return false;
+    };
+    match (focus.top_window(), panel.top_window()) {
+        (Some(focused), Some(own)) => focused.as_widget_ptr() == own.as_widget_ptr(),
+        _ => false,
+    }
+}
+
+/// 空闲检测状态。进入空闲状态时闪烁定时器自行暂停，主视图刷新改用较长的防抖间隔；退出空闲状态时立即恢复。
+#[derive(Debug)]
+struct IdleState {
+    policy: IdlePolicy,
+    /// 最近一次新数据到达或用户输入的时刻。
+    last_activity: Instant,
+    /// 最近一次检查时是否处于空闲状态。
+    idle: bool,
+    /// 主视图的防抖刷新函数，随空闲状态切换防抖间隔。
+    update_panel_fn: Arc<RwLock<TokioDebounce<bool>>>,
+    panel_updater: PanelUpdater,
+    /// 空闲时暂停的定时器。
+    timers: Arc<RwLock<TimerSet>>,
+    /// 主视图面板，用于判断所在窗口是否持有焦点。
+    panel: Frame,
+    /// 回顾区，空闲时一并暂停其闪烁定时器。
+    reviewer: Arc<RwLock<Option<RichReviewer>>>,
+}
+
+impl IdleState {
+    fn new(update_panel_fn: Arc<RwLock<TokioDebounce<bool>>>, panel_updater: PanelUpdater, timers: Arc<RwLock<TimerSet>>, panel: Frame, reviewer: Arc<RwLock<Option<RichReviewer>>>) -> Self {
+        Self { policy: IdlePolicy::default(), last_activity: deterministic::now(), idle: false, update_panel_fn, panel_updater, timers, panel, reviewer }
+    }
+
+    /// 记录一次活动并退出空闲状态，立即恢复正常的刷新防抖间隔和因空闲而暂停的定时器。
+    fn touch(&mut self) {
+        self.last_activity = deterministic::now();
+        if self.idle {
+            self.idle = false;
+            self.set_update_debounce(PANEL_UPDATE_DEBOUNCE);
+            let mut timers = self.timers.write();
+            for name in [BLINK_TIMER, RAPID_BLINK_TIMER, CURSOR_BLINK_TIMER, EXPIRE_TIMER] {
+                timers.wake(name);
+            }
+            drop(timers);
+            self.suspend_reviewer(false);
+        }
+    }
+
+    /// 检查并更新空闲状态，进入空闲状态时延长主视图刷新的防抖间隔，并暂停回顾区的闪烁定时器。
+    fn check(&mut self) -> bool {
+        let idle = self.policy.is_idle(deterministic::elapsed_since(self.last_activity), window_focused(&self.panel));
+        if idle && !self.idle {
+            let interval = Duration::try_from_secs_f64(self.policy.idle_interval).unwrap_or_default().max(PANEL_UPDATE_DEBOUNCE);
+            self.set_update_debounce(interval);
+            self.suspend_reviewer(true);
+        }
+        self.idle = idle;
+        idle
+    }
+
+    /// 暂停或恢复回顾区的闪烁定时器。回顾区正被锁定时跳过，避免在持有锁的调用中重入。
+    fn suspend_reviewer(&self, suspend: bool) {
+        if let Some(mut reviewer) = self.reviewer.try_write() {
+            if let Some(reviewer) = reviewer.as_mut() {
+                reviewer.suspend_timers(suspend);
+            }
+        }
+    }
+
+    /// 更换主视图刷新的防抖间隔，并补发一次刷新，避免丢失更换前尚未执行的刷新。
+    fn set_update_debounce(&self, interval: Duration) {
+        let mut update_panel_fn = self.update_panel_fn.write();
+        *update_panel_fn = self.panel_updater.debounce(interval);
+        update_panel_fn.update_param(false);
+    }
+}
+
+/// 已注册的监视关键字。
+#[derive(Debug, Default)]
+struct Watches {
+    next_id: u64,
+    patterns: BTreeMap<WatchId, String>,
+}
+
+impl Watches {
+    /// 查找新数据段文本命中的全部监视关键字。
+    fn hits(&self, data_id: i64, text: &str) -> Vec<WatchHit> {
+        self.patterns.iter()
+            .filter(|(_, pattern)| text.contains(pattern.as_str()))
+            .map(|(id, _)| WatchHit { id: *id, data_id, text: text.to_string() })
+            .collect()
+    }
+}
+
+/// 暂停输出时的状态。
+#[derive(Debug, Clone, Copy)]
+struct PauseState {
+    /// 暂停时的缓存长度，之后新增的数据段数量显示在暂停标记中。
+    len: usize,
+    /// 暂停时的滚动高度，画面冻结期间的点击检测和几何查询都按此位置计算。
+    scroll_y: i32,
+}
+
+/// 已注册的高亮规则。
+#[derive(Debug, Default)]
+struct Highlights {
+    next_id: u64,
+    rules: Vec<HighlightRule>,
+}
+
 // #[derive(Debug, Clone)]
 // struct ThrottleHolder {
 //     pub last_rid: i64,
@@ -39,6 +391,76 @@ pub const MAX_SIZE_OF_TEMP_BUFFER: usize = 1024 * 1024 * 10;
 // }
 
 
+/// 主视图离线缓存的刷新函数。防抖刷新和确定性模式下的立即刷新共用同一份绘制逻辑。
+#[derive(Clone)]
+struct PanelUpdater {
+    update: Arc<RwLock<Box<dyn FnMut(bool) + Send + Sync>>>,
+}
+
+impl Debug for PanelUpdater {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PanelUpdater: {}", Arc::strong_count(&self.update))
+    }
+}
+
+impl PanelUpdater {
+    fn new<F>(update: F) -> Self where F: FnMut(bool) + Send + Sync + 'static {
+        Self { update: Arc::new(RwLock::new(Box::new(update))) }
+    }
+
+    /// 立即刷新离线缓存。
+    fn update(&self, redraw: bool) {
+        (self.update.write())(redraw);
+    }
+
+    /// 创建指定防抖间隔的刷新函数。
+    fn debounce(&self, interval: Duration) -> TokioDebounce<bool> {
+        let panel_updater = self.clone();
+        TokioDebounce::new_debounce(move |redraw: bool| {
+            panel_updater.update(redraw);
+        }, interval, true)
+    }
+}
+
+/// 进入备用屏幕前保存的主屏幕状态。
+#[derive(Debug, Clone)]
+struct AlternateSaved {
+    cursor: LinePiece,
+    show_cursor: bool,
+    /// 进入备用屏幕时正处于面板流中，保存面板中的数据和定位面板，退出备用屏幕时恢复。
+    panel: Option<(Vec<RichData>, Option<ReWriteBoard>)>,
+}
+
+impl AlternateSaved {
+    /// 复制一份不与原状态共享分片的副本。
+    fn detached(&self) -> Self {
+        Self {
+            cursor: self.cursor.clone(),
+            show_cursor: self.show_cursor,
+            panel: self.panel.as_ref().map(|(buffer, board)| (detach_buffer(buffer), board.as_ref().map(|board| board.detached()))),
+        }
+    }
+}
+
+/// 会话快照，保存主视图的数据缓存、光标、定位面板和滚动区域等状态，可通过`RichText::restore`恢复。
+/// 创建快照时完整复制数据缓存及其分片，此后当前会话的重新排版不会影响快照。复制后的缓存以`Arc`方式持有，克隆快照的开销很小。
+#[derive(Debug, Clone)]
+pub struct SessionSnapshot {
+    current_buffer: Arc<Vec<RichData>>,
+    data_buffer: Arc<Option<Vec<RichData>>>,
+    rewrite_board: Arc<Option<ReWriteBoard>>,
+    cursor_piece: LinePiece,
+    show_cursor: bool,
+    remote_flow_control: bool,
+    line_counter: usize,
+    current_col: usize,
+    top_insert_pos: usize,
+    alternate_saved: Option<AlternateSaved>,
+    scroll_region: (usize, usize),
+    /// 保存快照时主视图的尺寸，恢复时尺寸未变化则无需重新排版。
+    layout_size: (i32, i32),
+}
+
 /// rich-display主面板结构。
 #[derive(Debug, Clone)]
 pub struct RichText {
@@ -49,13 +471,30 @@ pub struct RichText {
     background_color: Arc<RwLock<Color>>,
     buffer_max_lines: Arc<AtomicUsize>,
     notifier: Arc<RwLock<Option<Callback>>>,
+    /// 具名回调函数，与`notifier`中的回调结构体共享。
+    listeners: Arc<RwLock<Vec<NamedNotifier>>>,
+    /// 互动行为提示信息的显示状态。
+    tooltip: Arc<RwLock<TooltipState>>,
+    /// 右键菜单钩子函数。
+    context_menu_hook: Arc<RwLock<Option<ContextMenuHook>>>,
+    /// 在空白处右击时是否弹出组件内置菜单。
+    blank_menu: Arc<AtomicBool>,
+    /// 暂停输出时的状态，为空表示未暂停。
+    paused: Arc<RwLock<Option<PauseState>>>,
+    /// 每秒最大渲染帧数，为0时不限制。
+    max_fps: Arc<AtomicU32>,
+    /// 限制帧率时待合并的数据队列。
+    append_queue: Arc<RwLock<AppendQueue>>,
     inner: Flex,
     reviewer: Arc<RwLock<Option<RichReviewer>>>,
-    // panel_screen: Arc<RwLock<Offscreen>>,
+    /// 主面板的离线绘图板。
+    panel_screen: Arc<RwLock<Offscreen>>,
     // clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
     // /// 主面板上可见行片段的集合容器，在每次离线绘制时被清空和填充。
     // visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
     blink_flag: Arc<RwLock<BlinkState>>,
+    /// 与闪烁无关的绘制选项。
+    render_options: Arc<RwLock<RenderOptions>>,
     /// 默认字体。
     text_font: Arc<RwLock<Font>>,
     /// 默认字体颜色。
@@ -75,7 +514,129 @@ pub struct RichText {
     rewrite_board: Arc<RwLock<Option<ReWriteBoard>>>,
     max_rows: Arc<AtomicUsize>,
     max_cols: Arc<AtomicUsize>,
-    update_panel_fn: Arc<RwLock<TokioDebounce<bool>>>
+    update_panel_fn: Arc<RwLock<TokioDebounce<bool>>>,
+    /// 同步刷新主视图离线缓存。
+    panel_updater: PanelUpdater,
+    /// 是否处于确定性模式，此模式下定时器不再自动执行任务，闪烁相位保持不变。
+    deterministic: Arc<AtomicBool>,
+    /// 左侧行号栏宽度，为0时不显示行号。
+    gutter_width: Arc<AtomicI32>,
+    /// 下一个数据段首字符所在的逻辑行号。
+    line_counter: Arc<AtomicUsize>,
+    /// 是否启用分屏回看模式。
+    split_scrollback: Arc<AtomicBool>,
+    /// 是否在顶部追加新数据。
+    append_top: Arc<AtomicBool>,
+    /// 顶部追加模式下，最新一行数据的结尾在缓存中的位置。
+    top_insert_pos: Arc<AtomicUsize>,
+    /// 进入备用屏幕前保存的主屏幕状态，不为空时表示当前处于备用屏幕中。
+    alternate_saved: Arc<RwLock<Option<AlternateSaved>>>,
+    /// 是否显示译文。
+    show_translations: Arc<AtomicBool>,
+    /// 光标定位面板的滚动区域设置，创建新的定位面板时生效。
+    scroll_region: Arc<RwLock<(usize, usize)>>,
+    /// 重复数据检测窗口。
+    dedup_window: Arc<RwLock<DedupWindow>>,
+    /// 清屏操作日志。
+    erase_journal: Arc<RwLock<EraseJournal>>,
+    /// 自定义制表位所在的列，从0开始，为空时每隔`tab_width`列设置一个默认制表位。
+    tab_stops: Arc<RwLock<Option<BTreeSet<usize>>>>,
+    /// 常规流中下一个字符所在的列，从0开始。
+    current_col: Arc<AtomicUsize>,
+    /// 可互动数据段的默认有效期。
+    auto_expire: Arc<RwLock<Option<Duration>>>,
+    /// 待自动过期的数据段。
+    expire_wheel: Arc<RwLock<ExpireWheel>>,
+    /// 由服务端控制的终端模式状态。
+    terminal_modes: Arc<RwLock<TerminalModes>>,
+    /// 已注册的扩展插件。
+    plugins: Arc<RwLock<Vec<SharedPlugin>>>,
+    /// 会话标签，各会话拥有独立的数据缓存和光标状态，共享字体、主题和定时器。
+    sessions: Arc<RwLock<SessionTabs>>,
+    /// 监视关键字，新数据段的文本包含关键字时通过回调函数通知，不影响数据的显示。
+    watches: Arc<RwLock<Watches>>,
+    /// 高亮规则，在添加数据时拆分文本并调整匹配文字的样式。
+    highlights: Arc<RwLock<Highlights>>,
+    /// 具名样式定义。
+    styles: Arc<RwLock<HashMap<String, StyleDef>>>,
+    /// 按互动行为类别统计的数据到达情况。
+    category_stats: Arc<RwLock<BTreeMap<String, CategoryStats>>>,
+    /// 空闲检测状态，空闲时暂停闪烁并降低定时器频率。
+    idle_state: Arc<RwLock<IdleState>>,
+    /// 自动换行的断行策略。
+    wrap_policy: Arc<RwLock<WrapPolicy>>,
+    /// 自动换行产生的后续行的悬挂缩进宽度。
+    hanging_indent: Arc<AtomicI32>,
+    /// 换行试算缓存，由主视图和回顾区共享。
+    wrap_cache: Arc<RwLock<WrapCache>>,
+    /// 复制选中文字时是否在自动折行处插入换行符。
+    copy_soft_wraps: Arc<AtomicBool>,
+    /// 常规流中文本的行高与字体尺寸的比例。
+    line_height_factor: Arc<RwLock<f32>>,
+    /// 常规流中逻辑行结束后额外增加的段落间距。
+    paragraph_spacing: Arc<AtomicI32>,
+    /// 主视图内容与面板边界之间的空白。
+    padding: Arc<RwLock<Padding>>,
+    /// 新数据段淡入效果的持续时间，为空时关闭淡入效果。
+    fade_in: Arc<RwLock<Option<Duration>>>,
+    /// 最近一个淡入效果的结束时刻，在此之前定时刷新主视图。
+    fade_until: Arc<RwLock<Option<Instant>>>,
+    /// 无事可做时暂停的定时器。
+    timers: Arc<RwLock<TimerSet>>,
+    /// ANSI 16色调色板，为空时按照数据段自带的颜色绘制。
+    ansi_palette: Arc<RwLock<Option<[Color; 16]>>>,
+    /// 应用或主题设置的调色板，启用色觉障碍配色时以此为基础调整。
+    user_palette: Arc<RwLock<Option<[Color; 16]>>>,
+    /// 修饰键点击绑定。
+    modifier_clicks: Arc<RwLock<HashMap<Modifier, ClickBinding>>>,
+    /// 背景图片。
+    background_image: Arc<RwLock<BackgroundImage>>,
+    /// 已折叠的线索根数据段ID。
+    collapsed_threads: Arc<RwLock<HashSet<i64>>>,
+    /// 线索索引，记录主缓存中数据段ID对应的线索层级和隐藏状态，追加回复时据此查找父数据段。
+    thread_index: Arc<RwLock<HashMap<i64, (usize, bool)>>>,
+    /// 光标闪烁间隔时间，单位秒，小于等于0时光标不闪烁。
+    cursor_blink_interval: Arc<RwLock<f64>>,
+    /// 慢速闪烁内容的强度切换间隔时间。
+    blink_interval: Arc<RwLock<f64>>,
+    /// 快速闪烁内容的强度切换间隔时间。
+    rapid_blink_interval: Arc<RwLock<f64>>,
+    /// 链接预览提供者，为空时不检测新数据中的网址。
+    preview_provider: Arc<RwLock<Option<Box<dyn PreviewProvider>>>>,
+    /// 是否显示链接预览卡片。
+    link_preview_enabled: Arc<AtomicBool>,
+    /// 会话日志。
+    logger: Arc<RwLock<Option<LogSink>>>,
+    /// 最近请求过预览信息的网址，每个网址只在首次出现时显示预览卡片，数量超过`PREVIEWED_URLS_CAPACITY`时淘汰最久未出现的网址。
+    previewed_urls: Arc<RwLock<RecentUrls>>,
+    /// 回顾区是否在新数据到达时保持当前查找目标垂直居中。
+    follow_search_focus: Arc<AtomicBool>,
+    /// 是否在主视图中直接查找，而不打开回顾区。
+    search_in_main: Arc<AtomicBool>,
+    /// 主视图中的查找字符串，以及当前高亮目标所在数据段的ID和目标序号。
+    main_search: Arc<RwLock<Option<(String, i64, usize)>>>,
+    /// 持久高亮的关键字，按添加顺序排列。
+    keywords: Arc<RwLock<Vec<KeywordHighlight>>>,
+    /// 输入行模式下的输入内容，为空表示未启用输入行模式。
+    prompt: Arc<RwLock<Option<PromptLine>>>,
+    /// 底部回显区中的各行文字。
+    echo_area: Arc<RwLock<Vec<String>>>,
+    /// 回显区占用的高度，已计入`padding`的底部空白。
+    echo_reserve: Arc<AtomicI32>,
+    /// 用户设置的空白，`padding`在此基础上额外计入固定行和回显区的高度。
+    user_padding: Arc<RwLock<Padding>>,
+    /// 顶部和底部的固定行。
+    pinned: Arc<RwLock<PinnedRows>>,
+    /// 回顾区分栏显示的栏数。
+    review_columns: Arc<AtomicUsize>,
+    /// 朗读新文字行的钩子函数。
+    speech_hook: Arc<RwLock<Option<SpeechHook>>>,
+    /// 等待换行符的朗读文字。
+    speech_lines: Arc<RwLock<SpeechLines>>,
+    /// 绘制时使用的颜色变换。
+    color_transform: Arc<RwLock<ColorTransform>>,
+    /// 禁用数据段的显示样式。
+    disabled_style: Arc<RwLock<DisabledStyle>>,
 }
 widget_extends!(RichText, Flex, inner);
 
@@ -116,12 +677,20 @@ impl RichText {
         let visible_lines = Arc::new(RwLock::new(HashMap::<Rectangle, LinePiece>::new()));
         let clickable_data = Arc::new(RwLock::new(HashMap::<Rectangle, usize>::new()));
         let notifier: Arc<RwLock<Option<Callback>>> = Arc::new(RwLock::new(None));
+        let tooltip = Arc::new(RwLock::new(TooltipState::default()));
+        let context_menu_hook: Arc<RwLock<Option<ContextMenuHook>>> = Arc::new(RwLock::new(None));
+        let blank_menu = Arc::new(AtomicBool::new(false));
+        let paused: Arc<RwLock<Option<PauseState>>> = Arc::new(RwLock::new(None));
         let selected = Arc::new(AtomicBool::new(false));
+        let selected_pieces = Arc::new(RwLock::new(Vec::<Weak<RwLock<LinePiece>>>::new()));
+        // 选区两端所在的数据段id和字符位置，分片被重建后据此恢复选区。
+        let selection: Arc<RwLock<Option<SelectionRange>>> = Arc::new(RwLock::new(None));
         let should_resize_content = Arc::new(AtomicI32::new(0));
         let enable_blink = Arc::new(AtomicBool::new(true));
         let basic_char = Arc::new(RwLock::new(BASIC_UNIT_CHAR));
         let tab_width = Arc::new(AtomicU8::new(DEFAULT_TAB_WIDTH));
-        let cursor_piece = LinePiece::init_piece(DEFAULT_FONT_SIZE);
+        let padding = Arc::new(RwLock::new(PADDING));
+        let cursor_piece = LinePiece::init_piece(DEFAULT_FONT_SIZE, &PADDING);
         let show_cursor = Arc::new(AtomicBool::new(false));
         let remote_flow_control = Arc::new(AtomicBool::new(true));
         // let temp_buffer = Arc::new(RwLock::new(Some(Vec::new())));
@@ -129,6 +698,42 @@ impl RichText {
         let rewrite_board: Arc<RwLock<Option<ReWriteBoard>>> = Arc::new(RwLock::new(None));
         let max_rows = Arc::new(AtomicUsize::new(1usize));
         let max_cols = Arc::new(AtomicUsize::new(1usize));
+        let gutter_width = Arc::new(AtomicI32::new(0));
+        let line_counter = Arc::new(AtomicUsize::new(1usize));
+        let split_scrollback = Arc::new(AtomicBool::new(false));
+        let append_top = Arc::new(AtomicBool::new(false));
+        let top_insert_pos = Arc::new(AtomicUsize::new(0));
+        let alternate_saved: Arc<RwLock<Option<AlternateSaved>>> = Arc::new(RwLock::new(None));
+        let show_translations = Arc::new(AtomicBool::new(true));
+        let scroll_region = Arc::new(RwLock::new((0usize, 0usize)));
+        let dedup_window = Arc::new(RwLock::new(DedupWindow::default()));
+        let tab_stops: Arc<RwLock<Option<BTreeSet<usize>>>> = Arc::new(RwLock::new(None));
+        let current_col = Arc::new(AtomicUsize::new(0));
+        let auto_expire: Arc<RwLock<Option<Duration>>> = Arc::new(RwLock::new(None));
+        let expire_wheel = Arc::new(RwLock::new(ExpireWheel::default()));
+        let terminal_modes = Arc::new(RwLock::new(TerminalModes::default()));
+        let plugins: Arc<RwLock<Vec<SharedPlugin>>> = Arc::new(RwLock::new(Vec::new()));
+        let fade_until: Arc<RwLock<Option<Instant>>> = Arc::new(RwLock::new(None));
+        let ansi_palette: Arc<RwLock<Option<[Color; 16]>>> = Arc::new(RwLock::new(None));
+        let modifier_clicks: Arc<RwLock<HashMap<Modifier, ClickBinding>>> = Arc::new(RwLock::new(HashMap::new()));
+        let background_image = Arc::new(RwLock::new(BackgroundImage::new()));
+        let cursor_blink_interval = Arc::new(RwLock::new(BLINK_INTERVAL));
+        let blink_interval = Arc::new(RwLock::new(BLINK_INTERVAL));
+        let rapid_blink_interval = Arc::new(RwLock::new(RAPID_BLINK_INTERVAL));
+        let follow_search_focus = Arc::new(AtomicBool::new(false));
+        let keywords: Arc<RwLock<Vec<KeywordHighlight>>> = Arc::new(RwLock::new(vec![]));
+        let prompt: Arc<RwLock<Option<PromptLine>>> = Arc::new(RwLock::new(None));
+        let echo_area: Arc<RwLock<Vec<String>>> = Arc::new(RwLock::new(vec![]));
+        let echo_reserve = Arc::new(AtomicI32::new(0));
+        let user_padding = Arc::new(RwLock::new(PADDING));
+        let pinned: Arc<RwLock<PinnedRows>> = Arc::new(RwLock::new(PinnedRows::default()));
+        let review_columns = Arc::new(AtomicUsize::new(1));
+        let color_transform = Arc::new(RwLock::new(ColorTransform::default()));
+        let disabled_style = Arc::new(RwLock::new(DisabledStyle::default()));
+        let wrap_policy = Arc::new(RwLock::new(WrapPolicy::Anywhere));
+        let hanging_indent = Arc::new(AtomicI32::new(0));
+        let copy_soft_wraps = Arc::new(AtomicBool::new(false));
+        let wrap_cache = Arc::new(RwLock::new(WrapCache::new(DEFAULT_WRAP_CACHE_CAPACITY)));
 
         let _ = Self::update_window_size(
             text_font.clone(),
@@ -139,12 +744,17 @@ impl RichText {
             max_rows.clone(),
             max_cols.clone(),
             rewrite_board.clone(),
+            &PADDING,
         );
 
         // 数据段闪烁控制器
         let blink_flag = Arc::new(RwLock::new(BlinkState::new()));
+        let render_options = Arc::new(RwLock::new(RenderOptions::default()));
+        let timers = Arc::new(RwLock::new(TimerSet::default()));
+        let logger: Arc<RwLock<Option<LogSink>>> = Arc::new(RwLock::new(None));
 
-        let update_panel_fn = Arc::new(RwLock::new(TokioDebounce::new_debounce({
+        let panel_updater = PanelUpdater::new({
+            let paused_rc = paused.clone();
             let mut panel_rc = panel.clone();
             let screen_rc = panel_screen.clone();
             let visible_lines_rc = visible_lines.clone();
@@ -152,31 +762,104 @@ impl RichText {
             let bg_rc = background_color.clone();
             let buffer_rc = current_buffer.clone();
             let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
             let show_cursor_rc = show_cursor.clone();
             let cursor_piece_rc = cursor_piece.clone();
+            let gutter_width_rc = gutter_width.clone();
+            let reviewer_rc = reviewer.clone();
+            let append_top_rc = append_top.clone();
+            let background_image_rc = background_image.clone();
+            let rewrite_board_rc = rewrite_board.clone();
+            let padding_rc = padding.clone();
+            let pinned_rc = pinned.clone();
+            let selected_pieces_rc = selected_pieces.clone();
+            let selection_rc = selection.clone();
+            let color_transform_rc = color_transform.clone();
+            let timers_rc = timers.clone();
             move |redraw: bool| {
                 let enable_cursor = if show_cursor_rc.load(Ordering::Relaxed) {
                     Some(cursor_piece_rc.clone())
                 } else {
                     None
                 };
-                // debug!("update_panel_fn");
-                Self::draw_offline(
-                    screen_rc.clone(),
-                    &mut panel_rc,
-                    visible_lines_rc.clone(),
-                    clickable_data_rc.clone(),
-                    *bg_rc.read(),
-                    buffer_rc.clone(),
-                    blink_flag_rc.clone(),
-                    enable_cursor,
-               );
+                let gutter_width = gutter_width_rc.load(Ordering::Relaxed);
+                let pin_top = append_top_rc.load(Ordering::Relaxed);
+                if paused_rc.read().is_some() && !pin_top {
+                    // 暂停输出时保持当前画面，只刷新暂停标记。
+                    panel_rc.redraw();
+                    return;
+                }
+                keep_selection(buffer_rc.read().as_slice(), &selection_rc, selected_pieces_rc.clone());
+                let _transform = ColorTransformScope::enter(&color_transform_rc.read());
+                let mut board = rewrite_board_rc.write();
+                // 定位面板只发生了区域滚动或少量行变化时增量刷新，避免每次重绘整个面板。
+                let drawn = match board.as_mut() {
+                    Some(board) => {
+                        let damage = board.take_damage();
+                        damage.is_partial() && gutter_width == 0 && !pin_top && !background_image_rc.read().has_image()
+                            && Self::draw_board_offline(
+                                screen_rc.clone(),
+                                &mut panel_rc,
+                                visible_lines_rc.clone(),
+                                clickable_data_rc.clone(),
+                                *bg_rc.read(),
+                                buffer_rc.clone(),
+                                blink_flag_rc.clone(),
+                                &render_options_rc.read(),
+                                enable_cursor.clone(),
+                                board,
+                                &damage,
+                            )
+                    }
+                    None => false,
+                };
+                if !drawn {
+                    // debug!("update_panel_fn");
+                    Self::draw_offline(
+                        screen_rc.clone(),
+                        &mut panel_rc,
+                        visible_lines_rc.clone(),
+                        clickable_data_rc.clone(),
+                        *bg_rc.read(),
+                        buffer_rc.clone(),
+                        blink_flag_rc.clone(),
+                        &render_options_rc.read(),
+                        enable_cursor.clone(),
+                        gutter_width,
+                        pin_top,
+                        background_image_rc.clone(),
+                        *padding_rc.read(),
+                        &pinned_rc.read(),
+                    );
+                }
+                if let Some(board) = board.as_mut() {
+                    board.cursor_row = enable_cursor.and_then(|cursor| board.row_at(cursor.read().y));
+                    let window_height = panel_rc.height();
+                    if buffer_rc.read().iter().any(|rd| rd.v_bounds.read().1 > window_height) {
+                        // 内容超出面板时绘制位置发生了偏移，下次必须完整重绘。
+                        board.invalidate();
+                    }
+                }
+                drop(board);
+                if blink_flag_rc.read().rapid_on {
+                    // 可视区域出现快速闪烁内容时启动已暂停的快速闪烁定时器。
+                    timers_rc.write().wake(RAPID_BLINK_TIMER);
+                }
+                if let Some(rv) = reviewer_rc.write().as_mut() {
+                    if Arc::ptr_eq(&rv.data_buffer, &buffer_rc) {
+                        // 分屏回看模式下同步更新回顾区的内容高度
+                        rv.refresh_panel_height();
+                    }
+                }
                 if redraw {
                     panel_rc.redraw();
                 }
                // panel_rc.set_damage(true);
            }
-        }, Duration::from_millis(20), true)));
+        });
+
+        let update_panel_fn = Arc::new(RwLock::new(panel_updater.debounce(PANEL_UPDATE_DEBOUNCE)));
+        let deterministic = Arc::new(AtomicBool::new(false));
 
         let mut create_reviewer_fn = TokioDebounce::new_throttle({
             let mut flex = inner.clone();
@@ -186,56 +869,115 @@ impl RichText {
             let selected_rc = selected.clone();
             let enable_blink_rc = enable_blink.clone();
             let blink_flag_rc = blink_flag.clone();
+            let blink_interval_rc = blink_interval.clone();
+            let rapid_blink_interval_rc = rapid_blink_interval.clone();
+            let follow_search_focus_rc = follow_search_focus.clone();
+            let keywords_rc = keywords.clone();
             let basic_char_rc = basic_char.clone();
             let bg_rc = background_color.clone();
             let notifier_rc = notifier.clone();
             let remote_flow_control_rc = remote_flow_control.clone();
             let reviewer_rc = reviewer.clone();
+            let gutter_width_rc = gutter_width.clone();
+            let split_scrollback_rc = split_scrollback.clone();
+            let append_top_rc = append_top.clone();
             let update_panel_fn = update_panel_fn.clone();
             let should_resize = should_resize_content.clone();
+            let ansi_palette_rc = ansi_palette.clone();
+            let text_color_rc = text_color.clone();
+            let text_font_rc = text_font.clone();
+            let user_padding_rc = user_padding.clone();
+            let review_columns_rc = review_columns.clone();
+            let color_transform_rc = color_transform.clone();
+            let disabled_style_rc = disabled_style.clone();
+            let wrap_policy_rc = wrap_policy.clone();
+            let hanging_indent_rc = hanging_indent.clone();
+            let wrap_cache_rc = wrap_cache.clone();
+            let copy_soft_wraps_rc = copy_soft_wraps.clone();
+            let tooltip_rc = tooltip.clone();
             move |()| {
                 // 显示回顾区
-                let mut reviewer = RichReviewer::new(0, 0, flex.width(), flex.height() - MAIN_PANEL_FIX_HEIGHT, None);
+                let split = split_scrollback_rc.load(Ordering::Relaxed) && remote_flow_control_rc.load(Ordering::SeqCst);
+                let main_panel_height = Self::main_panel_height(split, flex.height());
+                // 回顾区沿用主视图的排版时直接共享数据缓存，不再复制快照；分栏显示需要重新排版，只能使用快照。
+                let shared = split || (remote_flow_control_rc.load(Ordering::SeqCst) && review_columns_rc.load(Ordering::Relaxed) <= 1);
+                let mut reviewer = if shared {
+                    RichReviewer::with_buffer(0, 0, flex.width(), flex.height() - main_panel_height, None, buffer_rc.clone())
+                } else {
+                    RichReviewer::new(0, 0, flex.width(), flex.height() - main_panel_height, None)
+                };
                 reviewer.set_enable_blink(enable_blink_rc.load(Ordering::Relaxed));
                 reviewer.set_blink_state(blink_flag_rc.read().clone());
+                reviewer.set_color_transform(color_transform_rc.read().clone());
+                reviewer.set_disabled_style(*disabled_style_rc.read());
+                reviewer.init_wrap_settings(*wrap_policy_rc.read(), hanging_indent_rc.load(Ordering::Relaxed), wrap_cache_rc.clone());
+                reviewer.set_copy_soft_wraps(copy_soft_wraps_rc.load(Ordering::Relaxed));
+                reviewer.set_blink_interval(*blink_interval_rc.read());
+                reviewer.set_rapid_blink_interval(*rapid_blink_interval_rc.read());
+                reviewer.set_follow_search_focus(follow_search_focus_rc.load(Ordering::Relaxed));
+                reviewer.set_keyword_highlights(keywords_rc.read().clone());
                 reviewer.set_background_color(*bg_rc.read());
+                reviewer.set_text_color(*text_color_rc.read());
+                reviewer.set_text_font(*text_font_rc.read());
                 reviewer.set_basic_char(*basic_char_rc.read());
+                reviewer.set_padding(*user_padding_rc.read());
+                if !shared {
+                    reviewer.set_columns(review_columns_rc.load(Ordering::Relaxed));
+                }
+                reviewer.set_tooltip_delay(tooltip_rc.read().delay);
+                if let Some(hook) = context_menu_hook_rc.read().as_ref() {
+                    reviewer.set_context_menu_hook(hook.clone());
+                }
+                reviewer.set_blank_context_menu(blank_menu_rc.load(Ordering::Relaxed));
+                reviewer.set_show_line_numbers(gutter_width_rc.load(Ordering::Relaxed) > 0);
+                if let Some(palette) = *ansi_palette_rc.read() {
+                    reviewer.set_ansi_palette(palette);
+                }
                 if let Some(notifier_rc_ref) = notifier_rc.write().as_mut() {
                     let cb = notifier_rc_ref.clone();
                     reviewer.set_notifier(cb);
                 }
-                // let drawable_max_width = flex.w() - PADDING.left - PADDING.right;
-                // let mut snapshot = Self::create_snapshot(buffer_rc.clone());
-                let mut snapshot = if remote_flow_control_rc.load(Ordering::SeqCst) {
-                    // 当前缓存就是主缓存
-                    buffer_rc.read().clone()
+                if shared {
+                    reviewer.refresh_panel_height();
                 } else {
-                    // 当前缓存是临时缓存，主缓存位于data_buffer中。
-                    if let Some(mb) = main_buffer.read().as_ref() {
-                        mb.clone()
+                    // let drawable_max_width = flex.w() - PADDING.left - PADDING.right;
+                    // let mut snapshot = Self::create_snapshot(buffer_rc.clone());
+                    let mut snapshot = if remote_flow_control_rc.load(Ordering::SeqCst) {
+                        // 当前缓存就是主缓存，图片数据通过Arc共享，复制快照时不会复制图片内容。
+                        buffer_rc.read().clone()
                     } else {
-                        vec![]
+                        // 当前缓存是临时缓存，主缓存位于data_buffer中。
+                        if let Some(mb) = main_buffer.read().as_ref() {
+                            mb.clone()
+                        } else {
+                            vec![]
+                        }
+                    };
+                    if selected_rc.load(Ordering::Relaxed) {
+                        snapshot.iter_mut().for_each(|rd| {
+                            rd.line_pieces.iter_mut().for_each(|piece| {
+                                piece.read().deselect();
+                            })
+                        });
                     }
-                };
-                if selected_rc.load(Ordering::Relaxed) {
-                    snapshot.iter_mut().for_each(|rd| {
-                        rd.line_pieces.iter_mut().for_each(|piece| {
-                            piece.read().deselect();
-                        })
-                    });
-                }
 
-                // debug!("历史数据长度：{}", snapshot.len());
+                    // debug!("历史数据长度：{}", snapshot.len());
 
-                reviewer.set_data(snapshot);
+                    reviewer.set_data(snapshot);
+                }
                 flex.insert(&reviewer.scroller, 0);
                 // flex.resizable(&reviewer.scroller);
-                flex.fixed(&panel_rc, MAIN_PANEL_FIX_HEIGHT);
+                flex.fixed(&panel_rc, main_panel_height);
                 flex.recalc();
 
-                should_resize.store(MAIN_PANEL_FIX_HEIGHT, Ordering::Relaxed);
+                should_resize.store(main_panel_height, Ordering::Relaxed);
 
-                reviewer.scroll_to_bottom();
+                if append_top_rc.load(Ordering::Relaxed) {
+                    // 顶部追加模式下最新的内容位于顶部
+                    reviewer.scroll_to_top();
+                } else {
+                    reviewer.scroll_to_bottom();
+                }
                 reviewer_rc.write().replace(reviewer);
                 update_panel_fn.write().update_param(false);
                 // debug!("打开回顾区");
@@ -245,85 +987,332 @@ impl RichText {
             }
         }, Duration::from_millis(100), true);
 
+        let idle_state = Arc::new(RwLock::new(IdleState::new(update_panel_fn.clone(), panel_updater.clone(), timers.clone(), panel.clone(), reviewer.clone())));
         let blink_handler = {
             let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
             let panel_rc = panel.clone();
             let enable_blink_rc = enable_blink.clone();
-            let show_cursor_rc = show_cursor.clone();
+            let blink_interval_rc = blink_interval.clone();
             let update_panel_fn = update_panel_fn.clone();
+            let deterministic_rc = deterministic.clone();
+            let idle_state_rc = idle_state.clone();
+            let timers_rc = timers.clone();
             move |handler| {
                 if !panel_rc.was_deleted() {
-                    if enable_blink_rc.load(Ordering::Relaxed) {
-                        if show_cursor_rc.load(Ordering::Relaxed) {
-                            blink_flag_rc.write().on();
+                    if !deterministic_rc.load(Ordering::Relaxed) {
+                        let idle = idle_state_rc.write().check();
+                        if idle {
+                            // 空闲时停留在正常显示强度并暂停定时器，收到新数据或用户输入时立即恢复。
+                            if blink_flag_rc.write().settle() {
+                                update_panel_fn.write().update_param(false);
+                            }
+                            timers_rc.write().park(BLINK_TIMER);
+                            app::remove_timeout3(handler);
+                            return;
+                        } else if enable_blink_rc.load(Ordering::Relaxed) {
+                            let should_toggle = blink_flag_rc.write().advance(BlinkSpeed::Slow, &render_options_rc.read());
+                            if should_toggle {
+                                // FULL_DRAW.store(false, Ordering::Relaxed);
+                                update_panel_fn.write().update_param(false);
+                            }
                         }
-                        let should_toggle = blink_flag_rc.write().toggle_when_on();
-                        if should_toggle {
-                            // FULL_DRAW.store(false, Ordering::Relaxed);
+                    }
+                    app::repeat_timeout3(*blink_interval_rc.read(), handler);
+                } else {
+                    app::remove_timeout3(handler);
+                }
+            }
+        };
+        timers.write().start(BLINK_TIMER, BLINK_INTERVAL, blink_handler);
+
+        // 快速闪烁内容使用独立的定时器，与慢速闪烁内容分别切换显示强度。
+        let rapid_blink_handler = {
+            let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
+            let panel_rc = panel.clone();
+            let enable_blink_rc = enable_blink.clone();
+            let rapid_blink_interval_rc = rapid_blink_interval.clone();
+            let update_panel_fn = update_panel_fn.clone();
+            let deterministic_rc = deterministic.clone();
+            let idle_state_rc = idle_state.clone();
+            let timers_rc = timers.clone();
+            move |handler| {
+                if !panel_rc.was_deleted() {
+                    let idle = idle_state_rc.read().idle;
+                    if idle || !blink_flag_rc.read().rapid_on {
+                        // 空闲或可视区域没有快速闪烁内容时暂停定时器，绘制出快速闪烁内容或退出空闲状态时再启动。
+                        timers_rc.write().park(RAPID_BLINK_TIMER);
+                        app::remove_timeout3(handler);
+                        return;
+                    }
+                    if enable_blink_rc.load(Ordering::Relaxed) && !deterministic_rc.load(Ordering::Relaxed) {
+                        if blink_flag_rc.write().advance(BlinkSpeed::Rapid, &render_options_rc.read()) {
                             update_panel_fn.write().update_param(false);
                         }
                     }
-                    app::repeat_timeout3(BLINK_INTERVAL, handler);
+                    app::repeat_timeout3(*rapid_blink_interval_rc.read(), handler);
                 } else {
                     app::remove_timeout3(handler);
                 }
             }
         };
-        app::add_timeout3(BLINK_INTERVAL, blink_handler);
+        timers.write().register(RAPID_BLINK_TIMER, RAPID_BLINK_INTERVAL, rapid_blink_handler);
 
-        panel.draw({
-            let screen_rc = panel_screen.clone();
-            let resize_to = should_resize_content.clone();
-            let flex = inner.clone();
-            let visible_lines_rc = visible_lines.clone();
-            let clickable_data_rc = clickable_data.clone();
-            let bg_rc = background_color.clone();
-            let buffer_rc = current_buffer.clone();
+        // 光标使用独立的闪烁定时器，闪烁间隔可单独设置。
+        let cursor_blink_handler = {
             let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
+            let panel_rc = panel.clone();
+            let enable_blink_rc = enable_blink.clone();
             let show_cursor_rc = show_cursor.clone();
-            let cursor_piece_rc = cursor_piece.clone();
-            move |ctx| {
-                // debug!("绘制主面板");
-                let h = resize_to.fetch_add(0, Ordering::Relaxed);
-                if h != 0 {
-                    let enable_cursor = if show_cursor_rc.load(Ordering::Relaxed) {
-                        Some(cursor_piece_rc.clone())
+            let cursor_blink_interval_rc = cursor_blink_interval.clone();
+            let update_panel_fn = update_panel_fn.clone();
+            let deterministic_rc = deterministic.clone();
+            let idle_state_rc = idle_state.clone();
+            let timers_rc = timers.clone();
+            move |handler| {
+                if !panel_rc.was_deleted() {
+                    let interval = *cursor_blink_interval_rc.read();
+                    let idle = idle_state_rc.read().idle;
+                    let show = show_cursor_rc.load(Ordering::Relaxed);
+                    let blinking = show && !idle && interval > 0.0 && enable_blink_rc.load(Ordering::Relaxed) && !deterministic_rc.load(Ordering::Relaxed)
+                        && !render_options_rc.read().reduced_motion();
+                    if blink_flag_rc.write().toggle_cursor(blinking) && show {
+                        update_panel_fn.write().update_param(false);
+                    }
+                    if blinking && blink_flag_rc.read().cursor_blinks() {
+                        app::repeat_timeout3(interval, handler);
                     } else {
-                        None
-                    };
-                    Self::new_offline(
-                        flex.width(),
-                        h,
-                        screen_rc.clone(),
-                        ctx,
-                        visible_lines_rc.clone(),
-                        clickable_data_rc.clone(),
-                        *bg_rc.read(),
-                        buffer_rc.clone(),
-                        blink_flag_rc.clone(),
-                        enable_cursor,
-                    );
+                        // 光标隐藏或不闪烁时保持正常显示并暂停定时器，需要闪烁时再启动。
+                        timers_rc.write().park(CURSOR_BLINK_TIMER);
+                        app::remove_timeout3(handler);
+                    }
+                } else {
+                    app::remove_timeout3(handler);
                 }
-                screen_rc.read().copy(ctx.x(), ctx.y(), ctx.width(), ctx.height(), 0, 0);
             }
-        });
+        };
+        timers.write().register(CURSOR_BLINK_TIMER, BLINK_INTERVAL, cursor_blink_handler);
 
-        /*
-        处理主面板容器的动作事件，打开或关闭回顾区。
-         */
-        inner.handle({
-            let last_window_size = Arc::new(RwLock::new((0, 0)));
-            let panel_rc = panel.clone();
+        let expire_handler = {
+            let expire_wheel_rc = expire_wheel.clone();
+            let current_buffer_rc = current_buffer.clone();
+            let data_buffer_rc = data_buffer.clone();
             let reviewer_rc = reviewer.clone();
-            let main_buffer = data_buffer.clone();
-            let buffer_rc = current_buffer.clone();
+            let panel_rc = panel.clone();
+            let update_panel_fn = update_panel_fn.clone();
+            let deterministic_rc = deterministic.clone();
+            let timers_rc = timers.clone();
+            let idle_state_rc = idle_state.clone();
+            move |handler| {
+                if !panel_rc.was_deleted() {
+                    if !deterministic_rc.load(Ordering::Relaxed) && Self::expire_due(&expire_wheel_rc, &current_buffer_rc, &data_buffer_rc, &reviewer_rc) {
+                        update_panel_fn.write().update_param(false);
+                    }
+                    if expire_wheel_rc.read().is_empty() || idle_state_rc.read().idle {
+                        // 没有待过期的数据段或处于空闲状态时暂停定时器，登记新的待过期数据段或退出空闲状态时再启动，届时一并处理已到期的数据段。
+                        timers_rc.write().park(EXPIRE_TIMER);
+                        app::remove_timeout3(handler);
+                    } else {
+                        app::repeat_timeout3(AUTO_EXPIRE_INTERVAL, handler);
+                    }
+                } else {
+                    app::remove_timeout3(handler);
+                }
+            }
+        };
+        timers.write().register(EXPIRE_TIMER, AUTO_EXPIRE_INTERVAL, expire_handler);
+
+        let log_flush_handler = {
+            let logger_rc = logger.clone();
+            let timers_rc = timers.clone();
+            move |handler| {
+                if let Some(logger) = logger_rc.write().as_mut() {
+                    if let Err(e) = logger.flush() {
+                        error!("写入会话日志失败：{:?} {:?}", logger.path(), e);
+                    }
+                }
+                // 每次写入后暂停，有新的数据段写入缓冲区时再启动。
+                timers_rc.write().park(LOG_FLUSH_TIMER);
+                app::remove_timeout3(handler);
+            }
+        };
+        timers.write().register(LOG_FLUSH_TIMER, LOG_FLUSH_INTERVAL, log_flush_handler);
+
+        let fade_handler = {
+            let fade_until_rc = fade_until.clone();
+            let panel_rc = panel.clone();
+            let update_panel_fn = update_panel_fn.clone();
+            let deterministic_rc = deterministic.clone();
+            let timers_rc = timers.clone();
+            move |handler| {
+                if !panel_rc.was_deleted() {
+                    let fading = if deterministic_rc.load(Ordering::Relaxed) { None } else { *fade_until_rc.read() };
+                    if let Some(until) = fading {
+                        // 淡入结束后再刷新一次，清除残留的着色。
+                        if deterministic::now() >= until {
+                            fade_until_rc.write().take();
+                        }
+                        update_panel_fn.write().update_param(false);
+                        app::repeat_timeout3(FADE_IN_INTERVAL, handler);
+                    } else {
+                        // 没有正在淡入的数据段时暂停定时器，新数据开始淡入时再启动。
+                        timers_rc.write().park(FADE_TIMER);
+                        app::remove_timeout3(handler);
+                    }
+                } else {
+                    app::remove_timeout3(handler);
+                }
+            }
+        };
+        timers.write().start(FADE_TIMER, FADE_IN_INTERVAL, fade_handler);
+
+        panel.draw({
+            let screen_rc = panel_screen.clone();
+            let resize_to = should_resize_content.clone();
+            let flex = inner.clone();
+            let visible_lines_rc = visible_lines.clone();
+            let clickable_data_rc = clickable_data.clone();
+            let bg_rc = background_color.clone();
+            let buffer_rc = current_buffer.clone();
+            let blink_flag_rc = blink_flag.clone();
+            let render_options_rc = render_options.clone();
+            let show_cursor_rc = show_cursor.clone();
+            let cursor_piece_rc = cursor_piece.clone();
+            let gutter_width_rc = gutter_width.clone();
+            let append_top_rc = append_top.clone();
+            let plugins_rc = plugins.clone();
+            let max_rows_rc = max_rows.clone();
+            let max_cols_rc = max_cols.clone();
+            let background_image_rc = background_image.clone();
+            let padding_rc = padding.clone();
+            let pinned_rc = pinned.clone();
+            let tooltip_rc = tooltip.clone();
+            let paused_rc = paused.clone();
+            let prompt_rc = prompt.clone();
+            let echo_area_rc = echo_area.clone();
+            let echo_reserve_rc = echo_reserve.clone();
+            let text_font_rc = text_font.clone();
+            let text_size_rc = text_size.clone();
+            let text_color_rc = text_color.clone();
+            let color_transform_rc = color_transform.clone();
+            move |ctx| {
+                // debug!("绘制主面板");
+                let h = resize_to.fetch_add(0, Ordering::Relaxed);
+                if h != 0 {
+                    let _transform = ColorTransformScope::enter(&color_transform_rc.read());
+                    let enable_cursor = if show_cursor_rc.load(Ordering::Relaxed) {
+                        Some(cursor_piece_rc.clone())
+                    } else {
+                        None
+                    };
+                    Self::new_offline(
+                        flex.width(),
+                        h,
+                        screen_rc.clone(),
+                        ctx,
+                        visible_lines_rc.clone(),
+                        clickable_data_rc.clone(),
+                        *bg_rc.read(),
+                        buffer_rc.clone(),
+                        blink_flag_rc.clone(),
+                        &render_options_rc.read(),
+                        enable_cursor,
+                        gutter_width_rc.load(Ordering::Relaxed),
+                        append_top_rc.load(Ordering::Relaxed),
+                        background_image_rc.clone(),
+                        *padding_rc.read(),
+                        &pinned_rc.read(),
+                    );
+                }
+                screen_rc.read().copy(ctx.x(), ctx.y(), ctx.width(), ctx.height(), 0, 0);
+
+                let plugins = snapshot_plugins(&plugins_rc);
+                if !plugins.is_empty() {
+                    let view = Self::panel_view(ctx, buffer_rc.clone(), append_top_rc.load(Ordering::Relaxed), max_rows_rc.load(Ordering::Relaxed), max_cols_rc.load(Ordering::Relaxed), &padding_rc.read());
+                    for plugin in plugins {
+                        plugin.write().on_draw_overlay(&view);
+                    }
+                }
+                if let Some(prompt) = prompt_rc.read().as_ref() {
+                    // 输入行绘制在光标位置，跟随输出内容移动。
+                    let cursor = cursor_piece_rc.read().clone();
+                    let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                    draw_prompt_line(
+                        prompt,
+                        (ctx.x() + cursor.x, ctx.y() + cursor.y - scroll_y, cursor.font_height),
+                        (ctx.x(), ctx.y(), ctx.w(), ctx.h()),
+                        *text_font_rc.read(),
+                        text_size_rc.load(Ordering::Relaxed),
+                        *text_color_rc.read(),
+                        *bg_rc.read(),
+                        ctx.has_focus(),
+                    );
+                }
+                let echo_height = echo_reserve_rc.load(Ordering::Relaxed);
+                if echo_height > 0 {
+                    // 回显区固定在底部空白中，位于最后一行输出之下。
+                    let padding = *padding_rc.read();
+                    draw_echo_area(
+                        &echo_area_rc.read(),
+                        (ctx.x(), ctx.y() + ctx.h() - padding.bottom, ctx.w(), echo_height),
+                        padding.left,
+                        *text_font_rc.read(),
+                        text_size_rc.load(Ordering::Relaxed),
+                        *text_color_rc.read(),
+                        *bg_rc.read(),
+                    );
+                }
+                if let Some(state) = *paused_rc.read() {
+                    draw_pause_indicator((ctx.x(), ctx.y(), ctx.w(), ctx.h()), buffer_rc.read().len().saturating_sub(state.len));
+                }
+                draw_tooltip(&tooltip_rc.read(), (ctx.x(), ctx.y(), ctx.w(), ctx.h()));
+            }
+        });
+
+        /*
+        处理主面板容器的动作事件，打开或关闭回顾区。
+         */
+        inner.handle({
+            let last_window_size = Arc::new(RwLock::new((0, 0)));
+            let panel_rc = panel.clone();
+            let reviewer_rc = reviewer.clone();
+            let main_buffer = data_buffer.clone();
+            let buffer_rc = current_buffer.clone();
             let bg_rc = background_color.clone();
             let notifier_rc = notifier.clone();
             let should_resize = should_resize_content.clone();
             let enable_blink_rc = enable_blink.clone();
             let blink_flag_rc = blink_flag.clone();
+            let blink_interval_rc = blink_interval.clone();
+            let rapid_blink_interval_rc = rapid_blink_interval.clone();
+            let follow_search_focus_rc = follow_search_focus.clone();
+            let keywords_rc = keywords.clone();
             let basic_char_rc = basic_char.clone();
             let remote_flow_control_rc = remote_flow_control.clone();
+            let gutter_width_rc = gutter_width.clone();
+            let split_scrollback_rc = split_scrollback.clone();
+            let append_top_rc = append_top.clone();
+            let ansi_palette_rc = ansi_palette.clone();
+            let text_color_rc = text_color.clone();
+            let text_font_rc = text_font.clone();
+            let text_size_rc = text_size.clone();
+            let cursor_piece_rc = cursor_piece.clone();
+            let rewrite_board_rc = rewrite_board.clone();
+            let top_insert_pos_rc = top_insert_pos.clone();
+            let max_rows_rc = max_rows.clone();
+            let max_cols_rc = max_cols.clone();
+            let update_panel_fn = update_panel_fn.clone();
+            let user_padding_rc = user_padding.clone();
+            let review_columns_rc = review_columns.clone();
+            let color_transform_rc = color_transform.clone();
+            let disabled_style_rc = disabled_style.clone();
+            let wrap_policy_rc = wrap_policy.clone();
+            let hanging_indent_rc = hanging_indent.clone();
+            let wrap_cache_rc = wrap_cache.clone();
+            let copy_soft_wraps_rc = copy_soft_wraps.clone();
+            let tooltip_rc = tooltip.clone();
             move |flex, evt| {
                 if evt == LocalEvent::DROP_REVIEWER_FROM_EXTERNAL.into() {
                     // 隐藏回顾区
@@ -335,36 +1324,76 @@ impl RichText {
                     );
                     true
                 } else if evt == LocalEvent::OPEN_REVIEWER_FROM_EXTERNAL.into() {
-                    let mut reviewer = RichReviewer::new(0, 0, flex.width(), flex.height() - MAIN_PANEL_FIX_HEIGHT, None);
+                    let split = split_scrollback_rc.load(Ordering::Relaxed) && remote_flow_control_rc.load(Ordering::SeqCst);
+                    let main_panel_height = Self::main_panel_height(split, flex.height());
+                    // 回顾区沿用主视图的排版时直接共享数据缓存，不再复制快照；分栏显示需要重新排版，只能使用快照。
+                    let shared = split || (remote_flow_control_rc.load(Ordering::SeqCst) && review_columns_rc.load(Ordering::Relaxed) <= 1);
+                    let mut reviewer = if shared {
+                        RichReviewer::with_buffer(0, 0, flex.width(), flex.height() - main_panel_height, None, buffer_rc.clone())
+                    } else {
+                        RichReviewer::new(0, 0, flex.width(), flex.height() - main_panel_height, None)
+                    };
                     reviewer.set_enable_blink(enable_blink_rc.load(Ordering::Relaxed));
                     reviewer.set_blink_state(blink_flag_rc.read().clone());
+                    reviewer.set_color_transform(color_transform_rc.read().clone());
+                    reviewer.set_disabled_style(*disabled_style_rc.read());
+                    reviewer.init_wrap_settings(*wrap_policy_rc.read(), hanging_indent_rc.load(Ordering::Relaxed), wrap_cache_rc.clone());
+                    reviewer.set_copy_soft_wraps(copy_soft_wraps_rc.load(Ordering::Relaxed));
+                    reviewer.set_blink_interval(*blink_interval_rc.read());
+                    reviewer.set_rapid_blink_interval(*rapid_blink_interval_rc.read());
+                    reviewer.set_follow_search_focus(follow_search_focus_rc.load(Ordering::Relaxed));
+                    reviewer.set_keyword_highlights(keywords_rc.read().clone());
                     reviewer.set_background_color(*bg_rc.read());
+                    reviewer.set_text_color(*text_color_rc.read());
+                    reviewer.set_text_font(*text_font_rc.read());
                     reviewer.set_basic_char(*basic_char_rc.read());
+                    reviewer.set_padding(*user_padding_rc.read());
+                    if !shared {
+                        reviewer.set_columns(review_columns_rc.load(Ordering::Relaxed));
+                    }
+                    reviewer.set_tooltip_delay(tooltip_rc.read().delay);
+                    if let Some(hook) = context_menu_hook_rc.read().as_ref() {
+                        reviewer.set_context_menu_hook(hook.clone());
+                    }
+                    reviewer.set_blank_context_menu(blank_menu_rc.load(Ordering::Relaxed));
+                    reviewer.set_show_line_numbers(gutter_width_rc.load(Ordering::Relaxed) > 0);
+                    if let Some(palette) = *ansi_palette_rc.read() {
+                        reviewer.set_ansi_palette(palette);
+                    }
                     if let Some(notifier_rc) = notifier_rc.read().as_ref() {
                         reviewer.set_notifier(notifier_rc.clone());
                     }
-                    // let drawable_max_width = flex.w() - PADDING.left - PADDING.right;
-                    // let snapshot = Self::create_snapshot(buffer_rc.clone());
-                    let snapshot = if remote_flow_control_rc.load(Ordering::SeqCst) {
-                        // 当前缓存就是主缓存
-                        buffer_rc.read().clone()
+                    if shared {
+                        reviewer.refresh_panel_height();
                     } else {
-                        // 当前缓存是临时缓存，主缓存位于data_buffer中。
-                        if let Some(mb) = main_buffer.read().as_ref() {
-                            mb.clone()
+                        // let drawable_max_width = flex.w() - PADDING.left - PADDING.right;
+                        // let snapshot = Self::create_snapshot(buffer_rc.clone());
+                        let snapshot = if remote_flow_control_rc.load(Ordering::SeqCst) {
+                            // 当前缓存就是主缓存
+                            buffer_rc.read().clone()
                         } else {
-                            vec![]
-                        }
-                    };
-                    reviewer.set_data(snapshot);
+                            // 当前缓存是临时缓存，主缓存位于data_buffer中。
+                            if let Some(mb) = main_buffer.read().as_ref() {
+                                mb.clone()
+                            } else {
+                                vec![]
+                            }
+                        };
+                        reviewer.set_data(snapshot);
+                    }
                     flex.insert(&reviewer.scroller, 0);
-                    flex.fixed(&panel_rc, MAIN_PANEL_FIX_HEIGHT);
+                    flex.fixed(&panel_rc, main_panel_height);
                     flex.recalc();
 
                     // 替换新的离线绘制板
-                    should_resize.store(MAIN_PANEL_FIX_HEIGHT, Ordering::Relaxed);
+                    should_resize.store(main_panel_height, Ordering::Relaxed);
 
-                    reviewer.scroll_to_bottom();
+                    if append_top_rc.load(Ordering::Relaxed) {
+                        // 顶部追加模式下最新的内容位于顶部
+                        reviewer.scroll_to_top();
+                    } else {
+                        reviewer.scroll_to_bottom();
+                    }
                     reviewer_rc.write().replace(reviewer);
                     true
                 } else {
@@ -378,8 +1407,8 @@ impl RichText {
                                     lws.0 = current_width;
                                     lws.1 = current_height;
                                 }
-                                let panel_height = if reviewer_rc.read().is_some() {
-                                    MAIN_PANEL_FIX_HEIGHT
+                                let panel_height = if let Some(rv) = &*reviewer_rc.read() {
+                                    Self::main_panel_height(Arc::ptr_eq(&rv.data_buffer, &buffer_rc), current_height)
                                 } else {
                                     current_height
                                 };
@@ -394,6 +1423,43 @@ impl RichText {
                             }
                             // debug!("容器面板缩放");
                         }
+                        Event::MouseWheel if app::is_event_ctrl() => {
+                            /*
+                            按住Ctrl键时缩放字体，不再显示或隐藏回顾区。
+                             */
+                            if app::event_inside_widget(flex) {
+                                let new_size = zoom_font_size(text_size_rc.load(Ordering::Relaxed), app::event_dy() == MouseWheel::Down);
+                                let top_insert_pos = if append_top_rc.load(Ordering::Relaxed) {
+                                    Some(top_insert_pos_rc.load(Ordering::Relaxed))
+                                } else {
+                                    None
+                                };
+                                let zoomed = Self::apply_zoom(
+                                    new_size,
+                                    &panel_rc,
+                                    text_size_rc.clone(),
+                                    text_font_rc.clone(),
+                                    basic_char_rc.clone(),
+                                    buffer_rc.clone(),
+                                    main_buffer.clone(),
+                                    remote_flow_control_rc.load(Ordering::SeqCst),
+                                    top_insert_pos,
+                                    rewrite_board_rc.clone(),
+                                    cursor_piece_rc.clone(),
+                                    reviewer_rc.clone(),
+                                    max_rows_rc.clone(),
+                                    max_cols_rc.clone(),
+                                    *padding_rc.read(),
+                                );
+                                if zoomed {
+                                    update_panel_fn.write().update_param(false);
+                                    if let Some(cb) = notifier_rc.write().as_mut() {
+                                        cb.notify(CallbackData::Zoom(new_size));
+                                    }
+                                }
+                                return true;
+                            }
+                        }
                         Event::MouseWheel => {
                             /*
                             显示或隐藏回顾区。
@@ -430,8 +1496,12 @@ impl RichText {
             let selected = selected.clone();
             let mut select_from_row = 0;
             let mut push_from_point = ClickPoint::new(0, 0);
-            let selected_pieces = Arc::new(RwLock::new(Vec::<Weak<RwLock<LinePiece>>>::new()));
+            let mut push_anchor: Option<(SelectionAnchor, i32)> = None;
+            let mut shift_extended = false;
+            let selected_pieces = selected_pieces.clone();
+            let selection_rc = selection.clone();
             let should_resize = should_resize_content.clone();
+            let paused_rc = paused.clone();
             let text_font_rc = text_font.clone();
             let text_size_rc = text_size.clone();
             let basic_char_rc = basic_char.clone();
@@ -439,7 +1509,65 @@ impl RichText {
             let max_rows_rc = max_rows.clone();
             let max_cols_rc = max_cols.clone();
             let update_panel_fn = update_panel_fn.clone();
+            let append_top_rc = append_top.clone();
+            let top_insert_pos_rc = top_insert_pos.clone();
+            let terminal_modes_rc = terminal_modes.clone();
+            let plugins_rc = plugins.clone();
+            let modifier_clicks_rc = modifier_clicks.clone();
+            let idle_state_rc = idle_state.clone();
+            let padding_rc = padding.clone();
+            let copy_soft_wraps_rc = copy_soft_wraps.clone();
+            let tooltip_rc = tooltip.clone();
+            let context_menu_hook_rc = context_menu_hook.clone();
+            let blank_menu_rc = blank_menu.clone();
+            let hover = Arc::new(RwLock::new(HoverTracker::default()));
+            // 按下鼠标时检测到的可拖出内容，以及该内容是否为选中的文字。
+            let mut drag_out: Option<(String, bool, (i32, i32))> = None;
+            // 是否正在接收拖放内容，用于区分拖放和普通粘贴产生的`Event::Paste`事件。
+            let mut dropping = false;
+            // 是否正在读取PRIMARY选区，用于区分中键粘贴产生的`Event::Paste`事件。
+            let mut pasting_primary = false;
+            let prompt_rc = prompt.clone();
+            let pinned_rc = pinned.clone();
+            let user_padding_rc = user_padding.clone();
+            let echo_reserve_rc = echo_reserve.clone();
             move |ctx, evt| {
+                if matches!(evt, Event::Push | Event::Drag | Event::MouseWheel | Event::Move | Event::Enter | Event::Focus | Event::KeyDown) {
+                    idle_state_rc.write().touch();
+                }
+                let plugins = snapshot_plugins(&plugins_rc);
+                if !plugins.is_empty() {
+                    let view = Self::panel_view(ctx, buffer_rc.clone(), append_top_rc.load(Ordering::Relaxed), max_rows_rc.load(Ordering::Relaxed), max_cols_rc.load(Ordering::Relaxed), &padding_rc.read());
+                    for plugin in plugins {
+                        if plugin.write().on_event(evt, &view) {
+                            return true;
+                        }
+                    }
+                }
+
+                // 开启鼠标汇报模式后，鼠标按键事件转交服务端处理，按住Shift键时仍可进行本地操作。
+                if matches!(evt, Event::Push | Event::Released) && !app::is_event_shift() {
+                    let modes = *terminal_modes_rc.read();
+                    if modes.mouse_tracking.is_some() {
+                        let button = match app::event_mouse_button() {
+                            MouseButton::Middle => 1,
+                            MouseButton::Right => 2,
+                            _ => 0,
+                        };
+                        draw::set_font(*text_font_rc.read(), text_size_rc.load(Ordering::Relaxed));
+                        let (char_width, _) = draw::measure(&basic_char_rc.read().to_string(), false);
+                        let line_height = (text_size_rc.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+                        let padding = *padding_rc.read();
+                        let col = ((app::event_x() - ctx.x() - padding.left) / max(char_width, 1) + 1).clamp(1, max_cols_rc.load(Ordering::Relaxed) as i32);
+                        let row = ((app::event_y() - ctx.y() - padding.top) / max(line_height, 1) + 1).clamp(1, max_rows_rc.load(Ordering::Relaxed) as i32);
+                        if let Some(report) = modes.encode_mouse(button, col as usize, row as usize, evt == Event::Push) {
+                            if let Some(cb) = notifier_rc.write().as_mut() {
+                                cb.notify(CallbackData::MouseReport(report));
+                            }
+                        }
+                        return true;
+                    }
+                }
                 // let enable_cursor = if show_cursor_rc.load(Ordering::Relaxed) {
                 //     Some(cursor_piece_rc.clone())
                 // } else {
@@ -458,12 +1586,24 @@ impl RichText {
                             }
                             if last_width != current_width {
                                 // 当窗口宽度发生变化时，需要重新计算数据分片坐标信息。
-                                let drawable_max_width = current_width - PADDING.left - PADDING.right;
-                                let mut last_piece = LinePiece::init_piece(text_size_rc.load(Ordering::Relaxed));
-                                for rich_data in buffer_rc.write().iter_mut() {
-                                    rich_data.line_pieces.clear();
-                                    last_piece = rich_data.estimate(last_piece, drawable_max_width, *basic_char_rc.read());
+                                let user_padding = *user_padding_rc.read();
+                                let pinned_heights = {
+                                    let mut pinned = pinned_rc.write();
+                                    pinned.layout(text_size_rc.load(Ordering::Relaxed), user_padding.drawable_width(current_width), *basic_char_rc.read(), &user_padding);
+                                    pinned.heights()
+                                };
+                                let padding = Self::reserved_padding(&user_padding, pinned_heights, echo_reserve_rc.load(Ordering::Relaxed));
+                                *padding_rc.write() = padding;
+                                if let Some(board) = rewrite_board_rc.write().as_mut() {
+                                    board.padding = padding;
                                 }
+                                let drawable_max_width = padding.drawable_width(current_width);
+                                let top_insert_pos = if append_top_rc.load(Ordering::Relaxed) {
+                                    Some(top_insert_pos_rc.load(Ordering::Relaxed))
+                                } else {
+                                    None
+                                };
+                                Self::relayout_buffer(&mut buffer_rc.write(), top_insert_pos, text_size_rc.load(Ordering::Relaxed), drawable_max_width, *basic_char_rc.read(), &padding);
                             }
 
                             if current_width > 0 || current_height > 0 {
@@ -476,10 +1616,19 @@ impl RichText {
                                     max_rows_rc.clone(),
                                     max_cols_rc.clone(),
                                     rewrite_board_rc.clone(),
+                                    &padding_rc.read(),
                                 );
 
+                                let shape = ShapeData::new(last_width, last_height, current_width, current_height, new_cols, new_rows);
                                 if let Some(cb) = notifier_rc.write().as_mut() {
-                                    cb.notify(CallbackData::Shape(ShapeData::new(last_width, last_height, current_width, current_height, new_cols, new_rows)));
+                                    cb.notify(CallbackData::Shape(shape));
+                                }
+                                let plugins = snapshot_plugins(&plugins_rc);
+                                if !plugins.is_empty() {
+                                    let view = Self::panel_view(ctx, buffer_rc.clone(), append_top_rc.load(Ordering::Relaxed), new_rows as usize, new_cols as usize, &padding_rc.read());
+                                    for plugin in plugins {
+                                        plugin.write().on_resize(&shape, &view);
+                                    }
                                 }
                             }
 
@@ -490,18 +1639,156 @@ impl RichText {
                         // debug!("主面板缩放");
                     }
                     Event::Move => {
-                        // 检测鼠标进入可互动区域，改变鼠标样式
-                        let (entered, _idx) = mouse_enter(clickable_data_rc.clone());
+                        // 检测鼠标进入可互动区域，改变鼠标样式并汇报悬停的数据段
+                        let (entered, idx) = mouse_enter(clickable_data_rc.clone());
                         if entered {
                             draw::set_cursor(Cursor::Hand);
                         } else {
                             draw::set_cursor(Cursor::Default);
                         }
+                        let target = if entered { buffer_rc.read().get(idx).map(|rd| (rd.id, rd.action.as_ref().map(|a| a.title.clone()))) } else { None };
+                        track_hover(&hover, target.as_ref().map(|(id, _)| *id), &notifier_rc);
+                        track_tooltip(&tooltip_rc, target.as_ref().and_then(|(_, title)| title.as_deref()), &*ctx);
                     }
                     Event::Leave => {
                         draw::set_cursor(Cursor::Default);
+                        track_hover(&hover, None, &notifier_rc);
+                        track_tooltip(&tooltip_rc, None, &*ctx);
+                    }
+                    Event::Focus | Event::Unfocus if prompt_rc.read().is_some() => {
+                        // 输入行模式下接收键盘焦点，焦点变化时重绘插入点，失去焦点时结束输入法的组字。
+                        if evt == Event::Unfocus {
+                            app::compose_reset();
+                            draw::reset_spot();
+                        }
+                        ctx.redraw();
+                        return true;
+                    }
+                    Event::KeyDown if prompt_rc.read().is_some() => {
+                        let submitted = {
+                            let mut guard = prompt_rc.write();
+                            let Some(prompt) = guard.as_mut() else {
+                                return false;
+                            };
+                            match app::event_key() {
+                                Key::Enter | Key::KPEnter => Some(prompt.submit()),
+                                Key::BackSpace => { prompt.backspace(); None }
+                                Key::Delete => { prompt.delete(); None }
+                                Key::Left => { prompt.move_cursor(false); None }
+                                Key::Right => { prompt.move_cursor(true); None }
+                                Key::Home => { prompt.move_to_edge(false); None }
+                                Key::End => { prompt.move_to_edge(true); None }
+                                Key::Up => { prompt.browse_history(true); None }
+                                Key::Down => { prompt.browse_history(false); None }
+                                Key::Escape => { prompt.set_text(""); None }
+                                _ => {
+                                    // 输入法组字时，新的预编辑文字或最终提交的文字替换之前插入的预编辑文字。
+                                    // 组合键和控制字符不属于文字输入，交由其他组件处理。
+                                    let Some(del) = app::compose() else {
+                                        return false;
+                                    };
+                                    let text = app::event_text();
+                                    if del == 0 && text.is_empty() {
+                                        return false;
+                                    }
+                                    prompt.compose(del.max(0) as usize, &text);
+                                    None
+                                }
+                            }
+                        };
+                        if let Some(line) = submitted {
+                            if let Some(cb) = notifier_rc.write().as_mut() {
+                                cb.notify(CallbackData::PromptInput(line));
+                            }
+                        }
+                        ctx.redraw();
+                        return true;
+                    }
+                    Event::DndEnter | Event::DndDrag => {
+                        // 开始拖放时放弃尚未收到的PRIMARY选区粘贴，随后的粘贴事件属于拖放内容。
+                        pasting_primary = false;
+                        // 设置了回调函数时才接收拖放内容。
+                        return notifier_rc.read().is_some();
+                    }
+                    Event::DndLeave => {
+                        dropping = false;
+                        return true;
+                    }
+                    Event::DndRelease => {
+                        dropping = true;
+                        return true;
+                    }
+                    Event::Paste if dropping => {
+                        dropping = false;
+                        pasting_primary = false;
+                        let text = app::event_text();
+                        let files = parse_dropped_files(&text);
+                        if let Some(cb) = notifier_rc.write().as_mut() {
+                            cb.notify(CallbackData::Drop { text, files });
+                        }
+                        return true;
+                    }
+                    Event::Paste if pasting_primary => {
+                        pasting_primary = false;
+                        if let Some(cb) = notifier_rc.write().as_mut() {
+                            cb.notify(CallbackData::PrimaryPaste(app::event_text()));
+                        }
+                        return true;
                     }
                     Event::Released => {
+                        if std::mem::take(&mut shift_extended) {
+                            // 按住Shift键点击扩展选区时不再执行常规的点击操作。
+                            return true;
+                        }
+                        // 在选中的文字上点击而未拖动时，按照普通点击清除选区。
+                        if let Some((_, true, _)) = drag_out.take() {
+                            selected.store(false, Ordering::Relaxed);
+                            clear_selected_pieces(selected_pieces.clone());
+                            selection_rc.write().take();
+                            update_panel_fn.write().update_param(true);
+                        }
+                        // 按住修饰键点击时执行绑定的动作，不再执行常规的互动操作。
+                        if app::event_mouse_button() == MouseButton::Left && !modifier_clicks_rc.read().is_empty() {
+                            let modifier = if app::is_event_ctrl() {
+                                Some(Modifier::Ctrl)
+                            } else if app::is_event_alt() {
+                                Some(Modifier::Alt)
+                            } else if app::is_event_command() {
+                                Some(Modifier::Meta)
+                            } else if app::is_event_shift() {
+                                Some(Modifier::Shift)
+                            } else {
+                                None
+                            };
+                            if let Some(mut binding) = modifier.and_then(|m| modifier_clicks_rc.read().get(&m).cloned()) {
+                                let (app_x, app_y) = app::event_coords();
+                                let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                                let mut data = ModifierClickData { modifier: modifier.unwrap(), click_point: (app_x, app_y), word: None, url: None, data: None };
+                                if let Some((idx, char_idx)) = locate_char_at(buffer_rc.read().as_slice(), app_x - ctx.x(), app_y - ctx.y() + scroll_y) {
+                                    if let Some(rd) = buffer_rc.read().get(idx) {
+                                        data.word = word_around(&rd.text, char_idx);
+                                        data.url = url_around(&rd.text, char_idx);
+                                        data.data = Some(rd.into());
+                                    }
+                                }
+                                binding.dispatch(&mut notifier_rc.write(), data);
+                                return true;
+                            }
+                        }
+
+                        // 点击链接预览卡片时汇报对应的网址。
+                        if app::event_mouse_button() == MouseButton::Left {
+                            let (app_x, app_y) = app::event_coords();
+                            let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                            let url = buffer_rc.read().iter().rev().find_map(|rd| rd.preview_url_at(app_x - ctx.x(), app_y - ctx.y() + scroll_y));
+                            if let Some(url) = url {
+                                if let Some(cb) = notifier_rc.write().as_mut() {
+                                    cb.notify(CallbackData::OpenUrl(url));
+                                }
+                                return true;
+                            }
+                        }
+
                         // 检测鼠标点击可互动区域，执行用户自定义操作
                         let mut target_opt: Option<UserData> = None;
                         let mut target_rd_v_bounds: Option<(i32, i32, i32, i32)> = None;
@@ -517,11 +1804,26 @@ impl RichText {
                             }
                         }
                         if app::event_mouse_button() == MouseButton::Right {
+                            let clickable = target_opt.is_some();
+                            if !clickable && context_menu_hook_rc.read().is_some() {
+                                // 在非互动数据段上右击时，同样为钩子函数提供所在的数据段。
+                                let (app_x, app_y) = app::event_coords();
+                                let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                                if let Some((idx, _)) = locate_char_at(buffer_rc.read().as_slice(), app_x - ctx.x(), app_y - ctx.y() + scroll_y) {
+                                    target_opt = buffer_rc.read().get(idx).map(UserData::from);
+                                }
+                            }
+                            let mut handled = false;
                             if let Some(ud) = target_opt {
-                                if ud.action.is_some() {
+                                let extra = context_menu_hook_rc.read().as_ref().map(|hook| hook.build(&ud)).unwrap_or_default();
+                                if ud.action.is_some() || !extra.is_empty() {
                                     // 右键弹出互动菜单
+                                    handled = true;
                                     let ud_rc = Rc::new(ud);
-                                    if let Some(action) = &ud_rc.action {
+                                    let action_len = ud_rc.action.as_ref().map_or(0, |action| action.items.len());
+                                    let extra_rc = Rc::new(RefCell::new(extra));
+                                    {
+                                        let action = ud_rc.action.clone().unwrap_or_default();
                                         let mut popup_menu_rc = MenuButton::new(0, 0, 0, 0, None);
                                         // popup_menu_rc.clear();
                                         popup_menu_rc.set_type(MenuButtonType::Popup1);
@@ -546,15 +1848,19 @@ impl RichText {
                                         for item in action.items.iter() {
                                             popup_menu_rc.add_choice(item.desc.as_str());
                                         }
+                                        extra_rc.borrow().append_to(&mut popup_menu_rc);
                                         // 用户选中的菜单项后将其附带到目标数据段中回传到上层应用。
                                         if ud_rc.data_type == DataType::Text {
                                             // 文字类型
                                             popup_menu_rc.set_callback({
                                                 let ud_rc_2 = ud_rc.clone();
                                                 let notifier_rc = notifier_rc.clone();
+                                                let extra_rc = extra_rc.clone();
                                                 move |menu| {
                                                     let selected_idx = menu.value();
-                                                    if selected_idx >= 0 {
+                                                    if selected_idx >= action_len as i32 {
+                                                        extra_rc.borrow_mut().run(selected_idx as usize - action_len, &ud_rc_2);
+                                                    } else if selected_idx >= 0 {
                                                         let mut ud = ud_rc_2.as_ref().clone();
                                                         if let Some(action) = &mut ud.action {
                                                             if let Some(item) = action.items.get(selected_idx as usize) {
@@ -572,7 +1878,7 @@ impl RichText {
                                             let click_point = if let Some(v_bounds) = target_rd_v_bounds {
                                                 let (app_x, app_y) = app::event_coords();
                                                 // debug!("rd v_bounds: {:?}, app_coords: {}, {}", v_bounds, app_x, app_y);
-                                                let scroll_y = Self::calc_scroll_height(buffer_rc.clone(), ctx.height());
+                                                let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
                                                 let click_at_x = app_x - ctx.x() - v_bounds.2;
                                                 let click_at_y = app_y - ctx.y() + scroll_y - v_bounds.0 - IMAGE_PADDING_V;
                                                 // debug!("click_at_x: {}, click_at_y: {}", click_at_x, click_at_y);
@@ -584,9 +1890,12 @@ impl RichText {
                                             popup_menu_rc.set_callback({
                                                 let ud_rc_2 = ud_rc.clone();
                                                 let notifier_rc = notifier_rc.clone();
+                                                let extra_rc = extra_rc.clone();
                                                 move |menu| {
                                                     let selected_idx = menu.value();
-                                                    if selected_idx >= 0 {
+                                                    if selected_idx >= action_len as i32 {
+                                                        extra_rc.borrow_mut().run(selected_idx as usize - action_len, &ud_rc_2);
+                                                    } else if selected_idx >= 0 {
                                                         let mut ud = ud_rc_2.as_ref().clone();
                                                         if let Some(action) = &mut ud.action {
                                                             if let Some(item) = action.items.get(selected_idx as usize) {
@@ -602,20 +1911,69 @@ impl RichText {
 
                                         popup_menu_rc.popup();
                                     }
+                                } else if clickable {
+                                    handled = true;
+                                    if let Some(cb) = notifier_rc.write().as_mut() {
+                                        // 直接返回当前目标数据
+                                        cb.notify(CallbackData::Data(ud));
+                                    }
+                                }
+                            }
+                            if !handled {
+                                if blank_menu_rc.load(Ordering::Relaxed) {
+                                    popup_blank_menu(buffer_rc.clone(), selected_pieces.clone(), copy_soft_wraps_rc.load(Ordering::Relaxed), {
+                                        let update_panel_fn = update_panel_fn.clone();
+                                        let selected = selected.clone();
+                                        let buffer_rc = buffer_rc.clone();
+                                        let selection_rc = selection_rc.clone();
+                                        move || {
+                                            selected.store(true, Ordering::Relaxed);
+                                            // 全选时选区从第一个数据段开始。
+                                            *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), 0);
+                                            update_panel_fn.write().update_param(true);
+                                        }
+                                    });
                                 } else if let Some(cb) = notifier_rc.write().as_mut() {
-                                    // 直接返回当前目标数据
-                                    cb.notify(CallbackData::Data(ud));
+                                    let (app_x, app_y) = app::event_coords();
+                                    cb.notify(CallbackData::ContextMenu(app_x, app_y));
                                 }
                             }
                         } else if app::event_mouse_button() == MouseButton::Left {
                             if app::event_clicks() {
                                 // debug!("双击");
-                                select_paragraph(select_from_row, &mut push_from_point, buffer_rc.read().as_slice(), selected_pieces.clone());
+                                select_paragraph(select_from_row, &mut push_from_point, buffer_rc.read().as_slice(), selected_pieces.clone(), &ColumnLayout::default(), copy_soft_wraps_rc.load(Ordering::Relaxed));
+                                *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), select_from_row);
                                 ctx.set_damage(true);
-                            } else if let Some(ud) = target_opt {
-                                // 左键弹出提示信息
-                                // debug!("左键点击：{:?}", ud);
-                                if let Some(action) = &ud.action {
+                            } else if let Some(mut ud) = target_opt {
+                                let default_item = ud.action.as_ref().and_then(|action| {
+                                    action.default_item.and_then(|idx| action.items.get(idx).cloned())
+                                });
+                                if let Some(item) = default_item {
+                                    // 左键直接执行默认动作
+                                    if ud.data_type == DataType::Text {
+                                        if let Some(action) = &mut ud.action {
+                                            action.active.replace(item.cmd.clone());
+                                        }
+                                        if let Some(cb) = notifier_rc.write().as_mut() {
+                                            cb.notify(CallbackData::Data(ud));
+                                        }
+                                    } else {
+                                        let click_point = if let Some(v_bounds) = target_rd_v_bounds {
+                                            let (app_x, app_y) = app::event_coords();
+                                            let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                                            let click_at_x = app_x - ctx.x() - v_bounds.2;
+                                            let click_at_y = app_y - ctx.y() + scroll_y - v_bounds.0 - IMAGE_PADDING_V;
+                                            (click_at_x, click_at_y)
+                                        } else {
+                                            (0, 0)
+                                        };
+                                        if let Some(cb) = notifier_rc.write().as_mut() {
+                                            cb.notify(CallbackData::Image(ImageEventData::new(click_point, ud.image_src_url, ud.id, item.cmd.clone(), ud.image_file_path.clone(), (ud.image_target_width, ud.image_target_height))));
+                                        }
+                                    }
+                                } else if let Some(action) = &ud.action {
+                                    // 左键弹出提示信息
+                                    // debug!("左键点击：{:?}", ud);
                                     let mut popup_menu_rc = MenuButton::new(0, 0, 0, 0, None);
                                     popup_menu_rc.set_type(MenuButtonType::Popup1);
                                     if !action.items.is_empty() {
@@ -647,47 +2005,109 @@ impl RichText {
                     }
                     Event::Push => {
                         let (push_from_x, push_from_y) = app::event_coords();
+                        drag_out = None;
+                        // 上一次中键点击没有收到粘贴事件时不再等待，避免之后的粘贴被误报为PRIMARY选区内容。
+                        pasting_primary = false;
+                        if prompt_rc.read().is_some() {
+                            let _ = ctx.take_focus();
+                        }
+                        if app::event_mouse_button() == MouseButton::Right {
+                            // 右键点击不改变选区，以便右键菜单复制选中内容。
+                            return true;
+                        }
+                        #[cfg(target_os = "linux")]
+                        if app::event_mouse_button() == MouseButton::Middle {
+                            // 中键点击不改变选区，读取PRIMARY选区后在随后的粘贴事件中汇报。
+                            if notifier_rc.read().is_some() {
+                                pasting_primary = true;
+                                app::paste_text2(&*ctx);
+                            }
+                            return true;
+                        }
+                        if app::event_mouse_button() == MouseButton::Left && app::is_event_shift() && push_anchor.is_some() && !modifier_clicks_rc.read().contains_key(&Modifier::Shift) {
+                            // 按住Shift键点击时，选择上次点击位置与当前位置之间的全部内容。
+                            let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                            let mut current_point = ClickPoint::new(push_from_x - ctx.x(), push_from_y - ctx.y() + scroll_y);
+                            current_point.align(ctx.width(), ctx.height(), scroll_y, &padding_rc.read());
+                            follow_push_anchor(buffer_rc.read().as_slice(), &mut push_anchor, &mut push_from_point, &mut select_from_row);
+                            update_selection_when_drag(push_from_point, select_from_row, &mut current_point, buffer_rc.read().as_slice(), selected_pieces.clone(), ctx, &padding_rc.read(), &ColumnLayout::default(), copy_soft_wraps_rc.load(Ordering::Relaxed));
+                            selected.store(!selected_pieces.read().is_empty(), Ordering::Relaxed);
+                            *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), select_from_row);
+                            update_panel_fn.write().update_param(true);
+                            shift_extended = true;
+                            return true;
+                        }
+                        let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
+                        let (data_x, data_y) = (push_from_x - ctx.x(), push_from_y - ctx.y() + scroll_y);
+                        if app::event_mouse_button() == MouseButton::Left {
+                            if selection_contains(&selected_pieces.read(), data_x, data_y) {
+                                // 在选中的文字上按下鼠标时保留选区，准备拖出选中内容。
+                                drag_out = Some((selection_text(&selected_pieces.read(), copy_soft_wraps_rc.load(Ordering::Relaxed)), true, (push_from_x, push_from_y)));
+                                return true;
+                            }
+                            drag_out = buffer_rc.read().iter().find_map(|rd| rd.image_drag_payload_at(data_x, data_y)).map(|path| (path, false, (push_from_x, push_from_y)));
+                        }
                         // debug!("清除选区");
                         selected.store(false, Ordering::Relaxed);
                         clear_selected_pieces(selected_pieces.clone());
+                        selection_rc.write().take();
                         update_panel_fn.write().update_param(true);
                         // ctx.set_damage(true);
                         select_from_row = 0;
+                        push_anchor = None;
 
                         let (p_offset_x, p_offset_y) = (ctx.x(), ctx.y());
-                        let scroll_y = Self::calc_scroll_height(buffer_rc.clone(), ctx.height());
+                        let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
                         push_from_point.x = push_from_x - p_offset_x;
                         push_from_point.y = push_from_y - p_offset_y + scroll_y;
                         // debug!("scroll_y: {scroll_y}, push_from: {:?}", push_from_point);
-                        push_from_point.align(ctx.width(), ctx.height(), scroll_y);
+                        push_from_point.align(ctx.width(), ctx.height(), scroll_y, &padding_rc.read());
 
                         // 尝试检测起始点击位置是否位于某个数据段内，可减少后续划选过程中的检测目标范围
                         let index_vec = (0..buffer_rc.read().len()).collect::<Vec<usize>>();
                         let rect = push_from_point.as_rect();
-                        if let Some(tr) = locate_target_rd(&mut push_from_point, rect, ctx.w(), buffer_rc.read().as_slice(), index_vec) {
+                        if let Some(tr) = locate_target_rd(&mut push_from_point, rect, ctx.w(), buffer_rc.read().as_slice(), index_vec, &padding_rc.read(), &ColumnLayout::default()) {
                             select_from_row = tr.row;
                             // debug!("选择行 {row}");
+                            // 以数据段id记录起点，划选过程中追加新数据或清理缓存时仍能找到起点。
+                            push_anchor = buffer_rc.read().get(select_from_row).map(|rd| (SelectionAnchor::new(rd, select_from_row, push_from_point.p_i, push_from_point.c_i), rd.v_bounds.read().0));
                         }
 
                         return true;
                     }
                     Event::Drag => {
+                        if let Some((_, _, origin)) = drag_out.as_ref() {
+                            if !drag_out_started(*origin, app::event_coords()) {
+                                // 移动距离不足时仍视为点击。
+                                return true;
+                            }
+                        }
+                        if let Some((payload, _, _)) = drag_out.take() {
+                            // 拖动选中的文字或图片时，将其拖出组件。
+                            start_drag_out(&payload);
+                            return true;
+                        }
                         let (current_x, current_y) = app::event_coords();
                         let (p_offset_x, p_offset_y) = (ctx.x(), ctx.y());
-                        let scroll_y = Self::calc_scroll_height(buffer_rc.clone(), ctx.height());
+                        let scroll_y = Self::view_scroll_height(buffer_rc.clone(), ctx.height(), append_top_rc.load(Ordering::Relaxed), &padding_rc.read(), &paused_rc);
                         let mut current_point = ClickPoint::new(current_x - p_offset_x, current_y - p_offset_y + scroll_y);
-                        current_point.align(ctx.width(), ctx.height(), scroll_y);
+                        current_point.align(ctx.width(), ctx.height(), scroll_y, &padding_rc.read());
+                        follow_push_anchor(buffer_rc.read().as_slice(), &mut push_anchor, &mut push_from_point, &mut select_from_row);
                         update_selection_when_drag(
                             push_from_point,
                             select_from_row,
                             &mut current_point,
                             buffer_rc.read().as_slice(),
                             selected_pieces.clone(),
-                            ctx
+                            ctx,
+                            &padding_rc.read(),
+                            &ColumnLayout::default(),
+                            copy_soft_wraps_rc.load(Ordering::Relaxed),
                         );
                         // selected.set(ret);
                         let need_redraw = !selected_pieces.read().is_empty();
                         selected.store(need_redraw, Ordering::Relaxed);
+                        *selection_rc.write() = selection_range(buffer_rc.read().as_slice(), select_from_row);
                         if need_redraw {
                             // debug!("{need_redraw}");
                             update_panel_fn.write().update_param(true);
@@ -704,11 +2124,48 @@ impl RichText {
         Self {
             panel, data_buffer,
             current_buffer,
-            background_color, buffer_max_lines: Arc::new(AtomicUsize::new(buffer_max_lines)), notifier, inner, reviewer,
-            blink_flag, text_font, text_color,
+            background_color, buffer_max_lines: Arc::new(AtomicUsize::new(buffer_max_lines)), notifier, listeners: Arc::new(RwLock::new(vec![])), tooltip, context_menu_hook, blank_menu, paused, max_fps: Arc::new(AtomicU32::new(0)), append_queue: Arc::new(RwLock::new(AppendQueue::default())), inner, reviewer, panel_screen,
+            blink_flag, render_options, text_font, text_color,
             text_size, piece_spacing, enable_blink, basic_char, tab_width,
             cursor_piece, show_cursor, remote_flow_control, rewrite_board, max_rows, max_cols,
-            update_panel_fn,
+            update_panel_fn, gutter_width, line_counter, split_scrollback, append_top, top_insert_pos,
+            alternate_saved, show_translations, scroll_region, dedup_window, erase_journal: Arc::new(RwLock::new(EraseJournal::default())), tab_stops, current_col,
+            auto_expire, expire_wheel, terminal_modes, plugins,
+            sessions: Arc::new(RwLock::new(SessionTabs::default())),
+            watches: Arc::new(RwLock::new(Watches::default())),
+            highlights: Arc::new(RwLock::new(Highlights::default())),
+            styles: Arc::new(RwLock::new(HashMap::new())),
+            category_stats: Arc::new(RwLock::new(BTreeMap::new())),
+            idle_state,
+            wrap_policy,
+            hanging_indent,
+            wrap_cache,
+            copy_soft_wraps,
+            line_height_factor: Arc::new(RwLock::new(LINE_HEIGHT_FACTOR)),
+            paragraph_spacing: Arc::new(AtomicI32::new(0)),
+            padding,
+            fade_in: Arc::new(RwLock::new(None)), fade_until, timers, ansi_palette, user_palette: Arc::new(RwLock::new(None)), modifier_clicks, background_image,
+            collapsed_threads: Arc::new(RwLock::new(HashSet::new())), thread_index: Arc::new(RwLock::new(HashMap::new())), cursor_blink_interval,
+            blink_interval, rapid_blink_interval,
+            panel_updater, deterministic,
+            preview_provider: Arc::new(RwLock::new(None)),
+            link_preview_enabled: Arc::new(AtomicBool::new(true)),
+            logger,
+            previewed_urls: Arc::new(RwLock::new(RecentUrls::new(PREVIEWED_URLS_CAPACITY))),
+            follow_search_focus,
+            search_in_main: Arc::new(AtomicBool::new(false)),
+            main_search: Arc::new(RwLock::new(None)),
+            keywords,
+            prompt,
+            echo_area,
+            echo_reserve,
+            user_padding,
+            pinned,
+            review_columns,
+            speech_hook: Arc::new(RwLock::new(None)),
+            speech_lines: Arc::new(RwLock::new(SpeechLines::default())),
+            color_transform,
+            disabled_style,
         }
     }
     
@@ -779,11 +2236,12 @@ impl RichText {
         max_rows_rc: Arc<AtomicUsize>,
         max_cols_rc: Arc<AtomicUsize>,
         rewrite_board_rc: Arc<RwLock<Option<ReWriteBoard>>>,
+        padding: &Padding,
     ) -> (i32, i32) {
         draw::set_font(*text_font_rc.read(), text_size_rc.load(Ordering::Relaxed));
         let (char_width, _) = draw::measure(&basic_char_rc.read().to_string(), false);
-        let new_cols = ((panel_width - PADDING.left - PADDING.right) as f32 / char_width as f32).floor() as i32;
-        let new_rows = ((panel_height - PADDING.top - PADDING.bottom) as f32 / (text_size_rc.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil()).floor() as i32;
+        let new_cols = (padding.drawable_width(panel_width) as f32 / char_width as f32).floor() as i32;
+        let new_rows = (padding.drawable_height(panel_height) as f32 / (text_size_rc.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil()).floor() as i32;
         max_rows_rc.store(max(new_rows, 1) as usize, Ordering::Relaxed);
         max_cols_rc.store(max(new_cols, 1) as usize, Ordering::Relaxed);
         if let Some(board) = rewrite_board_rc.write().as_mut() {
@@ -799,6 +2257,8 @@ impl RichText {
     ///
     /// * `buffer_rc`: 数据缓存。
     /// * `panel_height`: 目标面板。在当前场景中是主视图面板。
+    /// * `pin_top`: 是否固定显示顶部内容。
+    /// * `padding`: 面板边界空白。
     ///
     /// returns: i32 返回高度差，如果数据高度小于面板高度则返回0。
     ///
@@ -807,11 +2267,14 @@ impl RichText {
     /// ```
     ///
     /// ```
-    fn calc_scroll_height(buffer_rc: Arc<RwLock<Vec<RichData>>>, panel_height: i32) -> i32 {
+    fn calc_scroll_height(buffer_rc: Arc<RwLock<Vec<RichData>>>, panel_height: i32, pin_top: bool, padding: &Padding) -> i32 {
+        if pin_top {
+            return 0;
+        }
         if let Some(last_rd) = buffer_rc.read().iter().last() {
             let last_rd_bottom = last_rd.v_bounds.read().1;
-            if last_rd_bottom + PADDING.bottom > panel_height {
-                last_rd_bottom - panel_height + PADDING.bottom
+            if last_rd_bottom + padding.bottom > panel_height {
+                last_rd_bottom - panel_height + padding.bottom
             } else {
                 0
             }
@@ -820,6 +2283,126 @@ impl RichText {
         }
     }
 
+    /// 计算主视图画面对应的滚动高度。暂停输出期间画面冻结，返回暂停时记录的滚动高度。
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer_rc`: 数据缓存。
+    /// * `panel_height`: 主视图面板高度。
+    /// * `pin_top`: 是否固定显示顶部内容。
+    /// * `padding`: 面板边界空白。
+    /// * `paused`: 暂停状态。
+    ///
+    /// returns: i32
+    fn view_scroll_height(buffer_rc: Arc<RwLock<Vec<RichData>>>, panel_height: i32, pin_top: bool, padding: &Padding, paused: &RwLock<Option<PauseState>>) -> i32 {
+        match *paused.read() {
+            Some(state) if !pin_top => state.scroll_y,
+            _ => Self::calc_scroll_height(buffer_rc, panel_height, pin_top, padding),
+        }
+    }
+
+    /// 主视图当前画面对应的滚动高度，暂停输出期间为暂停时的滚动高度。
+    fn view_scroll_y(&self) -> i32 {
+        Self::view_scroll_height(self.current_buffer.clone(), self.panel.height(), self.append_top.load(Ordering::Relaxed), &self.padding.read(), &self.paused)
+    }
+
+    /// 缩放默认字体尺寸，并按相同比例缩放主缓存、定位面板和回顾区中的文本数据段，然后重新计算绘制位置和窗口行列数。
+    ///
+    /// # Arguments
+    ///
+    /// * `new_size`: 新的默认字体尺寸。
+    /// * `panel`: 主视图面板。
+    /// * `text_size`: 默认字体尺寸。
+    /// * `text_font`: 默认字体。
+    /// * `basic_char`: 基本字符。
+    /// * `current_buffer`: 当前数据缓存。
+    /// * `data_buffer`: 主缓存不是当前缓存时，保存主缓存数据。
+    /// * `remote_flow_control`: 当前缓存是否就是主缓存。
+    /// * `top_insert_pos`: 顶部追加模式下新数据的插入位置。
+    /// * `rewrite_board`: 定位面板。
+    /// * `cursor_piece`: 虚拟光标。
+    /// * `reviewer`: 回顾区。
+    /// * `max_rows`: 窗口行数。
+    /// * `max_cols`: 窗口列数。
+    /// * `padding`: 面板边界空白。
+    ///
+    /// returns: bool 字体尺寸是否发生了变化。
+    fn apply_zoom(
+        new_size: i32,
+        panel: &Frame,
+        text_size: Arc<AtomicI32>,
+        text_font: Arc<RwLock<Font>>,
+        basic_char: Arc<RwLock<char>>,
+        current_buffer: Arc<RwLock<Vec<RichData>>>,
+        data_buffer: Arc<RwLock<Option<Vec<RichData>>>>,
+        remote_flow_control: bool,
+        top_insert_pos: Option<usize>,
+        rewrite_board: Arc<RwLock<Option<ReWriteBoard>>>,
+        cursor_piece: Arc<RwLock<LinePiece>>,
+        reviewer: Arc<RwLock<Option<RichReviewer>>>,
+        max_rows: Arc<AtomicUsize>,
+        max_cols: Arc<AtomicUsize>,
+        padding: Padding) -> bool {
+        let old_size = text_size.swap(new_size, Ordering::Relaxed);
+        if old_size == new_size {
+            return false;
+        }
+        let drawable_max_width = padding.drawable_width(panel.width());
+        let bc = *basic_char.read();
+        draw::set_font(*text_font.read(), new_size);
+
+        if let Some(board) = rewrite_board.write().as_mut() {
+            // 定位面板按行重新计算，光标保持在原来的行列位置。
+            let (_, th) = measure(" ", false);
+            let line_height = max((new_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, th);
+            board.rescale(line_height as usize, drawable_max_width, bc, |rd| scale_font_size(rd, old_size, new_size));
+            *current_buffer.write() = board.all_data();
+            let (n, m) = board.cursor_pos.get();
+            let (char_width, _) = measure(&bc.to_string(), false);
+            let mut cursor = LinePiece::init_piece(new_size, &padding).read().clone();
+            cursor.move_cursor_to(padding.left + char_width * (m as i32 - 1), board.row_y(n));
+            *cursor_piece.write() = cursor;
+        } else if remote_flow_control {
+            // 当前缓存就是主缓存
+            let mut buffer = current_buffer.write();
+            buffer.iter_mut().for_each(|rd| scale_font_size(rd, old_size, new_size));
+            let last_piece = Self::relayout_buffer(&mut buffer, top_insert_pos, new_size, drawable_max_width, bc, &padding);
+            *cursor_piece.write() = last_piece.read().get_cursor();
+        }
+        if !remote_flow_control {
+            // 主缓存位于data_buffer中
+            if let Some(main_buffer) = data_buffer.write().as_mut() {
+                let mut last_piece = LinePiece::init_piece(new_size, &padding);
+                for rd in main_buffer.iter_mut() {
+                    scale_font_size(rd, old_size, new_size);
+                    rd.line_pieces.clear();
+                    last_piece = rd.estimate(last_piece, drawable_max_width, bc);
+                }
+            }
+        }
+
+        if let Some(rv) = reviewer.write().as_mut() {
+            if Arc::ptr_eq(&rv.data_buffer, &current_buffer) {
+                // 分屏回看模式下与主视图共享数据缓存，只需同步默认字体尺寸。
+                rv.set_text_size(new_size);
+            } else {
+                rv.zoom(new_size);
+            }
+        }
+
+        Self::update_window_size(text_font, text_size, basic_char, panel.width(), panel.height(), max_rows, max_cols, rewrite_board, &padding);
+        true
+    }
+
+    /// 计算打开回顾区后主面板的高度。分屏回看模式下主面板占据一半高度。
+    fn main_panel_height(split_scrollback: bool, total_height: i32) -> i32 {
+        if split_scrollback {
+            total_height / 2
+        } else {
+            MAIN_PANEL_FIX_HEIGHT
+        }
+    }
+
     /// 检查是否应该关闭回顾区，若满足关闭条件则关闭回顾区并记录待销毁的回顾区组件。
     fn should_hide_reviewer(
         reviewer_rc: Arc<RwLock<Option<RichReviewer>>>,
@@ -870,7 +2453,13 @@ impl RichText {
     ///
     /// ```
     pub fn append(&mut self, user_data: UserData) {
-        self._append(user_data);
+        if self.max_fps.load(Ordering::Relaxed) > 0 {
+            self.enqueue(vec![DocEditType::Data(user_data)]);
+            return;
+        }
+        if !self._append(user_data) {
+            self.notify_suppressed(1);
+        }
 
         self.update_panel_fn.write().update_param(false);
     }
@@ -889,21 +2478,162 @@ impl RichText {
     ///
     /// ```
     pub fn append_batch(&mut self, batch: &mut Vec<DocEditType>) {
+        if self.max_fps.load(Ordering::Relaxed) > 0 {
+            self.enqueue(batch.drain(..).collect());
+            return;
+        }
+        self.apply_batch(batch);
+    }
 
-        batch.reverse();
-        while let Some(at) = batch.pop() {
-            // debug!("append_batch: {:?}", at);
-            match at {
-                DocEditType::Data(user_data) => {
-                    // debug!("添加数据: {:?}", user_data.text);
-                    // let now = Instant::now();
-                    self._append(user_data);
-                    // debug!("添加数据耗时: {:?}", now.elapsed());
-                }
-                DocEditType::ToggleCursor(_param, show) => {
-                    // debug!("{}光标: {}", if show {"显示"} else {"关闭"}, _param);
-                    self.toggle_cursor(show);
-                }
+    /// 设置每秒最大渲染帧数。设置后新增数据先进入有界队列，每帧统一排版和绘制一次，避免高负载下频繁重排，
+    /// 添加数据的调用不会被阻塞。队列超过`set_append_queue_capacity`设置的长度时丢弃最早的数据段，控制操作不会被丢弃，
+    /// 并通过`CallbackData::Dropped`回调汇报丢弃的数量。
+    ///
+    /// 注意：只有添加数据及`append_batch`中的操作会进入队列，`update_data`、`disable_data`和`delete_last_data`等方法立即执行，
+    /// 可能先于仍在队列中的数据生效，例如找不到尚未添加的数据段，或删除了队列之前的数据。需要严格顺序时可先调用`flush`。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_fps`: 每秒最大帧数，为空或0时取消限制，队列中剩余的数据立即添加到视图中。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_max_fps(Some(30));
+    /// ```
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.max_fps.store(max_fps.unwrap_or(0), Ordering::Relaxed);
+        if max_fps.unwrap_or(0) == 0 && !self.append_queue.read().is_empty() {
+            self.render_frame();
+        }
+    }
+
+    /// 设置限制渲染帧率时待合并队列的最大长度，默认值为`APPEND_QUEUE_CAPACITY`。队列中已有的数据超出新长度时丢弃最早的数据段，控制操作保留。
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity`: 队列最大长度，至少为1。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_max_fps(Some(30));
+    /// rich_text.set_append_queue_capacity(2000);
+    /// ```
+    pub fn set_append_queue_capacity(&mut self, capacity: usize) {
+        self.append_queue.write().set_capacity(capacity);
+    }
+
+    /// 获取线程安全的数据发送端，可在其他线程中添加数据。发送的数据与限制帧率时的数据共用同一个待合并队列，
+    /// 未限制帧率时在`fltk`主线程被唤醒后立即处理，否则按照帧率合并处理。
+    ///
+    /// returns: RichTextSender
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let sender = rich_text.sender();
+    /// std::thread::spawn(move || {
+    ///     sender.send(UserData::new_text("来自后台线程的数据\n".to_string()));
+    /// });
+    /// ```
+    pub fn sender(&self) -> RichTextSender {
+        let key = Arc::as_ptr(&self.append_queue) as usize;
+        SENDER_TARGETS.with(|targets| {
+            let mut targets = targets.borrow_mut();
+            targets.retain(|_, rt| !rt.panel.was_deleted());
+            targets.entry(key).or_insert_with(|| self.clone());
+        });
+        RichTextSender { key, queue: self.append_queue.clone() }
+    }
+
+    /// 数据发送端唤醒主线程后的处理入口。
+    fn on_sender_awake(key: usize) {
+        let target = SENDER_TARGETS.with(|targets| targets.borrow().get(&key).cloned());
+        let Some(mut rich_text) = target else {
+            return;
+        };
+        if rich_text.panel.was_deleted() {
+            SENDER_TARGETS.with(|targets| targets.borrow_mut().remove(&key));
+        } else if rich_text.deterministic.load(Ordering::Relaxed) {
+            // 确定性模式下由`flush`或`tick`处理队列。
+        } else if rich_text.max_fps.load(Ordering::Relaxed) > 0 {
+            rich_text.schedule_frame();
+        } else {
+            rich_text.render_frame();
+        }
+    }
+
+    /// 将数据放入待合并队列，尚未安排下一帧时按照帧率安排。确定性模式下不安排定时任务，由`flush`或`tick`处理队列。
+    fn enqueue(&mut self, items: Vec<DocEditType>) {
+        let mut schedule = false;
+        {
+            let mut queue = self.append_queue.write();
+            for item in items {
+                schedule |= queue.push(item);
+            }
+        }
+        if schedule && !self.deterministic.load(Ordering::Relaxed) {
+            self.schedule_frame();
+        }
+    }
+
+    /// 按照帧率安排下一帧。
+    fn schedule_frame(&mut self) {
+        let delay = self.append_queue.read().frame_delay(self.max_fps.load(Ordering::Relaxed), deterministic::now());
+        let mut rich_text = self.clone();
+        app::add_timeout3(delay, move |_| {
+            if !rich_text.panel.was_deleted() {
+                rich_text.render_frame();
+            }
+        });
+    }
+
+    /// 处理待合并队列中的全部数据。
+    fn render_frame(&mut self) {
+        let (mut batch, dropped) = self.append_queue.write().take_frame(deterministic::now());
+        if dropped > 0 {
+            debug!("渲染队列已满，丢弃了{}条数据", dropped);
+            self.notify_dropped(dropped);
+        }
+        if !batch.is_empty() {
+            self.apply_batch(&mut batch);
+        }
+    }
+
+    /// 依次执行批量数据或操作。
+    fn apply_batch(&mut self, batch: &mut Vec<DocEditType>) {
+        let mut suppressed = 0;
+        batch.reverse();
+        while let Some(at) = batch.pop() {
+            // debug!("append_batch: {:?}", at);
+            match at {
+                DocEditType::Data(user_data) => {
+                    // debug!("添加数据: {:?}", user_data.text);
+                    // let now = Instant::now();
+                    if !self._append(user_data) {
+                        suppressed += 1;
+                    }
+                    // debug!("添加数据耗时: {:?}", now.elapsed());
+                }
+                DocEditType::ToggleCursor(_param, show) => {
+                    // debug!("{}光标: {}", if show {"显示"} else {"关闭"}, _param);
+                    self.toggle_cursor(show);
+                }
                 DocEditType::EraseInLine(mode) => {
                     // debug!("行内删除: {:?}", mode);
                     self.erase_in_line(mode);
@@ -950,900 +2680,5411 @@ impl RichText {
                         cb.report.write()(pos_str);
                     }
                 }
+                DocEditType::WindowSizeReport(cb) => {
+                    cb.report.write()(self.get_window_size_report());
+                }
                 DocEditType::PanelFlowEnd => {
                     debug!("面板流结束，切换到本地光标控制模式");
                     self.switch_mode(1);
                 }
+                DocEditType::AlternateScreen(on) => {
+                    debug!("{}备用屏幕", if on {"进入"} else {"退出"});
+                    self.switch_alternate_screen(on);
+                }
+                DocEditType::SetScrollRegion(top, bottom) => {
+                    debug!("设置滚动区域: {} - {}", top, bottom);
+                    self.set_scroll_region(top, bottom);
+                }
+                DocEditType::InsertLines(n) => {
+                    debug!("插入{}行", n);
+                    self.edit_board(|board, _, _| board.insert_lines(n));
+                }
+                DocEditType::DeleteLines(n) => {
+                    debug!("删除{}行", n);
+                    self.edit_board(|board, _, _| board.delete_lines(n));
+                }
+                DocEditType::InsertChars(n) => {
+                    debug!("插入{}个字符", n);
+                    self.edit_board(|board, max_width, basic_char| board.insert_chars(n, max_width, basic_char));
+                }
+                DocEditType::DeleteChars(n) => {
+                    debug!("删除{}个字符", n);
+                    self.edit_board(|board, max_width, basic_char| board.delete_chars(n, max_width, basic_char));
+                }
+                DocEditType::SetTabStop => {
+                    self.set_tab_stop();
+                }
+                DocEditType::ClearTabStop(mode) => {
+                    self.clear_tab_stop(mode);
+                }
+                DocEditType::BracketedPaste(on) => {
+                    debug!("{}括号粘贴模式", if on {"开启"} else {"关闭"});
+                    self.set_bracketed_paste(on);
+                }
+                DocEditType::MouseReport(mode, on) => {
+                    debug!("{}鼠标汇报模式: {:?}", if on {"开启"} else {"关闭"}, mode);
+                    self.set_mouse_report(mode, on);
+                }
+                DocEditType::CursorStyle(code) => {
+                    if let Some((style, blink)) = CursorStyle::from_decscusr(code) {
+                        self.blink_flag.write().set_cursor_style(style, blink);
+                        self.wake_cursor_blink();
+                        self.update_panel_fn.write().update_param(false);
+                    }
+                }
                 DocEditType::CursorNextLine(_) => {}
                 DocEditType::CursorPreviousLine(_) => {}
                 DocEditType::CursorHorizontalAbsolute(_) => {}
             }
         }
 
+        if suppressed > 0 {
+            self.notify_suppressed(suppressed);
+        }
+
         self.update_panel_fn.write().update_param(false);
 
         // debug!("append_batch: {:?}", now.elapsed());
     }
 
-    /// 向缓冲区添加数据，并计算数据片段的绘制坐标。
+    /// 设置重复数据检测窗口。在常规流中，若新数据与最近`size`个数据段之一的内容完全相同，且间隔未超过`within`，
+    /// 则不再添加该数据，并通过`CallbackData::Suppressed`回调汇报被抑制的数据段数量。可用于应对服务端的刷屏循环。
     ///
     /// # Arguments
     ///
-    /// * `user_data`:
+    /// * `size`: 参与比较的最近数据段数量，为0时关闭重复检测。
+    /// * `within`: 判定为重复发送的最大时间间隔。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_dedup_window(5, Duration::from_secs(3));
     /// ```
-    fn _append(&mut self, user_data: UserData) {
-        let default_font_text = !user_data.custom_font_text;
-        let default_font_color = !user_data.custom_font_color;
-        let mut rich_data: RichData = user_data.into();
-        rich_data.piece_spacing = self.piece_spacing.load(Ordering::Relaxed);
-
-        rich_data.text =  rich_data.text.replace('\t', &" ".repeat(self.tab_width.load(Ordering::Relaxed) as usize));
-
-        if default_font_text {
-            rich_data.font = *self.text_font.read();
-            rich_data.font_size = self.text_size.load(Ordering::Relaxed);
-        }
-        if default_font_color {
-            rich_data.fg_color = *self.text_color.read();
-        }
-        let window_width = self.panel.width();
-        let drawable_max_width = window_width - PADDING.left - PADDING.right;
+    pub fn set_dedup_window(&mut self, size: usize, within: Duration) {
+        let mut dedup = self.dedup_window.write();
+        dedup.size = size;
+        dedup.within = within;
+        dedup.recent.clear();
+    }
 
-        if rich_data.bg_color.is_none() {
-            rich_data.bg_color.replace(*self.background_color.read());
-        }
+    /// 设置可互动数据段的默认有效期。超过有效期后，数据段自动过期，其互动能力被禁用，文本显示删除线，图片显示为灰度图。
+    /// 通过`UserData::set_ttl()`单独设置了有效期的数据段，不论是否可互动，都按照自身的有效期过期。
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: 默认有效期，为0时关闭默认的自动过期。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_auto_expire(Duration::from_secs(60));
+    /// ```
+    pub fn set_auto_expire(&mut self, ttl: Duration) {
+        *self.auto_expire.write() = if ttl.is_zero() { None } else { Some(ttl) };
+    }
 
-        /*
-        对文档结束符进行特殊处理：当作光标移动到行首的操作，不作为可见数据添加。
-         */
-        match rich_data.data_type {
-            DataType::Text => {
-                // debug!("接收到 rich_data.text: {:?}", rich_data.text);
-                if self.rewrite_board.read().is_some() {
-                    // 从当前缓存中清理上一批面板流数据
-                    // self.clear_board_data();
-                    {
-                        self.current_buffer.write().clear();
-                    }
+    /// 设置新数据段的淡入效果。新添加的数据段先以淡黄色着色背景显示，着色在指定时间内逐渐消失，
+    /// 有助于在快速滚动时注意到新内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `duration`: 淡入效果的持续时间，建议500毫秒左右，为0时关闭淡入效果。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_fade_in(Duration::from_millis(500));
+    /// ```
+    pub fn set_fade_in(&mut self, duration: Duration) {
+        *self.fade_in.write() = if duration.is_zero() { None } else { Some(duration) };
+    }
 
-                    if let Some(board) = self.rewrite_board.write().as_mut() {
-                        // debug!("在面板流中添加数据：{:?}", rich_data.text);
-                        let mut board_data = board.add_data(rich_data, self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
-                        // debug!("面板流有 {} 条数据", board_data.len());
-                        self.current_buffer.write().append(&mut board_data);
-                    }
-                } else {
-                    // debug!("在常规流中添加数据：{:?}", rich_data.text);
-                    rich_data.text = rich_data.text.replace("\r", "");
-                    let last_piece = rich_data.estimate(self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
-                    *self.cursor_piece.write() = last_piece.read().get_cursor();
-                    self.current_buffer.write().push(rich_data);
+    /// 设置ANSI 16色调色板，设置了颜色序号的数据段按照调色板中的颜色绘制，已缓存的数据也会按照新调色板重新绘制。
+    /// 若已打开回顾区，回顾区同时使用新调色板。
+    ///
+    /// # Arguments
+    ///
+    /// * `palette`: 依次为黑、红、绿、黄、蓝、品红、青、白及其高亮色，默认值参见`DEFAULT_ANSI_PALETTE`。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::DEFAULT_ANSI_PALETTE;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_ansi_palette(DEFAULT_ANSI_PALETTE);
+    /// ```
+    pub fn set_ansi_palette(&mut self, palette: [Color; 16]) {
+        self.user_palette.write().replace(palette);
+        let palette = self.effective_palette();
+        self.use_ansi_palette(palette);
+    }
 
-                    if self.current_buffer.read().len() > self.buffer_max_lines.load(Ordering::Relaxed) {
-                        self.current_buffer.write().reverse();
-                        self.current_buffer.write().pop();
-                        self.current_buffer.write().reverse();
-                    }
-                }
+    /// 叠加色觉障碍配色后实际使用的调色板。
+    fn effective_palette(&self) -> [Color; 16] {
+        compose_palette(*self.user_palette.read(), self.color_transform.read().vision)
+    }
 
-            }
-            DataType::Image => {
-                let last_piece = rich_data.estimate(self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
-                *self.cursor_piece.write() = last_piece.read().get_cursor();
-                // self.throttle_holder.write().current_rid = rich_data.id;
-                // self.add_data(rich_data);
-                self.current_buffer.write().push(rich_data);
-            }
+    /// 将调色板应用到所有已缓存的数据和已打开的回顾区。
+    fn use_ansi_palette(&mut self, palette: [Color; 16]) {
+        self.ansi_palette.write().replace(palette);
+        self.current_buffer.write().iter_mut().for_each(|rd| rd.apply_palette(&palette));
+        if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            main_buffer.iter_mut().for_each(|rd| rd.apply_palette(&palette));
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.line_data_map.values_mut().flatten().for_each(|rd| rd.apply_palette(&palette));
+            board.invalidate();
         }
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_ansi_palette(palette);
+        }
+        self.update_panel_fn.write().update_param(false);
     }
 
-    /// 删除最后一个数据段。
-    pub fn delete_last_data(&mut self) {
-        if let Some(_rich_data) = self.current_buffer.write().pop() {
-            self.update_panel_fn.write().update_param(false);
-        }
+    /// 设置按住修饰键点击主视图时执行的动作。绑定的动作先于常规的互动操作检测，
+    /// 匹配时不再弹出互动菜单或汇报点击的数据段，未按修饰键的点击保持原有行为。仅响应鼠标左键。
+    ///
+    /// # Arguments
+    ///
+    /// * `modifier`: 修饰键。同时按下多个修饰键时，依次按照Ctrl、Alt、Meta、Shift的顺序匹配第一个。
+    /// * `binding`: 绑定的动作。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{ClickBinding, Modifier};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_modifier_click(Modifier::Ctrl, ClickBinding::OpenUrl);
+    /// ```
+    pub fn set_modifier_click(&mut self, modifier: Modifier, binding: ClickBinding) {
+        self.modifier_clicks.write().insert(modifier, binding);
     }
 
+    /// 移除修饰键点击绑定。
+    ///
+    /// # Arguments
+    ///
+    /// * `modifier`: 修饰键。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{ClickBinding, Modifier};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_modifier_click(Modifier::Ctrl, ClickBinding::OpenUrl);
+    /// rich_text.remove_modifier_click(Modifier::Ctrl);
+    /// ```
+    pub fn remove_modifier_click(&mut self, modifier: Modifier) {
+        self.modifier_clicks.write().remove(&modifier);
+    }
 
-    /// 查询目标字符串，并自动显示第一个或最后一个目标所在行。
-    /// 若以相同参数重复调用该方法，则每次调用都会自动定位到下一个查找到的目标位置。
+    /// 设置主视图的背景图片，图片绘制在数据内容下方，并按照淡化程度向背景色混合以保持文字清晰。
     ///
     /// # Arguments
     ///
-    /// * `search_str`: 目标字符串。如果给定一个空字符，则清空查询缓存。
-    /// * `forward`: true正向查找，false反向查找。
+    /// * `image`: 背景图片。
+    /// * `mode`: 显示方式。
     ///
-    /// returns: bool 若查找到目标返回true，否则返回false。
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
-    /// use fltk::{app, window};
-    /// use fltk::button::Button;
-    /// use fltk::group::Group;
-    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt, WindowExt};
+    /// use fltk::enums::ColorDepth;
+    /// use fltk::image::RgbImage;
     /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::BackgroundMode;
     ///
-    /// let app = app::App::default();
-    /// let mut win = window::Window::default().with_size(1000, 1000).with_label("Search").center_screen();
-    /// let group = Group::default_fill();
-    /// let mut btn1 = Button::new(200, 0, 100, 30, "查找字符串1");
     /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
-    /// btn1.set_callback({
-    ///     let mut rt = rich_text.clone();
-    ///     move |_| {
-    ///         rt.search_str(Some("程序".to_string()), false);
-    ///     }
-    /// });
-    /// group.end();
-    /// win.end();
-    /// win.show();
+    /// let image = RgbImage::new(&[64u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+    /// rich_text.set_background_image(image, BackgroundMode::Tile);
+    /// ```
+    pub fn set_background_image(&mut self, image: RgbImage, mode: BackgroundMode) {
+        self.background_image.write().set_image(Some(image), mode);
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 移除主视图的背景图片。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
     ///
-    /// while app.wait() {
-    ///     app::sleep(0.001);
-    ///     app::awake();
-    /// }
     /// ```
-    pub fn search_str(&mut self, search_str: Option<String>, forward: bool) -> bool {
-        let mut find_out = false;
-        if search_str.is_none() {
-            if let Some(rr) = &mut *self.reviewer.write() {
-                rr.clear_search_results();
-            }
-        } else if let Ok(open_suc) = self.auto_open_reviewer() {
-            if let Some(ref mut rr) = *self.reviewer.write() {
-                if let Some(search_str) = search_str {
-                    if !search_str.is_empty() {
-                        find_out = rr.search_str(search_str, forward);
-                        if !open_suc {
-                            // 如果回顾区早已打开，则强制刷新
-                            rr.scroller.set_damage(true);
-                        }
-                    } else {
-                        rr.clear_search_results();
-                    }
-                } else {
-                    rr.clear_search_results();
-                }
-            }
-        }
-
-        #[cfg(target_os = "linux")]
-        self.set_damage(true);
-
-        find_out
-    }
-
-    fn new_offline(
-        w: i32, h: i32, offscreen: Arc<RwLock<Offscreen>>,
-        panel: &mut impl WidgetBase,
-        visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
-        clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
-        bg_color: Color,
-        temp_buffer: Arc<RwLock<Vec<RichData>>>,
-        blink_flag: Arc<RwLock<BlinkState>>,
-        cursor: Option<Arc<RwLock<LinePiece>>>,
-        ) {
-        if let Some(offs) = Offscreen::new(w, h) {
-            *offscreen.write() = offs;
-            Self::draw_offline(offscreen.clone(), panel, visible_lines.clone(), clickable_data, bg_color, temp_buffer.clone(), blink_flag, cursor);
-        }
-    }
-
-    fn draw_offline(
-        offscreen: Arc<RwLock<Offscreen>>,
-        panel: &mut impl WidgetBase,
-        visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
-        clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
-        bg_color: Color,
-        current_buffer: Arc<RwLock<Vec<RichData>>>,
-        blink_flag: Arc<RwLock<BlinkState>>,
-        cursor: Option<Arc<RwLock<LinePiece>>>,) {
-        // debug!("开始离线绘制");
-        // let mut damage_area = (0, 0, 0, 0);
-        offscreen.read().begin();
-
-        let (panel_x, panel_y, window_width, window_height) = (panel.x(), panel.y(), panel.width(), panel.height());
-        let mut offset_y = 0;
-
-        let vl = &mut *visible_lines.write();
-        let cd = &mut *clickable_data.write();
-        vl.clear();
-        cd.clear();
-
-        // 填充背景
-        draw_rect_fill(0, 0, window_width, window_height, bg_color);
-        // damage_area = (0, 0, window_width, window_height);
-
-        let mut need_blink = false;
-
-        // 绘制数据内容
-        let data = current_buffer.read();
-        let mut set_offset_y = false;
-        let mut drawable_vec: Vec<&RichData> = vec![];
-        for (idx, rich_data) in data.iter().enumerate().rev() {
-            let bottom_y = rich_data.v_bounds.read().1;
-            if !set_offset_y && bottom_y > window_height {
-                offset_y = bottom_y - window_height + PADDING.bottom;
-                set_offset_y = true;
-            }
-
-            if bottom_y < offset_y {
-                break;
-            }
-
-            // 暂存主体任意部分可见的数据行信息
-            for piece in rich_data.line_pieces.iter() {
-                let piece = &*piece.read();
-                let y = piece.y - offset_y + panel_y;
-                let rect = Rectangle::new(piece.x + panel_x, y, piece.w, piece.h);
-                vl.insert(rect.clone(), piece.clone());
-
-                // 暂存可操作数据信息
-                if rich_data.clickable {
-                    cd.insert(rect, idx);
-                }
-            }
-
-            // rich_data.draw(offset_y, &*blink_flag.borrow());
-            // 倒序暂存
-            drawable_vec.push(rich_data);
-
-            if !need_blink && rich_data.blink {
-                need_blink = true;
-            }
-        }
-
-        // 顺序绘制
-        {
-            // debug!("本次绘制数据段：{:?}", drawable_vec.len());
-            let bf = &*blink_flag.read();
-            while let Some(rd) = drawable_vec.pop() {
-                // debug!("绘制数据段: {:?}", rd.text);
-                rd.draw(offset_y, bf);
-            }
-        }
-
-        // 填充顶部边界空白
-        draw_rect_fill(0, 0, window_width, PADDING.top, bg_color);
-
-        if let Some(cursor) = cursor {
-            // 绘制光标
-            blink_flag.write().on();
-            let cursor_piece = &*cursor.read();
-            // debug!("开始离线绘制光标: {:?}", cursor_piece);
-            let cursor_width = max(cursor_piece.font_size / 2, 4);
-            let y = cursor_piece.y - offset_y;
-            let bs = &*blink_flag.read();
-            let line_y = y + cursor_piece.font_height - ((cursor_piece.font_height as f32 / 10f32).floor() as i32 + 1);
-            match bs.next {
-                BlinkDegree::Normal => {
-                    // draw_rect_fill(cursor_piece.x, cursor_piece.y, cursor_width, cursor_piece.font_size, Color::White);
-                    set_draw_color(Color::White);
-                    // debug!("绘制白色光标");
-                    draw_line(cursor_piece.x, line_y, cursor_piece.x + cursor_width, line_y);
-                }
-                BlinkDegree::Contrast => {
-                    set_draw_color(bg_color);
-                    // debug!("绘制黑色光标");
-                    draw_line(cursor_piece.x, line_y, cursor_piece.x + cursor_width, line_y);
-                }
-            }
-
-            // damage_area = (cursor_piece.x, line_y - 1, cursor_width, 3);
-        }
-
-        offscreen.read().end();
-
-        // 更新闪烁标记
-        if need_blink {
-            blink_flag.write().on();
-        } else {
-            blink_flag.write().off();
-        }
-
-        // debug!("待刷新区域: {:?}", damage_area);
-        // panel.set_damage_area(Damage::All, damage_area.0, damage_area.1, damage_area.2, damage_area.3);
-        panel.set_damage(true);
+    /// use fltk::enums::ColorDepth;
+    /// use fltk::image::RgbImage;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::BackgroundMode;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let image = RgbImage::new(&[64u8; 12], 2, 2, ColorDepth::Rgb8).unwrap();
+    /// rich_text.set_background_image(image, BackgroundMode::Stretch);
+    /// rich_text.clear_background_image();
+    /// ```
+    pub fn clear_background_image(&mut self) {
+        self.background_image.write().set_image(None, BackgroundMode::default());
+        self.update_panel_fn.write().update_param(false);
     }
 
-    /// 设置面板背景色。
+    /// 设置背景图片的淡化程度，默认值为`DEFAULT_BACKGROUND_DIM`。
     ///
     /// # Arguments
     ///
-    /// * `background_color`: 背景色。
+    /// * `dim`: 淡化程度，取值范围0.0~1.0，0表示显示原图，1表示完全隐没在背景色中。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_background_dim(0.5);
     /// ```
-    pub fn set_background_color(&mut self, background_color: Color) {
-        *self.background_color.write() = background_color;
-        if let Some(reviewer) = self.reviewer.read().as_ref() {
-            reviewer.set_background_color(background_color);
+    pub fn set_background_dim(&mut self, dim: f32) {
+        let has_image = {
+            let mut bg = self.background_image.write();
+            bg.set_dim(dim);
+            bg.has_image()
+        };
+        if has_image {
+            self.update_panel_fn.write().update_param(false);
         }
     }
 
-    /// 设置数据缓存最大条数，并非行数。
-    ///
-    /// # Arguments
-    ///
-    /// * `max_lines`:
+    /// 获取换行试算缓存的统计信息。缓存由本组件的主视图和回顾区共享，相同字体、字号和可用宽度下的重复文本直接复用测量结果。
     ///
-    /// returns: ()
+    /// returns: WrapCacheStats
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let stats = rich_text.wrap_cache_stats();
+    /// assert!(stats.len <= stats.capacity);
+    /// assert!((0.0..=1.0).contains(&stats.hit_rate()));
     /// ```
-    pub fn set_cache_size(&mut self, max_lines: usize) {
-        self.buffer_max_lines.store(max_lines, Ordering::Relaxed);
-        if self.current_buffer.read().len() > self.buffer_max_lines.load(Ordering::Relaxed) {
-            let r = 0..(self.current_buffer.read().len() - self.buffer_max_lines.load(Ordering::Relaxed));
-            self.current_buffer.write().drain(r);
-            self.current_buffer.write().shrink_to_fit();
-        }
+    pub fn wrap_cache_stats(&self) -> WrapCacheStats {
+        self.wrap_cache.read().stats()
     }
 
-    /// 设置互动消息发送器。
+    /// 设置换行试算缓存的容量，超出容量时淘汰最久未使用的条目，默认值为`DEFAULT_WRAP_CACHE_CAPACITY`。
     ///
     /// # Arguments
     ///
-    /// * `notifier`:
+    /// * `capacity`: 缓存条目数上限，为0时关闭缓存。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_wrap_cache_capacity(1024);
+    /// assert_eq!(rich_text.wrap_cache_stats().capacity, 1024);
     /// ```
-    pub fn set_notifier<F>(&mut self, cb: F) where F: FnMut(CallbackData) + Send + Sync +'static {
-        let callback = Callback::new(Arc::new(RwLock::new(Box::new(cb))));
-        self.notifier.write().replace(callback);
+    pub fn set_wrap_cache_capacity(&mut self, capacity: usize) {
+        self.wrap_cache.write().set_capacity(capacity);
     }
 
-    /// 更改数据属性。
-    ///
-    /// # Arguments
-    ///
-    /// * `options`: 调整属性。
+    /// 清空换行试算缓存及其统计信息。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
-    /// use fltk::{app, window};
-    /// use fltk::enums::Color;
-    /// use fltk::prelude::{GroupExt, WidgetExt};
     /// use fltkrs_richdisplay::rich_text::RichText;
-    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
-    ///
-    /// pub enum GlobalMessage {
-    ///     ContentData(UserData),
-    ///     UpdateData(RichDataOptions),
-    ///     DisableData(i64),
-    /// }
     ///
-    /// let app = app::App::default();
-    /// let mut win = window::Window::default().with_size(1000, 600);
-    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
-    /// win.end();
-    /// win.show();
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.clear_wrap_cache();
+    /// assert_eq!(rich_text.wrap_cache_stats().len, 0);
+    /// ```
+    pub fn clear_wrap_cache(&mut self) {
+        self.wrap_cache.write().clear();
+    }
+
+    /// 强制完整重绘主视图和回顾区。丢弃离线绘图板，清空字形测量及换行试算缓存、对比度调整缓存、背景图片和图标缓存，
+    /// 重新计算所有数据的绘制位置后重绘。适用于修改全局`fltk`主题或字体配置等组件无法感知的外部变化之后。
     ///
-    /// let (sender, mut receiver) = tokio::sync::mpsc::channel::<UserData>(100);
-    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
-    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    /// returns: ()
     ///
-    /// let global_sender_rc = global_sender.clone();
-    /// tokio::spawn(async move {
-    ///     while let Some(data) = receiver.recv().await {
-    ///         if data.text.starts_with("14") {
-    ///             let toggle = !data.underline;
-    ///             let update_options = RichDataOptions::new(data.id).underline(toggle);
-    ///             global_sender_rc.send(GlobalMessage::UpdateData(update_options));
-    ///         } else if data.text.starts_with("22") {
-    ///             global_sender_rc.send(GlobalMessage::DisableData(data.id));
-    ///         }
-    ///     }
-    /// });
+    /// # Examples
     ///
-    /// while app.wait() {
-    ///     if let Some(msg) = global_receiver.recv() {
-    ///         match msg {
-    ///             GlobalMessage::ContentData(data) => {
-    ///                 rich_text.append(data);
-    ///             }
-    ///             GlobalMessage::UpdateData(options) => {
-    ///                 rich_text.update_data(options);
-    ///             }
-    ///             GlobalMessage::DisableData(id) => {
-    ///                 rich_text.disable_data(id);
-    ///             }
-    ///         }
-    ///     }
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
-    ///     app::sleep(0.001);
-    ///     app::awake();
-    /// }
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.invalidate_all();
     /// ```
-    pub fn update_data(&mut self, options: RichDataOptions) {
-        let mut find_out = false;
-        let mut target_idx = 0;
-        if let Ok(idx) = self.current_buffer.read().binary_search_by_key(&options.id, |rd| rd.id) {
-            target_idx = idx;
-            find_out = true;
+    pub fn invalidate_all(&mut self) {
+        self.wrap_cache.write().clear();
+        self.render_options.read().clear_cache();
+        self.background_image.write().clear_cache();
+        if let Some(offs) = Offscreen::new(max(self.panel.width(), 1), max(self.panel.height(), 1)) {
+            *self.panel_screen.write() = offs;
         }
-
-        if find_out {
-            if let Some(rd) = self.current_buffer.write().get_mut(target_idx) {
-                update_data_properties(options.clone(), rd);
-            }
-            self.update_panel_fn.write().update_param(false);
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.invalidate();
         }
+        self.relayout_main_buffer(|rd| rd.clear_image_cache());
 
         if let Some(reviewer) = self.reviewer.write().as_mut() {
-            reviewer.update_data(options);
+            reviewer.invalidate_all();
         }
 
-        // self.inner.redraw();
-        self.inner.set_damage(true);
+        self.update_panel_fn.write().update_param(false);
     }
 
-    /// 禁用数据片段的互动能力，同时伴随显示效果会有变化。
-    /// 对于文本段会增加删除线，对于图像会进行灰度处理。
+    /// 获取主视图上指定坐标处的单词，按照`unicode`单词边界规则分词。
+    /// 可用于实现按住Ctrl点击时将单词发送到服务端等功能。
     ///
     /// # Arguments
     ///
-    /// * `id`: 数据片段的ID。
+    /// * `x`: 窗口坐标系中的x坐标，例如`app::event_x()`。
+    /// * `y`: 窗口坐标系中的y坐标，例如`app::event_y()`。
     ///
-    /// returns: ()
+    /// returns: Option<String> 坐标处没有文字，或位于空白、标点上时返回空。
     ///
     /// # Examples
     ///
     /// ```
-    /// use fltk::{app, window};
-    /// use fltk::enums::Color;
-    /// use fltk::prelude::{GroupExt, WidgetExt};
+    /// use fltk::app;
     /// use fltkrs_richdisplay::rich_text::RichText;
-    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
     ///
-    /// pub enum GlobalMessage {
-    ///     ContentData(UserData),
-    ///     UpdateData(RichDataOptions),
-    ///     DisableData(i64),
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let (sender, _receiver) = app::channel::<String>();
+    /// // 例如在按住Ctrl点击时，将鼠标下的单词发送给服务端。
+    /// if let Some(word) = rich_text.word_at(app::event_x(), app::event_y()) {
+    ///     sender.send(word);
     /// }
+    /// ```
+    pub fn word_at(&self, x: i32, y: i32) -> Option<String> {
+        let (idx, char_idx) = self.locate_char(x, y)?;
+        word_around(&self.current_buffer.read().get(idx)?.text, char_idx)
+    }
+
+    /// 获取主视图上指定坐标处的网址。可用于实现鼠标悬停预览链接等功能。
     ///
-    /// let app = app::App::default();
-    /// let mut win = window::Window::default().with_size(1000, 600);
-    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
-    /// win.end();
-    /// win.show();
+    /// # Arguments
     ///
-    /// let (sender, mut receiver) = tokio::sync::mpsc::channel::<UserData>(100);
-    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
-    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    /// * `x`: 窗口坐标系中的x坐标，例如`app::event_x()`。
+    /// * `y`: 窗口坐标系中的y坐标，例如`app::event_y()`。
     ///
-    /// let global_sender_rc = global_sender.clone();
-    /// tokio::spawn(async move {
-    ///     while let Some(data) = receiver.recv().await {
-    ///         if data.text.starts_with("14") {
-    ///             let toggle = !data.underline;
-    ///             let update_options = RichDataOptions::new(data.id).underline(toggle);
-    ///             global_sender_rc.send(GlobalMessage::UpdateData(update_options));
-    ///         } else if data.text.starts_with("22") {
-    ///             global_sender_rc.send(GlobalMessage::DisableData(data.id));
-    ///         }
-    ///     }
-    /// });
+    /// returns: Option<String> 坐标处没有网址时返回空。
     ///
-    /// while app.wait() {
-    ///     if let Some(msg) = global_receiver.recv() {
-    ///         match msg {
-    ///             GlobalMessage::ContentData(data) => {
-    ///                 rich_text.append(data);
-    ///             }
-    ///             GlobalMessage::UpdateData(options) => {
-    ///                 rich_text.update_data(options);
-    ///             }
-    ///             GlobalMessage::DisableData(id) => {
-    ///                 rich_text.disable_data(id);
-    ///             }
-    ///         }
-    ///     }
+    /// # Examples
     ///
-    ///     app::sleep(0.001);
-    ///     app::awake();
+    /// ```
+    /// use fltk::app;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let (sender, _receiver) = app::channel::<String>();
+    /// // 例如在鼠标悬停时，请求上层应用预览链接。
+    /// if let Some(url) = rich_text.url_at(app::event_x(), app::event_y()) {
+    ///     sender.send(url);
     /// }
     /// ```
-    pub fn disable_data(&mut self, id: i64) {
-        let mut find_out = false;
-        let mut target_idx = 0;
-        if let Ok(idx) = self.current_buffer.read().binary_search_by_key(&id, |rd| rd.id) {
-            target_idx = idx;
-            find_out = true;
-        }
+    pub fn url_at(&self, x: i32, y: i32) -> Option<String> {
+        let (idx, char_idx) = self.locate_char(x, y)?;
+        url_around(&self.current_buffer.read().get(idx)?.text, char_idx)
+    }
 
-        if find_out {
-            if let Some(rd) = self.current_buffer.write().get_mut(target_idx) {
-                disable_data(rd);
-            }
+    /// 获取指定数据段在主视图上的显示区域，可用于在数据段附近放置应用自定义的浮层，例如提示、徽标或表情回应选择器。
+    /// 被高亮规则拆分的数据段返回全部片段的外接矩形，区域包括译文和链接预览卡片。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据段ID。
+    ///
+    /// returns: Option<(i32, i32, i32, i32)> 窗口坐标系中的x/y/w/h，数据段不在主视图缓存中、没有可见分片或滚动到可视区域之外时返回空。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::frame::Frame;
+    /// use fltk::prelude::*;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "hello\n".to_string()));
+    /// let mut badge = Frame::default().with_size(16, 16);
+    /// if let Some((x, y, w, _)) = rich_text.bounds_of(1) {
+    ///     badge.set_pos(x + w, y);
+    /// }
+    /// ```
+    pub fn bounds_of(&self, id: i64) -> Option<(i32, i32, i32, i32)> {
+        if !self.panel.visible() {
+            return None;
+        }
+        let scroll_y = self.view_scroll_y();
+        let (left, top, right, bottom) = self.current_buffer.read().iter()
+            .filter(|rd| rd.has_origin(id))
+            .filter_map(|rd| rd.piece_bounds())
+            .fold(None, |acc: Option<(i32, i32, i32, i32)>, (x, y, w, h)| match acc {
+                Some((l, t, r, b)) => Some((min(l, x), min(t, y), max(r, x + w), max(b, y + h))),
+                None => Some((x, y, x + w, y + h)),
+            })?;
+        let (x, y, w, h) = (left + self.panel.x(), top - scroll_y + self.panel.y(), right - left, bottom - top);
+        if y + h <= self.panel.y() || y >= self.panel.y() + self.panel.height() {
+            return None;
+        }
+        Some((x, y, w, h))
+    }
 
-            self.update_panel_fn.write().update_param(false);
+    /// 获取主视图上指定坐标处的数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `x`: 窗口坐标系中的x坐标，例如`app::event_x()`。
+    /// * `y`: 窗口坐标系中的y坐标，例如`app::event_y()`。
+    ///
+    /// returns: Option<UserData> 坐标处没有文字或图形时返回空。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::app;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "hello\n".to_string()));
+    /// if let Some(data) = rich_text.data_at_point(app::event_x(), app::event_y()) {
+    ///     assert_eq!(data.id, 1);
+    /// }
+    /// ```
+    pub fn data_at_point(&self, x: i32, y: i32) -> Option<UserData> {
+        if !self.panel.visible() {
+            return None;
         }
+        let scroll_y = self.view_scroll_y();
+        let buffer = self.current_buffer.read();
+        let idx = locate_data_at(buffer.as_slice(), x - self.panel.x(), y - self.panel.y() + scroll_y)?;
+        buffer.get(idx).map(UserData::from)
+    }
 
-        if let Some(reviewer) = self.reviewer.write().as_mut() {
-            reviewer.disable_data(id);
+    /// 将窗口坐标转换为主视图内容坐标，并定位该处的字符。
+    fn locate_char(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        if !self.panel.visible() {
+            return None;
         }
+        let scroll_y = self.view_scroll_y();
+        locate_char_at(self.current_buffer.read().as_slice(), x - self.panel.x(), y - self.panel.y() + scroll_y)
+    }
 
-        // self.inner.redraw();
-        self.inner.set_damage(true);
+    /// 获取由服务端控制的终端模式状态。
+    ///
+    /// returns: TerminalModes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_bracketed_paste(true);
+    /// assert!(rich_text.terminal_modes().bracketed_paste);
+    /// assert_eq!(rich_text.terminal_modes().mouse_tracking, None);
+    /// ```
+    pub fn terminal_modes(&self) -> TerminalModes {
+        *self.terminal_modes.read()
     }
 
-    /// 自动关闭回顾区的接口。当回顾区滚动条已抵达最底部时会关闭回顾区，否则不关闭也不产生额外干扰。
+    /// 开启或关闭括号粘贴模式。模式变化时通过`CallbackData::ModeChanged`回调通知上层应用。
     ///
-    /// 通常无需调用此方法，当回顾区的滚动条滚动到最底部时会自动关闭。
-    /// 若希望响应PageDown按键关闭回顾区，需要自行在window上注册事件处理逻辑，并调用该接口。
+    /// # Arguments
     ///
-    /// 该方法适合在调用者的事件处理器当中使用。
+    /// * `on`: 是否开启。
     ///
-    /// returns: bool 当满足关闭条件时，返回 `true`，否则返回 `false`。对于事件处理器来说，当本方法返回 `true` 时，提示事件应被消耗，否则应忽略当前事件。
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
-    /// use fltk::enums::{Event, Key};
-    /// use fltk::{app, window};
-    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt};
     /// use fltkrs_richdisplay::rich_text::RichText;
     ///
-    /// let app = app::App::default();
-    /// let mut win = window::Window::default().with_size(1000, 600);
-    /// let rich_text = RichText::new(100, 100, 800, 400, None);
-    /// win.handle({
-    ///     let rich_text_rc = rich_text.clone();
-    ///     move |_, evt| {
-    ///         let mut handled = false;
-    ///         match evt {
-    ///             Event::KeyDown => {
-    ///                 if app::event_key_down(Key::PageDown) {
-    ///                     handled = rich_text_rc.auto_close_reviewer();
-    ///                 } else if app::event_key_down(Key::PageUp) {
-    ///                     if let Ok(ret) = rich_text_rc.auto_open_reviewer() {
-    ///                         handled = ret;
-    ///                     }
-    ///                 }
-    ///
-    ///             }
-    ///             _ => {}
-    ///         }
-    ///         handled
-    ///     }
-    /// });
-    /// win.end();
-    /// win.show();
-    /// app.run().unwrap();
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_bracketed_paste(true);
+    /// assert_eq!(rich_text.wrap_paste("look"), "\x1b[200~look\x1b[201~");
     /// ```
-    pub fn auto_close_reviewer(&self) -> bool {
-        if self.reviewer.read().is_some() {
-            if let Err(e) = app::handle_main(LocalEvent::DROP_REVIEWER_FROM_EXTERNAL) {
-                error!("从外部发送关闭回顾区组件事件时出错: {:?}", e);
-            }
+    pub fn set_bracketed_paste(&mut self, on: bool) {
+        let changed = {
+            let mut modes = self.terminal_modes.write();
+            let changed = modes.bracketed_paste != on;
+            modes.bracketed_paste = on;
+            changed
+        };
+        if changed {
+            self.notify_modes();
         }
-        false
     }
 
-    /// 自动打开回顾区的接口。当没有显示回顾区时自动打开回顾区，否则不关闭也不产生额外干扰。该方法适合在调用者的事件处理器当中使用。
+    /// 开启或关闭鼠标汇报模式。模式变化时通过`CallbackData::ModeChanged`回调通知上层应用。
+    /// 开启跟踪模式后，鼠标在主视图上的按键事件不再用于本地选择和互动，而是编码后通过`CallbackData::MouseReport`回调交给上层应用发送至服务端，
+    /// 按住Shift键时仍可进行本地操作。
     ///
-    /// 通常无需调用此方法，当鼠标滚轮向上滚动时会自动打开回顾区。
-    /// 若希望响应PageUp按键打开回顾区，需要自行在window上注册事件处理逻辑，并调用该接口。
+    /// # Arguments
     ///
-    /// returns: bool 当满足关闭条件时，返回 `true`，否则返回 `false`。对于事件处理器来说，当本方法返回 `true` 时，提示事件应被消耗，否则应忽略当前事件。
+    /// * `mode`: 鼠标汇报模式，`X10`和`Normal`为跟踪模式，二者互斥；`Sgr`为编码方式。
+    /// * `on`: 是否开启。
+    ///
+    /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
-    /// use fltk::enums::{Event, Key};
-    /// use fltk::{app, window};
-    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt};
     /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::MouseReportMode;
     ///
-    /// let app = app::App::default();
-    /// let mut win = window::Window::default().with_size(1000, 600);
-    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
-    /// win.handle({
-    ///     let mut rich_text_rc = rich_text.clone();
-    ///     move |_, evt| {
-    ///         let mut handled = false;
-    ///         match evt {
-    ///             Event::KeyDown => {
-    ///                 if app::event_key_down(Key::PageDown) {
-    ///                     handled = rich_text_rc.auto_close_reviewer();
-    ///                 } else if app::event_key_down(Key::PageUp) {
-    ///                     if let Ok(ret) = rich_text_rc.auto_open_reviewer() {
-    ///                        handled = ret;
-    ///                     }
-    ///                 }
-    ///
-    ///             }
-    ///             _ => {}
-    ///         }
-    ///         handled
-    ///     }
-    /// });
-    /// win.end();
-    /// win.show();
-    /// app.run().unwrap();
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_mouse_report(MouseReportMode::Normal, true);
+    /// rich_text.set_mouse_report(MouseReportMode::Sgr, true);
+    /// assert_eq!(rich_text.terminal_modes().mouse_tracking, Some(MouseReportMode::Normal));
+    /// assert!(rich_text.terminal_modes().sgr_mouse);
     /// ```
-    pub fn auto_open_reviewer(&self) -> Result<bool, FltkError> {
-        return if !self.current_buffer.read().is_empty() && self.reviewer.read().is_none() {
-            let handle_result = app::handle_main(LocalEvent::OPEN_REVIEWER_FROM_EXTERNAL);
-            match handle_result {
-                Ok(handled) => {Ok(handled)}
-                Err(e) => {
-                    error!("从外部发送打开回顾区组件事件时出错: {:?}", e);
-                    Err(e)
+    pub fn set_mouse_report(&mut self, mode: MouseReportMode, on: bool) {
+        let changed = {
+            let mut modes = self.terminal_modes.write();
+            let old = *modes;
+            match mode {
+                MouseReportMode::Sgr => {
+                    modes.sgr_mouse = on;
+                }
+                tracking => {
+                    if on {
+                        modes.mouse_tracking.replace(tracking);
+                    } else if modes.mouse_tracking == Some(tracking) {
+                        modes.mouse_tracking = None;
+                    }
                 }
             }
+            old != *modes
+        };
+        if changed {
+            self.notify_modes();
+        }
+    }
+
+    /// 按照当前的括号粘贴模式包装待发送的粘贴内容。未开启该模式时原样返回。
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: 用户粘贴的内容。
+    ///
+    /// returns: String
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// assert_eq!(rich_text.wrap_paste("look"), "look");
+    /// rich_text.set_bracketed_paste(true);
+    /// assert_eq!(rich_text.wrap_paste("look"), "\x1b[200~look\x1b[201~");
+    /// ```
+    pub fn wrap_paste(&self, text: &str) -> String {
+        if self.terminal_modes.read().bracketed_paste {
+            // 移除内容中的结束标记，避免粘贴内容提前结束括号粘贴。
+            format!("\x1b[200~{}\x1b[201~", text.replace("\x1b[201~", ""))
         } else {
-            Ok(false)
+            text.to_string()
         }
     }
 
-    /// 设置默认的字体，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    /// 注册扩展插件。插件按照注册顺序调用，同名插件会被替换。
     ///
     /// # Arguments
     ///
-    /// * `font`: 默认字体。
+    /// * `plugin`: 实现了`RichDisplayPlugin`特征的插件。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::plugin::{PanelView, RichDisplayPlugin};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
     ///
+    /// struct Counter(usize);
+    /// impl RichDisplayPlugin for Counter {
+    ///     fn name(&self) -> &str { "counter" }
+    ///     fn on_append(&mut self, _data: &UserData, _view: &PanelView) { self.0 += 1; }
+    /// }
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.register_plugin(Box::new(Counter(0)));
     /// ```
-    pub fn set_text_font(&mut self, font: Font) {
-        // let old_font = self.text_font;
-        *self.text_font.write() = font;
+    pub fn register_plugin(&mut self, plugin: Box<dyn RichDisplayPlugin>) {
+        let mut plugins = self.plugins.write();
+        let name = plugin.name().to_string();
+        let plugin = Arc::new(RwLock::new(plugin));
+        if let Some(old) = plugins.iter_mut().find(|(n, _)| *n == name) {
+            old.1 = plugin;
+        } else {
+            plugins.push((name, plugin));
+        }
+    }
 
+    /// 移除指定名称的扩展插件。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 插件名称。
+    ///
+    /// returns: bool 存在该插件时返回`true`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::plugin::RichDisplayPlugin;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// struct Noop;
+    /// impl RichDisplayPlugin for Noop {
+    ///     fn name(&self) -> &str { "noop" }
+    /// }
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.register_plugin(Box::new(Noop));
+    /// assert!(rich_text.remove_plugin("noop"));
+    /// assert!(!rich_text.remove_plugin("noop"));
+    /// ```
+    pub fn remove_plugin(&mut self, name: &str) -> bool {
+        let mut plugins = self.plugins.write();
+        let len = plugins.len();
+        plugins.retain(|(n, _)| n != name);
+        plugins.len() != len
     }
 
-    /// 获取默认的字体。
-    pub fn text_font(&self) -> Font {
-        *self.text_font.read()
+    /// 添加监视关键字。此后添加到常规流中的文本数据段包含该关键字时，通过`CallbackData::Watch`回调通知，
+    /// 数据段的显示不受影响。定位面板中反复重绘的内容不参与监视。
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern`: 关键字，区分大小写。
+    ///
+    /// returns: WatchId 监视关键字的标识，用于移除监视。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{CallbackData, UserData};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let hits = Arc::new(Mutex::new(vec![]));
+    /// let hits_rc = hits.clone();
+    /// rich_text.set_notifier(move |data| {
+    ///     if let CallbackData::Watch(hit) = data {
+    ///         hits_rc.lock().unwrap().push(hit.data_id);
+    ///     }
+    /// });
+    /// rich_text.add_watch("张三");
+    /// rich_text.append(UserData::new_text_with_id(1, "张三对你说：你好\n".to_string()));
+    /// assert_eq!(*hits.lock().unwrap(), vec![1]);
+    /// ```
+    pub fn add_watch(&mut self, pattern: &str) -> WatchId {
+        let mut watches = self.watches.write();
+        let id = WatchId(watches.next_id);
+        watches.next_id += 1;
+        watches.patterns.insert(id, pattern.to_string());
+        id
     }
 
-    pub fn text_font_ref(&self) -> Arc<RwLock<Font>> {
-        self.text_font.clone()
+    /// 移除监视关键字。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 监视关键字的标识。
+    ///
+    /// returns: bool 目标不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let id = rich_text.add_watch("张三");
+    /// assert!(rich_text.remove_watch(id));
+    /// assert!(!rich_text.remove_watch(id));
+    /// ```
+    pub fn remove_watch(&mut self, id: WatchId) -> bool {
+        self.watches.write().patterns.remove(&id).is_some()
+    }
+
+    /// 添加高亮规则。此后添加的文本数据段中与规则匹配的文字被拆分为独立的数据段，并按照规则调整样式。
+    /// 规则开启触发时，命中后通过`CallbackData::Trigger`回调通知，定位面板中反复重绘的内容只调整样式不触发通知。
+    /// 多条规则匹配到重叠的文字时，先添加的规则优先。
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern`: 匹配规则。
+    /// * `style`: 匹配文字的样式调整。
+    ///
+    /// returns: HighlightId 高亮规则的标识，用于移除规则。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use regex::Regex;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::StyleOverride;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.add_highlight_rule(Regex::new(r"\d+ gold").unwrap(), StyleOverride::new().set_fg_color(Color::Yellow).set_trigger(true));
+    /// ```
+    pub fn add_highlight_rule(&mut self, pattern: Regex, style: StyleOverride) -> HighlightId {
+        let mut highlights = self.highlights.write();
+        let id = HighlightId(highlights.next_id);
+        highlights.next_id += 1;
+        highlights.rules.push(HighlightRule { id, pattern, style });
+        id
+    }
+
+    /// 移除高亮规则，已添加的数据不受影响。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 高亮规则的标识。
+    ///
+    /// returns: bool 目标不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use regex::Regex;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::StyleOverride;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let id = rich_text.add_highlight_rule(Regex::new(r"\d+ gold").unwrap(), StyleOverride::new().set_fg_color(Color::Yellow));
+    /// assert!(rich_text.remove_highlight_rule(id));
+    /// assert!(!rich_text.remove_highlight_rule(id));
+    /// ```
+    pub fn remove_highlight_rule(&mut self, id: HighlightId) -> bool {
+        let mut highlights = self.highlights.write();
+        let len = highlights.rules.len();
+        highlights.rules.retain(|rule| rule.id != id);
+        highlights.rules.len() != len
+    }
+
+    /// 添加持久高亮的关键字，已有和此后添加的数据中所有出现该关键字的位置都以指定背景色绘制，直到被移除，例如始终高亮自己的角色名。
+    /// 与高亮规则不同，关键字高亮不拆分数据段，只在绘制时叠加背景色。关键字已存在时更新其颜色，多个关键字重叠时先添加的关键字优先。
+    ///
+    /// # Arguments
+    ///
+    /// * `word`: 关键字。
+    /// * `color`: 背景色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.add_keyword_highlight("张三", Color::DarkRed);
+    /// ```
+    pub fn add_keyword_highlight(&mut self, word: &str, color: Color) {
+        {
+            let mut keywords = self.keywords.write();
+            match keywords.iter_mut().find(|keyword| keyword.word == word) {
+                Some(keyword) => keyword.color = color,
+                None => keywords.push(KeywordHighlight { word: word.to_string(), color }),
+            }
+        }
+        self.refresh_keywords();
+    }
+
+    /// 移除持久高亮的关键字，已有数据中的高亮同时清除。
+    ///
+    /// # Arguments
+    ///
+    /// * `word`: 关键字。
+    ///
+    /// returns: bool 关键字不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.add_keyword_highlight("张三", Color::DarkRed);
+    /// assert!(rich_text.remove_keyword_highlight("张三"));
+    /// assert!(!rich_text.remove_keyword_highlight("李四"));
+    /// ```
+    pub fn remove_keyword_highlight(&mut self, word: &str) -> bool {
+        let removed = {
+            let mut keywords = self.keywords.write();
+            let len = keywords.len();
+            keywords.retain(|keyword| keyword.word != word);
+            keywords.len() != len
+        };
+        if removed {
+            self.refresh_keywords();
+        }
+        removed
+    }
+
+    /// 按照当前的关键字重新计算所有缓存数据的高亮位置，并刷新主视图和回顾区。
+    fn refresh_keywords(&mut self) {
+        let keywords = self.keywords.read().clone();
+        self.current_buffer.write().iter_mut().for_each(|rd| rd.apply_keywords(&keywords));
+        if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            main_buffer.iter_mut().for_each(|rd| rd.apply_keywords(&keywords));
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.line_data_map.values_mut().flatten().for_each(|rd| rd.apply_keywords(&keywords));
+            board.invalidate();
+        }
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_keyword_highlights(keywords);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 定义或修改具名样式。此后添加的引用该样式的数据段按照定义设置样式，已缓存的引用该样式的数据段立即按新定义更新并重新排版，
+    /// 应用程序可据此集中调整各类消息的外观。旧定义设置过而新定义未设置的样式恢复为组件的默认样式，两者都未设置的样式保持不变。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 样式名称，与`UserData::with_style`中的名称对应。
+    /// * `def`: 样式定义。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{StyleDef, UserData};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.define_style("error", StyleDef::new().set_fg_color(Color::Red).set_blink(true));
+    /// rich_text.append(UserData::new_text("连接已断开\n".to_string()).with_style("error"));
+    /// rich_text.define_style("error", StyleDef::new().set_fg_color(Color::Magenta).set_blink(false));
+    /// ```
+    pub fn define_style(&mut self, name: &str, def: StyleDef) {
+        let previous = self.styles.write().insert(name.to_string(), def.clone());
+        let base = self.style_base();
+        let restyle = |rd: &mut RichData| {
+            if rd.style_name.as_deref() == Some(name) {
+                def.restyle(previous.as_ref(), &base, rd);
+            }
+        };
+        // 没有引用该样式的数据段时无需重新排版。
+        let in_use = |buffer: &[RichData]| buffer.iter().any(|rd| rd.style_name.as_deref() == Some(name));
+        if in_use(&self.current_buffer.read()) || self.data_buffer.read().as_ref().is_some_and(|buffer| in_use(buffer)) {
+            self.relayout_main_buffer(restyle);
+            self.update_panel_fn.write().update_param(false);
+        }
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.restyle(name, previous.as_ref(), &def);
+        }
+    }
+
+    /// 组件的默认样式，具名样式未设置或被撤销的部分以此为准。
+    fn style_base(&self) -> StyleDef {
+        StyleDef::new()
+            .set_font(*self.text_font.read())
+            .set_font_size(self.text_size.load(Ordering::Relaxed))
+            .set_fg_color(*self.text_color.read())
+            .set_bg_color(*self.background_color.read())
+    }
+
+    /// 获取按互动行为类别统计的数据到达情况，未设置`Action.category`的数据段不参与统计，被重复检测抑制的数据段不计入。
+    /// 上层应用可据此显示各频道的活跃程度，而无需遍历数据缓存。
+    ///
+    /// returns: BTreeMap<String, CategoryStats> 类别名称及其统计信息。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{Action, UserData};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let action = Action { category: Some("chat".to_string()), ..Default::default() };
+    /// rich_text.append(UserData::new_text("大家好\n".to_string()).set_action(action));
+    /// let stats = rich_text.category_stats();
+    /// assert_eq!(stats["chat"].segments, 1);
+    /// assert_eq!(stats["chat"].bytes, "大家好\n".len() as u64);
+    /// ```
+    pub fn category_stats(&self) -> BTreeMap<String, CategoryStats> {
+        self.category_stats.read().clone()
+    }
+
+    /// 清空按类别统计的数据到达情况。
+    pub fn reset_category_stats(&mut self) {
+        self.category_stats.write().clear();
+    }
+
+    /// 创建当前会话的快照，包括数据缓存、光标、定位面板和滚动区域状态。
+    /// 可配合`restore`实现可撤销的清屏，或在多个会话标签之间切换同一个组件。
+    /// 快照完整复制数据缓存和分片，开销与缓存的数据量成正比。
+    ///
+    /// returns: SessionSnapshot
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text("保留的内容".to_string()));
+    /// let snapshot = rich_text.snapshot();
+    /// rich_text.clear();
+    /// rich_text.restore(snapshot);
+    /// ```
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            current_buffer: Arc::new(detach_buffer(&self.current_buffer.read())),
+            data_buffer: Arc::new(self.data_buffer.read().as_deref().map(detach_buffer)),
+            rewrite_board: Arc::new(self.rewrite_board.read().as_ref().map(|board| board.detached())),
+            cursor_piece: self.cursor_piece.read().clone(),
+            show_cursor: self.show_cursor.load(Ordering::Relaxed),
+            remote_flow_control: self.remote_flow_control.load(Ordering::SeqCst),
+            line_counter: self.line_counter.load(Ordering::Relaxed),
+            current_col: self.current_col.load(Ordering::Relaxed),
+            top_insert_pos: self.top_insert_pos.load(Ordering::Relaxed),
+            alternate_saved: self.alternate_saved.read().as_ref().map(|saved| saved.detached()),
+            scroll_region: *self.scroll_region.read(),
+            layout_size: (self.panel.width(), self.panel.height()),
+        }
+    }
+
+    /// 将当前会话的状态移出组件，数据缓存直接转移到快照中而不复制，用于切换会话标签。
+    fn take_session(&mut self) -> SessionSnapshot {
+        SessionSnapshot {
+            current_buffer: Arc::new(std::mem::take(&mut *self.current_buffer.write())),
+            data_buffer: Arc::new(self.data_buffer.write().take()),
+            rewrite_board: Arc::new(self.rewrite_board.write().take()),
+            cursor_piece: self.cursor_piece.read().clone(),
+            show_cursor: self.show_cursor.load(Ordering::Relaxed),
+            remote_flow_control: self.remote_flow_control.load(Ordering::SeqCst),
+            line_counter: self.line_counter.load(Ordering::Relaxed),
+            current_col: self.current_col.load(Ordering::Relaxed),
+            top_insert_pos: self.top_insert_pos.load(Ordering::Relaxed),
+            alternate_saved: self.alternate_saved.write().take(),
+            scroll_region: *self.scroll_region.read(),
+            layout_size: (self.panel.width(), self.panel.height()),
+        }
+    }
+
+    /// 从快照恢复会话状态，当前的数据会被替换。若已打开回顾区，则先关闭回顾区。
+    /// 恢复时按照当前窗口尺寸重新计算数据的绘制位置。
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot`: 由`snapshot`创建的会话快照。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let snapshot = rich_text.snapshot();
+    /// rich_text.restore(snapshot);
+    /// ```
+    pub fn restore(&mut self, snapshot: SessionSnapshot) {
+        self.auto_close_reviewer();
+        self.put_session(snapshot, true);
+    }
+
+    /// 将快照中的状态放回组件。快照不再被其他克隆持有时直接取用其中的数据缓存，
+    /// 只有强制要求或主视图尺寸在保存快照后发生变化时才重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot`: 会话快照。
+    /// * `relayout`: 是否强制重新排版。
+    ///
+    /// returns: ()
+    fn put_session(&mut self, snapshot: SessionSnapshot, relayout: bool) {
+        let resized = snapshot.layout_size != (self.panel.width(), self.panel.height());
+        // 快照仍被其他克隆持有时复制一份，避免重新排版改变其他克隆中的分片。
+        *self.current_buffer.write() = Arc::try_unwrap(snapshot.current_buffer).unwrap_or_else(|b| detach_buffer(&b));
+        *self.data_buffer.write() = Arc::try_unwrap(snapshot.data_buffer).unwrap_or_else(|b| b.as_deref().map(detach_buffer));
+        *self.rewrite_board.write() = Arc::try_unwrap(snapshot.rewrite_board).unwrap_or_else(|b| b.as_ref().map(|board| board.detached()));
+        *self.cursor_piece.write() = snapshot.cursor_piece;
+        self.show_cursor.store(snapshot.show_cursor, Ordering::Relaxed);
+        self.wake_cursor_blink();
+        self.remote_flow_control.store(snapshot.remote_flow_control, Ordering::SeqCst);
+        self.line_counter.store(snapshot.line_counter, Ordering::Relaxed);
+        self.current_col.store(snapshot.current_col, Ordering::Relaxed);
+        self.top_insert_pos.store(snapshot.top_insert_pos, Ordering::Relaxed);
+        *self.alternate_saved.write() = snapshot.alternate_saved;
+        *self.scroll_region.write() = snapshot.scroll_region;
+        self.rebuild_thread_index();
+
+        // 窗口尺寸可能在快照之后发生了变化。
+        if resized {
+            if let Some(board) = self.rewrite_board.write().as_mut() {
+                board.resize(max(self.max_rows.load(Ordering::Relaxed), 2), max(self.max_cols.load(Ordering::Relaxed), 2));
+            }
+        }
+        if relayout || resized {
+            self.relayout_main_buffer(|_| {});
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置清屏操作日志保留的快照数量。开启后，服务端发送的整屏清除（`EraseInDisplay`的模式2和3）和本地的`clear`操作执行前都会保存会话快照，
+    /// 可通过`undo_erase`撤销。从光标处清除到屏幕首尾的局部清除以及定位面板中的清屏不记录。
+    ///
+    /// # Arguments
+    ///
+    /// * `depth`: 保留的最大快照数量，为0时关闭日志并丢弃已保存的快照。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_erase_journal(3);
+    /// rich_text.clear();
+    /// assert!(rich_text.undo_erase());
+    /// assert!(!rich_text.undo_erase());
+    /// ```
+    pub fn set_erase_journal(&mut self, depth: usize) {
+        let mut journal = self.erase_journal.write();
+        journal.depth = depth;
+        while journal.snapshots.len() > depth {
+            journal.snapshots.pop_front();
+        }
+    }
+
+    /// 清空主视图的所有数据，光标和定位面板等状态恢复为初始状态。若已打开回顾区，则先关闭回顾区。
+    /// 开启清屏操作日志时可通过`undo_erase`撤销。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text("hello".to_string()));
+    /// rich_text.clear();
+    /// ```
+    pub fn clear(&mut self) {
+        self.auto_close_reviewer();
+        // 清空的数据直接转移到操作日志中，不需要复制。
+        let erased = self.take_session();
+        if self.erase_journal.read().depth > 0 {
+            self.erase_journal.write().record(erased);
+        }
+        let empty = self.empty_snapshot();
+        self.put_session(empty, false);
+    }
+
+    /// 撤销最近一次清屏，将清屏前的数据放回主视图。清屏之后没有新数据时完整恢复清屏前的状态；
+    /// 已有新数据时，清屏前的数据放在新数据之前，光标等状态保持不变。
+    ///
+    /// returns: bool 没有可撤销的清屏时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{DocEditType, UserData};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_erase_journal(3);
+    /// rich_text.append(UserData::new_text("hello\n".to_string()));
+    /// // 局部清除不记录快照。
+    /// rich_text.append_batch(&mut vec![DocEditType::EraseInDisplay(0)]);
+    /// assert!(!rich_text.undo_erase());
+    /// rich_text.append_batch(&mut vec![DocEditType::EraseInDisplay(2)]);
+    /// // 清屏之后到达的数据保留在恢复的数据之后。
+    /// rich_text.append(UserData::new_text("world\n".to_string()));
+    /// assert!(rich_text.undo_erase());
+    /// assert!(!rich_text.undo_erase());
+    /// ```
+    pub fn undo_erase(&mut self) -> bool {
+        let Some(snapshot) = self.erase_journal.write().snapshots.pop_back() else {
+            return false;
+        };
+        self.auto_close_reviewer();
+        let journaled_ids = |buffer: &[RichData]| buffer.iter().map(|rd| rd.id).collect::<HashSet<i64>>();
+        let current_ids = journaled_ids(&snapshot.current_buffer);
+        let main_ids = snapshot.data_buffer.as_deref().map(journaled_ids).unwrap_or_default();
+        let has_newer = self.current_buffer.read().iter().any(|rd| !current_ids.contains(&rd.id))
+            || self.data_buffer.read().as_ref().is_some_and(|buffer| buffer.iter().any(|rd| !main_ids.contains(&rd.id)));
+        if !has_newer {
+            self.put_session(snapshot, true);
+            return true;
+        }
+
+        // 清屏时仍保留在缓存中的数据以日志中的版本为准，之后到达的数据接在其后。
+        let max_lines = self.buffer_max_lines.load(Ordering::Relaxed);
+        let prepend = |journaled: Vec<RichData>, current: &mut Vec<RichData>, ids: &HashSet<i64>| {
+            let newer = std::mem::take(current).into_iter().filter(|rd| !ids.contains(&rd.id));
+            *current = journaled;
+            current.extend(newer);
+            if current.len() > max_lines {
+                current.drain(..current.len() - max_lines);
+            }
+        };
+        let journaled = Arc::try_unwrap(snapshot.current_buffer).unwrap_or_else(|b| detach_buffer(&b));
+        prepend(journaled, &mut *self.current_buffer.write(), &current_ids);
+        let journaled = Arc::try_unwrap(snapshot.data_buffer).unwrap_or_else(|b| b.as_deref().map(detach_buffer));
+        if let (Some(journaled), Some(main_buffer)) = (journaled, self.data_buffer.write().as_mut()) {
+            prepend(journaled, main_buffer, &main_ids);
+        }
+        self.rebuild_thread_index();
+        self.relayout_main_buffer(|_| {});
+        self.update_panel_fn.write().update_param(false);
+        true
+    }
+
+    /// 开启清屏操作日志时保存当前的会话快照。服务端的整屏清除在原缓存上擦除分片，因此需要复制一份。
+    fn journal_erase(&mut self) {
+        if self.erase_journal.read().depth == 0 {
+            return;
+        }
+        let snapshot = self.snapshot();
+        self.erase_journal.write().record(snapshot);
+    }
+
+    /// 设置是否启用确定性模式。启用后闪烁、光标闪烁、淡入和自动过期定时器不再自动执行任务，闪烁相位固定为正常显示，
+    /// 由调用者通过`tick`和`flush`控制组件状态的推进时机。
+    /// 配合`deterministic::set_clock`和`deterministic::set_id_source`注入的时钟和ID来源，可对脚本化操作后的组件状态进行快照测试。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`: 是否启用。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use fltkrs_richdisplay::deterministic::{self, ManualClock};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// deterministic::set_clock(Some(Arc::new(ManualClock::new())));
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_deterministic(true);
+    /// rich_text.append(UserData::new_text_with_id(1, "hello\n".to_string()));
+    /// rich_text.flush();
+    /// assert!(rich_text.layout_snapshot().iter().all(|record| record.id == 1));
+    /// deterministic::set_clock(None);
+    /// ```
+    pub fn set_deterministic(&mut self, enable: bool) {
+        self.deterministic.store(enable, Ordering::Relaxed);
+        if enable {
+            self.blink_flag.write().reset_phase();
+            self.fade_until.write().take();
+        } else {
+            self.wake_cursor_blink();
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 是否处于确定性模式。
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic.load(Ordering::Relaxed)
+    }
+
+    /// 跳过防抖延迟，立即处理待合并队列中的数据，并刷新主视图的离线缓存和可见分片信息。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_max_fps(Some(30));
+    /// rich_text.append(UserData::new_text("hello\n".to_string()));
+    /// // 数据仍在待合并队列中，立即处理。
+    /// rich_text.flush();
+    /// assert_eq!(rich_text.export_text(false), "hello\n");
+    /// ```
+    pub fn flush(&mut self) {
+        if !self.append_queue.read().is_empty() {
+            self.render_frame();
+        }
+        self.panel_updater.update(true);
+    }
+
+    /// 手动执行一次定时任务：按照当前时钟处理到期的数据段，然后立即刷新主视图。
+    /// 确定性模式下定时器不再自动执行，需通过此方法推进。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::deterministic::{self, ManualClock};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let clock = Arc::new(ManualClock::new());
+    /// deterministic::set_clock(Some(clock.clone()));
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_deterministic(true);
+    /// clock.advance(Duration::from_secs(60));
+    /// rich_text.tick();
+    /// deterministic::set_clock(None);
+    /// ```
+    pub fn tick(&mut self) {
+        Self::expire_due(&self.expire_wheel, &self.current_buffer, &self.data_buffer, &self.reviewer);
+        self.flush();
+    }
+
+    /// 获取主视图当前缓存中所有分片的布局信息，按照数据段顺序排列，可用于快照测试。
+    ///
+    /// returns: Vec<LayoutRecord>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "hello\n".to_string()));
+    /// rich_text.flush();
+    /// assert!(rich_text.layout_snapshot().iter().all(|record| record.id == 1));
+    /// ```
+    pub fn layout_snapshot(&self) -> Vec<LayoutRecord> {
+        let mut records = vec![];
+        for rd in self.current_buffer.read().iter() {
+            for piece in rd.line_pieces.iter() {
+                let piece = &*piece.read();
+                records.push(LayoutRecord {
+                    id: rd.id,
+                    line: piece.line.clone(),
+                    x: piece.x,
+                    y: piece.y,
+                    w: piece.w,
+                    h: piece.h,
+                });
+            }
+        }
+        records
+    }
+
+    /// 使到期的数据段过期。
+    ///
+    /// returns: bool 是否有数据段过期。
+    fn expire_due(
+        expire_wheel: &Arc<RwLock<ExpireWheel>>,
+        current_buffer: &Arc<RwLock<Vec<RichData>>>,
+        data_buffer: &Arc<RwLock<Option<Vec<RichData>>>>,
+        reviewer: &Arc<RwLock<Option<RichReviewer>>>) -> bool {
+        let due = expire_wheel.write().take_due();
+        if due.is_empty() {
+            return false;
+        }
+        let ids: HashSet<i64> = due.into_iter().collect();
+        expire_data_by_ids(&mut current_buffer.write(), &ids);
+        if let Some(main_buffer) = data_buffer.write().as_mut() {
+            expire_data_by_ids(main_buffer, &ids);
+        }
+        if let Some(reviewer) = reviewer.write().as_mut() {
+            reviewer.expire_review_data_by_ids(&ids);
+        }
+        true
+    }
+
+    /// 创建新的会话标签，新会话的内容为空，创建后不会自动切换到该会话。
+    /// 组件初始时已有一个名为`default`的活动会话。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 会话名称。
+    ///
+    /// returns: SessionId
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let chat = rich_text.create_session("chat");
+    /// assert_eq!(rich_text.sessions().len(), 2);
+    /// assert_ne!(rich_text.active_session(), chat);
+    /// ```
+    pub fn create_session(&mut self, name: &str) -> SessionId {
+        let snapshot = self.empty_snapshot();
+        let mut sessions = self.sessions.write();
+        let id = SessionId(sessions.next_id);
+        sessions.next_id += 1;
+        sessions.slots.insert(id, SessionSlot { name: name.to_string(), snapshot: Some(snapshot), pending: VecDeque::new() });
+        id
+    }
+
+    /// 切换到指定的会话标签。当前会话的数据缓存直接转移到会话标签中保存，不复制数据；
+    /// 主视图尺寸未变化时目标会话也无需重新排版。目标会话在非活动期间收到的数据随即添加到视图中。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 目标会话。
+    ///
+    /// returns: bool 目标会话不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let default_session = rich_text.active_session();
+    /// let chat = rich_text.create_session("chat");
+    /// rich_text.append_to_session(chat, UserData::new_text("新消息".to_string()));
+    /// assert!(rich_text.switch_session(chat));
+    /// assert_eq!(rich_text.active_session(), chat);
+    /// assert!(rich_text.switch_session(default_session));
+    /// ```
+    pub fn switch_session(&mut self, id: SessionId) -> bool {
+        let current = self.sessions.read().active;
+        if current == id {
+            return true;
+        }
+        let (target, pending) = match self.sessions.write().slots.get_mut(&id) {
+            Some(slot) => (slot.snapshot.take(), std::mem::take(&mut slot.pending)),
+            None => return false,
+        };
+        self.auto_close_reviewer();
+        let saved = self.take_session();
+        {
+            let mut sessions = self.sessions.write();
+            if let Some(slot) = sessions.slots.get_mut(&current) {
+                slot.snapshot.replace(saved);
+            }
+            sessions.active = id;
+        }
+        if let Some(target) = target {
+            self.put_session(target, false);
+        }
+        for ud in pending {
+            self._append(ud);
+        }
+        self.update_panel_fn.write().update_param(false);
+        true
+    }
+
+    /// 关闭指定的会话标签。关闭活动会话时自动切换到剩余会话中的第一个，不能关闭最后一个会话。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 目标会话。
+    ///
+    /// returns: bool 成功关闭时返回`true`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let default_session = rich_text.active_session();
+    /// let chat = rich_text.create_session("chat");
+    /// rich_text.switch_session(chat);
+    /// assert!(rich_text.close_session(chat));
+    /// assert_eq!(rich_text.active_session(), default_session);
+    /// // 不能关闭最后一个会话。
+    /// assert!(!rich_text.close_session(default_session));
+    /// ```
+    pub fn close_session(&mut self, id: SessionId) -> bool {
+        let (active, next) = {
+            let sessions = self.sessions.read();
+            if !sessions.slots.contains_key(&id) || sessions.slots.len() <= 1 {
+                return false;
+            }
+            (sessions.active, sessions.slots.keys().copied().find(|k| *k != id))
+        };
+        if active == id {
+            if let Some(next) = next {
+                self.switch_session(next);
+            }
+        }
+        self.sessions.write().slots.remove(&id).is_some()
+    }
+
+    /// 向指定会话添加数据。目标为活动会话时直接添加到视图中，否则暂存起来，待切换到该会话时再添加。
+    /// 暂存的数据数量不超过缓存行数上限，超出时丢弃最早的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 目标会话。
+    /// * `user_data`: 数据段。
+    ///
+    /// returns: bool 目标会话不存在时返回`false`。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let chat = rich_text.create_session("chat");
+    /// assert!(rich_text.append_to_session(chat, UserData::new_text("后台会话中的消息".to_string())));
+    /// ```
+    pub fn append_to_session(&mut self, id: SessionId, user_data: UserData) -> bool {
+        if self.sessions.read().active == id {
+            self.append(user_data);
+            return true;
+        }
+        let max_lines = self.buffer_max_lines.load(Ordering::Relaxed);
+        match self.sessions.write().slots.get_mut(&id) {
+            Some(slot) => {
+                slot.pending.push_back(user_data);
+                while slot.pending.len() > max_lines {
+                    slot.pending.pop_front();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 获取当前活动的会话标签。
+    pub fn active_session(&self) -> SessionId {
+        self.sessions.read().active
+    }
+
+    /// 获取全部会话标签的标识和名称，按创建顺序排列。
+    pub fn sessions(&self) -> Vec<(SessionId, String)> {
+        self.sessions.read().slots.iter().map(|(id, slot)| (*id, slot.name.clone())).collect()
+    }
+
+    /// 生成空白会话的快照。
+    fn empty_snapshot(&self) -> SessionSnapshot {
+        let cursor_piece = LinePiece::init_piece(self.text_size.load(Ordering::Relaxed), &self.padding.read()).read().clone();
+        SessionSnapshot {
+            current_buffer: Arc::new(Vec::new()),
+            data_buffer: Arc::new(Some(Vec::new())),
+            rewrite_board: Arc::new(None),
+            cursor_piece,
+            show_cursor: false,
+            remote_flow_control: true,
+            line_counter: 1,
+            current_col: 0,
+            top_insert_pos: 0,
+            alternate_saved: None,
+            scroll_region: (0, 0),
+            layout_size: (self.panel.width(), self.panel.height()),
+        }
+    }
+
+    /// 生成提供给插件的主视图只读状态。
+    fn panel_view(panel: &Frame, buffer_rc: Arc<RwLock<Vec<RichData>>>, append_top: bool, rows: usize, cols: usize, padding: &Padding) -> PanelView {
+        let data_len = buffer_rc.read().len();
+        PanelView {
+            x: panel.x(),
+            y: panel.y(),
+            w: panel.width(),
+            h: panel.height(),
+            scroll_y: Self::calc_scroll_height(buffer_rc, panel.height(), append_top, padding),
+            data_len,
+            rows,
+            cols,
+        }
+    }
+
+    /// 通过回调函数通知终端模式变化。
+    fn notify_modes(&mut self) {
+        let modes = *self.terminal_modes.read();
+        if let Some(cb) = self.notifier.write().as_mut() {
+            cb.notify(CallbackData::ModeChanged(modes));
+        }
+    }
+
+    /// 检查新数据段是否命中监视关键字，并通过回调函数逐个通知。
+    fn notify_watches(&mut self, user_data: &UserData) {
+        if user_data.data_type != DataType::Text || self.rewrite_board.read().is_some() {
+            return;
+        }
+        let hits = {
+            let watches = self.watches.read();
+            if watches.patterns.is_empty() {
+                return;
+            }
+            watches.hits(user_data.id, &user_data.text)
+        };
+        if let Some(cb) = self.notifier.write().as_mut() {
+            for hit in hits {
+                cb.notify(CallbackData::Watch(hit));
+            }
+        }
+    }
+
+    /// 将新数据段中已经完整的文字行交给朗读钩子函数。
+    fn notify_speech(&mut self, user_data: &UserData) {
+        if self.rewrite_board.read().is_some() {
+            return;
+        }
+        // 钩子函数中可能再次访问组件，调用前先释放锁。
+        let Some(hook) = self.speech_hook.read().clone() else {
+            return;
+        };
+        let lines = self.speech_lines.write().push(user_data);
+        for line in lines {
+            hook.speak(&line);
+        }
+    }
+
+    /// 通过回调函数汇报被抑制的重复数据段数量。
+    fn notify_suppressed(&mut self, count: usize) {
+        if let Some(cb) = self.notifier.write().as_mut() {
+            cb.notify(CallbackData::Suppressed(count));
+        }
+    }
+
+    /// 通过回调函数汇报待合并队列已满时丢弃的数据段数量。
+    fn notify_dropped(&mut self, count: usize) {
+        if let Some(cb) = self.notifier.write().as_mut() {
+            cb.notify(CallbackData::Dropped(count));
+        }
+    }
+
+    /// 向缓冲区添加数据，并计算数据片段的绘制坐标。
+    ///
+    /// # Arguments
+    ///
+    /// * `user_data`:
+    ///
+    /// returns: bool 数据被重复检测抑制时返回false。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    fn _append(&mut self, mut user_data: UserData) -> bool {
+        self.idle_state.write().touch();
+        if let Some(def) = user_data.style_name.as_ref().and_then(|name| self.styles.read().get(name).cloned()) {
+            user_data = def.apply(user_data, &self.style_base());
+        }
+        // 结构标记、分隔线和空白文本不参与重复检测，面板流中的数据允许反复重绘。
+        let dedup = match user_data.data_type {
+            DataType::Text => !user_data.text.trim().is_empty(),
+            DataType::Image | DataType::Table => true,
+            DataType::Marker | DataType::Divider | DataType::Gauge => false,
+        };
+        if dedup && self.rewrite_board.read().is_none() && self.dedup_window.write().is_duplicate(user_data.content_hash()) {
+            return false;
+        }
+
+        let plugins = snapshot_plugins(&self.plugins);
+        if !plugins.is_empty() {
+            let view = Self::panel_view(&self.panel, self.current_buffer.clone(), self.append_top.load(Ordering::Relaxed), self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), &self.padding.read());
+            for plugin in plugins {
+                plugin.write().on_append(&user_data, &view);
+            }
+        }
+        let logged = match self.logger.write().as_mut() {
+            Some(logger) => match logger.write(&user_data) {
+                Ok(_) => true,
+                Err(e) => {
+                    error!("写入会话日志失败：{:?} {:?}", logger.path(), e);
+                    false
+                }
+            },
+            None => false,
+        };
+        if logged {
+            self.timers.write().wake(LOG_FLUSH_TIMER);
+        }
+        self.notify_watches(&user_data);
+        self.notify_speech(&user_data);
+        if let Some(category) = user_data.action.as_ref().and_then(|action| action.category.as_ref()) {
+            let mut stats = self.category_stats.write();
+            let entry = stats.entry(category.clone()).or_default();
+            entry.segments += 1;
+            entry.bytes += user_data.text.len() as u64;
+        }
+
+        if user_data.data_type == DataType::Text && !self.highlights.read().rules.is_empty() {
+            let (pieces, hits) = apply_highlights(user_data, &self.highlights.read().rules);
+            if !hits.is_empty() && self.rewrite_board.read().is_none() {
+                if let Some(cb) = self.notifier.write().as_mut() {
+                    for hit in hits {
+                        cb.notify(CallbackData::Trigger(hit));
+                    }
+                }
+            }
+            for piece in pieces {
+                self.append_segment(piece);
+            }
+        } else {
+            self.append_segment(user_data);
+        }
+        true
+    }
+
+    /// 设置单个数据段的过期时间和显示参数，计算绘制坐标后添加到缓冲区。
+    ///
+    /// # Arguments
+    ///
+    /// * `user_data`: 数据段。
+    ///
+    /// returns: ()
+    fn append_segment(&mut self, user_data: UserData) {
+        let ttl = user_data.ttl.or_else(|| {
+            if user_data.clickable || user_data.action.is_some() {
+                *self.auto_expire.read()
+            } else {
+                None
+            }
+        });
+        if let Some(ttl) = ttl {
+            if user_data.data_type != DataType::Marker {
+                self.expire_wheel.write().schedule(user_data.id, ttl);
+                self.timers.write().wake(EXPIRE_TIMER);
+            }
+        }
+
+        let mut rich_data = self.styled_rich_data(user_data);
+        let padding = *self.padding.read();
+
+        let in_board = self.rewrite_board.read().is_some();
+        if let Some(duration) = *self.fade_in.read() {
+            // 定位面板中的内容会被反复重绘，不使用淡入效果。
+            if !in_board && rich_data.data_type != DataType::Marker && !self.render_options.read().reduced_motion() {
+                let now = deterministic::now();
+                rich_data.fade_in.replace((now, duration));
+                self.fade_until.write().replace(now + duration);
+                self.timers.write().wake(FADE_TIMER);
+            }
+        }
+        let right_margin = self.max_cols.load(Ordering::Relaxed).saturating_sub(1);
+        let (text, end_col) = expand_tabs(&rich_data.text, self.current_col(), self.tab_width.load(Ordering::Relaxed) as usize, self.tab_stops.read().as_ref(), right_margin);
+        rich_data.text = text;
+        if rich_data.data_type == DataType::Text && !in_board {
+            self.current_col.store(end_col, Ordering::Relaxed);
+        }
+
+        if !in_board {
+            let mut thread_index = self.thread_index.write();
+            if let Some(parent_id) = rich_data.parent_id {
+                // 回复数据比父数据多缩进一级，父数据被折叠或隐藏时一并隐藏。
+                if let Some(&(depth, hidden)) = thread_index.get(&parent_id) {
+                    rich_data.thread_depth = depth + 1;
+                    rich_data.hidden = hidden || self.collapsed_threads.read().contains(&parent_id);
+                }
+            }
+            thread_index.insert(rich_data.id, (rich_data.thread_depth, rich_data.hidden));
+            if thread_index.len() > self.buffer_max_lines.load(Ordering::Relaxed) * 2 {
+                // 移出缓存的数据段不再作为父数据段。
+                let live: HashSet<i64> = self.current_buffer.read().iter().map(|rd| rd.id).collect();
+                thread_index.retain(|id, _| live.contains(id) || *id == rich_data.id);
+            }
+        }
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+
+        /*
+        对文档结束符进行特殊处理：当作光标移动到行首的操作，不作为可见数据添加。
+         */
+        match rich_data.data_type {
+            DataType::Text | DataType::Gauge => {
+                // debug!("接收到 rich_data.text: {:?}", rich_data.text);
+                if self.rewrite_board.read().is_some() {
+                    // 从当前缓存中清理上一批面板流数据
+                    // self.clear_board_data();
+                    {
+                        self.current_buffer.write().clear();
+                    }
+
+                    if let Some(board) = self.rewrite_board.write().as_mut() {
+                        // debug!("在面板流中添加数据：{:?}", rich_data.text);
+                        let mut board_data = board.add_data(rich_data, self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read(), &self.keywords.read());
+                        // debug!("面板流有 {} 条数据", board_data.len());
+                        self.current_buffer.write().append(&mut board_data);
+                    }
+                } else {
+                    // debug!("在常规流中添加数据：{:?}", rich_data.text);
+                    rich_data.text = rich_data.text.replace("\r", "");
+                    // 关键字高亮范围以字符位置表示，须在去除回车符之后计算。
+                    rich_data.apply_keywords(&self.keywords.read());
+                    self.assign_line_no(&mut rich_data);
+                    self.request_link_preview(&rich_data);
+                    if self.append_top.load(Ordering::Relaxed) {
+                        self.insert_at_top(rich_data, drawable_max_width);
+                    } else {
+                        let last_piece = rich_data.estimate(self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
+                        *self.cursor_piece.write() = last_piece.read().get_cursor();
+                        self.current_buffer.write().push(rich_data);
+
+                        // 暂停输出时尽量保留旧数据，恢复时再统一清理；暂停期间缓存最多增长到上限的两倍，超出后仍移除最早的数据。
+                        let max_lines = self.buffer_max_lines.load(Ordering::Relaxed);
+                        let limit = if self.paused.read().is_some() { max_lines.saturating_mul(PAUSED_BUFFER_FACTOR) } else { max_lines };
+                        if self.current_buffer.read().len() > limit {
+                            self.current_buffer.write().remove(0);
+                            if let Some(state) = self.paused.write().as_mut() {
+                                state.len = state.len.saturating_sub(1);
+                            }
+                        }
+                    }
+                }
+
+            }
+            DataType::Image | DataType::Divider | DataType::Table => {
+                self.assign_line_no(&mut rich_data);
+                if self.append_top.load(Ordering::Relaxed) {
+                    self.insert_at_top(rich_data, drawable_max_width);
+                } else {
+                    let last_piece = rich_data.estimate(self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
+                    *self.cursor_piece.write() = last_piece.read().get_cursor();
+                    // self.throttle_holder.write().current_rid = rich_data.id;
+                    // self.add_data(rich_data);
+                    self.current_buffer.write().push(rich_data);
+                }
+            }
+            DataType::Marker => {
+                // 结构标记不占据高度，只记录其在数据流中的位置。
+                if self.append_top.load(Ordering::Relaxed) {
+                    self.insert_at_top(rich_data, drawable_max_width);
+                } else {
+                    rich_data.estimate(self.cursor_piece.clone(), drawable_max_width, *self.basic_char.read());
+                    self.current_buffer.write().push(rich_data);
+                }
+            }
+        }
+    }
+
+    /// 按照组件的默认字体、颜色、调色板和排版参数，将数据段转换为待排版的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `user_data`: 数据段。
+    ///
+    /// returns: RichData
+    fn styled_rich_data(&self, user_data: UserData) -> RichData {
+        let default_font_text = !user_data.custom_font_text;
+        let default_font_color = !user_data.custom_font_color;
+        let mut rich_data: RichData = user_data.into();
+        rich_data.piece_spacing = self.piece_spacing.load(Ordering::Relaxed);
+        rich_data.wrap_policy = *self.wrap_policy.read();
+        rich_data.hanging_indent = self.hanging_indent.load(Ordering::Relaxed);
+        rich_data.line_height_factor = *self.line_height_factor.read();
+        rich_data.paragraph_spacing = self.paragraph_spacing.load(Ordering::Relaxed);
+        rich_data.disabled_style = *self.disabled_style.read();
+        rich_data.padding_left = self.padding.read().left;
+        rich_data.wrap_cache = Some(self.wrap_cache.clone());
+        if default_font_text {
+            rich_data.font = if rich_data.italic { italic_font(*self.text_font.read()) } else { *self.text_font.read() };
+            rich_data.font_size = self.text_size.load(Ordering::Relaxed);
+        }
+        if default_font_color {
+            rich_data.fg_color = *self.text_color.read();
+        }
+        if let Some(palette) = self.ansi_palette.read().as_ref() {
+            rich_data.apply_palette(palette);
+        }
+        if rich_data.bg_color.is_none() {
+            rich_data.bg_color.replace(*self.background_color.read());
+        }
+        rich_data
+    }
+
+    /// 顶部追加模式下，将新数据插入到最新一行的末尾。只重新计算最新一行数据的绘制位置，
+    /// 较早的数据按照最新一行高度的变化整体平移。
+    ///
+    /// # Arguments
+    ///
+    /// * `rich_data`: 新数据。
+    /// * `drawable_max_width`: 可绘制的最大宽度。
+    ///
+    /// returns: ()
+    fn insert_at_top(&mut self, rich_data: RichData, drawable_max_width: i32) {
+        let ends_line = rich_data.text.ends_with('\n');
+        let padding = *self.padding.read();
+        let text_size = self.text_size.load(Ordering::Relaxed);
+        let mut buffer = self.current_buffer.write();
+        let pos = self.top_insert_pos.load(Ordering::Relaxed).min(buffer.len());
+        // 较早数据的起始行顶部位置。
+        let old_top = buffer.get(pos).map(|rd| rd.line_pieces.first().map_or(rd.v_bounds.read().0, |piece| piece.read().top_y));
+        buffer.insert(pos, rich_data);
+
+        let mut last_piece = LinePiece::init_piece(text_size, &padding);
+        for rd in buffer[..=pos].iter_mut() {
+            rd.line_pieces.clear();
+            last_piece = rd.estimate(last_piece, drawable_max_width, *self.basic_char.read());
+        }
+        if let Some(old_top) = old_top {
+            // 最新一行未以换行符结束时，较早的数据从下一行开始。
+            let next_top = if last_piece.read().line.ends_with('\n') {
+                last_piece.read().get_cursor().top_y
+            } else {
+                last_piece.read().line_break_cursor(padding.left).top_y
+            };
+            let dy = next_top - old_top;
+            if dy != 0 {
+                buffer[pos + 1..].iter_mut().for_each(|rd| rd.shift_y(dy));
+            }
+        }
+        if buffer.len() > self.buffer_max_lines.load(Ordering::Relaxed) {
+            // 移除位于底部的最早数据
+            buffer.pop();
+        }
+        let len = buffer.len();
+        drop(buffer);
+
+        let (pos, cursor) = if ends_line {
+            (0, LinePiece::init_piece(text_size, &padding))
+        } else {
+            (min(pos + 1, len), last_piece)
+        };
+        self.top_insert_pos.store(pos, Ordering::Relaxed);
+        *self.cursor_piece.write() = cursor.read().get_cursor();
+    }
+
+    /// 重新计算缓存数据的绘制位置，返回最新数据末尾的分片。
+    /// 顶部追加模式下，`top_insert_pos`之前的数据属于最新的一行，当其未以换行符结束时需要强制换行，避免与较早的数据连成一行。
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: 数据缓存。
+    /// * `top_insert_pos`: 顶部追加模式下最新一行数据的结尾位置，底部追加模式下为`None`。
+    /// * `text_size`: 默认字体大小。
+    /// * `drawable_max_width`: 可绘制的最大宽度。
+    /// * `basic_char`: 用于计算字符宽度的标准字符。
+    /// * `padding`: 面板边界空白。
+    ///
+    /// returns: Arc<RwLock<LinePiece>>
+    fn relayout_buffer(buffer: &mut [RichData], top_insert_pos: Option<usize>, text_size: i32, drawable_max_width: i32, basic_char: char, padding: &Padding) -> Arc<RwLock<LinePiece>> {
+        let mut last_piece = LinePiece::init_piece(text_size, padding);
+        let mut cursor = last_piece.clone();
+        for (idx, rd) in buffer.iter_mut().enumerate() {
+            if idx > 0 && Some(idx) == top_insert_pos && !last_piece.read().line.ends_with('\n') {
+                let line_break = last_piece.read().line_break_cursor(padding.left);
+                last_piece = Arc::new(RwLock::new(line_break));
+            }
+            rd.line_pieces.clear();
+            last_piece = rd.estimate(last_piece, drawable_max_width, basic_char);
+            if Some(idx + 1) == top_insert_pos {
+                cursor = last_piece.clone();
+            }
+        }
+        if top_insert_pos.is_none() {
+            cursor = last_piece;
+        }
+        cursor
+    }
+
+    /// 设置新数据段的追加方向。顶部追加模式下，新数据显示在已有数据的上方，视图固定显示顶部内容，
+    /// 打开回顾区时回顾内容的顺序也随之倒置。切换方向时会按行倒置已有数据的顺序。
+    ///
+    /// # Arguments
+    ///
+    /// * `direction`: 追加方向。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::Direction;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_append_direction(Direction::Top);
+    /// ```
+    pub fn set_append_direction(&mut self, direction: Direction) {
+        let top = direction == Direction::Top;
+        if self.append_top.swap(top, Ordering::Relaxed) == top {
+            return;
+        }
+
+        // 按行拆分已有数据，顶部追加模式下最新一行的结尾由top_insert_pos确定。
+        let mut lines: Vec<Vec<RichData>> = vec![];
+        {
+            let mut buffer = self.current_buffer.write();
+            let mut rest: Vec<RichData> = buffer.drain(..).collect();
+            if !top {
+                let pos = self.top_insert_pos.load(Ordering::Relaxed).min(rest.len());
+                if pos > 0 {
+                    let tail = rest.split_off(pos);
+                    lines.push(rest);
+                    rest = tail;
+                }
+            }
+            let mut line = vec![];
+            for rd in rest {
+                let ends_line = rd.text.ends_with('\n');
+                line.push(rd);
+                if ends_line {
+                    lines.push(line);
+                    line = vec![];
+                }
+            }
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+
+        // 倒置行顺序，顶部追加模式下未结束的最新一行位于顶部。
+        lines.reverse();
+        let pos = if top {
+            match lines.first() {
+                Some(first) if first.last().is_some_and(|rd| !rd.text.ends_with('\n')) => first.len(),
+                _ => 0,
+            }
+        } else {
+            0
+        };
+        self.top_insert_pos.store(pos, Ordering::Relaxed);
+        self.current_buffer.write().extend(lines.into_iter().flatten());
+
+        if self.rewrite_board.read().is_none() {
+            let padding = *self.padding.read();
+            let drawable_max_width = padding.drawable_width(self.panel.width());
+            let top_insert_pos = if top { Some(pos) } else { None };
+            let last_piece = Self::relayout_buffer(&mut self.current_buffer.write(), top_insert_pos, self.text_size.load(Ordering::Relaxed), drawable_max_width, *self.basic_char.read(), &padding);
+            *self.cursor_piece.write() = last_piece.read().get_cursor();
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 为常规流中新增的数据段记录行号信息。
+    fn assign_line_no(&self, rich_data: &mut RichData) {
+        rich_data.gutter_width = self.gutter_width.load(Ordering::Relaxed);
+        rich_data.line_no = self.line_counter.fetch_add(rich_data.text.matches('\n').count(), Ordering::Relaxed);
+    }
+
+    /// 设置是否在左侧显示逻辑行号。行号不参与点击与选择。
+    ///
+    /// # Arguments
+    ///
+    /// * `show`: true显示行号，false隐藏行号。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_show_line_numbers(true);
+    /// ```
+    pub fn set_show_line_numbers(&mut self, show: bool) {
+        let gutter_width = if show {
+            draw::set_font(*self.text_font.read(), self.text_size.load(Ordering::Relaxed));
+            let (tw, _) = measure(&"0".repeat(LINE_NUMBER_DIGITS), false);
+            tw + IMAGE_PADDING_H * 4
+        } else {
+            0
+        };
+        if self.gutter_width.swap(gutter_width, Ordering::Relaxed) == gutter_width {
+            return;
+        }
+
+        // 面板流数据不显示行号。
+        self.relayout_main_buffer(|rd| rd.gutter_width = gutter_width);
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_show_line_numbers(show);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 调整主缓存中的数据属性后重新计算主缓存数据的绘制位置。
+    ///
+    /// # Arguments
+    ///
+    /// * `f`: 在重新计算前对每个数据段执行的调整函数。
+    ///
+    /// returns: ()
+    fn relayout_main_buffer<F>(&mut self, mut f: F) where F: FnMut(&mut RichData) {
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        let basic_char = *self.basic_char.read();
+        let mut last_piece = LinePiece::init_piece(self.text_size.load(Ordering::Relaxed), &padding);
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            // 当前缓存就是主缓存
+            let top_insert_pos = if self.append_top.load(Ordering::Relaxed) {
+                Some(self.top_insert_pos.load(Ordering::Relaxed))
+            } else {
+                None
+            };
+            let mut buffer = self.current_buffer.write();
+            buffer.iter_mut().for_each(&mut f);
+            last_piece = Self::relayout_buffer(&mut buffer, top_insert_pos, self.text_size.load(Ordering::Relaxed), drawable_max_width, basic_char, &padding);
+            *self.cursor_piece.write() = last_piece.read().get_cursor();
+        } else if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            // 主缓存位于data_buffer中，当前缓存是面板流数据。
+            for rd in main_buffer.iter_mut() {
+                f(rd);
+                rd.line_pieces.clear();
+                last_piece = rd.estimate(last_piece, drawable_max_width, basic_char);
+            }
+        }
+    }
+
+    /// 调整主缓存中的数据属性后，从第一个被调整的数据段所在行开始重新计算绘制位置，之前的数据段保持不变。
+    ///
+    /// # Arguments
+    ///
+    /// * `f`: 对每个数据段执行的调整函数，返回该数据段是否被调整。
+    ///
+    /// returns: bool 是否有数据段被调整。
+    fn relayout_main_buffer_from<F>(&mut self, mut f: F) -> bool where F: FnMut(&mut RichData) -> bool {
+        let padding = *self.padding.read();
+        let drawable_max_width = padding.drawable_width(self.panel.width());
+        let basic_char = *self.basic_char.read();
+        let text_size = self.text_size.load(Ordering::Relaxed);
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            let mut buffer = self.current_buffer.write();
+            if self.append_top.load(Ordering::Relaxed) {
+                // 顶部追加模式下数据段顺序与绘制顺序不一致，整体重新计算。
+                let mut changed = false;
+                buffer.iter_mut().for_each(|rd| changed |= f(rd));
+                if changed {
+                    let top_insert_pos = Some(self.top_insert_pos.load(Ordering::Relaxed));
+                    let cursor = Self::relayout_buffer(&mut buffer, top_insert_pos, text_size, drawable_max_width, basic_char, &padding);
+                    *self.cursor_piece.write() = cursor.read().get_cursor();
+                }
+                changed
+            } else if let Some(last_piece) = Self::relayout_buffer_from(&mut buffer, f, text_size, drawable_max_width, basic_char, &padding) {
+                *self.cursor_piece.write() = last_piece.read().get_cursor();
+                true
+            } else {
+                false
+            }
+        } else if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            Self::relayout_buffer_from(main_buffer, f, text_size, drawable_max_width, basic_char, &padding).is_some()
+        } else {
+            false
+        }
+    }
+
+    /// 调整数据缓存中的数据属性，从第一个被调整的数据段向前回溯到行首，重新计算此后所有数据段的绘制位置。
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: 数据缓存。
+    /// * `f`: 对每个数据段执行的调整函数，返回该数据段是否被调整。
+    /// * `text_size`: 默认字体大小。
+    /// * `drawable_max_width`: 可绘制的最大宽度。
+    /// * `basic_char`: 用于计算字符宽度的标准字符。
+    /// * `padding`: 面板边界空白。
+    ///
+    /// returns: Option<Arc<RwLock<LinePiece>>> 最后一个分片，没有数据段被调整时返回空。
+    fn relayout_buffer_from<F>(buffer: &mut [RichData], mut f: F, text_size: i32, drawable_max_width: i32, basic_char: char, padding: &Padding) -> Option<Arc<RwLock<LinePiece>>> where F: FnMut(&mut RichData) -> bool {
+        let mut first = None;
+        for (idx, rd) in buffer.iter_mut().enumerate() {
+            if f(rd) && first.is_none() {
+                first = Some(idx);
+            }
+        }
+        let first = first?;
+        // 同一行的分片共享行高，只能从某一行的开头重新计算。
+        let (start, mut last_piece) = buffer[..first].iter().enumerate().rev()
+            .find_map(|(idx, rd)| rd.line_start_tail().map(|piece| (idx + 1, piece)))
+            .unwrap_or_else(|| (0, LinePiece::init_piece(text_size, padding)));
+        for rd in buffer[start..].iter_mut() {
+            rd.line_pieces.clear();
+            last_piece = rd.estimate(last_piece, drawable_max_width, basic_char);
+        }
+        Some(last_piece)
+    }
+
+    /// 为指定数据段设置译文，译文以较小的暗色斜体字显示在原文下方。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据段ID。
+    /// * `text`: 译文，为空字符串时清除译文。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "hello\n".to_string()));
+    /// rich_text.set_translation(1, "你好".to_string());
+    /// ```
+    pub fn set_translation(&mut self, id: i64, text: String) {
+        let translation = if text.is_empty() { None } else { Some(text) };
+        let show = self.show_translations.load(Ordering::Relaxed);
+        // 高亮规则拆分出的片段记录了原ID，译文只显示在最后一个片段下方。
+        let is_target = |rd: &RichData| rd.has_origin(id) && rd.data_type == DataType::Text;
+        let fragments = if self.remote_flow_control.load(Ordering::SeqCst) {
+            self.current_buffer.read().iter().filter(|rd| is_target(rd)).count()
+        } else {
+            self.data_buffer.read().as_ref().map_or(0, |buffer| buffer.iter().filter(|rd| is_target(rd)).count())
+        };
+        let mut seen = 0;
+        let found = self.relayout_main_buffer_from(|rd| {
+            if is_target(rd) {
+                seen += 1;
+                if seen == fragments {
+                    rd.translation = translation.clone();
+                    rd.show_translation = show;
+                    return true;
+                }
+            }
+            false
+        });
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_translation(id, translation, show);
+        }
+
+        if found {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+    /// 设置是否显示所有数据段的译文。
+    ///
+    /// # Arguments
+    ///
+    /// * `show`: true显示译文，false隐藏译文。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "Hello\n".to_string()));
+    /// rich_text.set_translation(1, "你好".to_string());
+    /// rich_text.show_translations(true);
+    /// ```
+    pub fn show_translations(&mut self, show: bool) {
+        if self.show_translations.swap(show, Ordering::Relaxed) == show {
+            return;
+        }
+        self.relayout_main_buffer(|rd| rd.show_translation = show);
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.show_translations(show);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置空闲节能策略。默认在没有新数据到达且窗口失去焦点30秒后暂停闪烁定时器，并将主视图刷新的防抖间隔延长到1秒，
+    /// 收到新数据或用户输入后立即退出空闲状态。
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 空闲节能策略，`IdlePolicy::disabled()`表示从不进入空闲状态。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::IdlePolicy;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_idle_policy(IdlePolicy { idle_after: Some(Duration::from_secs(5)), idle_interval: 2.0, require_unfocused: false });
+    /// ```
+    pub fn set_idle_policy(&mut self, policy: IdlePolicy) {
+        let mut state = self.idle_state.write();
+        state.policy = policy;
+        state.touch();
+    }
+
+    /// 设置自动换行的断行策略，已有数据按新策略重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `policy`: 断行策略，默认在任意字符处断行。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::WrapPolicy;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_wrap_policy(WrapPolicy::WordBoundaryCJK);
+    /// ```
+    pub fn set_wrap_policy(&mut self, policy: WrapPolicy) {
+        {
+            let mut current = self.wrap_policy.write();
+            if *current == policy {
+                return;
+            }
+            *current = policy;
+        }
+        self.relayout_wrap_settings();
+    }
+
+    /// 设置自动换行产生的后续行的悬挂缩进宽度，已有数据按新的缩进重新排版。
+    /// 排版时悬挂缩进最多占用行首之后剩余宽度的一半，窄窗口中不会因缩进过宽而无法换行。
+    ///
+    /// # Arguments
+    ///
+    /// * `indent`: 缩进宽度，单位为像素，为0时后续行从行首开始。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_hanging_indent(32);
+    /// ```
+    pub fn set_hanging_indent(&mut self, indent: i32) {
+        let indent = max(indent, 0);
+        if self.hanging_indent.swap(indent, Ordering::Relaxed) == indent {
+            return;
+        }
+        self.relayout_wrap_settings();
+    }
+
+    /// 设置常规流中文本的行高与字体尺寸的比例，已有数据按新行高重新排版。
+    /// 密集的日志可使用较小的比例显示更多行，阅读场景可适当增大比例。光标定位面板始终按照默认行高排列。
+    ///
+    /// # Arguments
+    ///
+    /// * `factor`: 行高比例，默认为`LINE_HEIGHT_FACTOR`，不小于1。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_line_height_factor(1.2);
+    /// ```
+    pub fn set_line_height_factor(&mut self, factor: f32) {
+        let factor = factor.max(1.0);
+        {
+            let mut current = self.line_height_factor.write();
+            if *current == factor {
+                return;
+            }
+            *current = factor;
+        }
+        self.relayout_main_buffer(|rd| rd.line_height_factor = factor);
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_line_height_factor(factor);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置常规流中逻辑行结束后额外增加的段落间距，已有数据按新间距重新排版。
+    ///
+    /// # Arguments
+    ///
+    /// * `spacing`: 段落间距，单位为像素，默认为0。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_paragraph_spacing(6);
+    /// ```
+    pub fn set_paragraph_spacing(&mut self, spacing: i32) {
+        let spacing = max(spacing, 0);
+        if self.paragraph_spacing.swap(spacing, Ordering::Relaxed) == spacing {
+            return;
+        }
+        self.relayout_main_buffer(|rd| rd.paragraph_spacing = spacing);
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_paragraph_spacing(spacing);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置主视图和回顾区内容与面板边界之间的空白，已有数据按新的可绘制宽度重新排版，并重新计算窗口行列数。
+    /// 负值按0处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `left`: 左侧空白。
+    /// * `top`: 顶部空白。
+    /// * `right`: 右侧空白。
+    /// * `bottom`: 底部空白。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_padding(10, 8, 10, 8);
+    /// ```
+    pub fn set_padding(&mut self, left: i32, top: i32, right: i32, bottom: i32) {
+        *self.user_padding.write() = Padding::new(left, top, right, bottom);
+        self.apply_padding();
+    }
+
+    /// 在用户设置的空白基础上，顶部留出固定行的高度，底部留出回显区和固定行的高度。
+    ///
+    /// # Arguments
+    ///
+    /// * `user_padding`: 用户设置的空白。
+    /// * `pinned_heights`: 顶部和底部固定行的高度。
+    /// * `echo_reserve`: 回显区的高度。
+    ///
+    /// returns: Padding
+    fn reserved_padding(user_padding: &Padding, pinned_heights: (i32, i32), echo_reserve: i32) -> Padding {
+        Padding::new(
+            user_padding.left,
+            user_padding.top + pinned_heights.0,
+            user_padding.right,
+            user_padding.bottom + pinned_heights.1 + echo_reserve,
+        )
+    }
+
+    /// 重新试算固定行和回显区的高度，将用户设置的空白加上这些高度后应用到主视图和回顾区。
+    fn apply_padding(&mut self) {
+        let user_padding = *self.user_padding.read();
+        let text_size = self.text_size.load(Ordering::Relaxed);
+        let pinned_heights = {
+            let mut pinned = self.pinned.write();
+            pinned.layout(text_size, user_padding.drawable_width(self.panel.width()), *self.basic_char.read(), &user_padding);
+            pinned.heights()
+        };
+        let line_height = (text_size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+        let echo_reserve = line_height * self.echo_area.read().len() as i32;
+        self.echo_reserve.store(echo_reserve, Ordering::Relaxed);
+        let padding = Self::reserved_padding(&user_padding, pinned_heights, echo_reserve);
+        let old_padding = {
+            let mut current = self.padding.write();
+            if *current == padding {
+                drop(current);
+                // 空白未变化时只需刷新固定行和回显区。
+                self.update_panel_fn.write().update_param(false);
+                return;
+            }
+            std::mem::replace(&mut *current, padding)
+        };
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.padding = padding;
+        }
+        if old_padding.left == padding.left && old_padding.right == padding.right {
+            // 固定行和回显区的高度变化只改变上下空白，内容整体平移即可，无需重新排版。
+            self.shift_main_buffer(padding.top - old_padding.top);
+        } else {
+            self.relayout_main_buffer(|rd| rd.padding_left = padding.left);
+        }
+        if self.current_buffer.read().is_empty() {
+            *self.cursor_piece.write() = LinePiece::init_piece(self.text_size.load(Ordering::Relaxed), &padding).read().clone();
+        }
+        Self::update_window_size(
+            self.text_font.clone(),
+            self.text_size.clone(),
+            self.basic_char.clone(),
+            self.panel.width(),
+            self.panel.height(),
+            self.max_rows.clone(),
+            self.max_cols.clone(),
+            self.rewrite_board.clone(),
+            &padding,
+        );
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_padding(user_padding);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 将主缓存中所有数据的绘制位置整体垂直移动。
+    ///
+    /// # Arguments
+    ///
+    /// * `dy`: 垂直移动的距离，负数表示上移。
+    ///
+    /// returns: ()
+    fn shift_main_buffer(&mut self, dy: i32) {
+        if dy == 0 {
+            return;
+        }
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            self.current_buffer.write().iter_mut().for_each(|rd| rd.shift_y(dy));
+            let cursor = &mut *self.cursor_piece.write();
+            cursor.y += dy;
+            cursor.top_y += dy;
+            cursor.next_y += dy;
+        } else if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            main_buffer.iter_mut().for_each(|rd| rd.shift_y(dy));
+        }
+    }
+
+    /// 获取内容与面板边界之间的空白。
+    pub fn padding(&self) -> Padding {
+        *self.user_padding.read()
+    }
+
+    /// 设置回顾区分栏显示的栏数，在很宽的窗口中可以按报纸的样式分两到三栏阅读历史记录，参见`RichReviewer::set_columns`。
+    /// 单栏显示或分屏回看模式下回顾区与主视图共享数据缓存，此时修改栏数需要重新打开回顾区才能生效。
+    ///
+    /// # Arguments
+    ///
+    /// * `columns`: 栏数，限制在1到`MAX_COLUMNS`之间。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 1600, 400, None);
+    /// rich_text.set_review_columns(2);
+    /// ```
+    pub fn set_review_columns(&mut self, columns: usize) {
+        let columns = columns.clamp(1, MAX_COLUMNS);
+        self.review_columns.store(columns, Ordering::Relaxed);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            if !Arc::ptr_eq(&reviewer.data_buffer, &self.current_buffer) {
+                reviewer.set_columns(columns);
+            }
+        }
+    }
+
+    /// 将当前的换行设置应用到已有数据并重新排版。
+    fn relayout_wrap_settings(&mut self) {
+        let policy = *self.wrap_policy.read();
+        let indent = self.hanging_indent.load(Ordering::Relaxed);
+        self.relayout_main_buffer(|rd| {
+            rd.wrap_policy = policy;
+            rd.hanging_indent = indent;
+        });
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_wrap_settings(policy, indent);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置链接预览提供者。设置后检测新添加的文本数据中的网址，每个网址首次出现时通过提供者请求预览信息；
+    /// 为空时停止检测，已显示的预览卡片不受影响。
+    ///
+    /// # Arguments
+    ///
+    /// * `provider`: 链接预览提供者。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::PreviewProvider;
+    ///
+    /// struct TitleFetcher;
+    /// impl PreviewProvider for TitleFetcher {
+    ///     fn request(&self, _id: i64, _url: &str) {
+    ///         // 在后台任务中获取网页标题和图标，完成后在主线程中调用`RichText::set_link_preview`。
+    ///     }
+    /// }
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_preview_provider(Some(Box::new(TitleFetcher)));
+    /// ```
+    pub fn set_preview_provider(&mut self, provider: Option<Box<dyn PreviewProvider>>) {
+        *self.preview_provider.write() = provider;
+    }
+
+    /// 为指定数据段设置链接预览信息，预览卡片显示在数据段下方。应在`fltk`主线程中调用。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据段ID，即`PreviewProvider::request`收到的ID。
+    /// * `preview`: 链接预览信息，为空时移除预览卡片。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{LinkPreview, UserData};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "see https://www.rust-lang.org\n".to_string()));
+    /// rich_text.set_link_preview(1, Some(LinkPreview::new("https://www.rust-lang.org".to_string(), "Rust".to_string())));
+    /// ```
+    pub fn set_link_preview(&mut self, id: i64, preview: Option<LinkPreview>) {
+        if preview.is_some() && !self.link_preview_enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let found = self.relayout_main_buffer_from(|rd| {
+            // 高亮规则拆分出的片段记录了原ID，预览卡片只显示在包含网址的片段下方。
+            if rd.has_origin(id) && rd.data_type == DataType::Text && preview.as_ref().map_or(true, |p| rd.text.contains(&p.url)) {
+                rd.link_preview = preview.clone();
+                true
+            } else {
+                false
+            }
+        });
+        if found {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+    /// 开启或关闭链接预览，默认开启。关闭后不再向链接预览提供者请求预览信息，并移除已显示的预览卡片。
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled`: 是否显示链接预览卡片。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_link_preview_enabled(false);
+    /// ```
+    pub fn set_link_preview_enabled(&mut self, enabled: bool) {
+        if self.link_preview_enabled.swap(enabled, Ordering::Relaxed) == enabled || enabled {
+            return;
+        }
+        if self.relayout_main_buffer_from(|rd| rd.link_preview.take().is_some()) {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+    /// 检测新数据中首次出现的网址，通过链接预览提供者请求预览信息。每个数据段最多显示一个预览卡片。
+    fn request_link_preview(&self, rich_data: &RichData) {
+        if rich_data.data_type != DataType::Text || !self.link_preview_enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(provider) = self.preview_provider.read().as_ref() {
+            let mut previewed = self.previewed_urls.write();
+            if let Some(url) = urls_in(&rich_data.text).into_iter().find(|url| !previewed.touch(url)) {
+                provider.request(rich_data.id, &url);
+            }
+        }
+    }
+
+    /// 获取主缓存中直接回复指定数据段的子数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 父数据段ID。
+    ///
+    /// returns: Vec<UserData>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let topic = UserData::new_text("topic\n".to_string());
+    /// let reply = UserData::new_text("reply\n".to_string()).set_parent(topic.id);
+    /// let topic_id = topic.id;
+    /// rich_text.append(topic);
+    /// rich_text.append(reply);
+    /// assert_eq!(rich_text.children_of(topic_id).len(), 1);
+    /// ```
+    pub fn children_of(&self, id: i64) -> Vec<UserData> {
+        let collect = |buffer: &[RichData]| -> Vec<UserData> {
+            buffer.iter().filter(|rd| rd.parent_id == Some(id)).map(UserData::from).collect()
+        };
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            collect(&self.current_buffer.read())
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            collect(main_buffer)
+        } else {
+            vec![]
+        }
+    }
+
+    /// 折叠指定数据段所在的线索，隐藏其所有后代数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 线索根数据段ID。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let topic = UserData::new_text("topic\n".to_string());
+    /// let topic_id = topic.id;
+    /// rich_text.append(topic);
+    /// rich_text.append(UserData::new_text("reply\n".to_string()).set_parent(topic_id));
+    /// rich_text.collapse_thread(topic_id);
+    /// ```
+    pub fn collapse_thread(&mut self, id: i64) {
+        if self.collapsed_threads.write().insert(id) {
+            self.refresh_threads();
+        }
+    }
+
+    /// 展开已折叠的线索，重新显示其后代数据段。后代中单独折叠的线索保持折叠状态。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 线索根数据段ID。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let topic = UserData::new_text("topic\n".to_string());
+    /// let topic_id = topic.id;
+    /// rich_text.append(topic);
+    /// rich_text.append(UserData::new_text("reply\n".to_string()).set_parent(topic_id));
+    /// rich_text.collapse_thread(topic_id);
+    /// rich_text.expand_thread(topic_id);
+    /// ```
+    pub fn expand_thread(&mut self, id: i64) {
+        if self.collapsed_threads.write().remove(&id) {
+            self.refresh_threads();
+        }
+    }
+
+    /// 按照线索的折叠状态更新主缓存和回顾区中数据段的可见性，从第一个可见性变化的数据段所在行开始重新计算绘制位置。
+    fn refresh_threads(&mut self) {
+        let collapsed = self.collapsed_threads.read().clone();
+        let hidden = if self.remote_flow_control.load(Ordering::SeqCst) {
+            thread_hidden_ids(self.current_buffer.read().iter(), &collapsed)
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            thread_hidden_ids(main_buffer, &collapsed)
+        } else {
+            return;
+        };
+        self.thread_index.write().iter_mut().for_each(|(id, (_, is_hidden))| *is_hidden = hidden.contains(id));
+        let changed = self.relayout_main_buffer_from(|rd| {
+            let is_hidden = hidden.contains(&rd.id);
+            let changed = rd.hidden != is_hidden;
+            rd.hidden = is_hidden;
+            changed
+        });
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.refresh_threads(&collapsed);
+        }
+
+        if changed {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+    /// 按照主缓存中的数据重建线索索引。
+    fn rebuild_thread_index(&self) {
+        let index = self.current_buffer.read().iter().map(|rd| (rd.id, (rd.thread_depth, rd.hidden))).collect();
+        *self.thread_index.write() = index;
+    }
+
+    /// 导出主缓存中的文本内容，图片和结构标记不参与导出。
+    ///
+    /// # Arguments
+    ///
+    /// * `include_translations`: 是否在原文所在行的下方附加译文。
+    ///
+    /// returns: String
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "Hello\n".to_string()));
+    /// rich_text.set_translation(1, "你好".to_string());
+    /// assert_eq!(rich_text.export_text(false), "Hello\n");
+    /// assert_eq!(rich_text.export_text(true), "Hello\n你好\n");
+    /// ```
+    pub fn export_text(&self, include_translations: bool) -> String {
+        let mut text = String::new();
+        let mut export = |buffer: &[RichData]| {
+            for rd in buffer.iter().filter(|rd| rd.data_type == DataType::Text) {
+                text.push_str(&rd.text);
+                if include_translations {
+                    if let Some(translation) = &rd.translation {
+                        if !text.is_empty() && !text.ends_with('\n') {
+                            text.push('\n');
+                        }
+                        text.push_str(translation);
+                        text.push('\n');
+                    }
+                }
+            }
+        };
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            export(&self.current_buffer.read());
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            export(main_buffer);
+        }
+        text
+    }
+
+    /// 导出主缓存中的全部数据段，结构标记不参与导出。导出的数据段保留应用程序附加的元数据，可使用`serde`序列化保存。
+    ///
+    /// returns: Vec<UserData, Global>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let json = serde_json::to_string(&rich_text.export_data()).unwrap();
+    /// assert_eq!(json, "[]");
+    /// ```
+    pub fn export_data(&self) -> Vec<UserData> {
+        let export = |buffer: &[RichData]| buffer.iter().filter(|rd| rd.data_type != DataType::Marker).map(UserData::from).collect();
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            export(&self.current_buffer.read())
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            export(main_buffer)
+        } else {
+            vec![]
+        }
+    }
+
+    /// 将主缓存中的全部数据段以JSON格式保存为会话文件，包括结构标记。
+    /// 有本地文件的图片只保存文件路径，其他图片内嵌保存图片数据。
+    /// 保存的会话文件可通过`load_session`重新加载，或通过`read_session`读取后在回顾区以历史模式浏览。
+    ///
+    /// # Arguments
+    ///
+    /// * `writer`: 输出目标。
+    ///
+    /// returns: Result<(), Error>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let mut session = Vec::new();
+    /// rich_text.save_session(&mut session).unwrap();
+    /// ```
+    pub fn save_session<W: Write>(&self, writer: W) -> std::io::Result<()> {
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            write_session(&self.current_buffer.read(), writer)
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            write_session(main_buffer, writer)
+        } else {
+            write_session(&[], writer)
+        }
+    }
+
+    /// 加载由`save_session`保存的会话文件，替换主视图中的全部数据。若已打开回顾区，则先关闭回顾区。
+    /// 会话文件无法解析或包含无法解析的颜色时返回错误，主视图保持不变。
+    /// 载入的数据直接恢复到缓存中，不经过添加数据的流程：不写入会话日志，不触发监视、朗读和插件钩子，
+    /// 不计入类别统计，也不再次进行高亮拆分和重复检测。超出缓存上限时只保留最新的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `reader`: 输入来源。
+    ///
+    /// returns: Result<(), Error>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text("保存的内容\n".to_string()));
+    /// let mut session = Vec::new();
+    /// rich_text.save_session(&mut session).unwrap();
+    ///
+    /// let mut restored = RichText::new(100, 120, 800, 400, None);
+    /// restored.load_session(session.as_slice()).unwrap();
+    /// ```
+    pub fn load_session<R: Read>(&mut self, reader: R) -> std::io::Result<()> {
+        let data = read_session(reader)?;
+        let empty = self.empty_snapshot();
+        self.restore(empty);
+
+        let collapsed = self.collapsed_threads.read().clone();
+        let keywords = self.keywords.read().clone();
+        // 数据段ID对应的线索层级和隐藏状态。
+        let mut threads: HashMap<i64, (usize, bool)> = HashMap::new();
+        let mut buffer = Vec::with_capacity(data.len());
+        for user_data in data {
+            let mut rich_data = self.styled_rich_data(user_data);
+            rich_data.apply_keywords(&keywords);
+            if let Some(parent_id) = rich_data.parent_id {
+                if let Some(&(depth, hidden)) = threads.get(&parent_id) {
+                    rich_data.thread_depth = depth + 1;
+                    rich_data.hidden = hidden || collapsed.contains(&parent_id);
+                }
+            }
+            threads.insert(rich_data.id, (rich_data.thread_depth, rich_data.hidden));
+            if rich_data.data_type != DataType::Marker {
+                self.assign_line_no(&mut rich_data);
+            }
+            buffer.push(rich_data);
+        }
+        let max_lines = self.buffer_max_lines.load(Ordering::Relaxed);
+        if buffer.len() > max_lines {
+            buffer.drain(..buffer.len() - max_lines);
+        }
+        *self.current_buffer.write() = buffer;
+        *self.thread_index.write() = threads;
+        self.relayout_main_buffer(|_| {});
+        self.update_panel_fn.write().update_param(false);
+        Ok(())
+    }
+
+    /// 设置会话日志，之后添加到主视图的每个数据段都会写入日志文件，被重复检测抑制的数据不写入。
+    /// 数据段先进入缓冲区，每隔`LOG_FLUSH_INTERVAL`写入文件一次；被替换的会话日志会先写入全部缓冲内容。
+    /// 写入失败时只记录错误，不影响数据显示。
+    ///
+    /// # Arguments
+    ///
+    /// * `logger`: 会话日志，为空时停止记录。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::log_file::{LogSink, LogSinkFormat};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_logger(Some(LogSink::new(std::env::temp_dir().join("session.jsonl"), LogSinkFormat::JsonLines)));
+    /// ```
+    pub fn set_logger(&mut self, logger: Option<LogSink>) {
+        let old = std::mem::replace(&mut *self.logger.write(), logger);
+        if let Some(mut old) = old {
+            if let Err(e) = old.flush() {
+                error!("写入会话日志失败：{:?} {:?}", old.path(), e);
+            }
+        }
+    }
+
+    /// 将主视图截图保存为PNG文件。完整截图时按照当前布局分块离线绘制全部数据内容，每块绘制完成后立即压缩写入文件，
+    /// 光标、行号栏和背景图片不参与完整截图。
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: 文件路径。
+    /// * `range`: 截图范围。
+    ///
+    /// returns: Result<(), Error>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::SnapshotRange;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let path = std::env::temp_dir().join("rich_text_snapshot.png");
+    /// if rich_text.snapshot_png(&path, SnapshotRange::All).is_ok() {
+    ///     std::fs::remove_file(&path).ok();
+    /// }
+    /// ```
+    pub fn snapshot_png<P: AsRef<Path>>(&self, path: P, range: SnapshotRange) -> std::io::Result<()> {
+        let bg_color = *self.background_color.read();
+        let out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        match range {
+            SnapshotRange::Visible => {
+                let (width, height) = (max(self.panel.width(), 1), max(self.panel.height(), 1));
+                let image = draw::capture_offscreen(&mut self.panel_screen.write(), width, height).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+                let mut encoder = PngEncoder::new(out, width as u32, height as u32)?;
+                encoder.write_rows(&to_rgb8(&image.to_rgb_data(), image.depth(), bg_color))?;
+                encoder.finish()?;
+            }
+            SnapshotRange::All => {
+                let (width, bottom) = (max(self.panel.width(), 1), self.padding.read().bottom);
+                if self.remote_flow_control.load(Ordering::SeqCst) {
+                    Self::render_all(&self.current_buffer.read(), width, bottom, bg_color, out)?;
+                } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+                    Self::render_all(main_buffer, width, bottom, bg_color, out)?;
+                } else {
+                    Self::render_all(&[], width, bottom, bg_color, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 分块离线绘制全部数据内容，每块绘制完成后立即编码写入输出目标，不在内存中拼接整幅图片。
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer`: 已完成布局的数据缓存。
+    /// * `width`: 图片宽度。
+    /// * `bottom`: 底部边距。
+    /// * `bg_color`: 背景色。
+    /// * `out`: PNG输出目标。
+    ///
+    /// returns: Result<(), Error>
+    fn render_all<W: std::io::Write>(buffer: &[RichData], width: i32, bottom: i32, bg_color: Color, out: W) -> std::io::Result<()> {
+        let height = max(buffer.iter().map(|rd| rd.v_bounds.read().1).max().unwrap_or(0) + bottom, 1);
+        let blink_state = BlinkState::new();
+        let options = RenderOptions::default().on_background(bg_color);
+        let mut encoder = PngEncoder::new(out, width as u32, height as u32)?;
+        let mut top = 0;
+        while top < height {
+            let tile_height = min(SNAPSHOT_TILE_HEIGHT, height - top);
+            let Some(mut offs) = Offscreen::new(width, tile_height) else {
+                return Err(std::io::Error::new(std::io::ErrorKind::Other, "创建离线绘图板失败"));
+            };
+            offs.begin();
+            draw_rect_fill(0, 0, width, tile_height, bg_color);
+            for rd in buffer.iter() {
+                let (top_y, bottom_y, _, _) = *rd.v_bounds.read();
+                if bottom_y >= top && top_y < top + tile_height {
+                    rd.draw(top, &blink_state, &options);
+                }
+            }
+            offs.end();
+            let image = draw::capture_offscreen(&mut offs, width, tile_height).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?;
+            encoder.write_rows(&to_rgb8(&image.to_rgb_data(), image.depth(), bg_color))?;
+            top += tile_height;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// 打印主缓存中的全部数据内容。按照当前布局分页，分页处不截断文字行，内容宽度超出纸张宽度时按比例缩小。
+    /// 开始打印前会弹出系统打印对话框，用户取消打印时返回错误。
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 打印参数。
+    ///
+    /// returns: Result<(), FltkError>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::PrintOptions;
+    ///
+    /// let options = PrintOptions::new().set_title("会话记录").set_black_on_white(true);
+    /// ```
+    pub fn print(&self, options: PrintOptions) -> Result<(), FltkError> {
+        let mut buffer = if self.remote_flow_control.load(Ordering::SeqCst) {
+            self.current_buffer.read().clone()
+        } else {
+            self.data_buffer.read().clone().unwrap_or_default()
+        };
+        if options.black_on_white {
+            buffer.iter_mut().for_each(|rd| rd.to_print_colors());
+        }
+        let bg_color = if options.black_on_white { Color::White } else { *self.background_color.read() };
+        let width = max(self.panel.width(), 1);
+        let content_height = buffer.iter().map(|rd| rd.v_bounds.read().1).max().unwrap_or(0) + self.padding.read().bottom;
+        let rows: Vec<(i32, i32)> = buffer.iter().flat_map(|rd| rd.line_pieces.iter().map(|piece| {
+            let piece = piece.read();
+            (piece.y, piece.h)
+        })).collect();
+
+        let mut printer = Printer::default();
+        printer.begin_job(0)?;
+        let (page_width, page_height) = printer.printable_rect();
+        let scale = (page_width as f32 / width as f32).min(1.0);
+        draw::set_font(options.font, options.font_size);
+        let band = draw::height() + draw::descent();
+        let header = if options.title.is_some() { band } else { 0 };
+        let footer = if options.page_numbers { band } else { 0 };
+        let body_height = (page_height as f32 / scale) as i32 - header - footer;
+        if body_height <= 0 {
+            printer.end_job();
+            return Err(FltkError::Unknown("纸张的可打印高度不足以容纳页眉和页脚".to_string()));
+        }
+        let pages = paginate(&rows, content_height, body_height);
+        let blink_state = BlinkState::new();
+        let render_options = RenderOptions::default().on_background(bg_color);
+
+        for (page_no, (start, end)) in pages.iter().enumerate() {
+            printer.begin_page()?;
+            printer.scale(scale, None);
+            let page_bottom = header + body_height + footer;
+            draw_rect_fill(0, 0, width, page_bottom, bg_color);
+            draw::set_font(options.font, options.font_size);
+            set_draw_color(if options.black_on_white { Color::Black } else { *self.text_color.read() });
+            if let Some(title) = &options.title {
+                draw::draw_text2(title, 0, 0, width, header, Align::Left);
+            }
+            if options.page_numbers {
+                draw::draw_text2(&format!("{} / {}", page_no + 1, pages.len()), 0, header + body_height, width, footer, Align::Center);
+            }
+
+            draw::push_clip(0, header, width, end - start);
+            for rd in buffer.iter() {
+                let (top_y, bottom_y, _, _) = *rd.v_bounds.read();
+                if bottom_y >= *start && top_y < *end {
+                    rd.draw(start - header, &blink_state, &render_options);
+                }
+            }
+            draw::pop_clip();
+            printer.end_page()?;
+        }
+        printer.end_job();
+        Ok(())
+    }
+
+    /// 按照元数据查找主缓存中的数据段。
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: 元数据的键。
+    /// * `value`: 元数据的值。
+    ///
+    /// returns: Vec<i64, Global> 元数据匹配的数据段ID，按添加顺序排列。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// assert!(rich_text.find_by_meta("speaker", "zhangsan").is_empty());
+    /// ```
+    pub fn find_by_meta(&self, key: &str, value: &str) -> Vec<i64> {
+        let find = |buffer: &[RichData]| buffer.iter()
+            .filter(|rd| rd.meta.as_ref().and_then(|meta| meta.get(key)).is_some_and(|v| v == value))
+            .map(|rd| rd.id)
+            .collect();
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            find(&self.current_buffer.read())
+        } else if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            find(main_buffer)
+        } else {
+            vec![]
+        }
+    }
+
+    /// 设置是否启用分屏回看模式。启用后，向上翻页时主面板被分割为上下两部分：
+    /// 上半部分为可滚动的冻结视图，下半部分继续显示实时数据，两者共享同一份数据缓存，不再复制快照。
+    /// 当处于光标定位面板流模式时，仍以快照方式打开回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `split`: true启用分屏回看模式，false使用默认的回顾区。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_split_scrollback(true);
+    /// ```
+    pub fn set_split_scrollback(&mut self, split: bool) {
+        self.split_scrollback.store(split, Ordering::Relaxed);
+    }
+
+    /// 删除最后一个数据段。限制渲染帧率时立即执行，不等待待合并队列中的数据，参见`set_max_fps`。
+    pub fn delete_last_data(&mut self) {
+        if let Some(_rich_data) = self.current_buffer.write().pop() {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+
+    /// 查询目标字符串，并自动显示第一个或最后一个目标所在行。
+    /// 若以相同参数重复调用该方法，则每次调用都会自动定位到下一个查找到的目标位置。
+    /// 默认在回顾区中查找，通过`set_search_in_main`开启后直接在主视图中查找，不打开回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `search_str`: 目标字符串。如果给定一个空字符，则清空查询缓存。
+    /// * `forward`: true正向查找，false反向查找。
+    ///
+    /// returns: bool 若查找到目标返回true，否则返回false。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::{app, window};
+    /// use fltk::button::Button;
+    /// use fltk::group::Group;
+    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt, WindowExt};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let app = app::App::default();
+    /// let mut win = window::Window::default().with_size(1000, 1000).with_label("Search").center_screen();
+    /// let group = Group::default_fill();
+    /// let mut btn1 = Button::new(200, 0, 100, 30, "查找字符串1");
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// btn1.set_callback({
+    ///     let mut rt = rich_text.clone();
+    ///     move |_| {
+    ///         rt.search_str(Some("程序".to_string()), false);
+    ///     }
+    /// });
+    /// group.end();
+    /// win.end();
+    /// win.show();
+    ///
+    /// while app.wait() {
+    ///     app::sleep(0.001);
+    ///     app::awake();
+    /// }
+    /// ```
+    pub fn search_str(&mut self, search_str: Option<String>, forward: bool) -> bool {
+        if self.search_in_main.load(Ordering::Relaxed) {
+            return self.search_main(search_str, forward);
+        }
+        let mut find_out = false;
+        if search_str.is_none() {
+            if let Some(rr) = &mut *self.reviewer.write() {
+                rr.clear_search_results();
+            }
+        } else if let Ok(open_suc) = self.auto_open_reviewer() {
+            if let Some(ref mut rr) = *self.reviewer.write() {
+                if let Some(search_str) = search_str {
+                    if !search_str.is_empty() {
+                        find_out = rr.search_str(Some(search_str), forward);
+                        if !open_suc {
+                            // 如果回顾区早已打开，则强制刷新
+                            rr.scroller.set_damage(true);
+                        }
+                    } else {
+                        rr.clear_search_results();
+                    }
+                } else {
+                    rr.clear_search_results();
+                }
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        self.set_damage(true);
+
+        find_out
+    }
+
+    /// 设置回顾区是否在新数据到达时保持当前查找目标垂直居中。开启后每次通过`search_str`定位到目标时开始跟随，
+    /// 分屏回看模式下实时追加的数据不会将目标挤出可见区域；用户滚动到其他位置或清除查找后停止跟随。
+    ///
+    /// # Arguments
+    ///
+    /// * `follow`: 是否跟随。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_split_scrollback(true);
+    /// rich_text.set_follow_search_focus(true);
+    /// ```
+    pub fn set_follow_search_focus(&mut self, follow: bool) {
+        self.follow_search_focus.store(follow, Ordering::Relaxed);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_follow_search_focus(follow);
+        }
+    }
+
+    /// 设置`search_str`是否直接在主视图中查找，而不打开回顾区。
+    /// 开启后只在主视图当前可见的数据中查找并高亮所有目标，重复调用时按照查找方向循环切换焦点目标；
+    /// 查找之后到达的新数据不会被高亮，再次调用`search_str`时重新查找。关闭时清除主视图中的查找结果。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`: 是否在主视图中查找。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_search_in_main(true);
+    /// ```
+    pub fn set_search_in_main(&mut self, enable: bool) {
+        let old = self.search_in_main.swap(enable, Ordering::Relaxed);
+        if old && !enable {
+            self.search_main(None, true);
+        }
+    }
+
+    /// 在主视图可见的数据中查找目标字符串，并高亮下一个或上一个目标。
+    ///
+    /// # Arguments
+    ///
+    /// * `search_str`: 目标字符串，为空时清除查找结果。
+    /// * `forward`: true正向(从上向下)，false反向查找。
+    ///
+    /// returns: bool 是否找到目标。
+    fn search_main(&mut self, search_str: Option<String>, forward: bool) -> bool {
+        let pattern = search_str.filter(|s| !s.is_empty());
+        let old = self.main_search.write().take();
+        let panel_height = self.panel.height();
+        let scroll_y = Self::calc_scroll_height(self.current_buffer.clone(), panel_height, self.append_top.load(Ordering::Relaxed), &self.padding.read());
+
+        let focus = {
+            let mut buffer = self.current_buffer.write();
+            // 按照从上到下、从左到右的顺序收集可见数据中的所有目标。
+            let mut targets: Vec<(usize, usize)> = vec![];
+            for (idx, rd) in buffer.iter_mut().enumerate() {
+                rd.search_result_positions = None;
+                rd.search_highlight_pos = None;
+                let Some(pattern) = pattern.as_deref() else {
+                    continue;
+                };
+                let (top, bottom, _, _) = *rd.v_bounds.read();
+                if bottom < scroll_y || top > scroll_y + panel_height || !rd.text.contains(pattern) {
+                    continue;
+                }
+                let positions = search_positions(&rd.text, pattern);
+                targets.extend((0..positions.len()).rev().map(|pos| (idx, pos)));
+                rd.search_result_positions = Some(positions);
+            }
+
+            if targets.is_empty() {
+                None
+            } else {
+                let current = old.as_ref()
+                    .filter(|(old_pattern, _, _)| pattern.as_ref() == Some(old_pattern))
+                    .and_then(|(_, id, pos)| targets.iter().position(|(idx, i)| buffer[*idx].id == *id && i == pos));
+                let next = match current {
+                    Some(c) if forward => (c + 1) % targets.len(),
+                    Some(c) => (c + targets.len() - 1) % targets.len(),
+                    None if forward => 0,
+                    None => targets.len() - 1,
+                };
+                let (idx, pos) = targets[next];
+                buffer[idx].search_highlight_pos = Some(pos);
+                Some((buffer[idx].id, pos))
+            }
+        };
+
+        if let Some(pattern) = pattern {
+            let (id, pos) = focus.unwrap_or((0, 0));
+            self.main_search.write().replace((pattern, id, pos));
+        }
+        self.update_panel_fn.write().update_param(false);
+        self.inner.set_damage(true);
+        focus.is_some()
+    }
+
+    fn new_offline(
+        w: i32, h: i32, offscreen: Arc<RwLock<Offscreen>>,
+        panel: &mut impl WidgetBase,
+        visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
+        clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
+        bg_color: Color,
+        temp_buffer: Arc<RwLock<Vec<RichData>>>,
+        blink_flag: Arc<RwLock<BlinkState>>,
+        render_options: &RenderOptions,
+        cursor: Option<Arc<RwLock<LinePiece>>>,
+        gutter_width: i32,
+        pin_top: bool,
+        background_image: Arc<RwLock<BackgroundImage>>,
+        padding: Padding,
+        pinned: &PinnedRows,
+        ) {
+        if let Some(offs) = Offscreen::new(w, h) {
+            *offscreen.write() = offs;
+            Self::draw_offline(offscreen.clone(), panel, visible_lines.clone(), clickable_data, bg_color, temp_buffer.clone(), blink_flag, render_options, cursor, gutter_width, pin_top, background_image, padding, pinned);
+        }
+    }
+
+    /// 在当前绘制表面上绘制光标。
+    fn draw_cursor(cursor_piece: &LinePiece, offset_y: i32, bg_color: Color, bs: &BlinkState, data: &[RichData]) {
+        // debug!("开始离线绘制光标: {:?}", cursor_piece);
+        let cursor_width = max(cursor_piece.font_size / 2, 4);
+        let y = cursor_piece.y - offset_y;
+        let line_y = y + cursor_piece.font_height - ((cursor_piece.font_height as f32 / 10f32).floor() as i32 + 1);
+        let cursor_color = match bs.cursor_next {
+            BlinkDegree::Normal => bs.cursor_color,
+            BlinkDegree::Contrast => bg_color,
+        };
+        set_draw_color(cursor_color);
+        match bs.cursor_style {
+            CursorStyle::Block => {
+                if bs.cursor_next == BlinkDegree::Contrast {
+                    // 对比强度下不绘制方块，露出光标处的字符。
+                    return;
+                }
+                draw::draw_rectf(cursor_piece.x, y, cursor_width, cursor_piece.font_height);
+                // 在方块内以背景色重绘光标处的字符，避免被方块遮住。
+                draw::push_clip(cursor_piece.x, y, cursor_width, cursor_piece.font_height);
+                for rd in data.iter() {
+                    rd.redraw_text_in(cursor_piece.x, cursor_piece.y, cursor_width, cursor_piece.font_height, offset_y, bg_color);
+                }
+                draw::pop_clip();
+            }
+            CursorStyle::Underline => {
+                draw_line(cursor_piece.x, line_y, cursor_piece.x + cursor_width, line_y);
+            }
+            CursorStyle::Bar => {
+                draw::draw_rectf(cursor_piece.x, y, 2, cursor_piece.font_height);
+            }
+        }
+    }
+
+    /// 增量刷新定位面板的离线缓存。按顺序将滚动区域内已绘制的像素整体上移，然后只重绘内容发生变化的行以及新旧光标所在的行。
+    /// 存在闪烁内容或内容超出面板高度等无法增量刷新的情况时不做任何绘制，返回`false`，由调用者完整重绘。
+    ///
+    /// # Arguments
+    ///
+    /// * `offscreen`: 离线缓存。
+    /// * `panel`: 主视图面板。
+    /// * `visible_lines`: 可见行信息。
+    /// * `clickable_data`: 可操作数据信息。
+    /// * `bg_color`: 背景色。
+    /// * `current_buffer`: 当前数据缓存。
+    /// * `blink_flag`: 闪烁状态。
+    /// * `cursor`: 需要绘制的光标。
+    /// * `board`: 定位面板。
+    /// * `damage`: 定位面板自上次绘制以来的变化。
+    ///
+    /// returns: bool 是否已完成增量刷新。
+    fn draw_board_offline(
+        offscreen: Arc<RwLock<Offscreen>>,
+        panel: &mut impl WidgetBase,
+        visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
+        clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
+        bg_color: Color,
+        current_buffer: Arc<RwLock<Vec<RichData>>>,
+        blink_flag: Arc<RwLock<BlinkState>>,
+        render_options: &RenderOptions,
+        cursor: Option<Arc<RwLock<LinePiece>>>,
+        board: &ReWriteBoard,
+        damage: &BoardDamage) -> bool {
+        let bg_color = transform_color(bg_color, false);
+        let (panel_x, panel_y, window_width, window_height) = (panel.x(), panel.y(), panel.width(), panel.height());
+        let data = current_buffer.read();
+        {
+            let bs = blink_flag.read();
+            if bs.on || bs.rapid_on || data.iter().any(|rd| rd.blink || rd.v_bounds.read().1 > window_height) {
+                return false;
+            }
+        }
+
+        let row_height = (board.line_height + board.line_space) as i32;
+        let mut rows = damage.dirty_rows.clone();
+        rows.extend(board.cursor_row);
+        if let Some(cursor) = cursor.as_ref() {
+            rows.extend(board.row_at(cursor.read().y));
+        }
+
+        offscreen.read().begin();
+
+        // 复制滚动区域内保持不变的行
+        for &(top, bottom, n) in damage.scrolls.iter() {
+            if bottom > board.max_rows || top + n > bottom {
+                continue;
+            }
+            let (src_y, dst_y) = (board.row_y(top + n), board.row_y(top));
+            let h = board.row_y(bottom + 1) - src_y;
+            if let Ok(mut image) = draw::read_image(0, src_y, window_width, h) {
+                image.draw(0, dst_y, window_width, h);
+            }
+        }
+
+        // 重绘变化的行
+        {
+            let bs = &*blink_flag.read();
+            let options = render_options.on_background(bg_color);
+            for &row in rows.iter().filter(|&&row| row <= board.max_rows) {
+                draw_rect_fill(0, board.row_y(row), window_width, row_height, bg_color);
+                if let Some(rds) = board.line_data_map.get(&row) {
+                    rds.iter().for_each(|rd| rd.draw(0, bs, &options));
+                }
+            }
+        }
+
+        if let Some(cursor) = cursor {
+            let cursor = &*cursor.read();
+            let row_data = board.row_at(cursor.y).and_then(|row| board.line_data_map.get(&row)).map_or(&[][..], |rds| rds.as_slice());
+            Self::draw_cursor(cursor, 0, bg_color, &blink_flag.read(), row_data);
+        }
+
+        offscreen.read().end();
+
+        // 重新记录可见行和可操作数据信息
+        let vl = &mut *visible_lines.write();
+        let cd = &mut *clickable_data.write();
+        vl.clear();
+        cd.clear();
+        for (idx, rich_data) in data.iter().enumerate() {
+            for piece in rich_data.line_pieces.iter() {
+                let piece = &*piece.read();
+                let rect = Rectangle::new(piece.x + panel_x, piece.y + panel_y, piece.w, piece.h);
+                vl.insert(rect.clone(), piece.clone());
+                if rich_data.clickable {
+                    cd.insert(rect, idx);
+                }
+            }
+        }
+
+        panel.set_damage(true);
+        true
+    }
+
+    fn draw_offline(
+        offscreen: Arc<RwLock<Offscreen>>,
+        panel: &mut impl WidgetBase,
+        visible_lines: Arc<RwLock<HashMap<Rectangle, LinePiece>>>,
+        clickable_data: Arc<RwLock<HashMap<Rectangle, usize>>>,
+        bg_color: Color,
+        current_buffer: Arc<RwLock<Vec<RichData>>>,
+        blink_flag: Arc<RwLock<BlinkState>>,
+        render_options: &RenderOptions,
+        cursor: Option<Arc<RwLock<LinePiece>>>,
+        gutter_width: i32,
+        pin_top: bool,
+        background_image: Arc<RwLock<BackgroundImage>>,
+        padding: Padding,
+        pinned: &PinnedRows,) {
+        // debug!("开始离线绘制");
+        // let mut damage_area = (0, 0, 0, 0);
+        offscreen.read().begin();
+
+        let bg_color = transform_color(bg_color, false);
+        let (panel_x, panel_y, window_width, window_height) = (panel.x(), panel.y(), panel.width(), panel.height());
+        let mut offset_y = 0;
+
+        let vl = &mut *visible_lines.write();
+        let cd = &mut *clickable_data.write();
+        vl.clear();
+        cd.clear();
+
+        // 填充背景
+        draw_rect_fill(0, 0, window_width, window_height, bg_color);
+        background_image.write().draw(window_width, window_height, bg_color);
+        // damage_area = (0, 0, window_width, window_height);
+
+        let mut need_blink = false;
+        let mut need_rapid_blink = false;
+
+        // 绘制数据内容
+        let data = current_buffer.read();
+        // 顶部追加模式下固定显示顶部内容，无需偏移。
+        let mut set_offset_y = pin_top;
+        let mut drawable_vec: Vec<(usize, &RichData)> = vec![];
+        for (idx, rich_data) in data.iter().enumerate().rev() {
+            if pin_top && rich_data.v_bounds.read().0 > window_height {
+                continue;
+            }
+            let bottom_y = rich_data.v_bounds.read().1;
+            if !set_offset_y && bottom_y > window_height {
+                offset_y = bottom_y - window_height + padding.bottom;
+                set_offset_y = true;
+            }
+
+            if bottom_y < offset_y {
+                break;
+            }
+
+            // 暂存主体任意部分可见的数据行信息
+            for piece in rich_data.line_pieces.iter() {
+                let piece = &*piece.read();
+                let y = piece.y - offset_y + panel_y;
+                let rect = Rectangle::new(piece.x + panel_x, y, piece.w, piece.h);
+                vl.insert(rect.clone(), piece.clone());
+
+                // 暂存可操作数据信息
+                if rich_data.clickable {
+                    cd.insert(rect, idx);
+                }
+            }
+
+            // rich_data.draw(offset_y, &*blink_flag.borrow());
+            // 倒序暂存
+            drawable_vec.push((idx, rich_data));
+
+            if rich_data.blink {
+                match rich_data.blink_speed {
+                    BlinkSpeed::Slow => need_blink = true,
+                    BlinkSpeed::Rapid => need_rapid_blink = true,
+                }
+            }
+            if rich_data.search_highlight_pos.is_some() {
+                // 查找焦点框随慢速闪烁切换颜色。
+                need_blink = true;
+            }
+        }
+
+        // 顺序绘制
+        {
+            // debug!("本次绘制数据段：{:?}", drawable_vec.len());
+            let bf = &*blink_flag.read();
+            let options = render_options.on_background(bg_color);
+            while let Some((idx, rd)) = drawable_vec.pop() {
+                // debug!("绘制数据段: {:?}", rd.text);
+                rd.draw(offset_y, bf, &options);
+                if gutter_width > 0 {
+                    let line_start = data[..idx].iter().rev().find(|d| d.data_type != DataType::Marker).map(|d| d.text.ends_with('\n')).unwrap_or(true);
+                    rd.draw_line_numbers(offset_y, line_start, LINE_NUMBER_COLOR);
+                }
+            }
+        }
+
+        if gutter_width > 0 {
+            // 绘制行号栏分隔线
+            set_draw_color(LINE_NUMBER_COLOR);
+            draw_line(padding.left + gutter_width - IMAGE_PADDING_H, 0, padding.left + gutter_width - IMAGE_PADDING_H, window_height);
+        }
+
+        // 填充顶部边界空白
+        draw::push_clip(0, 0, window_width, padding.top);
+        draw_rect_fill(0, 0, window_width, padding.top, bg_color);
+        background_image.write().draw(window_width, window_height, bg_color);
+        draw::pop_clip();
+
+        // 绘制顶部和底部的固定行
+        for (strip_y, strip_h, rows) in pinned.strips(window_height) {
+            if strip_h <= 0 {
+                continue;
+            }
+            draw::push_clip(0, strip_y, window_width, strip_h);
+            draw_rect_fill(0, strip_y, window_width, strip_h, bg_color);
+            background_image.write().draw(window_width, window_height, bg_color);
+            let bf = &*blink_flag.read();
+            let options = render_options.on_background(bg_color);
+            for rd in rows {
+                rd.draw(-strip_y, bf, &options);
+            }
+            draw::pop_clip();
+        }
+
+        if let Some(cursor) = cursor {
+            // 绘制光标
+            blink_flag.write().on();
+            Self::draw_cursor(&cursor.read(), offset_y, bg_color, &blink_flag.read(), &data);
+            // damage_area = (cursor_piece.x, line_y - 1, cursor_width, 3);
+        }
+
+        offscreen.read().end();
+
+        // 更新闪烁标记
+        if need_blink {
+            blink_flag.write().on();
+        } else {
+            blink_flag.write().off();
+        }
+        blink_flag.write().set_rapid(need_rapid_blink);
+
+        // debug!("待刷新区域: {:?}", damage_area);
+        // panel.set_damage_area(Damage::All, damage_area.0, damage_area.1, damage_area.2, damage_area.3);
+        panel.set_damage(true);
+    }
+
+    /// 设置面板背景色。
+    ///
+    /// # Arguments
+    ///
+    /// * `background_color`: 背景色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_background_color(&mut self, background_color: Color) {
+        *self.background_color.write() = background_color;
+        if let Some(reviewer) = self.reviewer.read().as_ref() {
+            reviewer.set_background_color(background_color);
+        }
+    }
+
+    /// 应用主题，同时更新主视图和已打开的回顾区。已缓存数据中使用旧默认颜色的部分改用新主题的颜色，
+    /// 设置了颜色序号的数据段按照新主题的调色板着色。
+    ///
+    /// # Arguments
+    ///
+    /// * `theme`: 主题。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::Theme;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.apply_theme(&Theme::solarized_dark());
+    /// ```
+    pub fn apply_theme(&mut self, theme: &Theme) {
+        // 在整个切换过程中持有回顾区的写锁，避免回顾区在中途被创建或销毁。
+        let mut reviewer = self.reviewer.write();
+        let old = (*self.text_color.read(), *self.background_color.read(), *self.text_font.read());
+
+        *self.background_color.write() = theme.background_color;
+        *self.text_color.write() = theme.text_color;
+        *self.text_font.write() = theme.text_font;
+        theme.apply_to_blink_state(&mut self.blink_flag.write());
+        theme.apply_to_tooltip(&mut self.tooltip.write());
+        self.user_palette.write().replace(theme.ansi_palette);
+        self.ansi_palette.write().replace(theme.ansi_palette);
+
+        let mut font_changed = retheme_data(&mut self.current_buffer.write(), old, theme);
+        if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            font_changed |= retheme_data(main_buffer, old, theme);
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            let mut board_font_changed = false;
+            board.line_data_map.values_mut().for_each(|row| board_font_changed |= retheme_data(row, old, theme));
+            if board_font_changed {
+                // 字体变化后定位面板按行重新计算。
+                let drawable_max_width = self.padding.read().drawable_width(self.panel.width());
+                board.rescale(board.line_height, drawable_max_width, *self.basic_char.read(), |_| {});
+                *self.current_buffer.write() = board.all_data();
+            } else {
+                board.invalidate();
+            }
+        }
+        if let Some(reviewer) = reviewer.as_mut() {
+            reviewer.apply_theme(theme);
+        }
+        drop(reviewer);
+        if font_changed {
+            self.relayout_main_buffer(|_| {});
+        }
+        if self.color_transform.read().vision.is_some() {
+            // 主题的调色板同样需要叠加色觉障碍配色。
+            let palette = self.effective_palette();
+            self.use_ansi_palette(palette);
+        }
+
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置选中文字的背景色和前景色，同时应用到已打开的回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `bg`: 选中文字的背景色，默认为`Color::Selection`。当文字背景色与之相同时自动改用`Color::DarkMagenta`。
+    /// * `fg`: 选中文字的前景色，为空时保持文字原有颜色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_selection_colors(Color::from_rgb(38, 79, 120), Some(Color::White));
+    /// ```
+    pub fn set_selection_colors(&mut self, bg: Color, fg: Option<Color>) {
+        self.blink_flag.write().set_selection_colors(bg, fg);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_selection_colors(bg, fg);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置自动折行续行标记的颜色。设置后在主视图和回顾区中每个因超宽而自动折到下一行的续行左侧绘制一个小的折行标记，
+    /// 以便区分原始换行和自动折行。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`: 标记颜色，为空时不绘制标记。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_wrap_marker(Some(Color::from_rgb(90, 90, 90)));
+    /// ```
+    pub fn set_wrap_marker(&mut self, color: Option<Color>) {
+        self.blink_flag.write().set_wrap_marker(color);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_wrap_marker(color);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 开启或关闭减少动态效果模式，适用于对闪烁和动画敏感的用户。开启后主视图和回顾区的内容闪烁、光标闪烁均停止，
+    /// 新数据也不再使用淡入效果。
+    ///
+    /// # Arguments
+    ///
+    /// * `on`: 是否开启。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_reduced_motion(true);
+    /// ```
+    pub fn set_reduced_motion(&mut self, on: bool) {
+        self.render_options.write().set_reduced_motion(on);
+        if on {
+            // 将正处于对比强度的内容和光标恢复为正常显示。
+            self.blink_flag.write().reset_phase();
+        }
+        self.wake_cursor_blink();
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_reduced_motion(on);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置文字与背景的最低对比度，前景色与背景色的对比度低于该值时自动调整前景色，计算方法遵循WCAG 2.x的定义。
+    /// WCAG建议普通文字不低于4.5，大号文字不低于3。小于等于1时不调整，这是默认行为。
+    ///
+    /// # Arguments
+    ///
+    /// * `ratio`: 最低对比度，取值范围为1到21。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_minimum_contrast(4.5);
+    /// ```
+    pub fn set_minimum_contrast(&mut self, ratio: f32) {
+        self.render_options.write().set_minimum_contrast(ratio);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_minimum_contrast(ratio);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置文字阴影或描边效果，作用于主视图和回顾区中的文字以及图片上的说明文字。
+    /// 开启背景图片或半透明主题时，可用于保持浅色文字的可读性。
+    ///
+    /// # Arguments
+    ///
+    /// * `shadow`: 阴影或描边效果，为空时不绘制。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::TextShadow;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_text_shadow(Some(TextShadow::Shadow { offset_x: 1, offset_y: 1, color: Color::Black }));
+    /// ```
+    pub fn set_text_shadow(&mut self, shadow: Option<TextShadow>) {
+        self.render_options.write().set_text_shadow(shadow);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_text_shadow(shadow);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置绘制时的颜色变换函数，作用于主视图和回顾区中数据段的前景色、背景色以及视图背景色，不改变缓存中的数据。
+    /// 可用于实现护眼、反色等自定义配色效果。与`set_color_vision`同时使用时，先调整色觉障碍配色，再调用变换函数。
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: 变换函数，参数为原始颜色，返回绘制时使用的颜色。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_color_filter(|color| {
+    ///     let (r, g, b) = color.to_rgb();
+    ///     Color::from_rgb(r, g, b / 2)
+    /// });
+    /// ```
+    pub fn set_color_filter<F>(&mut self, filter: F) where F: FnMut(Color) -> Color + Send + Sync + 'static {
+        self.color_transform.write().filter.replace(ColorFilter::new(Arc::new(RwLock::new(Box::new(filter)))));
+        self.apply_color_transform();
+    }
+
+    /// 移除颜色变换函数。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_color_filter(|color| color.inactive());
+    /// rich_text.clear_color_filter();
+    /// ```
+    pub fn clear_color_filter(&mut self) {
+        self.color_transform.write().filter.take();
+        self.apply_color_transform();
+    }
+
+    /// 设置适配色觉障碍的配色方案。设置了ANSI颜色序号的数据段改用对应的内置调色板，直接指定的颜色在绘制时自动调整，
+    /// 使红色和绿色更易区分。应用或主题设置过自定义调色板时，在自定义调色板的基础上调整。为空时恢复原有调色板并停止调整。
+    ///
+    /// # Arguments
+    ///
+    /// * `vision`: 色觉障碍类型。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::ColorVision;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_color_vision(Some(ColorVision::Deuteranopia));
+    /// ```
+    pub fn set_color_vision(&mut self, vision: Option<ColorVision>) {
+        self.color_transform.write().vision = vision;
+        let palette = self.effective_palette();
+        self.use_ansi_palette(palette);
+        self.apply_color_transform();
+    }
+
+    /// 将颜色变换同步到回顾区并刷新显示。
+    fn apply_color_transform(&mut self) {
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_color_transform(self.color_transform.read().clone());
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.invalidate();
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 设置复制选中文字时是否保留自动折行。默认不保留，被自动折行的内容按照接收时的原样合并为一行；
+    /// 保留时在每个自动折行处插入换行符，复制结果与屏幕显示一致。该设置同时应用于回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `keep`: 是否保留自动折行。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_copy_soft_wraps(true);
+    /// ```
+    pub fn set_copy_soft_wraps(&mut self, keep: bool) {
+        self.copy_soft_wraps.store(keep, Ordering::Relaxed);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_copy_soft_wraps(keep);
+        }
+    }
+
+    /// 获取指定数据段起始位置所在的逻辑行序号，从0开始。逻辑行只以接收到的换行符划分，不受自动折行和窗口宽度影响，
+    /// 可用于导出时按照原始行还原内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据段ID。
+    ///
+    /// returns: Option<usize> 数据段不在主缓存中时返回空。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text_with_id(1, "first\n".to_string()));
+    /// rich_text.append(UserData::new_text_with_id(2, "second\n".to_string()));
+    /// assert_eq!(rich_text.logical_line(2), Some(1));
+    /// assert_eq!(rich_text.logical_line(3), None);
+    /// ```
+    pub fn logical_line(&self, id: i64) -> Option<usize> {
+        let find = |buffer: &[RichData]| buffer.iter().find(|rd| rd.has_origin(id)).map(|rd| rd.line_no);
+        if self.remote_flow_control.load(Ordering::SeqCst) {
+            find(&self.current_buffer.read())
+        } else {
+            self.data_buffer.read().as_ref().and_then(|main_buffer| find(main_buffer))
+        }
+    }
+
+    /// 设置数据缓存最大条数，并非行数。
+    ///
+    /// # Arguments
+    ///
+    /// * `max_lines`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_cache_size(&mut self, max_lines: usize) {
+        self.buffer_max_lines.store(max_lines, Ordering::Relaxed);
+        if self.current_buffer.read().len() > self.buffer_max_lines.load(Ordering::Relaxed) {
+            let r = 0..(self.current_buffer.read().len() - self.buffer_max_lines.load(Ordering::Relaxed));
+            self.current_buffer.write().drain(r);
+            self.current_buffer.write().shrink_to_fit();
+        }
+    }
+
+    /// 暂停或恢复主视图的自动滚动。暂停后主视图保持当前画面，新数据继续进入缓存，右上角显示暂停标记及新增数据段的数量，
+    /// 便于阅读快速刷新的内容。仅对底部追加模式有效。暂停期间点击、选择和几何查询都按照冻结的画面计算。
+    /// 暂停期间缓存最多增长到缓存上限的`PAUSED_BUFFER_FACTOR`倍，超出后移除最早的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `paused`: 是否暂停。取消暂停等同于调用`resume`。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_paused(true);
+    /// assert!(rich_text.is_paused());
+    /// rich_text.resume();
+    /// assert!(!rich_text.is_paused());
+    /// ```
+    pub fn set_paused(&mut self, paused: bool) {
+        if !paused {
+            self.resume();
+        } else if self.paused.read().is_none() {
+            let len = self.current_buffer.read().len();
+            let scroll_y = Self::calc_scroll_height(self.current_buffer.clone(), self.panel.height(), self.append_top.load(Ordering::Relaxed), &self.padding.read());
+            self.paused.write().replace(PauseState { len, scroll_y });
+            self.panel.redraw();
+        }
+    }
+
+    /// 恢复自动滚动，主视图跳转到最新数据，并清理暂停期间超出缓存容量的旧数据。
+    pub fn resume(&mut self) {
+        if self.paused.write().take().is_some() {
+            let max_lines = self.buffer_max_lines.load(Ordering::Relaxed);
+            self.set_cache_size(max_lines);
+            self.update_panel_fn.write().update_param(true);
+        }
+    }
+
+    /// 主视图是否处于暂停状态。
+    pub fn is_paused(&self) -> bool {
+        self.paused.read().is_some()
+    }
+
+    /// 设置互动消息发送器。
+    ///
+    /// # Arguments
+    ///
+    /// * `notifier`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_notifier<F>(&mut self, cb: F) where F: FnMut(CallbackData) + Send + Sync +'static {
+        let callback = Callback::with_listeners(Arc::new(RwLock::new(Box::new(cb))), self.listeners.clone());
+        self.notifier.write().replace(callback);
+    }
+
+    /// 设置鼠标在可互动数据段上停留多久后显示互动行为的提示信息，即`Action::title`。主视图和回顾区均会显示。
+    ///
+    /// # Arguments
+    ///
+    /// * `delay`: 停留时间，单位秒。为空时不显示提示信息。默认为`TOOLTIP_DELAY`。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_tooltip_delay(Some(1.0));
+    /// ```
+    pub fn set_tooltip_delay(&mut self, delay: Option<f64>) {
+        self.tooltip.write().delay = delay;
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_tooltip_delay(delay);
+        }
+    }
+
+    /// 设置右键菜单钩子函数。右键点击任意数据段时调用，钩子函数可向菜单中添加全局菜单项，如复制、在线搜索等。
+    /// 附加菜单项显示在数据段自身的互动菜单项之后，主视图和回顾区均有效。钩子函数没有添加菜单项时，右键点击的行为保持不变。
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: 钩子函数，参数为右键点击的数据段和菜单项构建器。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::app;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_context_menu_hook(|_data, menu| {
+    ///     menu.add_item("复制", |data| app::copy(&data.text));
+    /// });
+    /// ```
+    pub fn set_context_menu_hook<F>(&mut self, hook: F) where F: FnMut(&UserData, &mut MenuBuilder) + Send + Sync + 'static {
+        let hook = ContextMenuHook::new(Arc::new(RwLock::new(Box::new(hook))));
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_context_menu_hook(hook.clone());
+        }
+        self.context_menu_hook.write().replace(hook);
+    }
+
+    /// 设置朗读新文字行的钩子函数，便于应用把新到达的输出转交给屏幕阅读器或语音合成引擎。
+    /// 同一行内多个数据段拼接完整后才调用钩子，空白行和隐藏文字不会被朗读；
+    /// 不以换行符结束的文字，例如提示符，需要调用`flush_speech`输出。
+    ///
+    /// # Arguments
+    ///
+    /// * `hook`: 钩子函数，参数为一行纯文本。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let spoken = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    /// rich_text.set_speech_hook({
+    ///     let spoken = spoken.clone();
+    ///     move |line| spoken.lock().unwrap().push(line.to_string())
+    /// });
+    /// ```
+    pub fn set_speech_hook<F>(&mut self, hook: F) where F: FnMut(&str) + Send + Sync + 'static {
+        self.speech_lines.write().flush();
+        self.speech_hook.write().replace(SpeechHook::new(Arc::new(RwLock::new(Box::new(hook)))));
+    }
+
+    /// 移除朗读钩子函数。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_speech_hook(|_line| {});
+    /// rich_text.clear_speech_hook();
+    /// ```
+    pub fn clear_speech_hook(&mut self) {
+        self.speech_hook.write().take();
+        self.speech_lines.write().flush();
+    }
+
+    /// 朗读尚未以换行符结束的文字，例如服务端发送的提示符。可在收到`GA`/`EOR`等提示符结束标记后调用。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_speech_hook(|_line| {});
+    /// rich_text.flush_speech();
+    /// ```
+    pub fn flush_speech(&mut self) {
+        let line = self.speech_lines.write().flush();
+        let hook = self.speech_hook.read().clone();
+        if let (Some(line), Some(hook)) = (line, hook) {
+            hook.speak(&line);
+        }
+    }
+
+    /// 获取主视图可见区域内的纯文本，供屏幕阅读器等无障碍服务读取当前画面内容。
+    /// 与可见区域有交会的数据段整段输出，隐藏文字和图形等非文本数据段被忽略。
+    ///
+    /// returns: String
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// assert!(rich_text.visible_text().is_empty());
+    /// ```
+    pub fn visible_text(&self) -> String {
+        let scroll_y = self.view_scroll_y();
+        visible_text(&self.current_buffer.read(), scroll_y, scroll_y + self.panel.height())
+    }
+
+    /// 设置在空白处右击时的行为。启用后弹出组件内置菜单，提供复制选中内容和全选功能；
+    /// 未启用时通过回调函数发送`CallbackData::ContextMenu`，由上层应用弹出自己的菜单，如清屏、打开回顾区、另存为等。
+    /// 在没有互动菜单且右键菜单钩子函数未添加菜单项的非互动数据段上右击，视同在空白处右击。主视图和回顾区均有效。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`: 是否启用组件内置菜单，默认不启用。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_blank_context_menu(true);
+    /// ```
+    pub fn set_blank_context_menu(&mut self, enable: bool) {
+        self.blank_menu.store(enable, Ordering::Relaxed);
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_blank_context_menu(enable);
+        }
+    }
+
+    /// 注册具名回调函数。可以注册多个具名回调函数，每个回调函数只接收指定类别的事件，与`set_notifier`设置的回调函数互不影响。
+    /// 同名的回调函数会被替换。回调函数中可以注册或移除具名回调函数。回顾区滚动事件只发送给具名回调函数。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 回调函数名称。
+    /// * `kinds`: 接收的事件类别，为空时接收全部事件。
+    /// * `cb`: 回调函数。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::{Arc, Mutex};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{CallbackData, EventKind};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let status = Arc::new(Mutex::new(String::new()));
+    /// let status_rc = status.clone();
+    /// rich_text.add_notifier("status_bar", &[EventKind::Link, EventKind::Scroll], move |data| {
+    ///     if let CallbackData::OpenUrl(url) = data {
+    ///         *status_rc.lock().unwrap() = url;
+    ///     }
+    /// });
+    /// assert!(rich_text.remove_notifier("status_bar"));
+    /// ```
+    pub fn add_notifier<F>(&mut self, name: &str, kinds: &[EventKind], cb: F) where F: FnMut(CallbackData) + Send + Sync +'static {
+        {
+            let mut listeners = self.listeners.write();
+            listeners.retain(|l| l.name != name);
+            listeners.push(NamedNotifier::new(name, kinds, cb));
+        }
+        let mut notifier = self.notifier.write();
+        if notifier.is_none() {
+            // 尚未设置主回调函数时，以空函数代替，使具名回调函数能够收到事件。
+            notifier.replace(Callback::with_listeners(Arc::new(RwLock::new(Box::new(|_| {}))), self.listeners.clone()));
+        }
+    }
+
+    /// 移除具名回调函数。
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: 回调函数名称。
+    ///
+    /// returns: bool 是否存在该名称的回调函数。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::EventKind;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.add_notifier("links", &[EventKind::Link], |_data| {});
+    /// assert!(rich_text.remove_notifier("links"));
+    /// assert!(!rich_text.remove_notifier("links"));
+    /// ```
+    pub fn remove_notifier(&mut self, name: &str) -> bool {
+        let mut listeners = self.listeners.write();
+        let len = listeners.len();
+        listeners.retain(|l| l.name != name);
+        listeners.len() != len
+    }
+
+    /// 更改数据属性。限制渲染帧率时立即执行，不等待待合并队列中的数据，参见`set_max_fps`。
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: 调整属性。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::{app, window};
+    /// use fltk::enums::Color;
+    /// use fltk::prelude::{GroupExt, WidgetExt};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
+    ///
+    /// pub enum GlobalMessage {
+    ///     ContentData(UserData),
+    ///     UpdateData(RichDataOptions),
+    ///     DisableData(i64),
+    /// }
+    ///
+    /// let app = app::App::default();
+    /// let mut win = window::Window::default().with_size(1000, 600);
+    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
+    /// win.end();
+    /// win.show();
+    ///
+    /// let (sender, mut receiver) = tokio::sync::mpsc::channel::<UserData>(100);
+    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    ///
+    /// let global_sender_rc = global_sender.clone();
+    /// tokio::spawn(async move {
+    ///     while let Some(data) = receiver.recv().await {
+    ///         if data.text.starts_with("14") {
+    ///             let toggle = !data.underline;
+    ///             let update_options = RichDataOptions::new(data.id).underline(toggle);
+    ///             global_sender_rc.send(GlobalMessage::UpdateData(update_options));
+    ///         } else if data.text.starts_with("22") {
+    ///             global_sender_rc.send(GlobalMessage::DisableData(data.id));
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// while app.wait() {
+    ///     if let Some(msg) = global_receiver.recv() {
+    ///         match msg {
+    ///             GlobalMessage::ContentData(data) => {
+    ///                 rich_text.append(data);
+    ///             }
+    ///             GlobalMessage::UpdateData(options) => {
+    ///                 rich_text.update_data(options);
+    ///             }
+    ///             GlobalMessage::DisableData(id) => {
+    ///                 rich_text.disable_data(id);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     app::sleep(0.001);
+    ///     app::awake();
+    /// }
+    /// ```
+    pub fn update_data(&mut self, options: RichDataOptions) {
+        // 高亮规则拆分出的片段记录了原ID，一并更新。
+        let find_out = update_origin_data(&mut self.current_buffer.write(), &options, &self.keywords.read());
+
+        if find_out {
+            self.update_panel_fn.write().update_param(false);
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            // 定位面板添加新数据时会用面板中保存的数据替换当前缓存，需要同步更新。
+            board.update_data(&options, &self.keywords.read());
+        }
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.update_data(options);
+        }
+
+        // self.inner.redraw();
+        self.inner.set_damage(true);
+    }
+
+    /// 禁用数据片段的互动能力，同时伴随显示效果会有变化。
+    /// 对于文本段会增加删除线，对于图像会进行灰度处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: 数据片段的ID。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::{app, window};
+    /// use fltk::enums::Color;
+    /// use fltk::prelude::{GroupExt, WidgetExt};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{RichDataOptions, UserData};
+    ///
+    /// pub enum GlobalMessage {
+    ///     ContentData(UserData),
+    ///     UpdateData(RichDataOptions),
+    ///     DisableData(i64),
+    /// }
+    ///
+    /// let app = app::App::default();
+    /// let mut win = window::Window::default().with_size(1000, 600);
+    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
+    /// win.end();
+    /// win.show();
+    ///
+    /// let (sender, mut receiver) = tokio::sync::mpsc::channel::<UserData>(100);
+    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    /// let (global_sender, global_receiver) = app::channel::<GlobalMessage>();
+    ///
+    /// let global_sender_rc = global_sender.clone();
+    /// tokio::spawn(async move {
+    ///     while let Some(data) = receiver.recv().await {
+    ///         if data.text.starts_with("14") {
+    ///             let toggle = !data.underline;
+    ///             let update_options = RichDataOptions::new(data.id).underline(toggle);
+    ///             global_sender_rc.send(GlobalMessage::UpdateData(update_options));
+    ///         } else if data.text.starts_with("22") {
+    ///             global_sender_rc.send(GlobalMessage::DisableData(data.id));
+    ///         }
+    ///     }
+    /// });
+    ///
+    /// while app.wait() {
+    ///     if let Some(msg) = global_receiver.recv() {
+    ///         match msg {
+    ///             GlobalMessage::ContentData(data) => {
+    ///                 rich_text.append(data);
+    ///             }
+    ///             GlobalMessage::UpdateData(options) => {
+    ///                 rich_text.update_data(options);
+    ///             }
+    ///             GlobalMessage::DisableData(id) => {
+    ///                 rich_text.disable_data(id);
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     app::sleep(0.001);
+    ///     app::awake();
+    /// }
+    /// ```
+    pub fn disable_data(&mut self, id: i64) {
+        let find_out = {
+            let mut buffer = self.current_buffer.write();
+            let range = id_range(&buffer, id);
+            buffer[range.clone()].iter_mut().for_each(disable_data);
+            !range.is_empty()
+        };
+
+        if find_out {
+            self.update_panel_fn.write().update_param(false);
+        }
+
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.disable_data(id);
+        }
+
+        // self.inner.redraw();
+        self.inner.set_damage(true);
+    }
+
+    /// 设置禁用数据段的显示样式，同时应用到已缓存的数据和已打开的回顾区。可用于控制失效的链接和图片的外观。
+    /// 默认样式为图形以灰度显示，文本增加删除线。
+    ///
+    /// # Arguments
+    ///
+    /// * `style`: 禁用样式。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::DisabledStyle;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_disabled_style(DisabledStyle::Dim(0.6));
+    /// ```
+    pub fn set_disabled_style(&mut self, style: DisabledStyle) {
+        *self.disabled_style.write() = style;
+        self.current_buffer.write().iter_mut().for_each(|rd| rd.set_disabled_style(style));
+        if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+            main_buffer.iter_mut().for_each(|rd| rd.set_disabled_style(style));
+        }
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.line_data_map.values_mut().flatten().for_each(|rd| rd.set_disabled_style(style));
+            board.invalidate();
+        }
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_disabled_style(style);
+        }
+        self.update_panel_fn.write().update_param(false);
+    }
+
+    /// 自动关闭回顾区的接口。当回顾区滚动条已抵达最底部时会关闭回顾区，否则不关闭也不产生额外干扰。
+    ///
+    /// 通常无需调用此方法，当回顾区的滚动条滚动到最底部时会自动关闭。
+    /// 若希望响应PageDown按键关闭回顾区，需要自行在window上注册事件处理逻辑，并调用该接口。
+    ///
+    /// 该方法适合在调用者的事件处理器当中使用。
+    ///
+    /// returns: bool 当满足关闭条件时，返回 `true`，否则返回 `false`。对于事件处理器来说，当本方法返回 `true` 时，提示事件应被消耗，否则应忽略当前事件。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::{Event, Key};
+    /// use fltk::{app, window};
+    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let app = app::App::default();
+    /// let mut win = window::Window::default().with_size(1000, 600);
+    /// let rich_text = RichText::new(100, 100, 800, 400, None);
+    /// win.handle({
+    ///     let rich_text_rc = rich_text.clone();
+    ///     move |_, evt| {
+    ///         let mut handled = false;
+    ///         match evt {
+    ///             Event::KeyDown => {
+    ///                 if app::event_key_down(Key::PageDown) {
+    ///                     handled = rich_text_rc.auto_close_reviewer();
+    ///                 } else if app::event_key_down(Key::PageUp) {
+    ///                     if let Ok(ret) = rich_text_rc.auto_open_reviewer() {
+    ///                         handled = ret;
+    ///                     }
+    ///                 }
+    ///
+    ///             }
+    ///             _ => {}
+    ///         }
+    ///         handled
+    ///     }
+    /// });
+    /// win.end();
+    /// win.show();
+    /// app.run().unwrap();
+    /// ```
+    pub fn auto_close_reviewer(&self) -> bool {
+        if self.reviewer.read().is_some() {
+            if let Err(e) = app::handle_main(LocalEvent::DROP_REVIEWER_FROM_EXTERNAL) {
+                error!("从外部发送关闭回顾区组件事件时出错: {:?}", e);
+            }
+        }
+        false
+    }
+
+    /// 自动打开回顾区的接口。当没有显示回顾区时自动打开回顾区，否则不关闭也不产生额外干扰。该方法适合在调用者的事件处理器当中使用。
+    ///
+    /// 通常无需调用此方法，当鼠标滚轮向上滚动时会自动打开回顾区。
+    /// 若希望响应PageUp按键打开回顾区，需要自行在window上注册事件处理逻辑，并调用该接口。
+    ///
+    /// returns: bool 当满足关闭条件时，返回 `true`，否则返回 `false`。对于事件处理器来说，当本方法返回 `true` 时，提示事件应被消耗，否则应忽略当前事件。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltk::enums::{Event, Key};
+    /// use fltk::{app, window};
+    /// use fltk::prelude::{GroupExt, WidgetBase, WidgetExt};
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let app = app::App::default();
+    /// let mut win = window::Window::default().with_size(1000, 600);
+    /// let mut rich_text = RichText::new(100, 100, 800, 400, None);
+    /// win.handle({
+    ///     let mut rich_text_rc = rich_text.clone();
+    ///     move |_, evt| {
+    ///         let mut handled = false;
+    ///         match evt {
+    ///             Event::KeyDown => {
+    ///                 if app::event_key_down(Key::PageDown) {
+    ///                     handled = rich_text_rc.auto_close_reviewer();
+    ///                 } else if app::event_key_down(Key::PageUp) {
+    ///                     if let Ok(ret) = rich_text_rc.auto_open_reviewer() {
+    ///                        handled = ret;
+    ///                     }
+    ///                 }
+    ///
+    ///             }
+    ///             _ => {}
+    ///         }
+    ///         handled
+    ///     }
+    /// });
+    /// win.end();
+    /// win.show();
+    /// app.run().unwrap();
+    /// ```
+    pub fn auto_open_reviewer(&self) -> Result<bool, FltkError> {
+        return if !self.current_buffer.read().is_empty() && self.reviewer.read().is_none() {
+            let handle_result = app::handle_main(LocalEvent::OPEN_REVIEWER_FROM_EXTERNAL);
+            match handle_result {
+                Ok(handled) => {Ok(handled)}
+                Err(e) => {
+                    error!("从外部发送打开回顾区组件事件时出错: {:?}", e);
+                    Err(e)
+                }
+            }
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// 设置默认的字体，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    ///
+    /// # Arguments
+    ///
+    /// * `font`: 默认字体。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_text_font(&mut self, font: Font) {
+        // let old_font = self.text_font;
+        *self.text_font.write() = font;
+
+    }
+
+    /// 获取默认的字体。
+    pub fn text_font(&self) -> Font {
+        *self.text_font.read()
+    }
+
+    pub fn text_font_ref(&self) -> Arc<RwLock<Font>> {
+        self.text_font.clone()
+    }
+
+    /// 设置默认的字体颜色，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_text_color(&mut self, color: Color) {
+        // let old_cold = self.text_color;
+        *self.text_color.write() = color;
+
+    }
+
+    /// 获取默认的字体颜色。
+    pub fn text_color(&self) -> Color {
+        *self.text_color.read()
+    }
+
+    /// 设置默认的字体尺寸，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    ///
+    /// # Arguments
+    ///
+    /// * `color`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_text_size(&mut self, size: i32) {
+        // let old_size = self.text_size;
+        self.text_size.store(size, Ordering::Relaxed);
+        if self.current_buffer.read().is_empty() {
+            // 更新虚拟光标高度
+            let cursor = &mut *self.cursor_piece.write();
+            cursor.h = (size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+            cursor.font_size = size;
+            let padding = *self.padding.read();
+            *cursor.rd_bounds.write() = (padding.top, padding.top + (size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, padding.left, padding.left);
+        }
+        if !self.echo_area.read().is_empty() || self.pinned.read().heights() != (0, 0) {
+            // 按新的字体尺寸重新计算固定行和回显区高度。
+            self.apply_padding();
+        }
+    }
+
+    /// 获取默认的字体尺寸。
+    pub fn text_size(&self) -> i32 {
+        self.text_size.load(Ordering::Relaxed)
+    }
+
+    /// 缩放字体。设置新的默认字体尺寸，并按相同比例缩放主视图和回顾区中已有的文本数据段，然后重新计算绘制位置。
+    /// 按住Ctrl键在主视图或回顾区上滚动鼠标滚轮时也会执行缩放，并通过回调函数发送`CallbackData::Zoom`。
+    ///
+    /// # Arguments
+    ///
+    /// * `size`: 新的默认字体尺寸。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.zoom(20);
+    /// assert_eq!(rich_text.text_size(), 20);
+    /// ```
+    pub fn zoom(&mut self, size: i32) {
+        let top_insert_pos = if self.append_top.load(Ordering::Relaxed) {
+            Some(self.top_insert_pos.load(Ordering::Relaxed))
+        } else {
+            None
+        };
+        let zoomed = Self::apply_zoom(
+            max(size, 1),
+            &self.panel,
+            self.text_size.clone(),
+            self.text_font.clone(),
+            self.basic_char.clone(),
+            self.current_buffer.clone(),
+            self.data_buffer.clone(),
+            self.remote_flow_control.load(Ordering::SeqCst),
+            top_insert_pos,
+            self.rewrite_board.clone(),
+            self.cursor_piece.clone(),
+            self.reviewer.clone(),
+            self.max_rows.clone(),
+            self.max_cols.clone(),
+            *self.padding.read(),
+        );
+        if zoomed {
+            self.update_panel_fn.write().update_param(false);
+        }
+    }
+
+    /// 设置单个数据被自动分割成适应行宽的片段之间的水平间距（像素数，自动缩放），默认为0。
+    ///
+    /// # Arguments
+    ///
+    /// * `spacing`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_piece_spacing(&mut self, spacing: i32) {
+        self.piece_spacing.store(spacing, Ordering::Relaxed);
+    }
+
+
+    /// 设置启用或禁用闪烁支持。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_enable_blink(&mut self, enable: bool) {
+        self.enable_blink.store(enable, Ordering::Relaxed);
+        self.wake_cursor_blink();
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_enable_blink(enable);
+        }
+    }
+
+    /// 启用或禁用闪烁，切换状态。
+    pub fn toggle_blink(&mut self) {
+        let toggle = !self.enable_blink.load(Ordering::Relaxed);
+        self.enable_blink.store(toggle, Ordering::Relaxed);
+        self.wake_cursor_blink();
+        if let Some(reviewer) = self.reviewer.write().as_mut() {
+            reviewer.set_enable_blink(toggle);
+        }
+    }
+
+    pub fn set_search_focus_color(&mut self, color: Color) {
+        self.blink_flag.write().focus_boarder_color = color;
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_search_focus_color(color);
+        }
+    }
+
+    pub fn set_search_focus_contrast(&mut self, contrast: Color) {
+        self.blink_flag.write().focus_boarder_contrast_color = contrast;
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_search_focus_contrast(contrast);
+        }
+    }
+
+    pub fn set_search_focus_color_and_contrast(&mut self, color: Color, contrast: Color) {
+        let mut bf = self.blink_flag.write();
+        bf.focus_boarder_color = color;
+        bf.focus_boarder_contrast_color = contrast;
+
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_search_focus_color(color);
+            reviewer.set_search_focus_contrast(contrast);
+        }
+    }
+
+    pub fn set_search_focus_width(&mut self, width: u8) {
+        self.blink_flag.write().focus_boarder_width = width as i32;
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_search_focus_width(width);
+        }
+    }
+
+    pub fn set_search_focus_background_color(&mut self, background: Color) {
+        self.blink_flag.write().focus_background_color = background;
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_search_focus_background(background);
+        }
+    }
+
+    /// 计算当前主视图以默认字体大小可以完整显示的(列数，行数)。实际可见的行数可能大于计算返回的行数。
+    /// 若应用对窗口尺寸敏感，则建议使用等宽字体作为默认字体。`fltk`中`Font::Screen`代表等宽字体。
+    pub fn calc_default_window_size(&self) -> (i32, i32) {
+        draw::set_font(*self.text_font.read(), self.text_size.load(Ordering::Relaxed));
+        let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
+        let padding = *self.padding.read();
+        let new_cols = (padding.drawable_width(self.panel.w()) as f32 / char_width as f32).floor() as i32;
+        let new_rows = (padding.drawable_height(self.panel.h()) as f32 / (self.text_size.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil()).floor() as i32;
+        (new_cols, new_rows)
+    }
+
+    /// 设置用于衡量窗口尺寸的基本字符。对于非ASCII字符，可能计算出的尺寸要小于ASCII字符的，因为非ASCII字符可能需要占用更多的空间。
+    /// 例如以非等宽字体作为默认字体时，将`'a'`当作基本衡量单位计算出来的窗口尺寸，就要大于以`'中'`为基本衡量单位计算的结果。
+    /// 若应用对窗口尺寸敏感，则建议使用等宽字体作为默认字体。`fltk`中`Font::Screen`代表等宽字体。
+    ///
+    /// # Arguments
+    ///
+    /// * `basic_char`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_basic_char(&mut self, basic_char: char) {
+        *self.basic_char.write() = basic_char;
+        if let Some(reviewer) = &mut *self.reviewer.write() {
+            reviewer.set_basic_char(basic_char);
+        }
+    }
+
+
+    /// 设置默认制表位的间隔列数。未设置自定义制表位时，每隔`tab_width`列有一个制表位，
+    /// 文本内容中的'\t'将被替换为若干空格，使其后的文字对齐到下一个制表位。
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_width`: 默认制表位的间隔列数。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn set_tab_width(&mut self, tab_width: u8) {
+        self.tab_width.store(tab_width, Ordering::Relaxed);
+    }
+
+    /// 设置自定义制表位，替代默认的等间隔制表位。
+    ///
+    /// # Arguments
+    ///
+    /// * `stops`: 制表位所在的列，从0开始。为空时恢复默认的等间隔制表位。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_tab_stops(vec![8, 20, 32]);
+    /// ```
+    pub fn set_tab_stops(&mut self, stops: Vec<usize>) {
+        if stops.is_empty() {
+            self.tab_stops.write().take();
+        } else {
+            self.tab_stops.write().replace(stops.into_iter().collect());
+        }
+    }
+
+    /// 获取下一个字符所在的列，从0开始。
+    fn current_col(&self) -> usize {
+        if let Some(board) = self.rewrite_board.read().as_ref() {
+            board.cursor_pos.m - 1
+        } else {
+            self.current_col.load(Ordering::Relaxed)
+        }
+    }
+
+    /// 将默认制表位转换为自定义制表位，以便单独增删其中的制表位。
+    fn materialize_tab_stops(&self) {
+        let mut tab_stops = self.tab_stops.write();
+        if tab_stops.is_none() {
+            let width = max(self.tab_width.load(Ordering::Relaxed) as usize, 1);
+            let max_cols = max(self.max_cols.load(Ordering::Relaxed), self.current_col() + 1);
+            tab_stops.replace((1..=max_cols / width).map(|i| i * width).collect());
+        }
+    }
+
+    /// 在光标所在列设置制表位，对应`HTS`控制序列。
+    fn set_tab_stop(&mut self) {
+        self.materialize_tab_stops();
+        let col = self.current_col();
+        if let Some(stops) = self.tab_stops.write().as_mut() {
+            stops.insert(col);
+        }
+    }
+
+    /// 清除制表位，对应`TBC`控制序列。
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`: 0清除光标所在列的制表位，3清除所有制表位。
+    ///
+    /// returns: ()
+    fn clear_tab_stop(&mut self, mode: u8) {
+        match mode {
+            0 => {
+                self.materialize_tab_stops();
+                let col = self.current_col();
+                if let Some(stops) = self.tab_stops.write().as_mut() {
+                    stops.remove(&col);
+                }
+            }
+            3 => {
+                self.tab_stops.write().replace(BTreeSet::new());
+            }
+            _ => {}
+        }
+    }
+
+    /// 显示或关闭光标。
+    ///
+    /// # Arguments
+    ///
+    /// * `show`:
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
+    pub fn toggle_cursor(&mut self, show: bool) {
+        self.show_cursor.store(show, Ordering::Relaxed);
+        self.wake_cursor_blink();
+    }
+
+    /// 光标可能需要闪烁时启动已暂停的光标闪烁定时器，定时器在光标隐藏或不再闪烁时自行暂停。
+    fn wake_cursor_blink(&self) {
+        self.timers.write().wake(CURSOR_BLINK_TIMER);
+    }
+
+    /// 启用或关闭输入行模式。启用后主视图在光标位置显示一个由组件管理的单行输入区，使用与正文相同的字体和颜色回显输入的文字，
+    /// 支持输入法、左右移动、删除和上下键浏览历史记录等本地行编辑操作，按下回车键时通过`CallbackData::PromptInput`回调汇报输入内容并清空输入行。
+    /// 点击主视图即可获得键盘焦点。关闭时丢弃尚未提交的内容和历史记录。
+    ///
+    /// # Arguments
+    ///
+    /// * `enable`: 是否启用。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_prompt_mode(true);
+    /// rich_text.set_prompt_text("look");
+    /// assert_eq!(rich_text.prompt_text(), Some("look".to_string()));
+    /// ```
+    pub fn set_prompt_mode(&mut self, enable: bool) {
+        let enabled = self.prompt.read().is_some();
+        if enable && !enabled {
+            self.prompt.write().replace(PromptLine::default());
+        } else if !enable {
+            self.prompt.write().take();
+        }
+        self.panel.redraw();
+    }
+
+    /// 是否已启用输入行模式。
+    ///
+    /// returns: bool
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// assert!(!rich_text.is_prompt_mode());
+    /// rich_text.set_prompt_mode(true);
+    /// assert!(rich_text.is_prompt_mode());
+    /// ```
+    pub fn is_prompt_mode(&self) -> bool {
+        self.prompt.read().is_some()
+    }
+
+    /// 获取输入行中尚未提交的文字，未启用输入行模式时返回空。
+    ///
+    /// returns: Option<String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// assert_eq!(rich_text.prompt_text(), None);
+    /// rich_text.set_prompt_mode(true);
+    /// assert_eq!(rich_text.prompt_text(), Some(String::new()));
+    /// ```
+    pub fn prompt_text(&self) -> Option<String> {
+        self.prompt.read().as_ref().map(|prompt| prompt.text.clone())
+    }
+
+    /// 替换输入行中的文字，插入点移动到行尾，例如由上层应用补全命令。未启用输入行模式时忽略。
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: 新的输入内容。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_prompt_mode(true);
+    /// rich_text.set_prompt_text("north");
+    /// assert_eq!(rich_text.prompt_text(), Some("north".to_string()));
+    /// ```
+    pub fn set_prompt_text(&mut self, text: &str) {
+        if let Some(prompt) = self.prompt.write().as_mut() {
+            prompt.set_text(text);
+        }
+        self.panel.redraw();
+    }
+
+    /// 设置主视图底部回显区的文字。回显区固定显示在最后一行输出之下，不随输出滚动，最多显示`ECHO_MAX_LINES`行，
+    /// 超出时只保留最后几行，适合回显本地输入或正在编辑的命令。回显区的高度计入底部空白，输出内容会相应上移。
+    /// 传入空字符串时关闭回显区。
+    ///
+    /// # Arguments
+    ///
+    /// * `text`: 回显文字，可以包含换行。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_echo_text("say hello");
+    /// assert_eq!(rich_text.echo_text(), "say hello");
+    /// ```
+    pub fn set_echo_text(&mut self, text: &str) {
+        let lines = echo_lines(text);
+        let line_height = (self.text_size.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
+        let reserve = line_height * lines.len() as i32;
+        *self.echo_area.write() = lines;
+        if reserve != self.echo_reserve.load(Ordering::Relaxed) {
+            self.apply_padding();
+        } else {
+            self.panel.redraw();
+        }
+    }
+
+    /// 获取回显区中显示的文字，各行以换行符连接。
+    ///
+    /// returns: String
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_echo_text("look\nnorth");
+    /// assert_eq!(rich_text.echo_text(), "look\nnorth");
+    /// rich_text.set_echo_text("");
+    /// assert_eq!(rich_text.echo_text(), "");
+    /// ```
+    pub fn echo_text(&self) -> String {
+        self.echo_area.read().join("\n")
     }
 
-    /// 设置默认的字体颜色，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    /// 设置主视图顶部的固定行，例如连接状态栏。固定行使用与正文相同的样式和排版方式显示在内容上方，
+    /// 不随输出滚动，也不进入数据缓存，因此不会被回顾区、查找和导出等功能看到。传入空列表时移除顶部固定行。
     ///
     /// # Arguments
     ///
-    /// * `color`:
+    /// * `rows`: 固定行数据，多个数据段按顺序排版，文本以换行符结束时另起一行。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_pinned_top(vec![UserData::new_text("已连接\n".to_string())]);
     /// ```
-    pub fn set_text_color(&mut self, color: Color) {
-        // let old_cold = self.text_color;
-        *self.text_color.write() = color;
-
-    }
-
-    /// 获取默认的字体颜色。
-    pub fn text_color(&self) -> Color {
-        *self.text_color.read()
+    pub fn set_pinned_top(&mut self, rows: Vec<UserData>) {
+        let rows = self.pinned_rich_data(rows);
+        self.pinned.write().top = rows;
+        self.apply_padding();
     }
 
-    /// 设置默认的字体尺寸，并与`fltk`的其他输入型组件同名接口方法保持兼容。
+    /// 设置主视图底部的固定行，例如角色状态栏。固定行显示在最后一行输出和回显区之下，不随输出滚动，
+    /// 也不进入数据缓存。传入空列表时移除底部固定行。
     ///
     /// # Arguments
     ///
-    /// * `color`:
+    /// * `rows`: 固定行数据，多个数据段按顺序排版，文本以换行符结束时另起一行。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_pinned_bottom(vec![UserData::new_text("HP 100/100".to_string())]);
     /// ```
-    pub fn set_text_size(&mut self, size: i32) {
-        // let old_size = self.text_size;
-        self.text_size.store(size, Ordering::Relaxed);
-        if self.current_buffer.read().is_empty() {
-            // 更新虚拟光标高度
-            let cursor = &mut *self.cursor_piece.write();
-            cursor.h = (size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32;
-            cursor.font_size = size;
-            *cursor.rd_bounds.write() = (PADDING.top, PADDING.top + (size as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, PADDING.left, PADDING.left);
-        }
+    pub fn set_pinned_bottom(&mut self, rows: Vec<UserData>) {
+        let rows = self.pinned_rich_data(rows);
+        self.pinned.write().bottom = rows;
+        self.apply_padding();
     }
 
-    /// 获取默认的字体尺寸。
-    pub fn text_size(&self) -> i32 {
-        self.text_size.load(Ordering::Relaxed)
+    /// 按照与正文相同的命名样式、默认字体、颜色和调色板，将固定行数据转换为待排版的数据。
+    ///
+    /// # Arguments
+    ///
+    /// * `rows`: 固定行数据。
+    ///
+    /// returns: Vec<RichData>
+    fn pinned_rich_data(&self, rows: Vec<UserData>) -> Vec<RichData> {
+        rows.into_iter().map(|mut user_data| {
+            if let Some(def) = user_data.style_name.as_ref().and_then(|name| self.styles.read().get(name).cloned()) {
+                user_data = def.apply(user_data, &self.style_base());
+            }
+            self.styled_rich_data(user_data)
+        }).collect()
     }
 
-    /// 设置单个数据被自动分割成适应行宽的片段之间的水平间距（像素数，自动缩放），默认为0。
+    /// 设置光标样式，不改变光标是否闪烁。
     ///
     /// # Arguments
     ///
-    /// * `spacing`:
+    /// * `style`: 光标样式。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::CursorStyle;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_cursor_style(CursorStyle::Block);
     /// ```
-    pub fn set_piece_spacing(&mut self, spacing: i32) {
-        self.piece_spacing.store(spacing, Ordering::Relaxed);
+    pub fn set_cursor_style(&mut self, style: CursorStyle) {
+        let blink = self.blink_flag.read().cursor_blink;
+        self.blink_flag.write().set_cursor_style(style, blink);
+        self.wake_cursor_blink();
+        self.update_panel_fn.write().update_param(false);
     }
 
-
-    /// 设置启用或禁用闪烁支持。
+    /// 设置光标颜色。
     ///
     /// # Arguments
     ///
-    /// * `enable`:
+    /// * `color`: 光标颜色。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltk::enums::Color;
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_cursor_color(Color::Green);
     /// ```
-    pub fn set_enable_blink(&mut self, enable: bool) {
-        self.enable_blink.store(enable, Ordering::Relaxed);
-        if let Some(reviewer) = self.reviewer.write().as_mut() {
-            reviewer.set_enable_blink(enable);
-        }
-    }
-
-    /// 启用或禁用闪烁，切换状态。
-    pub fn toggle_blink(&mut self) {
-        let toggle = !self.enable_blink.load(Ordering::Relaxed);
-        self.enable_blink.store(toggle, Ordering::Relaxed);
-        if let Some(reviewer) = self.reviewer.write().as_mut() {
-            reviewer.set_enable_blink(toggle);
-        }
-    }
-
-    pub fn set_search_focus_color(&mut self, color: Color) {
-        self.blink_flag.write().focus_boarder_color = color;
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_search_focus_color(color);
-        }
-    }
-
-    pub fn set_search_focus_contrast(&mut self, contrast: Color) {
-        self.blink_flag.write().focus_boarder_contrast_color = contrast;
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_search_focus_contrast(contrast);
-        }
-    }
-
-    pub fn set_search_focus_color_and_contrast(&mut self, color: Color, contrast: Color) {
-        let mut bf = self.blink_flag.write();
-        bf.focus_boarder_color = color;
-        bf.focus_boarder_contrast_color = contrast;
-
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_search_focus_color(color);
-            reviewer.set_search_focus_contrast(contrast);
-        }
-    }
-
-    pub fn set_search_focus_width(&mut self, width: u8) {
-        self.blink_flag.write().focus_boarder_width = width as i32;
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_search_focus_width(width);
-        }
-    }
-
-    pub fn set_search_focus_background_color(&mut self, background: Color) {
-        self.blink_flag.write().focus_background_color = background;
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_search_focus_background(background);
-        }
-    }
-
-    /// 计算当前主视图以默认字体大小可以完整显示的(列数，行数)。实际可见的行数可能大于计算返回的行数。
-    /// 若应用对窗口尺寸敏感，则建议使用等宽字体作为默认字体。`fltk`中`Font::Screen`代表等宽字体。
-    pub fn calc_default_window_size(&self) -> (i32, i32) {
-        draw::set_font(*self.text_font.read(), self.text_size.load(Ordering::Relaxed));
-        let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
-        let new_cols = ((self.panel.w() - PADDING.left - PADDING.right) as f32 / char_width as f32).floor() as i32;
-        let new_rows = ((self.panel.h() - PADDING.top - PADDING.bottom) as f32 / (self.text_size.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil()).floor() as i32;
-        (new_cols, new_rows)
+    pub fn set_cursor_color(&mut self, color: Color) {
+        self.blink_flag.write().set_cursor_color(color);
+        self.update_panel_fn.write().update_param(false);
     }
 
-    /// 设置用于衡量窗口尺寸的基本字符。对于非ASCII字符，可能计算出的尺寸要小于ASCII字符的，因为非ASCII字符可能需要占用更多的空间。
-    /// 例如以非等宽字体作为默认字体时，将`'a'`当作基本衡量单位计算出来的窗口尺寸，就要大于以`'中'`为基本衡量单位计算的结果。
-    /// 若应用对窗口尺寸敏感，则建议使用等宽字体作为默认字体。`fltk`中`Font::Screen`代表等宽字体。
+    /// 设置光标闪烁间隔时间，默认值为`BLINK_INTERVAL`。新的间隔在下一次闪烁时生效。
     ///
     /// # Arguments
     ///
-    /// * `basic_char`:
+    /// * `interval`: 闪烁间隔时间，单位秒，小于等于0时光标不闪烁。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_cursor_blink_interval(0.8);
+    /// // 光标不闪烁。
+    /// rich_text.set_cursor_blink_interval(0.0);
     /// ```
-    pub fn set_basic_char(&mut self, basic_char: char) {
-        *self.basic_char.write() = basic_char;
-        if let Some(reviewer) = &mut *self.reviewer.write() {
-            reviewer.set_basic_char(basic_char);
-        }
+    pub fn set_cursor_blink_interval(&mut self, interval: f64) {
+        *self.cursor_blink_interval.write() = interval;
+        self.wake_cursor_blink();
     }
 
-
-    /// 设置'\t'所占的空格数。文本内容中的'\t'将被替换为`tab_width`个空格。
+    /// 设置慢速闪烁内容的强度切换间隔时间，默认值为`BLINK_INTERVAL`。新的间隔在下一次闪烁时生效，同时应用于回顾区。
     ///
     /// # Arguments
     ///
-    /// * `tab_width`: 一个`'\t'`所占的空格数。
+    /// * `interval`: 闪烁间隔时间，单位秒，小于等于0时忽略。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_blink_interval(0.8);
     /// ```
-    pub fn set_tab_width(&mut self, tab_width: u8) {
-        self.tab_width.store(tab_width, Ordering::Relaxed);
+    pub fn set_blink_interval(&mut self, interval: f64) {
+        if interval > 0.0 {
+            *self.blink_interval.write() = interval;
+            if let Some(reviewer) = self.reviewer.write().as_mut() {
+                reviewer.set_blink_interval(interval);
+            }
+        }
     }
 
-    /// 显示或关闭光标。
+    /// 设置快速闪烁内容的强度切换间隔时间，默认值为`RAPID_BLINK_INTERVAL`。新的间隔在下一次闪烁时生效，同时应用于回顾区。
     ///
     /// # Arguments
     ///
-    /// * `show`:
+    /// * `interval`: 闪烁间隔时间，单位秒，小于等于0时忽略。
     ///
     /// returns: ()
     ///
     /// # Examples
     ///
     /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
     ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.set_rapid_blink_interval(0.2);
     /// ```
-    pub fn toggle_cursor(&mut self, show: bool) {
-        self.show_cursor.store(show, Ordering::Relaxed);
+    pub fn set_rapid_blink_interval(&mut self, interval: f64) {
+        if interval > 0.0 {
+            *self.rapid_blink_interval.write() = interval;
+            if let Some(reviewer) = self.reviewer.write().as_mut() {
+                reviewer.set_rapid_blink_interval(interval);
+            }
+        }
     }
 
-    /// 获取当前坐标信息，以行、列的方式表示。
+    /// 获取当前光标位置报告，以行、列的方式表示，格式为`\x1b[n;mR`。
+    /// 光标定位面板中直接使用面板的光标位置；常规流中根据虚拟光标在可见区域内的纵坐标和当前列号计算。
+    ///
+    /// returns: Option<String>
+    ///
+    /// # Examples
+    ///
+    /// ```
+    ///
+    /// ```
     pub fn get_cursor_pos_dsr(&self) -> Option<String> {
         if let Some(board) = self.rewrite_board.read().as_ref() {
-            Some(board.cursor_pos.dsr())
-        } else {
-            None
+            return Some(board.cursor_pos.dsr());
         }
+
+        let (max_rows, max_cols) = (self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed));
+        let line_height = max((self.text_size.load(Ordering::Relaxed) as f32 * LINE_HEIGHT_FACTOR).ceil() as i32, 1);
+        let scroll_y = Self::calc_scroll_height(self.current_buffer.clone(), self.panel.height(), self.append_top.load(Ordering::Relaxed), &self.padding.read());
+        let cursor_y = self.cursor_piece.read().top_y;
+        let row = ((cursor_y - scroll_y - self.padding.read().top) / line_height + 1).clamp(1, max(max_rows, 1) as i32) as usize;
+        let col = min(self.current_col.load(Ordering::Relaxed) + 1, max(max_cols, 1));
+        Some(CursorPos::new(row, col, max_rows, max_cols).dsr())
+    }
+
+    /// 获取以字符为单位的窗口尺寸报告，格式为`\x1b[8;行数;列数t`，用于应答服务端的`\x1b[18t`请求。
+    ///
+    /// returns: String
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let rich_text = RichText::new(100, 120, 800, 400, None);
+    /// let report = rich_text.get_window_size_report();
+    /// assert!(report.starts_with("\x1b[8;") && report.ends_with('t'));
+    /// ```
+    pub fn get_window_size_report(&self) -> String {
+        format!("\x1b[8;{};{}t", self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed))
     }
 
     /// 切换光标控制模式。
@@ -1879,6 +8120,100 @@ impl RichText {
                 main_buffer.append(&mut *self.current_buffer.write());
             }
             self.show_cursor.store(true, Ordering::Relaxed);
+            self.wake_cursor_blink();
+        }
+    }
+
+    /// 进入或退出备用屏幕。
+    /// 进入时将现有数据移入主缓存并保存光标位置，之后的数据都写入临时缓存中；已处于面板流中时，同时保存面板中的数据和定位面板。
+    /// 退出时丢弃备用屏幕上的所有数据，恢复主缓存、面板和光标位置，因此全屏面板不会混入回看历史中。
+    ///
+    /// # Arguments
+    ///
+    /// * `on`: true进入备用屏幕，false退出备用屏幕。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::UserData;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.append(UserData::new_text("shell prompt\n".to_string()));
+    /// rich_text.switch_alternate_screen(true);
+    /// rich_text.append(UserData::new_text("全屏程序的画面".to_string()));
+    /// // 退出后全屏程序的画面被丢弃，恢复进入前的内容。
+    /// rich_text.switch_alternate_screen(false);
+    /// ```
+    pub fn switch_alternate_screen(&mut self, on: bool) {
+        if on {
+            if self.alternate_saved.read().is_some() {
+                return;
+            }
+            // 已处于面板流中时保存现有面板，避免被备用屏幕覆盖或并入主缓存。
+            let panel = if self.remote_flow_control.load(Ordering::SeqCst) {
+                None
+            } else {
+                Some((std::mem::take(&mut *self.current_buffer.write()), self.rewrite_board.write().take()))
+            };
+            let saved = AlternateSaved { cursor: self.cursor_piece.read().clone(), show_cursor: self.show_cursor.load(Ordering::Relaxed), panel };
+            self.alternate_saved.write().replace(saved);
+            self.move_cursor(1, 1);
+        } else {
+            let saved = self.alternate_saved.write().take();
+            if let Some(AlternateSaved { cursor, show_cursor, panel }) = saved {
+                match panel {
+                    Some((buffer, mut board)) => {
+                        // 回到进入备用屏幕前的面板，窗口尺寸可能已经变化。
+                        if let Some(board) = board.as_mut() {
+                            board.resize(max(self.max_rows.load(Ordering::Relaxed), 2), max(self.max_cols.load(Ordering::Relaxed), 2));
+                        }
+                        self.remote_flow_control.store(false, Ordering::SeqCst);
+                        *self.current_buffer.write() = buffer;
+                        *self.rewrite_board.write() = board;
+                    }
+                    None if !self.remote_flow_control.load(Ordering::SeqCst) => self.switch_mode(1),
+                    None => {}
+                }
+                *self.cursor_piece.write() = cursor;
+                self.show_cursor.store(show_cursor, Ordering::Relaxed);
+                self.wake_cursor_blink();
+                self.update_panel_fn.write().update_param(false);
+            }
+        }
+    }
+
+    /// 设置光标定位面板的滚动区域，对应`DECSTBM`控制序列。若已处于定位面板中，设置后光标移动到左上角。
+    /// 可用于底部有固定状态行的定位面板，换行时只滚动区域内的内容。
+    ///
+    /// # Arguments
+    ///
+    /// * `top`: 顶部行号，从1开始，0表示第一行。
+    /// * `bottom`: 底部行号，0表示最后一行。
+    ///
+    /// returns: ()
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// // 最后一行作为固定的状态行，不参与滚动。
+    /// rich_text.set_scroll_region(1, 23);
+    /// ```
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        *self.scroll_region.write() = (top, bottom);
+        let board_exists = if let Some(board) = self.rewrite_board.write().as_mut() {
+            board.set_scroll_region(top, bottom);
+            true
+        } else {
+            false
+        };
+        if board_exists {
+            self.move_cursor(1, 1);
         }
     }
 
@@ -1914,7 +8249,10 @@ impl RichText {
         if n == 1 && m == 1 && self.rewrite_board.read().is_none() {
             debug!("创建新的定位面板，尺寸：{}/{}", self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed));
             self.switch_mode(0);
-            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), offset_y as usize, default_line_height as usize, 0));
+            let mut board = ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), offset_y as usize, default_line_height as usize, 0, *self.padding.read());
+            let (top, bottom) = *self.scroll_region.read();
+            board.set_scroll_region(top, bottom);
+            self.rewrite_board.write().replace(board);
         }
 
         let mut need_insert_empty = false;
@@ -1924,39 +8262,57 @@ impl RichText {
             draw::set_font(*self.text_font.read(), self.text_size.load(Ordering::Relaxed));
 
             if let Some(rds) = board.line_data_map.get(&n) {
+                // 按照字符占用的列数定位，中日韩文字等宽字符占两列。
                 let mut total_char_len = 0;
+                let mut located = false;
                 // if n == 1 && m == 1 {
                 //     debug!("移动光标到左上角");
                 // }
                 for rd in rds {
-                    let char_len = rd.text.chars().count();
-                    if total_char_len < m && total_char_len + char_len > m {
-                        let char_pos = m - total_char_len - 1;
-                        let sub_str = rd.text.chars().take(char_pos).collect::<String>();
-                        let (char_width, _) = draw::measure(&sub_str, false);
+                    let char_len = text_columns(&rd.text);
+                    if total_char_len < m && total_char_len + char_len >= m {
+                        let (sub_len, _) = take_columns(&rd.text, m - total_char_len - 1);
                         if let Some(fp) = rd.line_pieces.first() {
                             let fpb = fp.read();
+                            let char_width = if rd.data_type == DataType::Gauge {
+                                // 面板中的进度条按整数列占用宽度。
+                                fpb.w * (m - total_char_len - 1) as i32 / max(char_len, 1) as i32
+                            } else {
+                                draw::measure(&rd.text[..sub_len], false).0
+                            };
                             let new_x = fpb.x + char_width;
                             let new_y = fpb.top_y;
                             self.cursor_piece.write().move_cursor_to(new_x, new_y);
                         }
+                        located = true;
                         break;
                     }
                     total_char_len += char_len;
                 }
+                if !located {
+                    // 光标位于行内容之后，按基本字符宽度补齐剩余的列。
+                    if let Some(lp) = rds.last().and_then(|rd| rd.line_pieces.last()) {
+                        let lpb = lp.read();
+                        let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
+                        let new_x = lpb.x + lpb.w + char_width * (m - total_char_len - 1) as i32;
+                        self.cursor_piece.write().move_cursor_to(new_x, lpb.top_y);
+                    }
+                }
             } else {
                 let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
 
-                let new_y = PADDING.top + (default_line_height * (n as i32 - 1)) + offset_y;
-                let new_x = PADDING.left + char_width * (m as i32 - 1);
+                let padding = *self.padding.read();
+                let new_y = padding.top + (default_line_height * (n as i32 - 1)) + offset_y;
+                let new_x = padding.left + char_width * (m as i32 - 1);
                 self.cursor_piece.write().move_cursor_to(new_x, new_y);
             }
 
         } else {
             let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
 
-            let new_y = PADDING.top + (default_line_height * (n as i32 - 1)) + offset_y;
-            let new_x = PADDING.left + char_width * (m as i32 - 1);
+            let padding = *self.padding.read();
+            let new_y = padding.top + (default_line_height * (n as i32 - 1)) + offset_y;
+            let new_x = padding.left + char_width * (m as i32 - 1);
             self.cursor_piece.write().move_cursor_to(new_x, new_y);
             need_insert_empty = true;
         }
@@ -1998,8 +8354,9 @@ impl RichText {
 
         let cursor_piece = &mut *self.cursor_piece.write();
         cursor_piece.y -= cursor_piece.h * n as i32;
-        if cursor_piece.y < PADDING.top {
-            cursor_piece.y = PADDING.top;
+        let top = self.padding.read().top;
+        if cursor_piece.y < top {
+            cursor_piece.y = top;
         }
         cursor_piece.next_y = cursor_piece.y;
         let mut rd_bounds = *cursor_piece.rd_bounds.write();
@@ -2008,7 +8365,7 @@ impl RichText {
         // *cursor_piece.rd_bounds.write() = rd_bounds;
         if self.rewrite_board.read().is_none() {
             let default_line_height = self.get_default_line_height();
-            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0));
+            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0, *self.padding.read()));
         }
         self.rewrite_board.write().as_mut().unwrap().cursor_pos.sub_n(n);
     }
@@ -2038,7 +8395,7 @@ impl RichText {
         // cursor_piece.rd_bounds.set(rd_bounds);
         if self.rewrite_board.read().is_none() {
             let default_line_height = self.get_default_line_height();
-            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0));
+            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0, *self.padding.read()));
         }
         self.rewrite_board.write().as_mut().unwrap().cursor_pos.add_n(n);
     }
@@ -2064,8 +8421,9 @@ impl RichText {
         let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
 
         cursor_piece.x -= char_width * m as i32;
-        if cursor_piece.x < PADDING.left {
-            cursor_piece.x = PADDING.left;
+        let left = self.padding.read().left;
+        if cursor_piece.x < left {
+            cursor_piece.x = left;
         }
         cursor_piece.next_x = cursor_piece.x;
         let mut rd_bounds = *cursor_piece.rd_bounds.write();
@@ -2074,7 +8432,7 @@ impl RichText {
         // cursor_piece.rd_bounds.set(rd_bounds);
         if self.rewrite_board.read().is_none() {
             let default_line_height = self.get_default_line_height();
-            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0));
+            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0, *self.padding.read()));
         }
         self.rewrite_board.write().as_mut().unwrap().cursor_pos.sub_m(m);
     }
@@ -2101,7 +8459,7 @@ impl RichText {
         let (char_width, _) = draw::measure(&self.basic_char.read().to_string(), false);
 
         cursor_piece.x += char_width * m as i32;
-        let max_width = self.panel.w() - PADDING.right;
+        let max_width = self.panel.w() - self.padding.read().right;
         if cursor_piece.x > max_width {
             cursor_piece.x = max_width;
         }
@@ -2112,11 +8470,28 @@ impl RichText {
         // cursor_piece.rd_bounds.set(rd_bounds);
         if self.rewrite_board.read().is_none() {
             let default_line_height = self.get_default_line_height();
-            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0));
+            self.rewrite_board.write().replace(ReWriteBoard::new(self.max_rows.load(Ordering::Relaxed), self.max_cols.load(Ordering::Relaxed), self.get_offset_y() as usize, default_line_height as usize, 0, *self.padding.read()));
         }
         self.rewrite_board.write().as_mut().unwrap().cursor_pos.add_m(m);
     }
 
+    /// 编辑光标定位面板上的数据，编辑后以面板数据替换当前缓存。不在定位面板中时不做处理。
+    ///
+    /// # Arguments
+    ///
+    /// * `edit`: 编辑函数，参数为定位面板、可绘制的最大宽度和基本字符。
+    ///
+    /// returns: ()
+    fn edit_board<F>(&mut self, edit: F) where F: FnOnce(&mut ReWriteBoard, i32, char) {
+        let drawable_max_width = self.padding.read().drawable_width(self.panel.width());
+        let basic_char = *self.basic_char.read();
+        draw::set_font(*self.text_font.read(), self.text_size.load(Ordering::Relaxed));
+        if let Some(board) = self.rewrite_board.write().as_mut() {
+            edit(board, drawable_max_width, basic_char);
+            *self.current_buffer.write() = board.all_data();
+        }
+    }
+
     /// 从当前光标处擦除行内数据，光标位置不变。。
     ///
     /// # Arguments
@@ -2217,6 +8592,9 @@ impl RichText {
 
     /// 计算y轴偏移量。
     fn get_offset_y(&self) -> i32 {
+        if self.append_top.load(Ordering::Relaxed) {
+            return 0;
+        }
         let (mut offset_y, window_height) = (0, self.panel.h());
         let bottom_y = if let Some(rd) = self.current_buffer.read().iter().last() {
             rd.v_bounds.read().1
@@ -2224,18 +8602,23 @@ impl RichText {
             0
         };
         if bottom_y > window_height {
-            offset_y = bottom_y - window_height + PADDING.bottom;
+            offset_y = bottom_y - window_height + self.padding.read().bottom;
         }
         offset_y
     }
 
     fn erase_in_display(&mut self, erase_mode: u8) {
         // debug!("erase in display: {erase_mode}");
+        // 只记录整屏清除，局部清除频繁出现且可由后续数据恢复。
+        if matches!(erase_mode, 2 | 3) && self.rewrite_board.read().is_none() {
+            self.journal_erase();
+        }
         if let Some(board) = self.rewrite_board.write().as_mut() {
             board.erase_in_display(erase_mode);
         } else {
             // 计算y轴偏移量
             let offset_y = self.get_offset_y();
+            let padding = *self.padding.read();
             let binding = self.cursor_piece.clone();
             let cursor_piece = &*binding.read();
             // 待擦除的矩形区域
@@ -2246,33 +8629,33 @@ impl RichText {
                     // 从光标位置擦除到面板左上角所有的行。
                     debug!("擦除到左上角");
                     let old_top = expand_rect.1 - offset_y;
-                    expand_rect.stretch_to_left(PADDING.left - expand_rect.0);
+                    expand_rect.stretch_to_left(padding.left - expand_rect.0);
                     current_line_rect.replace(expand_rect.clone());
 
-                    expand_rect.0 = PADDING.left;
-                    expand_rect.1 = PADDING.top - offset_y;
-                    expand_rect.2 = self.panel.w() - PADDING.left - PADDING.right;
-                    expand_rect.3 = self.panel.h() - PADDING.top - PADDING.bottom - old_top - 1;
+                    expand_rect.0 = padding.left;
+                    expand_rect.1 = padding.top - offset_y;
+                    expand_rect.2 = self.panel.w() - padding.left - padding.right;
+                    expand_rect.3 = self.panel.h() - padding.top - padding.bottom - old_top - 1;
                     // 待完善此场景
                 }
                 2 | 3 => {
                     // 擦除整个面板。
                     debug!("全部擦除");
-                    expand_rect.0 = PADDING.left;
-                    expand_rect.1 = PADDING.top - offset_y;
-                    expand_rect.2 = self.panel.w() - PADDING.left - PADDING.right;
-                    expand_rect.3 = self.panel.h() - PADDING.top - PADDING.bottom;
+                    expand_rect.0 = padding.left;
+                    expand_rect.1 = padding.top - offset_y;
+                    expand_rect.2 = self.panel.w() - padding.left - padding.right;
+                    expand_rect.3 = self.panel.h() - padding.top - padding.bottom;
                 }
                 _ => {
                     // 从光标位置擦除到面板右下角所有的行。
                     debug!("擦除到右下角");
-                    expand_rect.2 = self.panel.w() - PADDING.left - PADDING.right - expand_rect.0;
+                    expand_rect.2 = self.panel.w() - padding.left - padding.right - expand_rect.0;
                     current_line_rect.replace(expand_rect.clone());
 
-                    expand_rect.0 = PADDING.left;
+                    expand_rect.0 = padding.left;
                     expand_rect.1 = cursor_piece.y + cursor_piece.h + 1;
-                    expand_rect.2 = self.panel.w() - PADDING.left - PADDING.right;
-                    expand_rect.3 = self.panel.h() - (expand_rect.1 - offset_y) - PADDING.bottom;
+                    expand_rect.2 = self.panel.w() - padding.left - padding.right;
+                    expand_rect.3 = self.panel.h() - (expand_rect.1 - offset_y) - padding.bottom;
                 }
             }
 
@@ -2390,6 +8773,94 @@ impl RichText {
         }
     }
 
+    /// 使符合筛选条件的数据段过期，同时作用于主视图和回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: 筛选条件，可按类别、存在时长、ID范围或文本正则表达式筛选。
+    /// * `effect`: 过期后的显示效果。
+    ///
+    /// returns: usize 过期的数据段数量。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use regex::Regex;
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::{ExpireEffect, ExpireFilter};
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.expire_by(ExpireFilter::OlderThan(Duration::from_secs(600)), ExpireEffect::Dim);
+    /// rich_text.expire_by(ExpireFilter::TextMatches(Regex::new(r"^\[广告\]").unwrap()), ExpireEffect::Remove);
+    /// ```
+    pub fn expire_by(&mut self, filter: ExpireFilter, effect: ExpireEffect) -> usize {
+        let now = deterministic::now();
+        self.expire_matching(|rd| filter.matches(rd, now), effect)
+    }
+
+    /// 使满足判断条件的数据段过期，同时作用于主视图和回顾区。
+    ///
+    /// # Arguments
+    ///
+    /// * `predicate`: 判断条件，返回`true`的数据段过期。
+    /// * `effect`: 过期后的显示效果。
+    ///
+    /// returns: usize 过期的数据段数量。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use fltkrs_richdisplay::rich_text::RichText;
+    /// use fltkrs_richdisplay::ExpireEffect;
+    ///
+    /// let mut rich_text = RichText::new(100, 120, 800, 400, None);
+    /// rich_text.expire_where(|data| data.meta_value("channel") == Some("chat"), ExpireEffect::StrikeThrough);
+    /// ```
+    pub fn expire_where<F>(&mut self, mut predicate: F, effect: ExpireEffect) -> usize where F: FnMut(&UserData) -> bool {
+        self.expire_matching(|rd| predicate(&UserData::from(rd)), effect)
+    }
+
+    fn expire_matching<F>(&mut self, mut matches: F, effect: ExpireEffect) -> usize where F: FnMut(&RichData) -> bool {
+        let mut ids: HashSet<i64> = self.current_buffer.read().iter().filter(|rd| matches(rd)).map(|rd| rd.id).collect();
+        if let Some(main_buffer) = self.data_buffer.read().as_ref() {
+            ids.extend(main_buffer.iter().filter(|rd| matches(rd)).map(|rd| rd.id));
+        }
+        if let Some(reviewer) = self.reviewer.read().as_ref() {
+            ids.extend(reviewer.matching_ids(&mut matches));
+        }
+        if ids.is_empty() {
+            return 0;
+        }
+
+        if effect == ExpireEffect::Remove {
+            if self.append_top.load(Ordering::Relaxed) {
+                // 顶部追加模式下，插入位置之前被移除的数据段需从插入位置中扣除。
+                let pos = self.top_insert_pos.load(Ordering::Relaxed);
+                let removed = self.current_buffer.read().iter().take(pos).filter(|rd| rd.in_ids(&ids)).count();
+                self.top_insert_pos.store(pos - removed, Ordering::Relaxed);
+            }
+            self.current_buffer.write().retain(|rd| !rd.in_ids(&ids));
+            if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+                main_buffer.retain(|rd| !rd.in_ids(&ids));
+            }
+            self.relayout_main_buffer(|_| {});
+            if let Some(reviewer) = self.reviewer.write().as_mut() {
+                reviewer.remove_review_data(&ids);
+            }
+        } else {
+            expire_data_with_effect(&mut self.current_buffer.write(), &ids, effect);
+            if let Some(main_buffer) = self.data_buffer.write().as_mut() {
+                expire_data_with_effect(main_buffer, &ids, effect);
+            }
+            if let Some(reviewer) = self.reviewer.write().as_mut() {
+                reviewer.expire_review_data_with_effect(&ids, effect);
+            }
+        }
+        self.update_panel_fn.write().update_param(false);
+        ids.len()
+    }
+
     /// 获取远程流控制状态。
     pub fn get_remote_flow_control(&self) -> Arc<AtomicBool> {
         self.remote_flow_control.clone()